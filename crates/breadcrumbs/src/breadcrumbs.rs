@@ -50,9 +50,19 @@ impl Render for Breadcrumbs {
             return element;
         };
 
-        let Some(mut segments) = active_item.breadcrumbs(cx.theme(), cx) else {
+        let Some(segments) = active_item.breadcrumbs(cx.theme(), cx) else {
             return element;
         };
+        let editor = active_item
+            .downcast::<Editor>()
+            .map(|editor| editor.downgrade());
+
+        // Every segment after the path names a symbol containing the cursor.
+        let mut segments = segments
+            .into_iter()
+            .enumerate()
+            .map(|(ix, segment)| (ix.checked_sub(1), segment))
+            .collect::<Vec<_>>();
 
         let prefix_end_ix = cmp::min(segments.len(), MAX_SEGMENTS / 2);
         let suffix_start_ix = cmp::max(
@@ -63,15 +73,18 @@ impl Render for Breadcrumbs {
         if suffix_start_ix > prefix_end_ix {
             segments.splice(
                 prefix_end_ix..suffix_start_ix,
-                Some(BreadcrumbText {
-                    text: "⋯".into(),
-                    highlights: None,
-                    font: None,
-                }),
+                Some((
+                    None,
+                    BreadcrumbText {
+                        text: "⋯".into(),
+                        highlights: None,
+                        font: None,
+                    },
+                )),
             );
         }
 
-        let highlighted_segments = segments.into_iter().map(|segment| {
+        let highlighted_segments = segments.into_iter().map(|(symbol_ix, segment)| {
             let mut text_style = window.text_style();
             if let Some(font) = segment.font {
                 text_style.font_family = font.family;
@@ -81,9 +94,24 @@ impl Render for Breadcrumbs {
             }
             text_style.color = Color::Muted.color(cx);
 
-            StyledText::new(segment.text.replace('\n', "␤"))
-                .with_highlights(&text_style, segment.highlights.unwrap_or_default())
-                .into_any()
+            let text = StyledText::new(segment.text.replace('\n', "␤"))
+                .with_highlights(&text_style, segment.highlights.unwrap_or_default());
+            match symbol_ix.zip(editor.clone()) {
+                Some((symbol_ix, editor)) => div()
+                    .id(("breadcrumb-symbol", symbol_ix))
+                    .cursor_pointer()
+                    .child(text)
+                    .on_click(move |_, window, cx| {
+                        cx.stop_propagation();
+                        editor
+                            .update(cx, |editor, cx| {
+                                editor.go_to_enclosing_symbol(symbol_ix, window, cx)
+                            })
+                            .ok();
+                    })
+                    .into_any(),
+                None => text.into_any(),
+            }
         });
         let breadcrumbs = Itertools::intersperse_with(highlighted_segments, || {
             Label::new("›").color(Color::Placeholder).into_any_element()
@@ -91,10 +119,7 @@ impl Render for Breadcrumbs {
 
         let breadcrumbs_stack = h_flex().gap_1().children(breadcrumbs);
 
-        match active_item
-            .downcast::<Editor>()
-            .map(|editor| editor.downgrade())
-        {
+        match editor {
             Some(editor) => element.child(
                 ButtonLike::new("toggle outline view")
                     .child(breadcrumbs_stack)
@@ -1,14 +1,42 @@
+mod line_diff;
+
 use futures::{channel::oneshot, future::OptionFuture};
 use git2::{DiffLineType as GitDiffLineType, DiffOptions as GitOptions, Patch as GitPatch};
 use gpui::{App, AsyncApp, Context, Entity, EventEmitter};
 use language::{Language, LanguageRegistry};
 use rope::Rope;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use similar::{DiffTag, TextDiff};
 use std::{cmp, future::Future, iter, ops::Range, sync::Arc};
 use sum_tree::SumTree;
 use text::ToOffset as _;
 use text::{Anchor, Bias, BufferId, OffsetRangeExt, Point};
 use util::ResultExt;
 
+pub use line_diff::{diff_ropes, LineHunk};
+
+/// The combined length of the texts above which they're diffed with [`diff_ropes`], one chunk
+/// at a time, instead of being copied into strings to be diffed by git.
+const MAX_GIT_DIFF_LEN: usize = 16 * 1024 * 1024;
+
+/// How hunks are computed between a buffer and its base text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffAlgorithm {
+    /// The algorithm used by git by default.
+    #[default]
+    Myers,
+    /// Like Myers, but spends extra time to find the smallest possible diff.
+    Minimal,
+    /// Aligns the texts on lines that occur once in each of them, which
+    /// usually keeps moved or duplicated code in separate hunks.
+    Patience,
+    /// Like patience, but also aligns the texts on the lines that occur the
+    /// fewest times when no line occurs once, as git's `--histogram` does.
+    Histogram,
+}
+
 pub struct BufferDiff {
     pub buffer_id: BufferId,
     inner: BufferDiffInner,
@@ -443,27 +471,16 @@ impl BufferDiffInner {
 fn compute_hunks(
     diff_base: Option<(Arc<String>, Rope)>,
     buffer: text::BufferSnapshot,
+    algorithm: DiffAlgorithm,
 ) -> SumTree<InternalDiffHunk> {
     let mut tree = SumTree::new(&buffer);
 
     if let Some((diff_base, diff_base_rope)) = diff_base {
-        let buffer_text = buffer.as_rope().to_string();
-
-        let mut options = GitOptions::default();
-        options.context_lines(0);
-        let patch = GitPatch::from_buffers(
-            diff_base.as_bytes(),
-            None,
-            buffer_text.as_bytes(),
-            None,
-            Some(&mut options),
-        )
-        .log_err();
-
         // A common case in Zed is that the empty buffer is represented as just a newline,
         // but if we just compute a naive diff you get a "preserved" line in the middle,
         // which is a bit odd.
-        if buffer_text == "\n" && diff_base.ends_with("\n") && diff_base.len() > 1 {
+        let buffer_is_newline = buffer.len() == 1 && buffer.chars().next() == Some('\n');
+        if buffer_is_newline && diff_base.ends_with("\n") && diff_base.len() > 1 {
             tree.push(
                 InternalDiffHunk {
                     buffer_range: buffer.anchor_before(0)..buffer.anchor_before(0),
@@ -474,6 +491,52 @@ fn compute_hunks(
             return tree;
         }
 
+        // Libgit2 needs both texts as strings, so the large ones and the algorithms it doesn't
+        // have are diffed a chunk at a time instead.
+        if algorithm == DiffAlgorithm::Histogram
+            || diff_base.len() + buffer.len() > MAX_GIT_DIFF_LEN
+        {
+            for hunk in diff_ropes(&diff_base_rope, buffer.as_rope(), algorithm) {
+                let buffer_range =
+                    line_diff::row_start_offset(buffer.as_rope(), hunk.new_rows.start)
+                        ..line_diff::row_start_offset(buffer.as_rope(), hunk.new_rows.end);
+                let diff_base_byte_range =
+                    line_diff::row_start_offset(&diff_base_rope, hunk.old_rows.start)
+                        ..line_diff::row_start_offset(&diff_base_rope, hunk.old_rows.end);
+                tree.push(
+                    InternalDiffHunk {
+                        buffer_range: buffer.anchor_before(buffer_range.start)
+                            ..buffer.anchor_before(buffer_range.end),
+                        diff_base_byte_range,
+                    },
+                    &buffer,
+                );
+            }
+            return tree;
+        }
+
+        let buffer_text = buffer.as_rope().to_string();
+        let mut options = GitOptions::default();
+        options.context_lines(0);
+        match algorithm {
+            DiffAlgorithm::Myers => {}
+            DiffAlgorithm::Minimal => {
+                options.minimal(true);
+            }
+            DiffAlgorithm::Patience => {
+                options.patience(true);
+            }
+            DiffAlgorithm::Histogram => unreachable!("histogram diffs aren't computed by git"),
+        }
+        let patch = GitPatch::from_buffers(
+            diff_base.as_bytes(),
+            None,
+            buffer_text.as_bytes(),
+            None,
+            Some(&mut options),
+        )
+        .log_err();
+
         if let Some(patch) = patch {
             let mut divergence = 0;
             for hunk_index in 0..patch.num_hunks() {
@@ -568,6 +631,56 @@ fn process_patch_hunk(
     }
 }
 
+/// Returns the byte ranges of the words that differ between the base text and
+/// the buffer text of a modified hunk, in `old_text` and `new_text` respectively.
+///
+/// Adjacent changed words are merged along with the whitespace between them, so
+/// each range can be highlighted on its own.
+pub fn word_diff(old_text: &str, new_text: &str) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let diff = TextDiff::configure()
+        .algorithm(similar::Algorithm::Patience)
+        .diff_words(old_text, new_text);
+    let token_offsets = |tokens: &[&str]| {
+        iter::once(0)
+            .chain(tokens.iter().scan(0, |offset, token| {
+                *offset += token.len();
+                Some(*offset)
+            }))
+            .collect::<Vec<_>>()
+    };
+    let old_offsets = token_offsets(diff.old_slices());
+    let new_offsets = token_offsets(diff.new_slices());
+
+    let mut old_ranges = Vec::<Range<usize>>::new();
+    let mut new_ranges = Vec::<Range<usize>>::new();
+    let push_range = |ranges: &mut Vec<Range<usize>>, range: Range<usize>, text: &str| {
+        if range.is_empty() {
+            return;
+        }
+        match ranges.last_mut() {
+            Some(last) if text[last.end..range.start].trim().is_empty() => last.end = range.end,
+            _ => ranges.push(range),
+        }
+    };
+    for op in diff.ops() {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        if tag == DiffTag::Equal {
+            continue;
+        }
+        push_range(
+            &mut old_ranges,
+            old_offsets[old_range.start]..old_offsets[old_range.end],
+            old_text,
+        );
+        push_range(
+            &mut new_ranges,
+            new_offsets[new_range.start]..new_offsets[new_range.end],
+            new_text,
+        );
+    }
+    (old_ranges, new_ranges)
+}
+
 impl std::fmt::Debug for BufferDiff {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("BufferChangeSet")
@@ -593,8 +706,16 @@ impl BufferDiff {
         diff_base: String,
         cx: &mut gpui::TestAppContext,
     ) -> BufferDiffInner {
-        let snapshot =
-            cx.update(|cx| Self::build(buffer, Some(Arc::new(diff_base)), None, None, cx));
+        let snapshot = cx.update(|cx| {
+            Self::build(
+                buffer,
+                Some(Arc::new(diff_base)),
+                None,
+                None,
+                DiffAlgorithm::default(),
+                cx,
+            )
+        });
         cx.executor().block(snapshot)
     }
 
@@ -603,6 +724,7 @@ impl BufferDiff {
         diff_base: Option<Arc<String>>,
         language: Option<Arc<Language>>,
         language_registry: Option<Arc<LanguageRegistry>>,
+        algorithm: DiffAlgorithm,
         cx: &mut App,
     ) -> impl Future<Output = BufferDiffInner> {
         let diff_base =
@@ -621,7 +743,7 @@ impl BufferDiff {
 
         let hunks = cx.background_executor().spawn({
             let buffer = buffer.clone();
-            async move { compute_hunks(diff_base, buffer) }
+            async move { compute_hunks(diff_base, buffer, algorithm) }
         });
 
         async move {
@@ -634,6 +756,7 @@ impl BufferDiff {
         buffer: text::BufferSnapshot,
         diff_base: Option<Arc<String>>,
         diff_base_buffer: Option<language::BufferSnapshot>,
+        algorithm: DiffAlgorithm,
         cx: &App,
     ) -> impl Future<Output = BufferDiffInner> {
         let diff_base = diff_base.clone().zip(
@@ -643,7 +766,7 @@ impl BufferDiff {
         );
         cx.background_executor().spawn(async move {
             BufferDiffInner {
-                hunks: compute_hunks(diff_base, buffer),
+                hunks: compute_hunks(diff_base, buffer, algorithm),
                 base_text: diff_base_buffer,
             }
         })
@@ -730,6 +853,7 @@ impl BufferDiff {
         language_changed: bool,
         language: Option<Arc<Language>>,
         language_registry: Option<Arc<LanguageRegistry>>,
+        algorithm: DiffAlgorithm,
         cx: &mut AsyncApp,
     ) -> anyhow::Result<Option<Range<Anchor>>> {
         let snapshot = if base_text_changed || language_changed {
@@ -739,6 +863,7 @@ impl BufferDiff {
                     base_text,
                     language.clone(),
                     language_registry.clone(),
+                    algorithm,
                     cx,
                 )
             })?
@@ -749,6 +874,7 @@ impl BufferDiff {
                     buffer.clone(),
                     base_text,
                     this.base_text().cloned(),
+                    algorithm,
                     cx,
                 )
             })?
@@ -852,6 +978,7 @@ impl BufferDiff {
             Some(base_text),
             base_buffer.language().cloned(),
             language_registry,
+            DiffAlgorithm::default(),
             cx,
         );
         let complete_on_drop = util::defer(|| {
@@ -898,6 +1025,7 @@ impl BufferDiff {
             Some(base_text.into()),
             None,
             None,
+            DiffAlgorithm::default(),
             cx,
         );
         let snapshot = cx.background_executor().block(snapshot);
@@ -919,6 +1047,7 @@ impl BufferDiff {
             buffer.clone(),
             base_text.clone().map(Arc::new),
             self.inner.base_text.clone(),
+            DiffAlgorithm::default(),
             cx,
         );
         let snapshot = cx.background_executor().block(snapshot);
@@ -1181,7 +1310,14 @@ mod tests {
         let buffer = Buffer::new(0, BufferId::new(1).unwrap(), buffer_text);
         let diff = cx
             .update(|cx| {
-                BufferDiff::build(buffer.snapshot(), Some(diff_base.clone()), None, None, cx)
+                BufferDiff::build(
+                    buffer.snapshot(),
+                    Some(diff_base.clone()),
+                    None,
+                    None,
+                    DiffAlgorithm::default(),
+                    cx,
+                )
             })
             .await;
         assert_eq!(
@@ -1517,4 +1653,144 @@ mod tests {
             hunks = found_hunks;
         }
     }
+
+    #[test]
+    fn test_word_diff() {
+        let old_text = "let total = items.len() + 1;\n";
+        let new_text = "let count = items.iter().count() + 1;\n";
+        let (old_ranges, new_ranges) = word_diff(old_text, new_text);
+        assert_eq!(
+            old_ranges
+                .iter()
+                .map(|range| &old_text[range.clone()])
+                .collect::<Vec<_>>(),
+            ["total", "items.len()"]
+        );
+        assert_eq!(
+            new_ranges
+                .iter()
+                .map(|range| &new_text[range.clone()])
+                .collect::<Vec<_>>(),
+            ["count", "items.iter().count()"]
+        );
+
+        let (old_ranges, new_ranges) = word_diff("a b c d", "a x y d");
+        assert_eq!(old_ranges, [2..5]);
+        assert_eq!(new_ranges, [2..5]);
+        assert_eq!(word_diff("same\n", "same\n"), (vec![], vec![]));
+    }
+
+    #[gpui::test]
+    async fn test_buffer_diff_histogram(cx: &mut TestAppContext) {
+        let diff_base = "
+            fn one() {
+                a();
+            }
+
+            fn two() {
+                b();
+            }
+        "
+        .unindent();
+
+        let buffer_text = "
+            fn two() {
+                b();
+            }
+
+            fn one() {
+                a();
+            }
+            fn three() {}
+        "
+        .unindent();
+
+        let buffer = Buffer::new(0, BufferId::new(1).unwrap(), buffer_text);
+        let diff = cx
+            .update(|cx| {
+                BufferDiff::build(
+                    buffer.snapshot(),
+                    Some(diff_base.clone()),
+                    None,
+                    None,
+                    DiffAlgorithm::Histogram,
+                    cx,
+                )
+            })
+            .await;
+        assert_hunks(
+            diff.hunks_intersecting_range(Anchor::MIN..Anchor::MAX, &buffer, None),
+            &buffer,
+            &diff_base,
+            &[
+                (
+                    0..0,
+                    "fn one() {\n    a();\n}\n\n",
+                    "",
+                    DiffHunkStatus::removed(),
+                ),
+                (
+                    3..8,
+                    "",
+                    "\nfn one() {\n    a();\n}\nfn three() {}\n",
+                    DiffHunkStatus::added(),
+                ),
+            ],
+        );
+    }
+
+    #[gpui::test(iterations = 100)]
+    fn test_diff_ropes(mut rng: StdRng) {
+        let random_text = |rng: &mut StdRng| {
+            let mut text = String::new();
+            for _ in 0..rng.gen_range(0..20) {
+                text.push(rng.gen_range(b'a'..=b'd') as char);
+                if rng.gen_bool(0.9) {
+                    text.push('\n');
+                }
+            }
+            text
+        };
+        let old_text = random_text(&mut rng);
+        let new_text = random_text(&mut rng);
+        let old_lines = old_text.split_inclusive('\n').collect::<Vec<_>>();
+        let new_lines = new_text.split_inclusive('\n').collect::<Vec<_>>();
+
+        for algorithm in [
+            DiffAlgorithm::Myers,
+            DiffAlgorithm::Minimal,
+            DiffAlgorithm::Patience,
+            DiffAlgorithm::Histogram,
+        ] {
+            let hunks = diff_ropes(
+                &Rope::from(old_text.as_str()),
+                &Rope::from(new_text.as_str()),
+                algorithm,
+            );
+
+            // Replacing the old lines of each hunk by its new lines gives the new text, and
+            // the lines between the hunks are unchanged.
+            let mut patched_lines = Vec::new();
+            let (mut old_row, mut new_row) = (0, 0);
+            for hunk in &hunks {
+                assert!(hunk.old_rows.start >= old_row && hunk.new_rows.start >= new_row);
+                assert!(!hunk.old_rows.is_empty() || !hunk.new_rows.is_empty());
+                assert_eq!(
+                    old_lines[old_row as usize..hunk.old_rows.start as usize],
+                    new_lines[new_row as usize..hunk.new_rows.start as usize],
+                    "{algorithm:?}: {old_text:?} -> {new_text:?}"
+                );
+                patched_lines.extend(&old_lines[old_row as usize..hunk.old_rows.start as usize]);
+                patched_lines
+                    .extend(&new_lines[hunk.new_rows.start as usize..hunk.new_rows.end as usize]);
+                (old_row, new_row) = (hunk.old_rows.end, hunk.new_rows.end);
+            }
+            patched_lines.extend(&old_lines[old_row as usize..]);
+            assert_eq!(
+                patched_lines.concat(),
+                new_text,
+                "{algorithm:?}: {old_text:?} -> {new_text:?}"
+            );
+        }
+    }
 }
@@ -0,0 +1,274 @@
+//! A line diff of ropes that reads them chunk by chunk, so that large texts are diffed without
+//! copying them into strings.
+//!
+//! The lines of both ropes are interned as numbers, which are then diffed with the chosen
+//! [`DiffAlgorithm`]. The histogram algorithm, which git offers as `--histogram`, is implemented
+//! here; the others are those of [`similar`].
+
+use crate::DiffAlgorithm;
+use rope::{Point, Rope};
+use similar::{capture_diff_slices, Algorithm, DiffTag};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::Hasher as _,
+    mem,
+    ops::Range,
+};
+
+/// The number of occurrences of a line above which the histogram algorithm doesn't align on
+/// it, falling back to Myers when all the lines of a range occur more often.
+const MAX_CHAIN_LEN: usize = 63;
+
+/// The rows of lines that differ between two texts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineHunk {
+    pub old_rows: Range<u32>,
+    pub new_rows: Range<u32>,
+}
+
+/// Diffs the lines of the ropes with the algorithm, returning the sorted hunks of the lines
+/// that differ. Unlike a diff of their text, they're read one chunk at a time.
+pub fn diff_ropes(old: &Rope, new: &Rope, algorithm: DiffAlgorithm) -> Vec<LineHunk> {
+    let mut interner = LineInterner {
+        old,
+        new,
+        lines_by_hash: HashMap::default(),
+        line_count: 0,
+    };
+    let old_lines = interner.intern_lines(Side::Old);
+    let new_lines = interner.intern_lines(Side::New);
+    let mut hunks = Vec::new();
+    match algorithm {
+        DiffAlgorithm::Histogram => histogram_diff(&old_lines, &new_lines, &mut hunks),
+        DiffAlgorithm::Patience => push_diff_ops(
+            Algorithm::Patience,
+            &old_lines,
+            &new_lines,
+            0,
+            0,
+            &mut hunks,
+        ),
+        DiffAlgorithm::Myers | DiffAlgorithm::Minimal => {
+            push_diff_ops(Algorithm::Myers, &old_lines, &new_lines, 0, 0, &mut hunks)
+        }
+    }
+    hunks
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Old,
+    New,
+}
+
+/// Numbers the distinct lines of the texts, comparing lines whose hashes collide by their text.
+struct LineInterner<'a> {
+    old: &'a Rope,
+    new: &'a Rope,
+    /// The number of each line, by hash, along with where it first occurred.
+    lines_by_hash: HashMap<u64, Vec<(u32, Side, Range<usize>)>>,
+    line_count: u32,
+}
+
+impl<'a> LineInterner<'a> {
+    fn rope(&self, side: Side) -> &'a Rope {
+        match side {
+            Side::Old => self.old,
+            Side::New => self.new,
+        }
+    }
+
+    fn intern_lines(&mut self, side: Side) -> Vec<u32> {
+        let rope = self.rope(side);
+        let mut lines = Vec::with_capacity(rope.max_point().row as usize + 1);
+        let mut line_ranges = Vec::with_capacity(lines.capacity());
+        let mut hasher = DefaultHasher::new();
+        let mut line_start = 0;
+        let mut offset = 0;
+        for chunk in rope.chunks() {
+            let mut rest = chunk;
+            while let Some(newline_ix) = rest.find('\n') {
+                hasher.write(rest[..=newline_ix].as_bytes());
+                offset += newline_ix + 1;
+                let hash = mem::replace(&mut hasher, DefaultHasher::new()).finish();
+                line_ranges.push((hash, line_start..offset));
+                line_start = offset;
+                rest = &rest[newline_ix + 1..];
+            }
+            hasher.write(rest.as_bytes());
+            offset += rest.len();
+        }
+        if line_start < offset {
+            line_ranges.push((hasher.finish(), line_start..offset));
+        }
+        for (hash, range) in line_ranges {
+            lines.push(self.intern(hash, side, range));
+        }
+        lines
+    }
+
+    fn intern(&mut self, hash: u64, side: Side, range: Range<usize>) -> u32 {
+        let (old, new) = (self.old, self.new);
+        let rope = self.rope(side);
+        let candidates = self.lines_by_hash.entry(hash).or_default();
+        for (line, line_side, line_range) in candidates.iter() {
+            let line_rope = match line_side {
+                Side::Old => old,
+                Side::New => new,
+            };
+            if line_range.len() == range.len()
+                && line_rope
+                    .chunks_in_range(line_range.clone())
+                    .flat_map(str::bytes)
+                    .eq(rope.chunks_in_range(range.clone()).flat_map(str::bytes))
+            {
+                return *line;
+            }
+        }
+        let line = self.line_count;
+        self.line_count += 1;
+        candidates.push((line, side, range));
+        line
+    }
+}
+
+/// Returns the offset of the start of the row, or the length of the rope past its last row.
+pub(crate) fn row_start_offset(rope: &Rope, row: u32) -> usize {
+    if row > rope.max_point().row {
+        rope.len()
+    } else {
+        rope.point_to_offset(Point::new(row, 0))
+    }
+}
+
+/// Diffs the lines with the histogram algorithm: the ranges are split around their longest
+/// common region of lines occurring the fewest times, until they have no line in common.
+fn histogram_diff(old: &[u32], new: &[u32], hunks: &mut Vec<LineHunk>) {
+    // The ranges left to diff, the last one first, so that the hunks are pushed in order.
+    let mut ranges = vec![(0..old.len(), 0..new.len())];
+    while let Some((mut old_range, mut new_range)) = ranges.pop() {
+        let prefix_len = old[old_range.clone()]
+            .iter()
+            .zip(&new[new_range.clone()])
+            .take_while(|(old_line, new_line)| old_line == new_line)
+            .count();
+        old_range.start += prefix_len;
+        new_range.start += prefix_len;
+        let suffix_len = old[old_range.clone()]
+            .iter()
+            .rev()
+            .zip(new[new_range.clone()].iter().rev())
+            .take_while(|(old_line, new_line)| old_line == new_line)
+            .count();
+        old_range.end -= suffix_len;
+        new_range.end -= suffix_len;
+
+        if old_range.is_empty() || new_range.is_empty() {
+            if !old_range.is_empty() || !new_range.is_empty() {
+                push_hunk(hunks, old_range, new_range);
+            }
+            continue;
+        }
+
+        match longest_common_region(&old[old_range.clone()], &new[new_range.clone()]) {
+            Some((old_start, new_start, len)) => {
+                let old_start = old_range.start + old_start;
+                let new_start = new_range.start + new_start;
+                ranges.push((
+                    old_start + len..old_range.end,
+                    new_start + len..new_range.end,
+                ));
+                ranges.push((old_range.start..old_start, new_range.start..new_start));
+            }
+            None => push_diff_ops(
+                Algorithm::Myers,
+                &old[old_range.clone()],
+                &new[new_range.clone()],
+                old_range.start,
+                new_range.start,
+                hunks,
+            ),
+        }
+    }
+}
+
+/// Returns the start of the longest region the lines have in common, in each of them, along
+/// with its length, preferring the regions whose lines occur the fewest times in `old`.
+fn longest_common_region(old: &[u32], new: &[u32]) -> Option<(usize, usize, usize)> {
+    let mut occurrences = HashMap::<u32, Vec<usize>>::default();
+    for (ix, line) in old.iter().enumerate() {
+        occurrences.entry(*line).or_default().push(ix);
+    }
+
+    let mut best_region = None;
+    let mut best_count = MAX_CHAIN_LEN + 1;
+    let mut new_ix = 0;
+    while new_ix < new.len() {
+        let mut next_new_ix = new_ix + 1;
+        let old_ixs = occurrences
+            .get(&new[new_ix])
+            .filter(|old_ixs| old_ixs.len() <= best_count && old_ixs.len() <= MAX_CHAIN_LEN);
+        for &old_ix in old_ixs.into_iter().flatten() {
+            let (mut old_start, mut new_start) = (old_ix, new_ix);
+            while old_start > 0 && new_start > 0 && old[old_start - 1] == new[new_start - 1] {
+                old_start -= 1;
+                new_start -= 1;
+            }
+            let (mut old_end, mut new_end) = (old_ix + 1, new_ix + 1);
+            while old_end < old.len() && new_end < new.len() && old[old_end] == new[new_end] {
+                old_end += 1;
+                new_end += 1;
+            }
+            let count = old[old_start..old_end]
+                .iter()
+                .map(|line| occurrences.get(line).map_or(0, Vec::len))
+                .min()
+                .unwrap_or(0);
+            let len = old_end - old_start;
+            if best_region.map_or(true, |(_, _, best_len)| len > best_len) || count < best_count {
+                best_region = Some((old_start, new_start, len));
+                best_count = count;
+            }
+            next_new_ix = next_new_ix.max(new_end);
+        }
+        new_ix = next_new_ix;
+    }
+    best_region
+}
+
+/// Diffs the lines with one of the algorithms of `similar`, pushing the hunks offset by the
+/// given rows.
+fn push_diff_ops(
+    algorithm: Algorithm,
+    old: &[u32],
+    new: &[u32],
+    old_offset: usize,
+    new_offset: usize,
+    hunks: &mut Vec<LineHunk>,
+) {
+    for op in capture_diff_slices(algorithm, old, new) {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        if tag != DiffTag::Equal {
+            push_hunk(
+                hunks,
+                old_offset + old_range.start..old_offset + old_range.end,
+                new_offset + new_range.start..new_offset + new_range.end,
+            );
+        }
+    }
+}
+
+/// Pushes the hunk, merging it with the previous one when they're adjacent.
+fn push_hunk(hunks: &mut Vec<LineHunk>, old_range: Range<usize>, new_range: Range<usize>) {
+    let old_rows = old_range.start as u32..old_range.end as u32;
+    let new_rows = new_range.start as u32..new_range.end as u32;
+    match hunks.last_mut() {
+        Some(last)
+            if last.old_rows.end == old_rows.start && last.new_rows.end == new_rows.start =>
+        {
+            last.old_rows.end = old_rows.end;
+            last.new_rows.end = new_rows.end;
+        }
+        _ => hunks.push(LineHunk { old_rows, new_rows }),
+    }
+}
@@ -45,6 +45,8 @@ struct TelemetryState {
     first_event_date_time: Option<Instant>,
     event_coalescer: EventCoalescer,
     max_queue_size: usize,
+    /// The number of consecutive uploads that failed, which delays the next one.
+    failed_upload_count: u32,
     worktree_id_map: WorktreeIdMap,
 
     os_name: String,
@@ -81,6 +83,12 @@ const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
 
 #[cfg(not(debug_assertions))]
 const FLUSH_INTERVAL: Duration = Duration::from_secs(60 * 5);
+
+/// The longest delay between two upload attempts while uploads are failing.
+const MAX_FLUSH_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// How many events are kept while they can't be uploaded. The oldest ones are dropped first.
+const MAX_PENDING_EVENTS: usize = 1000;
 static ZED_CLIENT_CHECKSUM_SEED: LazyLock<Option<Vec<u8>>> = LazyLock::new(|| {
     option_env!("ZED_CLIENT_CHECKSUM_SEED")
         .map(|s| s.as_bytes().into())
@@ -198,6 +206,7 @@ impl Telemetry {
             first_event_date_time: None,
             event_coalescer: EventCoalescer::new(clock.clone()),
             max_queue_size: MAX_QUEUE_LEN,
+            failed_upload_count: 0,
             worktree_id_map: WorktreeIdMap(HashMap::from_iter([
                 (
                     "pnpm-lock.yaml".to_string(),
@@ -238,9 +247,16 @@ impl Telemetry {
             move |cx| {
                 let mut state = state.lock();
                 state.settings = *TelemetrySettings::get_global(cx);
+                ::telemetry::set_enabled(state.settings.metrics);
+                if !state.settings.metrics {
+                    state.events_queue.clear();
+                    state.flush_events_task = None;
+                    state.first_event_date_time = None;
+                }
             }
         })
         .detach();
+        ::telemetry::set_enabled(state.lock().settings.metrics);
 
         let this = Arc::new(Self {
             clock,
@@ -355,6 +371,9 @@ impl Telemetry {
 
     pub fn log_edit_event(self: &Arc<Self>, environment: &'static str, is_via_ssh: bool) {
         let mut state = self.state.lock();
+        if !state.settings.metrics {
+            return;
+        }
         let period_data = state.event_coalescer.log_event(environment);
         drop(state);
 
@@ -380,6 +399,9 @@ impl Telemetry {
     ) {
         let project_type_names: Vec<String> = {
             let mut state = self.state.lock();
+            if !state.settings.metrics {
+                return;
+            }
             state
                 .worktree_id_map
                 .0
@@ -425,14 +447,7 @@ impl Telemetry {
             return;
         }
 
-        if state.flush_events_task.is_none() {
-            let this = self.clone();
-            let executor = self.executor.clone();
-            state.flush_events_task = Some(self.executor.spawn(async move {
-                executor.timer(FLUSH_INTERVAL).await;
-                this.flush_events();
-            }));
-        }
+        self.schedule_flush(&mut state);
 
         let date_time = self.clock.utc_now();
 
@@ -453,13 +468,51 @@ impl Telemetry {
             milliseconds_since_first_event,
             event,
         });
+        drop_oldest_events(&mut state.events_queue);
 
-        if state.installation_id.is_some() && state.events_queue.len() >= state.max_queue_size {
+        if state.installation_id.is_some()
+            && state.failed_upload_count == 0
+            && state.events_queue.len() >= state.max_queue_size
+        {
             drop(state);
             self.flush_events();
         }
     }
 
+    fn schedule_flush(self: &Arc<Self>, state: &mut TelemetryState) {
+        if state.flush_events_task.is_none() {
+            let this = self.clone();
+            let executor = self.executor.clone();
+            let delay = flush_delay(state.failed_upload_count);
+            state.flush_events_task = Some(self.executor.spawn(async move {
+                executor.timer(delay).await;
+                this.flush_events();
+            }));
+        }
+    }
+
+    /// Puts the events of a failed upload back into the queue, so that they're sent
+    /// along with the next batch.
+    fn requeue_events(self: &Arc<Self>, mut events: Vec<EventWrapper>) {
+        let mut state = self.state.lock();
+        state.failed_upload_count = state.failed_upload_count.saturating_add(1);
+        if !state.settings.metrics {
+            return;
+        }
+        events.append(&mut state.events_queue);
+        drop_oldest_events(&mut events);
+        state.events_queue = events;
+        self.schedule_flush(&mut state);
+    }
+
+    /// Returns the request body that the next upload would send, containing the
+    /// events that are currently queued.
+    pub fn pending_payload(self: &Arc<Self>) -> Result<String> {
+        let state = self.state.lock();
+        let request_body = request_body(&state, state.events_queue.clone());
+        Ok(serde_json::to_string_pretty(&request_body)?)
+    }
+
     pub fn metrics_id(self: &Arc<Self>) -> Option<Arc<str>> {
         self.state.lock().metrics_id.clone()
     }
@@ -480,10 +533,10 @@ impl Telemetry {
         self: &Arc<Self>,
         // We take in the JSON bytes buffer so we can reuse the existing allocation.
         mut json_bytes: Vec<u8>,
-        event_request: EventRequestBody,
+        event_request: &EventRequestBody,
     ) -> Result<Request<AsyncBody>> {
         json_bytes.clear();
-        serde_json::to_writer(&mut json_bytes, &event_request)?;
+        serde_json::to_writer(&mut json_bytes, event_request)?;
 
         let checksum = calculate_json_checksum(&json_bytes).unwrap_or("".to_string());
 
@@ -504,8 +557,9 @@ impl Telemetry {
         state.first_event_date_time = None;
         let mut events = mem::take(&mut state.events_queue);
         state.flush_events_task.take();
+        let metrics_enabled = state.settings.metrics;
         drop(state);
-        if events.is_empty() {
+        if events.is_empty() || !metrics_enabled {
             return;
         }
 
@@ -524,29 +578,20 @@ impl Telemetry {
                         }
                     }
 
-                    let request_body = {
-                        let state = this.state.lock();
-
-                        EventRequestBody {
-                            system_id: state.system_id.as_deref().map(Into::into),
-                            installation_id: state.installation_id.as_deref().map(Into::into),
-                            session_id: state.session_id.clone(),
-                            metrics_id: state.metrics_id.as_deref().map(Into::into),
-                            is_staff: state.is_staff,
-                            app_version: state.app_version.clone(),
-                            os_name: state.os_name.clone(),
-                            os_version: state.os_version.clone(),
-                            architecture: state.architecture.to_string(),
-
-                            release_channel: state.release_channel.map(Into::into),
-                            events,
+                    let request_body = request_body(&this.state.lock(), events);
+                    let request = this.build_request(json_bytes, &request_body)?;
+                    match this.http_client.send(request).await {
+                        Ok(response) if response.status() == 200 => {
+                            this.state.lock().failed_upload_count = 0;
+                        }
+                        Ok(response) => {
+                            log::error!("Failed to send events: HTTP {:?}", response.status());
+                            this.requeue_events(request_body.events);
+                        }
+                        Err(error) => {
+                            this.requeue_events(request_body.events);
+                            return Err(error);
                         }
-                    };
-
-                    let request = this.build_request(json_bytes, request_body)?;
-                    let response = this.http_client.send(request).await?;
-                    if response.status() != 200 {
-                        log::error!("Failed to send events: HTTP {:?}", response.status());
                     }
                     anyhow::Ok(())
                 }
@@ -556,6 +601,37 @@ impl Telemetry {
     }
 }
 
+fn request_body(state: &TelemetryState, events: Vec<EventWrapper>) -> EventRequestBody {
+    EventRequestBody {
+        system_id: state.system_id.as_deref().map(Into::into),
+        installation_id: state.installation_id.as_deref().map(Into::into),
+        session_id: state.session_id.clone(),
+        metrics_id: state.metrics_id.as_deref().map(Into::into),
+        is_staff: state.is_staff,
+        app_version: state.app_version.clone(),
+        os_name: state.os_name.clone(),
+        os_version: state.os_version.clone(),
+        architecture: state.architecture.to_string(),
+
+        release_channel: state.release_channel.map(Into::into),
+        events,
+    }
+}
+
+/// Drops the oldest events beyond [`MAX_PENDING_EVENTS`], which accumulate while uploads
+/// are failing or before the installation id is known.
+fn drop_oldest_events(events: &mut Vec<EventWrapper>) {
+    let dropped_count = events.len().saturating_sub(MAX_PENDING_EVENTS);
+    events.drain(..dropped_count);
+}
+
+/// Doubles the flush interval for each consecutive failed upload.
+fn flush_delay(failed_upload_count: u32) -> Duration {
+    FLUSH_INTERVAL
+        .saturating_mul(1 << failed_upload_count.min(16))
+        .min(MAX_FLUSH_BACKOFF)
+}
+
 pub fn calculate_json_checksum(json: &impl AsRef<[u8]>) -> Option<String> {
     let Some(checksum_seed) = &*ZED_CLIENT_CHECKSUM_SEED else {
         return None;
@@ -698,6 +774,71 @@ mod tests {
         });
     }
 
+    #[gpui::test]
+    async fn test_telemetry_requeues_events_on_failed_upload(
+        executor: BackgroundExecutor,
+        cx: &mut TestAppContext,
+    ) {
+        init_test(cx);
+        let clock = Arc::new(FakeSystemClock::new());
+        let http = FakeHttpClient::with_404_response();
+
+        let telemetry = cx.update(|cx| {
+            let telemetry = Telemetry::new(clock.clone(), http, cx);
+            telemetry.state.lock().max_queue_size = 1;
+            telemetry.start(
+                Some("system_id".to_string()),
+                Some("installation_id".to_string()),
+                "session_id".to_string(),
+                cx,
+            );
+            telemetry
+        });
+
+        let event = FlexibleEvent {
+            event_type: "test".to_string(),
+            event_properties: HashMap::default(),
+        };
+        telemetry.report_event(Event::Flexible(event.clone()));
+        executor.run_until_parked();
+
+        // The failed upload puts the event back, and delays the next upload.
+        assert_eq!(telemetry.state.lock().events_queue.len(), 1);
+        assert_eq!(telemetry.state.lock().failed_upload_count, 1);
+        assert!(telemetry.state.lock().flush_events_task.is_some());
+        assert!(telemetry.pending_payload().unwrap().contains("\"test\""));
+
+        // Reaching the maximum queue size doesn't flush while uploads are failing.
+        telemetry.report_event(Event::Flexible(event));
+        assert_eq!(telemetry.state.lock().events_queue.len(), 2);
+
+        executor.advance_clock(flush_delay(1) - Duration::from_millis(1));
+        assert_eq!(telemetry.state.lock().failed_upload_count, 1);
+        executor.advance_clock(Duration::from_millis(1));
+        executor.run_until_parked();
+        assert_eq!(telemetry.state.lock().events_queue.len(), 2);
+        assert_eq!(telemetry.state.lock().failed_upload_count, 2);
+
+        // The queue keeps only the most recent events while uploads are failing.
+        for _ in 0..MAX_PENDING_EVENTS {
+            telemetry.report_event(Event::Flexible(FlexibleEvent {
+                event_type: "latest".to_string(),
+                event_properties: HashMap::default(),
+            }));
+        }
+        let state = telemetry.state.lock();
+        assert_eq!(state.events_queue.len(), MAX_PENDING_EVENTS);
+        assert!(state.events_queue.iter().all(|event| matches!(
+            &event.event,
+            Event::Flexible(event) if event.event_type == "latest"
+        )));
+        drop(state);
+
+        assert_eq!(flush_delay(0), FLUSH_INTERVAL);
+        assert_eq!(flush_delay(2), FLUSH_INTERVAL * 4);
+        assert_eq!(flush_delay(u32::MAX), MAX_FLUSH_BACKOFF);
+    }
+
     // TODO:
     // Test settings
     // Update FakeHTTPClient to keep track of the number of requests and assert on it
@@ -1044,6 +1044,7 @@ fn random_diagnostic(
             is_disk_based: false,
             is_unnecessary: false,
             data: None,
+            related_information: Vec::new(),
         },
     }
 }
@@ -188,6 +188,56 @@ pub struct DeleteToPreviousWordStart {
 #[derive(PartialEq, Clone, Deserialize, Default, JsonSchema)]
 pub struct FoldAtLevel(pub u32);
 
+#[derive(PartialEq, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SurroundSelections {
+    /// The delimiter inserted before each selection, e.g. `<!-- `.
+    pub start: String,
+    /// The delimiter inserted after each selection. Defaults to the end of the
+    /// language's surround pair starting with `start`, or else to `start`.
+    #[serde(default)]
+    pub end: Option<String>,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ChangeSurroundingPair {
+    /// The delimiter replacing the opening one of the enclosing pair.
+    pub start: String,
+    /// The delimiter replacing the closing one of the enclosing pair. Defaults
+    /// to the end of the language's surround pair starting with `start`, or
+    /// else to `start`.
+    #[serde(default)]
+    pub end: Option<String>,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Default, JsonSchema)]
+pub struct SetDiagnosticsMaxSeverity(pub DiagnosticSeverityFilter);
+
+#[derive(PartialEq, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SelectAround {
+    /// The object whose text and delimiters are selected. Defaults to the innermost brackets.
+    #[serde(default)]
+    pub object: SurroundingObject,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SelectInside {
+    /// The object whose text is selected. Defaults to the innermost brackets.
+    #[serde(default)]
+    pub object: SurroundingObject,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SplitSelectionAtDelimiter {
+    /// The text to split at, after which a newline is inserted. Defaults to `,`.
+    #[serde(default)]
+    pub delimiter: Option<String>,
+}
+
 #[derive(PartialEq, Clone, Deserialize, Default, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct SpawnNearestTask {
@@ -195,6 +245,29 @@ pub struct SpawnNearestTask {
     pub reveal: task::RevealStrategy,
 }
 
+#[derive(PartialEq, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ExportAsHtml {
+    /// Whether folded ranges are replaced by a placeholder instead of being exported.
+    #[serde(default = "default_true")]
+    pub preserve_folds: bool,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ExportAsAnsi {
+    /// Whether folded ranges are replaced by a placeholder instead of being exported.
+    #[serde(default = "default_true")]
+    pub preserve_folds: bool,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct OpenVirtualDocument {
+    /// The URI of the document, like `git://HEAD~1/src/main.rs`.
+    pub uri: String,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize, Default)]
 pub enum UuidVersion {
     #[default]
@@ -213,6 +286,8 @@ impl_actions!(
         ExpandExcerpts,
         ExpandExcerptsDown,
         ExpandExcerptsUp,
+        ExportAsAnsi,
+        ExportAsHtml,
         FoldAt,
         HandleInput,
         MoveDownByLines,
@@ -221,7 +296,10 @@ impl_actions!(
         MoveToBeginningOfLine,
         MoveToEndOfLine,
         MoveUpByLines,
+        OpenVirtualDocument,
+        SelectAround,
         SelectDownByLines,
+        SelectInside,
         SelectNext,
         SelectPrevious,
         SelectToBeginningOfLine,
@@ -229,10 +307,14 @@ impl_actions!(
         SelectUpByLines,
         SpawnNearestTask,
         ShowCompletions,
+        SplitSelectionAtDelimiter,
+        SurroundSelections,
+        ChangeSurroundingPair,
         ToggleCodeActions,
         ToggleComments,
         UnfoldAt,
         FoldAtLevel,
+        SetDiagnosticsMaxSeverity,
     ]
 );
 
@@ -246,6 +328,7 @@ gpui::actions!(
         AddSelectionBelow,
         ApplyAllDiffHunks,
         ApplyDiffHunk,
+        ApplyDiffHunksInFile,
         Backspace,
         Cancel,
         CancelLanguageServerWork,
@@ -270,8 +353,10 @@ gpui::actions!(
         CopyPath,
         CopyPermalinkToLine,
         CopyRelativePath,
+        CopyWithContext,
         Cut,
         CutToEndOfLine,
+        CycleStringQuotes,
         Delete,
         DeleteLine,
         DeleteToBeginningOfLine,
@@ -282,13 +367,18 @@ gpui::actions!(
         DuplicateLineDown,
         DuplicateLineUp,
         DuplicateSelection,
+        EditBookmarkNote,
+        EditStringLiteral,
         ExpandAllHunkDiffs,
         ExpandMacroRecursively,
+        ExportBookmarks,
         FindAllReferences,
         Fold,
         FoldAll,
+        FoldAllExceptSelections,
         FoldFunctionBodies,
         FoldRecursive,
+        FoldSelectedLines,
         FoldSelectedRanges,
         ToggleFold,
         ToggleFoldRecursive,
@@ -302,14 +392,19 @@ gpui::actions!(
         GoToHunk,
         GoToImplementation,
         GoToImplementationSplit,
+        GoToLastEdit,
+        GoToNextEdit,
         GoToPrevDiagnostic,
         GoToPrevHunk,
+        GoToPreviousEdit,
         GoToTypeDefinition,
         GoToTypeDefinitionSplit,
         HalfPageDown,
         HalfPageUp,
         Hover,
+        ImportBookmarks,
         Indent,
+        InsertSelectionEvaluation,
         InsertUuidV4,
         InsertUuidV7,
         JoinLines,
@@ -350,11 +445,15 @@ gpui::actions!(
         PageDown,
         PageUp,
         Paste,
+        PasteAsMarkdown,
+        PasteWithoutReindent,
         PreviousEditPrediction,
         Redo,
         RedoSelection,
+        RejectDiffHunksInFile,
         Rename,
         RestartLanguageServer,
+        RestartLanguageServerWithNewConfig,
         RevealInFileManager,
         ReverseLines,
         RevertFile,
@@ -389,6 +488,8 @@ gpui::actions!(
         ShowEditPrediction,
         ShowSignatureHelp,
         ShuffleLines,
+        SkipAndSelectNext,
+        SmartJoinLines,
         SortLinesCaseInsensitive,
         SortLinesCaseSensitive,
         SplitSelectionIntoLines,
@@ -396,21 +497,28 @@ gpui::actions!(
         Tab,
         TabPrev,
         ToggleAutoSignatureHelp,
+        ToggleBookmark,
+        ToggleDiagnosticDetails,
+        ToggleFocusMode,
         ToggleGitBlame,
         ToggleGitBlameInline,
         ToggleIndentGuides,
         ToggleInlayHints,
+        ToggleInlineDiagnostics,
         ToggleEditPrediction,
         ToggleLineNumbers,
+        ToggleRawString,
         ToggleStagedSelectedDiffHunks,
         SwapSelectionEnds,
         SetMark,
         ToggleRelativeLineNumbers,
+        ToggleSelectionEvaluation,
         ToggleSelectionMenu,
         ToggleSoftWrap,
         ToggleTabBar,
         Transpose,
         Undo,
+        UndoSelectNext,
         UndoSelection,
         UnfoldAll,
         UnfoldLines,
@@ -0,0 +1,580 @@
+//! Bookmarked lines, each optionally annotated with a note.
+//!
+//! Bookmarks are kept per buffer, as anchors, so that they follow the line they were set on as
+//! the buffer is edited, and they're shown in every editor of the buffer. The note of a bookmark
+//! is shown when hovering its indicator in the gutter. The bookmarks of a project can be exported
+//! to a JSON file, with paths relative to their worktree, and imported in another checkout of
+//! the project, for instance to walk reviewers through a codebase.
+
+use crate::{
+    actions::{EditBookmarkNote, ExportBookmarks, ImportBookmarks, SelectAll, ToggleBookmark},
+    display_map::{DisplayRow, ToDisplayPoint as _},
+    DisplayPoint, Editor, EditorSnapshot,
+};
+use anyhow::Context as _;
+use collections::HashMap;
+use gpui::{
+    App, AsyncWindowContext, Context, DismissEvent, Entity, EntityId, EventEmitter, FocusHandle,
+    Focusable, Global, PathPromptOptions, Render, SharedString, WeakEntity, Window,
+};
+use language::{Buffer, BufferSnapshot, Point};
+use project::{Project, ProjectPath};
+use serde::{Deserialize, Serialize};
+use std::{ops::Range, path::PathBuf, sync::Arc};
+use text::{Bias, ToOffset as _, ToPoint as _};
+use theme::ActiveTheme as _;
+use ui::{prelude::*, IconButtonShape, Tooltip};
+use util::ResultExt as _;
+use workspace::ModalView;
+
+#[derive(Clone)]
+struct Bookmark {
+    anchor: text::Anchor,
+    note: SharedString,
+}
+
+struct BufferBookmarks {
+    buffer: WeakEntity<Buffer>,
+    bookmarks: Vec<Bookmark>,
+}
+
+#[derive(Default)]
+struct Bookmarks {
+    buffers: HashMap<EntityId, BufferBookmarks>,
+}
+
+impl Global for Bookmarks {}
+
+/// The bookmarks of a project, as exported to a file.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct BookmarksFile {
+    bookmarks: Vec<BookmarkEntry>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct BookmarkEntry {
+    /// The path of the file, relative to its worktree.
+    path: PathBuf,
+    /// The one-based line of the bookmark.
+    line: u32,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    note: String,
+}
+
+impl Bookmarks {
+    fn for_buffer(&self, buffer: &Entity<Buffer>) -> &[Bookmark] {
+        self.buffers
+            .get(&buffer.entity_id())
+            .map_or(&[], |buffer_bookmarks| &buffer_bookmarks.bookmarks)
+    }
+
+    fn position(
+        &self,
+        buffer: &Entity<Buffer>,
+        snapshot: &BufferSnapshot,
+        row: u32,
+    ) -> Option<usize> {
+        self.for_buffer(buffer)
+            .iter()
+            .position(|bookmark| bookmark.anchor.to_point(snapshot).row == row)
+    }
+
+    fn insert(
+        &mut self,
+        buffer: &Entity<Buffer>,
+        snapshot: &BufferSnapshot,
+        row: u32,
+        note: SharedString,
+    ) {
+        if let Some(ix) = self.position(buffer, snapshot, row) {
+            self.buffers.get_mut(&buffer.entity_id()).unwrap().bookmarks[ix].note = note;
+            return;
+        }
+        let anchor = snapshot.anchor_after(Point::new(row, 0));
+        self.buffers
+            .retain(|_, buffer_bookmarks| buffer_bookmarks.buffer.upgrade().is_some());
+        self.buffers
+            .entry(buffer.entity_id())
+            .or_insert_with(|| BufferBookmarks {
+                buffer: buffer.downgrade(),
+                bookmarks: Vec::new(),
+            })
+            .bookmarks
+            .push(Bookmark { anchor, note });
+    }
+
+    fn remove(&mut self, buffer: &Entity<Buffer>, snapshot: &BufferSnapshot, row: u32) {
+        if let Some(ix) = self.position(buffer, snapshot, row) {
+            self.buffers
+                .get_mut(&buffer.entity_id())
+                .unwrap()
+                .bookmarks
+                .remove(ix);
+        }
+    }
+}
+
+impl Editor {
+    pub fn toggle_bookmark(&mut self, _: &ToggleBookmark, _: &mut Window, cx: &mut Context<Self>) {
+        let mut rows = self
+            .selections
+            .all::<Point>(cx)
+            .into_iter()
+            .filter_map(|selection| self.buffer_row(selection.head(), cx))
+            .collect::<Vec<_>>();
+        rows.dedup_by(|(a_buffer, a_row), (b_buffer, b_row)| {
+            a_buffer == b_buffer && a_row == b_row
+        });
+
+        for (buffer, row) in &rows {
+            let snapshot = buffer.read(cx).snapshot();
+            let bookmarks = cx.default_global::<Bookmarks>();
+            if bookmarks.position(buffer, &snapshot, *row).is_some() {
+                bookmarks.remove(buffer, &snapshot, *row);
+            } else {
+                bookmarks.insert(buffer, &snapshot, *row, SharedString::default());
+            }
+        }
+        cx.notify();
+    }
+
+    pub fn edit_bookmark_note(
+        &mut self,
+        _: &EditBookmarkNote,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let head = self.selections.newest::<Point>(cx).head();
+        if let Some((buffer, row)) = self.buffer_row(head, cx) {
+            self.open_bookmark_note_editor(buffer, row, window, cx);
+        }
+    }
+
+    /// Records the note of the bookmark on the buffer row, bookmarking it if needed.
+    pub fn set_bookmark_note(
+        buffer: &Entity<Buffer>,
+        row: u32,
+        note: impl Into<SharedString>,
+        cx: &mut App,
+    ) {
+        let snapshot = buffer.read(cx).snapshot();
+        cx.default_global::<Bookmarks>()
+            .insert(buffer, &snapshot, row, note.into());
+    }
+
+    pub fn export_bookmarks(
+        &mut self,
+        _: &ExportBookmarks,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(project) = self.project.clone() else {
+            return;
+        };
+        let bookmarks_file = bookmarks_file(&project, cx);
+        let directory = project
+            .read(cx)
+            .visible_worktrees(cx)
+            .next()
+            .map(|worktree| worktree.read(cx).abs_path().to_path_buf())
+            .unwrap_or_else(|| util::paths::home_dir().clone());
+
+        let fs = project.read(cx).fs().clone();
+        let path = cx.prompt_for_new_path(&directory);
+        cx.spawn_in(window, |_, _| async move {
+            let Some(path) = path.await?? else {
+                return anyhow::Ok(());
+            };
+            let json = serde_json::to_string_pretty(&bookmarks_file)?;
+            fs.save(&path, &json.as_str().into(), Default::default())
+                .await?;
+            anyhow::Ok(())
+        })
+        .detach_and_log_err(cx);
+    }
+
+    pub fn import_bookmarks(
+        &mut self,
+        _: &ImportBookmarks,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(project) = self.project.clone() else {
+            return;
+        };
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+        });
+        cx.spawn_in(window, |editor, mut cx| async move {
+            let Some(path) = paths.await??.and_then(|paths| paths.into_iter().next()) else {
+                return anyhow::Ok(());
+            };
+            let fs = project.update(&mut cx, |project, _| project.fs().clone())?;
+            let json = fs.load(&path).await?;
+            let bookmarks_file = serde_json::from_str::<BookmarksFile>(&json)
+                .with_context(|| format!("parsing bookmarks from {path:?}"))?;
+            import_bookmarks_file(&project, bookmarks_file, &mut cx).await;
+            editor.update(&mut cx, |_, cx| cx.notify())?;
+            anyhow::Ok(())
+        })
+        .detach_and_log_err(cx);
+    }
+
+    /// The bookmarks shown in the given display rows, with their notes, except those of the rows
+    /// showing a runnable's indicator, when runnables are shown.
+    pub(crate) fn bookmarks_in_display_rows(
+        &self,
+        snapshot: &EditorSnapshot,
+        range: Range<DisplayRow>,
+        show_runnables: bool,
+        cx: &App,
+    ) -> Vec<(DisplayRow, SharedString)> {
+        let Some(bookmarks) = cx.try_global::<Bookmarks>() else {
+            return Vec::new();
+        };
+        let multi_buffer = self.buffer.read(cx);
+        let start = snapshot
+            .display_point_to_anchor(DisplayPoint::new(range.start, 0), Bias::Left)
+            .to_offset(&snapshot.buffer_snapshot);
+        let end = snapshot
+            .display_point_to_anchor(DisplayPoint::new(range.end, 0), Bias::Right)
+            .to_offset(&snapshot.buffer_snapshot);
+        let mut rows = Vec::new();
+        for (buffer_snapshot, buffer_range, excerpt_id) in
+            snapshot.buffer_snapshot.range_to_buffer_ranges(start..end)
+        {
+            let buffer_id = buffer_snapshot.remote_id();
+            let Some(buffer_bookmarks) = multi_buffer
+                .buffer(buffer_id)
+                .and_then(|buffer| bookmarks.buffers.get(&buffer.entity_id()))
+            else {
+                continue;
+            };
+            for bookmark in &buffer_bookmarks.bookmarks {
+                let offset = bookmark.anchor.to_offset(buffer_snapshot);
+                if offset < buffer_range.start || buffer_range.end < offset {
+                    continue;
+                }
+                let buffer_row = bookmark.anchor.to_point(buffer_snapshot).row;
+                if show_runnables && self.tasks.contains_key(&(buffer_id, buffer_row)) {
+                    continue;
+                }
+                let Some(anchor) = snapshot
+                    .buffer_snapshot
+                    .anchor_in_excerpt(excerpt_id, bookmark.anchor)
+                else {
+                    continue;
+                };
+                let row = anchor.to_display_point(&snapshot.display_snapshot).row();
+                if range.contains(&row) {
+                    rows.push((row, bookmark.note.clone()));
+                }
+            }
+        }
+        rows.sort_by_key(|(row, _)| *row);
+        rows.dedup_by_key(|(row, _)| *row);
+        rows
+    }
+
+    pub(crate) fn render_bookmark_indicator(
+        &self,
+        row: DisplayRow,
+        note: SharedString,
+        cx: &mut Context<Self>,
+    ) -> IconButton {
+        let tooltip = if note.is_empty() {
+            SharedString::from("Bookmark")
+        } else {
+            note
+        };
+        IconButton::new(("bookmark_indicator", row.0 as usize), IconName::Bookmark)
+            .shape(IconButtonShape::Square)
+            .icon_size(IconSize::XSmall)
+            .icon_color(Color::Accent)
+            .tooltip(Tooltip::text(tooltip))
+            .on_click(cx.listener(move |editor, _, window, cx| {
+                let snapshot = editor.snapshot(window, cx);
+                let point = DisplayPoint::new(row, 0).to_point(&snapshot.display_snapshot);
+                if let Some((buffer, row)) = editor.buffer_row(point, cx) {
+                    editor.open_bookmark_note_editor(buffer, row, window, cx);
+                }
+            }))
+    }
+
+    /// The buffer containing the multibuffer point, and the row of the point in it.
+    fn buffer_row(&self, point: Point, cx: &App) -> Option<(Entity<Buffer>, u32)> {
+        let (buffer, buffer_point, _) = self.buffer.read(cx).point_to_buffer_point(point, cx)?;
+        Some((buffer, buffer_point.row))
+    }
+
+    fn open_bookmark_note_editor(
+        &mut self,
+        buffer: Entity<Buffer>,
+        row: u32,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(workspace) = self.workspace() else {
+            return;
+        };
+        let snapshot = buffer.read(cx).snapshot();
+        let note = cx.try_global::<Bookmarks>().and_then(|bookmarks| {
+            let ix = bookmarks.position(&buffer, &snapshot, row)?;
+            Some(bookmarks.for_buffer(&buffer)[ix].note.clone())
+        });
+        let editor = cx.entity().downgrade();
+        workspace.update(cx, |workspace, cx| {
+            workspace.toggle_modal(window, cx, |window, cx| {
+                BookmarkNoteEditor::new(editor, buffer, row, note, window, cx)
+            })
+        });
+    }
+}
+
+/// The bookmarks of the project's buffers, sorted by path and line.
+fn bookmarks_file(project: &Entity<Project>, cx: &App) -> BookmarksFile {
+    let mut entries = Vec::new();
+    if let Some(bookmarks) = cx.try_global::<Bookmarks>() {
+        for buffer_bookmarks in bookmarks.buffers.values() {
+            let Some(buffer) = buffer_bookmarks.buffer.upgrade() else {
+                continue;
+            };
+            let buffer = buffer.read(cx);
+            let Some(file) = buffer.file() else {
+                continue;
+            };
+            if project
+                .read(cx)
+                .worktree_for_id(file.worktree_id(cx), cx)
+                .is_none()
+            {
+                continue;
+            }
+            for bookmark in &buffer_bookmarks.bookmarks {
+                entries.push(BookmarkEntry {
+                    path: file.path().to_path_buf(),
+                    line: bookmark.anchor.to_point(buffer).row + 1,
+                    note: bookmark.note.to_string(),
+                });
+            }
+        }
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+    BookmarksFile { bookmarks: entries }
+}
+
+/// Bookmarks the lines listed in the file, in the first worktree containing each path.
+async fn import_bookmarks_file(
+    project: &Entity<Project>,
+    bookmarks_file: BookmarksFile,
+    cx: &mut AsyncWindowContext,
+) {
+    for entry in bookmarks_file.bookmarks {
+        let Ok(Some(project_path)) = project.update(cx, |project, cx| {
+            project.worktrees(cx).find_map(|worktree| {
+                let worktree = worktree.read(cx);
+                worktree.entry_for_path(&entry.path).map(|_| ProjectPath {
+                    worktree_id: worktree.id(),
+                    path: Arc::from(entry.path.as_path()),
+                })
+            })
+        }) else {
+            log::warn!("no worktree contains the bookmarked file {:?}", entry.path);
+            continue;
+        };
+        let Ok(open_buffer) =
+            project.update(cx, |project, cx| project.open_buffer(project_path, cx))
+        else {
+            return;
+        };
+        let Some(buffer) = open_buffer.await.log_err() else {
+            continue;
+        };
+        cx.update(|_, cx| {
+            let row = entry
+                .line
+                .saturating_sub(1)
+                .min(buffer.read(cx).max_point().row);
+            Editor::set_bookmark_note(&buffer, row, entry.note, cx);
+        })
+        .ok();
+    }
+}
+
+struct BookmarkNoteEditor {
+    note_editor: Entity<Editor>,
+    editor: WeakEntity<Editor>,
+    buffer: Entity<Buffer>,
+    row: u32,
+}
+
+impl ModalView for BookmarkNoteEditor {}
+
+impl EventEmitter<DismissEvent> for BookmarkNoteEditor {}
+
+impl Focusable for BookmarkNoteEditor {
+    fn focus_handle(&self, cx: &App) -> FocusHandle {
+        self.note_editor.focus_handle(cx)
+    }
+}
+
+impl BookmarkNoteEditor {
+    fn new(
+        editor: WeakEntity<Editor>,
+        buffer: Entity<Buffer>,
+        row: u32,
+        note: Option<SharedString>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let note_editor = cx.new(|cx| {
+            let mut note_editor = Editor::single_line(window, cx);
+            note_editor.set_placeholder_text("Add a note to the bookmark…", cx);
+            if let Some(note) = note {
+                note_editor.set_text(note.to_string(), window, cx);
+                note_editor.select_all(&SelectAll, window, cx);
+            }
+            note_editor
+        });
+        Self {
+            note_editor,
+            editor,
+            buffer,
+            row,
+        }
+    }
+
+    fn cancel(&mut self, _: &menu::Cancel, _: &mut Window, cx: &mut Context<Self>) {
+        cx.emit(DismissEvent);
+    }
+
+    fn confirm(&mut self, _: &menu::Confirm, window: &mut Window, cx: &mut Context<Self>) {
+        let note = self.note_editor.read(cx).text(cx);
+        Editor::set_bookmark_note(&self.buffer, self.row, note.trim().to_string(), cx);
+        self.editor
+            .update(cx, |editor, cx| {
+                editor.focus_handle(cx).focus(window);
+                cx.notify();
+            })
+            .ok();
+        cx.emit(DismissEvent);
+    }
+}
+
+impl Render for BookmarkNoteEditor {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        v_flex()
+            .w(rems(34.))
+            .elevation_2(cx)
+            .key_context("BookmarkNoteEditor")
+            .on_action(cx.listener(Self::cancel))
+            .on_action(cx.listener(Self::confirm))
+            .child(
+                div()
+                    .border_b_1()
+                    .border_color(cx.theme().colors().border_variant)
+                    .px_2()
+                    .py_1()
+                    .child(self.note_editor.clone()),
+            )
+            .child(
+                h_flex().px_2().py_1().child(
+                    Label::new(format!("Note for the bookmark on line {}", self.row + 1))
+                        .color(Color::Muted),
+                ),
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::editor_test_context::EditorTestContext;
+    use gpui::TestAppContext;
+    use indoc::indoc;
+
+    #[gpui::test]
+    async fn test_toggle_bookmark(cx: &mut TestAppContext) {
+        crate::editor_tests::init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state(indoc! {"
+            one
+            tˇwo
+            three
+        "});
+
+        let bookmarked_rows = |cx: &mut EditorTestContext| {
+            cx.update_editor(|editor, window, cx| {
+                let snapshot = editor.snapshot(window, cx);
+                editor
+                    .bookmarks_in_display_rows(&snapshot, DisplayRow(0)..DisplayRow(4), cx)
+                    .into_iter()
+                    .map(|(row, note)| (row.0, note.to_string()))
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        cx.update_editor(|editor, window, cx| editor.toggle_bookmark(&ToggleBookmark, window, cx));
+        assert_eq!(bookmarked_rows(&mut cx), [(1, String::new())]);
+
+        // The bookmark follows its line.
+        cx.update_editor(|editor, window, cx| {
+            editor.change_selections(None, window, cx, |s| {
+                s.select_ranges([Point::new(0, 0)..Point::new(0, 0)])
+            })
+        });
+        cx.simulate_input("zero\n");
+        assert_eq!(bookmarked_rows(&mut cx), [(2, String::new())]);
+
+        cx.update_editor(|editor, _, cx| {
+            let buffer = editor.buffer().read(cx).as_singleton().unwrap();
+            Editor::set_bookmark_note(&buffer, 2, "Where it starts", cx);
+        });
+        assert_eq!(
+            bookmarked_rows(&mut cx),
+            [(2, "Where it starts".to_string())]
+        );
+
+        cx.update_editor(|editor, window, cx| {
+            editor.change_selections(None, window, cx, |s| {
+                s.select_ranges([Point::new(2, 1)..Point::new(2, 1)])
+            });
+            editor.toggle_bookmark(&ToggleBookmark, window, cx);
+        });
+        assert_eq!(bookmarked_rows(&mut cx), Vec::new());
+    }
+
+    #[test]
+    fn test_bookmarks_file_format() {
+        let bookmarks_file = BookmarksFile {
+            bookmarks: vec![
+                BookmarkEntry {
+                    path: PathBuf::from("src/main.rs"),
+                    line: 3,
+                    note: "Where it starts".into(),
+                },
+                BookmarkEntry {
+                    path: PathBuf::from("src/lib.rs"),
+                    line: 10,
+                    note: String::new(),
+                },
+            ],
+        };
+        let json = serde_json::to_value(&bookmarks_file).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "bookmarks": [
+                    { "path": "src/main.rs", "line": 3, "note": "Where it starts" },
+                    { "path": "src/lib.rs", "line": 10 },
+                ]
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<BookmarksFile>(json).unwrap(),
+            bookmarks_file
+        );
+    }
+}
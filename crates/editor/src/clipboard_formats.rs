@@ -0,0 +1,273 @@
+//! Pasting what the clipboard holds besides plain text, and copying more than plain text.
+//!
+//! Files copied from a file manager are pasted as their paths, one per line, or opened in the
+//! workspace, as the `paste_files` setting says. Text copied from a browser or a document comes
+//! with its HTML: it's pasted as plain text, or converted to Markdown by `editor::PasteAsMarkdown`.
+//! When `copy_syntax_highlighting` is on, copied text carries its highlighting as HTML, for the
+//! targets that paste rich text. `editor::CopyWithContext` copies the selection after a comment
+//! saying where it comes from: its path, its lines and the symbols it's in, as in
+//! `// src/main.rs:3-4 (impl Foo > fn bar)`.
+
+use crate::{
+    actions::{CopyWithContext, PasteAsMarkdown},
+    Editor, EditorSettings, PasteFiles,
+};
+use gpui::{App, ClipboardItem, Context, Window};
+use html_to_markdown::{convert_html_to_markdown, markdown, TagHandler};
+use language::{BufferSnapshot, Point};
+use multi_buffer::MultiBufferRow;
+use settings::Settings as _;
+use std::{cell::RefCell, ops::Range, path::PathBuf, rc::Rc};
+use util::ResultExt as _;
+use workspace::OpenVisible;
+
+impl Editor {
+    pub fn paste_as_markdown(
+        &mut self,
+        _: &PasteAsMarkdown,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(markdown) = cx
+            .read_from_clipboard()
+            .and_then(|item| item.html().map(ToOwned::to_owned))
+            .and_then(|html| html_to_markdown(&html).log_err())
+        else {
+            self.paste_from_clipboard(true, window, cx);
+            return;
+        };
+        self.do_paste(&markdown, None, false, window, cx);
+    }
+
+    pub fn copy_with_context(
+        &mut self,
+        _: &CopyWithContext,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let mut selection = self.selections.newest::<Point>(cx).range();
+        if selection.is_empty() {
+            let row = selection.start.row;
+            selection = Point::new(row, 0)..Point::new(row, snapshot.line_len(MultiBufferRow(row)));
+        }
+        let Some((buffer, range, _)) = snapshot
+            .range_to_buffer_ranges(selection)
+            .into_iter()
+            .next()
+        else {
+            return;
+        };
+
+        let text = buffer.text_for_range(range.clone()).collect::<String>();
+        let header = context_comment(buffer, range);
+        cx.write_to_clipboard(ClipboardItem::new_string(format!("{header}\n{text}")));
+    }
+
+    /// Pastes the paths of copied files, or opens them.
+    pub(crate) fn paste_external_paths(
+        &mut self,
+        paths: Vec<PathBuf>,
+        reindent: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if EditorSettings::get_global(cx).paste_files == PasteFiles::Open {
+            if let Some(workspace) = self.workspace() {
+                window.defer(cx, move |window, cx| {
+                    workspace.update(cx, |workspace, cx| {
+                        workspace
+                            .open_paths(paths, OpenVisible::None, None, window, cx)
+                            .detach();
+                    });
+                });
+                return;
+            }
+        }
+
+        let text = paths
+            .iter()
+            .map(|path| path.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.do_paste_with_reindent(&text, None, false, reindent, window, cx);
+    }
+
+    /// Adds the highlighted HTML of the copied ranges to the item, when
+    /// `copy_syntax_highlighting` is on.
+    pub(crate) fn with_copied_html(
+        &self,
+        item: ClipboardItem,
+        copied_ranges: impl IntoIterator<Item = Range<Point>>,
+        cx: &App,
+    ) -> ClipboardItem {
+        if !EditorSettings::get_global(cx).copy_syntax_highlighting {
+            return item;
+        }
+        let copied_ranges = copied_ranges.into_iter().collect::<Vec<_>>();
+        match self.html_for_clipboard(&copied_ranges, cx) {
+            Some(html) => item.with_html(html),
+            None => item,
+        }
+    }
+}
+
+/// A comment saying where the range of the buffer is: its path, its lines and the symbols
+/// containing it.
+fn context_comment(buffer: &BufferSnapshot, range: Range<usize>) -> String {
+    let start = buffer.offset_to_point(range.start);
+    let mut end = buffer.offset_to_point(range.end);
+    if end.column == 0 && end.row > start.row {
+        end.row -= 1;
+    }
+
+    let mut context = buffer.file().map_or_else(
+        || "untitled".to_string(),
+        |file| file.path().to_string_lossy().into_owned(),
+    );
+    if start.row == end.row {
+        context.push_str(&format!(":{}", start.row + 1));
+    } else {
+        context.push_str(&format!(":{}-{}", start.row + 1, end.row + 1));
+    }
+    // Look up the symbols after the indentation of the first line, which may be outside of them.
+    let indent_len = buffer
+        .chars_at(range.start)
+        .take_while(|c| c.is_whitespace())
+        .map(char::len_utf8)
+        .sum::<usize>();
+    let symbols = buffer
+        .symbols_containing((range.start + indent_len).min(range.end), None)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|symbol| symbol.text)
+        .collect::<Vec<_>>();
+    if !symbols.is_empty() {
+        context.push_str(&format!(" ({})", symbols.join(" > ")));
+    }
+
+    let Some(scope) = buffer.language_scope_at(range.start) else {
+        return context;
+    };
+    if let Some(prefix) = scope.line_comment_prefixes().first() {
+        format!("{prefix}{context}")
+    } else if let Some((start, end)) = scope.block_comment_delimiters() {
+        format!("{start}{context}{end}")
+    } else {
+        context
+    }
+}
+
+fn html_to_markdown(html: &str) -> anyhow::Result<String> {
+    let mut handlers: Vec<TagHandler> = vec![
+        Rc::new(RefCell::new(markdown::WebpageChromeRemover)),
+        Rc::new(RefCell::new(markdown::ParagraphHandler)),
+        Rc::new(RefCell::new(markdown::HeadingHandler)),
+        Rc::new(RefCell::new(markdown::ListHandler)),
+        Rc::new(RefCell::new(markdown::TableHandler::new())),
+        Rc::new(RefCell::new(markdown::StyledTextHandler)),
+        Rc::new(RefCell::new(markdown::CodeHandler)),
+    ];
+    convert_html_to_markdown(html.as_bytes(), &mut handlers)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        actions::{CopyWithContext, Paste, PasteAsMarkdown},
+        editor_tests::init_test,
+        test::editor_test_context::EditorTestContext,
+    };
+    use gpui::{ClipboardItem, TestAppContext};
+    use indoc::indoc;
+    use language::{Language, LanguageConfig};
+    use std::{path::PathBuf, sync::Arc};
+
+    #[gpui::test]
+    async fn test_paste_formats(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+
+        // Copied files are pasted as their paths.
+        cx.set_state("ˇ");
+        cx.write_to_clipboard(ClipboardItem::new_external_paths([
+            PathBuf::from("/root/one.txt"),
+            PathBuf::from("/root/two.txt"),
+        ]));
+        cx.update_editor(|editor, window, cx| editor.paste(&Paste, window, cx));
+        cx.assert_editor_state("/root/one.txt\n/root/two.txtˇ");
+
+        // HTML is pasted as plain text, unless it's converted to Markdown.
+        cx.set_state("ˇ");
+        cx.write_to_clipboard(
+            ClipboardItem::new_string("Title\nSome bold text.".into())
+                .with_html("<h1>Title</h1><p>Some <strong>bold</strong> text.</p>".into()),
+        );
+        cx.update_editor(|editor, window, cx| editor.paste(&Paste, window, cx));
+        cx.assert_editor_state("Title\nSome bold text.ˇ");
+
+        cx.set_state("ˇ");
+        cx.update_editor(|editor, window, cx| {
+            editor.paste_as_markdown(&PasteAsMarkdown, window, cx)
+        });
+        cx.assert_editor_state("# Title\n\nSome **bold** text.ˇ");
+    }
+
+    #[gpui::test]
+    async fn test_copy_with_context(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        let language = Language::new(
+            LanguageConfig {
+                name: "Rust".into(),
+                line_comments: vec!["// ".into()],
+                ..Default::default()
+            },
+            Some(tree_sitter_rust::LANGUAGE.into()),
+        )
+        .with_outline_query(
+            r#"
+            (impl_item "impl" @context type: (_) @name) @item
+            (function_item "fn" @context name: (_) @name) @item
+            "#,
+        )
+        .unwrap();
+        cx.update_buffer(|buffer, cx| buffer.set_language(Some(Arc::new(language)), cx));
+
+        cx.set_state(indoc! {"
+            impl Foo {
+                fn bar() {
+                    «let a = 1;
+                    let b = 2;ˇ»
+                }
+            }
+        "});
+        cx.run_until_parked();
+        cx.update_editor(|editor, window, cx| {
+            editor.copy_with_context(&CopyWithContext, window, cx)
+        });
+        assert_eq!(
+            cx.read_from_clipboard()
+                .and_then(|item| item.text())
+                .unwrap(),
+            "// file:3-4 (impl Foo > fn bar)\nlet a = 1;\n        let b = 2;"
+        );
+
+        // Without a selection, the line is copied.
+        cx.set_state(indoc! {"
+            impl Foo {
+                fn barˇ() {}
+            }
+        "});
+        cx.run_until_parked();
+        cx.update_editor(|editor, window, cx| {
+            editor.copy_with_context(&CopyWithContext, window, cx)
+        });
+        assert_eq!(
+            cx.read_from_clipboard()
+                .and_then(|item| item.text())
+                .unwrap(),
+            "// file:2 (impl Foo > fn bar)\n    fn bar() {}"
+        );
+    }
+}
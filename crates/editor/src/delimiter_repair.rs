@@ -0,0 +1,106 @@
+//! Repairs of the commas separating list items, in the languages with the `repair_delimiters`
+//! setting enabled.
+//!
+//! Text pasted into a comma separated list gets a comma between it and the items around it that
+//! it isn't separated from, and loses the trailing comma it ends with when it became the last item
+//! of a list that didn't end with one. Joined lines of a list get a comma between their items,
+//! like smart joins do. Lists are the brackets of the syntax tree that contain commas outside of
+//! strings and comments. The repairs are made in the transaction of the paste or join, so that
+//! they're undone along with it.
+
+use crate::{joins_list_items, top_level_delimiter_offsets, Editor};
+use gpui::{App, Context};
+use multi_buffer::{Anchor, MultiBufferSnapshot, ToOffset as _, ToPoint as _};
+use std::ops::Range;
+
+impl Editor {
+    /// Repairs the delimiters around each of the ranges of pasted text.
+    pub(crate) fn repair_pasted_delimiters(
+        &mut self,
+        pasted_ranges: &[Range<Anchor>],
+        cx: &mut Context<Self>,
+    ) {
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let mut edits = pasted_ranges
+            .iter()
+            .flat_map(|range| {
+                let range = range.start.to_offset(&snapshot)..range.end.to_offset(&snapshot);
+                pasted_delimiter_repairs(&snapshot, range, cx)
+            })
+            .collect::<Vec<_>>();
+        if edits.is_empty() {
+            return;
+        }
+
+        // Adjacent pastes may add the same comma between them.
+        edits.sort_by_key(|(range, _)| range.start);
+        edits.dedup();
+        self.buffer
+            .update(cx, |buffer, cx| buffer.edit(edits, None, cx));
+    }
+}
+
+fn pasted_delimiter_repairs(
+    snapshot: &MultiBufferSnapshot,
+    range: Range<usize>,
+    cx: &App,
+) -> Vec<(Range<usize>, &'static str)> {
+    if !snapshot.settings_at(range.start, cx).repair_delimiters {
+        return Vec::new();
+    }
+    let pasted = snapshot.text_for_range(range.clone()).collect::<String>();
+    let content = pasted.trim();
+    if content.is_empty() {
+        return Vec::new();
+    }
+    let Some((open, close)) = snapshot.innermost_enclosing_bracket_ranges(range.clone(), None)
+    else {
+        return Vec::new();
+    };
+
+    let content_start = range.start + pasted.len() - pasted.trim_start().len();
+    let content_end = range.end - (pasted.len() - pasted.trim_end().len());
+    let whitespace_len = |c: char| c.is_whitespace().then(|| c.len_utf8());
+    let previous_end = content_start
+        - snapshot
+            .reversed_chars_at(content_start)
+            .map_while(whitespace_len)
+            .sum::<usize>();
+    let next_start = content_end
+        + snapshot
+            .chars_at(content_end)
+            .map_while(whitespace_len)
+            .sum::<usize>();
+
+    let mut edits = Vec::new();
+    if joins_list_items(
+        snapshot,
+        previous_end.to_point(snapshot),
+        content_start.to_point(snapshot),
+    ) {
+        let comma = if previous_end == content_start {
+            ", "
+        } else {
+            ","
+        };
+        edits.push((previous_end..previous_end, comma));
+    }
+
+    let trailing_comma = content.ends_with(',')
+        && top_level_delimiter_offsets(snapshot, open.end..close.start, ",")
+            .contains(&(content_end - 1));
+    if trailing_comma {
+        let list_had_trailing_comma = snapshot.reversed_chars_at(previous_end).next() == Some(',');
+        if next_start == close.start && !list_had_trailing_comma {
+            edits.push((content_end - 1..content_end, ""));
+        }
+    } else if joins_list_items(
+        snapshot,
+        content_end.to_point(snapshot),
+        next_start.to_point(snapshot),
+    ) {
+        let comma = if next_start == content_end { ", " } else { "," };
+        edits.push((content_end..content_end, comma));
+    }
+    edits
+}
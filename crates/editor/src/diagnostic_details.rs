@@ -0,0 +1,464 @@
+//! Diagnostics expanded into the whole tree of their messages.
+//!
+//! When a language server reports related information with a diagnostic, like the other locations
+//! involved in an error or notes about it, the diagnostic is grouped with one supporting
+//! diagnostic per related location in its file, and keeps the related locations in other files.
+//! `editor::ToggleDiagnosticDetails`, or the button of the diagnostic's hover popover, expands
+//! the group into a block below the line of its primary diagnostic: the whole primary message,
+//! followed by the messages of the related locations, which move the cursor to their location
+//! when clicked, opening their file if it's another one.
+//!
+//! The block is only inserted while the group is expanded, so that the layout of the buffer
+//! doesn't change otherwise. It's collapsed by the same command, by escape, or once the language
+//! server stops reporting the diagnostic.
+
+use crate::{
+    actions::ToggleDiagnosticDetails,
+    diagnostic_style,
+    display_map::{
+        BlockContext, BlockPlacement, BlockProperties, BlockStyle, CustomBlockId, RenderBlock,
+    },
+    highlight_diagnostic_message,
+    scroll::Autoscroll,
+    Editor,
+};
+use anyhow::Context as _;
+use collections::HashSet;
+use gpui::{
+    Context, FontWeight, HighlightStyle, LineWrapperHandle, Pixels, StyledText, TextStyle,
+    WeakEntity, Window,
+};
+use language::{Diagnostic, DiagnosticSeverity, PointUtf16, Unclipped};
+use multi_buffer::{Anchor, MultiBufferSnapshot, ToOffset as _};
+use settings::Settings as _;
+use std::{ops::Range, path::PathBuf, sync::Arc};
+use text::{Bias, BufferId};
+use theme::ThemeSettings;
+use ui::prelude::*;
+
+/// The diagnostic group expanded in an editor.
+pub(crate) struct ExpandedDiagnostic {
+    buffer_id: BufferId,
+    group_id: usize,
+    primary_range: Range<Anchor>,
+    primary_message: String,
+    block_id: CustomBlockId,
+}
+
+/// The message of an expanded group at one of the related locations.
+struct RelatedDiagnostic {
+    location: RelatedLocation,
+    /// The location, as shown before the message.
+    label: String,
+    diagnostic: Diagnostic,
+}
+
+enum RelatedLocation {
+    /// A supporting diagnostic of the group, in the buffer of its primary diagnostic.
+    Buffer(Anchor),
+    /// Related information of the primary diagnostic, in another file.
+    File {
+        abs_path: PathBuf,
+        point: PointUtf16,
+    },
+}
+
+impl Editor {
+    pub fn toggle_diagnostic_details(
+        &mut self,
+        _: &ToggleDiagnosticDetails,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.collapse_diagnostic_details(cx) {
+            return;
+        }
+
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let head = self.selections.newest::<usize>(cx).head();
+        let Some(entry) = snapshot
+            .diagnostics_in_range::<usize>(head..head)
+            .min_by_key(|entry| entry.range.len())
+        else {
+            return;
+        };
+        let Some(buffer_id) = snapshot.anchor_after(entry.range.start).buffer_id else {
+            return;
+        };
+        self.expand_diagnostic_details(buffer_id, entry.diagnostic.group_id, cx);
+    }
+
+    /// Expands a diagnostic group into a block below the line of its primary diagnostic,
+    /// collapsing the group expanded before.
+    pub(crate) fn expand_diagnostic_details(
+        &mut self,
+        buffer_id: BufferId,
+        group_id: usize,
+        cx: &mut Context<Self>,
+    ) {
+        self.collapse_diagnostic_details(cx);
+
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let mut primary = None;
+        let mut related = Vec::new();
+        for entry in snapshot.diagnostic_group(buffer_id, group_id) {
+            if entry.diagnostic.is_primary && primary.is_none() {
+                primary = Some(entry);
+            } else {
+                let Some((_, point, _)) = snapshot.point_to_buffer_point(entry.range.start) else {
+                    continue;
+                };
+                related.push(RelatedDiagnostic {
+                    location: RelatedLocation::Buffer(snapshot.anchor_before(entry.range.start)),
+                    label: format!("{}:{}", point.row + 1, point.column + 1),
+                    diagnostic: entry.diagnostic,
+                });
+            }
+        }
+        let Some(primary) = primary else {
+            return;
+        };
+        for info in &primary.diagnostic.related_information {
+            let Ok(abs_path) = info.location.uri.to_file_path() else {
+                continue;
+            };
+            let path = self
+                .project
+                .as_ref()
+                .and_then(|project| project.read(cx).find_project_path(&abs_path, cx))
+                .map_or_else(
+                    || abs_path.to_string_lossy().into_owned(),
+                    |project_path| project_path.path.to_string_lossy().into_owned(),
+                );
+            let start = info.location.range.start;
+            related.push(RelatedDiagnostic {
+                location: RelatedLocation::File {
+                    abs_path,
+                    point: PointUtf16::new(start.line, start.character),
+                },
+                label: format!("{path}:{}:{}", start.line + 1, start.character + 1),
+                diagnostic: Diagnostic {
+                    severity: DiagnosticSeverity::INFORMATION,
+                    message: info.message.trim().to_string(),
+                    ..Default::default()
+                },
+            });
+        }
+
+        // The block is resized to the height of its contents once it's laid out, but wrapping
+        // its messages like the text of the editor gets that height right from the start.
+        let mut line_wrapper = self.display_map.read(cx).line_wrapper(cx);
+        let indent = primary.range.start.column as usize;
+        let height = message_rows(&primary.diagnostic, indent, &mut line_wrapper)
+            + related
+                .iter()
+                .map(|related| {
+                    let indent = indent + related.label.chars().count() + 3;
+                    message_rows(&related.diagnostic, indent, &mut line_wrapper)
+                })
+                .sum::<u32>();
+        let block = BlockProperties {
+            placement: BlockPlacement::Below(snapshot.anchor_after(primary.range.start)),
+            height,
+            style: BlockStyle::Fixed,
+            render: render_diagnostic_details(
+                primary.diagnostic.clone(),
+                related,
+                cx.entity().downgrade(),
+            ),
+            priority: 0,
+        };
+        let block_id = self.insert_blocks([block], None, cx)[0];
+        self.expanded_diagnostic = Some(ExpandedDiagnostic {
+            buffer_id,
+            group_id,
+            primary_range: snapshot.anchor_before(primary.range.start)
+                ..snapshot.anchor_after(primary.range.end),
+            primary_message: primary.diagnostic.message,
+            block_id,
+        });
+        cx.notify();
+    }
+
+    /// Collapses the expanded diagnostic group, returning whether there was one.
+    pub(crate) fn collapse_diagnostic_details(&mut self, cx: &mut Context<Self>) -> bool {
+        let Some(expanded) = self.expanded_diagnostic.take() else {
+            return false;
+        };
+        self.remove_blocks(HashSet::from_iter([expanded.block_id]), None, cx);
+        true
+    }
+
+    /// Expands the expanded diagnostic again when diagnostics are updated, as their groups are
+    /// numbered anew, or collapses it once it's no longer reported.
+    pub(crate) fn refresh_diagnostic_details(&mut self, cx: &mut Context<Self>) {
+        let Some(expanded) = self.expanded_diagnostic.as_ref() else {
+            return;
+        };
+        let buffer_id = expanded.buffer_id;
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        match reported_group(expanded, &snapshot) {
+            Some(group_id) if group_id == expanded.group_id => {}
+            Some(group_id) => self.expand_diagnostic_details(buffer_id, group_id, cx),
+            None => {
+                self.collapse_diagnostic_details(cx);
+            }
+        }
+    }
+}
+
+/// The group of the expanded diagnostic's primary diagnostic, if it's still reported.
+fn reported_group(expanded: &ExpandedDiagnostic, snapshot: &MultiBufferSnapshot) -> Option<usize> {
+    let start = expanded.primary_range.start.to_offset(snapshot);
+    let end = expanded.primary_range.end.to_offset(snapshot);
+    snapshot
+        .diagnostics_in_range::<usize>(start..end)
+        .find(|entry| {
+            entry.diagnostic.is_primary
+                && entry.range.start == start
+                && entry.diagnostic.message == expanded.primary_message
+        })
+        .map(|entry| entry.diagnostic.group_id)
+}
+
+/// The number of rows the message of the diagnostic takes, starting after the indent, once its
+/// lines are wrapped like the text of the editor, if it's wrapped.
+fn message_rows(
+    diagnostic: &Diagnostic,
+    indent: usize,
+    line_wrapper: &mut Option<(LineWrapperHandle, Pixels)>,
+) -> u32 {
+    let (message, _) = highlight_diagnostic_message(diagnostic, None);
+    message
+        .split('\n')
+        .map(|line| match line_wrapper {
+            Some((line_wrapper, wrap_width)) => {
+                let line = format!("{:indent$}{line}", "");
+                line_wrapper.wrap_line(&line, *wrap_width).count() as u32 + 1
+            }
+            None => 1,
+        })
+        .sum()
+}
+
+impl Editor {
+    fn go_to_related_location(
+        &mut self,
+        location: &RelatedLocation,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        match location {
+            RelatedLocation::Buffer(position) => {
+                let position = *position;
+                self.change_selections(Some(Autoscroll::fit()), window, cx, |selections| {
+                    selections.select_anchor_ranges([position..position])
+                });
+            }
+            RelatedLocation::File { abs_path, point } => {
+                let Some(workspace) = self.workspace() else {
+                    return;
+                };
+                let open = workspace.update(cx, |workspace, cx| {
+                    workspace.open_abs_path(abs_path.clone(), true, window, cx)
+                });
+                let point = *point;
+                cx.spawn_in(window, |_, mut cx| async move {
+                    let editor = open
+                        .await?
+                        .downcast::<Editor>()
+                        .context("related location isn't opened in an editor")?;
+                    editor.update_in(&mut cx, |editor, window, cx| {
+                        let snapshot = editor.buffer.read(cx).snapshot(cx);
+                        let point = snapshot.clip_point_utf16(Unclipped(point), Bias::Left);
+                        let offset = snapshot.point_utf16_to_offset(point);
+                        editor.change_selections(
+                            Some(Autoscroll::center()),
+                            window,
+                            cx,
+                            |selections| selections.select_ranges([offset..offset]),
+                        );
+                    })
+                })
+                .detach_and_log_err(cx);
+            }
+        }
+    }
+}
+
+fn render_diagnostic_details(
+    primary: Diagnostic,
+    related: Vec<RelatedDiagnostic>,
+    editor: WeakEntity<Editor>,
+) -> RenderBlock {
+    let related = Arc::<[RelatedDiagnostic]>::from(related);
+    Arc::new(move |cx: &mut BlockContext| {
+        let mut text_style = cx.window.text_style().clone();
+        let theme_settings = ThemeSettings::get_global(cx);
+        text_style.font_family = theme_settings.buffer_font.family.clone();
+        text_style.font_style = theme_settings.buffer_font.style;
+        text_style.font_features = theme_settings.buffer_font.features.clone();
+        text_style.font_weight = theme_settings.buffer_font.weight;
+        let status_colors = cx.theme().status();
+        let muted_color = cx.theme().colors().text_muted;
+        let hover_background = cx.theme().colors().element_hover;
+
+        let message = |diagnostic: &Diagnostic| {
+            let mut text_style = text_style.clone();
+            text_style.color = diagnostic_style(diagnostic.severity, status_colors);
+            styled_message(diagnostic, &text_style)
+        };
+
+        let related_count = related.len();
+        v_flex()
+            .id(cx.block_id)
+            .block_mouse_down()
+            .pl(cx.anchor_x)
+            .w(cx.max_width)
+            .child(message(&primary))
+            .children(related.iter().enumerate().map(|(ix, entry)| {
+                let branch = if ix + 1 == related_count {
+                    "└"
+                } else {
+                    "├"
+                };
+                let location = format!("{branch} {} ", entry.label);
+                let related = related.clone();
+                let editor = editor.clone();
+                h_flex()
+                    .id(("related-diagnostic", ix))
+                    .items_start()
+                    .cursor_pointer()
+                    .hover(move |style| style.bg(hover_background))
+                    .child(div().flex_none().text_color(muted_color).child(location))
+                    .child(div().flex_1().min_w_0().child(message(&entry.diagnostic)))
+                    .on_click(move |_, window, cx| {
+                        editor
+                            .update(cx, |editor, cx| {
+                                editor.go_to_related_location(&related[ix].location, window, cx)
+                            })
+                            .ok();
+                    })
+            }))
+            .into_any_element()
+    })
+}
+
+fn styled_message(diagnostic: &Diagnostic, text_style: &TextStyle) -> StyledText {
+    let (text, code_ranges) = highlight_diagnostic_message(diagnostic, None);
+    StyledText::new(text).with_highlights(
+        text_style,
+        code_ranges.into_iter().map(|range| {
+            (
+                range,
+                HighlightStyle {
+                    font_weight: Some(FontWeight::BOLD),
+                    ..Default::default()
+                },
+            )
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        actions::ToggleDiagnosticDetails, editor_tests::init_test,
+        test::editor_test_context::EditorTestContext,
+    };
+    use gpui::{px, TestAppContext};
+    use indoc::indoc;
+    use lsp::LanguageServerId;
+    use util::path;
+
+    #[gpui::test]
+    async fn test_toggle_diagnostic_details(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state(indoc! {"
+            fn func(abˇc: i32) {
+                abc;
+            }
+        "});
+        let lsp_store =
+            cx.update_editor(|editor, _, cx| editor.project.as_ref().unwrap().read(cx).lsp_store());
+        let uri = lsp::Url::from_file_path(path!("/root/file")).unwrap();
+        let publish = |diagnostics: Vec<lsp::Diagnostic>, cx: &mut EditorTestContext| {
+            cx.update(|_, cx| {
+                lsp_store.update(cx, |lsp_store, cx| {
+                    lsp_store.update_diagnostics(
+                        LanguageServerId(0),
+                        lsp::PublishDiagnosticsParams {
+                            uri: uri.clone(),
+                            version: None,
+                            diagnostics,
+                        },
+                        &[],
+                        cx,
+                    )
+                })
+            })
+            .unwrap();
+            cx.run_until_parked();
+        };
+        let diagnostic = lsp::Diagnostic {
+            range: lsp::Range::new(lsp::Position::new(0, 8), lsp::Position::new(0, 11)),
+            severity: Some(lsp::DiagnosticSeverity::ERROR),
+            message: "unused parameter".to_string(),
+            related_information: Some(vec![
+                lsp::DiagnosticRelatedInformation {
+                    location: lsp::Location {
+                        uri: uri.clone(),
+                        range: lsp::Range::new(lsp::Position::new(1, 4), lsp::Position::new(1, 7)),
+                    },
+                    message: "shadowed here".to_string(),
+                },
+                lsp::DiagnosticRelatedInformation {
+                    location: lsp::Location {
+                        uri: lsp::Url::from_file_path(path!("/root/other.rs")).unwrap(),
+                        range: lsp::Range::new(lsp::Position::new(3, 0), lsp::Position::new(3, 3)),
+                    },
+                    message: "defined here".to_string(),
+                },
+            ]),
+            ..Default::default()
+        };
+        publish(vec![diagnostic.clone()], &mut cx);
+
+        let max_row = |cx: &mut EditorTestContext| {
+            cx.update_editor(|editor, window, cx| editor.snapshot(window, cx).max_point().row().0)
+        };
+        let collapsed_max_row = max_row(&mut cx);
+
+        // The block shows the primary message and the related ones, in this file and another one.
+        cx.update_editor(|editor, window, cx| {
+            editor.toggle_diagnostic_details(&ToggleDiagnosticDetails, window, cx)
+        });
+        assert_eq!(max_row(&mut cx), collapsed_max_row + 3);
+        cx.update_editor(|editor, window, cx| {
+            editor.toggle_diagnostic_details(&ToggleDiagnosticDetails, window, cx)
+        });
+        assert_eq!(max_row(&mut cx), collapsed_max_row);
+
+        // The group stays expanded while it's reported again, and collapses once it isn't.
+        cx.update_editor(|editor, window, cx| {
+            editor.toggle_diagnostic_details(&ToggleDiagnosticDetails, window, cx)
+        });
+        publish(vec![diagnostic.clone()], &mut cx);
+        assert_eq!(max_row(&mut cx), collapsed_max_row + 3);
+        publish(Vec::new(), &mut cx);
+        assert_eq!(max_row(&mut cx), collapsed_max_row);
+        cx.update_editor(|editor, _, _| assert!(editor.expanded_diagnostic.is_none()));
+
+        // The messages take more rows when they're soft wrapped.
+        publish(vec![diagnostic], &mut cx);
+        cx.update_editor(|editor, _, cx| {
+            editor.set_wrap_width(Some(px(140.)), cx);
+        });
+        let collapsed_max_row = max_row(&mut cx);
+        cx.update_editor(|editor, window, cx| {
+            editor.toggle_diagnostic_details(&ToggleDiagnosticDetails, window, cx)
+        });
+        assert!(max_row(&mut cx) > collapsed_max_row + 3);
+    }
+}
@@ -27,7 +27,8 @@ mod tab_map;
 mod wrap_map;
 
 use crate::{
-    hover_links::InlayHighlight, movement::TextLayoutDetails, EditorStyle, InlayId, RowExt,
+    editor_settings::DiagnosticSeverityFilter, hover_links::InlayHighlight,
+    movement::TextLayoutDetails, EditorStyle, InlayId, RowExt,
 };
 pub use block_map::{
     Block, BlockChunks as DisplayChunks, BlockContext, BlockId, BlockMap, BlockPlacement,
@@ -37,15 +38,19 @@ pub use block_map::{
 use block_map::{BlockRow, BlockSnapshot};
 use collections::{HashMap, HashSet};
 pub use crease_map::*;
+pub(crate) use custom_highlights::PrecomputedSyntaxHighlights;
 pub use fold_map::{Fold, FoldId, FoldPlaceholder, FoldPoint};
 use fold_map::{FoldMap, FoldSnapshot};
-use gpui::{App, Context, Entity, Font, HighlightStyle, LineLayout, Pixels, UnderlineStyle};
+use gpui::{
+    App, Context, Entity, Font, HighlightStyle, LineLayout, LineWrapperHandle, Pixels,
+    Subscription, UnderlineStyle,
+};
 pub use inlay_map::Inlay;
 use inlay_map::{InlayMap, InlaySnapshot};
 pub use inlay_map::{InlayOffset, InlayPoint};
 use invisibles::{is_invisible, replacement};
 use language::{
-    language_settings::language_settings, ChunkRenderer, OffsetUtf16, Point,
+    language_settings::language_settings, CharClassifier, ChunkRenderer, OffsetUtf16, Point,
     Subscription as BufferSubscription,
 };
 use lsp::DiagnosticSeverity;
@@ -54,6 +59,7 @@ use multi_buffer::{
     RowInfo, ToOffset, ToPoint,
 };
 use serde::Deserialize;
+use settings::SettingsStore;
 use std::{
     any::TypeId,
     borrow::Cow,
@@ -68,6 +74,7 @@ use tab_map::{TabMap, TabSnapshot};
 use text::{BufferId, LineIndent};
 use ui::{px, SharedString};
 use unicode_segmentation::UnicodeSegmentation;
+pub use wrap_map::WrapMapEvent;
 use wrap_map::{WrapMap, WrapSnapshot};
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -110,6 +117,12 @@ pub struct DisplayMap {
     pub(crate) fold_placeholder: FoldPlaceholder,
     pub clip_at_line_ends: bool,
     pub(crate) masked: bool,
+    /// The least severe diagnostics that are underlined.
+    pub(crate) diagnostics_max_severity: DiagnosticSeverityFilter,
+    /// The `word_characters` setting of the buffer, updated when the settings or the buffer's
+    /// language or file change.
+    word_characters: Option<Arc<[char]>>,
+    _subscriptions: [Subscription; 2],
 }
 
 impl DisplayMap {
@@ -145,6 +158,20 @@ impl DisplayMap {
 
         cx.observe(&wrap_map, |_, _, cx| cx.notify()).detach();
 
+        let word_characters = Self::word_characters(&buffer, cx);
+        let _subscriptions = [
+            cx.observe_global::<SettingsStore>(|this, cx| {
+                this.word_characters = Self::word_characters(&this.buffer, cx);
+            }),
+            cx.subscribe(&buffer, |this, _, event, cx| {
+                if let multi_buffer::Event::LanguageChanged(_)
+                | multi_buffer::Event::FileHandleChanged = event
+                {
+                    this.word_characters = Self::word_characters(&this.buffer, cx);
+                }
+            }),
+        ];
+
         DisplayMap {
             buffer,
             buffer_subscription,
@@ -159,6 +186,9 @@ impl DisplayMap {
             inlay_highlights: Default::default(),
             clip_at_line_ends: false,
             masked: false,
+            diagnostics_max_severity: DiagnosticSeverityFilter::default(),
+            word_characters,
+            _subscriptions,
         }
     }
 
@@ -186,7 +216,9 @@ impl DisplayMap {
             inlay_highlights: self.inlay_highlights.clone(),
             clip_at_line_ends: self.clip_at_line_ends,
             masked: self.masked,
+            diagnostics_max_severity: self.diagnostics_max_severity,
             fold_placeholder: self.fold_placeholder.clone(),
+            word_characters: self.word_characters.clone(),
         }
     }
 
@@ -502,6 +534,73 @@ impl DisplayMap {
             .update(cx, |map, cx| map.set_wrap_width(width, cx))
     }
 
+    /// A wrapper of lines in the font of the text, along with the width the text is wrapped at,
+    /// unless it isn't wrapped.
+    pub fn line_wrapper(&self, cx: &App) -> Option<(LineWrapperHandle, Pixels)> {
+        self.wrap_map.read(cx).line_wrapper(cx)
+    }
+
+    /// Pauses soft wrapping in the background while the text isn't displayed. Returns whether
+    /// it wasn't already.
+    pub fn set_wrap_paused(&self, paused: bool, cx: &mut Context<Self>) -> bool {
+        self.wrap_map
+            .update(cx, |map, cx| map.set_paused(paused, cx))
+    }
+
+    pub fn set_scale_factor(&self, scale_factor: f32, cx: &mut Context<Self>) -> bool {
+        self.wrap_map
+            .update(cx, |map, cx| map.set_scale_factor(scale_factor, cx))
+    }
+
+    /// Hints which display rows are on screen, so that rewrapping after large
+    /// edits lays out those rows first.
+    pub fn set_visible_rows(
+        &self,
+        rows: Range<DisplayRow>,
+        snapshot: &DisplaySnapshot,
+        cx: &mut Context<Self>,
+    ) {
+        let start = snapshot
+            .block_snapshot
+            .to_wrap_point(BlockPoint::new(rows.start.0, 0), Bias::Left);
+        let end = snapshot
+            .block_snapshot
+            .to_wrap_point(BlockPoint::new(rows.end.0, 0), Bias::Right);
+        self.wrap_map.update(cx, |map, _| {
+            map.set_visible_rows(start.row()..end.row() + 1)
+        });
+    }
+
+    /// Sets the ranges whose lines overflow horizontally instead of being soft-wrapped.
+    pub fn set_no_wrap_ranges(
+        &mut self,
+        ranges: Vec<Range<Anchor>>,
+        cx: &mut Context<Self>,
+    ) -> bool {
+        let buffer_snapshot = self.buffer.read(cx).snapshot(cx);
+        let edits = self.buffer_subscription.consume().into_inner();
+        let tab_size = Self::tab_size(&self.buffer, cx);
+        let (snapshot, edits) = self.inlay_map.sync(buffer_snapshot, edits);
+        let (snapshot, edits) = self.fold_map.read(snapshot, edits);
+        let (snapshot, edits) = self.tab_map.sync(snapshot, edits, tab_size);
+        let (snapshot, edits) = self
+            .wrap_map
+            .update(cx, |map, cx| map.sync(snapshot, edits, cx));
+        self.block_map.read(snapshot, edits);
+        self.wrap_map
+            .update(cx, |map, cx| map.set_no_wrap_ranges(ranges.into(), cx))
+    }
+
+    /// The wrap map, whose [`WrapMapEvent`]s report problems loading the font.
+    pub(crate) fn wrap_map(&self) -> &Entity<WrapMap> {
+        &self.wrap_map
+    }
+
+    pub fn set_line_wrap_budget(&self, budget: u32, cx: &mut Context<Self>) -> bool {
+        self.wrap_map
+            .update(cx, |map, cx| map.set_line_wrap_budget(budget, cx))
+    }
+
     pub(crate) fn current_inlays(&self) -> impl Iterator<Item = &Inlay> {
         self.inlay_map.current_inlays()
     }
@@ -541,7 +640,20 @@ impl DisplayMap {
             .and_then(|buffer| buffer.language())
             .map(|l| l.name());
         let file = buffer.and_then(|buffer| buffer.file());
-        language_settings(language, file, cx).tab_size
+        let modeline_tab_size = buffer
+            .and_then(|buffer| buffer.modeline())
+            .and_then(|modeline| modeline.tab_size);
+        modeline_tab_size.unwrap_or_else(|| language_settings(language, file, cx).tab_size)
+    }
+
+    fn word_characters(buffer: &Entity<MultiBuffer>, cx: &App) -> Option<Arc<[char]>> {
+        let buffer = buffer.read(cx).as_singleton().map(|buffer| buffer.read(cx));
+        let language = buffer
+            .and_then(|buffer| buffer.language())
+            .map(|l| l.name());
+        let file = buffer.and_then(|buffer| buffer.file());
+        let word_characters = &language_settings(language, file, cx).word_characters;
+        (!word_characters.is_empty()).then(|| Arc::from(word_characters.as_slice()))
     }
 
     #[cfg(test)]
@@ -558,6 +670,7 @@ impl DisplayMap {
 pub(crate) struct Highlights<'a> {
     pub text_highlights: Option<&'a TextHighlights>,
     pub inlay_highlights: Option<&'a InlayHighlights>,
+    pub syntax_highlights: Option<&'a PrecomputedSyntaxHighlights>,
     pub styles: HighlightStyles,
 }
 
@@ -694,10 +807,20 @@ pub struct DisplaySnapshot {
     inlay_highlights: InlayHighlights,
     clip_at_line_ends: bool,
     masked: bool,
+    diagnostics_max_severity: DiagnosticSeverityFilter,
     pub(crate) fold_placeholder: FoldPlaceholder,
+    word_characters: Option<Arc<[char]>>,
 }
 
 impl DisplaySnapshot {
+    /// Returns the classifier used by word motions and selections at the given
+    /// position, which includes the `word_characters` configured in settings.
+    pub fn char_classifier_at<T: ToOffset>(&self, position: T) -> CharClassifier {
+        self.buffer_snapshot
+            .char_classifier_at(position)
+            .with_word_characters(self.word_characters.clone())
+    }
+
     #[cfg(test)]
     pub fn fold_count(&self) -> usize {
         self.fold_snapshot.fold_count()
@@ -867,6 +990,18 @@ impl DisplaySnapshot {
         language_aware: bool,
         highlight_styles: HighlightStyles,
     ) -> DisplayChunks<'_> {
+        self.chunks_with_syntax_highlights(display_rows, language_aware, None, highlight_styles)
+    }
+
+    /// Returns the chunks of the rows, taking their syntax highlights from
+    /// `syntax_highlights` where they were precomputed and are current.
+    pub(crate) fn chunks_with_syntax_highlights<'a>(
+        &'a self,
+        display_rows: Range<DisplayRow>,
+        language_aware: bool,
+        syntax_highlights: Option<&'a PrecomputedSyntaxHighlights>,
+        highlight_styles: HighlightStyles,
+    ) -> DisplayChunks<'a> {
         self.block_snapshot.chunks(
             display_rows.start.0..display_rows.end.0,
             language_aware,
@@ -874,6 +1009,7 @@ impl DisplaySnapshot {
             Highlights {
                 text_highlights: Some(&self.text_highlights),
                 inlay_highlights: Some(&self.inlay_highlights),
+                syntax_highlights,
                 styles: highlight_styles,
             },
         )
@@ -885,9 +1021,25 @@ impl DisplaySnapshot {
         language_aware: bool,
         editor_style: &'a EditorStyle,
     ) -> impl Iterator<Item = HighlightedChunk<'a>> {
-        self.chunks(
+        self.highlighted_chunks_with_syntax_highlights(
             display_rows,
             language_aware,
+            None,
+            editor_style,
+        )
+    }
+
+    pub(crate) fn highlighted_chunks_with_syntax_highlights<'a>(
+        &'a self,
+        display_rows: Range<DisplayRow>,
+        language_aware: bool,
+        syntax_highlights: Option<&'a PrecomputedSyntaxHighlights>,
+        editor_style: &'a EditorStyle,
+    ) -> impl Iterator<Item = HighlightedChunk<'a>> {
+        self.chunks_with_syntax_highlights(
+            display_rows,
+            language_aware,
+            syntax_highlights,
             HighlightStyles {
                 inlay_hint: Some(editor_style.inlay_hints_style),
                 inline_completion: Some(editor_style.inline_completion_styles),
@@ -912,7 +1064,10 @@ impl DisplaySnapshot {
                 diagnostic_highlight.fade_out = Some(editor_style.unnecessary_code_fade);
             }
 
-            if let Some(severity) = chunk.diagnostic_severity {
+            if let Some(severity) = chunk
+                .diagnostic_severity
+                .filter(|severity| self.diagnostics_max_severity.includes(*severity))
+            {
                 // Omit underlines for HINT/INFO diagnostics on 'unnecessary' code.
                 if severity <= DiagnosticSeverity::WARNING || !chunk.is_unnecessary {
                     let diagnostic_color = super::diagnostic_style(severity, &editor_style.status);
@@ -1124,6 +1279,16 @@ impl DisplaySnapshot {
         self.wrap_snapshot.soft_wrap_indent(wrap_row)
     }
 
+    /// Returns whether the soft wrap ending `display_row` was placed without
+    /// measuring the text, because its line was too long to wrap precisely.
+    pub fn is_soft_wrap_approximate(&self, display_row: DisplayRow) -> bool {
+        let wrap_row = self
+            .block_snapshot
+            .to_wrap_point(BlockPoint::new(display_row.0, 0), Bias::Left)
+            .row();
+        self.wrap_snapshot.is_soft_wrap_approximate(wrap_row)
+    }
+
     pub fn text(&self) -> String {
         self.text_chunks(DisplayRow(0)).collect()
     }
@@ -1955,7 +2120,7 @@ pub mod tests {
 
         let map = cx.new(|cx| {
             DisplayMap::new(
-                buffer,
+                buffer.clone(),
                 font("Helvetica"),
                 font_size,
                 None,
@@ -1987,6 +2152,58 @@ pub mod tests {
             ]
         );
 
+        // Precomputed syntax highlights are used in place of the syntax trees, until the
+        // buffer changes, and are moved along with the text once interpolated.
+        let highlights = cx.update(|cx| {
+            let snapshot = map.update(cx, |map, cx| map.snapshot(cx));
+            let buffer_snapshot = &snapshot.buffer_snapshot;
+            PrecomputedSyntaxHighlights::compute(buffer_snapshot, 0..buffer_snapshot.len())
+        });
+        assert_eq!(
+            cx.update(|cx| precomputed_syntax_chunks(
+                DisplayRow(3)..DisplayRow(5),
+                &map,
+                &highlights,
+                &theme,
+                cx
+            )),
+            vec![
+                ("    fn ".to_string(), Some(Hsla::red())),
+                ("inner".to_string(), Some(Hsla::blue())),
+                ("() {}\n}".to_string(), Some(Hsla::red())),
+            ]
+        );
+        buffer.update(cx, |buffer, cx| buffer.edit([(0..0, "\n")], None, cx));
+        assert!(cx
+            .update(|cx| precomputed_syntax_chunks(
+                DisplayRow(3)..DisplayRow(5),
+                &map,
+                &highlights,
+                &theme,
+                cx
+            ))
+            .iter()
+            .all(|(_, color)| color.is_none()));
+        let highlights = cx.update(|cx| {
+            let snapshot = map.update(cx, |map, cx| map.snapshot(cx));
+            highlights.interpolate(&snapshot.buffer_snapshot)
+        });
+        assert_eq!(
+            cx.update(|cx| precomputed_syntax_chunks(
+                DisplayRow(4)..DisplayRow(6),
+                &map,
+                &highlights,
+                &theme,
+                cx
+            )),
+            vec![
+                ("    fn ".to_string(), Some(Hsla::red())),
+                ("inner".to_string(), Some(Hsla::blue())),
+                ("() {}\n}".to_string(), Some(Hsla::red())),
+            ]
+        );
+        buffer.update(cx, |buffer, cx| buffer.edit([(0..1, "")], None, cx));
+
         map.update(cx, |map, cx| {
             map.fold(
                 vec![Crease::simple(
@@ -2622,6 +2839,37 @@ pub mod tests {
         });
     }
 
+    #[gpui::test]
+    fn test_word_characters_follow_settings(cx: &mut gpui::App) {
+        init_test(cx, |_| {});
+
+        let buffer = MultiBuffer::build_simple("foo-bar", cx);
+        let map = cx.new(|cx| {
+            DisplayMap::new(
+                buffer.clone(),
+                font("Helvetica"),
+                px(14.0),
+                None,
+                true,
+                1,
+                1,
+                0,
+                FoldPlaceholder::test(),
+                cx,
+            )
+        });
+        let snapshot = map.update(cx, |map, cx| map.snapshot(cx));
+        assert!(!snapshot.char_classifier_at(0).is_word('-'));
+
+        cx.update_global::<SettingsStore, _>(|store, cx| {
+            store.update_user_settings::<AllLanguageSettings>(cx, |settings| {
+                settings.defaults.word_characters = Some(vec!['-']);
+            });
+        });
+        let snapshot = map.update(cx, |map, cx| map.snapshot(cx));
+        assert!(snapshot.char_classifier_at(0).is_word('-'));
+    }
+
     #[gpui::test]
     fn test_tabs_with_multibyte_chars(cx: &mut gpui::App) {
         init_test(cx, |_| {});
@@ -2739,6 +2987,34 @@ pub mod tests {
             .collect()
     }
 
+    fn precomputed_syntax_chunks(
+        rows: Range<DisplayRow>,
+        map: &Entity<DisplayMap>,
+        highlights: &PrecomputedSyntaxHighlights,
+        theme: &SyntaxTheme,
+        cx: &mut App,
+    ) -> Vec<(String, Option<Hsla>)> {
+        let snapshot = map.update(cx, |map, cx| map.snapshot(cx));
+        let mut chunks: Vec<(String, Option<Hsla>)> = Vec::new();
+        for chunk in snapshot.chunks_with_syntax_highlights(
+            rows,
+            true,
+            Some(highlights),
+            HighlightStyles::default(),
+        ) {
+            let color = chunk
+                .syntax_highlight_id
+                .and_then(|id| id.style(theme)?.color);
+            match chunks.last_mut() {
+                Some((last_chunk, last_color)) if *last_color == color => {
+                    last_chunk.push_str(chunk.text)
+                }
+                _ => chunks.push((chunk.text.to_string(), color)),
+            }
+        }
+        chunks
+    }
+
     fn chunks(
         rows: Range<DisplayRow>,
         map: &Entity<DisplayMap>,
@@ -1,6 +1,6 @@
 use collections::BTreeMap;
 use gpui::HighlightStyle;
-use language::Chunk;
+use language::{Chunk, HighlightId};
 use multi_buffer::{Anchor, MultiBufferChunks, MultiBufferSnapshot, ToOffset as _};
 use std::{
     any::TypeId,
@@ -21,6 +21,107 @@ pub struct CustomHighlightsChunks<'a> {
     highlight_endpoints: Peekable<vec::IntoIter<HighlightEndpoint>>,
     active_highlights: BTreeMap<TypeId, HighlightStyle>,
     text_highlights: Option<&'a TreeMap<TypeId, Arc<(HighlightStyle, Vec<Range<Anchor>>)>>>,
+
+    /// The precomputed syntax highlights, if they're current.
+    syntax_highlights: &'a [(Range<usize>, HighlightId)],
+    /// The precomputed syntax highlights that don't end before the current offset.
+    remaining_syntax_highlights: &'a [(Range<usize>, HighlightId)],
+}
+
+/// Syntax highlights computed ahead of time for a range of a multibuffer, which are used in
+/// place of querying its syntax trees when highlighting that range.
+#[derive(Debug)]
+pub(crate) struct PrecomputedSyntaxHighlights {
+    edit_count: usize,
+    non_text_state_update_count: usize,
+    pub range: Range<usize>,
+    anchor_range: Range<Anchor>,
+    /// Sorted, disjoint ranges and their highlights.
+    highlights: Vec<(Range<usize>, HighlightId)>,
+    /// The anchors of the highlighted ranges, which they're moved along with after edits.
+    anchors: Vec<Range<Anchor>>,
+}
+
+impl PrecomputedSyntaxHighlights {
+    pub fn empty() -> Self {
+        Self {
+            edit_count: 0,
+            non_text_state_update_count: 0,
+            range: 0..0,
+            anchor_range: Anchor::min()..Anchor::min(),
+            highlights: Vec::new(),
+            anchors: Vec::new(),
+        }
+    }
+
+    pub fn compute(buffer: &MultiBufferSnapshot, range: Range<usize>) -> Self {
+        let mut highlights = Vec::<(Range<usize>, HighlightId)>::new();
+        let mut offset = range.start;
+        for chunk in buffer.chunks(range.clone(), true) {
+            let end = offset + chunk.text.len();
+            if let Some(id) = chunk.syntax_highlight_id {
+                match highlights.last_mut() {
+                    Some((last_range, last_id)) if last_range.end == offset && *last_id == id => {
+                        last_range.end = end;
+                    }
+                    _ => highlights.push((offset..end, id)),
+                }
+            }
+            offset = end;
+        }
+        Self {
+            edit_count: buffer.edit_count(),
+            non_text_state_update_count: buffer.non_text_state_update_count(),
+            anchor_range: buffer.anchor_before(range.start)..buffer.anchor_after(range.end),
+            range,
+            anchors: highlights
+                .iter()
+                .map(|(range, _)| buffer.anchor_after(range.start)..buffer.anchor_before(range.end))
+                .collect(),
+            highlights,
+        }
+    }
+
+    /// Returns the highlights for the given state of the multibuffer, moving them along with
+    /// the edits made since they were computed, and dropping those of the edited text.
+    pub fn interpolate(&self, buffer: &MultiBufferSnapshot) -> Self {
+        let offsets = buffer.summaries_for_anchors::<usize, _>(
+            self.anchors
+                .iter()
+                .flat_map(|range| [&range.start, &range.end]),
+        );
+        let mut highlights = Vec::with_capacity(self.highlights.len());
+        let mut anchors = Vec::with_capacity(self.anchors.len());
+        for (((old_range, id), anchor_range), new_range) in self
+            .highlights
+            .iter()
+            .zip(&self.anchors)
+            .zip(offsets.chunks_exact(2))
+        {
+            let new_range = new_range[0]..new_range[1];
+            if new_range.len() == old_range.len() {
+                highlights.push((new_range, *id));
+                anchors.push(anchor_range.clone());
+            }
+        }
+        Self {
+            edit_count: buffer.edit_count(),
+            non_text_state_update_count: buffer.non_text_state_update_count(),
+            range: self.anchor_range.start.to_offset(buffer)
+                ..self.anchor_range.end.to_offset(buffer),
+            anchor_range: self.anchor_range.clone(),
+            highlights,
+            anchors,
+        }
+    }
+
+    /// Whether the highlights were computed for the given state of the multibuffer. Stale
+    /// highlights are ignored, since their ranges may no longer match the text, until they're
+    /// interpolated.
+    pub fn is_current(&self, buffer: &MultiBufferSnapshot) -> bool {
+        self.edit_count == buffer.edit_count()
+            && self.non_text_state_update_count == buffer.non_text_state_update_count()
+    }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -36,10 +137,19 @@ impl<'a> CustomHighlightsChunks<'a> {
         range: Range<usize>,
         language_aware: bool,
         text_highlights: Option<&'a TreeMap<TypeId, Arc<(HighlightStyle, Vec<Range<Anchor>>)>>>,
+        syntax_highlights: Option<&'a PrecomputedSyntaxHighlights>,
         multibuffer_snapshot: &'a MultiBufferSnapshot,
     ) -> Self {
+        let buffer_chunks = multibuffer_snapshot.chunks_with_syntax(
+            range.clone(),
+            language_aware,
+            syntax_highlights.is_none(),
+        );
+        let syntax_highlights = syntax_highlights
+            .filter(|highlights| highlights.is_current(multibuffer_snapshot))
+            .map_or(&[][..], |highlights| highlights.highlights.as_slice());
         Self {
-            buffer_chunks: multibuffer_snapshot.chunks(range.clone(), language_aware),
+            buffer_chunks,
             buffer_chunk: None,
             offset: range.start,
 
@@ -51,12 +161,19 @@ impl<'a> CustomHighlightsChunks<'a> {
             ),
             active_highlights: Default::default(),
             multibuffer_snapshot,
+            syntax_highlights,
+            remaining_syntax_highlights: remaining_syntax_highlights(
+                syntax_highlights,
+                range.start,
+            ),
         }
     }
 
     pub fn seek(&mut self, new_range: Range<usize>) {
         self.highlight_endpoints =
             create_highlight_endpoints(&new_range, self.text_highlights, self.multibuffer_snapshot);
+        self.remaining_syntax_highlights =
+            remaining_syntax_highlights(self.syntax_highlights, new_range.start);
         self.offset = new_range.start;
         self.buffer_chunks.seek(new_range);
         self.buffer_chunk.take();
@@ -64,6 +181,14 @@ impl<'a> CustomHighlightsChunks<'a> {
     }
 }
 
+fn remaining_syntax_highlights(
+    syntax_highlights: &[(Range<usize>, HighlightId)],
+    offset: usize,
+) -> &[(Range<usize>, HighlightId)] {
+    let start_ix = syntax_highlights.partition_point(|(range, _)| range.end <= offset);
+    &syntax_highlights[start_ix..]
+}
+
 fn create_highlight_endpoints(
     range: &Range<usize>,
     text_highlights: Option<&TreeMap<TypeId, Arc<(HighlightStyle, Vec<Range<Anchor>>)>>>,
@@ -131,6 +256,22 @@ impl<'a> Iterator for CustomHighlightsChunks<'a> {
             }
         }
 
+        while let Some(((range, _), rest)) = self.remaining_syntax_highlights.split_first() {
+            if range.end > self.offset {
+                break;
+            }
+            self.remaining_syntax_highlights = rest;
+        }
+        let mut syntax_highlight_id = None;
+        if let Some((range, id)) = self.remaining_syntax_highlights.first() {
+            if range.start <= self.offset {
+                syntax_highlight_id = Some(*id);
+                next_highlight_endpoint = next_highlight_endpoint.min(range.end);
+            } else {
+                next_highlight_endpoint = next_highlight_endpoint.min(range.start);
+            }
+        }
+
         let chunk = self
             .buffer_chunk
             .get_or_insert_with(|| self.buffer_chunks.next().unwrap());
@@ -148,6 +289,9 @@ impl<'a> Iterator for CustomHighlightsChunks<'a> {
             text: prefix,
             ..chunk.clone()
         };
+        if syntax_highlight_id.is_some() {
+            prefix.syntax_highlight_id = syntax_highlight_id;
+        }
         if !self.active_highlights.is_empty() {
             let mut highlight_style = HighlightStyle::default();
             for active_highlight in self.active_highlights.values() {
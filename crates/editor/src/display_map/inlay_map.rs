@@ -64,6 +64,14 @@ impl Inlay {
             text: text.into(),
         }
     }
+
+    pub fn evaluation<T: Into<Rope>>(id: usize, position: Anchor, text: T) -> Self {
+        Self {
+            id: InlayId::Evaluation(id),
+            position,
+            text: text.into(),
+        }
+    }
 }
 
 impl sum_tree::Item for Transform {
@@ -286,7 +294,7 @@ impl<'a> Iterator for InlayChunks<'a> {
                             }
                         })
                     }
-                    InlayId::Hint(_) => self.highlight_styles.inlay_hint,
+                    InlayId::Hint(_) | InlayId::Evaluation(_) => self.highlight_styles.inlay_hint,
                 };
                 let next_inlay_highlight_endpoint;
                 let offset_in_inlay = self.output_offset - self.transforms.start().0;
@@ -998,6 +1006,7 @@ impl InlaySnapshot {
             buffer_range,
             language_aware,
             highlights.text_highlights,
+            highlights.syntax_highlights,
             &self.buffer,
         );
 
@@ -3,18 +3,46 @@ use super::{
     tab_map::{self, TabEdit, TabPoint, TabSnapshot},
     Highlights,
 };
-use gpui::{App, AppContext as _, Context, Entity, Font, LineWrapper, Pixels, Task};
+use futures::{
+    channel::{mpsc, oneshot},
+    Future, StreamExt as _,
+};
+use gpui::{
+    px, supervised, App, AppContext as _, BackgroundExecutor, Context, Entity, EventEmitter, Font,
+    LineWrapper, LineWrapperHandle, Pixels, SharedString, Task, TaskPanic, TaskRecovery,
+    TaskSupervisor,
+};
 use language::{Chunk, Point};
-use multi_buffer::{MultiBufferSnapshot, RowInfo};
+use multi_buffer::{Anchor, MultiBufferSnapshot, RowInfo, ToPoint as _};
 use smol::future::yield_now;
 use std::sync::LazyLock;
-use std::{cmp, collections::VecDeque, mem, ops::Range, time::Duration};
-use sum_tree::{Bias, Cursor, SumTree};
+use std::{
+    cmp,
+    collections::VecDeque,
+    mem,
+    ops::Range,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use sum_tree::{Bias, Cursor, ItemsIn, SumTree};
 use text::Patch;
 
 pub use super::tab_map::TextSummary;
 pub type WrapEdit = text::Edit<u32>;
 
+/// How many rows a line is wrapped into by measuring its text. The rest of the
+/// line is wrapped at fixed character intervals, which keeps wrapping a
+/// multi-megabyte minified line from stalling the background wrapper.
+pub const DEFAULT_LINE_WRAP_BUDGET: u32 = 256;
+
+/// Edits touching at least this many rows wrap the visible rows first and
+/// publish an intermediate snapshot before wrapping the remainder.
+const PRIORITY_WRAP_MIN_ROWS: u32 = 1024;
+
+/// The minimum time between two intermediate snapshots published by a
+/// background wrap. Snapshots produced in between are never sent.
+const INTERMEDIATE_SNAPSHOT_INTERVAL: Duration = Duration::from_millis(16);
+
 /// Handles soft wrapping of text.
 ///
 /// See the [`display_map` module documentation](crate::display_map) for more information.
@@ -24,21 +52,65 @@ pub struct WrapMap {
     interpolated_edits: Patch<u32>,
     edits_since_sync: Patch<u32>,
     wrap_width: Option<Pixels>,
+    /// The scale factor of the window the text is displayed in. Wrap widths are snapped to its
+    /// device pixels, which is where the text is actually clipped.
+    scale_factor: f32,
     background_task: Option<Task<()>>,
     font_with_size: (Font, Pixels),
+    line_wrap_budget: u32,
+    visible_tab_rows: Option<Range<u32>>,
+    snapshot_waiters: Vec<(usize, oneshot::Sender<WrapSnapshot>)>,
+    font_unavailable: bool,
+    /// The last problem loading the font, reported again to late subscribers.
+    font_warning: Option<WrapMapEvent>,
+    /// The version of the text system's script fallbacks the text was wrapped with.
+    script_fallbacks_version: usize,
+    /// Whether the text isn't displayed, in which case it's not wrapped in the background.
+    paused: bool,
+    /// Whether the text has to be rewrapped once the map is resumed.
+    rewrap_when_resumed: bool,
+    wrap_supervisor: TaskSupervisor,
+    /// Whether the background wrap panicked too many times in a row, in which case lines
+    /// aren't wrapped anymore.
+    wrapping_degraded: bool,
+    #[cfg(test)]
+    injected_panics: usize,
+}
+
+/// Emitted when the font used to measure soft wraps can't be loaded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WrapMapEvent {
+    /// The font failed to load, so lines are wrapped using a fallback font.
+    FontFallback { family: SharedString },
+    /// Neither the font nor any of its fallbacks could be loaded, so lines are
+    /// left unwrapped.
+    FontUnavailable { family: SharedString },
 }
 
+impl EventEmitter<WrapMapEvent> for WrapMap {}
+
+/// The soft-wrapped text. Wrap boundaries are computed on the tab-expanded text, and the edits
+/// received from the [`TabMap`](super::tab_map::TabMap) are in tab-expanded coordinates too.
+/// Because a wrap boundary can split the expansion of a tab, a wrap point can correspond to a
+/// column in the middle of a tab. Use [`WrapSnapshot::from_tab_point`] to convert tab points
+/// that might not lie on a character boundary.
 #[derive(Clone)]
 pub struct WrapSnapshot {
     tab_snapshot: TabSnapshot,
     transforms: SumTree<Transform>,
     interpolated: bool,
+    epoch: usize,
+    /// The ranges whose lines overflow horizontally instead of being soft-wrapped.
+    no_wrap_ranges: Arc<[Range<Anchor>]>,
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 struct Transform {
     summary: TransformSummary,
     display_text: Option<&'static str>,
+    /// Whether this soft wrap was placed by fixed-width chunking rather than by
+    /// measuring the text, meaning the layout around it is only approximate.
+    approximate: bool,
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -55,7 +127,7 @@ pub struct WrapChunks<'a> {
     input_chunk: Chunk<'a>,
     output_position: WrapPoint,
     max_output_row: u32,
-    transforms: Cursor<'a, Transform, (WrapPoint, TabPoint)>,
+    transforms: ItemsIn<'a, Transform, (WrapPoint, TabPoint), WrapPoint>,
     snapshot: &'a WrapSnapshot,
 }
 
@@ -96,12 +168,26 @@ impl WrapMap {
             let mut this = Self {
                 font_with_size: (font, font_size),
                 wrap_width: None,
+                scale_factor: 1.,
                 pending_edits: Default::default(),
                 interpolated_edits: Default::default(),
                 edits_since_sync: Default::default(),
                 snapshot: WrapSnapshot::new(tab_snapshot),
                 background_task: None,
+                line_wrap_budget: DEFAULT_LINE_WRAP_BUDGET,
+                visible_tab_rows: None,
+                snapshot_waiters: Vec::new(),
+                font_unavailable: false,
+                font_warning: None,
+                script_fallbacks_version: cx.text_system().script_fallbacks_version(),
+                paused: false,
+                rewrap_when_resumed: false,
+                wrap_supervisor: TaskSupervisor::default(),
+                wrapping_degraded: false,
+                #[cfg(test)]
+                injected_panics: 0,
             };
+            this.resolve_font(cx);
             this.set_wrap_width(wrap_width, cx);
             mem::take(&mut this.edits_since_sync);
             this
@@ -121,7 +207,7 @@ impl WrapMap {
         edits: Vec<TabEdit>,
         cx: &mut Context<Self>,
     ) -> (WrapSnapshot, Patch<u32>) {
-        if self.wrap_width.is_some() {
+        if self.effective_wrap_width().is_some() {
             self.pending_edits.push_back((tab_snapshot, edits));
             self.flush_edits(cx);
         } else {
@@ -129,11 +215,69 @@ impl WrapMap {
                 .edits_since_sync
                 .compose(self.snapshot.interpolate(tab_snapshot, &edits));
             self.snapshot.interpolated = false;
+            self.snapshot.epoch += 1;
+            self.resolve_snapshot_waiters();
+        }
+
+        // Characters wrapped with other fallback fonts than they are now rendered with
+        // have other widths.
+        let script_fallbacks_version = cx.text_system().script_fallbacks_version();
+        if script_fallbacks_version != self.script_fallbacks_version {
+            self.script_fallbacks_version = script_fallbacks_version;
+            if self.effective_wrap_width().is_some() {
+                self.rewrap(cx);
+            }
         }
 
         (self.snapshot.clone(), mem::take(&mut self.edits_since_sync))
     }
 
+    /// Returns a future that resolves once the map has wrapped all input up to
+    /// (at least) the given [`TabSnapshot`] version, yielding that snapshot.
+    ///
+    /// Unlike the snapshots returned by [`Self::sync`], which may be interpolated
+    /// while wrapping happens in the background, the resolved snapshot has exact
+    /// coordinates. The future resolves to `None` if the map is dropped first.
+    pub fn snapshot_at_least(
+        &mut self,
+        version: usize,
+    ) -> impl Future<Output = Option<WrapSnapshot>> + 'static {
+        let (tx, rx) = oneshot::channel();
+        self.snapshot_waiters.push((version, tx));
+        self.resolve_snapshot_waiters();
+        async move { rx.await.ok() }
+    }
+
+    /// The epoch of the latest published snapshot.
+    pub fn epoch(&self) -> usize {
+        self.snapshot.epoch
+    }
+
+    /// Replaces the current snapshot with one produced by the wrapper, starting
+    /// a new epoch.
+    fn publish(&mut self, mut snapshot: WrapSnapshot) {
+        snapshot.epoch = self.snapshot.epoch + 1;
+        self.snapshot = snapshot;
+    }
+
+    fn resolve_snapshot_waiters(&mut self) {
+        // Waiters whose future was dropped are pruned even while wrapping, so that
+        // waiting on versions that are never reached doesn't accumulate them.
+        self.snapshot_waiters.retain(|(_, tx)| !tx.is_canceled());
+        if self.snapshot.interpolated || self.background_task.is_some() {
+            return;
+        }
+
+        let snapshot_version = self.snapshot.version();
+        for (version, tx) in mem::take(&mut self.snapshot_waiters) {
+            if version <= snapshot_version {
+                tx.send(self.snapshot.clone()).ok();
+            } else {
+                self.snapshot_waiters.push((version, tx));
+            }
+        }
+    }
+
     pub fn set_font_with_size(
         &mut self,
         font: Font,
@@ -146,73 +290,218 @@ impl WrapMap {
             false
         } else {
             self.font_with_size = font_with_size;
+            self.resolve_font(cx);
+            self.retry_degraded_wrapping();
             self.rewrap(cx);
             true
         }
     }
 
+    /// Checks that the wrap font can be loaded, so that a missing font family
+    /// degrades to a fallback font (or to no wrapping at all) instead of
+    /// panicking on the background wrapping task.
+    fn resolve_font(&mut self, cx: &mut Context<Self>) {
+        let text_system = cx.text_system().clone();
+        let font = &self.font_with_size.0;
+        self.font_unavailable = false;
+        self.font_warning = None;
+        if text_system.font_id(font).is_ok() {
+            return;
+        }
+
+        let family = font.family.clone();
+        let warning = match text_system.try_resolve_font(font) {
+            Ok(_) => {
+                log::warn!("failed to load font {family:?} for soft wrapping, using a fallback");
+                WrapMapEvent::FontFallback { family }
+            }
+            Err(error) => {
+                log::error!("{error:#}, lines will not be soft wrapped");
+                self.font_unavailable = true;
+                WrapMapEvent::FontUnavailable { family }
+            }
+        };
+        self.font_warning = Some(warning.clone());
+        cx.emit(warning);
+    }
+
+    /// The problem loading the font the last time it was set, if any.
+    pub fn font_warning(&self) -> Option<&WrapMapEvent> {
+        self.font_warning.as_ref()
+    }
+
+    /// A wrapper of lines in the font of the text, along with the width the text is wrapped at,
+    /// unless it isn't wrapped.
+    pub fn line_wrapper(&self, cx: &App) -> Option<(LineWrapperHandle, Pixels)> {
+        let wrap_width = self.effective_wrap_width()?;
+        let (font, font_size) = self.font_with_size.clone();
+        let line_wrapper = cx.text_system().try_line_wrapper(font, font_size).ok()?;
+        Some((line_wrapper, wrap_width))
+    }
+
+    fn effective_wrap_width(&self) -> Option<Pixels> {
+        self.wrap_width
+            .filter(|_| !self.font_unavailable && !self.wrapping_degraded)
+            .map(|wrap_width| snap_to_device_pixels(wrap_width, self.scale_factor))
+    }
+
     pub fn set_wrap_width(&mut self, wrap_width: Option<Pixels>, cx: &mut Context<Self>) -> bool {
         if wrap_width == self.wrap_width {
             return false;
         }
 
         self.wrap_width = wrap_width;
+        self.retry_degraded_wrapping();
         self.rewrap(cx);
         true
     }
 
+    /// Wraps lines again after the background wrap panicked too many times in a row, since it
+    /// may not panic with another font or wrap width.
+    fn retry_degraded_wrapping(&mut self) {
+        self.wrapping_degraded = false;
+        self.wrap_supervisor.task_completed();
+    }
+
+    /// Sets the scale factor of the window the text is displayed in, rewrapping the text
+    /// when it changes the wrap width in device pixels.
+    pub fn set_scale_factor(&mut self, scale_factor: f32, cx: &mut Context<Self>) -> bool {
+        if scale_factor == self.scale_factor || scale_factor <= 0. {
+            return false;
+        }
+
+        let old_wrap_width = self.effective_wrap_width();
+        self.scale_factor = scale_factor;
+        if self.effective_wrap_width() != old_wrap_width {
+            self.rewrap(cx);
+        }
+        true
+    }
+
+    /// Hints which wrap rows are currently on screen, so that large rewraps can
+    /// lay out those rows before the rest of the text.
+    pub fn set_visible_rows(&mut self, rows: Range<u32>) {
+        let start = self.snapshot.to_tab_point(WrapPoint::new(rows.start, 0));
+        let end = self.snapshot.to_tab_point(WrapPoint::new(rows.end, 0));
+        self.visible_tab_rows = Some(start.row()..end.row() + 1);
+    }
+
+    /// Sets how many rows a line is wrapped into precisely, before the rest of it is
+    /// wrapped approximately.
+    pub fn set_line_wrap_budget(&mut self, budget: u32, cx: &mut Context<Self>) -> bool {
+        if budget == self.line_wrap_budget {
+            return false;
+        }
+
+        self.line_wrap_budget = budget;
+        self.rewrap(cx);
+        true
+    }
+
+    /// Sets the ranges whose lines overflow horizontally instead of being soft-wrapped,
+    /// rewrapping the text when they cover different rows than before.
+    pub fn set_no_wrap_ranges(
+        &mut self,
+        ranges: Arc<[Range<Anchor>]>,
+        cx: &mut Context<Self>,
+    ) -> bool {
+        let buffer = self.snapshot.buffer_snapshot();
+        if no_wrap_rows(&ranges, buffer) == no_wrap_rows(&self.snapshot.no_wrap_ranges, buffer) {
+            self.snapshot.no_wrap_ranges = ranges;
+            return false;
+        }
+
+        self.snapshot.no_wrap_ranges = ranges;
+        self.rewrap(cx);
+        true
+    }
+
+    /// Pauses wrapping the text while it isn't displayed, e.g. in a pane hidden by another
+    /// one being maximized. In the meantime, edits are only interpolated, and rewraps are
+    /// postponed until the map is resumed.
+    pub fn set_paused(&mut self, paused: bool, cx: &mut Context<Self>) -> bool {
+        if paused == self.paused {
+            return false;
+        }
+
+        self.paused = paused;
+        if !paused {
+            if mem::take(&mut self.rewrap_when_resumed) {
+                self.rewrap(cx);
+            } else {
+                self.flush_edits(cx);
+            }
+        }
+        true
+    }
+
     fn rewrap(&mut self, cx: &mut Context<Self>) {
+        if self.paused && self.effective_wrap_width().is_some() {
+            self.rewrap_when_resumed = true;
+            return;
+        }
+
+        self.rewrap_when_resumed = false;
         self.background_task.take();
         self.interpolated_edits.clear();
         self.pending_edits.clear();
 
-        if let Some(wrap_width) = self.wrap_width {
+        if let Some(wrap_width) = self.effective_wrap_width() {
             let mut new_snapshot = self.snapshot.clone();
 
             let text_system = cx.text_system().clone();
             let (font, font_size) = self.font_with_size.clone();
-            let task = cx.background_executor().spawn(async move {
-                let mut line_wrapper = text_system.line_wrapper(font, font_size);
-                let tab_snapshot = new_snapshot.tab_snapshot.clone();
-                let range = TabPoint::zero()..tab_snapshot.max_point();
-                let edits = new_snapshot
-                    .update(
-                        tab_snapshot,
-                        &[TabEdit {
-                            old: range.clone(),
-                            new: range.clone(),
-                        }],
-                        wrap_width,
-                        &mut line_wrapper,
-                    )
-                    .await;
-                (new_snapshot, edits)
-            });
+            let line_wrap_budget = self.line_wrap_budget;
+            let priority_rows = self.visible_tab_rows.clone();
+            let (intermediate_tx, intermediate_rx) = mpsc::unbounded();
+            let mut throttle = IntermediateSnapshotThrottle::new(cx.background_executor().clone());
+            let inject_panic = self.take_injected_panic();
+            let task = cx
+                .background_executor()
+                .spawn(supervised("soft wrapper", async move {
+                    if inject_panic {
+                        panic!("injected soft wrap panic");
+                    }
+                    let mut line_wrapper = text_system.line_wrapper(font, font_size);
+                    let tab_snapshot = new_snapshot.tab_snapshot.clone();
+                    let range = TabPoint::zero()..tab_snapshot.max_point();
+                    let edits = new_snapshot
+                        .update_prioritized(
+                            tab_snapshot,
+                            &[TabEdit {
+                                old: range.clone(),
+                                new: range.clone(),
+                            }],
+                            wrap_width,
+                            line_wrap_budget,
+                            priority_rows,
+                            &mut line_wrapper,
+                            &mut |snapshot, edits| {
+                                if throttle.ready() {
+                                    intermediate_tx
+                                        .unbounded_send((snapshot.clone(), edits.clone()))
+                                        .ok();
+                                }
+                            },
+                        )
+                        .await;
+                    (new_snapshot, edits)
+                }));
 
             match cx
                 .background_executor()
                 .block_with_timeout(Duration::from_millis(5), task)
             {
-                Ok((snapshot, edits)) => {
-                    self.snapshot = snapshot;
+                Ok(Ok((snapshot, edits))) => {
+                    self.wrap_supervisor.task_completed();
+                    self.publish(snapshot);
                     self.edits_since_sync = self.edits_since_sync.compose(&edits);
                 }
-                Err(wrap_task) => {
-                    self.background_task = Some(cx.spawn(|this, mut cx| async move {
-                        let (snapshot, edits) = wrap_task.await;
-                        this.update(&mut cx, |this, cx| {
-                            this.snapshot = snapshot;
-                            this.edits_since_sync = this
-                                .edits_since_sync
-                                .compose(mem::take(&mut this.interpolated_edits).invert())
-                                .compose(&edits);
-                            this.background_task = None;
-                            this.flush_edits(cx);
-                            cx.notify();
-                        })
-                        .ok();
-                    }));
+                Ok(Err(panic)) => {
+                    self.recover_from_wrap_panic(panic, cx);
+                    return;
                 }
+                Err(wrap_task) => self.wait_for_background_wrap(wrap_task, intermediate_rx, cx),
             }
         } else {
             let old_rows = self.snapshot.transforms.summary().output.lines.row + 1;
@@ -225,11 +514,13 @@ impl WrapMap {
             }
             let new_rows = self.snapshot.transforms.summary().output.lines.row + 1;
             self.snapshot.interpolated = false;
+            self.snapshot.epoch += 1;
             self.edits_since_sync = self.edits_since_sync.compose(Patch::new(vec![WrapEdit {
                 old: 0..old_rows,
                 new: 0..new_rows,
             }]));
         }
+        self.resolve_snapshot_waiters();
     }
 
     fn flush_edits(&mut self, cx: &mut Context<Self>) {
@@ -249,52 +540,167 @@ impl WrapMap {
             return;
         }
 
-        if let Some(wrap_width) = self.wrap_width {
-            if self.background_task.is_none() {
+        if let Some(wrap_width) = self.effective_wrap_width() {
+            if self.background_task.is_none() && !self.paused {
                 let pending_edits = self.pending_edits.clone();
                 let mut snapshot = self.snapshot.clone();
                 let text_system = cx.text_system().clone();
                 let (font, font_size) = self.font_with_size.clone();
-                let update_task = cx.background_executor().spawn(async move {
-                    let mut edits = Patch::default();
-                    let mut line_wrapper = text_system.line_wrapper(font, font_size);
-                    for (tab_snapshot, tab_edits) in pending_edits {
-                        let wrap_edits = snapshot
-                            .update(tab_snapshot, &tab_edits, wrap_width, &mut line_wrapper)
-                            .await;
-                        edits = edits.compose(&wrap_edits);
-                    }
-                    (snapshot, edits)
-                });
+                let line_wrap_budget = self.line_wrap_budget;
+                let priority_rows = self.visible_tab_rows.clone();
+                let (intermediate_tx, intermediate_rx) = mpsc::unbounded();
+                let mut throttle =
+                    IntermediateSnapshotThrottle::new(cx.background_executor().clone());
+                let inject_panic = self.take_injected_panic();
+                let update_task =
+                    cx.background_executor()
+                        .spawn(supervised("soft wrapper", async move {
+                            if inject_panic {
+                                panic!("injected soft wrap panic");
+                            }
+                            let mut edits = Patch::default();
+                            let mut line_wrapper = text_system.line_wrapper(font, font_size);
+                            for (tab_snapshot, tab_edits) in pending_edits {
+                                let wrap_edits = snapshot
+                                    .update_prioritized(
+                                        tab_snapshot,
+                                        &tab_edits,
+                                        wrap_width,
+                                        line_wrap_budget,
+                                        priority_rows.clone(),
+                                        &mut line_wrapper,
+                                        &mut |snapshot, partial_edits| {
+                                            if throttle.ready() {
+                                                intermediate_tx
+                                                    .unbounded_send((
+                                                        snapshot.clone(),
+                                                        edits.compose(partial_edits),
+                                                    ))
+                                                    .ok();
+                                            }
+                                        },
+                                    )
+                                    .await;
+                                edits = edits.compose(&wrap_edits);
+                            }
+                            (snapshot, edits)
+                        }));
 
                 match cx
                     .background_executor()
                     .block_with_timeout(Duration::from_millis(1), update_task)
                 {
-                    Ok((snapshot, output_edits)) => {
-                        self.snapshot = snapshot;
+                    Ok(Ok((snapshot, output_edits))) => {
+                        self.wrap_supervisor.task_completed();
+                        self.publish(snapshot);
                         self.edits_since_sync = self.edits_since_sync.compose(&output_edits);
                     }
+                    Ok(Err(panic)) => {
+                        self.recover_from_wrap_panic(panic, cx);
+                        return;
+                    }
                     Err(update_task) => {
-                        self.background_task = Some(cx.spawn(|this, mut cx| async move {
-                            let (snapshot, edits) = update_task.await;
-                            this.update(&mut cx, |this, cx| {
-                                this.snapshot = snapshot;
-                                this.edits_since_sync = this
-                                    .edits_since_sync
-                                    .compose(mem::take(&mut this.interpolated_edits).invert())
-                                    .compose(&edits);
-                                this.background_task = None;
-                                this.flush_edits(cx);
-                                cx.notify();
-                            })
-                            .ok();
-                        }));
+                        self.wait_for_background_wrap(update_task, intermediate_rx, cx)
                     }
                 }
             }
         }
 
+        self.interpolate_pending_edits();
+        self.resolve_snapshot_waiters();
+    }
+
+    /// Publishes the snapshots produced by a background wrap as they arrive.
+    ///
+    /// Both intermediate and final snapshots are expressed relative to the
+    /// snapshot the background wrap started from, so the edits reported for any
+    /// interpolation layered on top of the previously published snapshot are
+    /// undone before applying them. For the same reason, intermediate snapshots
+    /// that queued up while the map was busy are skipped in favor of the latest.
+    fn wait_for_background_wrap(
+        &mut self,
+        wrap_task: Task<Result<(WrapSnapshot, Patch<u32>), TaskPanic>>,
+        mut intermediate_snapshots: mpsc::UnboundedReceiver<(WrapSnapshot, Patch<u32>)>,
+        cx: &mut Context<Self>,
+    ) {
+        self.background_task = Some(cx.spawn(|this, mut cx| async move {
+            while let Some(mut intermediate) = intermediate_snapshots.next().await {
+                while let Ok(Some(newer)) = intermediate_snapshots.try_next() {
+                    intermediate = newer;
+                }
+                let (mut snapshot, edits) = intermediate;
+                // Keep pending edits around until the final snapshot arrives.
+                snapshot.interpolated = true;
+                let updated = this.update(&mut cx, |this, cx| {
+                    this.publish(snapshot);
+                    this.edits_since_sync = this
+                        .edits_since_sync
+                        .compose(mem::take(&mut this.interpolated_edits).invert())
+                        .compose(&edits);
+                    this.interpolated_edits = edits;
+                    this.interpolate_pending_edits();
+                    cx.notify();
+                });
+                if updated.is_err() {
+                    return;
+                }
+            }
+
+            let (snapshot, edits) = match wrap_task.await {
+                Ok(wrapped) => wrapped,
+                Err(panic) => {
+                    this.update(&mut cx, |this, cx| {
+                        this.background_task = None;
+                        this.recover_from_wrap_panic(panic, cx);
+                        cx.notify();
+                    })
+                    .ok();
+                    return;
+                }
+            };
+            this.update(&mut cx, |this, cx| {
+                this.wrap_supervisor.task_completed();
+                this.publish(snapshot);
+                this.edits_since_sync = this
+                    .edits_since_sync
+                    .compose(mem::take(&mut this.interpolated_edits).invert())
+                    .compose(&edits);
+                this.background_task = None;
+                this.resolve_snapshot_waiters();
+                this.flush_edits(cx);
+                cx.notify();
+            })
+            .ok();
+        }));
+    }
+
+    /// Restarts the background wrap stopped by the given panic, by rewrapping the latest
+    /// snapshot once interpolated with the edits the wrap didn't get to. Once it panicked too
+    /// many times in a row, lines are left unwrapped instead.
+    fn recover_from_wrap_panic(&mut self, panic: TaskPanic, cx: &mut Context<Self>) {
+        if self.wrap_supervisor.task_panicked(&panic, cx) == TaskRecovery::Degraded {
+            self.wrapping_degraded = true;
+        }
+        self.interpolate_pending_edits();
+        self.rewrap(cx);
+    }
+
+    #[cfg(test)]
+    fn take_injected_panic(&mut self) -> bool {
+        if self.injected_panics > 0 {
+            self.injected_panics -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    #[cfg(not(test))]
+    fn take_injected_panic(&mut self) -> bool {
+        false
+    }
+
+    fn interpolate_pending_edits(&mut self) {
         let was_interpolated = self.snapshot.interpolated;
         let mut to_remove_len = 0;
         for (tab_snapshot, edits) in &self.pending_edits {
@@ -313,6 +719,72 @@ impl WrapMap {
     }
 }
 
+/// Returns the buffer rows spanned by the ranges, as sorted, disjoint ranges of rows.
+fn no_wrap_rows(ranges: &[Range<Anchor>], buffer: &MultiBufferSnapshot) -> Vec<Range<u32>> {
+    let mut rows = ranges
+        .iter()
+        .map(|range| {
+            let start = range.start.to_point(buffer);
+            let end = range.end.to_point(buffer);
+            // A range ending at the start of a line doesn't include that line.
+            let end_row = if end.column == 0 && end.row > start.row {
+                end.row
+            } else {
+                end.row + 1
+            };
+            start.row..end_row
+        })
+        .collect::<Vec<_>>();
+    rows.sort_unstable_by_key(|rows| rows.start);
+    let mut merged: Vec<Range<u32>> = Vec::with_capacity(rows.len());
+    for rows in rows {
+        match merged.last_mut() {
+            Some(last) if rows.start <= last.end => last.end = last.end.max(rows.end),
+            _ => merged.push(rows),
+        }
+    }
+    merged
+}
+
+fn is_no_wrap_row(no_wrap_rows: &[Range<u32>], row: u32) -> bool {
+    let ix = no_wrap_rows.partition_point(|rows| rows.start <= row);
+    ix > 0 && no_wrap_rows[ix - 1].contains(&row)
+}
+
+/// Rounds a width down to a whole number of device pixels at the given scale factor.
+fn snap_to_device_pixels(width: Pixels, scale_factor: f32) -> Pixels {
+    px((width.0 * scale_factor).floor() / scale_factor)
+}
+
+/// Limits how often a background wrap publishes intermediate snapshots, since
+/// each one is a full copy of the snapshot that the map may not get to read
+/// before the next one arrives.
+struct IntermediateSnapshotThrottle {
+    executor: BackgroundExecutor,
+    last_sent_at: Option<Instant>,
+}
+
+impl IntermediateSnapshotThrottle {
+    fn new(executor: BackgroundExecutor) -> Self {
+        Self {
+            executor,
+            last_sent_at: None,
+        }
+    }
+
+    fn ready(&mut self) -> bool {
+        let now = self.executor.now();
+        if self.last_sent_at.map_or(true, |last_sent_at| {
+            now.duration_since(last_sent_at) >= INTERMEDIATE_SNAPSHOT_INTERVAL
+        }) {
+            self.last_sent_at = Some(now);
+            true
+        } else {
+            false
+        }
+    }
+}
+
 impl WrapSnapshot {
     fn new(tab_snapshot: TabSnapshot) -> Self {
         let mut transforms = SumTree::default();
@@ -324,6 +796,8 @@ impl WrapSnapshot {
             transforms,
             tab_snapshot,
             interpolated: true,
+            epoch: 0,
+            no_wrap_ranges: Arc::default(),
         }
     }
 
@@ -331,6 +805,18 @@ impl WrapSnapshot {
         self.tab_snapshot.buffer_snapshot()
     }
 
+    /// The version of the [`TabSnapshot`] this snapshot was wrapped from.
+    pub fn version(&self) -> usize {
+        self.tab_snapshot.version
+    }
+
+    /// The number of the publication this snapshot belongs to. The wrap map
+    /// starts a new epoch whenever it publishes a newly wrapped snapshot, so a
+    /// snapshot is stale if its epoch is lower than [`WrapMap::epoch`].
+    pub fn epoch(&self) -> usize {
+        self.epoch
+    }
+
     fn interpolate(&mut self, new_tab_snapshot: TabSnapshot, tab_edits: &[TabEdit]) -> Patch<u32> {
         let mut new_transforms;
         if tab_edits.is_empty() {
@@ -391,6 +877,8 @@ impl WrapSnapshot {
                 tab_snapshot: new_tab_snapshot,
                 transforms: new_transforms,
                 interpolated: true,
+                epoch: self.epoch,
+                no_wrap_ranges: self.no_wrap_ranges.clone(),
             },
         );
         self.check_invariants();
@@ -402,6 +890,7 @@ impl WrapSnapshot {
         new_tab_snapshot: TabSnapshot,
         tab_edits: &[TabEdit],
         wrap_width: Pixels,
+        line_wrap_budget: u32,
         line_wrapper: &mut LineWrapper,
     ) -> Patch<u32> {
         #[derive(Debug)]
@@ -431,6 +920,7 @@ impl WrapSnapshot {
             row_edits.push(row_edit);
         }
 
+        let no_wrap_rows = no_wrap_rows(&self.no_wrap_ranges, new_tab_snapshot.buffer_snapshot());
         let mut new_transforms;
         if row_edits.is_empty() {
             new_transforms = self.transforms.clone();
@@ -461,7 +951,7 @@ impl WrapSnapshot {
                     Highlights::default(),
                 );
                 let mut edit_transforms = Vec::<Transform>::new();
-                for _ in edit.new_rows.start..edit.new_rows.end {
+                for row in edit.new_rows.start..edit.new_rows.end {
                     while let Some(chunk) =
                         remaining.take().or_else(|| chunks.next().map(|c| c.text))
                     {
@@ -479,11 +969,38 @@ impl WrapSnapshot {
                     }
 
                     let mut prev_boundary_ix = 0;
-                    for boundary in line_wrapper.wrap_line(&line, wrap_width) {
-                        let wrapped = &line[prev_boundary_ix..boundary.ix];
-                        push_isomorphic(&mut edit_transforms, TextSummary::from(wrapped));
-                        edit_transforms.push(Transform::wrap(boundary.next_indent));
-                        prev_boundary_ix = boundary.ix;
+                    let buffer_row = new_tab_snapshot
+                        .to_point(TabPoint::new(row, 0), Bias::Left)
+                        .row;
+                    if is_no_wrap_row(&no_wrap_rows, buffer_row) {
+                        // Lines of no-wrap ranges overflow horizontally.
+                    } else {
+                        let mut wrapped_rows = 0;
+                        let mut over_budget = false;
+                        for boundary in line_wrapper.wrap_line(&line, wrap_width) {
+                            if wrapped_rows == line_wrap_budget {
+                                over_budget = true;
+                                break;
+                            }
+                            let wrapped = &line[prev_boundary_ix..boundary.ix];
+                            push_isomorphic(&mut edit_transforms, TextSummary::from(wrapped));
+                            edit_transforms.push(Transform::wrap(boundary.next_indent));
+                            prev_boundary_ix = boundary.ix;
+                            wrapped_rows += 1;
+                        }
+                        if over_budget {
+                            // Past the budget, the rest of the line is wrapped approximately.
+                            let rest_start_ix = prev_boundary_ix;
+                            for boundary in line_wrapper
+                                .wrap_line_fixed_width(&line[rest_start_ix..], wrap_width)
+                            {
+                                let boundary_ix = rest_start_ix + boundary.ix;
+                                let wrapped = &line[prev_boundary_ix..boundary_ix];
+                                push_isomorphic(&mut edit_transforms, TextSummary::from(wrapped));
+                                edit_transforms.push(Transform::approximate_wrap());
+                                prev_boundary_ix = boundary_ix;
+                            }
+                        }
                     }
 
                     if prev_boundary_ix < line.len() {
@@ -541,12 +1058,96 @@ impl WrapSnapshot {
                 tab_snapshot: new_tab_snapshot,
                 transforms: new_transforms,
                 interpolated: false,
+                epoch: self.epoch,
+                no_wrap_ranges: self.no_wrap_ranges.clone(),
             },
         );
         self.check_invariants();
         old_snapshot.compute_edits(tab_edits, self)
     }
 
+    /// Like [`Self::update`], but when the edits touch many rows and
+    /// `priority_rows` are given, the priority rows are wrapped first and the
+    /// resulting snapshot passed to `publish` (along with the edits since
+    /// `self`) before the remaining rows are wrapped.
+    #[allow(clippy::too_many_arguments)]
+    async fn update_prioritized(
+        &mut self,
+        new_tab_snapshot: TabSnapshot,
+        tab_edits: &[TabEdit],
+        wrap_width: Pixels,
+        line_wrap_budget: u32,
+        priority_rows: Option<Range<u32>>,
+        line_wrapper: &mut LineWrapper,
+        publish: &mut (dyn FnMut(&WrapSnapshot, &Patch<u32>) + Send),
+    ) -> Patch<u32> {
+        let edited_rows = tab_edits
+            .iter()
+            .map(|edit| edit.new.end.row() + 1 - edit.new.start.row())
+            .sum::<u32>();
+        let priority_rows = match priority_rows {
+            Some(rows) if edited_rows >= PRIORITY_WRAP_MIN_ROWS => rows,
+            _ => {
+                return self
+                    .update(
+                        new_tab_snapshot,
+                        tab_edits,
+                        wrap_width,
+                        line_wrap_budget,
+                        line_wrapper,
+                    )
+                    .await
+            }
+        };
+
+        // Apply the edits without wrapping, then rewrap the edited rows in place:
+        // first the ones overlapping the priority rows, then everything else.
+        let max_row = new_tab_snapshot.max_point().row();
+        let mut edits = self.interpolate(new_tab_snapshot.clone(), tab_edits);
+        let mut priority_edits = Vec::new();
+        let mut remaining_edits = Vec::new();
+        let push_rows = |edits: &mut Vec<TabEdit>, rows: Range<u32>| {
+            if !rows.is_empty() {
+                let range = TabPoint::new(rows.start, 0)..TabPoint::new(rows.end - 1, 0);
+                edits.push(TabEdit {
+                    old: range.clone(),
+                    new: range,
+                });
+            }
+        };
+        for edit in tab_edits {
+            let rows = edit.new.start.row()..edit.new.end.row().min(max_row) + 1;
+            let priority_start = priority_rows.start.clamp(rows.start, rows.end);
+            let priority_end = priority_rows.end.clamp(priority_start, rows.end);
+            push_rows(&mut remaining_edits, rows.start..priority_start);
+            push_rows(&mut priority_edits, priority_start..priority_end);
+            push_rows(&mut remaining_edits, priority_end..rows.end);
+        }
+
+        let wrap_edits = self
+            .update(
+                new_tab_snapshot.clone(),
+                &priority_edits,
+                wrap_width,
+                line_wrap_budget,
+                line_wrapper,
+            )
+            .await;
+        edits = edits.compose(&wrap_edits);
+        publish(self, &edits);
+
+        let wrap_edits = self
+            .update(
+                new_tab_snapshot,
+                &remaining_edits,
+                wrap_width,
+                line_wrap_budget,
+                line_wrapper,
+            )
+            .await;
+        edits.compose(&wrap_edits)
+    }
+
     fn compute_edits(&self, tab_edits: &[TabEdit], new_snapshot: &WrapSnapshot) -> Patch<u32> {
         let mut wrap_edits = Vec::new();
         let mut old_cursor = self.transforms.cursor::<TransformSummary>(&());
@@ -591,8 +1192,9 @@ impl WrapSnapshot {
     ) -> WrapChunks<'a> {
         let output_start = WrapPoint::new(rows.start, 0);
         let output_end = WrapPoint::new(rows.end, 0);
-        let mut transforms = self.transforms.cursor::<(WrapPoint, TabPoint)>(&());
-        transforms.seek(&output_start, Bias::Right, &());
+        let transforms = self
+            .transforms
+            .iter_items_in::<(WrapPoint, TabPoint), _>(output_start..output_end, &());
         let mut input_start = TabPoint(transforms.start().1 .0);
         if transforms.item().map_or(false, |t| t.is_isomorphic()) {
             input_start.0 += output_start.0 - transforms.start().0 .0;
@@ -709,6 +1311,17 @@ impl WrapSnapshot {
         })
     }
 
+    /// Returns whether the soft wrap ending `row` was placed approximately because
+    /// its line exceeded the wrap budget, in which case the row's layout may not
+    /// match its rendered width.
+    pub fn is_soft_wrap_approximate(&self, row: u32) -> bool {
+        let mut cursor = self.transforms.cursor::<WrapPoint>(&());
+        cursor.seek(&WrapPoint::new(row + 1, 0), Bias::Left, &());
+        cursor
+            .item()
+            .map_or(false, |transform| transform.approximate)
+    }
+
     pub fn longest_row(&self) -> u32 {
         self.transforms.summary().output.longest_row
     }
@@ -733,6 +1346,8 @@ impl WrapSnapshot {
         }
     }
 
+    /// Converts a wrap point to the tab-expanded column it displays. Points in the indent of a
+    /// soft-wrapped row map to the wrap boundary, which may be in the middle of a tab.
     pub fn to_tab_point(&self, point: WrapPoint) -> TabPoint {
         let mut cursor = self.transforms.cursor::<(WrapPoint, TabPoint)>(&());
         cursor.seek(&point, Bias::Right, &());
@@ -751,6 +1366,13 @@ impl WrapSnapshot {
         self.tab_point_to_wrap_point(self.tab_snapshot.make_tab_point(point, bias))
     }
 
+    /// Converts a tab point to a wrap point, after clipping it to the start or the end of the tab
+    /// it falls into according to `bias`. Points at a wrap boundary map to the start of the
+    /// following wrap row, like in [`Self::tab_point_to_wrap_point`].
+    pub fn from_tab_point(&self, point: TabPoint, bias: Bias) -> WrapPoint {
+        self.tab_point_to_wrap_point(self.tab_snapshot.clip_point(point, bias))
+    }
+
     pub fn tab_point_to_wrap_point(&self, point: TabPoint) -> WrapPoint {
         let mut cursor = self.transforms.cursor::<(TabPoint, WrapPoint)>(&());
         cursor.seek(&point, Bias::Right, &());
@@ -767,7 +1389,7 @@ impl WrapSnapshot {
             }
         }
 
-        self.tab_point_to_wrap_point(self.tab_snapshot.clip_point(self.to_tab_point(point), bias))
+        self.from_tab_point(self.to_tab_point(point), bias)
     }
 
     pub fn prev_row_boundary(&self, mut point: WrapPoint) -> u32 {
@@ -797,17 +1419,12 @@ impl WrapSnapshot {
     pub fn next_row_boundary(&self, mut point: WrapPoint) -> Option<u32> {
         point.0 += Point::new(1, 0);
 
-        let mut cursor = self.transforms.cursor::<(WrapPoint, TabPoint)>(&());
-        cursor.seek(&point, Bias::Right, &());
-        while let Some(transform) = cursor.item() {
-            if transform.is_isomorphic() && cursor.start().1.column() == 0 {
-                return Some(cmp::max(cursor.start().0.row(), point.row()));
-            } else {
-                cursor.next(&());
-            }
-        }
-
-        None
+        self.transforms
+            .iter_items_in::<(WrapPoint, TabPoint), _>(point..WrapPoint(Point::MAX), &())
+            .find(|(transform, (_, tab_start))| {
+                transform.is_isomorphic() && tab_start.column() == 0
+            })
+            .map(|(_, (wrap_start, _))| cmp::max(wrap_start.row(), point.row()))
     }
 
     #[cfg(test)]
@@ -876,7 +1493,7 @@ impl<'a> WrapChunks<'a> {
     pub(crate) fn seek(&mut self, rows: Range<u32>) {
         let output_start = WrapPoint::new(rows.start, 0);
         let output_end = WrapPoint::new(rows.end, 0);
-        self.transforms.seek(&output_start, Bias::Right, &());
+        self.transforms.seek(output_start..output_end);
         let mut input_start = TabPoint(self.transforms.start().1 .0);
         if self.transforms.item().map_or(false, |t| t.is_isomorphic()) {
             input_start.0 += output_start.0 - self.transforms.start().0 .0;
@@ -917,7 +1534,7 @@ impl<'a> Iterator for WrapChunks<'a> {
             }
 
             self.output_position.0 += summary;
-            self.transforms.next(&());
+            self.transforms.next();
             return Some(Chunk {
                 text: &display_text[start_ix..end_ix],
                 ..self.input_chunk.clone()
@@ -929,7 +1546,7 @@ impl<'a> Iterator for WrapChunks<'a> {
         }
 
         let mut input_len = 0;
-        let transform_end = self.transforms.end(&()).0;
+        let transform_end = self.transforms.end().0;
         for c in self.input_chunk.text.chars() {
             let char_len = c.len_utf8();
             input_len += char_len;
@@ -941,7 +1558,7 @@ impl<'a> Iterator for WrapChunks<'a> {
             }
 
             if self.output_position >= transform_end {
-                self.transforms.next(&());
+                self.transforms.next();
                 break;
             }
         }
@@ -1001,6 +1618,7 @@ impl Transform {
                 output: summary,
             },
             display_text: None,
+            approximate: false,
         }
     }
 
@@ -1024,6 +1642,14 @@ impl Transform {
                 },
             },
             display_text: Some(&WRAP_TEXT[..1 + indent as usize]),
+            approximate: false,
+        }
+    }
+
+    fn approximate_wrap() -> Self {
+        Self {
+            approximate: true,
+            ..Self::wrap(0)
         }
     }
 
@@ -1172,11 +1798,11 @@ mod tests {
         test::test_font,
         MultiBuffer,
     };
-    use gpui::{px, test::observe};
+    use gpui::{px, test::observe, FontScript};
     use rand::prelude::*;
     use settings::SettingsStore;
     use smol::stream::StreamExt;
-    use std::{cmp, env, num::NonZeroU32};
+    use std::{cell::RefCell, cmp, env, num::NonZeroU32, rc::Rc};
     use text::Rope;
     use theme::LoadThemes;
 
@@ -1227,8 +1853,13 @@ mod tests {
         log::info!("TabMap text: {:?}", tabs_snapshot.text());
 
         let mut line_wrapper = text_system.line_wrapper(font.clone(), font_size);
+        let mut scale_factor = 1.;
         let unwrapped_text = tabs_snapshot.text();
-        let expected_text = wrap_text(&unwrapped_text, wrap_width, &mut line_wrapper);
+        let expected_text = wrap_text(
+            &unwrapped_text,
+            wrap_width.map(|wrap_width| snap_to_device_pixels(wrap_width, scale_factor)),
+            &mut line_wrapper,
+        );
 
         let (wrap_map, _) =
             cx.update(|cx| WrapMap::new(tabs_snapshot.clone(), font, font_size, wrap_width, cx));
@@ -1267,7 +1898,12 @@ mod tests {
                     log::info!("Setting wrap width to {:?}", wrap_width);
                     wrap_map.update(cx, |map, cx| map.set_wrap_width(wrap_width, cx));
                 }
-                20..=39 => {
+                20..=24 => {
+                    scale_factor = *[1., 1.5, 2.].choose(&mut rng).unwrap();
+                    log::info!("Setting scale factor to {:?}", scale_factor);
+                    wrap_map.update(cx, |map, cx| map.set_scale_factor(scale_factor, cx));
+                }
+                25..=39 => {
                     for (fold_snapshot, fold_edits) in fold_map.randomly_mutate(&mut rng) {
                         let (tabs_snapshot, tab_edits) =
                             tab_map.sync(fold_snapshot, fold_edits, tab_size);
@@ -1311,7 +1947,11 @@ mod tests {
             log::info!("TabMap text: {:?}", tabs_snapshot.text());
 
             let unwrapped_text = tabs_snapshot.text();
-            let expected_text = wrap_text(&unwrapped_text, wrap_width, &mut line_wrapper);
+            let expected_text = wrap_text(
+                &unwrapped_text,
+                wrap_width.map(|wrap_width| snap_to_device_pixels(wrap_width, scale_factor)),
+                &mut line_wrapper,
+            );
             let (mut snapshot, wrap_edits) =
                 wrap_map.update(cx, |map, cx| map.sync(tabs_snapshot.clone(), tab_edits, cx));
             snapshot.check_invariants();
@@ -1415,6 +2055,497 @@ mod tests {
         wrap_map.read_with(cx, |map, _| assert!(map.pending_edits.is_empty()));
     }
 
+    #[gpui::test]
+    fn test_intermediate_snapshot_throttle(cx: &mut gpui::TestAppContext) {
+        let executor = cx.executor();
+        let mut throttle = IntermediateSnapshotThrottle::new(executor.clone());
+        assert!(throttle.ready());
+        assert!(!throttle.ready());
+
+        executor.advance_clock(INTERMEDIATE_SNAPSHOT_INTERVAL / 2);
+        assert!(!throttle.ready());
+        executor.advance_clock(INTERMEDIATE_SNAPSHOT_INTERVAL / 2);
+        assert!(throttle.ready());
+        assert!(!throttle.ready());
+    }
+
+    #[gpui::test]
+    async fn test_line_wrap_budget(cx: &mut gpui::TestAppContext) {
+        init_test(cx);
+
+        let text_system = cx.read(|cx| cx.text_system().clone());
+        let font = test_font();
+        let font_size = px(14.0);
+        let wrap_width = Some(px(72.0));
+        let text = format!("{}\nshort line", "abc def ".repeat(8));
+
+        let buffer = cx.update(|cx| MultiBuffer::build_simple(&text, cx));
+        let buffer_snapshot = buffer.read_with(cx, |buffer, cx| buffer.snapshot(cx));
+        let (_, inlay_snapshot) = InlayMap::new(buffer_snapshot);
+        let (_, fold_snapshot) = FoldMap::new(inlay_snapshot);
+        let (_, tab_snapshot) = TabMap::new(fold_snapshot, NonZeroU32::new(4).unwrap());
+
+        let mut line_wrapper = text_system.line_wrapper(font.clone(), font_size);
+        let columns_per_row = line_wrapper
+            .wrap_line_fixed_width(&"m".repeat(64), wrap_width.unwrap())
+            .next()
+            .unwrap()
+            .ix;
+
+        let (wrap_map, _) =
+            cx.update(|cx| WrapMap::new(tab_snapshot.clone(), font, font_size, wrap_width, cx));
+        let mut notifications = observe(&wrap_map, cx);
+        wrap_map.update(cx, |map, cx| map.set_line_wrap_budget(2, cx));
+        while wrap_map.read_with(cx, |map, _| map.is_rewrapping()) {
+            notifications.next().await.unwrap();
+        }
+
+        let (snapshot, _) =
+            wrap_map.update(cx, |map, cx| map.sync(tab_snapshot.clone(), Vec::new(), cx));
+        let lines = snapshot
+            .text()
+            .lines()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let (long_line_rows, short_line_rows) = lines.split_at(lines.len() - 1);
+        assert_eq!(short_line_rows, ["short line"]);
+        assert!(long_line_rows.len() > 3);
+        for (row, line) in long_line_rows.iter().enumerate() {
+            if row < 2 {
+                // The first rows of the budget are wrapped at word boundaries.
+                assert!(line.ends_with(' '));
+                assert!(!snapshot.is_soft_wrap_approximate(row as u32));
+            } else if row + 1 < long_line_rows.len() {
+                // The rest of the line is chunked at a fixed column, regardless of
+                // word boundaries.
+                assert_eq!(line.len(), columns_per_row);
+                assert!(snapshot.is_soft_wrap_approximate(row as u32));
+            } else {
+                assert!(!snapshot.is_soft_wrap_approximate(row as u32));
+            }
+        }
+    }
+
+    #[gpui::test]
+    async fn test_rewrap_on_script_fallbacks_change(cx: &mut gpui::TestAppContext) {
+        init_test(cx);
+
+        let font = test_font();
+        let font_size = px(14.0);
+        let text = "abc def ghi 日本語 jkl mno pqr";
+
+        let buffer = cx.update(|cx| MultiBuffer::build_simple(text, cx));
+        let buffer_snapshot = buffer.read_with(cx, |buffer, cx| buffer.snapshot(cx));
+        let (_, inlay_snapshot) = InlayMap::new(buffer_snapshot);
+        let (_, fold_snapshot) = FoldMap::new(inlay_snapshot);
+        let (_, tab_snapshot) = TabMap::new(fold_snapshot, NonZeroU32::new(4).unwrap());
+
+        let (wrap_map, _) =
+            cx.update(|cx| WrapMap::new(tab_snapshot.clone(), font, font_size, Some(px(72.0)), cx));
+        let mut notifications = observe(&wrap_map, cx);
+        while wrap_map.read_with(cx, |map, _| map.is_rewrapping()) {
+            notifications.next().await.unwrap();
+        }
+
+        // Syncing after the fallbacks change rewraps the text with the new fallbacks.
+        cx.update(|cx| {
+            cx.text_system().set_script_fallbacks(
+                [(FontScript::Cjk, vec!["Noto Sans CJK".into()])]
+                    .into_iter()
+                    .collect(),
+            )
+        });
+        wrap_map.update(cx, |map, cx| {
+            map.sync(tab_snapshot.clone(), Vec::new(), cx);
+            assert!(map.is_rewrapping());
+        });
+        while wrap_map.read_with(cx, |map, _| map.is_rewrapping()) {
+            notifications.next().await.unwrap();
+        }
+
+        // Syncing again doesn't rewrap.
+        wrap_map.update(cx, |map, cx| {
+            map.sync(tab_snapshot.clone(), Vec::new(), cx);
+            assert!(!map.is_rewrapping());
+        });
+    }
+
+    #[gpui::test]
+    async fn test_no_wrap_ranges(cx: &mut gpui::TestAppContext) {
+        init_test(cx);
+
+        let font = test_font();
+        let font_size = px(14.0);
+        let wrap_width = Some(px(72.0));
+        let long_line = "abc def ".repeat(8);
+        let text = format!("{long_line}\n| {long_line}|\n{long_line}");
+
+        let buffer = cx.update(|cx| MultiBuffer::build_simple(&text, cx));
+        let buffer_snapshot = buffer.read_with(cx, |buffer, cx| buffer.snapshot(cx));
+        let (_, inlay_snapshot) = InlayMap::new(buffer_snapshot.clone());
+        let (_, fold_snapshot) = FoldMap::new(inlay_snapshot);
+        let (_, tab_snapshot) = TabMap::new(fold_snapshot, NonZeroU32::new(4).unwrap());
+
+        let (wrap_map, _) =
+            cx.update(|cx| WrapMap::new(tab_snapshot.clone(), font, font_size, wrap_width, cx));
+        let mut notifications = observe(&wrap_map, cx);
+        while wrap_map.read_with(cx, |map, _| map.is_rewrapping()) {
+            notifications.next().await.unwrap();
+        }
+        let (snapshot, _) =
+            wrap_map.update(cx, |map, cx| map.sync(tab_snapshot.clone(), Vec::new(), cx));
+        let rows_per_line = snapshot.tab_point_to_wrap_point(TabPoint::new(1, 0)).row();
+        assert!(rows_per_line > 1);
+
+        // Only the line of the no-wrap range overflows.
+        let table_start = long_line.len() + 1;
+        let table_end = table_start + long_line.len() + 3;
+        let range =
+            buffer_snapshot.anchor_after(table_start)..buffer_snapshot.anchor_before(table_end);
+        let rewrapped = wrap_map.update(cx, |map, cx| {
+            map.set_no_wrap_ranges(Arc::from([range.clone()]), cx)
+        });
+        assert!(rewrapped);
+        while wrap_map.read_with(cx, |map, _| map.is_rewrapping()) {
+            notifications.next().await.unwrap();
+        }
+        let (snapshot, _) =
+            wrap_map.update(cx, |map, cx| map.sync(tab_snapshot.clone(), Vec::new(), cx));
+        let lines = snapshot
+            .text()
+            .lines()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len() as u32, 2 * rows_per_line + 1);
+        assert_eq!(lines[rows_per_line as usize], format!("| {long_line}|"));
+
+        // Setting ranges covering the same rows doesn't rewrap.
+        let rewrapped =
+            wrap_map.update(cx, |map, cx| map.set_no_wrap_ranges(Arc::from([range]), cx));
+        assert!(!rewrapped);
+    }
+
+    #[gpui::test]
+    async fn test_wrap_panic_recovery(cx: &mut gpui::TestAppContext) {
+        init_test(cx);
+
+        let text_system = cx.read(|cx| cx.text_system().clone());
+        let font = test_font();
+        let font_size = px(14.0);
+        let wrap_width = px(72.0);
+        let text = "abc def ".repeat(8);
+
+        let buffer = cx.update(|cx| MultiBuffer::build_simple(&text, cx));
+        let buffer_snapshot = buffer.read_with(cx, |buffer, cx| buffer.snapshot(cx));
+        let (_, inlay_snapshot) = InlayMap::new(buffer_snapshot);
+        let (_, fold_snapshot) = FoldMap::new(inlay_snapshot);
+        let (_, tab_snapshot) = TabMap::new(fold_snapshot, NonZeroU32::new(4).unwrap());
+
+        let recoveries = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let recoveries = recoveries.clone();
+            cx.on_task_panic(move |_, recovery, _| recoveries.borrow_mut().push(recovery));
+        });
+
+        let (wrap_map, _) =
+            cx.update(|cx| WrapMap::new(tab_snapshot.clone(), font.clone(), font_size, None, cx));
+        let mut notifications = observe(&wrap_map, cx);
+
+        // A wrap that panicked is restarted.
+        wrap_map.update(cx, |map, cx| {
+            map.injected_panics = 1;
+            map.set_wrap_width(Some(wrap_width), cx);
+        });
+        while wrap_map.read_with(cx, |map, _| map.is_rewrapping()) {
+            notifications.next().await.unwrap();
+        }
+        assert_eq!(*recoveries.borrow(), [TaskRecovery::Restarted]);
+        let mut line_wrapper = text_system.line_wrapper(font, font_size);
+        let expected_text = wrap_text(&tab_snapshot.text(), Some(wrap_width), &mut line_wrapper);
+        let (snapshot, _) =
+            wrap_map.update(cx, |map, cx| map.sync(tab_snapshot.clone(), Vec::new(), cx));
+        assert_eq!(snapshot.text(), expected_text);
+
+        // Once it panicked too many times in a row, lines are left unwrapped.
+        wrap_map.update(cx, |map, cx| {
+            map.injected_panics = TaskSupervisor::MAX_RESTARTS + 1;
+            map.set_wrap_width(Some(px(144.0)), cx);
+        });
+        while wrap_map.read_with(cx, |map, _| map.is_rewrapping()) {
+            notifications.next().await.unwrap();
+        }
+        assert_eq!(recoveries.borrow().len(), TaskSupervisor::MAX_RESTARTS + 2);
+        assert_eq!(recoveries.borrow().last(), Some(&TaskRecovery::Degraded));
+        let (snapshot, _) =
+            wrap_map.update(cx, |map, cx| map.sync(tab_snapshot.clone(), Vec::new(), cx));
+        assert_eq!(snapshot.text(), tab_snapshot.text());
+
+        // Changing the wrap width wraps lines again.
+        wrap_map.update(cx, |map, cx| {
+            map.set_wrap_width(Some(wrap_width), cx);
+        });
+        while wrap_map.read_with(cx, |map, _| map.is_rewrapping()) {
+            notifications.next().await.unwrap();
+        }
+        let (snapshot, _) =
+            wrap_map.update(cx, |map, cx| map.sync(tab_snapshot.clone(), Vec::new(), cx));
+        assert_eq!(snapshot.text(), expected_text);
+    }
+
+    #[gpui::test]
+    async fn test_priority_wrapping(cx: &mut gpui::TestAppContext) {
+        init_test(cx);
+
+        let text_system = cx.read(|cx| cx.text_system().clone());
+        let font = test_font();
+        let font_size = px(14.0);
+        let wrap_width = px(72.0);
+        let line = "abc def ghi jkl mno pqr";
+        let text = vec![line; PRIORITY_WRAP_MIN_ROWS as usize * 2].join("\n");
+
+        let buffer = cx.update(|cx| MultiBuffer::build_simple(&text, cx));
+        let buffer_snapshot = buffer.read_with(cx, |buffer, cx| buffer.snapshot(cx));
+        let (_, inlay_snapshot) = InlayMap::new(buffer_snapshot);
+        let (_, fold_snapshot) = FoldMap::new(inlay_snapshot);
+        let (_, tab_snapshot) = TabMap::new(fold_snapshot, NonZeroU32::new(4).unwrap());
+
+        let mut line_wrapper = text_system.line_wrapper(font, font_size);
+        let expected_text = wrap_text(&tab_snapshot.text(), Some(wrap_width), &mut line_wrapper);
+        let wrapped_line = wrap_text(line, Some(wrap_width), &mut line_wrapper);
+        assert_ne!(wrapped_line, line);
+
+        let range = TabPoint::zero()..tab_snapshot.max_point();
+        let mut snapshot = WrapSnapshot::new(tab_snapshot.clone());
+        let mut intermediate_snapshot = None;
+        let edits = snapshot
+            .update_prioritized(
+                tab_snapshot,
+                &[TabEdit {
+                    old: range.clone(),
+                    new: range,
+                }],
+                wrap_width,
+                DEFAULT_LINE_WRAP_BUDGET,
+                Some(500..510),
+                &mut line_wrapper,
+                &mut |snapshot, _| intermediate_snapshot = Some(snapshot.clone()),
+            )
+            .await;
+
+        // The intermediate snapshot only wraps the priority rows.
+        let intermediate_snapshot = intermediate_snapshot.unwrap();
+        let intermediate_text = intermediate_snapshot.text();
+        let intermediate_lines = intermediate_text.split('\n').collect::<Vec<_>>();
+        assert_eq!(intermediate_lines[0], line);
+        assert_eq!(intermediate_lines[499], line);
+        assert_eq!(
+            intermediate_lines[500..]
+                .iter()
+                .take(wrapped_line.lines().count())
+                .copied()
+                .collect::<Vec<_>>(),
+            wrapped_line.lines().collect::<Vec<_>>()
+        );
+
+        assert_eq!(snapshot.text(), expected_text);
+        assert_eq!(
+            edits.into_inner().last().unwrap().new.end,
+            snapshot.max_point().row() + 1
+        );
+    }
+
+    #[gpui::test]
+    async fn test_snapshot_at_least(cx: &mut gpui::TestAppContext) {
+        init_test(cx);
+
+        cx.background_executor.set_block_on_ticks(0..=0);
+        let text_system = cx.read(|cx| cx.text_system().clone());
+        let font = test_font();
+        let font_size = px(14.0);
+        let wrap_width = Some(px(72.0));
+        let text = vec!["abc def ghi jkl mno pqr"; 64].join("\n");
+
+        let buffer = cx.update(|cx| MultiBuffer::build_simple(&text, cx));
+        let buffer_snapshot = buffer.read_with(cx, |buffer, cx| buffer.snapshot(cx));
+        let (_, inlay_snapshot) = InlayMap::new(buffer_snapshot);
+        let (_, fold_snapshot) = FoldMap::new(inlay_snapshot);
+        let (_, tab_snapshot) = TabMap::new(fold_snapshot, NonZeroU32::new(4).unwrap());
+
+        let mut line_wrapper = text_system.line_wrapper(font.clone(), font_size);
+        let expected_text = wrap_text(&tab_snapshot.text(), wrap_width, &mut line_wrapper);
+
+        let (wrap_map, _) =
+            cx.update(|cx| WrapMap::new(tab_snapshot.clone(), font, font_size, wrap_width, cx));
+        let snapshot = wrap_map
+            .update(cx, |map, _| map.snapshot_at_least(tab_snapshot.version))
+            .await
+            .unwrap();
+        assert_eq!(snapshot.version(), tab_snapshot.version);
+        assert!(!snapshot.interpolated);
+        assert_eq!(snapshot.text(), expected_text);
+        wrap_map.read_with(cx, |map, _| assert!(!map.is_rewrapping()));
+
+        // Versions that were already wrapped resolve immediately.
+        let snapshot = wrap_map
+            .update(cx, |map, _| map.snapshot_at_least(0))
+            .await
+            .unwrap();
+        assert_eq!(snapshot.text(), expected_text);
+
+        // Waiters for versions that aren't reached yet are pruned once their future
+        // is dropped.
+        for version in tab_snapshot.version + 1..tab_snapshot.version + 4 {
+            drop(wrap_map.update(cx, |map, _| map.snapshot_at_least(version)));
+        }
+        let pending_snapshot =
+            wrap_map.update(cx, |map, _| map.snapshot_at_least(tab_snapshot.version + 1));
+        wrap_map.read_with(cx, |map, _| assert_eq!(map.snapshot_waiters.len(), 1));
+        drop(pending_snapshot);
+    }
+
+    #[gpui::test]
+    async fn test_snapshot_epochs(cx: &mut gpui::TestAppContext) {
+        init_test(cx);
+
+        let font = test_font();
+        let font_size = px(14.0);
+        let text = vec!["abc def ghi jkl mno pqr"; 64].join("\n");
+
+        let buffer = cx.update(|cx| MultiBuffer::build_simple(&text, cx));
+        let buffer_snapshot = buffer.read_with(cx, |buffer, cx| buffer.snapshot(cx));
+        let (_, inlay_snapshot) = InlayMap::new(buffer_snapshot);
+        let (_, fold_snapshot) = FoldMap::new(inlay_snapshot);
+        let (_, tab_snapshot) = TabMap::new(fold_snapshot, NonZeroU32::new(4).unwrap());
+
+        let (wrap_map, initial_snapshot) =
+            cx.update(|cx| WrapMap::new(tab_snapshot.clone(), font, font_size, None, cx));
+        let mut notifications = observe(&wrap_map, cx);
+        wrap_map.update(cx, |map, cx| map.set_wrap_width(Some(px(72.0)), cx));
+        while wrap_map.read_with(cx, |map, _| map.is_rewrapping()) {
+            notifications.next().await.unwrap();
+        }
+
+        // Publishing the wrapped snapshot starts a new epoch.
+        let (wrapped_snapshot, _) =
+            wrap_map.update(cx, |map, cx| map.sync(tab_snapshot.clone(), Vec::new(), cx));
+        assert!(wrapped_snapshot.epoch() > initial_snapshot.epoch());
+        assert_eq!(
+            wrap_map.read_with(cx, |map, _| map.epoch()),
+            wrapped_snapshot.epoch()
+        );
+
+        // Syncing without changes doesn't publish anything.
+        let (snapshot, _) =
+            wrap_map.update(cx, |map, cx| map.sync(tab_snapshot.clone(), Vec::new(), cx));
+        assert_eq!(snapshot.epoch(), wrapped_snapshot.epoch());
+
+        // Readers holding on to an older snapshot can tell that it is stale.
+        wrap_map.update(cx, |map, cx| map.set_wrap_width(None, cx));
+        assert!(wrap_map.read_with(cx, |map, _| map.epoch()) > wrapped_snapshot.epoch());
+    }
+
+    #[gpui::test]
+    async fn test_paused_wrapping(cx: &mut gpui::TestAppContext) {
+        init_test(cx);
+
+        let text_system = cx.read(|cx| cx.text_system().clone());
+        let font = test_font();
+        let font_size = px(14.0);
+        let text = vec!["abc def ghi jkl mno pqr"; 8].join("\n");
+
+        let buffer = cx.update(|cx| MultiBuffer::build_simple(&text, cx));
+        let buffer_snapshot = buffer.read_with(cx, |buffer, cx| buffer.snapshot(cx));
+        let (_, inlay_snapshot) = InlayMap::new(buffer_snapshot);
+        let (_, fold_snapshot) = FoldMap::new(inlay_snapshot);
+        let (_, tab_snapshot) = TabMap::new(fold_snapshot, NonZeroU32::new(4).unwrap());
+
+        let mut line_wrapper = text_system.line_wrapper(font.clone(), font_size);
+        let narrow_text = wrap_text(&tab_snapshot.text(), Some(px(72.0)), &mut line_wrapper);
+        let wide_text = wrap_text(&tab_snapshot.text(), Some(px(144.0)), &mut line_wrapper);
+        assert_ne!(narrow_text, wide_text);
+
+        let (wrap_map, _) =
+            cx.update(|cx| WrapMap::new(tab_snapshot.clone(), font, font_size, Some(px(72.0)), cx));
+        let wrapped_text = |cx: &mut gpui::TestAppContext| {
+            cx.run_until_parked();
+            wrap_map.update(cx, |map, cx| {
+                map.sync(tab_snapshot.clone(), Vec::new(), cx).0.text()
+            })
+        };
+        assert_eq!(wrapped_text(cx), narrow_text);
+
+        // Rewraps wait for the map to be resumed.
+        wrap_map.update(cx, |map, cx| {
+            map.set_paused(true, cx);
+            map.set_wrap_width(Some(px(144.0)), cx);
+        });
+        wrap_map.read_with(cx, |map, _| assert!(!map.is_rewrapping()));
+        assert_eq!(wrapped_text(cx), narrow_text);
+
+        wrap_map.update(cx, |map, cx| map.set_paused(false, cx));
+        assert_eq!(wrapped_text(cx), wide_text);
+    }
+
+    #[gpui::test]
+    async fn test_tabs_at_wrap_boundaries(cx: &mut gpui::TestAppContext) {
+        init_test(cx);
+
+        let font = test_font();
+        let font_size = px(14.0);
+        let text = "a\t\tb\t\t\tc\n\t\t\t\td\te\t\tf\n\t\t\t\t\t\t";
+
+        let buffer = cx.update(|cx| MultiBuffer::build_simple(text, cx));
+        let buffer_snapshot = buffer.read_with(cx, |buffer, cx| buffer.snapshot(cx));
+        let (_, inlay_snapshot) = InlayMap::new(buffer_snapshot);
+        let (_, fold_snapshot) = FoldMap::new(inlay_snapshot);
+        let (_, tab_snapshot) = TabMap::new(fold_snapshot, NonZeroU32::new(4).unwrap());
+
+        let (wrap_map, _) =
+            cx.update(|cx| WrapMap::new(tab_snapshot.clone(), font, font_size, Some(px(40.0)), cx));
+        let mut notifications = observe(&wrap_map, cx);
+        while wrap_map.read_with(cx, |map, _| map.is_rewrapping()) {
+            notifications.next().await.unwrap();
+        }
+        let (snapshot, _) =
+            wrap_map.update(cx, |map, cx| map.sync(tab_snapshot.clone(), Vec::new(), cx));
+
+        // Tab points round-trip through wrap points once clipped to a tab boundary.
+        for row in 0..=tab_snapshot.max_point().row() {
+            for column in 0..=tab_snapshot.line_len(row) {
+                let tab_point = TabPoint::new(row, column);
+                for bias in [Bias::Left, Bias::Right] {
+                    let clipped_tab_point = tab_snapshot.clip_point(tab_point, bias);
+                    let wrap_point = snapshot.from_tab_point(tab_point, bias);
+                    assert_eq!(
+                        snapshot.to_tab_point(wrap_point),
+                        clipped_tab_point,
+                        "{tab_point:?}, {bias:?}"
+                    );
+                    assert_eq!(
+                        snapshot.clip_point(wrap_point, bias),
+                        wrap_point,
+                        "{tab_point:?}, {bias:?}"
+                    );
+                }
+            }
+        }
+
+        // Clipped wrap points never land in the middle of a tab, even when a soft wrap splits it.
+        for row in 0..=snapshot.max_point().row() {
+            for column in 0..=snapshot.line_len(row) {
+                let wrap_point = WrapPoint::new(row, column);
+                for bias in [Bias::Left, Bias::Right] {
+                    let tab_point = snapshot.to_tab_point(snapshot.clip_point(wrap_point, bias));
+                    assert_eq!(
+                        tab_snapshot.clip_point(tab_point, bias),
+                        tab_point,
+                        "{wrap_point:?}, {bias:?}"
+                    );
+                }
+            }
+        }
+    }
+
     fn init_test(cx: &mut gpui::TestAppContext) {
         cx.update(|cx| {
             let settings = SettingsStore::test(cx);
@@ -0,0 +1,251 @@
+//! Recent edit locations, to jump back to where text was last typed.
+//!
+//! The locations are kept across buffers, in the order of the edits, as anchors, so that they
+//! follow later edits, undo and redo included. An edit close to a kept location replaces it
+//! rather than adding another one: within a few rows of it, and in the same innermost function
+//! according to the text objects of the buffer's language. Cycling through the locations skips
+//! the ones at the cursor, and editing again starts over from the most recent location.
+
+use crate::{
+    actions::{GoToLastEdit, GoToNextEdit, GoToPreviousEdit},
+    Editor, EditorMode,
+};
+use gpui::{App, Context, Entity, Global, WeakEntity, Window};
+use language::{
+    Buffer, BufferSnapshot, TextObject, ToOffset as _, ToPoint as _, TreeSitterOptions,
+};
+use std::{collections::VecDeque, ops::Range};
+
+/// How many edit locations are kept. The oldest ones are forgotten first.
+const MAX_EDIT_LOCATIONS: usize = 64;
+
+/// How many rows apart edits may be to count as the same location.
+const EDIT_LOCATION_PROXIMITY_ROWS: u32 = 4;
+
+#[derive(Clone)]
+struct EditLocation {
+    buffer: WeakEntity<Buffer>,
+    anchor: text::Anchor,
+}
+
+#[derive(Default)]
+struct EditLocations {
+    /// The locations, from the oldest to the most recent.
+    locations: VecDeque<EditLocation>,
+    /// The location last jumped to, while cycling through them.
+    cursor: Option<usize>,
+}
+
+impl Global for EditLocations {}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Older,
+    Newer,
+}
+
+impl Editor {
+    /// Records the position of the newest cursor as the most recent edit location.
+    pub(crate) fn record_edit_location(&self, cx: &mut App) {
+        if self.mode != EditorMode::Full {
+            return;
+        }
+        let Some((buffer, offset)) = self.newest_buffer_position(cx) else {
+            return;
+        };
+
+        let snapshot = buffer.read(cx).snapshot();
+        let anchor = snapshot.anchor_after(offset);
+        let edit_locations = cx.default_global::<EditLocations>();
+        edit_locations.cursor = None;
+        edit_locations.locations.retain(|location| {
+            location.buffer.entity_id() != buffer.entity_id()
+                || !is_same_location(&snapshot, location.anchor.to_offset(&snapshot), offset)
+        });
+        edit_locations.locations.push_back(EditLocation {
+            buffer: buffer.downgrade(),
+            anchor,
+        });
+        if edit_locations.locations.len() > MAX_EDIT_LOCATIONS {
+            edit_locations.locations.pop_front();
+        }
+    }
+
+    pub fn go_to_last_edit(
+        &mut self,
+        _: &GoToLastEdit,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let edit_locations = cx.default_global::<EditLocations>();
+        edit_locations
+            .locations
+            .retain(|location| location.buffer.upgrade().is_some());
+        let Some(ix) = edit_locations.locations.len().checked_sub(1) else {
+            return;
+        };
+        edit_locations.cursor = Some(ix);
+        let location = edit_locations.locations[ix].clone();
+        self.jump_to_edit_location(location, window, cx);
+    }
+
+    pub fn go_to_previous_edit(
+        &mut self,
+        _: &GoToPreviousEdit,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.cycle_edit_locations(Direction::Older, window, cx);
+    }
+
+    pub fn go_to_next_edit(
+        &mut self,
+        _: &GoToNextEdit,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.cycle_edit_locations(Direction::Newer, window, cx);
+    }
+
+    fn cycle_edit_locations(
+        &mut self,
+        direction: Direction,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let position = self.newest_buffer_position(cx);
+        let edit_locations = cx.default_global::<EditLocations>();
+        edit_locations
+            .locations
+            .retain(|location| location.buffer.upgrade().is_some());
+        let locations = edit_locations.locations.clone();
+        let mut ix = edit_locations.cursor.unwrap_or(locations.len());
+
+        let location = loop {
+            ix = match direction {
+                Direction::Older => match ix.checked_sub(1) {
+                    Some(ix) => ix,
+                    None => return,
+                },
+                Direction::Newer if ix + 1 < locations.len() => ix + 1,
+                Direction::Newer => return,
+            };
+            let location = &locations[ix];
+            let is_at_cursor = position.as_ref().map_or(false, |(buffer, offset)| {
+                let Some(location_buffer) = location.buffer.upgrade() else {
+                    return false;
+                };
+                if location_buffer != *buffer {
+                    return false;
+                }
+                let snapshot = buffer.read(cx).snapshot();
+                is_same_location(&snapshot, location.anchor.to_offset(&snapshot), *offset)
+            });
+            if !is_at_cursor {
+                break location.clone();
+            }
+        };
+
+        cx.default_global::<EditLocations>().cursor = Some(ix);
+        self.jump_to_edit_location(location, window, cx);
+    }
+
+    /// The buffer containing the newest cursor, and its offset in it.
+    fn newest_buffer_position(&self, cx: &App) -> Option<(Entity<Buffer>, usize)> {
+        let head = self.selections.newest_anchor().head();
+        let buffer = self.buffer.read(cx).buffer(head.buffer_id?)?;
+        let offset = head.text_anchor.to_offset(&buffer.read(cx).snapshot());
+        Some((buffer, offset))
+    }
+
+    fn jump_to_edit_location(
+        &mut self,
+        location: EditLocation,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(buffer) = location.buffer.upgrade() else {
+            return;
+        };
+        let point = location.anchor.to_point(&buffer.read(cx).snapshot());
+        if self.buffer.read(cx).as_singleton().as_ref() == Some(&buffer) {
+            self.go_to_singleton_buffer_point(point, window, cx);
+            return;
+        }
+
+        let Some(workspace) = self.workspace() else {
+            return;
+        };
+        // Defer opening the buffer, since activating another item calls back into this editor.
+        window.defer(cx, move |window, cx| {
+            workspace.update(cx, |workspace, cx| {
+                // Buffers without a file aren't project items, so look for an editor showing them.
+                let existing_editor = workspace.panes().iter().find_map(|pane| {
+                    pane.read(cx).items().enumerate().find_map(|(ix, item)| {
+                        let editor = item.downcast::<Editor>()?;
+                        let shows_buffer =
+                            editor.read(cx).buffer().read(cx).as_singleton()? == buffer;
+                        shows_buffer.then(|| (pane.clone(), ix, editor))
+                    })
+                });
+                let editor = if let Some((pane, ix, editor)) = existing_editor {
+                    pane.update(cx, |pane, cx| {
+                        pane.activate_item(ix, true, true, window, cx)
+                    });
+                    editor
+                } else if buffer.read(cx).file().is_some() {
+                    let pane = workspace.active_pane().clone();
+                    workspace.open_project_item::<Editor>(pane, buffer, true, true, window, cx)
+                } else {
+                    return;
+                };
+                editor.update(cx, |editor, cx| {
+                    editor.go_to_singleton_buffer_point(point, window, cx)
+                });
+            })
+        });
+    }
+}
+
+/// Whether edits at the two offsets are at the same location: close to each other, and in the
+/// same innermost function.
+fn is_same_location(buffer: &BufferSnapshot, a: usize, b: usize) -> bool {
+    let a_row = buffer.offset_to_point(a).row;
+    let b_row = buffer.offset_to_point(b).row;
+    a_row.abs_diff(b_row) <= EDIT_LOCATION_PROXIMITY_ROWS
+        && innermost_function(buffer, a) == innermost_function(buffer, b)
+}
+
+fn innermost_function(buffer: &BufferSnapshot, offset: usize) -> Option<Range<usize>> {
+    buffer
+        .text_object_ranges(offset..offset, TreeSitterOptions::default())
+        .filter(|(range, object)| {
+            *object == TextObject::AroundFunction && range.start <= offset && offset <= range.end
+        })
+        .map(|(range, _)| range)
+        .min_by_key(|range| range.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::editor_lsp_test_context::rust_lang;
+    use gpui::{AppContext as _, TestAppContext};
+
+    #[gpui::test]
+    fn test_is_same_location(cx: &mut TestAppContext) {
+        let text = "fn a() {\n    b();\n}\nfn c() {\n    d();\n}\n\n\n\n\n\n\nfn e() {}\n";
+        let buffer = cx.new(|cx| Buffer::local(text, cx).with_language(rust_lang(), cx));
+        cx.run_until_parked();
+        let snapshot = buffer.read_with(cx, |buffer, _| buffer.snapshot());
+        let offset = |needle| text.find(needle).unwrap();
+
+        assert!(is_same_location(&snapshot, offset("fn a"), offset("b()")));
+        // Nearby edits in different functions are different locations.
+        assert!(!is_same_location(&snapshot, offset("b()"), offset("d()")));
+        // Outside of functions, only the distance between the edits matters.
+        let blank_row = offset("\n\n\n") + 1;
+        assert!(is_same_location(&snapshot, blank_row, blank_row + 2));
+        assert!(!is_same_location(&snapshot, 0, text.len()));
+    }
+}
@@ -14,13 +14,20 @@
 //! If you're looking to improve Vim mode, you should check out Vim crate that wraps Editor and overrides its behavior.
 pub mod actions;
 mod blink_manager;
+mod bookmarks;
 mod clangd_ext;
+mod clipboard_formats;
 mod code_context_menus;
 pub mod commit_tooltip;
+mod delimiter_repair;
+mod diagnostic_details;
 pub mod display_map;
+mod edit_locations;
 mod editor_settings;
 mod editor_settings_controls;
 mod element;
+mod export;
+mod focus_mode;
 mod git;
 mod highlight_matching_bracket;
 mod hover_links;
@@ -28,16 +35,31 @@ mod hover_popover;
 mod indent_guides;
 mod inlay_hint_cache;
 pub mod items;
+mod key_repeat;
 mod linked_editing_ranges;
 mod lsp_ext;
+mod merge_conflicts;
 mod mouse_context_menu;
 pub mod movement;
+mod paragraph_spacing;
 mod persistence;
 mod proposed_changes_editor;
+mod rainbow_brackets;
 mod rust_analyzer_ext;
 pub mod scroll;
+mod selection_evaluation;
 mod selections_collection;
+mod soft_wrap_exclusions;
+mod string_literals;
+mod syntax_fallback;
 pub mod tasks;
+mod text_objects;
+mod type_ahead;
+mod unchanged_lines;
+mod virtual_documents;
+mod word_diff_highlights;
+mod workspace_edit_preview;
+mod wrap_width;
 
 #[cfg(test)]
 mod editor_tests;
@@ -60,7 +82,8 @@ use convert_case::{Case, Casing};
 use display_map::*;
 pub use display_map::{DisplayPoint, FoldPlaceholder};
 pub use editor_settings::{
-    CurrentLineHighlight, EditorSettings, ScrollBeyondLastLine, SearchSettings, ShowScrollbar,
+    CurrentLineHighlight, DiagnosticSeverityFilter, EditorSettings, PasteFiles,
+    ScrollBeyondLastLine, SearchSettings, ShowScrollbar,
 };
 pub use editor_settings_controls::*;
 use element::{AcceptEditPredictionBinding, LineWithInvisibles, PositionMap};
@@ -93,22 +116,27 @@ pub use inline_completion::Direction;
 use inline_completion::{EditPredictionProvider, InlineCompletionProviderHandle};
 pub use items::MAX_TAB_TITLE_LEN;
 use itertools::Itertools;
+use key_repeat::{KeyRepeatState, RepeatedMotion};
 use language::{
     language_settings::{self, all_language_settings, language_settings, InlayHintSettings},
     markdown, point_from_lsp, AutoindentMode, BracketPair, Buffer, Capability, CharKind, CodeLabel,
     CompletionDocumentation, CursorShape, Diagnostic, DiskState, EditPredictionsMode, EditPreview,
-    HighlightedText, IndentKind, IndentSize, Language, OffsetRangeExt, Point, Selection,
-    SelectionGoal, TextObject, TransactionId, TreeSitterOptions,
+    HighlightedText, IndentKind, IndentSize, Language, LanguageScope, OffsetRangeExt, Point,
+    Selection, SelectionGoal, TextObject, TransactionId, TransactionMetadata, TreeSitterOptions,
 };
 use language::{point_to_lsp, BufferRow, CharClassifier, Runnable, RunnableRange};
 use linked_editing_ranges::refresh_linked_ranges;
 use mouse_context_menu::MouseContextMenu;
+use paragraph_spacing::ParagraphSpacing;
 pub use proposed_changes_editor::{
     ProposedChangeLocation, ProposedChangesEditor, ProposedChangesEditorToolbar,
 };
 use similar::{ChangeTag, TextDiff};
 use std::iter::Peekable;
+use syntax_fallback::{SyntaxFallback, SyntaxHighlighting};
 use task::{ResolvedTask, TaskTemplate, TaskVariables};
+pub use text_objects::{surrounding_object_range, SurroundingObject};
+use type_ahead::TypeAhead;
 
 use hover_links::{find_file, HoverLink, HoveredLinkState, InlayHighlight};
 pub use lsp::CompletionContext;
@@ -128,14 +156,18 @@ use multi_buffer::{
     ToOffsetUtf16,
 };
 use project::{
+    file_patch::ProjectPatch,
     lsp_store::{FormatTrigger, LspFormatTarget, OpenLspBufferHandle},
     project_settings::{GitGutterSetting, ProjectSettings},
     CodeAction, Completion, CompletionIntent, DocumentHighlight, InlayHint, Location, LocationLink,
     LspStore, PrepareRenameResponse, Project, ProjectItem, ProjectTransaction, TaskSourceKind,
 };
+use rainbow_brackets::RainbowBrackets;
 use rand::prelude::*;
 use rpc::{proto::*, ErrorExt};
 use scroll::{Autoscroll, OngoingScroll, ScrollAnchor, ScrollManager, ScrollbarAutoHide};
+use selection_evaluation::SelectionEvaluation;
+pub use selection_evaluation::SelectionEvaluator;
 use selections_collection::{
     resolve_selections, MutableSelectionsCollection, SelectionsCollection,
 };
@@ -143,6 +175,8 @@ use serde::{Deserialize, Serialize};
 use settings::{update_settings_file, Settings, SettingsLocation, SettingsStore};
 use smallvec::SmallVec;
 use snippet::Snippet;
+use soft_wrap_exclusions::SoftWrapExclusions;
+use word_diff_highlights::WordDiffHighlights;
 use std::{
     any::TypeId,
     borrow::Cow,
@@ -165,12 +199,14 @@ use ui::{
     Tooltip,
 };
 use util::{defer, maybe, post_inc, RangeExt, ResultExt, TakeUntilExt, TryFutureExt};
-use workspace::item::{ItemHandle, PreviewTabsSettings};
+use workspace::item::{ItemHandle, PreviewTabsSettings, RevealPosition};
 use workspace::notifications::{DetachAndPromptErr, NotificationId, NotifyTaskExt};
 use workspace::{
-    searchable::SearchEvent, ItemNavHistory, SplitDirection, ViewId, Workspace, WorkspaceId,
+    searchable::SearchEvent, ItemNavHistory, OpenAtOptions, OpenTarget, SplitDirection, ViewId,
+    Workspace, WorkspaceId,
 };
 use workspace::{Item as WorkspaceItem, OpenInTerminal, OpenTerminal, TabBarSettings, Toast};
+use wrap_width::WrapWidthFeedback;
 
 use crate::hover_links::{find_url, find_url_from_range};
 use crate::signature_help::{SignatureHelpHiddenBy, SignatureHelpState};
@@ -189,6 +225,7 @@ pub const CODE_ACTIONS_DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(250);
 
 pub(crate) const FORMAT_TIMEOUT: Duration = Duration::from_secs(2);
 pub(crate) const SCROLL_CENTER_TOP_BOTTOM_DEBOUNCE_TIMEOUT: Duration = Duration::from_secs(1);
+const RANGE_FLASH_DURATION: Duration = Duration::from_millis(600);
 
 pub(crate) const EDIT_PREDICTION_KEY_CONTEXT: &str = "edit_prediction";
 pub(crate) const EDIT_PREDICTION_REQUIRES_MODIFIER_KEY_CONTEXT: &str =
@@ -264,6 +301,7 @@ pub fn render_parsed_markdown(
 pub enum InlayId {
     InlineCompletion(usize),
     Hint(usize),
+    Evaluation(usize),
 }
 
 impl InlayId {
@@ -271,6 +309,7 @@ impl InlayId {
         match self {
             Self::InlineCompletion(id) => *id,
             Self::Hint(id) => *id,
+            Self::Evaluation(id) => *id,
         }
     }
 }
@@ -312,6 +351,7 @@ pub fn init(cx: &mut App) {
             workspace.register_action(Editor::new_file_vertical);
             workspace.register_action(Editor::new_file_horizontal);
             workspace.register_action(Editor::cancel_language_server_work);
+            workspace.register_action(Editor::open_virtual_document_for_action);
         },
     )
     .detach();
@@ -646,6 +686,8 @@ pub struct Editor {
     select_larger_syntax_node_stack: Vec<Box<[Selection<usize>]>>,
     ime_transaction: Option<TransactionId>,
     active_diagnostics: Option<ActiveDiagnosticGroup>,
+    expanded_diagnostic: Option<diagnostic_details::ExpandedDiagnostic>,
+    merge_conflicts: Option<merge_conflicts::MergeConflicts>,
     soft_wrap_mode_override: Option<language_settings::SoftWrap>,
 
     // TODO: make this a access method
@@ -672,6 +714,7 @@ pub struct Editor {
     highlight_order: usize,
     highlighted_rows: HashMap<TypeId, Vec<RowHighlight>>,
     background_highlights: TreeMap<TypeId, BackgroundHighlight>,
+    range_flash_task: Option<Task<()>>,
     gutter_highlights: TreeMap<TypeId, GutterHighlight>,
     scrollbar_marker_state: ScrollbarMarkerState,
     active_indent_guides_state: ActiveIndentGuidesState,
@@ -733,7 +776,28 @@ pub struct Editor {
     show_git_blame_inline: bool,
     show_git_blame_inline_delay_task: Option<Task<()>>,
     distinguish_unstaged_diff_hunks: bool,
+    show_unchanged_line_counts: bool,
     git_blame_inline_enabled: bool,
+    diagnostics_max_severity: DiagnosticSeverityFilter,
+    inline_diagnostics_enabled: bool,
+    /// The settings the two fields above were last set from, which are only reset, discarding
+    /// the toggles of this editor, when the settings change.
+    diagnostics_max_severity_setting: DiagnosticSeverityFilter,
+    inline_diagnostics_setting: bool,
+    rainbow_brackets: Option<RainbowBrackets>,
+    /// The generation of the theme the highlights colored with the theme were computed for.
+    theme_generation: usize,
+    soft_wrap_exclusions: SoftWrapExclusions,
+    word_diff_highlights: WordDiffHighlights,
+    /// Overrides the `focus_mode` setting, once focus mode was toggled in this editor.
+    focus_mode: Option<bool>,
+    focus_mode_task: Option<Task<()>>,
+    selection_evaluation: SelectionEvaluation,
+    paragraph_spacing: ParagraphSpacing,
+    wrap_width_feedback: WrapWidthFeedback,
+    key_repeat: KeyRepeatState,
+    type_ahead: TypeAhead,
+    syntax_fallback: SyntaxFallback,
     serialize_dirty_buffers: bool,
     show_selection_menu: Option<bool>,
     blame: Option<Entity<GitBlame>>,
@@ -800,6 +864,7 @@ pub struct EditorSnapshot {
     ongoing_scroll: OngoingScroll,
     current_line_highlight: CurrentLineHighlight,
     gutter_hovered: bool,
+    syntax_highlighting: SyntaxHighlighting,
 }
 
 const GIT_BLAME_MAX_AUTHOR_CHARS_DISPLAYED: usize = 20;
@@ -958,6 +1023,27 @@ struct SelectNextState {
     query: AhoCorasick,
     wordwise: bool,
     done: bool,
+    /// The occurrences selected by this search, in the order they were added.
+    added_occurrences: Vec<AddedOccurrence>,
+    /// The occurrences that were skipped over, which aren't selected again when
+    /// the search wraps around.
+    skipped_ranges: Vec<Range<Anchor>>,
+    /// Where the search for the next occurrence resumes, i.e. the end of the last
+    /// occurrence that was added. Defaults to the end of the newest selection.
+    search_start: Option<Anchor>,
+}
+
+impl SelectNextState {
+    fn new(query: AhoCorasick, wordwise: bool, done: bool) -> Self {
+        Self {
+            query,
+            wordwise,
+            done,
+            added_occurrences: Vec::new(),
+            skipped_ranges: Vec::new(),
+            search_start: None,
+        }
+    }
 }
 
 impl std::fmt::Debug for SelectNextState {
@@ -965,10 +1051,19 @@ impl std::fmt::Debug for SelectNextState {
         f.debug_struct(std::any::type_name::<Self>())
             .field("wordwise", &self.wordwise)
             .field("done", &self.done)
+            .field("added_occurrences", &self.added_occurrences)
             .finish()
     }
 }
 
+/// A selection added by [`SelectNext`], so that it can be removed again with [`UndoSelectNext`].
+#[derive(Clone, Debug)]
+struct AddedOccurrence {
+    selection_id: usize,
+    /// The selection this one replaced, when it was added by skipping the newest selection.
+    skipped: Option<Selection<Anchor>>,
+}
+
 #[derive(Debug)]
 struct AutocloseRegion {
     selection_id: usize,
@@ -1165,18 +1260,25 @@ impl Editor {
             window,
             cx,
         );
-        self.display_map.update(cx, |display_map, cx| {
-            let snapshot = display_map.snapshot(cx);
-            clone.display_map.update(cx, |display_map, cx| {
-                display_map.set_state(&snapshot, cx);
-            });
-        });
-        clone.selections.clone_state(&self.selections);
-        clone.scroll_manager.clone_state(&self.scroll_manager);
-        clone.searchable = self.searchable;
+        self.clone_state_into(&mut clone, cx);
         clone
     }
 
+    /// Copies the folds, selections and scroll position of this editor into another editor of the
+    /// same buffer, e.g. when splitting a pane. The editors diverge independently afterwards.
+    pub fn clone_state_into(&self, target: &mut Self, cx: &mut App) {
+        let snapshot = self
+            .display_map
+            .update(cx, |display_map, cx| display_map.snapshot(cx));
+        target.display_map.update(cx, |display_map, cx| {
+            display_map.set_state(&snapshot, cx);
+        });
+        target.selections.clone_state(&self.selections);
+        target.scroll_manager.clone_state(&self.scroll_manager);
+        target.soft_wrap_mode_override = self.soft_wrap_mode_override;
+        target.searchable = self.searchable;
+    }
+
     pub fn new(
         mode: EditorMode,
         buffer: Entity<MultiBuffer>,
@@ -1234,7 +1336,13 @@ impl Editor {
                 cx,
             )
         });
+        let diagnostics_max_severity = EditorSettings::get_global(cx).diagnostics_max_severity;
+        display_map.update(cx, |display_map, cx| {
+            display_map.diagnostics_max_severity = diagnostics_max_severity;
+            display_map.set_scale_factor(window.scale_factor(), cx);
+        });
 
+        let wrap_map = display_map.read(cx).wrap_map().clone();
         let selections = SelectionsCollection::new(display_map.clone(), buffer.clone());
 
         let blink_manager = cx.new(|cx| BlinkManager::new(CURSOR_BLINK_INTERVAL, cx));
@@ -1346,6 +1454,8 @@ impl Editor {
             select_larger_syntax_node_stack: Vec::new(),
             ime_transaction: Default::default(),
             active_diagnostics: None,
+            expanded_diagnostic: None,
+            merge_conflicts: None,
             soft_wrap_mode_override,
             completion_provider: project.clone().map(|project| Box::new(project) as _),
             semantics_provider: project.clone().map(|project| Rc::new(project) as _),
@@ -1368,6 +1478,7 @@ impl Editor {
             highlight_order: 0,
             highlighted_rows: HashMap::default(),
             background_highlights: Default::default(),
+            range_flash_task: None,
             gutter_highlights: TreeMap::default(),
             scrollbar_marker_state: ScrollbarMarkerState::default(),
             active_indent_guides_state: ActiveIndentGuidesState::default(),
@@ -1432,9 +1543,26 @@ impl Editor {
             show_git_blame_gutter: false,
             show_git_blame_inline: false,
             distinguish_unstaged_diff_hunks: false,
+            show_unchanged_line_counts: false,
             show_selection_menu: None,
             show_git_blame_inline_delay_task: None,
             git_blame_inline_enabled: ProjectSettings::get_global(cx).git.inline_blame_enabled(),
+            diagnostics_max_severity,
+            inline_diagnostics_enabled: EditorSettings::get_global(cx).inline_diagnostics.enabled,
+            diagnostics_max_severity_setting: diagnostics_max_severity,
+            inline_diagnostics_setting: EditorSettings::get_global(cx).inline_diagnostics.enabled,
+            rainbow_brackets: None,
+            theme_generation: cx.theme_generation(),
+            soft_wrap_exclusions: SoftWrapExclusions::default(),
+            word_diff_highlights: WordDiffHighlights::default(),
+            focus_mode: None,
+            focus_mode_task: None,
+            selection_evaluation: SelectionEvaluation::default(),
+            paragraph_spacing: ParagraphSpacing::default(),
+            wrap_width_feedback: WrapWidthFeedback::default(),
+            key_repeat: KeyRepeatState::default(),
+            type_ahead: TypeAhead::default(),
+            syntax_fallback: SyntaxFallback::default(),
             serialize_dirty_buffers: ProjectSettings::get_global(cx)
                 .session
                 .restore_unsaved_buffers,
@@ -1445,6 +1573,9 @@ impl Editor {
                 cx.observe(&buffer, Self::on_buffer_changed),
                 cx.subscribe_in(&buffer, window, Self::on_buffer_event),
                 cx.observe_in(&display_map, window, Self::on_display_map_changed),
+                cx.subscribe(&wrap_map, |editor, _, event, cx| {
+                    editor.show_wrap_font_warning(event, cx)
+                }),
                 cx.observe(&blink_manager, |_, _, cx| cx.notify()),
                 cx.observe_global_in::<SettingsStore>(window, Self::settings_changed),
                 cx.observe_window_activation(window, |editor, window, cx| {
@@ -1457,6 +1588,12 @@ impl Editor {
                         }
                     });
                 }),
+                cx.observe_window_scale_factor(window, |editor, window, cx| {
+                    editor.display_map.update(cx, |display_map, cx| {
+                        display_map.set_scale_factor(window.scale_factor(), cx)
+                    });
+                    cx.notify();
+                }),
             ],
             tasks_update_task: None,
             linked_edit_ranges: Default::default(),
@@ -1473,6 +1610,9 @@ impl Editor {
             text_style_refinement: None,
         };
         this.tasks_update_task = Some(this.refresh_runnables(window, cx));
+        this.refresh_soft_wrap_exclusions(cx);
+        this.refresh_paragraph_spacing(cx);
+        this.refresh_line_wrap_budget(cx);
         this._subscriptions.extend(project_subscriptions);
 
         this.end_selection(window, cx);
@@ -1742,6 +1882,7 @@ impl Editor {
                 .current_line_highlight
                 .unwrap_or_else(|| EditorSettings::get_global(cx).current_line_highlight),
             gutter_hovered: self.gutter_hovered,
+            syntax_highlighting: self.syntax_fallback.highlighting(),
         }
     }
 
@@ -2138,6 +2279,8 @@ impl Editor {
             }
             self.refresh_code_actions(window, cx);
             self.refresh_document_highlights(cx);
+            self.refresh_focus_mode(cx);
+            self.refresh_selection_evaluation(cx);
             refresh_matching_bracket_highlights(self, window, cx);
             self.update_visible_inline_completion(window, cx);
             self.edit_prediction_requires_modifier_in_leading_space = true;
@@ -2682,6 +2825,10 @@ impl Editor {
             return true;
         }
 
+        if self.collapse_diagnostic_details(cx) {
+            return true;
+        }
+
         false
     }
 
@@ -2743,12 +2890,15 @@ impl Editor {
     }
 
     pub fn handle_input(&mut self, text: &str, window: &mut Window, cx: &mut Context<Self>) {
+        self.flush_repeated_motions(window, cx);
+
         let text: Arc<str> = text.into();
 
         if self.read_only(cx) {
             return;
         }
 
+        self.record_type_ahead(&text, cx);
         let selections = self.selections.all_adjusted(cx);
         let mut bracket_inserted = false;
         let mut edits = Vec::new();
@@ -2761,6 +2911,34 @@ impl Editor {
             self.selections_with_autoclose_regions(selections, &snapshot)
         {
             if let Some(scope) = snapshot.language_scope_at(selection.head()) {
+                // Surround the selected text with the language's surround pair
+                // that is explicitly triggered by the inserted text.
+                if !selection.is_empty()
+                    && self.use_auto_surround
+                    && snapshot.settings_at(selection.start, cx).use_auto_surround
+                {
+                    let surround_pair = scope
+                        .surround_pairs()
+                        .into_iter()
+                        .find(|pair| pair.trigger() == Some(text.as_ref()));
+                    if let Some(pair) = surround_pair {
+                        edits.push((selection.start..selection.start, pair.start.into()));
+                        edits.push((selection.end..selection.end, pair.end.into()));
+                        bracket_inserted = true;
+                        new_selections.push((
+                            Selection {
+                                id: selection.id,
+                                start: snapshot.anchor_after(selection.start),
+                                end: snapshot.anchor_before(selection.end),
+                                reversed: selection.reversed,
+                                goal: selection.goal,
+                            },
+                            0,
+                        ));
+                        continue;
+                    }
+                }
+
                 // Determine if the inserted text matches the opening or closing
                 // bracket of any of this language's bracket pairs.
                 let mut bracket_pair = None;
@@ -2886,7 +3064,15 @@ impl Editor {
                         && is_bracket_pair_start
                         && bracket_pair.start.chars().count() == 1
                     {
-                        edits.push((selection.start..selection.start, text.clone()));
+                        let selected_text = snapshot
+                            .text_for_range(selection.start..selection.end)
+                            .collect::<String>();
+                        let bracket_pair =
+                            quote_pair_for_surrounded_text(&scope, bracket_pair, &selected_text);
+                        edits.push((
+                            selection.start..selection.start,
+                            bracket_pair.start.as_str().into(),
+                        ));
                         edits.push((
                             selection.end..selection.end,
                             bracket_pair.end.as_str().into(),
@@ -3119,6 +3305,11 @@ impl Editor {
     }
 
     pub fn newline(&mut self, _: &Newline, window: &mut Window, cx: &mut Context<Self>) {
+        if matches!(self.mode, EditorMode::SingleLine { .. }) {
+            cx.propagate();
+            return;
+        }
+
         self.transact(window, cx, |this, window, cx| {
             let (edits, selection_fixup_info): (Vec<_>, Vec<_>) = {
                 let selections = this.selections.all::<usize>(cx);
@@ -3260,6 +3451,11 @@ impl Editor {
     }
 
     pub fn newline_above(&mut self, _: &NewlineAbove, window: &mut Window, cx: &mut Context<Self>) {
+        if matches!(self.mode, EditorMode::SingleLine { .. }) {
+            cx.propagate();
+            return;
+        }
+
         let buffer = self.buffer.read(cx);
         let snapshot = buffer.snapshot(cx);
 
@@ -3317,6 +3513,11 @@ impl Editor {
     }
 
     pub fn newline_below(&mut self, _: &NewlineBelow, window: &mut Window, cx: &mut Context<Self>) {
+        if matches!(self.mode, EditorMode::SingleLine { .. }) {
+            cx.propagate();
+            return;
+        }
+
         let buffer = self.buffer.read(cx);
         let snapshot = buffer.snapshot(cx);
 
@@ -3394,7 +3595,11 @@ impl Editor {
             return;
         }
 
-        let text: Arc<str> = text.into();
+        let text: Arc<str> = if matches!(self.mode, EditorMode::SingleLine { .. }) {
+            single_line_text(text).into()
+        } else {
+            text.into()
+        };
         self.transact(window, cx, |this, window, cx| {
             let old_selections = this.selections.all_adjusted(cx);
             let selection_anchors = this.buffer.update(cx, |buffer, cx| {
@@ -3762,10 +3967,14 @@ impl Editor {
                         return None;
                     }
                 }
+                let buffer_entity = multi_buffer.buffer(buffer.remote_id())?;
+                if buffer_entity.read(cx).disabled_features().inlay_hints {
+                    return None;
+                }
                 Some((
                     excerpt_id,
                     (
-                        multi_buffer.buffer(buffer.remote_id()).unwrap(),
+                        buffer_entity,
                         buffer.version().clone(),
                         excerpt_visible_range,
                     ),
@@ -4381,8 +4590,18 @@ impl Editor {
                 let apply_code_action =
                     provider.apply_code_action(buffer, action, excerpt_id, true, window, cx);
                 let workspace = workspace.downgrade();
-                Some(cx.spawn_in(window, |editor, cx| async move {
+                Some(cx.spawn_in(window, |editor, mut cx| async move {
                     let project_transaction = apply_code_action.await?;
+                    cx.update(|_, cx| {
+                        Self::set_transactions_command(
+                            project_transaction
+                                .0
+                                .iter()
+                                .map(|(buffer, transaction)| (buffer.clone(), transaction.id)),
+                            ConfirmCodeAction::debug_name(),
+                            cx,
+                        )
+                    })?;
                     Self::open_project_transaction(
                         &editor,
                         workspace,
@@ -4396,6 +4615,27 @@ impl Editor {
         }
     }
 
+    /// Records the command that made the transactions in the undo history of their buffers,
+    /// keeping the tool that made them on the command's behalf.
+    fn set_transactions_command(
+        transactions: impl IntoIterator<Item = (Entity<Buffer>, TransactionId)>,
+        command: &'static str,
+        cx: &mut App,
+    ) {
+        for (buffer, transaction_id) in transactions {
+            buffer.update(cx, |buffer, _| {
+                let Some(metadata) = buffer.transaction_metadata(transaction_id) else {
+                    return;
+                };
+                let metadata = TransactionMetadata {
+                    command: Some(command.into()),
+                    ..metadata.clone()
+                };
+                buffer.set_transaction_metadata(transaction_id, metadata);
+            });
+        }
+    }
+
     pub async fn open_project_transaction(
         this: &WeakEntity<Editor>,
         workspace: WeakEntity<Workspace>,
@@ -4403,12 +4643,15 @@ impl Editor {
         title: String,
         mut cx: AsyncWindowContext,
     ) -> Result<()> {
-        let mut entries = transaction.0.into_iter().collect::<Vec<_>>();
-        cx.update(|_, cx| {
-            entries.sort_unstable_by_key(|(buffer, _)| {
-                buffer.read(cx).file().map(|f| f.path().clone())
-            });
-        })?;
+        if cx.update(|_, cx| {
+            workspace.upgrade().map_or(false, |workspace| {
+                Self::previews_workspace_edits(workspace.read(cx).project().read(cx), cx)
+            })
+        })? {
+            return Self::preview_project_transaction(workspace, transaction, title, cx).await;
+        }
+
+        let entries = transaction.0.into_iter().collect::<Vec<_>>();
 
         // If the project transaction's edits are all contained within this editor, then
         // avoid opening a new editor to display them.
@@ -4443,6 +4686,32 @@ impl Editor {
             return Ok(());
         }
 
+        workspace.update_in(&mut cx, |workspace, window, cx| {
+            Self::open_project_transaction_in_workspace(
+                workspace,
+                ProjectTransaction(entries.into_iter().collect()),
+                title,
+                window,
+                cx,
+            );
+        })?;
+
+        Ok(())
+    }
+
+    /// Opens the edits of a project transaction in a multibuffer, where they can be
+    /// reviewed and then saved, or undone.
+    pub fn open_project_transaction_in_workspace(
+        workspace: &mut Workspace,
+        transaction: ProjectTransaction,
+        title: String,
+        window: &mut Window,
+        cx: &mut Context<Workspace>,
+    ) {
+        let mut entries = transaction.0.into_iter().collect::<Vec<_>>();
+        entries
+            .sort_unstable_by_key(|(buffer, _)| buffer.read(cx).file().map(|f| f.path().clone()));
+
         let mut ranges_to_highlight = Vec::new();
         let excerpt_buffer = cx.new(|cx| {
             let mut multibuffer = MultiBuffer::new(Capability::ReadWrite).with_title(title);
@@ -4461,23 +4730,19 @@ impl Editor {
             }
             multibuffer.push_transaction(entries.iter().map(|(b, t)| (b, t)), cx);
             multibuffer
-        })?;
-
-        workspace.update_in(&mut cx, |workspace, window, cx| {
-            let project = workspace.project().clone();
-            let editor = cx
-                .new(|cx| Editor::for_multibuffer(excerpt_buffer, Some(project), true, window, cx));
-            workspace.add_item_to_active_pane(Box::new(editor.clone()), None, true, window, cx);
-            editor.update(cx, |editor, cx| {
-                editor.highlight_background::<Self>(
-                    &ranges_to_highlight,
-                    |theme| theme.editor_highlighted_line_background,
-                    cx,
-                );
-            });
-        })?;
+        });
 
-        Ok(())
+        let project = workspace.project().clone();
+        let editor =
+            cx.new(|cx| Editor::for_multibuffer(excerpt_buffer, Some(project), true, window, cx));
+        workspace.add_item_to_active_pane(Box::new(editor.clone()), None, true, window, cx);
+        editor.update(cx, |editor, cx| {
+            editor.highlight_background::<Self>(
+                &ranges_to_highlight,
+                |theme| theme.editor_highlighted_line_background,
+                cx,
+            );
+        });
     }
 
     pub fn clear_code_action_providers(&mut self) {
@@ -6801,6 +7066,18 @@ impl Editor {
         insert_whitespace: bool,
         window: &mut Window,
         cx: &mut Context<Self>,
+    ) {
+        self.join_rows(insert_whitespace, false, window, cx);
+    }
+
+    /// Joins the selected rows with the ones that follow them. Smart joins also remove
+    /// the comment leaders of joined comment lines, and separate joined list items with `, `.
+    fn join_rows(
+        &mut self,
+        insert_whitespace: bool,
+        smart: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
     ) {
         if self.read_only(cx) {
             return;
@@ -6838,17 +7115,47 @@ impl Editor {
         self.transact(window, cx, |this, window, cx| {
             for row_range in row_ranges.into_iter().rev() {
                 for row in row_range.iter_rows().rev() {
-                    let end_of_line = Point::new(row.0, snapshot.line_len(row));
+                    let mut end_of_line = Point::new(row.0, snapshot.line_len(row));
                     let next_line_row = row.next_row();
                     let indent = snapshot.indent_size_for_line(next_line_row);
-                    let start_of_next_line = Point::new(next_line_row.0, indent.len);
+                    let mut start_of_next_line = Point::new(next_line_row.0, indent.len);
+
+                    let mut separator = " ";
+                    if smart {
+                        let trailing_whitespace = snapshot
+                            .reversed_chars_at(end_of_line)
+                            .take_while(|c| *c == ' ' || *c == '\t')
+                            .count();
+                        end_of_line.column -= trailing_whitespace as u32;
+                        if let Some(prefix_len) =
+                            joined_comment_prefix_len(&snapshot, row, next_line_row)
+                        {
+                            start_of_next_line.column += prefix_len;
+                        } else if joins_list_items(&snapshot, end_of_line, start_of_next_line) {
+                            separator = ", ";
+                        }
+                    } else if snapshot.settings_at(end_of_line, cx).repair_delimiters {
+                        let trailing_whitespace = snapshot
+                            .reversed_chars_at(end_of_line)
+                            .take_while(|c| *c == ' ' || *c == '\t')
+                            .count();
+                        let end_of_item =
+                            Point::new(row.0, end_of_line.column - trailing_whitespace as u32);
+                        if joins_list_items(&snapshot, end_of_item, start_of_next_line) {
+                            end_of_line = end_of_item;
+                            separator = ", ";
+                        }
+                    }
 
-                    let replace =
-                        if snapshot.line_len(next_line_row) > indent.len && insert_whitespace {
-                            " "
+                    let replace = if snapshot.line_len(next_line_row) > start_of_next_line.column {
+                        if insert_whitespace {
+                            separator
                         } else {
-                            ""
-                        };
+                            separator.trim_end()
+                        }
+                    } else {
+                        ""
+                    };
 
                     this.buffer.update(cx, |buffer, cx| {
                         buffer.edit([(end_of_line..start_of_next_line, replace)], None, cx)
@@ -6866,6 +7173,15 @@ impl Editor {
         self.join_lines_impl(true, window, cx);
     }
 
+    pub fn smart_join_lines(
+        &mut self,
+        _: &SmartJoinLines,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.join_rows(true, true, window, cx);
+    }
+
     pub fn sort_lines_case_sensitive(
         &mut self,
         _: &SortLinesCaseSensitive,
@@ -7732,8 +8048,10 @@ impl Editor {
                 continue;
             };
 
+            // The wrap column is the one in effect where the line prefix ends, so that
+            // comments are wrapped at the column of their syntax scope.
             let wrap_column = buffer
-                .settings_at(Point::new(start_row, 0), cx)
+                .settings_at(Point::new(start_row, line_prefix.len() as u32), cx)
                 .preferred_line_length as usize;
             let wrapped_text = wrap_with_prefix(
                 line_prefix,
@@ -7830,13 +8148,18 @@ impl Editor {
             }
         }
 
+        let item = self.with_copied_html(
+            ClipboardItem::new_string_with_json_metadata(text, clipboard_selections),
+            selections.iter().map(|selection| selection.range()),
+            cx,
+        );
         self.transact(window, cx, |this, window, cx| {
             this.change_selections(Some(Autoscroll::fit()), window, cx, |s| {
                 s.select(selections);
             });
             this.insert("", window, cx);
         });
-        ClipboardItem::new_string_with_json_metadata(text, clipboard_selections)
+        item
     }
 
     pub fn cut(&mut self, _: &Cut, window: &mut Window, cx: &mut Context<Self>) {
@@ -7880,6 +8203,7 @@ impl Editor {
         let mut text = String::new();
 
         let mut clipboard_selections = Vec::with_capacity(selections.len());
+        let mut copied_ranges = Vec::with_capacity(selections.len());
         {
             let max_point = buffer.max_point();
             let mut is_first = true;
@@ -7891,6 +8215,7 @@ impl Editor {
                     start = Point::new(start.row, 0);
                     end = cmp::min(max_point, Point::new(end.row + 1, 0));
                 }
+                copied_ranges.push(start..end);
                 if is_first {
                     is_first = false;
                 } else {
@@ -7908,18 +8233,44 @@ impl Editor {
                 });
             }
         }
+        drop(buffer);
+
+        let item = self.with_copied_html(
+            ClipboardItem::new_string_with_json_metadata(text, clipboard_selections),
+            copied_ranges,
+            cx,
+        );
+        cx.write_to_clipboard(item);
+    }
 
-        cx.write_to_clipboard(ClipboardItem::new_string_with_json_metadata(
+    pub fn do_paste(
+        &mut self,
+        text: &String,
+        clipboard_selections: Option<Vec<ClipboardSelection>>,
+        handle_entire_lines: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.do_paste_with_reindent(
             text,
             clipboard_selections,
-        ));
+            handle_entire_lines,
+            true,
+            window,
+            cx,
+        );
     }
 
-    pub fn do_paste(
+    /// Pastes `text` into every selection. When `reindent` is true, the pasted
+    /// block is shifted so that its first line matches the indentation of the
+    /// destination while the relative indentation of the remaining lines is
+    /// preserved (subject to the `auto_indent_on_paste` language setting).
+    pub fn do_paste_with_reindent(
         &mut self,
         text: &String,
         clipboard_selections: Option<Vec<ClipboardSelection>>,
         handle_entire_lines: bool,
+        reindent: bool,
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
@@ -7927,7 +8278,14 @@ impl Editor {
             return;
         }
 
-        let clipboard_text = Cow::Borrowed(text);
+        let mut clipboard_text = Cow::Borrowed(text);
+        let mut clipboard_selections = clipboard_selections;
+        if matches!(self.mode, EditorMode::SingleLine { .. }) {
+            // Per-selection slices assume the text's newlines are preserved, so
+            // when they're collapsed the whole text is pasted at every cursor.
+            clipboard_text = Cow::Owned(single_line_text(text).into_owned());
+            clipboard_selections = None;
+        }
 
         self.transact(window, cx, |this, window, cx| {
             if let Some(mut clipboard_selections) = clipboard_selections {
@@ -7940,15 +8298,16 @@ impl Editor {
                     clipboard_selections.drain(..);
                 }
                 let cursor_offset = this.selections.last::<usize>(cx).head();
-                let mut auto_indent_on_paste = true;
+                let mut auto_indent_on_paste = reindent;
 
-                this.buffer.update(cx, |buffer, cx| {
+                let pasted_ranges = this.buffer.update(cx, |buffer, cx| {
                     let snapshot = buffer.read(cx);
-                    auto_indent_on_paste =
-                        snapshot.settings_at(cursor_offset, cx).auto_indent_on_paste;
+                    auto_indent_on_paste = auto_indent_on_paste
+                        && snapshot.settings_at(cursor_offset, cx).auto_indent_on_paste;
 
                     let mut start_offset = 0;
                     let mut edits = Vec::new();
+                    let mut pasted_ranges = Vec::new();
                     let mut original_indent_columns = Vec::new();
                     for (ix, selection) in old_selections.iter().enumerate() {
                         let to_insert;
@@ -7978,6 +8337,9 @@ impl Editor {
                             selection.range()
                         };
 
+                        pasted_ranges.push(
+                            snapshot.anchor_before(range.start)..snapshot.anchor_after(range.end),
+                        );
                         edits.push((range, to_insert));
                         original_indent_columns.extend(original_indent_column);
                     }
@@ -7994,34 +8356,79 @@ impl Editor {
                         },
                         cx,
                     );
+                    pasted_ranges
                 });
+                this.repair_pasted_delimiters(&pasted_ranges, cx);
 
                 let selections = this.selections.all::<usize>(cx);
                 this.change_selections(Some(Autoscroll::fit()), window, cx, |s| {
                     s.select(selections)
                 });
             } else {
-                this.insert(&clipboard_text, window, cx);
+                let snapshot = this.buffer.read(cx).snapshot(cx);
+                let pasted_ranges = this
+                    .selections
+                    .all::<usize>(cx)
+                    .into_iter()
+                    .map(|selection| {
+                        snapshot.anchor_before(selection.start)
+                            ..snapshot.anchor_after(selection.end)
+                    })
+                    .collect::<Vec<_>>();
+                if reindent {
+                    this.insert(&clipboard_text, window, cx);
+                } else {
+                    this.insert_with_autoindent_mode(&clipboard_text, None, window, cx);
+                }
+                this.repair_pasted_delimiters(&pasted_ranges, cx);
             }
         });
     }
 
     pub fn paste(&mut self, _: &Paste, window: &mut Window, cx: &mut Context<Self>) {
+        self.paste_from_clipboard(true, window, cx);
+    }
+
+    pub fn paste_without_reindent(
+        &mut self,
+        _: &PasteWithoutReindent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.paste_from_clipboard(false, window, cx);
+    }
+
+    fn paste_from_clipboard(
+        &mut self,
+        reindent: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
         if let Some(item) = cx.read_from_clipboard() {
-            let entries = item.entries();
+            if let Some(paths) = item.external_paths() {
+                self.paste_external_paths(paths.paths().to_vec(), reindent, window, cx);
+                return;
+            }
 
-            match entries.first() {
+            match item.string() {
                 // For now, we only support applying metadata if there's one string. In the future, we can incorporate all the selections
                 // of all the pasted entries.
-                Some(ClipboardEntry::String(clipboard_string)) if entries.len() == 1 => self
-                    .do_paste(
-                        clipboard_string.text(),
-                        clipboard_string.metadata_json::<Vec<ClipboardSelection>>(),
-                        true,
-                        window,
-                        cx,
-                    ),
-                _ => self.do_paste(&item.text().unwrap_or_default(), None, true, window, cx),
+                Some(clipboard_string) => self.do_paste_with_reindent(
+                    clipboard_string.text(),
+                    clipboard_string.metadata_json::<Vec<ClipboardSelection>>(),
+                    true,
+                    reindent,
+                    window,
+                    cx,
+                ),
+                None => self.do_paste_with_reindent(
+                    &item.text().unwrap_or_default(),
+                    None,
+                    true,
+                    reindent,
+                    window,
+                    cx,
+                ),
             }
         }
     }
@@ -8078,6 +8485,10 @@ impl Editor {
     }
 
     pub fn move_left(&mut self, _: &MoveLeft, window: &mut Window, cx: &mut Context<Self>) {
+        if self.queue_repeated_motion(RepeatedMotion::Left, window, cx) {
+            return;
+        }
+
         self.change_selections(Some(Autoscroll::fit()), window, cx, |s| {
             let line_mode = s.line_mode;
             s.move_with(|map, selection| {
@@ -8092,12 +8503,20 @@ impl Editor {
     }
 
     pub fn select_left(&mut self, _: &SelectLeft, window: &mut Window, cx: &mut Context<Self>) {
+        if self.queue_repeated_motion(RepeatedMotion::SelectLeft, window, cx) {
+            return;
+        }
+
         self.change_selections(Some(Autoscroll::fit()), window, cx, |s| {
             s.move_heads_with(|map, head, _| (movement::left(map, head), SelectionGoal::None));
         })
     }
 
     pub fn move_right(&mut self, _: &MoveRight, window: &mut Window, cx: &mut Context<Self>) {
+        if self.queue_repeated_motion(RepeatedMotion::Right, window, cx) {
+            return;
+        }
+
         self.change_selections(Some(Autoscroll::fit()), window, cx, |s| {
             let line_mode = s.line_mode;
             s.move_with(|map, selection| {
@@ -8112,12 +8531,20 @@ impl Editor {
     }
 
     pub fn select_right(&mut self, _: &SelectRight, window: &mut Window, cx: &mut Context<Self>) {
+        if self.queue_repeated_motion(RepeatedMotion::SelectRight, window, cx) {
+            return;
+        }
+
         self.change_selections(Some(Autoscroll::fit()), window, cx, |s| {
             s.move_heads_with(|map, head, _| (movement::right(map, head), SelectionGoal::None));
         })
     }
 
     pub fn move_up(&mut self, _: &MoveUp, window: &mut Window, cx: &mut Context<Self>) {
+        if self.queue_repeated_motion(RepeatedMotion::Up, window, cx) {
+            return;
+        }
+
         if self.take_rename(true, window, cx).is_some() {
             return;
         }
@@ -8279,6 +8706,13 @@ impl Editor {
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
+        let motion = RepeatedMotion::PageUp {
+            center_cursor: action.center_cursor,
+        };
+        if self.queue_repeated_motion(motion, window, cx) {
+            return;
+        }
+
         if self.take_rename(true, window, cx).is_some() {
             return;
         }
@@ -8330,6 +8764,10 @@ impl Editor {
     }
 
     pub fn select_up(&mut self, _: &SelectUp, window: &mut Window, cx: &mut Context<Self>) {
+        if self.queue_repeated_motion(RepeatedMotion::SelectUp, window, cx) {
+            return;
+        }
+
         let text_layout_details = &self.text_layout_details(window);
         self.change_selections(Some(Autoscroll::fit()), window, cx, |s| {
             s.move_heads_with(|map, head, goal| {
@@ -8339,6 +8777,10 @@ impl Editor {
     }
 
     pub fn move_down(&mut self, _: &MoveDown, window: &mut Window, cx: &mut Context<Self>) {
+        if self.queue_repeated_motion(RepeatedMotion::Down, window, cx) {
+            return;
+        }
+
         self.take_rename(true, window, cx);
 
         if matches!(self.mode, EditorMode::SingleLine { .. }) {
@@ -8398,6 +8840,13 @@ impl Editor {
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
+        let motion = RepeatedMotion::PageDown {
+            center_cursor: action.center_cursor,
+        };
+        if self.queue_repeated_motion(motion, window, cx) {
+            return;
+        }
+
         if self.take_rename(true, window, cx).is_some() {
             return;
         }
@@ -8448,6 +8897,10 @@ impl Editor {
     }
 
     pub fn select_down(&mut self, _: &SelectDown, window: &mut Window, cx: &mut Context<Self>) {
+        if self.queue_repeated_motion(RepeatedMotion::SelectDown, window, cx) {
+            return;
+        }
+
         let text_layout_details = &self.text_layout_details(window);
         self.change_selections(Some(Autoscroll::fit()), window, cx, |s| {
             s.move_heads_with(|map, head, goal| {
@@ -9052,31 +9505,164 @@ impl Editor {
         });
     }
 
-    pub fn add_selection_above(
-        &mut self,
-        _: &AddSelectionAbove,
-        window: &mut Window,
-        cx: &mut Context<Self>,
-    ) {
-        self.add_selection(true, window, cx);
-    }
-
-    pub fn add_selection_below(
+    /// Inserts a newline after each delimiter in the selections that isn't nested in
+    /// brackets, strings or comments. Empty selections split the contents of the
+    /// brackets they are in instead.
+    pub fn split_selection_at_delimiter(
         &mut self,
-        _: &AddSelectionBelow,
+        action: &SplitSelectionAtDelimiter,
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
-        self.add_selection(false, window, cx);
-    }
-
-    fn add_selection(&mut self, above: bool, window: &mut Window, cx: &mut Context<Self>) {
-        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
-        let mut selections = self.selections.all::<Point>(cx);
-        let text_layout_details = self.text_layout_details(window);
-        let mut state = self.add_selections_state.take().unwrap_or_else(|| {
-            let oldest_selection = selections.iter().min_by_key(|s| s.id).unwrap().clone();
-            let range = oldest_selection.display_range(&display_map).sorted();
+        if self.read_only(cx) {
+            return;
+        }
+        let delimiter = action.delimiter.as_deref().unwrap_or(",");
+        if delimiter.is_empty() {
+            return;
+        }
+
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let mut edits = Vec::new();
+        for selection in self.selections.all::<usize>(cx) {
+            let range = if selection.is_empty() {
+                let Some((open, close)) =
+                    snapshot.innermost_enclosing_bracket_ranges(selection.range(), None)
+                else {
+                    continue;
+                };
+                open.end..close.start
+            } else {
+                selection.range()
+            };
+            for offset in top_level_delimiter_offsets(&snapshot, range.clone(), delimiter) {
+                let start = offset + delimiter.len();
+                let whitespace_len = snapshot
+                    .chars_at(start)
+                    .take_while(|c| *c == ' ' || *c == '\t')
+                    .map(char::len_utf8)
+                    .sum::<usize>();
+                let end = cmp::min(start + whitespace_len, range.end);
+                edits.push((start..end, "\n"));
+            }
+        }
+        if edits.is_empty() {
+            return;
+        }
+
+        self.transact(window, cx, |this, _, cx| {
+            this.buffer.update(cx, |buffer, cx| {
+                buffer.edit(edits, Some(AutoindentMode::EachLine), cx)
+            });
+            this.request_autoscroll(Autoscroll::fit(), cx);
+        });
+    }
+
+    /// Surrounds each non-empty selection with the given delimiters.
+    pub fn surround_selections(
+        &mut self,
+        action: &SurroundSelections,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.read_only(cx) || action.start.is_empty() {
+            return;
+        }
+
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let mut edits = Vec::new();
+        let mut new_selections = Vec::new();
+        for selection in self.selections.all::<usize>(cx) {
+            if selection.is_empty() {
+                new_selections.push(selection.map(|offset| snapshot.anchor_before(offset)));
+                continue;
+            }
+            let end = surround_pair_end(&snapshot, selection.start, &action.start, &action.end);
+            edits.push((selection.start..selection.start, action.start.clone()));
+            edits.push((selection.end..selection.end, end));
+            new_selections.push(Selection {
+                id: selection.id,
+                start: snapshot.anchor_after(selection.start),
+                end: snapshot.anchor_before(selection.end),
+                reversed: selection.reversed,
+                goal: selection.goal,
+            });
+        }
+        if edits.is_empty() {
+            return;
+        }
+
+        self.transact(window, cx, |this, window, cx| {
+            this.buffer
+                .update(cx, |buffer, cx| buffer.edit(edits, None, cx));
+            this.change_selections(Some(Autoscroll::fit()), window, cx, |s| {
+                s.select_anchors(new_selections)
+            });
+        });
+    }
+
+    /// Replaces both delimiters of the innermost syntactic pair enclosing each
+    /// selection, like the quotes of a string, with the given ones.
+    pub fn change_surrounding_pair(
+        &mut self,
+        action: &ChangeSurroundingPair,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.read_only(cx) || action.start.is_empty() {
+            return;
+        }
+
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let mut edits = Vec::new();
+        let mut changed_pairs = HashSet::default();
+        for selection in self.selections.all::<usize>(cx) {
+            let Some((open, close)) =
+                snapshot.innermost_enclosing_bracket_ranges(selection.range(), None)
+            else {
+                continue;
+            };
+            if changed_pairs.insert(open.start) {
+                let end = surround_pair_end(&snapshot, open.start, &action.start, &action.end);
+                edits.push((open, action.start.clone()));
+                edits.push((close, end));
+            }
+        }
+        if edits.is_empty() {
+            return;
+        }
+
+        self.transact(window, cx, |this, _, cx| {
+            this.buffer
+                .update(cx, |buffer, cx| buffer.edit(edits, None, cx));
+        });
+    }
+
+    pub fn add_selection_above(
+        &mut self,
+        _: &AddSelectionAbove,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.add_selection(true, window, cx);
+    }
+
+    pub fn add_selection_below(
+        &mut self,
+        _: &AddSelectionBelow,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.add_selection(false, window, cx);
+    }
+
+    fn add_selection(&mut self, above: bool, window: &mut Window, cx: &mut Context<Self>) {
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        let mut selections = self.selections.all::<Point>(cx);
+        let text_layout_details = self.text_layout_details(window);
+        let mut state = self.add_selections_state.take().unwrap_or_else(|| {
+            let oldest_selection = selections.iter().min_by_key(|s| s.id).unwrap().clone();
+            let range = oldest_selection.display_range(&display_map).sorted();
 
             let start_x = display_map.x_for_display_point(range.start, &text_layout_details);
             let end_x = display_map.x_for_display_point(range.end, &text_layout_details);
@@ -9203,19 +9789,25 @@ impl Editor {
         if let Some(mut select_next_state) = self.select_next_state.take() {
             let query = &select_next_state.query;
             if !select_next_state.done {
-                let first_selection = selections.iter().min_by_key(|s| s.id).unwrap();
                 let last_selection = selections.iter().max_by_key(|s| s.id).unwrap();
+                let search_start = select_next_state
+                    .search_start
+                    .map_or(last_selection.end, |start| start.to_offset(buffer));
+                let skipped_ranges = select_next_state
+                    .skipped_ranges
+                    .iter()
+                    .map(|range| range.start.to_offset(buffer)..range.end.to_offset(buffer))
+                    .collect::<Vec<_>>();
                 let mut next_selected_range = None;
 
-                let bytes_after_last_selection =
-                    buffer.bytes_in_range(last_selection.end..buffer.len());
-                let bytes_before_first_selection = buffer.bytes_in_range(0..first_selection.start);
+                let bytes_after_search_start = buffer.bytes_in_range(search_start..buffer.len());
+                let bytes_before_search_start = buffer.bytes_in_range(0..search_start);
                 let query_matches = query
-                    .stream_find_iter(bytes_after_last_selection)
-                    .map(|result| (last_selection.end, result))
+                    .stream_find_iter(bytes_after_search_start)
+                    .map(|result| (search_start, result))
                     .chain(
                         query
-                            .stream_find_iter(bytes_before_first_selection)
+                            .stream_find_iter(bytes_before_search_start)
                             .map(|result| (0, result)),
                     );
 
@@ -9234,6 +9826,9 @@ impl Editor {
                         if !selections
                             .iter()
                             .any(|selection| selection.range().overlaps(&offset_range))
+                            && !skipped_ranges
+                                .iter()
+                                .any(|skipped| skipped.overlaps(&offset_range))
                         {
                             next_selected_range = Some(offset_range);
                             break;
@@ -9242,6 +9837,9 @@ impl Editor {
                 }
 
                 if let Some(next_selected_range) = next_selected_range {
+                    let skipped = replace_newest.then(|| self.selections.newest_anchor().clone());
+                    select_next_state.search_start =
+                        Some(buffer.anchor_before(next_selected_range.end));
                     select_next_match_ranges(
                         self,
                         next_selected_range,
@@ -9250,6 +9848,13 @@ impl Editor {
                         window,
                         cx,
                     );
+                    select_next_state
+                        .skipped_ranges
+                        .extend(skipped.as_ref().map(|skipped| skipped.range()));
+                    select_next_state.added_occurrences.push(AddedOccurrence {
+                        selection_id: self.selections.newest_anchor().id,
+                        skipped,
+                    });
                 } else {
                     select_next_state.done = true;
                 }
@@ -9318,21 +9923,18 @@ impl Editor {
                         .text_for_range(selection.start..selection.end)
                         .collect::<String>();
                     let is_empty = query.is_empty();
-                    let select_state = SelectNextState {
-                        query: AhoCorasick::new(&[query])?,
-                        wordwise: true,
-                        done: is_empty,
-                    };
+                    let select_state =
+                        SelectNextState::new(AhoCorasick::new(&[query])?, true, is_empty);
                     self.select_next_state = Some(select_state);
                 } else {
                     self.select_next_state = None;
                 }
             } else if let Some(selected_text) = selected_text {
-                self.select_next_state = Some(SelectNextState {
-                    query: AhoCorasick::new(&[selected_text])?,
-                    wordwise: false,
-                    done: false,
-                });
+                self.select_next_state = Some(SelectNextState::new(
+                    AhoCorasick::new(&[selected_text])?,
+                    false,
+                    false,
+                ));
                 self.select_next_match_internal(
                     display_map,
                     replace_newest,
@@ -9448,6 +10050,82 @@ impl Editor {
         Ok(())
     }
 
+    /// Replaces the newest selection with the next occurrence of its text, like
+    /// [`SelectNext`] with `replace_newest`. Skipped occurrences aren't selected again.
+    pub fn skip_and_select_next(
+        &mut self,
+        _: &SkipAndSelectNext,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Result<()> {
+        self.select_next(
+            &SelectNext {
+                replace_newest: true,
+            },
+            window,
+            cx,
+        )
+    }
+
+    /// Removes the last selection added by [`SelectNext`], restoring the occurrence it
+    /// skipped, if any. The next [`SelectNext`] resumes the search from there.
+    pub fn undo_select_next(
+        &mut self,
+        _: &UndoSelectNext,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(occurrence) = self
+            .select_next_state
+            .as_ref()
+            .and_then(|state| state.added_occurrences.last())
+            .cloned()
+        else {
+            return;
+        };
+        let removed = self
+            .selections
+            .disjoint_anchors()
+            .iter()
+            .find(|selection| selection.id == occurrence.selection_id)
+            .cloned();
+        // Never remove the only selection.
+        if removed.is_some() && occurrence.skipped.is_none() && self.selections.count() == 1 {
+            return;
+        }
+
+        self.push_to_selection_history();
+        let Some(mut select_next_state) = self.select_next_state.take() else {
+            return;
+        };
+        select_next_state.added_occurrences.pop();
+        let buffer = self.buffer.read(cx).snapshot(cx);
+        self.change_selections(Some(Autoscroll::newest()), window, cx, |s| {
+            s.delete(occurrence.selection_id);
+            if let Some(skipped) = &occurrence.skipped {
+                s.insert_range(skipped.start.to_offset(&buffer)..skipped.end.to_offset(&buffer));
+            }
+        });
+
+        if let Some(skipped) = &occurrence.skipped {
+            // The restored selection has a new id, which must be used to undo its addition.
+            let restored_id = self.selections.newest_anchor().id;
+            for added in &mut select_next_state.added_occurrences {
+                if added.selection_id == skipped.id {
+                    added.selection_id = restored_id;
+                }
+            }
+            select_next_state
+                .skipped_ranges
+                .retain(|range| *range != skipped.range());
+            select_next_state.search_start = Some(skipped.end);
+        } else if let Some(removed) = &removed {
+            select_next_state.search_start = Some(removed.start);
+        }
+        select_next_state.done = false;
+        self.select_next_state = Some(select_next_state);
+    }
+
     pub fn select_previous(
         &mut self,
         action: &SelectPrevious,
@@ -9560,11 +10238,11 @@ impl Editor {
                         .text_for_range(selection.start..selection.end)
                         .collect::<String>();
                     let is_empty = query.is_empty();
-                    let select_state = SelectNextState {
-                        query: AhoCorasick::new(&[query.chars().rev().collect::<String>()])?,
-                        wordwise: true,
-                        done: is_empty,
-                    };
+                    let select_state = SelectNextState::new(
+                        AhoCorasick::new(&[query.chars().rev().collect::<String>()])?,
+                        true,
+                        is_empty,
+                    );
                     self.select_prev_state = Some(select_state);
                 } else {
                     self.select_prev_state = None;
@@ -9580,11 +10258,11 @@ impl Editor {
                     s.select(selections);
                 });
             } else if let Some(selected_text) = selected_text {
-                self.select_prev_state = Some(SelectNextState {
-                    query: AhoCorasick::new(&[selected_text.chars().rev().collect::<String>()])?,
-                    wordwise: false,
-                    done: false,
-                });
+                self.select_prev_state = Some(SelectNextState::new(
+                    AhoCorasick::new(&[selected_text.chars().rev().collect::<String>()])?,
+                    false,
+                    false,
+                ));
                 self.select_previous(action, window, cx)?;
             }
         }
@@ -10367,6 +11045,27 @@ impl Editor {
         });
     }
 
+    /// Moves the cursor to the start of the `symbol_ix`-th outermost symbol
+    /// containing it, i.e. to the symbol of the breadcrumb following the path's.
+    pub fn go_to_enclosing_symbol(
+        &mut self,
+        symbol_ix: usize,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let cursor = self.selections.newest_anchor().head();
+        let Some((_, symbols)) = self.buffer.read(cx).symbols_containing(cursor, None, cx) else {
+            return;
+        };
+        let Some(symbol) = symbols.get(symbol_ix) else {
+            return;
+        };
+        let start = symbol.range.start;
+        self.change_selections(Some(Autoscroll::center()), window, cx, |s| {
+            s.select_anchor_ranges([start..start])
+        });
+    }
+
     fn go_to_diagnostic(
         &mut self,
         _: &GoToDiagnostic,
@@ -10445,6 +11144,10 @@ impl Editor {
             .filter(|entry| entry.diagnostic.is_primary)
             .filter(|entry| entry.range.start != entry.range.end)
             .filter(|entry| entry.diagnostic.severity <= DiagnosticSeverity::WARNING)
+            .filter(|entry| {
+                self.diagnostics_max_severity
+                    .includes(entry.diagnostic.severity)
+            })
             .filter(|entry| !snapshot.intersects_fold(entry.range.start))
             .collect::<Vec<_>>();
         let last_same_group_diagnostic_before = active_group_id.and_then(|active_group_id| {
@@ -10458,6 +11161,10 @@ impl Editor {
             .filter(|entry| entry.diagnostic.is_primary)
             .filter(|entry| entry.range.start != entry.range.end)
             .filter(|entry| entry.diagnostic.severity <= DiagnosticSeverity::WARNING)
+            .filter(|entry| {
+                self.diagnostics_max_severity
+                    .includes(entry.diagnostic.severity)
+            })
             .filter(|diagnostic| !snapshot.intersects_fold(diagnostic.range.start))
             .collect::<Vec<_>>();
         let last_same_group_diagnostic_after = active_group_id.and_then(|active_group_id| {
@@ -10859,6 +11566,23 @@ impl Editor {
 
                     if Some(&target.buffer) == editor.buffer.read(cx).as_singleton().as_ref() {
                         editor.go_to_singleton_buffer_range(range.clone(), window, cx);
+                    } else if let Some(project_path) = target.buffer.read(cx).project_path(cx) {
+                        let options = OpenAtOptions {
+                            target: if split {
+                                OpenTarget::AdjacentPane
+                            } else {
+                                OpenTarget::ActivePane
+                            },
+                            position: RevealPosition::Top,
+                            ..OpenAtOptions::default()
+                        };
+                        window.defer(cx, move |window, cx| {
+                            workspace.update(cx, |workspace, cx| {
+                                workspace
+                                    .open_path_at(project_path, range, options, window, cx)
+                                    .detach_and_log_err(cx);
+                            });
+                        });
                     } else {
                         window.defer(cx, move |window, cx| {
                             let target_editor: Entity<Self> =
@@ -11414,23 +12138,45 @@ impl Editor {
         let old_name = rename.old_name;
         let new_name = rename.editor.read(cx).text(cx);
 
+        // The files a previewed rename edits are only opened when they're opened from the
+        // preview.
+        let patch_unopened_files = self.project.as_ref().map_or(false, |project| {
+            Self::previews_workspace_edits(project.read(cx), cx)
+        });
         let rename = self.semantics_provider.as_ref()?.perform_rename(
             &buffer,
             start,
             new_name.clone(),
+            patch_unopened_files,
             cx,
         )?;
 
         Some(cx.spawn_in(window, |editor, mut cx| async move {
-            let project_transaction = rename.await?;
-            Self::open_project_transaction(
-                &editor,
-                workspace,
-                project_transaction,
-                format!("Rename: {} → {}", old_name, new_name),
-                cx.clone(),
-            )
-            .await?;
+            let project_patch = rename.await?;
+            cx.update(|_, cx| {
+                Self::set_transactions_command(
+                    project_patch
+                        .transaction
+                        .0
+                        .iter()
+                        .map(|(buffer, transaction)| (buffer.clone(), transaction.id)),
+                    ConfirmRename::debug_name(),
+                    cx,
+                )
+            })?;
+            let title = format!("Rename: {} → {}", old_name, new_name);
+            if project_patch.file_patches.is_empty() {
+                Self::open_project_transaction(
+                    &editor,
+                    workspace,
+                    project_patch.transaction,
+                    title,
+                    cx.clone(),
+                )
+                .await?;
+            } else {
+                Self::preview_project_patch(workspace, project_patch, title, cx.clone()).await?;
+            }
 
             editor.update(&mut cx, |editor, cx| {
                 editor.refresh_document_highlights(cx);
@@ -11574,6 +12320,34 @@ impl Editor {
             }
         };
 
+        // Formatting on save isn't previewed, as the buffers are saved right after.
+        let preview_workspace = if trigger == FormatTrigger::Manual
+            && Self::previews_workspace_edits(project.read(cx), cx)
+        {
+            self.workspace().map(|workspace| workspace.downgrade())
+        } else {
+            None
+        };
+
+        // Formatting pushes its transactions to the undo history without returning them, so
+        // the command is recorded in the transactions at the top of the buffers' undo stacks
+        // once it's done.
+        let command = match (trigger, &target) {
+            (FormatTrigger::Save, _) => workspace::Save::debug_name(),
+            (FormatTrigger::Manual, LspFormatTarget::Buffers) => Format::debug_name(),
+            (FormatTrigger::Manual, LspFormatTarget::Ranges(_)) => FormatSelections::debug_name(),
+        };
+        let last_transactions = buffers
+            .iter()
+            .map(|buffer| {
+                let last_transaction = buffer
+                    .read(cx)
+                    .peek_undo_stack()
+                    .map(|entry| entry.transaction_id());
+                (buffer.clone(), last_transaction)
+            })
+            .collect::<Vec<_>>();
+
         let mut timeout = cx.background_executor().timer(FORMAT_TIMEOUT).fuse();
         let format = project.update(cx, |project, cx| {
             project.format(buffers, target, true, trigger, cx)
@@ -11588,6 +12362,34 @@ impl Editor {
                 transaction = format.log_err().fuse() => transaction,
             };
 
+            cx.update(|_, cx| {
+                let formatted_transactions = last_transactions
+                    .into_iter()
+                    .filter_map(|(buffer, last_transaction)| {
+                        let entry = buffer.read(cx).peek_undo_stack()?;
+                        let transaction_id = entry.transaction_id();
+                        let formatted = Some(transaction_id) != last_transaction
+                            && !entry.metadata().is_manual();
+                        formatted.then_some((buffer, transaction_id))
+                    })
+                    .collect::<Vec<_>>();
+                Self::set_transactions_command(formatted_transactions, command, cx);
+            })
+            .ok();
+
+            let transaction = match (preview_workspace, transaction) {
+                (Some(workspace), Some(transaction)) => {
+                    return Self::preview_project_transaction(
+                        workspace,
+                        transaction,
+                        "Format".to_string(),
+                        cx,
+                    )
+                    .await;
+                }
+                (_, transaction) => transaction,
+            };
+
             buffer
                 .update(&mut cx, |buffer, cx| {
                     if let Some(transaction) = transaction {
@@ -11619,6 +12421,34 @@ impl Editor {
         }
     }
 
+    /// Restarts the language servers of the buffers with their current settings, including
+    /// the ones of their worktrees, which aren't applied when they change. The settings are
+    /// checked first, and left unapplied if they're invalid. Open documents are opened again
+    /// in the restarted servers, with their unsaved changes.
+    fn restart_language_server_with_new_config(
+        &mut self,
+        _: &RestartLanguageServerWithNewConfig,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(project) = self.project.clone() else {
+            return;
+        };
+        let buffers = self.buffer.read(cx).all_buffers();
+        if let Err(error) = project
+            .read(cx)
+            .validate_language_server_settings(buffers.iter().cloned(), cx)
+        {
+            if let Some(workspace) = self.workspace() {
+                workspace.update(cx, |workspace, cx| workspace.show_error(&error, cx));
+            }
+            return;
+        }
+        project.update(cx, |project, cx| {
+            project.restart_language_servers_for_buffers(buffers, cx);
+        });
+    }
+
     fn cancel_language_server_work(
         workspace: &mut Workspace,
         _: &actions::CancelLanguageServerWork,
@@ -11827,6 +12657,7 @@ impl Editor {
                 log::error!("unexpectedly ended a transaction that wasn't started by this editor");
             }
 
+            self.record_edit_location(cx);
             cx.emit(EditorEvent::Edited { transaction_id });
             Some(transaction_id)
         } else {
@@ -12129,6 +12960,23 @@ impl Editor {
         }
     }
 
+    /// Unfolds the display row when it's folded, and otherwise folds the crease starting on its
+    /// buffer row.
+    pub fn toggle_fold_at_row(
+        &mut self,
+        row: DisplayRow,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        let buffer_row = MultiBufferRow(DisplayPoint::new(row, 0).to_point(&display_map).row);
+        if display_map.is_line_folded(buffer_row) {
+            self.unfold_at(&UnfoldAt { buffer_row }, window, cx);
+        } else {
+            self.fold_at(&FoldAt { buffer_row }, window, cx);
+        }
+    }
+
     pub fn unfold_lines(&mut self, _: &UnfoldLines, _window: &mut Window, cx: &mut Context<Self>) {
         if self.is_singleton(cx) {
             let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
@@ -12215,6 +13063,8 @@ impl Editor {
             let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
             self.unfold_ranges(&[0..display_map.buffer_snapshot.len()], true, true, cx);
         } else {
+            let snapshot = self.buffer.read(cx).snapshot(cx);
+            self.unfold_ranges(&[0..snapshot.len()], true, true, cx);
             self.toggle_fold_multiple_buffers = cx.spawn(|editor, mut cx| async move {
                 editor
                     .update(&mut cx, |editor, cx| {
@@ -12256,6 +13106,99 @@ impl Editor {
         self.fold_creases(ranges, true, window, cx);
     }
 
+    /// Folds the lines spanned by each selection.
+    pub fn fold_selected_lines(
+        &mut self,
+        _: &FoldSelectedLines,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let selections = self.selections.all::<Point>(cx);
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        let creases = selections
+            .into_iter()
+            .map(|selection| {
+                let start = Point::new(selection.start.row, 0);
+                let end = Point::new(
+                    selection.end.row,
+                    display_map
+                        .buffer_snapshot
+                        .line_len(MultiBufferRow(selection.end.row)),
+                );
+                Crease::simple(start..end, display_map.fold_placeholder.clone())
+            })
+            .collect::<Vec<_>>();
+        self.fold_creases(creases, true, window, cx);
+    }
+
+    /// Folds everything but the functions containing the selections, or the lines they span when
+    /// they're outside of any function. In multibuffers, folds the buffers without selections.
+    pub fn fold_all_except_selections(
+        &mut self,
+        _: &FoldAllExceptSelections,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        if !self.buffer.read(cx).is_singleton() {
+            let selected_buffer_ids: HashSet<_> = snapshot
+                .ranges_to_buffer_ranges(self.selections.disjoint_anchor_ranges())
+                .map(|(buffer, _, _)| buffer.remote_id())
+                .collect();
+            self.toggle_fold_multiple_buffers = cx.spawn_in(window, |editor, mut cx| async move {
+                editor
+                    .update_in(&mut cx, |editor, _, cx| {
+                        for buffer_id in editor.buffer.read(cx).excerpt_buffer_ids() {
+                            if !selected_buffer_ids.contains(&buffer_id) {
+                                editor.fold_buffer(buffer_id, cx);
+                            }
+                        }
+                    })
+                    .ok();
+            });
+            return;
+        }
+
+        let mut kept_rows = self
+            .selections
+            .all::<usize>(cx)
+            .into_iter()
+            .map(|selection| {
+                let range = snapshot
+                    .text_object_ranges(selection.range(), TreeSitterOptions::default())
+                    .filter(|(range, object)| {
+                        *object == TextObject::AroundFunction
+                            && range.start <= selection.start
+                            && range.end >= selection.end
+                    })
+                    .map(|(range, _)| range)
+                    .max_by_key(|range| range.len())
+                    .unwrap_or(selection.range());
+                range.start.to_point(&snapshot).row..=range.end.to_point(&snapshot).row
+            })
+            .collect::<Vec<_>>();
+        kept_rows.sort_unstable_by_key(|rows| *rows.start());
+
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        let mut creases = Vec::new();
+        let mut gap_start = 0;
+        let gap_ends = kept_rows
+            .iter()
+            .map(|rows| (*rows.start(), *rows.end() + 1))
+            .chain([(snapshot.max_row().0 + 1, 0)]);
+        for (kept_start, kept_end) in gap_ends {
+            if kept_start > gap_start {
+                let gap_end = MultiBufferRow(kept_start - 1);
+                creases.push(Crease::simple(
+                    Point::new(gap_start, 0)..Point::new(gap_end.0, snapshot.line_len(gap_end)),
+                    display_map.fold_placeholder.clone(),
+                ));
+            }
+            gap_start = gap_start.max(kept_end);
+        }
+        self.fold_creases(creases, true, window, cx);
+    }
+
     pub fn fold_ranges<T: ToOffset + Clone>(
         &mut self,
         ranges: Vec<Range<T>>,
@@ -12823,7 +13766,14 @@ impl Editor {
     }
 
     pub fn soft_wrap_mode(&self, cx: &App) -> SoftWrap {
-        let settings = self.buffer.read(cx).settings_at(0, cx);
+        let buffer = self.buffer.read(cx);
+        let soft_wrap_disabled = buffer.as_singleton().map_or(false, |buffer| {
+            buffer.read(cx).disabled_features().soft_wrap
+        });
+        if soft_wrap_disabled && self.soft_wrap_mode_override.is_none() {
+            return SoftWrap::None;
+        }
+        let settings = buffer.settings_at(0, cx);
         let mode = self.soft_wrap_mode_override.unwrap_or(settings.soft_wrap);
         match mode {
             language_settings::SoftWrap::PreferLine | language_settings::SoftWrap::None => {
@@ -13146,6 +14096,48 @@ impl Editor {
         self.git_blame_inline_enabled
     }
 
+    pub fn toggle_inline_diagnostics(
+        &mut self,
+        _: &ToggleInlineDiagnostics,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.inline_diagnostics_enabled = !self.inline_diagnostics_enabled;
+        cx.notify();
+    }
+
+    pub fn inline_diagnostics_enabled(&self) -> bool {
+        self.inline_diagnostics_enabled
+    }
+
+    pub fn diagnostics_max_severity(&self) -> DiagnosticSeverityFilter {
+        self.diagnostics_max_severity
+    }
+
+    pub fn set_diagnostics_max_severity(
+        &mut self,
+        action: &SetDiagnosticsMaxSeverity,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.update_diagnostics_max_severity(action.0, cx);
+    }
+
+    fn update_diagnostics_max_severity(
+        &mut self,
+        max_severity: DiagnosticSeverityFilter,
+        cx: &mut Context<Self>,
+    ) {
+        if self.diagnostics_max_severity == max_severity {
+            return;
+        }
+        self.diagnostics_max_severity = max_severity;
+        self.display_map.update(cx, |display_map, _| {
+            display_map.diagnostics_max_severity = max_severity;
+        });
+        cx.notify();
+    }
+
     pub fn toggle_selection_menu(
         &mut self,
         _: &ToggleSelectionMenu,
@@ -13699,6 +14691,24 @@ impl Editor {
         Some(text_highlights)
     }
 
+    /// Highlights the range for a moment, so that it can be spotted once scrolled to.
+    pub fn flash_range(&mut self, range: Range<Anchor>, cx: &mut Context<Self>) {
+        enum RangeFlash {}
+
+        self.highlight_background::<RangeFlash>(
+            &[range],
+            |colors| colors.editor_highlighted_line_background,
+            cx,
+        );
+        self.range_flash_task = Some(cx.spawn(|this, mut cx| async move {
+            cx.background_executor().timer(RANGE_FLASH_DURATION).await;
+            this.update(&mut cx, |this, cx| {
+                this.clear_background_highlights::<RangeFlash>(cx);
+            })
+            .ok();
+        }));
+    }
+
     pub fn highlight_gutter<T: 'static>(
         &mut self,
         ranges: &[Range<Anchor>],
@@ -13750,6 +14760,24 @@ impl Editor {
         }
     }
 
+    #[cfg(feature = "test-support")]
+    pub fn out_of_scope_search_highlights(&mut self, cx: &mut Context<Self>) -> Vec<Range<Point>> {
+        let snapshot = self.buffer().read(cx).snapshot(cx);
+
+        let highlights = self
+            .background_highlights
+            .get(&TypeId::of::<items::OutOfScopeSearchHighlights>());
+
+        if let Some((_color, ranges)) = highlights {
+            ranges
+                .iter()
+                .map(|range| range.start.to_point(&snapshot)..range.end.to_point(&snapshot))
+                .collect_vec()
+        } else {
+            vec![]
+        }
+    }
+
     fn document_highlights_for_position<'a>(
         &'a self,
         position: Anchor,
@@ -14049,7 +15077,15 @@ impl Editor {
                 self.scrollbar_marker_state.dirty = true;
                 self.active_indent_guides_state.dirty = true;
                 self.refresh_active_diagnostics(cx);
+                self.refresh_rainbow_brackets(cx);
+                self.refresh_focus_mode(cx);
+                self.refresh_selection_evaluation(cx);
+                self.refresh_paragraph_spacing(cx);
                 self.refresh_code_actions(window, cx);
+                if buffer_edited.is_none() {
+                    // Diffs are updated after the edits to their buffers, without one.
+                    self.refresh_word_diff_highlights(cx);
+                }
                 if self.has_active_inline_completion() {
                     self.update_visible_inline_completion(window, cx);
                 }
@@ -14117,6 +15153,7 @@ impl Editor {
                 excerpts,
             } => {
                 self.tasks_update_task = Some(self.refresh_runnables(window, cx));
+                self.refresh_soft_wrap_exclusions(cx);
                 let buffer_id = buffer.read(cx).remote_id();
                 if self.buffer.read(cx).diff_for(buffer_id).is_none() {
                     if let Some(project) = &self.project {
@@ -14151,22 +15188,30 @@ impl Editor {
             }
             multi_buffer::Event::Reparsed(buffer_id) => {
                 self.tasks_update_task = Some(self.refresh_runnables(window, cx));
+                self.refresh_rainbow_brackets(cx);
+                self.refresh_focus_mode(cx);
+                self.refresh_soft_wrap_exclusions(cx);
 
                 cx.emit(EditorEvent::Reparsed(*buffer_id));
             }
             multi_buffer::Event::DiffHunksToggled => {
                 self.tasks_update_task = Some(self.refresh_runnables(window, cx));
+                self.refresh_word_diff_highlights(cx);
             }
             multi_buffer::Event::LanguageChanged(buffer_id) => {
                 linked_editing_ranges::refresh_linked_ranges(self, window, cx);
+                self.refresh_soft_wrap_exclusions(cx);
                 cx.emit(EditorEvent::Reparsed(*buffer_id));
                 cx.notify();
             }
             multi_buffer::Event::DirtyChanged => cx.emit(EditorEvent::DirtyChanged),
             multi_buffer::Event::Saved => cx.emit(EditorEvent::Saved),
-            multi_buffer::Event::FileHandleChanged | multi_buffer::Event::Reloaded => {
+            multi_buffer::Event::FileHandleChanged => {
+                // The features disabled for the buffer may have changed with its path.
+                self.refresh_inlay_hints(InlayHintRefreshReason::RefreshRequested, cx);
                 cx.emit(EditorEvent::TitleChanged)
             }
+            multi_buffer::Event::Reloaded => cx.emit(EditorEvent::TitleChanged),
             // multi_buffer::Event::DiffBaseChanged => {
             //     self.scrollbar_marker_state.dirty = true;
             //     cx.emit(EditorEvent::DiffBaseChanged);
@@ -14175,6 +15220,7 @@ impl Editor {
             multi_buffer::Event::Closed => cx.emit(EditorEvent::Closed),
             multi_buffer::Event::DiagnosticsUpdated => {
                 self.refresh_active_diagnostics(cx);
+                self.refresh_diagnostic_details(cx);
                 self.scrollbar_marker_state.dirty = true;
                 cx.notify();
             }
@@ -14191,6 +15237,36 @@ impl Editor {
         cx.notify();
     }
 
+    /// Tells the user the font failed to load for soft wrapping, so that they can fix it
+    /// in their settings.
+    pub(crate) fn show_wrap_font_warning(&mut self, event: &WrapMapEvent, cx: &mut Context<Self>) {
+        struct WrapFontWarning;
+
+        let Some(workspace) = self.workspace() else {
+            return;
+        };
+        let message = match event {
+            WrapMapEvent::FontFallback { family } => {
+                format!("Failed to load font \"{family}\", soft wrapping with a fallback font")
+            }
+            WrapMapEvent::FontUnavailable { family } => {
+                format!("Failed to load font \"{family}\" or a fallback, lines aren't soft wrapped")
+            }
+        };
+        workspace.update(cx, |workspace, cx| {
+            workspace.show_toast(
+                Toast::new(NotificationId::unique::<WrapFontWarning>(), message),
+                cx,
+            )
+        });
+    }
+
+    fn refresh_line_wrap_budget(&mut self, cx: &mut Context<Self>) {
+        let budget = EditorSettings::get_global(cx).soft_wrap_row_budget;
+        self.display_map
+            .update(cx, |map, cx| map.set_line_wrap_budget(budget, cx));
+    }
+
     fn settings_changed(&mut self, window: &mut Window, cx: &mut Context<Self>) {
         self.tasks_update_task = Some(self.refresh_runnables(window, cx));
         self.refresh_inline_completion(true, false, window, cx);
@@ -14203,6 +15279,15 @@ impl Editor {
             cx,
         );
 
+        // The brackets are recolored on paint when only the theme changed.
+        if EditorSettings::get_global(cx).rainbow_brackets != self.rainbow_brackets.is_some() {
+            self.refresh_rainbow_brackets(cx);
+        }
+        self.refresh_focus_mode(cx);
+        self.refresh_soft_wrap_exclusions(cx);
+        self.refresh_paragraph_spacing(cx);
+        self.refresh_line_wrap_budget(cx);
+
         let old_cursor_shape = self.cursor_shape;
 
         {
@@ -14210,6 +15295,15 @@ impl Editor {
             self.scroll_manager.vertical_scroll_margin = editor_settings.vertical_scroll_margin;
             self.show_breadcrumbs = editor_settings.toolbar.breadcrumbs;
             self.cursor_shape = editor_settings.cursor_shape.unwrap_or_default();
+            if self.inline_diagnostics_setting != editor_settings.inline_diagnostics.enabled {
+                self.inline_diagnostics_setting = editor_settings.inline_diagnostics.enabled;
+                self.inline_diagnostics_enabled = self.inline_diagnostics_setting;
+            }
+        }
+        let diagnostics_max_severity = EditorSettings::get_global(cx).diagnostics_max_severity;
+        if self.diagnostics_max_severity_setting != diagnostics_max_severity {
+            self.diagnostics_max_severity_setting = diagnostics_max_severity;
+            self.update_diagnostics_max_severity(diagnostics_max_severity, cx);
         }
 
         if old_cursor_shape != self.cursor_shape {
@@ -14229,6 +15323,17 @@ impl Editor {
         cx.notify();
     }
 
+    /// Recomputes the highlights colored with the theme if it changed since they were. This is
+    /// done when the editor is painted rather than when the theme is switched, so that only the
+    /// displayed editors recompute theirs right away, and the other ones once displayed.
+    pub(crate) fn refresh_theme_styles(&mut self, cx: &mut Context<Self>) {
+        let theme_generation = cx.theme_generation();
+        if self.theme_generation != theme_generation {
+            self.theme_generation = theme_generation;
+            self.refresh_rainbow_brackets(cx);
+        }
+    }
+
     pub fn set_searchable(&mut self, searchable: bool) {
         self.searchable = searchable;
     }
@@ -14417,6 +15522,27 @@ impl Editor {
                 };
 
                 for (buffer, (ranges, scroll_offset)) in new_selections_by_buffer {
+                    let position = match scroll_offset {
+                        Some(scroll_offset) => RevealPosition::RowsFromTop(scroll_offset),
+                        None => RevealPosition::Fit,
+                    };
+                    if let ([range], Some(project_path)) =
+                        (ranges.as_slice(), buffer.read(cx).project_path(cx))
+                    {
+                        let buffer = buffer.read(cx);
+                        let range =
+                            buffer.offset_to_point(range.start)..buffer.offset_to_point(range.end);
+                        let options = OpenAtOptions {
+                            target: OpenTarget::Pane(pane.downgrade()),
+                            position,
+                            ..OpenAtOptions::default()
+                        };
+                        workspace
+                            .open_path_at(project_path, range, options, window, cx)
+                            .detach_and_log_err(cx);
+                        continue;
+                    }
+
                     let editor = buffer
                         .read(cx)
                         .file()
@@ -15260,13 +16386,16 @@ pub trait SemanticsProvider {
         cx: &mut App,
     ) -> Option<Task<Result<Option<Range<text::Anchor>>>>>;
 
+    /// Renames the symbol at the position. With `patch_unopened_files`, the edits to the files
+    /// with no open buffer may be returned as patches, instead of being made in buffers.
     fn perform_rename(
         &self,
         buffer: &Entity<Buffer>,
         position: text::Anchor,
         new_name: String,
+        patch_unopened_files: bool,
         cx: &mut App,
-    ) -> Option<Task<Result<ProjectTransaction>>>;
+    ) -> Option<Task<Result<ProjectPatch>>>;
 }
 
 pub trait CompletionProvider {
@@ -15703,10 +16832,17 @@ impl SemanticsProvider for Entity<Project> {
         buffer: &Entity<Buffer>,
         position: text::Anchor,
         new_name: String,
+        patch_unopened_files: bool,
         cx: &mut App,
-    ) -> Option<Task<Result<ProjectTransaction>>> {
+    ) -> Option<Task<Result<ProjectPatch>>> {
         Some(self.update(cx, |project, cx| {
-            project.perform_rename(buffer.clone(), position, new_name, cx)
+            project.perform_rename_with_file_patches(
+                buffer.clone(),
+                position,
+                new_name,
+                patch_unopened_files,
+                cx,
+            )
         }))
     }
 }
@@ -15750,6 +16886,143 @@ fn ending_row(next_selection: &Selection<Point>, display_map: &DisplaySnapshot)
     }
 }
 
+/// Returns how many columns to remove from the start of `next_row`, after its
+/// indentation, to join it with the comment on `row`.
+fn joined_comment_prefix_len(
+    snapshot: &MultiBufferSnapshot,
+    row: MultiBufferRow,
+    next_row: MultiBufferRow,
+) -> Option<u32> {
+    let line_start = Point::new(row.0, snapshot.indent_size_for_line(row).len);
+    let next_line_start = Point::new(next_row.0, snapshot.indent_size_for_line(next_row).len);
+    let scope = snapshot.language_scope_at(line_start)?;
+    let longest_prefix_at = |point: Point| {
+        scope
+            .line_comment_prefixes()
+            .iter()
+            .map(|prefix| prefix.trim_end())
+            .filter(|prefix| !prefix.is_empty() && snapshot.contains_str_at(point, prefix))
+            .max_by_key(|prefix| prefix.len())
+    };
+    let prefix = longest_prefix_at(line_start)?;
+    if longest_prefix_at(next_line_start) != Some(prefix) {
+        return None;
+    }
+
+    let prefix_end = next_line_start.column + prefix.len() as u32;
+    let whitespace_len = snapshot
+        .chars_at(Point::new(next_row.0, prefix_end))
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .count();
+    Some(prefix.len() as u32 + whitespace_len as u32)
+}
+
+/// Whether joining the line ending at `end_of_line` with the one starting at
+/// `start_of_next_line` joins two items of a comma separated list.
+fn joins_list_items(
+    snapshot: &MultiBufferSnapshot,
+    end_of_line: Point,
+    start_of_next_line: Point,
+) -> bool {
+    let (Some(last_char), Some(next_char)) = (
+        snapshot.reversed_chars_at(end_of_line).next(),
+        snapshot.chars_at(start_of_next_line).next(),
+    ) else {
+        return false;
+    };
+    if last_char.is_whitespace()
+        || matches!(last_char, ',' | ';' | '(' | '[' | '{')
+        || matches!(next_char, ',' | ';' | '.' | ')' | ']' | '}')
+    {
+        return false;
+    }
+
+    let end_offset = end_of_line.to_offset(snapshot);
+    let Some((open, close)) =
+        snapshot.innermost_enclosing_bracket_ranges(end_offset..end_offset, None)
+    else {
+        return false;
+    };
+    !top_level_delimiter_offsets(snapshot, open.end..close.start, ",").is_empty()
+}
+
+/// Returns the offsets of the delimiters in the given range that are not nested in
+/// brackets within the range, nor in strings or comments.
+fn top_level_delimiter_offsets(
+    snapshot: &MultiBufferSnapshot,
+    range: Range<usize>,
+    delimiter: &str,
+) -> Vec<usize> {
+    let enclosing_brackets = snapshot.innermost_enclosing_bracket_ranges(range.clone(), None);
+    let text = snapshot.text_for_range(range.clone()).collect::<String>();
+    text.match_indices(delimiter)
+        .map(|(ix, _)| range.start + ix)
+        .filter(|&offset| {
+            let in_string_or_comment = snapshot
+                .language_scope_at(offset)
+                .and_then(|scope| {
+                    scope
+                        .override_name()
+                        .map(|name| name == "string" || name == "comment")
+                })
+                .unwrap_or(false);
+            !in_string_or_comment
+                && snapshot
+                    .innermost_enclosing_bracket_ranges(offset..offset + delimiter.len(), None)
+                    == enclosing_brackets
+        })
+        .collect()
+}
+
+/// Returns the end delimiter to pair with `start`: the given one, or the end of
+/// the language's surround pair starting with `start`, or else `start` itself.
+fn surround_pair_end(
+    snapshot: &MultiBufferSnapshot,
+    offset: usize,
+    start: &str,
+    end: &Option<String>,
+) -> String {
+    if let Some(end) = end {
+        return end.clone();
+    }
+    snapshot
+        .language_scope_at(offset)
+        .and_then(|scope| {
+            scope
+                .surround_pairs()
+                .into_iter()
+                .find(|pair| pair.start == start)
+        })
+        .map_or_else(|| start.to_string(), |pair| pair.end)
+}
+
+/// When surrounding text that contains a single or a double quote with that
+/// same quote, returns the language's pair for the other quote so that the
+/// text doesn't need escaping.
+fn quote_pair_for_surrounded_text(
+    scope: &LanguageScope,
+    pair: BracketPair,
+    text: &str,
+) -> BracketPair {
+    let alternate_quote = match pair.start.as_str() {
+        "\"" => "'",
+        "'" => "\"",
+        _ => return pair,
+    };
+    if pair.end != pair.start || !text.contains(&pair.start) || text.contains(alternate_quote) {
+        return pair;
+    }
+    scope
+        .brackets()
+        .find(|(candidate, enabled)| {
+            *enabled
+                && candidate.surround
+                && candidate.start == alternate_quote
+                && candidate.end == alternate_quote
+        })
+        .map_or(pair, |(candidate, _)| candidate.clone())
+}
+
 impl EditorSnapshot {
     pub fn remote_selections_in_range<'a>(
         &'a self,
@@ -16715,6 +17988,20 @@ fn diagnostic_style(severity: DiagnosticSeverity, colors: &StatusColors) -> Hsla
     }
 }
 
+/// Collapses `text` onto a single line, joining its lines with spaces, so
+/// that single-line editors never end up containing newlines.
+fn single_line_text(text: &str) -> Cow<'_, str> {
+    if !text.contains(['\n', '\r']) {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(
+        text.trim_end_matches(['\n', '\r'])
+            .lines()
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
 pub fn styled_runs_for_code_label<'a>(
     label: &'a CodeLabel,
     syntax_theme: &'a theme::SyntaxTheme,
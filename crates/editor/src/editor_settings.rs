@@ -1,5 +1,6 @@
 use gpui::App;
 use language::CursorShape;
+use lsp::DiagnosticSeverity;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use settings::{Settings, SettingsSources};
@@ -9,16 +10,24 @@ pub struct EditorSettings {
     pub cursor_blink: bool,
     pub cursor_shape: Option<CursorShape>,
     pub current_line_highlight: CurrentLineHighlight,
+    pub rainbow_brackets: bool,
+    pub focus_mode: bool,
+    pub paragraph_spacing: u32,
+    pub soft_wrap_row_budget: u32,
+    pub preview_workspace_edits: bool,
     pub lsp_highlight_debounce: u64,
     pub hover_popover_enabled: bool,
     pub hover_popover_delay: u64,
     pub toolbar: Toolbar,
     pub scrollbar: Scrollbar,
     pub gutter: Gutter,
+    pub diagnostics_max_severity: DiagnosticSeverityFilter,
+    pub inline_diagnostics: InlineDiagnostics,
     pub scroll_beyond_last_line: ScrollBeyondLastLine,
     pub vertical_scroll_margin: f32,
     pub autoscroll_on_clicks: bool,
     pub horizontal_scroll_margin: f32,
+    pub scroll_beyond_last_column: f32,
     pub scroll_sensitivity: f32,
     pub relative_line_numbers: bool,
     pub seed_search_query_from_cursor: SeedQuerySetting,
@@ -28,6 +37,9 @@ pub struct EditorSettings {
     pub expand_excerpt_lines: u32,
     pub middle_click_paste: bool,
     #[serde(default)]
+    pub paste_files: PasteFiles,
+    pub copy_syntax_highlighting: bool,
+    #[serde(default)]
     pub double_click_in_multibuffer: DoubleClickInMultibuffer,
     pub search_wrap: bool,
     #[serde(default)]
@@ -62,6 +74,17 @@ pub enum SeedQuerySetting {
     Never,
 }
 
+/// What to do when files copied from a file manager are pasted.
+#[derive(Default, Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PasteFiles {
+    /// Insert the paths of the files, one per line.
+    #[default]
+    InsertPaths,
+    /// Open the files in the workspace.
+    Open,
+}
+
 /// What to do when multibuffer is double clicked in some of its excerpts (parts of singleton buffers).
 #[derive(Default, Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -98,6 +121,12 @@ pub struct Toolbar {
     pub selections_menu: bool,
 }
 
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct InlineDiagnostics {
+    pub enabled: bool,
+    pub max_length: usize,
+}
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 pub struct Scrollbar {
     pub show: ShowScrollbar,
@@ -149,6 +178,38 @@ pub struct ScrollbarAxes {
     pub vertical: bool,
 }
 
+/// The least severe diagnostics shown in the editor.
+///
+/// Default: hint
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticSeverityFilter {
+    /// Don't show diagnostics.
+    Off,
+    /// Show errors only.
+    Error,
+    /// Show errors and warnings.
+    Warning,
+    /// Show errors, warnings and information.
+    Info,
+    /// Show all diagnostics.
+    #[default]
+    Hint,
+}
+
+impl DiagnosticSeverityFilter {
+    pub fn includes(self, severity: DiagnosticSeverity) -> bool {
+        let max_severity = match self {
+            DiagnosticSeverityFilter::Off => return false,
+            DiagnosticSeverityFilter::Error => DiagnosticSeverity::ERROR,
+            DiagnosticSeverityFilter::Warning => DiagnosticSeverity::WARNING,
+            DiagnosticSeverityFilter::Info => DiagnosticSeverity::INFORMATION,
+            DiagnosticSeverityFilter::Hint => DiagnosticSeverity::HINT,
+        };
+        severity <= max_severity
+    }
+}
+
 /// Which diagnostic indicators to show in the scrollbar.
 ///
 /// Default: all
@@ -239,10 +300,27 @@ pub enum ScrollBeyondLastLine {
     /// The editor will scroll beyond the last line by one page.
     OnePage,
 
+    /// The editor will scroll beyond the last line by half a page, so that the
+    /// last line can be centered on screen.
+    HalfPage,
+
     /// The editor will scroll beyond the last line by the same number of lines as vertical_scroll_margin.
     VerticalScrollMargin,
 }
 
+impl ScrollBeyondLastLine {
+    /// The number of rows the editor can scroll past its last line, when the
+    /// given number of lines fit in the viewport.
+    pub fn rows_beyond_last_line(self, height_in_lines: f32, vertical_scroll_margin: f32) -> f32 {
+        match self {
+            ScrollBeyondLastLine::Off => 0.,
+            ScrollBeyondLastLine::OnePage => height_in_lines - 1.,
+            ScrollBeyondLastLine::HalfPage => ((height_in_lines - 1.) / 2.).max(0.),
+            ScrollBeyondLastLine::VerticalScrollMargin => vertical_scroll_margin,
+        }
+    }
+}
+
 /// Default options for buffer and project search items.
 #[derive(Copy, Clone, Default, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 pub struct SearchSettings {
@@ -271,6 +349,29 @@ pub struct EditorSettingsContent {
     ///
     /// Default: all
     pub current_line_highlight: Option<CurrentLineHighlight>,
+    /// Whether to color bracket pairs by their nesting depth.
+    ///
+    /// Default: false
+    pub rainbow_brackets: Option<bool>,
+    /// Whether to dim the text outside of the function or paragraph containing the cursor.
+    ///
+    /// Default: false
+    pub focus_mode: Option<bool>,
+    /// How many blank rows to add above each paragraph following blank lines, to space them out.
+    ///
+    /// Default: 0
+    pub paragraph_spacing: Option<u32>,
+    /// How many rows a line is soft wrapped into by measuring its text. The rest of longer
+    /// lines, like minified ones, is wrapped at a fixed number of columns instead, which is
+    /// only approximate but doesn't stall the wrapping of the whole buffer.
+    ///
+    /// Default: 256
+    pub soft_wrap_row_budget: Option<u32>,
+    /// Whether to preview the edits of code actions, renames and formatting in a diff before
+    /// applying them.
+    ///
+    /// Default: false
+    pub preview_workspace_edits: Option<bool>,
     /// The debounce delay before querying highlights from the language
     /// server based on the current cursor location.
     ///
@@ -291,6 +392,13 @@ pub struct EditorSettingsContent {
     pub scrollbar: Option<ScrollbarContent>,
     /// Gutter related settings
     pub gutter: Option<GutterContent>,
+    /// The least severe diagnostics to underline and show inline in the editor:
+    /// "off", "error", "warning", "info" or "hint".
+    ///
+    /// Default: hint
+    pub diagnostics_max_severity: Option<DiagnosticSeverityFilter>,
+    /// Inline diagnostics related settings
+    pub inline_diagnostics: Option<InlineDiagnosticsContent>,
     /// Whether the editor will scroll beyond the last line.
     ///
     /// Default: one_page
@@ -307,6 +415,11 @@ pub struct EditorSettingsContent {
     ///
     /// Default: 5.
     pub horizontal_scroll_margin: Option<f32>,
+    /// The number of characters the editor can scroll beyond the end of the
+    /// longest line when soft wrap is off.
+    ///
+    /// Default: 0.
+    pub scroll_beyond_last_column: Option<f32>,
     /// Scroll sensitivity multiplier. This multiplier is applied
     /// to both the horizontal and vertical delta values while scrolling.
     ///
@@ -342,6 +455,17 @@ pub struct EditorSettingsContent {
     /// Default: true
     pub middle_click_paste: Option<bool>,
 
+    /// What to do when files copied from a file manager are pasted.
+    ///
+    /// Default: insert_paths
+    pub paste_files: Option<PasteFiles>,
+
+    /// Whether copied text also carries its syntax highlighting, as HTML, for
+    /// the targets that paste rich text.
+    ///
+    /// Default: false
+    pub copy_syntax_highlighting: Option<bool>,
+
     /// What to do when multibuffer is double clicked in some of its excerpts
     /// (parts of singleton buffers).
     ///
@@ -434,6 +558,21 @@ pub struct ScrollbarAxesContent {
     vertical: Option<bool>,
 }
 
+/// Inline diagnostics related settings
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct InlineDiagnosticsContent {
+    /// Whether to show the first line of diagnostic messages after the text
+    /// of the lines they start on.
+    ///
+    /// Default: false
+    pub enabled: Option<bool>,
+    /// The maximum number of characters of the messages shown inline. Longer
+    /// messages are truncated, and shown in full when hovered.
+    ///
+    /// Default: 120
+    pub max_length: Option<usize>,
+}
+
 /// Gutter related settings
 #[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 pub struct GutterContent {
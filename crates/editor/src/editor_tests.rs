@@ -21,7 +21,7 @@ use language::{
     BracketPairConfig,
     Capability::ReadWrite,
     FakeLspAdapter, LanguageConfig, LanguageConfigOverride, LanguageMatcher, LanguageName,
-    Override, ParsedMarkdown, Point,
+    LineEnding, Override, ParsedMarkdown, Point, PointUtf16, SurroundPair, Unclipped,
 };
 use language_settings::{Formatter, FormatterList, IndentGuideSettings};
 use multi_buffer::IndentGuide;
@@ -29,8 +29,10 @@ use parking_lot::Mutex;
 use pretty_assertions::{assert_eq, assert_ne};
 use project::FakeFs;
 use project::{
+    file_patch::FilePatch,
     lsp_command::SIGNATURE_HELP_HIGHLIGHT_CURRENT,
     project_settings::{LspSettings, ProjectSettings},
+    ProjectPath,
 };
 use serde_json::{self, json};
 use std::{cell::RefCell, future::Future, rc::Rc, time::Instant};
@@ -674,6 +676,84 @@ fn test_clone(cx: &mut TestAppContext) {
     );
 }
 
+#[gpui::test]
+fn test_clone_state_into(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let buffer = cx.update(|cx| MultiBuffer::build_simple(&sample_text(10, 4, 'a'), cx));
+    let editor = cx.add_window(|window, cx| build_editor(buffer.clone(), window, cx));
+    let split_editor = cx.add_window(|window, cx| build_editor(buffer.clone(), window, cx));
+
+    _ = editor.update(cx, |editor, window, cx| {
+        editor.change_selections(None, window, cx, |s| {
+            s.select_ranges([Point::new(6, 1)..Point::new(6, 2)])
+        });
+        editor.fold_creases(
+            vec![Crease::simple(
+                Point::new(1, 0)..Point::new(3, 0),
+                FoldPlaceholder::test(),
+            )],
+            true,
+            window,
+            cx,
+        );
+        editor.set_scroll_position(gpui::Point::new(0., 2.), window, cx);
+    });
+    let editor_handle = editor.root(cx).unwrap();
+    _ = split_editor.update(cx, |split_editor, _, cx| {
+        editor_handle.update(cx, |editor, cx| editor.clone_state_into(split_editor, cx));
+    });
+
+    let display_text = editor.update(cx, |e, _, cx| e.display_text(cx)).unwrap();
+    let selections = editor
+        .update(cx, |e, _, cx| e.selections.ranges::<Point>(cx))
+        .unwrap();
+    assert_eq!(
+        split_editor
+            .update(cx, |e, _, cx| e.display_text(cx))
+            .unwrap(),
+        display_text
+    );
+    assert_eq!(
+        split_editor
+            .update(cx, |e, _, cx| e.selections.ranges::<Point>(cx))
+            .unwrap(),
+        selections
+    );
+    assert_eq!(
+        split_editor
+            .update(cx, |e, window, cx| e.snapshot(window, cx).scroll_position())
+            .unwrap(),
+        editor
+            .update(cx, |e, window, cx| e.snapshot(window, cx).scroll_position())
+            .unwrap()
+    );
+
+    // The editors diverge after their state was cloned.
+    _ = split_editor.update(cx, |split_editor, window, cx| {
+        split_editor.unfold_all(&UnfoldAll, window, cx);
+        split_editor.change_selections(None, window, cx, |s| {
+            s.select_ranges([Point::new(0, 0)..Point::new(0, 0)])
+        });
+    });
+    assert_eq!(
+        editor.update(cx, |e, _, cx| e.display_text(cx)).unwrap(),
+        display_text
+    );
+    assert_eq!(
+        editor
+            .update(cx, |e, _, cx| e.selections.ranges::<Point>(cx))
+            .unwrap(),
+        selections
+    );
+    assert_eq!(
+        split_editor
+            .update(cx, |e, _, cx| e.display_text(cx))
+            .unwrap(),
+        sample_text(10, 4, 'a')
+    );
+}
+
 #[gpui::test]
 async fn test_navigation_history(cx: &mut TestAppContext) {
     init_test(cx, |_| {});
@@ -949,6 +1029,72 @@ fn test_fold_action(cx: &mut TestAppContext) {
     });
 }
 
+#[gpui::test]
+fn test_toggle_fold_at_row(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let editor = cx.add_window(|window, cx| {
+        let buffer = MultiBuffer::build_simple(
+            &"
+                fn a() {
+                    1
+                }
+
+                fn b() {
+                    2
+                }
+            "
+            .unindent(),
+            cx,
+        );
+        build_editor(buffer.clone(), window, cx)
+    });
+
+    _ = editor.update(cx, |editor, window, cx| {
+        editor.toggle_fold_at_row(DisplayRow(4), window, cx);
+        assert_eq!(
+            editor.display_text(cx),
+            "
+                fn a() {
+                    1
+                }
+
+                fn b() {⋯
+                }
+            "
+            .unindent(),
+        );
+
+        editor.toggle_fold_at_row(DisplayRow(0), window, cx);
+        assert_eq!(
+            editor.display_text(cx),
+            "
+                fn a() {⋯
+                }
+
+                fn b() {⋯
+                }
+            "
+            .unindent(),
+        );
+
+        // Display rows follow the folds.
+        editor.toggle_fold_at_row(DisplayRow(3), window, cx);
+        assert_eq!(
+            editor.display_text(cx),
+            "
+                fn a() {⋯
+                }
+
+                fn b() {
+                    2
+                }
+            "
+            .unindent(),
+        );
+    });
+}
+
 #[gpui::test]
 fn test_fold_action_whitespace_sensitive_language(cx: &mut TestAppContext) {
     init_test(cx, |_| {});
@@ -1899,6 +2045,58 @@ fn test_prev_next_word_bounds_with_soft_wrap(cx: &mut TestAppContext) {
     });
 }
 
+#[gpui::test]
+async fn test_go_to_edit_locations(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+    let mut cx = EditorTestContext::new(cx).await;
+    let blank_rows = "\n".repeat(12);
+    cx.set_state(&format!("ˇa{blank_rows}b"));
+
+    cx.update_editor(|editor, window, cx| {
+        editor.finalize_last_transaction(cx);
+        editor.handle_input("1", window, cx);
+        editor.finalize_last_transaction(cx);
+        editor.change_selections(None, window, cx, |s| {
+            s.select_ranges([Point::new(12, 1)..Point::new(12, 1)])
+        });
+        editor.handle_input("9", window, cx);
+        editor.change_selections(None, window, cx, |s| {
+            s.select_ranges([Point::new(6, 0)..Point::new(6, 0)])
+        });
+    });
+
+    cx.update_editor(|editor, window, cx| {
+        editor.go_to_previous_edit(&GoToPreviousEdit, window, cx)
+    });
+    cx.assert_editor_state(&format!("1a{blank_rows}b9ˇ"));
+    cx.update_editor(|editor, window, cx| {
+        editor.go_to_previous_edit(&GoToPreviousEdit, window, cx)
+    });
+    cx.assert_editor_state(&format!("1ˇa{blank_rows}b9"));
+    cx.update_editor(|editor, window, cx| editor.go_to_next_edit(&GoToNextEdit, window, cx));
+    cx.assert_editor_state(&format!("1a{blank_rows}b9ˇ"));
+
+    // Editing near a location replaces it, and going back skips the location at the cursor.
+    cx.update_editor(|editor, window, cx| {
+        editor.finalize_last_transaction(cx);
+        editor.handle_input("8", window, cx);
+        editor.go_to_previous_edit(&GoToPreviousEdit, window, cx);
+    });
+    cx.assert_editor_state(&format!("1ˇa{blank_rows}b98"));
+
+    // The locations survive undoing the edits.
+    cx.update_editor(|editor, window, cx| {
+        editor.undo(&Undo, window, cx);
+        editor.undo(&Undo, window, cx);
+        editor.undo(&Undo, window, cx);
+        editor.change_selections(None, window, cx, |s| {
+            s.select_ranges([Point::new(6, 0)..Point::new(6, 0)])
+        });
+        editor.go_to_last_edit(&GoToLastEdit, window, cx);
+    });
+    cx.assert_editor_state(&format!("a{blank_rows}bˇ"));
+}
+
 #[gpui::test]
 async fn test_move_start_of_paragraph_end_of_paragraph(cx: &mut gpui::TestAppContext) {
     init_test(cx, |_| {});
@@ -2078,6 +2276,47 @@ async fn test_scroll_page_up_page_down(cx: &mut gpui::TestAppContext) {
     });
 }
 
+#[gpui::test]
+async fn test_scroll_beyond_last_line(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+    let mut cx = EditorTestContext::new(cx).await;
+    let line_height = cx.editor(|editor, window, _| {
+        editor
+            .style()
+            .unwrap()
+            .text
+            .line_height_in_pixels(window.rem_size())
+    });
+    let window = cx.window;
+    cx.simulate_window_resize(window, size(px(1000.), 5. * line_height));
+    cx.set_state("ˇone\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten");
+
+    for (scroll_beyond_last_line, max_scroll_top) in [
+        (ScrollBeyondLastLine::Off, 5.),
+        (ScrollBeyondLastLine::HalfPage, 7.),
+        (ScrollBeyondLastLine::OnePage, 9.),
+    ] {
+        cx.update(|_, cx| {
+            cx.update_global::<SettingsStore, _>(|settings, cx| {
+                settings.update_user_settings::<EditorSettings>(cx, |settings| {
+                    settings.scroll_beyond_last_line = Some(scroll_beyond_last_line);
+                });
+            });
+        });
+        cx.update_editor(|editor, window, cx| {
+            editor.set_scroll_position(gpui::Point::new(0., 100.), window, cx);
+        });
+        cx.run_until_parked();
+        cx.update_editor(|editor, window, cx| {
+            assert_eq!(
+                editor.snapshot(window, cx).scroll_position(),
+                gpui::Point::new(0., max_scroll_top),
+                "{scroll_beyond_last_line:?}"
+            );
+        });
+    }
+}
+
 #[gpui::test]
 async fn test_autoscroll(cx: &mut gpui::TestAppContext) {
     init_test(cx, |_| {});
@@ -3427,6 +3666,144 @@ async fn test_join_lines_with_git_diff_base(
     );
 }
 
+#[gpui::test]
+async fn test_smart_join_lines(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorTestContext::new(cx).await;
+    let language = Arc::new(
+        Language::new(
+            LanguageConfig {
+                line_comments: vec!["// ".into()],
+                ..LanguageConfig::default()
+            },
+            Some(tree_sitter_rust::LANGUAGE.into()),
+        )
+        .with_brackets_query(indoc! {r#"
+            ("(" @open ")" @close)
+            ("[" @open "]" @close)
+            ("{" @open "}" @close)
+        "#})
+        .unwrap(),
+    );
+    cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+
+    // Comment leaders of joined lines are removed.
+    cx.set_state(indoc! {"
+        fn main() {
+            // The first lineˇ
+            // and the second one.
+        }
+    "});
+    cx.update_editor(|editor, window, cx| editor.smart_join_lines(&SmartJoinLines, window, cx));
+    cx.assert_editor_state(indoc! {"
+        fn main() {
+            // The first lineˇ and the second one.
+        }
+    "});
+
+    // List items are separated with commas.
+    cx.set_state(indoc! {"
+        fn main() {
+            let items = vec![«one,
+                two
+        ˇ»        three];
+        }
+    "});
+    cx.update_editor(|editor, window, cx| editor.smart_join_lines(&SmartJoinLines, window, cx));
+    cx.assert_editor_state(indoc! {"
+        fn main() {
+            let items = vec![one, twoˇ, three];
+        }
+    "});
+
+    // Method chains are joined with a space, as with `JoinLines`.
+    cx.set_state(indoc! {"
+        fn main() {
+            items(one)ˇ
+                .count();
+        }
+    "});
+    cx.update_editor(|editor, window, cx| editor.smart_join_lines(&SmartJoinLines, window, cx));
+    cx.assert_editor_state(indoc! {"
+        fn main() {
+            items(one)ˇ .count();
+        }
+    "});
+}
+
+#[gpui::test]
+async fn test_split_selection_at_delimiter(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |settings| {
+        settings.defaults.tab_size = NonZeroU32::new(4)
+    });
+
+    let mut cx = EditorTestContext::new(cx).await;
+    let language = Arc::new(
+        Language::new(
+            LanguageConfig::default(),
+            Some(tree_sitter_rust::LANGUAGE.into()),
+        )
+        .with_brackets_query(indoc! {r#"
+                ("(" @open ")" @close)
+                ("{" @open "}" @close)
+            "#})
+        .unwrap()
+        .with_indents_query(indoc! {r#"
+                (_ "(" ")" @end) @indent
+                (_ "{" "}" @end) @indent
+            "#})
+        .unwrap()
+        .with_override_query("(string_literal) @string")
+        .unwrap(),
+    );
+    cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+
+    // Empty selections split the contents of their enclosing brackets, skipping
+    // nested brackets and strings.
+    cx.set_state(indoc! {r#"
+        fn main() {
+            call(ˇfirst, inner(a, b), "x, y", last);
+        }
+    "#});
+    cx.update_editor(|editor, window, cx| {
+        editor.split_selection_at_delimiter(&SplitSelectionAtDelimiter::default(), window, cx)
+    });
+    cx.run_until_parked();
+    cx.assert_editor_state(indoc! {r#"
+        fn main() {
+            call(ˇfirst,
+                inner(a, b),
+                "x, y",
+                last);
+        }
+    "#});
+
+    // Non-empty selections are split at the given delimiter.
+    cx.set_state(indoc! {r#"
+        fn main() {
+            «a; b; cˇ»
+        }
+    "#});
+    cx.update_editor(|editor, window, cx| {
+        editor.split_selection_at_delimiter(
+            &SplitSelectionAtDelimiter {
+                delimiter: Some(";".into()),
+            },
+            window,
+            cx,
+        )
+    });
+    cx.run_until_parked();
+    cx.assert_editor_state(indoc! {r#"
+        fn main() {
+            «a;
+            b;
+            cˇ»
+        }
+    "#});
+}
+
 #[gpui::test]
 async fn test_custom_newlines_cause_no_false_positive_diffs(
     executor: BackgroundExecutor,
@@ -4810,38 +5187,168 @@ async fn test_paste_multiline(cx: &mut gpui::TestAppContext) {
 }
 
 #[gpui::test]
-fn test_select_all(cx: &mut TestAppContext) {
+fn test_single_line_editor_collapses_newlines(cx: &mut TestAppContext) {
     init_test(cx, |_| {});
 
-    let editor = cx.add_window(|window, cx| {
-        let buffer = MultiBuffer::build_simple("abc\nde\nfgh", cx);
-        build_editor(buffer, window, cx)
-    });
+    let editor = cx.add_window(|window, cx| Editor::single_line(window, cx));
     _ = editor.update(cx, |editor, window, cx| {
-        editor.select_all(&SelectAll, window, cx);
-        assert_eq!(
-            editor.selections.display_ranges(cx),
-            &[DisplayPoint::new(DisplayRow(0), 0)..DisplayPoint::new(DisplayRow(2), 3)]
-        );
+        editor.insert("one\ntwo\r\nthree\n", window, cx);
+        assert_eq!(editor.text(cx), "one two three");
+
+        cx.write_to_clipboard(ClipboardItem::new_string("four\nfive".into()));
+        editor.paste(&Paste, window, cx);
+        assert_eq!(editor.text(cx), "one two threefour five");
+
+        // Newline actions are left for the editor's host to handle.
+        editor.newline(&Newline, window, cx);
+        editor.newline_above(&NewlineAbove, window, cx);
+        editor.newline_below(&NewlineBelow, window, cx);
+        assert_eq!(editor.text(cx), "one two threefour five");
     });
 }
 
 #[gpui::test]
-fn test_select_line(cx: &mut TestAppContext) {
+async fn test_paste_without_reindent(cx: &mut gpui::TestAppContext) {
     init_test(cx, |_| {});
 
-    let editor = cx.add_window(|window, cx| {
-        let buffer = MultiBuffer::build_simple(&sample_text(6, 5, 'a'), cx);
-        build_editor(buffer, window, cx)
-    });
-    _ = editor.update(cx, |editor, window, cx| {
-        editor.change_selections(None, window, cx, |s| {
-            s.select_display_ranges([
-                DisplayPoint::new(DisplayRow(0), 0)..DisplayPoint::new(DisplayRow(0), 1),
-                DisplayPoint::new(DisplayRow(0), 2)..DisplayPoint::new(DisplayRow(0), 2),
-                DisplayPoint::new(DisplayRow(1), 0)..DisplayPoint::new(DisplayRow(1), 0),
-                DisplayPoint::new(DisplayRow(4), 2)..DisplayPoint::new(DisplayRow(4), 2),
-            ])
+    let mut cx = EditorTestContext::new(cx).await;
+    let language = Arc::new(Language::new(
+        LanguageConfig::default(),
+        Some(tree_sitter_rust::LANGUAGE.into()),
+    ));
+    cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+
+    cx.set_state(indoc! {"
+        fn a() {
+            «b(
+                c,
+            )ˇ»
+        }
+    "});
+    cx.update_editor(|e, window, cx| e.copy(&Copy, window, cx));
+
+    // The pasted block keeps its original indentation instead of being
+    // shifted to match the destination line.
+    cx.set_state(indoc! {"
+        fn d() {
+                ˇ
+        }
+    "});
+    cx.update_editor(|e, window, cx| e.paste_without_reindent(&PasteWithoutReindent, window, cx));
+    cx.assert_editor_state(indoc! {"
+        fn d() {
+                b(
+                c,
+            )ˇ
+        }
+    "});
+
+    // A regular paste re-indents the block relative to the destination.
+    cx.set_state(indoc! {"
+        fn d() {
+                ˇ
+        }
+    "});
+    cx.update_editor(|e, window, cx| e.paste(&Paste, window, cx));
+    cx.assert_editor_state(indoc! {"
+        fn d() {
+                b(
+                    c,
+                )ˇ
+        }
+    "});
+}
+
+#[gpui::test]
+async fn test_repair_delimiters(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |settings| {
+        settings.defaults.repair_delimiters = Some(true)
+    });
+
+    let mut cx = EditorTestContext::new(cx).await;
+    let language = Arc::new(
+        Language::new(
+            LanguageConfig::default(),
+            Some(tree_sitter_rust::LANGUAGE.into()),
+        )
+        .with_brackets_query(indoc! {r#"
+            ("(" @open ")" @close)
+            ("[" @open "]" @close)
+            ("{" @open "}" @close)
+        "#})
+        .unwrap(),
+    );
+    cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+    let paste = |text: &str, cx: &mut EditorTestContext| {
+        cx.write_to_clipboard(ClipboardItem::new_string(text.into()));
+        cx.update_editor(|e, window, cx| e.paste(&Paste, window, cx));
+    };
+
+    // An item pasted as the last one is separated from the previous one, and loses its
+    // trailing comma, since the list had none.
+    cx.set_state("let items = vec![one, twoˇ];");
+    paste(" three,", &mut cx);
+    cx.assert_editor_state("let items = vec![one, two, threeˇ];");
+
+    // It keeps it in lists that had one.
+    cx.set_state("let items = vec![one, ˇ];");
+    paste("two,", &mut cx);
+    cx.assert_editor_state("let items = vec![one, two,ˇ];");
+
+    // An item pasted before another one is separated from it.
+    cx.set_state("let items = vec![ˇtwo, three];");
+    paste("one", &mut cx);
+    cx.assert_editor_state("let items = vec![one, ˇtwo, three];");
+
+    // Text pasted outside of lists is left as is.
+    cx.set_state("let item = ˇ;");
+    paste("one,", &mut cx);
+    cx.assert_editor_state("let item = one,ˇ;");
+
+    // Joined list items are separated with commas.
+    cx.set_state(indoc! {"
+        let items = vec![one, ˇtwo
+            three];
+    "});
+    cx.update_editor(|e, window, cx| e.join_lines(&JoinLines, window, cx));
+    cx.assert_editor_state(indoc! {"
+        let items = vec![one, twoˇ, three];
+    "});
+}
+
+#[gpui::test]
+fn test_select_all(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let editor = cx.add_window(|window, cx| {
+        let buffer = MultiBuffer::build_simple("abc\nde\nfgh", cx);
+        build_editor(buffer, window, cx)
+    });
+    _ = editor.update(cx, |editor, window, cx| {
+        editor.select_all(&SelectAll, window, cx);
+        assert_eq!(
+            editor.selections.display_ranges(cx),
+            &[DisplayPoint::new(DisplayRow(0), 0)..DisplayPoint::new(DisplayRow(2), 3)]
+        );
+    });
+}
+
+#[gpui::test]
+fn test_select_line(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let editor = cx.add_window(|window, cx| {
+        let buffer = MultiBuffer::build_simple(&sample_text(6, 5, 'a'), cx);
+        build_editor(buffer, window, cx)
+    });
+    _ = editor.update(cx, |editor, window, cx| {
+        editor.change_selections(None, window, cx, |s| {
+            s.select_display_ranges([
+                DisplayPoint::new(DisplayRow(0), 0)..DisplayPoint::new(DisplayRow(0), 1),
+                DisplayPoint::new(DisplayRow(0), 2)..DisplayPoint::new(DisplayRow(0), 2),
+                DisplayPoint::new(DisplayRow(1), 0)..DisplayPoint::new(DisplayRow(1), 0),
+                DisplayPoint::new(DisplayRow(4), 2)..DisplayPoint::new(DisplayRow(4), 2),
+            ])
         });
         editor.select_line(&SelectLine, window, cx);
         assert_eq!(
@@ -5233,6 +5740,51 @@ async fn test_select_next(cx: &mut gpui::TestAppContext) {
     cx.assert_editor_state("«abcˇ»\n«abcˇ» «abcˇ»\ndefabc\n«abcˇ»");
 }
 
+#[gpui::test]
+async fn test_skip_and_undo_select_next(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorTestContext::new(cx).await;
+    cx.set_state("abc\nˇabc abc\ndefabc\nabc");
+
+    cx.update_editor(|e, window, cx| e.select_next(&SelectNext::default(), window, cx))
+        .unwrap();
+    cx.update_editor(|e, window, cx| e.select_next(&SelectNext::default(), window, cx))
+        .unwrap();
+    cx.assert_editor_state("abc\n«abcˇ» «abcˇ»\ndefabc\nabc");
+
+    cx.update_editor(|e, window, cx| e.skip_and_select_next(&SkipAndSelectNext, window, cx))
+        .unwrap();
+    cx.assert_editor_state("abc\n«abcˇ» abc\ndefabc\n«abcˇ»");
+
+    // Skipped occurrences aren't selected again when wrapping around.
+    cx.update_editor(|e, window, cx| e.select_next(&SelectNext::default(), window, cx))
+        .unwrap();
+    cx.assert_editor_state("«abcˇ»\n«abcˇ» abc\ndefabc\n«abcˇ»");
+    cx.update_editor(|e, window, cx| e.select_next(&SelectNext::default(), window, cx))
+        .unwrap();
+    cx.assert_editor_state("«abcˇ»\n«abcˇ» abc\ndefabc\n«abcˇ»");
+
+    cx.update_editor(|e, window, cx| e.undo_select_next(&UndoSelectNext, window, cx));
+    cx.assert_editor_state("abc\n«abcˇ» abc\ndefabc\n«abcˇ»");
+
+    // Undoing a skip restores the skipped occurrence.
+    cx.update_editor(|e, window, cx| e.undo_select_next(&UndoSelectNext, window, cx));
+    cx.assert_editor_state("abc\n«abcˇ» «abcˇ»\ndefabc\nabc");
+
+    cx.update_editor(|e, window, cx| e.undo_select_next(&UndoSelectNext, window, cx));
+    cx.assert_editor_state("abc\n«abcˇ» abc\ndefabc\nabc");
+
+    // The original selection is never removed.
+    cx.update_editor(|e, window, cx| e.undo_select_next(&UndoSelectNext, window, cx));
+    cx.assert_editor_state("abc\n«abcˇ» abc\ndefabc\nabc");
+
+    // The search resumes from the removed occurrence.
+    cx.update_editor(|e, window, cx| e.select_next(&SelectNext::default(), window, cx))
+        .unwrap();
+    cx.assert_editor_state("abc\n«abcˇ» «abcˇ»\ndefabc\nabc");
+}
+
 #[gpui::test]
 async fn test_select_all_matches(cx: &mut gpui::TestAppContext) {
     init_test(cx, |_| {});
@@ -5733,6 +6285,79 @@ async fn test_fold_function_bodies(cx: &mut gpui::TestAppContext) {
     });
 }
 
+#[gpui::test]
+async fn test_fold_selected_lines_and_all_except_selections(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorLspTestContext::new_rust(Default::default(), cx).await;
+    cx.set_state(
+        &r#"
+        use std::fmt;
+
+        fn a() {
+            «b();
+            cˇ»();
+        }
+
+        fn d() {
+            e();
+        }
+
+        fn f() {
+            ˇg();
+        }
+    "#
+        .unindent(),
+    );
+
+    cx.update_editor(|editor, window, cx| {
+        editor.fold_selected_lines(&FoldSelectedLines, window, cx);
+        assert_eq!(
+            editor.display_text(cx),
+            "
+            use std::fmt;
+
+            fn a() {
+            ⋯
+            }
+
+            fn d() {
+                e();
+            }
+
+            fn f() {
+            ⋯
+            }
+            "
+            .unindent()
+        );
+
+        editor.unfold_all(&UnfoldAll, window, cx);
+        editor.fold_all_except_selections(&FoldAllExceptSelections, window, cx);
+        assert_eq!(
+            editor.display_text(cx),
+            "
+            ⋯
+            fn a() {
+                b();
+                c();
+            }
+            ⋯
+            fn f() {
+                g();
+            }
+            "
+            .unindent()
+        );
+
+        editor.unfold_all(&UnfoldAll, window, cx);
+        assert_eq!(
+            editor.display_text(cx),
+            editor.buffer.read(cx).read(cx).text()
+        );
+    });
+}
+
 #[gpui::test]
 async fn test_autoindent(cx: &mut gpui::TestAppContext) {
     init_test(cx, |_| {});
@@ -6502,6 +7127,118 @@ async fn test_autoclose_with_overrides(cx: &mut gpui::TestAppContext) {
     );
 }
 
+#[gpui::test]
+async fn test_surround_pairs_and_quote_styles(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorTestContext::new(cx).await;
+    let bracket_pair = |start: &str, end: &str| BracketPair {
+        start: start.to_string(),
+        end: end.to_string(),
+        close: true,
+        surround: true,
+        newline: false,
+    };
+    let language = Arc::new(
+        Language::new(
+            LanguageConfig {
+                brackets: BracketPairConfig {
+                    pairs: vec![
+                        bracket_pair("(", ")"),
+                        bracket_pair("[", "]"),
+                        bracket_pair("\"", "\""),
+                        bracket_pair("'", "'"),
+                    ],
+                    ..Default::default()
+                },
+                surround_pairs: vec![
+                    SurroundPair {
+                        start: "<!-- ".to_string(),
+                        end: " -->".to_string(),
+                        trigger: Some("!".to_string()),
+                    },
+                    SurroundPair {
+                        start: "*".to_string(),
+                        end: "*".to_string(),
+                        trigger: None,
+                    },
+                ],
+                ..Default::default()
+            },
+            Some(tree_sitter_rust::LANGUAGE.into()),
+        )
+        .with_brackets_query(indoc! {r#"
+            ("(" @open ")" @close)
+            ("\"" @open "\"" @close)
+        "#})
+        .unwrap(),
+    );
+    cx.update_buffer(|buffer, cx| buffer.set_language(Some(language), cx));
+
+    // Quoting text that contains the typed quote uses the other quote.
+    cx.set_state("«it'sˇ» «fineˇ»");
+    cx.update_editor(|editor, window, cx| editor.handle_input("'", window, cx));
+    cx.assert_editor_state("\"«it'sˇ»\" '«fineˇ»'");
+
+    // Multi-character pairs are inserted by their trigger, or by the action.
+    cx.set_state("«aˇ» b «cˇ»");
+    cx.update_editor(|editor, window, cx| editor.handle_input("!", window, cx));
+    cx.assert_editor_state("<!-- «aˇ» --> b <!-- «cˇ» -->");
+
+    // Single-character pairs are triggered by their start delimiter.
+    cx.set_state("«aˇ» b");
+    cx.update_editor(|editor, window, cx| editor.handle_input("*", window, cx));
+    cx.assert_editor_state("*«aˇ»* b");
+
+    cx.set_state("«aˇ» bˇ");
+    cx.update_editor(|editor, window, cx| {
+        editor.surround_selections(
+            &SurroundSelections {
+                start: "<!-- ".to_string(),
+                end: None,
+            },
+            window,
+            cx,
+        )
+    });
+    cx.assert_editor_state("<!-- «aˇ» --> bˇ");
+
+    // Changing the surrounding pair rewrites both of its delimiters.
+    cx.set_state(indoc! {r#"
+        let s = call("heˇllo");
+    "#});
+    cx.update_editor(|editor, window, cx| {
+        editor.change_surrounding_pair(
+            &ChangeSurroundingPair {
+                start: "'".to_string(),
+                end: None,
+            },
+            window,
+            cx,
+        )
+    });
+    cx.assert_editor_state(indoc! {r#"
+        let s = call('heˇllo');
+    "#});
+
+    cx.set_state(indoc! {r#"
+        let s = call(ˇx);
+    "#});
+    cx.update_editor(|editor, window, cx| {
+        editor.change_surrounding_pair(
+            &ChangeSurroundingPair {
+                start: "[".to_string(),
+                end: None,
+            },
+            window,
+            cx,
+        )
+    });
+    cx.assert_editor_state(indoc! {r#"
+        let s = call[ˇx];
+    "#});
+}
+
 #[gpui::test]
 async fn test_surround_with_pair(cx: &mut gpui::TestAppContext) {
     init_test(cx, |_| {});
@@ -7174,62 +7911,190 @@ async fn test_document_format_during_save(cx: &mut gpui::TestAppContext) {
             editor.save(true, project.clone(), window, cx)
         })
         .unwrap();
-    cx.executor().advance_clock(super::FORMAT_TIMEOUT);
-    cx.executor().start_waiting();
+    cx.executor().advance_clock(super::FORMAT_TIMEOUT);
+    cx.executor().start_waiting();
+    save.await;
+    assert_eq!(
+        editor.update(cx, |editor, cx| editor.text(cx)),
+        "one\ntwo\nthree\n"
+    );
+    assert!(!cx.read(|cx| editor.is_dirty(cx)));
+
+    // For non-dirty buffer, no formatting request should be sent
+    let save = editor
+        .update_in(cx, |editor, window, cx| {
+            editor.save(true, project.clone(), window, cx)
+        })
+        .unwrap();
+    let _pending_format_request = fake_server
+        .handle_request::<lsp::request::RangeFormatting, _, _>(move |_, _| async move {
+            panic!("Should not be invoked on non-dirty buffer");
+        })
+        .next();
+    cx.executor().start_waiting();
+    save.await;
+
+    // Set rust language override and assert overridden tabsize is sent to language server
+    update_test_language_settings(cx, |settings| {
+        settings.languages.insert(
+            "Rust".into(),
+            LanguageSettingsContent {
+                tab_size: NonZeroU32::new(8),
+                ..Default::default()
+            },
+        );
+    });
+
+    editor.update_in(cx, |editor, window, cx| {
+        editor.set_text("somehting_new\n", window, cx)
+    });
+    assert!(cx.read(|cx| editor.is_dirty(cx)));
+    let save = editor
+        .update_in(cx, |editor, window, cx| {
+            editor.save(true, project.clone(), window, cx)
+        })
+        .unwrap();
+    fake_server
+        .handle_request::<lsp::request::Formatting, _, _>(move |params, _| async move {
+            assert_eq!(
+                params.text_document.uri,
+                lsp::Url::from_file_path(path!("/file.rs")).unwrap()
+            );
+            assert_eq!(params.options.tab_size, 8);
+            Ok(Some(vec![]))
+        })
+        .next()
+        .await;
+    cx.executor().start_waiting();
+    save.await;
+}
+
+#[gpui::test]
+async fn test_code_actions_on_save(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |settings| {
+        settings.defaults.code_actions_on_save =
+            Some(HashMap::from_iter([("source.fixAll".to_string(), true)]));
+        settings.defaults.code_actions_on_format = Some(HashMap::from_iter([
+            ("source.fixAll".to_string(), true),
+            ("source.organizeImports".to_string(), true),
+        ]));
+        settings.defaults.code_actions_on_save_timeout_ms = Some(500);
+    });
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_file(path!("/file.rs"), Default::default()).await;
+
+    let project = Project::test(fs, [path!("/file.rs").as_ref()], cx).await;
+
+    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
+    language_registry.add(rust_lang());
+    let mut fake_servers = language_registry.register_fake_lsp(
+        "Rust",
+        FakeLspAdapter {
+            capabilities: lsp::ServerCapabilities {
+                document_formatting_provider: Some(lsp::OneOf::Left(true)),
+                code_action_provider: Some(lsp::CodeActionProviderCapability::Simple(true)),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    );
+
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/file.rs"), cx)
+        })
+        .await
+        .unwrap();
+
+    let buffer = cx.new(|cx| MultiBuffer::singleton(buffer, cx));
+    let (editor, cx) = cx.add_window_view(|window, cx| {
+        build_editor_with_project(project.clone(), buffer, window, cx)
+    });
+    editor.update_in(cx, |editor, window, cx| {
+        editor.set_text("one\ntwo\n", window, cx)
+    });
+
+    cx.executor().start_waiting();
+    let fake_server = fake_servers.next().await.unwrap();
+    fake_server.handle_request::<lsp::request::Formatting, _, _>(|_, _| async move {
+        Ok(Some(Vec::new()))
+    });
+    let requested_kinds = Arc::new(Mutex::new(Vec::new()));
+    fake_server.handle_request::<lsp::request::CodeActionRequest, _, _>({
+        let requested_kinds = requested_kinds.clone();
+        move |params, _| {
+            let requested_kinds = requested_kinds.clone();
+            async move {
+                let kinds = params.context.only.unwrap_or_default();
+                requested_kinds.lock().push(kinds.clone());
+                if kinds != [lsp::CodeActionKind::SOURCE_FIX_ALL] {
+                    return Ok(Some(Vec::new()));
+                }
+                let edits = vec![lsp::TextEdit::new(
+                    lsp::Range::new(lsp::Position::new(0, 0), lsp::Position::new(0, 3)),
+                    "ONE".to_string(),
+                )];
+                Ok(Some(vec![lsp::CodeActionOrCommand::CodeAction(
+                    lsp::CodeAction {
+                        title: "fix all".to_string(),
+                        kind: Some(lsp::CodeActionKind::SOURCE_FIX_ALL),
+                        edit: Some(lsp::WorkspaceEdit::new(
+                            std::collections::HashMap::from_iter([(
+                                params.text_document.uri,
+                                edits,
+                            )]),
+                        )),
+                        ..Default::default()
+                    },
+                )]))
+            }
+        }
+    });
+
+    let save = editor
+        .update_in(cx, |editor, window, cx| {
+            editor.save(true, project.clone(), window, cx)
+        })
+        .unwrap();
     save.await;
     assert_eq!(
         editor.update(cx, |editor, cx| editor.text(cx)),
-        "one\ntwo\nthree\n"
+        "ONE\ntwo\n"
+    );
+    // The kinds listed in both settings only run once, on save.
+    assert_eq!(
+        *requested_kinds.lock(),
+        [
+            vec![lsp::CodeActionKind::SOURCE_FIX_ALL],
+            vec![lsp::CodeActionKind::SOURCE_ORGANIZE_IMPORTS]
+        ]
     );
-    assert!(!cx.read(|cx| editor.is_dirty(cx)));
-
-    // For non-dirty buffer, no formatting request should be sent
-    let save = editor
-        .update_in(cx, |editor, window, cx| {
-            editor.save(true, project.clone(), window, cx)
-        })
-        .unwrap();
-    let _pending_format_request = fake_server
-        .handle_request::<lsp::request::RangeFormatting, _, _>(move |_, _| async move {
-            panic!("Should not be invoked on non-dirty buffer");
-        })
-        .next();
-    cx.executor().start_waiting();
-    save.await;
 
-    // Set rust language override and assert overridden tabsize is sent to language server
-    update_test_language_settings(cx, |settings| {
-        settings.languages.insert(
-            "Rust".into(),
-            LanguageSettingsContent {
-                tab_size: NonZeroU32::new(8),
-                ..Default::default()
-            },
-        );
+    // Code actions on save taking too long are given up on, without holding the save.
+    fake_server.handle_request::<lsp::request::CodeActionRequest, _, _>(|params, _| async move {
+        if params.context.only == Some(vec![lsp::CodeActionKind::SOURCE_FIX_ALL]) {
+            futures::future::pending::<()>().await;
+        }
+        Ok(Some(Vec::new()))
     });
-
     editor.update_in(cx, |editor, window, cx| {
-        editor.set_text("somehting_new\n", window, cx)
+        editor.set_text("one\ntwo\n", window, cx)
     });
-    assert!(cx.read(|cx| editor.is_dirty(cx)));
     let save = editor
         .update_in(cx, |editor, window, cx| {
             editor.save(true, project.clone(), window, cx)
         })
         .unwrap();
-    fake_server
-        .handle_request::<lsp::request::Formatting, _, _>(move |params, _| async move {
-            assert_eq!(
-                params.text_document.uri,
-                lsp::Url::from_file_path(path!("/file.rs")).unwrap()
-            );
-            assert_eq!(params.options.tab_size, 8);
-            Ok(Some(vec![]))
-        })
-        .next()
-        .await;
+    cx.executor().run_until_parked();
+    cx.executor().advance_clock(Duration::from_millis(500));
     cx.executor().start_waiting();
     save.await;
+    assert_eq!(
+        editor.update(cx, |editor, cx| editor.text(cx)),
+        "one\ntwo\n"
+    );
+    assert!(!cx.read(|cx| editor.is_dirty(cx)));
 }
 
 #[gpui::test]
@@ -7702,6 +8567,12 @@ async fn test_document_format_manual_trigger(cx: &mut gpui::TestAppContext) {
         editor.update(cx, |editor, cx| editor.text(cx)),
         "one, two\nthree\n"
     );
+    editor.update(cx, |editor, cx| {
+        let buffer = editor.buffer().read(cx).as_singleton().unwrap().read(cx);
+        let metadata = buffer.peek_undo_stack().unwrap().metadata();
+        assert_eq!(metadata.command.as_deref(), Some("editor::Format"));
+        assert_eq!(metadata.tool.as_deref(), Some("formatter"));
+    });
 
     editor.update_in(cx, |editor, window, cx| {
         editor.set_text("one\ntwo\nthree\n", window, cx)
@@ -10668,6 +11539,147 @@ async fn go_to_prev_overlapping_diagnostic(
     "});
 }
 
+#[gpui::test]
+async fn test_go_to_diagnostic_respects_max_severity(
+    executor: BackgroundExecutor,
+    cx: &mut gpui::TestAppContext,
+) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorTestContext::new(cx).await;
+    let lsp_store =
+        cx.update_editor(|editor, _, cx| editor.project.as_ref().unwrap().read(cx).lsp_store());
+
+    cx.set_state(indoc! {"
+        ˇfn func(abc def: i32) -> u32 {
+        }
+    "});
+
+    cx.update(|_, cx| {
+        lsp_store.update(cx, |lsp_store, cx| {
+            lsp_store
+                .update_diagnostics(
+                    LanguageServerId(0),
+                    lsp::PublishDiagnosticsParams {
+                        uri: lsp::Url::from_file_path(path!("/root/file")).unwrap(),
+                        version: None,
+                        diagnostics: vec![
+                            lsp::Diagnostic {
+                                range: lsp::Range::new(
+                                    lsp::Position::new(0, 8),
+                                    lsp::Position::new(0, 11),
+                                ),
+                                severity: Some(lsp::DiagnosticSeverity::WARNING),
+                                ..Default::default()
+                            },
+                            lsp::Diagnostic {
+                                range: lsp::Range::new(
+                                    lsp::Position::new(0, 25),
+                                    lsp::Position::new(0, 28),
+                                ),
+                                severity: Some(lsp::DiagnosticSeverity::ERROR),
+                                ..Default::default()
+                            },
+                        ],
+                    },
+                    &[],
+                    cx,
+                )
+                .unwrap()
+        });
+    });
+
+    executor.run_until_parked();
+
+    cx.update_editor(|editor, window, cx| {
+        editor.set_diagnostics_max_severity(
+            &SetDiagnosticsMaxSeverity(DiagnosticSeverityFilter::Error),
+            window,
+            cx,
+        );
+        editor.go_to_diagnostic(&GoToDiagnostic, window, cx);
+    });
+    cx.assert_editor_state(indoc! {"
+        fn func(abc def: i32) -> ˇu32 {
+        }
+    "});
+
+    cx.update_editor(|editor, window, cx| {
+        editor.set_diagnostics_max_severity(
+            &SetDiagnosticsMaxSeverity(DiagnosticSeverityFilter::Warning),
+            window,
+            cx,
+        );
+        editor.go_to_diagnostic(&GoToDiagnostic, window, cx);
+    });
+    cx.assert_editor_state(indoc! {"
+        fn func(ˇabc def: i32) -> u32 {
+        }
+    "});
+}
+
+#[gpui::test]
+async fn test_diagnostics_toggles_survive_settings_changes(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorTestContext::new(cx).await;
+    cx.update_editor(|editor, window, cx| {
+        editor.toggle_inline_diagnostics(&ToggleInlineDiagnostics, window, cx);
+        editor.set_diagnostics_max_severity(
+            &SetDiagnosticsMaxSeverity(DiagnosticSeverityFilter::Error),
+            window,
+            cx,
+        );
+    });
+    let inline_diagnostics_enabled = cx.update_editor(|editor, _, _| {
+        assert_eq!(
+            editor.diagnostics_max_severity(),
+            DiagnosticSeverityFilter::Error
+        );
+        editor.inline_diagnostics_enabled()
+    });
+
+    // Unrelated settings changes keep the toggles of the editor.
+    cx.update(|_, cx| {
+        cx.update_global::<SettingsStore, _>(|settings, cx| {
+            settings.update_user_settings::<EditorSettings>(cx, |settings| {
+                settings.cursor_blink = Some(false);
+            });
+        });
+    });
+    cx.run_until_parked();
+    cx.update_editor(|editor, _, _| {
+        assert_eq!(
+            editor.inline_diagnostics_enabled(),
+            inline_diagnostics_enabled
+        );
+        assert_eq!(
+            editor.diagnostics_max_severity(),
+            DiagnosticSeverityFilter::Error
+        );
+    });
+
+    // Changing the setting resets its toggle.
+    cx.update(|_, cx| {
+        cx.update_global::<SettingsStore, _>(|settings, cx| {
+            settings.update_user_settings::<EditorSettings>(cx, |settings| {
+                settings.diagnostics_max_severity = Some(DiagnosticSeverityFilter::Warning);
+            });
+        });
+    });
+    cx.run_until_parked();
+    cx.update_editor(|editor, _, _| {
+        assert_eq!(
+            editor.inline_diagnostics_enabled(),
+            inline_diagnostics_enabled
+        );
+        assert_eq!(
+            editor.diagnostics_max_severity(),
+            DiagnosticSeverityFilter::Warning
+        );
+    });
+}
+
 #[gpui::test]
 async fn cycle_through_same_place_diagnostics(
     executor: BackgroundExecutor,
@@ -15854,6 +16866,190 @@ async fn test_rename_without_prepare(cx: &mut gpui::TestAppContext) {
     "});
 }
 
+#[gpui::test]
+async fn test_apply_and_reject_proposed_changes_in_file(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let buffer_1 = cx.new(|cx| language::Buffer::local("one\ntwo\n", cx));
+    let buffer_2 = cx.new(|cx| language::Buffer::local("six\nten\n", cx));
+    let preview = cx.add_window(|window, cx| {
+        let locations = [&buffer_1, &buffer_2]
+            .into_iter()
+            .map(|buffer| ProposedChangeLocation {
+                buffer: buffer.clone(),
+                ranges: vec![Point::zero()..buffer.read(cx).max_point()],
+            })
+            .collect();
+        ProposedChangesEditor::new("Changes", locations, None, window, cx)
+    });
+    preview
+        .update(cx, |preview, _, cx| {
+            for (buffer, new_text) in [(&buffer_1, "TWO"), (&buffer_2, "TEN")] {
+                let branch_buffer = preview.branch_buffer_for_base(buffer).unwrap();
+                branch_buffer.update(cx, |branch_buffer, cx| {
+                    branch_buffer.edit([(4..7, new_text)], None, cx)
+                });
+            }
+        })
+        .unwrap();
+    cx.run_until_parked();
+
+    preview
+        .update(cx, |preview, _, cx| {
+            preview.apply_changes_in_buffer(&buffer_1, cx);
+            preview.reject_changes_in_buffer(&buffer_2, cx);
+            assert!(preview.branch_buffer_for_base(&buffer_1).is_none());
+            assert!(preview.branch_buffer_for_base(&buffer_2).is_none());
+        })
+        .unwrap();
+    cx.run_until_parked();
+    assert_eq!(
+        buffer_1.read_with(cx, |buffer, _| buffer.text()),
+        "one\nTWO\n"
+    );
+    assert_eq!(
+        buffer_2.read_with(cx, |buffer, _| buffer.text()),
+        "six\nten\n"
+    );
+}
+
+#[gpui::test]
+async fn test_open_and_apply_file_patches(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "one.rs": "let one = 1;\n",
+            "two.rs": "let two = 2;\n",
+            "three.rs": "let three = 3;\n",
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let worktree_id = project.read_with(cx, |project, cx| {
+        project.worktrees(cx).next().unwrap().read(cx).id()
+    });
+    let project_path = |name: &str| ProjectPath {
+        worktree_id,
+        path: Path::new(name).into(),
+    };
+
+    let mut file_patches = Vec::new();
+    for (name, old_name, new_name) in [
+        ("one.rs", "one", "ONE"),
+        ("two.rs", "two", "TWO"),
+        ("three.rs", "three", "THREE"),
+    ] {
+        let range = Unclipped(PointUtf16::new(0, 4))
+            ..Unclipped(PointUtf16::new(0, 4 + old_name.len() as u32));
+        let file_patch = FilePatch::read(
+            fs.as_ref(),
+            project_path(name),
+            Path::new(path!("/dir")).join(name),
+            vec![(range, new_name.to_string())],
+        )
+        .await
+        .unwrap();
+        file_patches.push(file_patch);
+    }
+    let preview = cx.add_window(|window, cx| {
+        let mut preview = ProposedChangesEditor::new(
+            "Rename",
+            Vec::<ProposedChangeLocation<usize>>::new(),
+            Some(project.clone()),
+            window,
+            cx,
+        );
+        preview.set_file_patches(file_patches, cx);
+        preview
+    });
+
+    // Opening a file patch proposes its edits in the buffer of its file.
+    preview
+        .update(cx, |preview, window, cx| {
+            preview.open_file_patch(0, window, cx)
+        })
+        .unwrap();
+    cx.run_until_parked();
+    let buffer = project
+        .read_with(cx, |project, cx| {
+            project.get_open_buffer(&project_path("one.rs"), cx)
+        })
+        .unwrap();
+    preview
+        .update(cx, |preview, _, cx| {
+            let branch_buffer = preview.branch_buffer_for_base(&buffer).unwrap();
+            assert_eq!(branch_buffer.read(cx).text(), "let ONE = 1;\n");
+            assert_eq!(
+                preview.file_patch_paths(),
+                [
+                    (PathBuf::from(path!("/dir/two.rs")), false),
+                    (PathBuf::from(path!("/dir/three.rs")), false)
+                ]
+            );
+        })
+        .unwrap();
+    assert_eq!(
+        buffer.read_with(cx, |buffer, _| buffer.text()),
+        "let one = 1;\n"
+    );
+
+    // Applying the file patches writes their files, except those changed on disk since.
+    fs.save(
+        path!("/dir/three.rs").as_ref(),
+        &"let four = 4;\n".into(),
+        LineEnding::Unix,
+    )
+    .await
+    .unwrap();
+    preview
+        .update(cx, |preview, window, cx| {
+            preview.apply_all_file_patches(&ApplyAllDiffHunks, window, cx)
+        })
+        .unwrap();
+    cx.run_until_parked();
+    assert_eq!(
+        fs.load(path!("/dir/two.rs").as_ref()).await.unwrap(),
+        "let TWO = 2;\n"
+    );
+    assert_eq!(
+        fs.load(path!("/dir/three.rs").as_ref()).await.unwrap(),
+        "let four = 4;\n"
+    );
+    preview
+        .update(cx, |preview, _, _| {
+            assert_eq!(
+                preview.file_patch_paths(),
+                [(PathBuf::from(path!("/dir/three.rs")), true)]
+            );
+        })
+        .unwrap();
+
+    // A conflicted file patch stays conflicted once opened.
+    preview
+        .update(cx, |preview, window, cx| {
+            preview.open_file_patch(0, window, cx)
+        })
+        .unwrap();
+    cx.run_until_parked();
+    let buffer = project
+        .read_with(cx, |project, cx| {
+            project.get_open_buffer(&project_path("three.rs"), cx)
+        })
+        .unwrap();
+    preview
+        .update(cx, |preview, _, _| {
+            assert!(preview.branch_buffer_for_base(&buffer).is_none());
+            assert_eq!(
+                preview.file_patch_paths(),
+                [(PathBuf::from(path!("/dir/three.rs")), true)]
+            );
+        })
+        .unwrap();
+}
+
 fn empty_range(row: usize, column: usize) -> Range<DisplayPoint> {
     let point = DisplayPoint::new(DisplayRow(row as u32), column as u32);
     point..point
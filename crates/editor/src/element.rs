@@ -5,8 +5,8 @@ use crate::{
         Block, BlockContext, BlockStyle, DisplaySnapshot, HighlightedChunk, ToDisplayPoint,
     },
     editor_settings::{
-        CurrentLineHighlight, DoubleClickInMultibuffer, MultiCursorModifier, ScrollBeyondLastLine,
-        ScrollbarDiagnostics, ShowScrollbar,
+        CurrentLineHighlight, DoubleClickInMultibuffer, MultiCursorModifier, ScrollbarDiagnostics,
+        ShowScrollbar,
     },
     git::blame::GitBlame,
     hover_popover::{
@@ -15,12 +15,15 @@ use crate::{
     items::BufferSearchHighlights,
     mouse_context_menu::{self, MenuPosition, MouseContextMenu},
     scroll::{axis_pair, scroll_amount::ScrollAmount, AxisPair},
+    syntax_fallback::SyntaxHighlighting,
+    type_ahead::{PendingTypeAhead, TypeAheadLine},
+    unchanged_lines::{unchanged_rows_above, unchanged_rows_below, unchanged_rows_between},
     AcceptEditPrediction, BlockId, ChunkReplacement, CursorShape, CustomBlockId, DisplayPoint,
     DisplayRow, DocumentHighlightRead, DocumentHighlightWrite, EditDisplayMode, Editor, EditorMode,
     EditorSettings, EditorSnapshot, EditorStyle, ExpandExcerpts, FocusedBlock, GoToHunk,
     GoToPrevHunk, GutterDimensions, HalfPageDown, HalfPageUp, HandleInput, HoveredCursor,
     InlineCompletion, JumpData, LineDown, LineUp, OpenExcerpts, PageDown, PageUp, Point,
-    RevertSelectedHunks, RowExt, RowRangeExt, SelectPhase, Selection, SoftWrap,
+    RevertSelectedHunks, RowExt, RowRangeExt, SelectMode, SelectPhase, Selection, SoftWrap,
     StickyHeaderExcerpt, ToPoint, ToggleFold, CURSORS_VISIBLE_FOR,
     EDIT_PREDICTION_REQUIRES_MODIFIER_KEY_CONTEXT, FILE_HEADER_HEIGHT,
     GIT_BLAME_MAX_AUTHOR_CHARS_DISPLAYED, MAX_LINE_LEN, MULTI_BUFFER_EXCERPT_HEADER_HEIGHT,
@@ -35,7 +38,7 @@ use gpui::{
     point, px, quad, relative, size, svg, transparent_black, Action, AnyElement, App,
     AvailableSpace, Axis, Bounds, ClickEvent, ClipboardItem, ContentMask, Context, Corner, Corners,
     CursorStyle, DispatchPhase, Edges, Element, ElementInputHandler, Entity, Focusable as _,
-    FontId, GlobalElementId, Hitbox, Hsla, InteractiveElement, IntoElement,
+    FontFeatures, FontId, GlobalElementId, Hitbox, Hsla, InteractiveElement, IntoElement,
     KeyBindingContextPredicate, Keystroke, Length, ModifiersChangedEvent, MouseButton,
     MouseDownEvent, MouseMoveEvent, MouseUpEvent, PaintQuad, ParentElement, Pixels, ScrollDelta,
     ScrollWheelEvent, ShapedLine, SharedString, Size, StatefulInteractiveElement, Style, Styled,
@@ -44,10 +47,10 @@ use gpui::{
 use itertools::Itertools;
 use language::{
     language_settings::{
-        IndentGuideBackgroundColoring, IndentGuideColoring, IndentGuideSettings,
+        language_settings, IndentGuideBackgroundColoring, IndentGuideColoring, IndentGuideSettings,
         ShowWhitespaceSetting,
     },
-    ChunkRendererContext,
+    ChunkRendererContext, DiagnosticEntry,
 };
 use lsp::DiagnosticSeverity;
 use multi_buffer::{
@@ -66,6 +69,7 @@ use std::{
     ops::{Deref, Range},
     rc::Rc,
     sync::Arc,
+    time::Instant,
 };
 use sum_tree::Bias;
 use text::BufferId;
@@ -79,6 +83,7 @@ use util::{markdown::MarkdownString, RangeExt, ResultExt};
 use workspace::{item::Item, notifications::NotifyTaskExt, Workspace};
 
 const INLINE_BLAME_PADDING_EM_WIDTHS: f32 = 7.;
+const INLINE_DIAGNOSTIC_PADDING_EM_WIDTHS: f32 = 4.;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum DisplayDiffHunk {
@@ -213,6 +218,7 @@ impl EditorElement {
         register_action(editor, window, Editor::autoindent);
         register_action(editor, window, Editor::delete_line);
         register_action(editor, window, Editor::join_lines);
+        register_action(editor, window, Editor::smart_join_lines);
         register_action(editor, window, Editor::sort_lines_case_sensitive);
         register_action(editor, window, Editor::sort_lines_case_insensitive);
         register_action(editor, window, Editor::reverse_lines);
@@ -244,6 +250,8 @@ impl EditorElement {
         register_action(editor, window, Editor::kill_ring_yank);
         register_action(editor, window, Editor::copy);
         register_action(editor, window, Editor::paste);
+        register_action(editor, window, Editor::paste_without_reindent);
+        register_action(editor, window, Editor::paste_as_markdown);
         register_action(editor, window, Editor::undo);
         register_action(editor, window, Editor::redo);
         register_action(editor, window, Editor::move_page_up);
@@ -311,11 +319,18 @@ impl EditorElement {
         });
         register_action(editor, window, Editor::select_line);
         register_action(editor, window, Editor::split_selection_into_lines);
+        register_action(editor, window, Editor::split_selection_at_delimiter);
+        register_action(editor, window, Editor::surround_selections);
+        register_action(editor, window, Editor::change_surrounding_pair);
         register_action(editor, window, Editor::add_selection_above);
         register_action(editor, window, Editor::add_selection_below);
         register_action(editor, window, |editor, action, window, cx| {
             editor.select_next(action, window, cx).log_err();
         });
+        register_action(editor, window, |editor, action, window, cx| {
+            editor.skip_and_select_next(action, window, cx).log_err();
+        });
+        register_action(editor, window, Editor::undo_select_next);
         register_action(editor, window, |editor, action, window, cx| {
             editor.select_previous(action, window, cx).log_err();
         });
@@ -323,6 +338,8 @@ impl EditorElement {
         register_action(editor, window, Editor::select_larger_syntax_node);
         register_action(editor, window, Editor::select_smaller_syntax_node);
         register_action(editor, window, Editor::select_enclosing_symbol);
+        register_action(editor, window, Editor::select_inside);
+        register_action(editor, window, Editor::select_around);
         register_action(editor, window, Editor::move_to_enclosing_bracket);
         register_action(editor, window, Editor::undo_selection);
         register_action(editor, window, Editor::redo_selection);
@@ -332,6 +349,7 @@ impl EditorElement {
             register_action(editor, window, Editor::expand_excerpts_down);
         }
         register_action(editor, window, Editor::go_to_diagnostic);
+        register_action(editor, window, Editor::toggle_diagnostic_details);
         register_action(editor, window, Editor::go_to_prev_diagnostic);
         register_action(editor, window, Editor::go_to_next_hunk);
         register_action(editor, window, Editor::go_to_prev_hunk);
@@ -380,6 +398,7 @@ impl EditorElement {
         register_action(editor, window, Editor::fold);
         register_action(editor, window, Editor::fold_at_level);
         register_action(editor, window, Editor::fold_all);
+        register_action(editor, window, Editor::fold_all_except_selections);
         register_action(editor, window, Editor::fold_function_bodies);
         register_action(editor, window, Editor::fold_at);
         register_action(editor, window, Editor::fold_recursive);
@@ -390,6 +409,7 @@ impl EditorElement {
         register_action(editor, window, Editor::unfold_all);
         register_action(editor, window, Editor::unfold_at);
         register_action(editor, window, Editor::fold_selected_ranges);
+        register_action(editor, window, Editor::fold_selected_lines);
         register_action(editor, window, Editor::set_mark);
         register_action(editor, window, Editor::swap_selection_ends);
         register_action(editor, window, Editor::show_completions);
@@ -402,6 +422,12 @@ impl EditorElement {
         register_action(editor, window, Editor::toggle_line_numbers);
         register_action(editor, window, Editor::toggle_relative_line_numbers);
         register_action(editor, window, Editor::toggle_indent_guides);
+        register_action(editor, window, Editor::toggle_focus_mode);
+        register_action(editor, window, Editor::toggle_selection_evaluation);
+        register_action(editor, window, Editor::insert_selection_evaluation);
+        register_action(editor, window, Editor::go_to_last_edit);
+        register_action(editor, window, Editor::go_to_previous_edit);
+        register_action(editor, window, Editor::go_to_next_edit);
         register_action(editor, window, Editor::toggle_inlay_hints);
         register_action(editor, window, Editor::toggle_inline_completions);
         register_action(editor, window, hover_popover::hover);
@@ -411,11 +437,23 @@ impl EditorElement {
         register_action(editor, window, Editor::copy_file_name);
         register_action(editor, window, Editor::copy_file_name_without_extension);
         register_action(editor, window, Editor::copy_highlight_json);
+        register_action(editor, window, Editor::export_as_html);
+        register_action(editor, window, Editor::export_as_ansi);
+        register_action(editor, window, Editor::toggle_bookmark);
+        register_action(editor, window, Editor::edit_bookmark_note);
+        register_action(editor, window, Editor::export_bookmarks);
+        register_action(editor, window, Editor::import_bookmarks);
         register_action(editor, window, Editor::copy_permalink_to_line);
         register_action(editor, window, Editor::open_permalink_to_line);
         register_action(editor, window, Editor::copy_file_location);
+        register_action(editor, window, Editor::copy_with_context);
+        register_action(editor, window, Editor::cycle_string_quotes);
+        register_action(editor, window, Editor::toggle_raw_string);
+        register_action(editor, window, Editor::edit_string_literal);
         register_action(editor, window, Editor::toggle_git_blame);
         register_action(editor, window, Editor::toggle_git_blame_inline);
+        register_action(editor, window, Editor::toggle_inline_diagnostics);
+        register_action(editor, window, Editor::set_diagnostics_max_severity);
         register_action(editor, window, Editor::toggle_selected_diff_hunks);
         register_action(editor, window, Editor::toggle_staged_selected_diff_hunks);
         register_action(editor, window, Editor::expand_all_diff_hunks);
@@ -435,6 +473,11 @@ impl EditorElement {
             }
         });
         register_action(editor, window, Editor::restart_language_server);
+        register_action(
+            editor,
+            window,
+            Editor::restart_language_server_with_new_config,
+        );
         register_action(editor, window, Editor::show_character_palette);
         register_action(editor, window, |editor, action, window, cx| {
             if let Some(task) = editor.confirm_completion(action, window, cx) {
@@ -538,12 +581,20 @@ impl EditorElement {
         let gutter_hitbox = &position_map.gutter_hitbox;
         let mut click_count = event.click_count;
         let mut modifiers = event.modifiers;
+        let mut gutter_row = None;
 
         if let Some(hovered_hunk) = hovered_hunk {
             editor.toggle_diff_hunks_in_ranges_narrow(vec![hovered_hunk], cx);
             cx.notify();
             return;
         } else if gutter_hitbox.is_hovered(window) {
+            let row = position_map.row_for_position(event.position);
+            if modifiers.alt && !modifiers.shift {
+                editor.toggle_fold_at_row(row, window, cx);
+                cx.stop_propagation();
+                return;
+            }
+            gutter_row = Some(row);
             click_count = 3; // Simulate triple-click when clicking the gutter to select lines
         } else if !text_hitbox.is_hovered(window) {
             return;
@@ -593,7 +644,11 @@ impl EditorElement {
         }
 
         let point_for_position = position_map.point_for_position(event.position);
-        let position = point_for_position.previous_valid;
+        // Clicks in the gutter select the lines of the display row, which span the whole fold
+        // on folded rows.
+        let position = gutter_row.map_or(point_for_position.previous_valid, |row| {
+            DisplayPoint::new(row, 0)
+        });
         if modifiers.shift && modifiers.alt {
             editor.select(
                 SelectPhase::BeginColumnar {
@@ -792,6 +847,14 @@ impl EditorElement {
 
         let text_bounds = position_map.text_hitbox.bounds;
         let point_for_position = position_map.point_for_position(event.position);
+        // Lines are selected whole, so dragging line selections follows the display rows under
+        // the pointer, even over the gutter, without scrolling horizontally.
+        let selecting_lines = matches!(editor.selections.pending_mode(), Some(SelectMode::Line(_)));
+        let position = if selecting_lines {
+            DisplayPoint::new(position_map.row_for_position(event.position), 0)
+        } else {
+            point_for_position.previous_valid
+        };
         let mut scroll_delta = gpui::Point::<f32>::default();
         let vertical_margin = position_map.line_height.min(text_bounds.size.height / 3.0);
         let top = text_bounds.origin.y + vertical_margin;
@@ -816,16 +879,18 @@ impl EditorElement {
         let left = text_bounds.origin.x + scroll_space;
         let right = text_bounds.top_right().x - scroll_space;
 
-        if event.position.x < left {
-            scroll_delta.x = -scale_horizontal_mouse_autoscroll_delta(left - event.position.x);
-        }
-        if event.position.x > right {
-            scroll_delta.x = scale_horizontal_mouse_autoscroll_delta(event.position.x - right);
+        if !selecting_lines {
+            if event.position.x < left {
+                scroll_delta.x = -scale_horizontal_mouse_autoscroll_delta(left - event.position.x);
+            }
+            if event.position.x > right {
+                scroll_delta.x = scale_horizontal_mouse_autoscroll_delta(event.position.x - right);
+            }
         }
 
         editor.select(
             SelectPhase::Update {
-                position: point_for_position.previous_valid,
+                position,
                 goal_column: point_for_position.exact_unclipped.column(),
                 scroll_delta,
             },
@@ -1687,6 +1752,94 @@ impl EditorElement {
         Some(element)
     }
 
+    /// Lays out the message of the most severe primary diagnostic starting on
+    /// each visible row, after the end of that row.
+    #[allow(clippy::too_many_arguments)]
+    fn layout_inline_diagnostics(
+        &self,
+        snapshot: &EditorSnapshot,
+        rows: Range<DisplayRow>,
+        line_layouts: &[LineWithInvisibles],
+        crease_trailers: &[Option<CreaseTrailerLayout>],
+        em_width: Pixels,
+        content_origin: gpui::Point<Pixels>,
+        scroll_pixel_position: gpui::Point<Pixels>,
+        line_height: Pixels,
+        window: &mut Window,
+        cx: &mut App,
+    ) -> HashMap<DisplayRow, AnyElement> {
+        let editor = self.editor.read(cx);
+        if !editor.inline_diagnostics_enabled() {
+            return HashMap::default();
+        }
+        let max_severity = editor.diagnostics_max_severity();
+        let max_length = EditorSettings::get_global(cx).inline_diagnostics.max_length;
+
+        let start = DisplayPoint::new(rows.start, 0).to_point(&snapshot.display_snapshot);
+        let end = cmp::min(
+            DisplayPoint::new(rows.end, 0),
+            snapshot.display_snapshot.max_point(),
+        )
+        .to_point(&snapshot.display_snapshot);
+        let mut diagnostics_by_row = BTreeMap::<DisplayRow, Vec<DiagnosticEntry<Point>>>::new();
+        for entry in snapshot
+            .buffer_snapshot
+            .diagnostics_in_range::<Point>(start..end)
+        {
+            if !entry.diagnostic.is_primary || !max_severity.includes(entry.diagnostic.severity) {
+                continue;
+            }
+            let row = entry
+                .range
+                .start
+                .to_display_point(&snapshot.display_snapshot)
+                .row();
+            if rows.contains(&row) {
+                diagnostics_by_row.entry(row).or_default().push(entry);
+            }
+        }
+
+        let padding = INLINE_DIAGNOSTIC_PADDING_EM_WIDTHS * em_width;
+        diagnostics_by_row
+            .into_iter()
+            .filter_map(|(row, entries)| {
+                // Show the most severe of the row's diagnostics, along with the ones other
+                // sources reported at the same range.
+                let merged = language::merge_diagnostics(entries)
+                    .into_iter()
+                    .min_by_key(|merged| merged.severity())?;
+                let line_ix = row.minus(rows.start) as usize;
+                let diagnostic = &merged.diagnostics[0];
+                let message = diagnostic.message.lines().next().unwrap_or_default();
+                let full_message = SharedString::from(merged.message());
+                let mut element = h_flex()
+                    .id(("inline-diagnostic", row.0 as usize))
+                    .font_family(self.style.text.font().family)
+                    .text_color(crate::diagnostic_style(
+                        diagnostic.severity,
+                        cx.theme().status(),
+                    ))
+                    .line_height(self.style.text.line_height)
+                    .child(util::truncate_and_trailoff(message, max_length))
+                    .tooltip(Tooltip::text(full_message))
+                    .into_any();
+
+                let line_end = if let Some(crease_trailer) = &crease_trailers[line_ix] {
+                    crease_trailer.bounds.right()
+                } else {
+                    content_origin.x - scroll_pixel_position.x + line_layouts[line_ix].width
+                };
+                let origin = point(
+                    line_end + padding,
+                    content_origin.y
+                        + line_height * (row.as_f32() - scroll_pixel_position.y / line_height),
+                );
+                element.prepaint_as_root(origin, AvailableSpace::min_size(), window, cx);
+                Some((row, element))
+            })
+            .collect()
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn layout_blame_entries(
         &self,
@@ -1967,6 +2120,48 @@ impl EditorElement {
         })
     }
 
+    #[allow(clippy::too_many_arguments)]
+    fn layout_bookmark_indicators(
+        &self,
+        line_height: Pixels,
+        range: Range<DisplayRow>,
+        scroll_pixel_position: gpui::Point<Pixels>,
+        gutter_dimensions: &GutterDimensions,
+        gutter_hitbox: &Hitbox,
+        rows_with_hunk_bounds: &HashMap<DisplayRow, Bounds<Pixels>>,
+        snapshot: &EditorSnapshot,
+        code_actions_indicator_row: Option<DisplayRow>,
+        window: &mut Window,
+        cx: &mut App,
+    ) -> Vec<AnyElement> {
+        let show_runnables = snapshot
+            .show_runnables
+            .unwrap_or(EditorSettings::get_global(cx).gutter.runnables);
+        self.editor.update(cx, |editor, cx| {
+            // The bookmarks of the rows showing a runnable's or the code actions' indicator
+            // aren't shown over it.
+            editor
+                .bookmarks_in_display_rows(snapshot, range, show_runnables, cx)
+                .into_iter()
+                .filter(|(display_row, _)| Some(*display_row) != code_actions_indicator_row)
+                .map(|(display_row, note)| {
+                    let button = editor.render_bookmark_indicator(display_row, note, cx);
+                    prepaint_gutter_button(
+                        button,
+                        display_row,
+                        line_height,
+                        gutter_dimensions,
+                        scroll_pixel_position,
+                        gutter_hitbox,
+                        rows_with_hunk_bounds,
+                        window,
+                        cx,
+                    )
+                })
+                .collect_vec()
+        })
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn layout_code_actions_indicator(
         &self,
@@ -2264,21 +2459,141 @@ impl EditorElement {
                 })
                 .collect()
         } else {
-            let chunks = snapshot.highlighted_chunks(rows.clone(), true, style);
-            LineWithInvisibles::from_chunks(
-                chunks,
-                &style,
-                MAX_LINE_LEN,
-                rows.len(),
-                snapshot.mode,
-                editor_width,
-                is_row_soft_wrapped,
-                window,
-                cx,
-            )
+            // The rows of buffers whose language overrides the font features are shaped with
+            // those features.
+            let mut lines = Vec::with_capacity(rows.len());
+            for (run_rows, font_features) in Self::font_features_by_rows(rows.clone(), snapshot, cx)
+            {
+                let run_style = font_features.map(|font_features| {
+                    let mut style = style.clone();
+                    style.text.font_features = font_features;
+                    style
+                });
+                let style = run_style.as_ref().unwrap_or(style);
+                let chunks = match &snapshot.syntax_highlighting {
+                    SyntaxHighlighting::Live => {
+                        snapshot.highlighted_chunks(run_rows.clone(), true, style)
+                    }
+                    SyntaxHighlighting::Deferred(highlights) => snapshot
+                        .highlighted_chunks_with_syntax_highlights(
+                            run_rows.clone(),
+                            true,
+                            Some(highlights.as_ref()),
+                            style,
+                        ),
+                };
+                let row_offset = (run_rows.start.0 - rows.start.0) as usize;
+                lines.extend(LineWithInvisibles::from_chunks(
+                    chunks,
+                    style,
+                    MAX_LINE_LEN,
+                    run_rows.len(),
+                    snapshot.mode,
+                    editor_width,
+                    move |ix| is_row_soft_wrapped(row_offset + ix),
+                    window,
+                    cx,
+                ));
+            }
+            lines
         }
     }
 
+    /// Splits the rows into runs of rows of buffers using the same font features, along with
+    /// the features their language overrides the buffer font's features with, if any. They're
+    /// resolved once per buffer, so that the features don't depend on the cursor position.
+    fn font_features_by_rows(
+        rows: Range<DisplayRow>,
+        snapshot: &EditorSnapshot,
+        cx: &App,
+    ) -> Vec<(Range<DisplayRow>, Option<FontFeatures>)> {
+        let mut features_by_buffer = HashMap::<BufferId, Option<FontFeatures>>::default();
+        let mut runs: Vec<(Range<DisplayRow>, Option<FontFeatures>)> = Vec::new();
+        for row in rows.start.0..rows.end.0 {
+            let point = snapshot
+                .display_snapshot
+                .display_point_to_point(DisplayPoint::new(DisplayRow(row), 0), Bias::Left);
+            let font_features = snapshot
+                .buffer_snapshot
+                .point_to_buffer_point(point)
+                .and_then(|(buffer, _, _)| {
+                    features_by_buffer
+                        .entry(buffer.remote_id())
+                        .or_insert_with(|| {
+                            language_settings(
+                                buffer.language().map(|language| language.name()),
+                                buffer.file(),
+                                cx,
+                            )
+                            .font_features
+                            .clone()
+                        })
+                        .clone()
+                });
+            match runs.last_mut() {
+                Some((run_rows, run_features)) if *run_features == font_features => {
+                    run_rows.end = DisplayRow(row + 1);
+                }
+                _ => runs.push((DisplayRow(row)..DisplayRow(row + 1), font_features)),
+            }
+        }
+        runs
+    }
+
+    /// Lays out the line text was just typed into by splicing the typed text into the layout
+    /// the line had in the previous frame, if the rest of the line didn't change since then.
+    /// The display map may not contain the typed text yet, e.g. while a huge file is wrapped
+    /// again, in which case the typed text is painted ahead of it.
+    fn layout_type_ahead_line(
+        type_ahead: &PendingTypeAhead,
+        last_line: &TypeAheadLine,
+        snapshot: &EditorSnapshot,
+        style: &EditorStyle,
+        window: &mut Window,
+    ) -> Option<(DisplayRow, LineWithInvisibles)> {
+        let position = type_ahead.position.to_display_point(snapshot);
+        let row = position.row();
+        if row != last_line.row {
+            return None;
+        }
+        let column = position.column() as usize;
+        let previous_text = last_line.line.text.as_ref();
+        let expected_text = [
+            previous_text.get(..column)?,
+            type_ahead.text.as_str(),
+            previous_text.get(column..)?,
+        ]
+        .concat();
+        if expected_text.len() > MAX_LINE_LEN {
+            return None;
+        }
+        let line_text = snapshot.line(row);
+        if line_text != expected_text && line_text != previous_text {
+            return None;
+        }
+
+        let font_size = style.text.font_size.to_pixels(window.rem_size());
+        let typed_line = window
+            .text_system()
+            .shape_line(
+                type_ahead.text.clone().into(),
+                font_size,
+                &[style.text.to_run(type_ahead.text.len())],
+            )
+            .log_err()?;
+        let line = last_line.line.with_insertion(column, &typed_line);
+        Some((
+            row,
+            LineWithInvisibles {
+                width: line.width,
+                len: line.len,
+                fragments: smallvec![LineFragment::Text(line)],
+                invisibles: Vec::new(),
+                font_size,
+            },
+        ))
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn prepaint_lines(
         &self,
@@ -2389,6 +2704,7 @@ impl EditorElement {
                 height,
             } => {
                 let selected = selected_buffer_ids.contains(&first_excerpt.buffer_id);
+                let show_unchanged_line_counts = self.editor.read(cx).show_unchanged_line_counts;
                 let mut result = v_flex().id(block_id).w_full();
 
                 if let Some(prev_excerpt) = prev_excerpt {
@@ -2397,6 +2713,10 @@ impl EditorElement {
                             block_id,
                             ExpandExcerptDirection::Down,
                             prev_excerpt.id,
+                            show_unchanged_line_counts.then(|| UnchangedLines {
+                                rows: unchanged_rows_below(prev_excerpt),
+                                between: None,
+                            }),
                             gutter_dimensions,
                             window,
                             cx,
@@ -2425,14 +2745,22 @@ impl EditorElement {
                 starts_new_buffer,
             } => {
                 let color = cx.theme().colors().clone();
+                let show_unchanged_line_counts = self.editor.read(cx).show_unchanged_line_counts;
                 let mut result = v_flex().id(block_id).w_full();
 
                 if let Some(prev_excerpt) = prev_excerpt {
                     if *show_excerpt_controls {
+                        // Between excerpts of the same buffer, the control expanding the next
+                        // one up shows the unchanged lines.
+                        let ends_buffer = *starts_new_buffer || next_excerpt.is_none();
                         result = result.child(self.render_expand_excerpt_control(
                             block_id,
                             ExpandExcerptDirection::Down,
                             prev_excerpt.id,
+                            (show_unchanged_line_counts && ends_buffer).then(|| UnchangedLines {
+                                rows: unchanged_rows_below(prev_excerpt),
+                                between: None,
+                            }),
                             gutter_dimensions,
                             window,
                             cx,
@@ -2466,6 +2794,10 @@ impl EditorElement {
                                 block_id,
                                 ExpandExcerptDirection::Up,
                                 next_excerpt.id,
+                                show_unchanged_line_counts.then(|| UnchangedLines {
+                                    rows: unchanged_rows_above(next_excerpt),
+                                    between: None,
+                                }),
                                 gutter_dimensions,
                                 window,
                                 cx,
@@ -2473,6 +2805,13 @@ impl EditorElement {
                         }
                     } else {
                         if *show_excerpt_controls {
+                            let unchanged_lines = prev_excerpt
+                                .as_ref()
+                                .filter(|_| show_unchanged_line_counts)
+                                .map(|prev_excerpt| UnchangedLines {
+                                    rows: unchanged_rows_between(prev_excerpt, next_excerpt),
+                                    between: Some((prev_excerpt.id, next_excerpt.id)),
+                                });
                             result = result.child(
                                 h_flex()
                                     .relative()
@@ -2488,6 +2827,7 @@ impl EditorElement {
                                         block_id,
                                         ExpandExcerptDirection::Up,
                                         next_excerpt.id,
+                                        unchanged_lines,
                                         gutter_dimensions,
                                         window,
                                         cx,
@@ -2683,6 +3023,7 @@ impl EditorElement {
         block_id: BlockId,
         direction: ExpandExcerptDirection,
         excerpt_id: ExcerptId,
+        unchanged_lines: Option<UnchangedLines>,
         gutter_dimensions: &GutterDimensions,
         window: &Window,
         cx: &mut App,
@@ -2759,6 +3100,39 @@ impl EditorElement {
                         ),
                 ),
         )
+        .when_some(
+            unchanged_lines.filter(|unchanged_lines| unchanged_lines.rows > 0),
+            |this, unchanged_lines| {
+                let rows = unchanged_lines.rows;
+                let label = if rows == 1 {
+                    "1 unchanged line".to_string()
+                } else {
+                    format!("{rows} unchanged lines")
+                };
+                this.child(
+                    div()
+                        .id("unchanged-lines")
+                        .pl_2()
+                        .child(Label::new(label).size(LabelSize::Small).color(Color::Muted))
+                        .when_some(
+                            unchanged_lines.between,
+                            |this, (prev_excerpt, next_excerpt)| {
+                                this.on_click(window.listener_for(&self.editor, {
+                                    move |editor, _, _, cx| {
+                                        editor.expand_unchanged_lines(
+                                            prev_excerpt,
+                                            next_excerpt,
+                                            rows,
+                                            cx,
+                                        );
+                                        cx.stop_propagation();
+                                    }
+                                }))
+                            },
+                        ),
+                )
+            },
+        )
         .on_click(window.listener_for(&self.editor, {
             move |editor, _, _, cx| {
                 editor.expand_excerpt(excerpt_id, direction, cx);
@@ -4698,6 +5072,10 @@ impl EditorElement {
                 }
             });
 
+            for bookmark_indicator in layout.bookmark_indicators.iter_mut() {
+                bookmark_indicator.paint(window, cx);
+            }
+
             for test_indicator in layout.test_indicators.iter_mut() {
                 test_indicator.paint(window, cx);
             }
@@ -4805,6 +5183,7 @@ impl EditorElement {
                 self.paint_redactions(layout, window);
                 self.paint_cursors(layout, window, cx);
                 self.paint_inline_blame(layout, window, cx);
+                self.paint_inline_diagnostics(layout, window, cx);
                 self.paint_diff_hunk_controls(layout, window, cx);
                 window.with_element_namespace("crease_trailers", |window| {
                     for trailer in layout.crease_trailers.iter_mut().flatten() {
@@ -5485,6 +5864,22 @@ impl EditorElement {
         }
     }
 
+    fn paint_inline_diagnostics(
+        &mut self,
+        layout: &mut EditorLayout,
+        window: &mut Window,
+        cx: &mut App,
+    ) {
+        if layout.inline_diagnostics.is_empty() {
+            return;
+        }
+        window.paint_layer(layout.position_map.text_hitbox.bounds, |window| {
+            for (_, diagnostic) in layout.inline_diagnostics.iter_mut() {
+                diagnostic.paint(window, cx);
+            }
+        })
+    }
+
     fn paint_diff_hunk_controls(
         &mut self,
         layout: &mut EditorLayout,
@@ -5622,6 +6017,9 @@ impl EditorElement {
 
             move |event: &MouseDownEvent, phase, window, cx| {
                 if phase == DispatchPhase::Bubble {
+                    // The motions queued by held keys are applied before the click, which may
+                    // move the cursor from where they leave it.
+                    editor.update(cx, |editor, cx| editor.flush_repeated_motions(window, cx));
                     match event.button {
                         MouseButton::Left => editor.update(cx, |editor, cx| {
                             let pending_mouse_down = editor
@@ -6764,9 +7162,10 @@ impl Element for EditorElement {
         window.with_rem_size(rem_size, |window| {
             window.with_text_style(Some(text_style), |window| {
                 window.with_content_mask(Some(ContentMask { bounds }), |window| {
-                    let mut snapshot = self
-                        .editor
-                        .update(cx, |editor, cx| editor.snapshot(window, cx));
+                    let mut snapshot = self.editor.update(cx, |editor, cx| {
+                        editor.refresh_theme_styles(cx);
+                        editor.snapshot(window, cx)
+                    });
                     let style = self.style.clone();
 
                     let font_id = window.text_system().resolve_font(&style.text.font());
@@ -6801,14 +7200,24 @@ impl Element for EditorElement {
                             let wrap_width = match editor.soft_wrap_mode(cx) {
                                 SoftWrap::GitDiff => None,
                                 SoftWrap::None => Some((MAX_LINE_LEN / 2) as f32 * em_advance),
-                                SoftWrap::EditorWidth => Some(editor_width),
+                                SoftWrap::EditorWidth => Some(editor.wrap_width_for_text_area(
+                                    editor_width,
+                                    em_advance,
+                                    cx,
+                                )),
                                 SoftWrap::Column(column) => Some(column as f32 * em_advance),
-                                SoftWrap::Bounded(column) => {
-                                    Some(editor_width.min(column as f32 * em_advance))
-                                }
+                                SoftWrap::Bounded(column) => Some(
+                                    editor
+                                        .wrap_width_for_text_area(editor_width, em_advance, cx)
+                                        .min(column as f32 * em_advance),
+                                ),
                             };
 
-                            if editor.set_wrap_width(wrap_width, cx) {
+                            // Items moved out of a hidden pane are displayed again.
+                            let resumed = editor
+                                .display_map
+                                .update(cx, |map, cx| map.set_wrap_paused(false, cx));
+                            if editor.set_wrap_width(wrap_width, cx) || resumed {
                                 editor.snapshot(window, cx)
                             } else {
                                 snapshot
@@ -6851,15 +7260,11 @@ impl Element for EditorElement {
                     let max_scroll_top = if matches!(snapshot.mode, EditorMode::AutoHeight { .. }) {
                         (max_row - height_in_lines + 1.).max(0.)
                     } else {
-                        let settings = EditorSettings::get_global(cx);
-                        match settings.scroll_beyond_last_line {
-                            ScrollBeyondLastLine::OnePage => max_row,
-                            ScrollBeyondLastLine::Off => (max_row - height_in_lines + 1.).max(0.),
-                            ScrollBeyondLastLine::VerticalScrollMargin => {
-                                (max_row - height_in_lines + 1. + settings.vertical_scroll_margin)
-                                    .max(0.)
-                            }
-                        }
+                        self.editor.read(cx).scroll_manager.max_scroll_top(
+                            max_row,
+                            height_in_lines,
+                            cx,
+                        )
                     };
 
                     // TODO: Autoscrolling for both axes
@@ -6891,7 +7296,18 @@ impl Element for EditorElement {
                         max_row.next_row().0,
                     );
                     let end_row = DisplayRow(end_row);
-
+                    let visible_range = DisplayPoint::new(start_row, 0)
+                        .to_offset(&snapshot.display_snapshot, Bias::Left)
+                        ..DisplayPoint::new(end_row, 0)
+                            .min(snapshot.max_point())
+                            .to_offset(&snapshot.display_snapshot, Bias::Right);
+                    snapshot.syntax_highlighting = self.editor.update(cx, |editor, cx| {
+                        editor.refresh_syntax_highlighting(
+                            visible_range,
+                            &snapshot.buffer_snapshot,
+                            cx,
+                        )
+                    });
                     let row_infos = snapshot
                         .row_infos(start_row)
                         .take((start_row..end_row).len())
@@ -7063,15 +7479,89 @@ impl Element for EditorElement {
                         cx,
                     );
 
-                    let mut line_layouts = Self::layout_lines(
-                        start_row..end_row,
-                        &snapshot,
-                        &self.style,
-                        editor_width,
-                        is_row_soft_wrapped,
-                        window,
-                        cx,
-                    );
+                    let (type_ahead, typed_at) = self.editor.update(cx, |editor, cx| {
+                        // The typed text is only painted while the buffer still contains it.
+                        let buffer = editor.buffer.read(cx).snapshot(cx);
+                        let mut pending = editor.type_ahead.pending.take().filter(|pending| {
+                            buffer.contains_str_at(pending.position, &pending.text)
+                        });
+                        let typed_at = pending.as_mut().and_then(|pending| pending.typed_at.take());
+                        (pending.zip(editor.type_ahead.last_line.take()), typed_at)
+                    });
+                    let type_ahead_line = type_ahead
+                        .and_then(|(pending, last_line)| {
+                            let (row, line) = Self::layout_type_ahead_line(
+                                &pending,
+                                &last_line,
+                                &snapshot,
+                                &self.style,
+                                window,
+                            )?;
+                            // Until the display map contains the typed text, it keeps being
+                            // painted ahead of it.
+                            if snapshot.line(row) == last_line.line.text.as_ref() {
+                                self.editor.update(cx, |editor, _| {
+                                    editor.type_ahead.pending = Some(pending);
+                                    editor.type_ahead.last_line = Some(last_line);
+                                });
+                            }
+                            Some((row, line))
+                        })
+                        .filter(|(row, _)| (start_row..end_row).contains(row));
+                    let mut line_layouts = if let Some((row, line)) = type_ahead_line {
+                        // The typed line is laid out again from the display map on the next frame.
+                        window.request_animation_frame();
+                        let next_row_ix = (row.next_row() - start_row).0 as usize;
+                        let mut line_layouts = Self::layout_lines(
+                            start_row..row,
+                            &snapshot,
+                            &self.style,
+                            editor_width,
+                            is_row_soft_wrapped,
+                            window,
+                            cx,
+                        );
+                        line_layouts.push(line);
+                        line_layouts.extend(Self::layout_lines(
+                            row.next_row()..end_row,
+                            &snapshot,
+                            &self.style,
+                            editor_width,
+                            move |ix| is_row_soft_wrapped(next_row_ix + ix),
+                            window,
+                            cx,
+                        ));
+                        line_layouts
+                    } else {
+                        let line_layouts = Self::layout_lines(
+                            start_row..end_row,
+                            &snapshot,
+                            &self.style,
+                            editor_width,
+                            is_row_soft_wrapped,
+                            window,
+                            cx,
+                        );
+                        let last_line = newest_selection_head.and_then(|head| {
+                            if !(start_row..end_row).contains(&head.row()) {
+                                return None;
+                            }
+                            let layout = line_layouts.get((head.row() - start_row).0 as usize)?;
+                            match layout.fragments.as_slice() {
+                                [LineFragment::Text(line)] if layout.invisibles.is_empty() => {
+                                    Some(TypeAheadLine {
+                                        row: head.row(),
+                                        line: line.clone(),
+                                    })
+                                }
+                                _ => None,
+                            }
+                        });
+                        self.editor.update(cx, |editor, _| {
+                            editor.type_ahead.last_line = last_line;
+                        });
+                        line_layouts
+                    };
 
                     let longest_line_blame_width = self
                         .editor
@@ -7116,6 +7606,10 @@ impl Element for EditorElement {
                     )
                     .width;
 
+                    let soft_wrapped = !matches!(
+                        self.editor.read(cx).soft_wrap_mode(cx),
+                        SoftWrap::None | SoftWrap::GitDiff
+                    );
                     let scrollbar_range_data = ScrollbarRangeData::new(
                         scrollbar_bounds,
                         letter_size,
@@ -7124,6 +7618,7 @@ impl Element for EditorElement {
                         longest_line_blame_width,
                         &style,
                         editor_width,
+                        soft_wrapped,
                         cx,
                     );
 
@@ -7246,10 +7741,25 @@ impl Element for EditorElement {
                             )
                         });
 
+                    let inline_diagnostics = self.layout_inline_diagnostics(
+                        &snapshot,
+                        start_row..end_row,
+                        &line_layouts,
+                        &crease_trailers,
+                        em_width,
+                        content_origin,
+                        scroll_pixel_position,
+                        line_height,
+                        window,
+                        cx,
+                    );
+
                     let mut inline_blame = None;
                     if let Some(newest_selection_head) = newest_selection_head {
                         let display_row = newest_selection_head.row();
-                        if (start_row..end_row).contains(&display_row) {
+                        if (start_row..end_row).contains(&display_row)
+                            && !inline_diagnostics.contains_key(&display_row)
+                        {
                             let line_ix = display_row.minus(start_row) as usize;
                             let row_info = &row_infos[line_ix];
                             let line_layout = &line_layouts[line_ix];
@@ -7473,6 +7983,26 @@ impl Element for EditorElement {
                         Vec::new()
                     };
 
+                    let bookmark_indicators = if snapshot.show_gutter {
+                        self.layout_bookmark_indicators(
+                            line_height,
+                            start_row..end_row,
+                            scroll_pixel_position,
+                            &gutter_dimensions,
+                            &gutter_hitbox,
+                            &rows_with_hunk_bounds,
+                            &snapshot,
+                            code_actions_indicator
+                                .as_ref()
+                                .and(newest_selection_head)
+                                .map(|head| head.row()),
+                            window,
+                            cx,
+                        )
+                    } else {
+                        Vec::new()
+                    };
+
                     self.layout_signature_help(
                         &hitbox,
                         content_origin,
@@ -7626,6 +8156,7 @@ impl Element for EditorElement {
                         line_numbers,
                         blamed_display_rows,
                         inline_blame,
+                        inline_diagnostics,
                         blocks,
                         cursors,
                         visible_cursors,
@@ -7634,12 +8165,14 @@ impl Element for EditorElement {
                         diff_hunk_controls: hunk_controls,
                         mouse_context_menu,
                         test_indicators,
+                        bookmark_indicators,
                         code_actions_indicator,
                         crease_toggles,
                         crease_trailers,
                         tab_invisible,
                         space_invisible,
                         sticky_buffer_header,
+                        typed_at,
                     }
                 })
             })
@@ -7711,7 +8244,13 @@ impl Element for EditorElement {
                     self.paint_mouse_context_menu(layout, window, cx);
                 });
             })
-        })
+        });
+
+        if let Some(typed_at) = layout.typed_at {
+            self.editor.update(cx, |editor, _| {
+                editor.type_ahead.latency.record(typed_at.elapsed());
+            });
+        }
     }
 }
 
@@ -7741,6 +8280,7 @@ impl ScrollbarRangeData {
         longest_line_blame_width: Pixels,
         style: &EditorStyle,
         editor_width: Pixels,
+        soft_wrapped: bool,
         cx: &mut App,
     ) -> ScrollbarRangeData {
         // TODO: Simplify this function down, it requires a lot of parameters
@@ -7748,20 +8288,25 @@ impl ScrollbarRangeData {
         let text_bounds_size = size(longest_line_width, max_row.0 as f32 * letter_size.height);
 
         let settings = EditorSettings::get_global(cx);
-        let scroll_beyond_last_line: Pixels = match settings.scroll_beyond_last_line {
-            ScrollBeyondLastLine::OnePage => px(scrollbar_bounds.size.height / letter_size.height),
-            ScrollBeyondLastLine::Off => px(1.),
-            ScrollBeyondLastLine::VerticalScrollMargin => px(1.0 + settings.vertical_scroll_margin),
-        };
+        let scroll_beyond_last_line = px(1.
+            + settings.scroll_beyond_last_line.rows_beyond_last_line(
+                scrollbar_bounds.size.height / letter_size.height,
+                settings.vertical_scroll_margin,
+            ));
 
         let right_margin = if longest_line_width + longest_line_blame_width >= editor_width {
             letter_size.width + style.scrollbar_width
         } else {
             px(0.0)
         };
+        let scroll_beyond_last_column = if soft_wrapped {
+            px(0.)
+        } else {
+            letter_size.width * settings.scroll_beyond_last_column
+        };
 
         let overscroll = size(
-            right_margin + longest_line_blame_width,
+            right_margin + longest_line_blame_width + scroll_beyond_last_column,
             letter_size.height * scroll_beyond_last_line,
         );
 
@@ -7803,6 +8348,7 @@ pub struct EditorLayout {
     display_hunks: Vec<(DisplayDiffHunk, Option<Hitbox>)>,
     blamed_display_rows: Option<Vec<AnyElement>>,
     inline_blame: Option<AnyElement>,
+    inline_diagnostics: HashMap<DisplayRow, AnyElement>,
     blocks: Vec<BlockLayout>,
     highlighted_ranges: Vec<(Range<DisplayPoint>, Hsla)>,
     highlighted_gutter_ranges: Vec<(Range<DisplayPoint>, Hsla)>,
@@ -7812,6 +8358,7 @@ pub struct EditorLayout {
     selections: Vec<(PlayerColor, Vec<SelectionLayout>)>,
     code_actions_indicator: Option<AnyElement>,
     test_indicators: Vec<AnyElement>,
+    bookmark_indicators: Vec<AnyElement>,
     crease_toggles: Vec<Option<AnyElement>>,
     diff_hunk_controls: Vec<AnyElement>,
     crease_trailers: Vec<Option<CreaseTrailerLayout>>,
@@ -7820,6 +8367,8 @@ pub struct EditorLayout {
     tab_invisible: ShapedLine,
     space_invisible: ShapedLine,
     sticky_buffer_header: Option<AnyElement>,
+    /// When the text painted ahead of its line being shaped again was typed.
+    typed_at: Option<Instant>,
 }
 
 impl EditorLayout {
@@ -7828,6 +8377,14 @@ impl EditorLayout {
     }
 }
 
+/// The unchanged lines of a buffer skipped by an expand control, shown in diff views.
+#[derive(Clone, Copy)]
+struct UnchangedLines {
+    rows: u32,
+    /// The excerpts around the lines, when they're between two excerpts of the buffer.
+    between: Option<(ExcerptId, ExcerptId)>,
+}
+
 struct LineNumberLayout {
     shaped_line: ShapedLine,
     hitbox: Option<Hitbox>,
@@ -8001,6 +8558,14 @@ impl PointForPosition {
 }
 
 impl PositionMap {
+    /// The display row at the vertical position, regardless of the horizontal one, clamped to
+    /// the rows of the editor. Used to hit-test the gutter, whose rows match the text's.
+    pub(crate) fn row_for_position(&self, position: gpui::Point<Pixels>) -> DisplayRow {
+        let y = (position.y - self.text_hitbox.bounds.origin.y).max(px(0.));
+        let row = (y / self.line_height + self.snapshot.scroll_position().y) as u32;
+        DisplayRow(row.min(self.snapshot.max_point().row().0))
+    }
+
     pub(crate) fn point_for_position(&self, position: gpui::Point<Pixels>) -> PointForPosition {
         let text_bounds = self.text_hitbox.bounds;
         let scroll_position = self.snapshot.scroll_position();
@@ -8373,6 +8938,7 @@ pub fn register_action<T: Action>(
         let action = action.downcast_ref().unwrap();
         if phase == DispatchPhase::Bubble {
             editor.update(cx, |editor, cx| {
+                editor.flush_repeated_motions_before(TypeId::of::<T>(), window, cx);
                 listener(editor, action, window, cx);
             })
         }
@@ -8442,6 +9008,84 @@ mod tests {
     use std::num::NonZeroU32;
     use util::test::sample_text;
 
+    #[gpui::test]
+    fn test_font_features_by_rows(cx: &mut TestAppContext) {
+        let no_ligatures = FontFeatures(Arc::new(vec![("calt".to_string(), 0)]));
+        init_test(cx, |settings| {
+            settings.languages.insert(
+                "Rust".into(),
+                language_settings::LanguageSettingsContent {
+                    font_features: Some(no_ligatures.clone()),
+                    ..Default::default()
+                },
+            );
+        });
+
+        let rust_language = Arc::new(language::Language::new(
+            language::LanguageConfig {
+                name: "Rust".into(),
+                ..Default::default()
+            },
+            None,
+        ));
+        let window = cx.add_window(|window, cx| {
+            let text_buffer = cx.new(|cx| language::Buffer::local("a -> b\nc -> d\n", cx));
+            let rust_buffer = cx.new(|cx| {
+                language::Buffer::local("fn f() -> u8 {}\n", cx).with_language(rust_language, cx)
+            });
+            let buffer = cx.new(|cx| {
+                let mut buffer = MultiBuffer::new(language::Capability::ReadWrite);
+                for (buffer_to_add, end) in [
+                    (text_buffer.clone(), Point::new(2, 0)),
+                    (rust_buffer.clone(), Point::new(1, 0)),
+                    (text_buffer, Point::new(1, 0)),
+                ] {
+                    buffer.push_excerpts(
+                        buffer_to_add,
+                        [multi_buffer::ExcerptRange {
+                            context: Point::zero()..end,
+                            primary: None,
+                        }],
+                        cx,
+                    );
+                }
+                buffer
+            });
+            Editor::new(EditorMode::Full, buffer, None, true, window, cx)
+        });
+        let snapshot = window
+            .update(cx, |editor, window, cx| editor.snapshot(window, cx))
+            .unwrap();
+
+        // The rows of the Rust buffer use its features, wherever the cursor is.
+        let runs = cx.update(|cx| {
+            EditorElement::font_features_by_rows(
+                DisplayRow(0)..snapshot.max_point().row().next_row(),
+                &snapshot,
+                cx,
+            )
+        });
+        let rust_row = snapshot
+            .buffer_snapshot
+            .text()
+            .lines()
+            .position(|line| line.starts_with("fn"))
+            .unwrap();
+        let rust_row = Point::new(rust_row as u32, 0)
+            .to_display_point(&snapshot.display_snapshot)
+            .row();
+        let features_at = |row: DisplayRow| {
+            runs.iter()
+                .find(|(rows, _)| rows.contains(&row))
+                .map(|(_, font_features)| font_features.clone())
+                .unwrap()
+        };
+        assert_eq!(runs.len(), 3);
+        assert_eq!(features_at(rust_row), Some(no_ligatures));
+        assert_eq!(features_at(runs[0].0.start), None);
+        assert_eq!(features_at(runs[2].0.start), None);
+    }
+
     #[gpui::test]
     fn test_shape_line_numbers(cx: &mut TestAppContext) {
         init_test(cx, |_| {});
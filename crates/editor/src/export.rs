@@ -0,0 +1,390 @@
+//! Renders the text of an editor with its syntax highlighting, for sharing snippets or printing.
+//!
+//! The selection (or the whole buffer when nothing is selected) is exported either to a
+//! standalone HTML document, or to text colored with 24-bit ANSI escape sequences. Folded
+//! ranges can be kept folded, in which case they are replaced by a placeholder. Copied text
+//! carries the same HTML, without the document around it, when `copy_syntax_highlighting` is on.
+
+use crate::{
+    actions::{ExportAsAnsi, ExportAsHtml},
+    Editor,
+};
+use gpui::{
+    App, ClipboardItem, Context, FontStyle, FontWeight, HighlightStyle, Hsla, Rgba, SharedString,
+    Window,
+};
+use language::Point;
+use multi_buffer::ToOffset as _;
+use std::{fmt::Write as _, ops::Range, path::PathBuf};
+
+/// The text replacing folded ranges, when folds are preserved.
+const FOLD_PLACEHOLDER: &str = "⋯";
+
+/// A run of exported text with a single style.
+#[derive(Clone, Debug, PartialEq)]
+struct StyledText {
+    text: String,
+    style: HighlightStyle,
+}
+
+/// The colors and font of the exported document.
+struct ExportStyle {
+    background: Hsla,
+    foreground: Hsla,
+    font_family: SharedString,
+}
+
+impl Editor {
+    pub fn export_as_html(
+        &mut self,
+        action: &ExportAsHtml,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(project) = self.project.clone() else {
+            return;
+        };
+        let Some((runs, export_style)) = self.styled_text_for_export(action.preserve_folds, cx)
+        else {
+            return;
+        };
+
+        let file = self.target_file_abs_path(cx);
+        let title = file
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "untitled".to_string());
+        let directory = file
+            .as_ref()
+            .and_then(|path| path.parent())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| util::paths::home_dir().clone());
+        let html = render_html(&runs, &title, &export_style);
+
+        let fs = project.read(cx).fs().clone();
+        let path = cx.prompt_for_new_path(&directory);
+        cx.spawn_in(window, |_, _| async move {
+            let Some(path) = path.await?? else {
+                return anyhow::Ok(());
+            };
+            fs.save(&path, &html.as_str().into(), Default::default())
+                .await?;
+            anyhow::Ok(())
+        })
+        .detach_and_log_err(cx);
+    }
+
+    pub fn export_as_ansi(
+        &mut self,
+        action: &ExportAsAnsi,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some((runs, _)) = self.styled_text_for_export(action.preserve_folds, cx) else {
+            return;
+        };
+        cx.write_to_clipboard(ClipboardItem::new_string(render_ansi(&runs)));
+    }
+
+    /// Returns the styled runs of the newest selection, or of the whole buffer when the
+    /// selection is empty.
+    fn styled_text_for_export(
+        &mut self,
+        preserve_folds: bool,
+        cx: &mut Context<Self>,
+    ) -> Option<(Vec<StyledText>, ExportStyle)> {
+        let style = self.style.as_ref()?;
+        let export_style = ExportStyle {
+            background: style.background,
+            foreground: style.text.color,
+            font_family: style.text.font_family.clone(),
+        };
+        let syntax = style.syntax.clone();
+
+        let display_snapshot = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        let snapshot = &display_snapshot.buffer_snapshot;
+        let selection = self.selections.newest::<usize>(cx);
+        let range = if selection.is_empty() {
+            0..snapshot.len()
+        } else {
+            selection.range()
+        };
+
+        let mut folds = Vec::<Range<usize>>::new();
+        if preserve_folds {
+            let mut fold_ranges = display_snapshot
+                .folds_in_range(range.clone())
+                .map(|fold| {
+                    fold.range.start.to_offset(snapshot)..fold.range.end.to_offset(snapshot)
+                })
+                .collect::<Vec<_>>();
+            fold_ranges.sort_by_key(|fold| fold.start);
+            for fold in fold_ranges {
+                let fold = fold.start.max(range.start)..fold.end.min(range.end);
+                match folds.last_mut() {
+                    Some(last) if last.end >= fold.start => last.end = last.end.max(fold.end),
+                    _ => folds.push(fold),
+                }
+            }
+        }
+
+        let mut runs = Vec::<StyledText>::new();
+        let mut start = range.start;
+        for fold in folds.into_iter().chain([range.end..range.end]) {
+            for chunk in snapshot.chunks(start..fold.start, true) {
+                let style = chunk
+                    .syntax_highlight_id
+                    .and_then(|id| id.style(&syntax))
+                    .unwrap_or_default();
+                push_run(&mut runs, chunk.text, style);
+            }
+            if !fold.is_empty() {
+                push_run(&mut runs, FOLD_PLACEHOLDER, HighlightStyle::default());
+            }
+            start = fold.end;
+        }
+
+        Some((runs, export_style))
+    }
+
+    /// Renders the copied ranges, separated by newlines like their text is, as highlighted HTML
+    /// for the clipboard.
+    pub(crate) fn html_for_clipboard(&self, ranges: &[Range<Point>], cx: &App) -> Option<String> {
+        let style = self.style.as_ref()?;
+        let export_style = ExportStyle {
+            background: style.background,
+            foreground: style.text.color,
+            font_family: style.text.font_family.clone(),
+        };
+
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let mut runs = Vec::<StyledText>::new();
+        for (ix, range) in ranges.iter().enumerate() {
+            if ix > 0 {
+                push_run(&mut runs, "\n", HighlightStyle::default());
+            }
+            for chunk in snapshot.chunks(range.clone(), true) {
+                let style = chunk
+                    .syntax_highlight_id
+                    .and_then(|id| id.style(&style.syntax))
+                    .unwrap_or_default();
+                push_run(&mut runs, chunk.text, style);
+            }
+        }
+
+        let mut html = String::new();
+        write_pre(&mut html, &runs, &export_style);
+        Some(html)
+    }
+}
+
+fn push_run(runs: &mut Vec<StyledText>, text: &str, style: HighlightStyle) {
+    match runs.last_mut() {
+        Some(last) if last.style == style => last.text.push_str(text),
+        _ => runs.push(StyledText {
+            text: text.to_string(),
+            style,
+        }),
+    }
+}
+
+/// Renders the runs as a standalone HTML document.
+fn render_html(runs: &[StyledText], title: &str, export_style: &ExportStyle) -> String {
+    let mut html = String::new();
+    writeln!(html, "<!DOCTYPE html>").ok();
+    writeln!(html, "<html>\n<head>\n<meta charset=\"utf-8\">").ok();
+    writeln!(html, "<title>{}</title>", escape_html(title)).ok();
+    writeln!(html, "</head>").ok();
+    writeln!(
+        html,
+        "<body style=\"margin: 0; background: {};\">",
+        css_color(export_style.background)
+    )
+    .ok();
+    write_pre(&mut html, runs, export_style);
+    writeln!(html, "\n</body>\n</html>").ok();
+    html
+}
+
+/// Writes the runs as a `<pre>` element colored like the editor is.
+fn write_pre(html: &mut String, runs: &[StyledText], export_style: &ExportStyle) {
+    write!(
+        html,
+        "<pre style=\"margin: 0; padding: 1em; background: {}; color: {}; font-family: '{}', monospace;\">",
+        css_color(export_style.background),
+        css_color(export_style.foreground),
+        escape_html(&export_style.font_family)
+    )
+    .ok();
+    for run in runs {
+        let css = css_style(&run.style);
+        if css.is_empty() {
+            html.push_str(&escape_html(&run.text));
+        } else {
+            write!(
+                html,
+                "<span style=\"{css}\">{}</span>",
+                escape_html(&run.text)
+            )
+            .ok();
+        }
+    }
+    html.push_str("</pre>");
+}
+
+/// Renders the runs as text colored with 24-bit ANSI escape sequences. Styles are reset
+/// at the end of each line, so that lines can be copied individually.
+fn render_ansi(runs: &[StyledText]) -> String {
+    let mut ansi = String::new();
+    for run in runs {
+        let parameters = ansi_parameters(&run.style);
+        for (ix, line) in run.text.split('\n').enumerate() {
+            if ix > 0 {
+                ansi.push('\n');
+            }
+            if line.is_empty() {
+                continue;
+            }
+            if parameters.is_empty() {
+                ansi.push_str(line);
+            } else {
+                write!(ansi, "\x1b[{parameters}m{line}\x1b[0m").ok();
+            }
+        }
+    }
+    ansi
+}
+
+fn css_style(style: &HighlightStyle) -> String {
+    let mut css = Vec::new();
+    if let Some(color) = style.color {
+        css.push(format!("color: {}", css_color(color)));
+    }
+    if let Some(background) = style.background_color {
+        css.push(format!("background: {}", css_color(background)));
+    }
+    if style
+        .font_weight
+        .map_or(false, |weight| weight >= FontWeight::BOLD)
+    {
+        css.push("font-weight: bold".to_string());
+    }
+    if matches!(
+        style.font_style,
+        Some(FontStyle::Italic | FontStyle::Oblique)
+    ) {
+        css.push("font-style: italic".to_string());
+    }
+    match (style.underline.is_some(), style.strikethrough.is_some()) {
+        (true, true) => css.push("text-decoration: underline line-through".to_string()),
+        (true, false) => css.push("text-decoration: underline".to_string()),
+        (false, true) => css.push("text-decoration: line-through".to_string()),
+        (false, false) => {}
+    }
+    css.join("; ")
+}
+
+fn ansi_parameters(style: &HighlightStyle) -> String {
+    let mut parameters = Vec::new();
+    if style
+        .font_weight
+        .map_or(false, |weight| weight >= FontWeight::BOLD)
+    {
+        parameters.push("1".to_string());
+    }
+    if matches!(
+        style.font_style,
+        Some(FontStyle::Italic | FontStyle::Oblique)
+    ) {
+        parameters.push("3".to_string());
+    }
+    if style.underline.is_some() {
+        parameters.push("4".to_string());
+    }
+    if style.strikethrough.is_some() {
+        parameters.push("9".to_string());
+    }
+    if let Some(color) = style.color {
+        let color = Rgba::from(color);
+        parameters.push(format!("38;2;{}", rgb_components(color).join(";")));
+    }
+    if let Some(background) = style.background_color {
+        let background = Rgba::from(background);
+        parameters.push(format!("48;2;{}", rgb_components(background).join(";")));
+    }
+    parameters.join(";")
+}
+
+fn css_color(color: Hsla) -> String {
+    let color = Rgba::from(color);
+    let [r, g, b] = rgb_components(color);
+    if color.a >= 1. {
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    } else {
+        format!("rgba({r}, {g}, {b}, {:.3})", color.a)
+    }
+}
+
+fn rgb_components(color: Rgba) -> [u8; 3] {
+    [color.r, color.g, color.b].map(|component| (component.clamp(0., 1.) * 255.).round() as u8)
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::rgb;
+
+    #[test]
+    fn test_render_exports() {
+        let keyword = HighlightStyle {
+            color: Some(rgb(0xff0000).into()),
+            font_weight: Some(FontWeight::BOLD),
+            ..HighlightStyle::default()
+        };
+        let runs = [
+            StyledText {
+                text: "fn".into(),
+                style: keyword,
+            },
+            StyledText {
+                text: " a<T>() {\n}".into(),
+                style: HighlightStyle::default(),
+            },
+        ];
+
+        let html = render_html(
+            &runs,
+            "a.rs",
+            &ExportStyle {
+                background: rgb(0x000000).into(),
+                foreground: rgb(0xffffff).into(),
+                font_family: "Zed Mono".into(),
+            },
+        );
+        assert!(html.contains("<title>a.rs</title>"));
+        assert!(html.contains("background: #000000"));
+        assert!(html.contains(
+            "<span style=\"color: #ff0000; font-weight: bold\">fn</span> a&lt;T&gt;() {\n}</pre>"
+        ));
+
+        assert_eq!(
+            render_ansi(&runs),
+            "\x1b[1;38;2;255;0;0mfn\x1b[0m a<T>() {\n}"
+        );
+    }
+}
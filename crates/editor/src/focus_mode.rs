@@ -0,0 +1,150 @@
+//! Dims the text outside of the scope containing the cursor, to help focusing on it.
+//!
+//! The scope is the innermost function around the cursor, according to the text objects of
+//! the buffer's language. Outside of functions, and in buffers without a syntax tree, it is
+//! the paragraph around the cursor: the lines between the nearest blank lines. The scope is
+//! recomputed shortly after the selections stop changing, and on edits.
+
+use crate::{actions::ToggleFocusMode, Editor, EditorMode, EditorSettings};
+use gpui::{App, Context, HighlightStyle, Window};
+use language::{BufferSnapshot, Point, TextObject, TreeSitterOptions};
+use multi_buffer::Anchor;
+use settings::Settings;
+use std::{ops::Range, time::Duration};
+
+/// How long the selections must stay still before the focused scope is updated.
+const FOCUS_MODE_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// How much the text outside of the focused scope is faded out.
+const FOCUS_MODE_FADE_OUT: f32 = 0.6;
+
+/// The key of the text highlights dimming the text outside of the focused scope.
+enum FocusModeDimming {}
+
+impl Editor {
+    pub fn toggle_focus_mode(
+        &mut self,
+        _: &ToggleFocusMode,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.focus_mode = Some(!self.focus_mode_enabled(cx));
+        self.update_focus_mode(cx);
+    }
+
+    pub fn focus_mode_enabled(&self, cx: &App) -> bool {
+        self.mode == EditorMode::Full
+            && self
+                .focus_mode
+                .unwrap_or_else(|| EditorSettings::get_global(cx).focus_mode)
+    }
+
+    /// Updates the focused scope once the selections stop changing.
+    pub(crate) fn refresh_focus_mode(&mut self, cx: &mut Context<Self>) {
+        if !self.focus_mode_enabled(cx) {
+            self.update_focus_mode(cx);
+            return;
+        }
+        self.focus_mode_task = Some(cx.spawn(|this, mut cx| async move {
+            cx.background_executor().timer(FOCUS_MODE_DEBOUNCE).await;
+            this.update(&mut cx, |this, cx| this.update_focus_mode(cx))
+                .ok();
+        }));
+    }
+
+    fn update_focus_mode(&mut self, cx: &mut Context<Self>) {
+        if !self.focus_mode_enabled(cx) {
+            self.focus_mode_task = None;
+            self.clear_highlights::<FocusModeDimming>(cx);
+            return;
+        }
+
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let head = self.selections.newest::<usize>(cx).head();
+        let scope = snapshot
+            .range_to_buffer_ranges(head..head)
+            .into_iter()
+            .next()
+            .and_then(|(buffer, range, excerpt_id)| {
+                let scope = focused_scope(buffer, range.start);
+                let start =
+                    snapshot.anchor_in_excerpt(excerpt_id, buffer.anchor_after(scope.start))?;
+                let end =
+                    snapshot.anchor_in_excerpt(excerpt_id, buffer.anchor_before(scope.end))?;
+                Some(start..end)
+            });
+        let Some(scope) = scope else {
+            self.clear_highlights::<FocusModeDimming>(cx);
+            return;
+        };
+
+        self.highlight_text::<FocusModeDimming>(
+            vec![Anchor::min()..scope.start, scope.end..Anchor::max()],
+            HighlightStyle {
+                fade_out: Some(FOCUS_MODE_FADE_OUT),
+                ..HighlightStyle::default()
+            },
+            cx,
+        );
+    }
+}
+
+/// Returns the range of the scope containing the offset: the innermost function around it,
+/// or else its paragraph.
+fn focused_scope(buffer: &BufferSnapshot, offset: usize) -> Range<usize> {
+    let function = buffer
+        .text_object_ranges(offset..offset, TreeSitterOptions::default())
+        .filter(|(range, object)| {
+            *object == TextObject::AroundFunction && range.start <= offset && offset <= range.end
+        })
+        .map(|(range, _)| range)
+        .min_by_key(|range| range.len());
+    if let Some(function) = function {
+        let start = Point::new(buffer.offset_to_point(function.start).row, 0);
+        return buffer.point_to_offset(start)..function.end;
+    }
+
+    let row = buffer.offset_to_point(offset).row;
+    let mut start_row = row;
+    let mut end_row = row;
+    if !buffer.is_line_blank(row) {
+        while start_row > 0 && !buffer.is_line_blank(start_row - 1) {
+            start_row -= 1;
+        }
+        while end_row < buffer.max_point().row && !buffer.is_line_blank(end_row + 1) {
+            end_row += 1;
+        }
+    }
+    buffer.point_to_offset(Point::new(start_row, 0))
+        ..buffer.point_to_offset(Point::new(end_row, buffer.line_len(end_row)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::editor_lsp_test_context::rust_lang;
+    use gpui::{AppContext as _, TestAppContext};
+    use language::Buffer;
+
+    #[gpui::test]
+    fn test_focused_scope(cx: &mut TestAppContext) {
+        let text = "use a;\n\nfn b() {\n    c();\n}\n\nfn d() {}\n";
+        let buffer = cx.new(|cx| Buffer::local(text, cx).with_language(rust_lang(), cx));
+        cx.run_until_parked();
+        let snapshot = buffer.read_with(cx, |buffer, _| buffer.snapshot());
+        let scope = |offset| &text[focused_scope(&snapshot, offset)];
+        assert_eq!(scope(text.find("c()").unwrap()), "fn b() {\n    c();\n}");
+        assert_eq!(scope(text.find("d()").unwrap()), "fn d() {}");
+        // Outside of functions, the paragraph is focused.
+        assert_eq!(scope(1), "use a;");
+
+        // Without a syntax tree, paragraphs are focused.
+        let text = "one\ntwo\n\nthree\n";
+        let buffer = cx.new(|cx| Buffer::local(text, cx));
+        let snapshot = buffer.read_with(cx, |buffer, _| buffer.snapshot());
+        let scope = |offset| &text[focused_scope(&snapshot, offset)];
+        assert_eq!(scope(5), "one\ntwo");
+        assert_eq!(scope(text.len() - 2), "three");
+        assert_eq!(scope(8), "");
+    }
+}
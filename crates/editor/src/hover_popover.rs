@@ -12,7 +12,7 @@ use gpui::{
     Window,
 };
 use itertools::Itertools;
-use language::{DiagnosticEntry, Language, LanguageRegistry};
+use language::{merge_diagnostics, DiagnosticEntry, Language, LanguageRegistry};
 use lsp::DiagnosticSeverity;
 use markdown::{Markdown, MarkdownStyle};
 use multi_buffer::ToOffset;
@@ -280,28 +280,48 @@ fn show_hover(
             }
 
             let offset = anchor.to_offset(&snapshot.buffer_snapshot);
-            let local_diagnostic = snapshot
+            let local_diagnostics = snapshot
                 .buffer_snapshot
                 .diagnostics_in_range::<usize>(offset..offset)
-                // Find the entry with the most specific range
-                .min_by_key(|entry| entry.range.len());
+                .collect::<Vec<_>>();
+            // Find the entry with the most specific range, along with the ones other sources
+            // reported at the same range.
+            let local_range = local_diagnostics
+                .iter()
+                .min_by_key(|entry| entry.range.len())
+                .map(|entry| entry.range.clone());
+            let merged_diagnostic = local_range.and_then(|local_range| {
+                merge_diagnostics(
+                    local_diagnostics
+                        .into_iter()
+                        .filter(|entry| entry.range == local_range),
+                )
+                .pop()
+            });
 
-            let diagnostic_popover = if let Some(local_diagnostic) = local_diagnostic {
-                let text = match local_diagnostic.diagnostic.source {
-                    Some(ref source) => {
-                        format!("{source}: {}", local_diagnostic.diagnostic.message)
-                    }
-                    None => local_diagnostic.diagnostic.message.clone(),
-                };
+            let diagnostic_popover = if let Some(mut merged_diagnostic) = merged_diagnostic {
+                let text = merged_diagnostic.message();
                 let local_diagnostic = DiagnosticEntry {
-                    diagnostic: local_diagnostic.diagnostic,
+                    diagnostic: merged_diagnostic.diagnostics.swap_remove(0),
                     range: snapshot
                         .buffer_snapshot
-                        .anchor_before(local_diagnostic.range.start)
+                        .anchor_before(merged_diagnostic.range.start)
                         ..snapshot
                             .buffer_snapshot
-                            .anchor_after(local_diagnostic.range.end),
+                            .anchor_after(merged_diagnostic.range.end),
                 };
+                let related_count = local_diagnostic
+                    .range
+                    .start
+                    .buffer_id
+                    .map_or(0, |buffer_id| {
+                        snapshot
+                            .buffer_snapshot
+                            .diagnostic_group(buffer_id, local_diagnostic.diagnostic.group_id)
+                            .map(|entry| 1 + entry.diagnostic.related_information.len())
+                            .sum::<usize>()
+                            .saturating_sub(1)
+                    });
 
                 let mut border_color: Option<Hsla> = None;
                 let mut background_color: Option<Hsla> = None;
@@ -362,6 +382,7 @@ fn show_hover(
 
                 Some(DiagnosticPopover {
                     local_diagnostic,
+                    related_count,
                     parsed_content,
                     border_color,
                     background_color,
@@ -795,6 +816,8 @@ impl InfoPopover {
 #[derive(Debug, Clone)]
 pub struct DiagnosticPopover {
     pub(crate) local_diagnostic: DiagnosticEntry<Anchor>,
+    /// The number of related locations the diagnostic can be expanded with.
+    related_count: usize,
     parsed_content: Option<Entity<Markdown>>,
     border_color: Option<Hsla>,
     background_color: Option<Hsla>,
@@ -843,7 +866,32 @@ impl DiagnosticPopover {
                 *keyboard_grace = false;
                 cx.stop_propagation();
             })
-            .child(markdown_div);
+            .child(markdown_div)
+            .when_some(
+                self.local_diagnostic
+                    .range
+                    .start
+                    .buffer_id
+                    .filter(|_| self.related_count > 0),
+                |this, buffer_id| {
+                    let group_id = self.local_diagnostic.diagnostic.group_id;
+                    let label = if self.related_count == 1 {
+                        "Show 1 Related Location".to_string()
+                    } else {
+                        format!("Show {} Related Locations", self.related_count)
+                    };
+                    this.child(
+                        div().px_1().py_0p5().child(
+                            Button::new("expand-diagnostic", label)
+                                .label_size(LabelSize::Small)
+                                .on_click(cx.listener(move |editor, _, _, cx| {
+                                    editor.expand_diagnostic_details(buffer_id, group_id, cx);
+                                    hide_hover(editor, cx);
+                                })),
+                        ),
+                    )
+                },
+            );
 
         diagnostic_div.into_any_element()
     }
@@ -19,13 +19,13 @@ use crate::{
 use anyhow::Context as _;
 use clock::Global;
 use futures::future;
-use gpui::{AsyncApp, Context, Entity, Task, Window};
+use gpui::{App, AsyncApp, Context, Entity, Task, Window};
 use language::{language_settings::InlayHintKind, Buffer, BufferSnapshot};
 use parking_lot::RwLock;
 use project::{InlayHint, ResolveState};
 
 use collections::{hash_map, HashMap, HashSet};
-use language::language_settings::InlayHintSettings;
+use language::language_settings::{language_settings, InlayHintSettings};
 use smol::lock::Semaphore;
 use sum_tree::Bias;
 use text::{BufferId, ToOffset, ToPoint};
@@ -993,6 +993,12 @@ fn fetch_and_update_hints(
             new_hints.len()
         );
         log::trace!("Fetched hints: {new_hints:?}");
+        let new_hints = cx.update(|cx| {
+            new_hints
+                .into_iter()
+                .filter(|hint| !disabled_in_syntax_scope(hint, &buffer_snapshot, cx))
+                .collect::<Vec<_>>()
+        })?;
 
         let background_task_buffer_snapshot = buffer_snapshot.clone();
         let background_fetch_range = fetch_range.clone();
@@ -1127,6 +1133,26 @@ fn calculate_hint_updates(
     }
 }
 
+/// Whether the settings of the syntax scope at the hint's position, like `macro`, disable the
+/// hints that the settings of its language enable.
+fn disabled_in_syntax_scope(hint: &InlayHint, buffer_snapshot: &BufferSnapshot, cx: &App) -> bool {
+    let settings = language_settings(
+        buffer_snapshot
+            .language_at(hint.position)
+            .map(|language| language.name()),
+        buffer_snapshot.file(),
+        cx,
+    );
+    if settings.scopes.is_empty() || !settings.inlay_hints.enabled {
+        return false;
+    }
+    let scope = buffer_snapshot.syntax_scope_at(hint.position);
+    !settings
+        .in_scope(scope.as_ref().and_then(|scope| scope.override_name()))
+        .inlay_hints
+        .enabled
+}
+
 fn contains_position(
     range: &Range<language::Anchor>,
     position: language::Anchor,
@@ -1265,7 +1291,10 @@ pub mod tests {
     use futures::StreamExt;
     use gpui::{AppContext as _, Context, SemanticVersion, TestAppContext, WindowHandle};
     use itertools::Itertools as _;
-    use language::{language_settings::AllLanguageSettingsContent, Capability, FakeLspAdapter};
+    use language::{
+        language_settings::{AllLanguageSettingsContent, LanguageSettingsContent},
+        Capability, FakeLspAdapter,
+    };
     use language::{Language, LanguageConfig, LanguageMatcher};
     use lsp::FakeLanguageServer;
     use parking_lot::Mutex;
@@ -1382,6 +1411,114 @@ pub mod tests {
             .unwrap();
     }
 
+    #[gpui::test]
+    async fn test_hints_disabled_in_syntax_scopes(cx: &mut gpui::TestAppContext) {
+        let hint_settings = |enabled| InlayHintSettings {
+            enabled,
+            edit_debounce_ms: 0,
+            scroll_debounce_ms: 0,
+            show_type_hints: true,
+            show_parameter_hints: true,
+            show_other_hints: true,
+            show_background: false,
+        };
+        init_test(cx, |settings| {
+            settings.defaults.inlay_hints = Some(hint_settings(true));
+            settings.defaults.scopes = Some(
+                [(
+                    "macro".to_string(),
+                    LanguageSettingsContent {
+                        inlay_hints: Some(hint_settings(false)),
+                        ..Default::default()
+                    },
+                )]
+                .into_iter()
+                .collect(),
+            );
+        });
+
+        let fs = FakeFs::new(cx.background_executor.clone());
+        fs.insert_tree(
+            path!("/a"),
+            json!({
+                "main.rs": "fn main() { let a = vec![1]; }",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, [path!("/a").as_ref()], cx).await;
+        let language_registry = project.read_with(cx, |project, _| project.languages().clone());
+        language_registry.add(Arc::new(
+            Language::new(
+                LanguageConfig {
+                    name: "Rust".into(),
+                    matcher: LanguageMatcher {
+                        path_suffixes: vec!["rs".to_string()],
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                Some(tree_sitter_rust::LANGUAGE.into()),
+            )
+            .with_override_query("(macro_invocation) @macro")
+            .unwrap(),
+        ));
+        let mut fake_servers = language_registry.register_fake_lsp(
+            "Rust",
+            FakeLspAdapter {
+                capabilities: lsp::ServerCapabilities {
+                    inlay_hint_provider: Some(lsp::OneOf::Left(true)),
+                    ..Default::default()
+                },
+                initializer: Some(Box::new(|fake_server| {
+                    fake_server.handle_request::<lsp::request::InlayHintRequest, _, _>(
+                        |_, _| async move {
+                            Ok(Some(
+                                [(17, ": i32"), (25, ": usize")]
+                                    .into_iter()
+                                    .map(|(column, label)| lsp::InlayHint {
+                                        position: lsp::Position::new(0, column),
+                                        label: lsp::InlayHintLabel::String(label.to_string()),
+                                        kind: None,
+                                        text_edits: None,
+                                        tooltip: None,
+                                        padding_left: None,
+                                        padding_right: None,
+                                        data: None,
+                                    })
+                                    .collect(),
+                            ))
+                        },
+                    );
+                })),
+                ..Default::default()
+            },
+        );
+
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_local_buffer(path!("/a/main.rs"), cx)
+            })
+            .await
+            .unwrap();
+        let editor =
+            cx.add_window(|window, cx| Editor::for_buffer(buffer, Some(project), window, cx));
+        cx.executor().run_until_parked();
+        let _fake_server = fake_servers.next().await.unwrap();
+        cx.executor().run_until_parked();
+
+        editor
+            .update(cx, |editor, _window, cx| {
+                let expected_hints = vec![": i32".to_string()];
+                assert_eq!(
+                    expected_hints,
+                    cached_hint_labels(editor),
+                    "Should not get the hints in the macro, whose scope disables them"
+                );
+                assert_eq!(expected_hints, visible_hint_labels(editor, cx));
+            })
+            .unwrap();
+    }
+
     #[gpui::test]
     async fn test_cache_update_on_lsp_completion_tasks(cx: &mut gpui::TestAppContext) {
         init_test(cx, |settings| {
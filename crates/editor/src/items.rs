@@ -2,8 +2,9 @@ use crate::{
     editor_settings::SeedQuerySetting,
     persistence::{SerializedEditor, DB},
     scroll::ScrollAnchor,
-    Anchor, Autoscroll, Editor, EditorEvent, EditorSettings, ExcerptId, ExcerptRange, FormatTarget,
-    MultiBuffer, MultiBufferSnapshot, NavigationData, SearchWithinRange, ToPoint as _,
+    Anchor, AnchorRangeExt as _, Autoscroll, Editor, EditorEvent, EditorSettings, ExcerptId,
+    ExcerptRange, FormatTarget, MultiBuffer, MultiBufferSnapshot, NavigationData,
+    SearchWithinRange, ToPoint as _,
 };
 use anyhow::{anyhow, Context as _, Result};
 use collections::HashSet;
@@ -23,7 +24,7 @@ use project::{
     lsp_store::FormatTrigger, project_settings::ProjectSettings, search::SearchQuery, Project,
     ProjectItem as _, ProjectPath,
 };
-use rpc::proto::{self, update_view, PeerId};
+use rpc::proto::{self, update_view, Message as _, PeerId};
 use settings::Settings;
 use std::{
     any::TypeId,
@@ -38,13 +39,13 @@ use text::{BufferId, Selection};
 use theme::{Theme, ThemeSettings};
 use ui::{prelude::*, IconDecorationKind};
 use util::{paths::PathExt, ResultExt, TryFutureExt};
-use workspace::item::{Dedup, ItemSettings, SerializableItem, TabContentParams};
+use workspace::item::{ClosedItemState, Dedup, ItemSettings, SerializableItem, TabContentParams};
 use workspace::{
     item::{BreadcrumbText, FollowEvent},
     searchable::SearchOptions,
 };
 use workspace::{
-    item::{FollowableItem, Item, ItemEvent, ProjectItem},
+    item::{FollowableItem, Item, ItemEvent, ProjectItem, RevealPosition},
     searchable::{Direction, SearchEvent, SearchableItem, SearchableItemHandle},
     ItemId, ItemNavHistory, ToolbarItemLocation, ViewId, Workspace, WorkspaceId,
 };
@@ -590,6 +591,99 @@ impl Item for Editor {
         }
     }
 
+    fn closed_state(&mut self, cx: &mut Context<Self>) -> Option<ClosedItemState> {
+        if !self.buffer.read(cx).is_singleton() {
+            return None;
+        }
+        let cursor = self.selections.newest::<Point>(cx).head();
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        let buffer = &display_map.buffer_snapshot;
+        let scroll_top_row = self.scroll_manager.anchor().top_row(buffer);
+        let folds = display_map
+            .folds_in_range(0..buffer.len())
+            .map(|fold| {
+                let range = fold.range.to_point(buffer);
+                (range.start.row, range.start.column)..(range.end.row, range.end.column)
+            })
+            .collect();
+        Some(ClosedItemState {
+            cursor: (cursor.row, cursor.column),
+            scroll_top_row,
+            folds,
+        })
+    }
+
+    fn restore_closed_state(
+        &mut self,
+        state: ClosedItemState,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let buffer = self.buffer.read(cx).snapshot(cx);
+        let point = |(row, column)| buffer.clip_point(Point::new(row, column), Bias::Left);
+        let folds = state
+            .folds
+            .into_iter()
+            .map(|range| point(range.start)..point(range.end))
+            .collect::<Vec<_>>();
+        let cursor = point(state.cursor);
+        let scroll_anchor = ScrollAnchor {
+            anchor: buffer.anchor_before(point((state.scroll_top_row, 0))),
+            offset: gpui::Point::default(),
+        };
+        self.fold_ranges(folds, false, window, cx);
+        self.change_selections(None, window, cx, |s| s.select_ranges([cursor..cursor]));
+        self.set_scroll_anchor(scroll_anchor, window, cx);
+    }
+
+    fn reveal_range(
+        &mut self,
+        range: Range<Point>,
+        position: RevealPosition,
+        flash: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> bool {
+        let multibuffer = self.buffer.read(cx);
+        let Some(buffer) = multibuffer.as_singleton() else {
+            return false;
+        };
+        let snapshot = buffer.read(cx).snapshot();
+        let range = snapshot.clip_point(range.start, Bias::Left)
+            ..snapshot.clip_point(range.end, Bias::Left);
+        // An empty range, like a cursor position, flashes its line. Reversed ranges select
+        // backwards, towards their start.
+        let flashed_range = if range.start == range.end {
+            Point::new(range.start.row, 0)
+                ..Point::new(range.start.row, snapshot.line_len(range.start.row))
+        } else {
+            range.start.min(range.end)..range.start.max(range.end)
+        };
+        let anchor = |point| multibuffer.buffer_point_to_anchor(&buffer, point, cx);
+        let (Some(start), Some(end), Some(flash_start), Some(flash_end)) = (
+            anchor(range.start),
+            anchor(range.end),
+            anchor(flashed_range.start),
+            anchor(flashed_range.end),
+        ) else {
+            return false;
+        };
+
+        let autoscroll = match position {
+            RevealPosition::Center => Autoscroll::center(),
+            RevealPosition::Fit => Autoscroll::fit(),
+            RevealPosition::Top => Autoscroll::focused(),
+            RevealPosition::RowsFromTop(rows) => Autoscroll::top_relative(rows as usize),
+        };
+        self.change_selections(Some(autoscroll), window, cx, |s| {
+            s.select_anchor_ranges([start..end])
+        });
+        if flash {
+            self.flash_range(flash_start..flash_end, cx);
+        }
+        true
+    }
+
     fn tab_tooltip_text(&self, cx: &App) -> Option<SharedString> {
         let file_path = self
             .buffer()
@@ -742,8 +836,13 @@ impl Item for Editor {
         self.hide_hovered_link(cx);
     }
 
+    fn pane_visibility_changed(&mut self, visible: bool, _: &mut Window, cx: &mut Context<Self>) {
+        self.display_map
+            .update(cx, |map, cx| map.set_wrap_paused(!visible, cx));
+    }
+
     fn is_dirty(&self, cx: &App) -> bool {
-        self.buffer().read(cx).read(cx).is_dirty()
+        self.has_merge_conflicts() || self.buffer().read(cx).read(cx).is_dirty()
     }
 
     fn has_deleted_file(&self, cx: &App) -> bool {
@@ -755,6 +854,9 @@ impl Item for Editor {
     }
 
     fn can_save(&self, cx: &App) -> bool {
+        if self.has_merge_conflicts() {
+            return true;
+        }
         let buffer = &self.buffer().read(cx);
         if let Some(buffer) = buffer.as_singleton() {
             buffer.read(cx).project_path(cx).is_some()
@@ -770,6 +872,9 @@ impl Item for Editor {
         window: &mut Window,
         cx: &mut Context<Self>,
     ) -> Task<Result<()>> {
+        if self.has_merge_conflicts() {
+            return self.save_merge_resolution(project, window, cx);
+        }
         self.report_editor_event("Editor Saved", None, cx);
         let buffers = self.buffer().clone().read(cx).all_buffers();
         let buffers = buffers
@@ -877,6 +982,59 @@ impl Item for Editor {
         })
     }
 
+    fn can_merge(&self, cx: &App) -> bool {
+        self.buffer().read(cx).all_buffers().iter().any(|buffer| {
+            let buffer = buffer.read(cx);
+            buffer.has_conflict() && buffer.file().is_some_and(|file| file.as_local().is_some())
+        })
+    }
+
+    fn merge(
+        &mut self,
+        resolve_conflicts: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<bool>> {
+        // Merges known to conflict aren't computed again, unless to resolve their conflicts.
+        let buffers = self
+            .buffer()
+            .read(cx)
+            .all_buffers()
+            .into_iter()
+            .filter(|buffer| {
+                let buffer = buffer.read(cx);
+                buffer.has_conflict() && (resolve_conflicts || !buffer.has_known_merge_conflicts())
+            })
+            .collect::<Vec<_>>();
+        if buffers.is_empty() {
+            return Task::ready(Ok(false));
+        }
+        let merges = buffers
+            .iter()
+            .map(|buffer| buffer.read(cx).merge_with_file(cx))
+            .collect::<Vec<_>>();
+        cx.spawn_in(window, |this, mut cx| async move {
+            let merges = try_join_all(merges).await?;
+            let mut merged_all = true;
+            for (buffer, merge) in buffers.into_iter().zip(merges) {
+                let conflicts =
+                    buffer.update(&mut cx, |buffer, cx| buffer.apply_file_merge(merge, cx))??;
+                if let Some(conflicts) = conflicts {
+                    merged_all = false;
+                    if resolve_conflicts {
+                        this.update_in(&mut cx, |editor, window, cx| {
+                            editor.open_merge_conflicts(buffer, conflicts, window, cx)
+                        })??;
+                    }
+                }
+            }
+            this.update(&mut cx, |editor, cx| {
+                editor.request_autoscroll(Autoscroll::fit(), cx)
+            })?;
+            Ok(merged_all)
+        })
+    }
+
     fn as_searchable(&self, handle: &Entity<Self>) -> Option<Box<dyn SearchableItemHandle>> {
         Some(Box::new(handle.clone()))
     }
@@ -941,9 +1099,15 @@ impl Item for Editor {
         &mut self,
         workspace: &mut Workspace,
         _window: &mut Window,
-        _: &mut Context<Self>,
+        cx: &mut Context<Self>,
     ) {
         self.workspace = Some((workspace.weak_handle(), workspace.database_id()));
+        // The font may have failed to load before the editor was added to the workspace.
+        let wrap_map = self.display_map.read(cx).wrap_map();
+        let font_warning = wrap_map.read(cx).font_warning().cloned();
+        if let Some(warning) = font_warning {
+            self.show_wrap_font_warning(&warning, cx);
+        }
     }
 
     fn to_item_events(event: &EditorEvent, mut f: impl FnMut(ItemEvent)) {
@@ -1011,10 +1175,12 @@ impl SerializableItem for Editor {
             .get_serialized_editor(item_id, workspace_id)
             .context("Failed to query editor state")
         {
-            Ok(Some(serialized_editor)) => {
-                if ProjectSettings::get_global(cx)
-                    .session
-                    .restore_unsaved_buffers
+            Ok(Some(mut serialized_editor)) => {
+                let session_settings = &ProjectSettings::get_global(cx).session;
+                if serialized_editor.contents.is_none() && !session_settings.restore_undo_history {
+                    serialized_editor.operation_log = None;
+                }
+                if session_settings.restore_unsaved_buffers || serialized_editor.contents.is_none()
                 {
                     serialized_editor
                 } else {
@@ -1023,6 +1189,7 @@ impl SerializableItem for Editor {
                         contents: None,
                         language: None,
                         mtime: None,
+                        operation_log: None,
                     }
                 }
             }
@@ -1039,6 +1206,7 @@ impl SerializableItem for Editor {
                 abs_path: None,
                 contents: Some(contents),
                 language,
+                operation_log,
                 ..
             } => window.spawn(cx, |mut cx| {
                 let project = project.clone();
@@ -1068,7 +1236,7 @@ impl SerializableItem for Editor {
                         if let Some(language) = language {
                             buffer.set_language(Some(language), cx);
                         }
-                        buffer.set_text(contents, cx);
+                        restore_buffer_contents(buffer, contents, operation_log, cx);
                     })?;
 
                     cx.update(|window, cx| {
@@ -1085,6 +1253,7 @@ impl SerializableItem for Editor {
                 abs_path: Some(abs_path),
                 contents,
                 mtime,
+                operation_log,
                 ..
             } => {
                 let project_item = project.update(cx, |project, cx| {
@@ -1121,7 +1290,11 @@ impl SerializableItem for Editor {
                                             cx,
                                         );
                                     }
-                                    buffer.set_text(buffer_text, cx);
+                                    restore_buffer_contents(buffer, buffer_text, operation_log, cx);
+                                })?;
+                            } else if let Some(operation_log) = operation_log {
+                                buffer.update(&mut cx, |buffer, cx| {
+                                    restore_undo_history(buffer, operation_log, cx);
                                 })?;
                             }
 
@@ -1187,6 +1360,10 @@ impl SerializableItem for Editor {
         let workspace_id = workspace.database_id()?;
 
         let buffer = self.buffer().read(cx).as_singleton()?;
+        // Virtual documents can't be restored without opening their URIs again.
+        if project.read(cx).is_virtual_document(&buffer) {
+            return None;
+        }
 
         let abs_path = buffer.read(cx).file().and_then(|file| {
             let worktree_id = file.worktree_id(cx);
@@ -1205,8 +1382,25 @@ impl SerializableItem for Editor {
         let mtime = buffer.read(cx).saved_mtime();
 
         let snapshot = buffer.read(cx).snapshot();
+        let has_undo_history = buffer.read(cx).peek_undo_stack().is_some()
+            || buffer.read(cx).peek_redo_stack().is_some();
+        let serialize_undo_history = abs_path.is_some()
+            && !is_dirty
+            && has_undo_history
+            && ProjectSettings::get_global(cx).session.restore_undo_history;
+        let operation_log = if serialize_dirty_buffers && is_dirty {
+            Some(buffer.read(cx).operation_log(cx))
+        } else if serialize_undo_history {
+            Some(buffer.read(cx).undo_history_log(cx))
+        } else {
+            None
+        };
 
         Some(cx.spawn_in(window, |_this, cx| async move {
+            let operation_log = match operation_log {
+                Some(operation_log) => Some(operation_log.await.encode_to_vec()),
+                None => None,
+            };
             cx.background_executor()
                 .spawn(async move {
                     let (contents, language) = if serialize_dirty_buffers && is_dirty {
@@ -1222,6 +1416,7 @@ impl SerializableItem for Editor {
                         contents,
                         language,
                         mtime,
+                        operation_log,
                     };
                     DB.save_serialized_editor(item_id, workspace_id, editor)
                         .await
@@ -1242,6 +1437,46 @@ impl SerializableItem for Editor {
     }
 }
 
+/// Restores the unsaved contents of a deserialized buffer, replaying their operation log
+/// when it still applies to the buffer, so that the undo history is restored too.
+fn restore_buffer_contents(
+    buffer: &mut Buffer,
+    contents: String,
+    operation_log: Option<Vec<u8>>,
+    cx: &mut Context<Buffer>,
+) {
+    let operation_log =
+        operation_log.and_then(|log| proto::BufferOperationLog::decode(log.as_slice()).log_err());
+    if let Some(operation_log) = operation_log {
+        match buffer.restore_operation_log(operation_log, cx) {
+            Ok(()) if buffer.text() == contents => return,
+            Ok(()) => log::warn!("operation log of buffer doesn't match its contents"),
+            // The file changed on disk since the log was taken.
+            Err(error) => log::debug!("not restoring operation log: {error:#}"),
+        }
+    }
+    buffer.set_text(contents, cx);
+}
+
+/// Restores the undo history of a deserialized buffer without unsaved contents, unless its file
+/// changed on disk since the history was persisted.
+fn restore_undo_history(buffer: &mut Buffer, operation_log: Vec<u8>, cx: &mut Context<Buffer>) {
+    let Some(operation_log) = proto::BufferOperationLog::decode(operation_log.as_slice()).log_err()
+    else {
+        return;
+    };
+    if buffer.fingerprint().to_u64() != operation_log.saved_text_fingerprint {
+        log::debug!("not restoring undo history: the file changed on disk");
+        return;
+    }
+    let old_text = buffer.text();
+    match buffer.restore_operation_log(operation_log, cx) {
+        Ok(()) if buffer.text() == old_text => {}
+        Ok(()) => log::warn!("restoring the undo history of a buffer changed its contents"),
+        Err(error) => log::debug!("not restoring undo history: {error:#}"),
+    }
+}
+
 impl ProjectItem for Editor {
     type Item = Buffer;
 
@@ -1258,6 +1493,57 @@ impl ProjectItem for Editor {
 impl EventEmitter<SearchEvent> for Editor {}
 
 pub(crate) enum BufferSearchHighlights {}
+/// Dimmed highlights for the matches outside of the ranges that the search is
+/// restricted to.
+pub(crate) enum OutOfScopeSearchHighlights {}
+
+/// Searches the given ranges of a multi-buffer, returning the matches in order.
+async fn search_ranges(
+    buffer: &MultiBufferSnapshot,
+    query: &project::search::SearchQuery,
+    ranges: Vec<Range<Anchor>>,
+) -> Vec<Range<Anchor>> {
+    let mut matches = Vec::new();
+    for range in ranges {
+        for (search_buffer, search_range, excerpt_id, deleted_hunk_anchor) in
+            buffer.range_to_buffer_ranges_with_deleted_hunks(range)
+        {
+            matches.extend(
+                query
+                    .search(search_buffer, Some(search_range.clone()))
+                    .await
+                    .into_iter()
+                    .map(|match_range| {
+                        if let Some(deleted_hunk_anchor) = deleted_hunk_anchor {
+                            let start =
+                                search_buffer.anchor_after(search_range.start + match_range.start);
+                            let end =
+                                search_buffer.anchor_before(search_range.start + match_range.end);
+                            Anchor {
+                                diff_base_anchor: Some(start),
+                                ..deleted_hunk_anchor
+                            }..Anchor {
+                                diff_base_anchor: Some(end),
+                                ..deleted_hunk_anchor
+                            }
+                        } else {
+                            let start =
+                                search_buffer.anchor_after(search_range.start + match_range.start);
+                            let end =
+                                search_buffer.anchor_before(search_range.start + match_range.end);
+                            Anchor::range_in_buffer(
+                                excerpt_id,
+                                search_buffer.remote_id(),
+                                start..end,
+                            )
+                        }
+                    }),
+            );
+        }
+    }
+    matches
+}
+
 impl SearchableItem for Editor {
     type Match = Range<Anchor>;
 
@@ -1270,6 +1556,7 @@ impl SearchableItem for Editor {
     }
 
     fn clear_matches(&mut self, _: &mut Window, cx: &mut Context<Self>) {
+        self.clear_background_highlights::<OutOfScopeSearchHighlights>(cx);
         if self
             .clear_background_highlights::<BufferSearchHighlights>(cx)
             .is_some()
@@ -1316,6 +1603,7 @@ impl SearchableItem for Editor {
         }
 
         if !enabled {
+            self.clear_background_highlights::<OutOfScopeSearchHighlights>(cx);
             return;
         }
 
@@ -1524,54 +1812,43 @@ impl SearchableItem for Editor {
                 ranges.iter().cloned().collect::<Vec<_>>()
             });
 
-        cx.background_executor().spawn(async move {
-            let mut ranges = Vec::new();
-
-            let search_within_ranges = if search_within_ranges.is_empty() {
-                vec![buffer.anchor_before(0)..buffer.anchor_after(buffer.len())]
-            } else {
-                search_within_ranges
-            };
-
-            for range in search_within_ranges {
-                for (search_buffer, search_range, excerpt_id, deleted_hunk_anchor) in
-                    buffer.range_to_buffer_ranges_with_deleted_hunks(range)
-                {
-                    ranges.extend(
-                        query
-                            .search(search_buffer, Some(search_range.clone()))
-                            .await
-                            .into_iter()
-                            .map(|match_range| {
-                                if let Some(deleted_hunk_anchor) = deleted_hunk_anchor {
-                                    let start = search_buffer
-                                        .anchor_after(search_range.start + match_range.start);
-                                    let end = search_buffer
-                                        .anchor_before(search_range.start + match_range.end);
-                                    Anchor {
-                                        diff_base_anchor: Some(start),
-                                        ..deleted_hunk_anchor
-                                    }..Anchor {
-                                        diff_base_anchor: Some(end),
-                                        ..deleted_hunk_anchor
-                                    }
-                                } else {
-                                    let start = search_buffer
-                                        .anchor_after(search_range.start + match_range.start);
-                                    let end = search_buffer
-                                        .anchor_before(search_range.start + match_range.end);
-                                    Anchor::range_in_buffer(
-                                        excerpt_id,
-                                        search_buffer.remote_id(),
-                                        start..end,
-                                    )
-                                }
-                            }),
-                    );
-                }
+        let search = cx.background_executor().spawn(async move {
+            // The whole buffer is searched once, to also find the matches to dim outside of the
+            // ranges the search is restricted to.
+            let whole_buffer = buffer.anchor_before(0)..buffer.anchor_after(buffer.len());
+            let matches = search_ranges(&buffer, &query, vec![whole_buffer]).await;
+            if search_within_ranges.is_empty() {
+                return (matches, Vec::new());
             }
 
-            ranges
+            let scopes = search_within_ranges
+                .iter()
+                .map(|range| range.to_offset(&buffer))
+                .collect::<Vec<_>>();
+            matches.into_iter().partition(|range| {
+                let range = range.to_offset(&buffer);
+                scopes
+                    .iter()
+                    .any(|scope| scope.start <= range.start && range.end <= scope.end)
+            })
+        });
+
+        cx.spawn(|editor, mut cx| async move {
+            let (matches, out_of_scope_matches) = search.await;
+            editor
+                .update(&mut cx, |editor, cx| {
+                    if out_of_scope_matches.is_empty() {
+                        editor.clear_background_highlights::<OutOfScopeSearchHighlights>(cx);
+                    } else {
+                        editor.highlight_background::<OutOfScopeSearchHighlights>(
+                            &out_of_scope_matches,
+                            |theme| theme.search_match_background.opacity(0.3),
+                            cx,
+                        );
+                    }
+                })
+                .ok();
+            matches
         })
     }
 
@@ -1728,6 +2005,65 @@ mod tests {
         assert_eq!(path_for_file(&file, 0, false, cx), None);
     }
 
+    #[gpui::test]
+    async fn test_open_path_at(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            path!("/dir"),
+            serde_json::json!({ "a.rs": "one\ntwo\nthree\nfour" }),
+        )
+        .await;
+        let project = Project::test(fs, [path!("/dir").as_ref()], cx).await;
+        let (workspace, cx) =
+            cx.add_window_view(|window, cx| Workspace::test_new(project.clone(), window, cx));
+        let worktree_id = project.update(cx, |project, cx| {
+            project.worktrees(cx).next().unwrap().read(cx).id()
+        });
+
+        let item = workspace
+            .update_in(cx, |workspace, window, cx| {
+                workspace.open_path_at(
+                    (worktree_id, "a.rs"),
+                    Point::new(2, 1)..Point::new(2, 4),
+                    Default::default(),
+                    window,
+                    cx,
+                )
+            })
+            .await
+            .unwrap();
+        let editor = item.downcast::<Editor>().unwrap();
+        editor.update(cx, |editor, cx| {
+            assert_eq!(
+                editor.selections.newest::<Point>(cx).range(),
+                Point::new(2, 1)..Point::new(2, 4)
+            );
+        });
+
+        // Opening a path that's open already moves to the range in its item.
+        let item = workspace
+            .update_in(cx, |workspace, window, cx| {
+                workspace.open_path_at(
+                    (worktree_id, "a.rs"),
+                    Point::new(0, 2)..Point::new(0, 2),
+                    Default::default(),
+                    window,
+                    cx,
+                )
+            })
+            .await
+            .unwrap();
+        assert_eq!(item.item_id(), editor.entity_id());
+        editor.update(cx, |editor, cx| {
+            assert_eq!(
+                editor.selections.newest::<Point>(cx).range(),
+                Point::new(0, 2)..Point::new(0, 2)
+            );
+        });
+    }
+
     async fn deserialize_editor(
         item_id: ItemId,
         workspace_id: WorkspaceId,
@@ -1793,6 +2129,7 @@ mod tests {
                 contents: Some("fn main() {}".to_string()),
                 language: Some("Rust".to_string()),
                 mtime: Some(mtime),
+                operation_log: None,
             };
 
             DB.save_serialized_editor(item_id, workspace_id, serialized_editor.clone())
@@ -1825,6 +2162,7 @@ mod tests {
                 contents: None,
                 language: None,
                 mtime: None,
+                operation_log: None,
             };
 
             DB.save_serialized_editor(item_id, workspace_id, serialized_editor)
@@ -1861,6 +2199,7 @@ mod tests {
                 contents: Some("hello".to_string()),
                 language: Some("Rust".to_string()),
                 mtime: None,
+                operation_log: None,
             };
 
             DB.save_serialized_editor(item_id, workspace_id, serialized_editor)
@@ -1898,6 +2237,7 @@ mod tests {
                 contents: Some("fn main() {}".to_string()),
                 language: Some("Rust".to_string()),
                 mtime: Some(old_mtime),
+                operation_log: None,
             };
 
             DB.save_serialized_editor(item_id, workspace_id, serialized_editor)
@@ -0,0 +1,232 @@
+//! Coalesces the motions repeated while their key is held down.
+//!
+//! Applying a motion updates the selections, autoscrolls and renders the editor. When a held key
+//! repeats faster than frames are drawn, applying every repetition as it arrives builds a backlog
+//! of events that keeps moving the cursor after the key is released. Instead, one repetition is
+//! applied per frame, and the identical ones arriving before the next frame are counted, then
+//! applied at once right before it's drawn. Any other action or input applies the pending
+//! motions first, so that they keep their order.
+
+use crate::{
+    actions::{
+        MoveDown, MoveDownByLines, MoveLeft, MovePageDown, MovePageUp, MoveRight, MoveUp,
+        MoveUpByLines, SelectDown, SelectDownByLines, SelectLeft, SelectRight, SelectUp,
+        SelectUpByLines,
+    },
+    Editor, EditorMode,
+};
+use gpui::{Context, Window};
+use std::any::TypeId;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum RepeatedMotion {
+    Left,
+    Right,
+    Up,
+    Down,
+    SelectLeft,
+    SelectRight,
+    SelectUp,
+    SelectDown,
+    PageUp { center_cursor: bool },
+    PageDown { center_cursor: bool },
+}
+
+impl RepeatedMotion {
+    /// Whether actions of the type are motions that may be coalesced.
+    fn is_motion_action(action: TypeId) -> bool {
+        [
+            TypeId::of::<MoveLeft>(),
+            TypeId::of::<MoveRight>(),
+            TypeId::of::<MoveUp>(),
+            TypeId::of::<MoveDown>(),
+            TypeId::of::<SelectLeft>(),
+            TypeId::of::<SelectRight>(),
+            TypeId::of::<SelectUp>(),
+            TypeId::of::<SelectDown>(),
+            TypeId::of::<MovePageUp>(),
+            TypeId::of::<MovePageDown>(),
+        ]
+        .contains(&action)
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct KeyRepeatState {
+    /// Whether a repeated motion was applied since the last frame.
+    applied_in_frame: bool,
+    /// The motion repeated since, and how many times.
+    queued: Option<(RepeatedMotion, u32)>,
+    flushing: bool,
+}
+
+impl Editor {
+    /// Queues the motion if it repeats a held key since a repeated motion was applied in this
+    /// frame, in which case it must not be applied now.
+    pub(crate) fn queue_repeated_motion(
+        &mut self,
+        motion: RepeatedMotion,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> bool {
+        if !window.is_dispatching_key_repeat()
+            || self.key_repeat.flushing
+            || self.mode != EditorMode::Full
+            || self.pending_rename.is_some()
+            || self.context_menu_visible()
+        {
+            return false;
+        }
+
+        if let Some((queued_motion, count)) = self.key_repeat.queued.as_mut() {
+            if *queued_motion == motion {
+                *count += 1;
+                return true;
+            }
+            self.flush_repeated_motions(window, cx);
+        }
+        if self.key_repeat.applied_in_frame {
+            self.key_repeat.queued = Some((motion, 1));
+            return true;
+        }
+
+        self.key_repeat.applied_in_frame = true;
+        let editor = cx.entity().downgrade();
+        window.on_next_frame(move |window, cx| {
+            editor
+                .update(cx, |editor, cx| {
+                    editor.key_repeat.applied_in_frame = false;
+                    editor.flush_repeated_motions(window, cx);
+                })
+                .ok();
+        });
+        false
+    }
+
+    /// Applies the queued motions before an action of the given type, unless it's a motion that
+    /// may be queued after them.
+    pub(crate) fn flush_repeated_motions_before(
+        &mut self,
+        action: TypeId,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.key_repeat.queued.is_some() && !RepeatedMotion::is_motion_action(action) {
+            self.flush_repeated_motions(window, cx);
+        }
+    }
+
+    pub(crate) fn flush_repeated_motions(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some((motion, count)) = self.key_repeat.queued.take() else {
+            return;
+        };
+
+        self.key_repeat.flushing = true;
+        match motion {
+            RepeatedMotion::Up => {
+                self.move_up_by_lines(&MoveUpByLines { lines: count }, window, cx)
+            }
+            RepeatedMotion::Down => {
+                self.move_down_by_lines(&MoveDownByLines { lines: count }, window, cx)
+            }
+            RepeatedMotion::SelectUp => {
+                self.select_up_by_lines(&SelectUpByLines { lines: count }, window, cx)
+            }
+            RepeatedMotion::SelectDown => {
+                self.select_down_by_lines(&SelectDownByLines { lines: count }, window, cx)
+            }
+            _ => {
+                for _ in 0..count {
+                    match motion {
+                        RepeatedMotion::Left => self.move_left(&MoveLeft, window, cx),
+                        RepeatedMotion::Right => self.move_right(&MoveRight, window, cx),
+                        RepeatedMotion::SelectLeft => self.select_left(&SelectLeft, window, cx),
+                        RepeatedMotion::SelectRight => self.select_right(&SelectRight, window, cx),
+                        RepeatedMotion::PageUp { center_cursor } => {
+                            self.move_page_up(&MovePageUp { center_cursor }, window, cx)
+                        }
+                        RepeatedMotion::PageDown { center_cursor } => {
+                            self.move_page_down(&MovePageDown { center_cursor }, window, cx)
+                        }
+                        RepeatedMotion::Up
+                        | RepeatedMotion::Down
+                        | RepeatedMotion::SelectUp
+                        | RepeatedMotion::SelectDown => unreachable!(),
+                    }
+                }
+            }
+        }
+        self.key_repeat.flushing = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+    use gpui::{KeyBinding, KeyDownEvent, Keystroke, Modifiers, TestAppContext};
+    use indoc::indoc;
+
+    #[gpui::test]
+    async fn test_coalescing_repeated_motions(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.update(|_, cx| cx.bind_keys([KeyBinding::new("down", crate::actions::MoveDown, None)]));
+        cx.set_state(indoc! {"
+            ˇone
+            two
+            three
+            four
+            five
+        "});
+
+        let hold_down = |cx: &mut EditorTestContext| {
+            cx.simulate_event(KeyDownEvent {
+                keystroke: Keystroke::parse("down").unwrap(),
+                is_held: true,
+            })
+        };
+
+        // The first repetition is applied, and the next ones wait for the next frame.
+        hold_down(&mut cx);
+        hold_down(&mut cx);
+        hold_down(&mut cx);
+        cx.assert_editor_state(indoc! {"
+            one
+            ˇtwo
+            three
+            four
+            five
+        "});
+
+        // Other input applies the queued motions first.
+        cx.simulate_input("x");
+        cx.assert_editor_state(indoc! {"
+            one
+            two
+            three
+            xˇfour
+            five
+        "});
+
+        // So does a click, after which the queued motions don't move the cursor.
+        let position = cx.pixel_position(indoc! {"
+            ˇone
+            two
+            three
+            xfour
+            five
+        "});
+        hold_down(&mut cx);
+        hold_down(&mut cx);
+        hold_down(&mut cx);
+        cx.simulate_click(position, Modifiers::none());
+        cx.run_until_parked();
+        cx.assert_editor_state(indoc! {"
+            ˇone
+            two
+            three
+            xfour
+            five
+        "});
+    }
+}
@@ -0,0 +1,92 @@
+//! Editors of the conflicts left by merging buffers with the changes made to their files.
+//!
+//! When a buffer's unsaved edits and the changes made to its file on disk change the same lines
+//! differently, the changes that don't conflict are made to the buffer, and the merged text is
+//! opened in a separate editor, with each conflict between conflict markers. Saving that editor
+//! once its conflicts are resolved replaces the buffer's text with it and saves the buffer, which
+//! no longer conflicts with its file, then closes the editor.
+
+use crate::{Editor, EditorEvent};
+use anyhow::{Context as _, Result};
+use gpui::{AppContext as _, Context, Entity, Task, Window};
+use language::{Buffer, FileMergeConflicts};
+use multi_buffer::MultiBuffer;
+use project::Project;
+
+/// The conflicts shown in an editor, and the buffer they're resolved in.
+pub(crate) struct MergeConflicts {
+    buffer: Entity<Buffer>,
+    conflicts: FileMergeConflicts,
+}
+
+impl Editor {
+    /// Opens the conflicts of the buffer's merge with its file in a new editor, in the active
+    /// pane.
+    pub(crate) fn open_merge_conflicts(
+        &self,
+        buffer: Entity<Buffer>,
+        conflicts: FileMergeConflicts,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Result<()> {
+        let workspace = self
+            .workspace()
+            .context("no workspace to open the merge conflicts in")?;
+        let project = self
+            .project
+            .clone()
+            .context("no project to open the merge conflicts in")?;
+        let file_name = buffer
+            .read(cx)
+            .file()
+            .map(|file| file.file_name(cx).to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let title = format!("{file_name} (Conflicts)");
+        let language = buffer.read(cx).language().cloned();
+        let conflicts_buffer = project.update(cx, |project, cx| {
+            project.create_local_buffer(conflicts.text(), language, cx)
+        });
+        let multibuffer =
+            cx.new(|cx| MultiBuffer::singleton(conflicts_buffer, cx).with_title(title));
+        let editor = cx.new(|cx| {
+            let mut editor = Editor::for_multibuffer(multibuffer, Some(project), true, window, cx);
+            editor.merge_conflicts = Some(MergeConflicts { buffer, conflicts });
+            editor
+        });
+        workspace.update(cx, |workspace, cx| {
+            workspace.add_item_to_active_pane(Box::new(editor), None, true, window, cx)
+        });
+        Ok(())
+    }
+
+    /// Resolves the conflicts shown in the editor with its text, saving their buffer.
+    pub(crate) fn save_merge_resolution(
+        &mut self,
+        project: Entity<Project>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        let Some(merge_conflicts) = self.merge_conflicts.as_ref() else {
+            return Task::ready(Ok(()));
+        };
+        let buffer = merge_conflicts.buffer.clone();
+        let resolved_text = self.buffer.read(cx).snapshot(cx).text();
+        let resolved = buffer.update(cx, |buffer, cx| {
+            buffer.resolve_file_merge(&merge_conflicts.conflicts, &resolved_text, cx)
+        });
+        if let Err(error) = resolved {
+            return Task::ready(Err(error));
+        }
+        self.merge_conflicts = None;
+
+        let save = project.update(cx, |project, cx| project.save_buffer(buffer, cx));
+        cx.spawn_in(window, |this, mut cx| async move {
+            save.await?;
+            this.update(&mut cx, |_, cx| cx.emit(EditorEvent::Closed))
+        })
+    }
+
+    pub(crate) fn has_merge_conflicts(&self) -> bool {
+        self.merge_conflicts.is_some()
+    }
+}
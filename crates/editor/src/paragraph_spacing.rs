@@ -0,0 +1,223 @@
+//! Extra space between paragraphs, set with the `paragraph_spacing` setting.
+//!
+//! The space is made of blocks of empty rows, placed above the first line of each paragraph
+//! that follows blank lines. Since they're rows of the display map, scrolling, hit testing and
+//! the mapping from rows to pixels account for them like for any other block. They're
+//! recomputed shortly after the buffers are edited, only in the rows around the edits, and in
+//! all rows when the setting changes.
+
+use crate::{
+    display_map::{BlockPlacement, BlockProperties, BlockStyle, CustomBlockId},
+    Editor, EditorMode, EditorSettings,
+};
+use collections::HashSet;
+use gpui::{div, App, Context, IntoElement as _, Task};
+use language::{Point, Subscription as BufferSubscription};
+use multi_buffer::{Anchor, MultiBufferRow, MultiBufferSnapshot, ToPoint as _};
+use settings::Settings;
+use std::{mem, ops::Range, sync::Arc, time::Duration};
+
+/// How long the buffers must stay unchanged before the spacing is recomputed.
+const PARAGRAPH_SPACING_DEBOUNCE: Duration = Duration::from_millis(50);
+
+#[derive(Default)]
+pub(crate) struct ParagraphSpacing {
+    spacing: u32,
+    /// The blocks making the space, along with the start of the line they're placed above.
+    blocks: Vec<(Anchor, CustomBlockId)>,
+    /// The edits made to the buffers since the blocks were last recomputed.
+    edits: Option<BufferSubscription>,
+    /// The ranges whose blocks have yet to be recomputed.
+    dirty_ranges: Vec<Range<Anchor>>,
+    task: Option<Task<()>>,
+}
+
+impl Editor {
+    fn paragraph_spacing(&self, cx: &App) -> u32 {
+        if self.mode == EditorMode::Full {
+            EditorSettings::get_global(cx).paragraph_spacing
+        } else {
+            0
+        }
+    }
+
+    pub(crate) fn refresh_paragraph_spacing(&mut self, cx: &mut Context<Self>) {
+        let spacing = self.paragraph_spacing(cx);
+        if spacing != self.paragraph_spacing.spacing {
+            let state = mem::take(&mut self.paragraph_spacing);
+            let blocks = state.blocks.into_iter().map(|(_, block_id)| block_id);
+            self.remove_blocks(blocks.collect(), None, cx);
+            if spacing != 0 {
+                self.paragraph_spacing = ParagraphSpacing {
+                    spacing,
+                    edits: Some(self.buffer.update(cx, |buffer, _| buffer.subscribe())),
+                    dirty_ranges: vec![Anchor::min()..Anchor::max()],
+                    ..ParagraphSpacing::default()
+                };
+            }
+        }
+        if spacing == 0 {
+            return;
+        }
+
+        self.paragraph_spacing.task = Some(cx.spawn(|this, mut cx| async move {
+            cx.background_executor()
+                .timer(PARAGRAPH_SPACING_DEBOUNCE)
+                .await;
+            let Ok((snapshot, dirty_rows, dirty_range_count)) = this.update(&mut cx, |this, cx| {
+                let snapshot = this.buffer.read(cx).snapshot(cx);
+                let state = &mut this.paragraph_spacing;
+                if let Some(edits) = state.edits.as_mut() {
+                    state
+                        .dirty_ranges
+                        .extend(edits.consume().into_inner().into_iter().map(|edit| {
+                            snapshot.anchor_before(edit.new.start)
+                                ..snapshot.anchor_after(edit.new.end)
+                        }));
+                }
+                let dirty_rows = dirty_rows(&state.dirty_ranges, &snapshot);
+                (snapshot, dirty_rows, state.dirty_ranges.len())
+            }) else {
+                return;
+            };
+            let (dirty_ranges, paragraph_starts) = cx
+                .background_executor()
+                .spawn(async move {
+                    let paragraph_starts = dirty_rows
+                        .iter()
+                        .flat_map(|rows| paragraph_starts(&snapshot, rows.clone()))
+                        .collect::<Vec<_>>();
+                    let dirty_ranges = dirty_rows
+                        .into_iter()
+                        .map(|rows| {
+                            row_start(&snapshot, rows.start)..row_start(&snapshot, rows.end)
+                        })
+                        .collect::<Vec<_>>();
+                    (dirty_ranges, paragraph_starts)
+                })
+                .await;
+            this.update(&mut cx, |this, cx| {
+                let snapshot = this.buffer.read(cx).snapshot(cx);
+                let state = &mut this.paragraph_spacing;
+                state.dirty_ranges.drain(..dirty_range_count);
+                let mut removed_blocks = HashSet::default();
+                state.blocks.retain(|(start, block_id)| {
+                    let is_dirty = dirty_ranges.iter().any(|range| {
+                        range.start.cmp(start, &snapshot).is_le()
+                            && start.cmp(&range.end, &snapshot).is_lt()
+                    });
+                    if is_dirty {
+                        removed_blocks.insert(*block_id);
+                    }
+                    !is_dirty
+                });
+                this.remove_blocks(removed_blocks, None, cx);
+
+                let blocks = paragraph_starts.iter().map(|start| BlockProperties {
+                    placement: BlockPlacement::Above(*start),
+                    height: spacing,
+                    style: BlockStyle::Fixed,
+                    render: Arc::new(|_| div().into_any_element()),
+                    priority: 0,
+                });
+                let block_ids = this.insert_blocks(blocks, None, cx);
+                this.paragraph_spacing
+                    .blocks
+                    .extend(paragraph_starts.into_iter().zip(block_ids));
+            })
+            .ok();
+        }));
+    }
+}
+
+/// Returns the sorted, disjoint rows whose lines may have started or stopped following blank
+/// lines since the ranges were edited: their rows, and the row after them.
+fn dirty_rows(ranges: &[Range<Anchor>], snapshot: &MultiBufferSnapshot) -> Vec<Range<u32>> {
+    let row_count = snapshot.max_point().row + 1;
+    let mut rows = ranges
+        .iter()
+        .map(|range| {
+            range.start.to_point(snapshot).row
+                ..(range.end.to_point(snapshot).row + 2).min(row_count)
+        })
+        .collect::<Vec<_>>();
+    rows.sort_unstable_by_key(|rows| rows.start);
+    let mut merged_rows: Vec<Range<u32>> = Vec::with_capacity(rows.len());
+    for rows in rows {
+        match merged_rows.last_mut() {
+            Some(last) if last.end >= rows.start => last.end = last.end.max(rows.end),
+            _ => merged_rows.push(rows),
+        }
+    }
+    merged_rows
+}
+
+fn row_start(snapshot: &MultiBufferSnapshot, row: u32) -> Anchor {
+    if row > snapshot.max_point().row {
+        Anchor::max()
+    } else {
+        snapshot.anchor_before(Point::new(row, 0))
+    }
+}
+
+/// The starts of the lines of the rows following blank lines, except at the start of the buffer.
+fn paragraph_starts(snapshot: &MultiBufferSnapshot, rows: Range<u32>) -> Vec<Anchor> {
+    let mut starts = Vec::new();
+    let mut previous_row_blank =
+        rows.start > 0 && snapshot.is_line_blank(MultiBufferRow(rows.start - 1));
+    for row in rows {
+        let row_blank = snapshot.is_line_blank(MultiBufferRow(row));
+        if previous_row_blank && !row_blank {
+            starts.push(snapshot.anchor_before(Point::new(row, 0)));
+        }
+        previous_row_blank = row_blank;
+    }
+    starts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+    use gpui::TestAppContext;
+    use settings::SettingsStore;
+
+    #[gpui::test]
+    async fn test_paragraph_spacing(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state("ˇone\ntwo\n\nthree\n\n\nfour");
+        let set_spacing = |spacing, cx: &mut EditorTestContext| {
+            cx.update(|_, cx| {
+                cx.update_global::<SettingsStore, _>(|store, cx| {
+                    store.update_user_settings::<EditorSettings>(cx, |settings| {
+                        settings.paragraph_spacing = Some(spacing)
+                    });
+                })
+            });
+            cx.executor().advance_clock(PARAGRAPH_SPACING_DEBOUNCE);
+            cx.run_until_parked();
+        };
+
+        set_spacing(1, &mut cx);
+        assert_eq!(
+            cx.editor(|editor, _, cx| editor.display_text(cx)),
+            "one\ntwo\n\n\nthree\n\n\n\nfour"
+        );
+
+        // The spacing follows edits.
+        cx.simulate_input("\n");
+        cx.executor().advance_clock(PARAGRAPH_SPACING_DEBOUNCE);
+        cx.run_until_parked();
+        assert_eq!(
+            cx.editor(|editor, _, cx| editor.display_text(cx)),
+            "\n\none\ntwo\n\n\nthree\n\n\n\nfour"
+        );
+
+        set_spacing(0, &mut cx);
+        assert_eq!(
+            cx.editor(|editor, _, cx| editor.display_text(cx)),
+            "\none\ntwo\n\nthree\n\n\nfour"
+        );
+    }
+}
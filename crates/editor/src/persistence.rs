@@ -15,11 +15,14 @@ pub(crate) struct SerializedEditor {
     pub(crate) contents: Option<String>,
     pub(crate) language: Option<String>,
     pub(crate) mtime: Option<MTime>,
+    /// The encoded [`language::proto::BufferOperationLog`] of the unsaved contents, from which
+    /// they're restored along with their undo history.
+    pub(crate) operation_log: Option<Vec<u8>>,
 }
 
 impl StaticColumnCount for SerializedEditor {
     fn column_count() -> usize {
-        5
+        6
     }
 }
 
@@ -42,6 +45,7 @@ impl Bind for SerializedEditor {
                 statement.bind::<Option<i32>>(&None, start_index)?
             }
         };
+        let start_index = statement.bind(&self.operation_log, start_index)?;
         Ok(start_index)
     }
 }
@@ -58,6 +62,8 @@ impl Column for SerializedEditor {
             Column::column(statement, start_index)?;
         let (mtime_nanos, start_index): (Option<i32>, i32) =
             Column::column(statement, start_index)?;
+        let (operation_log, start_index): (Option<Vec<u8>>, i32) =
+            Column::column(statement, start_index)?;
 
         let mtime = mtime_seconds
             .zip(mtime_nanos)
@@ -68,6 +74,7 @@ impl Column for SerializedEditor {
             contents,
             language,
             mtime,
+            operation_log,
         };
         Ok((editor, start_index))
     }
@@ -86,6 +93,7 @@ define_connection!(
     //   language: Option<String>,
     //   mtime_seconds: Option<i64>,
     //   mtime_nanos: Option<i32>,
+    //   operation_log: Option<Vec<u8>>,
     // )
     pub static ref DB: EditorDb<WorkspaceDb> = &[
         sql! (
@@ -134,13 +142,16 @@ define_connection!(
             ALTER TABLE editors ADD COLUMN mtime_seconds INTEGER DEFAULT NULL;
             ALTER TABLE editors ADD COLUMN mtime_nanos INTEGER DEFAULT NULL;
         ),
+        sql! (
+            ALTER TABLE editors ADD COLUMN operation_log BLOB DEFAULT NULL;
+        ),
     ];
 );
 
 impl EditorDb {
     query! {
         pub fn get_serialized_editor(item_id: ItemId, workspace_id: WorkspaceId) -> Result<Option<SerializedEditor>> {
-            SELECT path, contents, language, mtime_seconds, mtime_nanos FROM editors
+            SELECT path, contents, language, mtime_seconds, mtime_nanos, operation_log FROM editors
             WHERE item_id = ? AND workspace_id = ?
         }
     }
@@ -148,9 +159,9 @@ impl EditorDb {
     query! {
         pub async fn save_serialized_editor(item_id: ItemId, workspace_id: WorkspaceId, serialized_editor: SerializedEditor) -> Result<()> {
             INSERT INTO editors
-                (item_id, workspace_id, path, contents, language, mtime_seconds, mtime_nanos)
+                (item_id, workspace_id, path, contents, language, mtime_seconds, mtime_nanos, operation_log)
             VALUES
-                (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
             ON CONFLICT DO UPDATE SET
                 item_id = ?1,
                 workspace_id = ?2,
@@ -158,7 +169,8 @@ impl EditorDb {
                 contents = ?4,
                 language = ?5,
                 mtime_seconds = ?6,
-                mtime_nanos = ?7
+                mtime_nanos = ?7,
+                operation_log = ?8
         }
     }
 
@@ -228,6 +240,7 @@ mod tests {
             contents: None,
             language: None,
             mtime: None,
+            operation_log: None,
         };
 
         DB.save_serialized_editor(1234, workspace_id, serialized_editor.clone())
@@ -246,6 +259,7 @@ mod tests {
             contents: Some("Test".to_owned()),
             language: Some("Go".to_owned()),
             mtime: None,
+            operation_log: None,
         };
 
         DB.save_serialized_editor(1234, workspace_id, serialized_editor.clone())
@@ -264,6 +278,7 @@ mod tests {
             contents: None,
             language: None,
             mtime: None,
+            operation_log: None,
         };
 
         DB.save_serialized_editor(1234, workspace_id, serialized_editor.clone())
@@ -282,6 +297,7 @@ mod tests {
             contents: None,
             language: None,
             mtime: Some(MTime::from_seconds_and_nanos(100, 42)),
+            operation_log: None,
         };
 
         DB.save_serialized_editor(1234, workspace_id, serialized_editor.clone())
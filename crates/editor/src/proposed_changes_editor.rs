@@ -1,11 +1,20 @@
-use crate::{ApplyAllDiffHunks, Editor, EditorEvent, SemanticsProvider};
+use crate::{
+    workspace_edit_preview::excerpt_ranges, ApplyAllDiffHunks, ApplyDiffHunksInFile, Editor,
+    EditorEvent, RejectDiffHunksInFile, SemanticsProvider,
+};
 use buffer_diff::BufferDiff;
 use collections::HashSet;
 use futures::{channel::mpsc, future::join_all};
-use gpui::{App, Entity, EventEmitter, Focusable, Render, Subscription, Task};
+use gpui::{
+    uniform_list, Action, AnyElement, App, Entity, EventEmitter, Focusable, Render, Subscription,
+    Task,
+};
 use language::{Buffer, BufferEvent, Capability};
 use multi_buffer::{ExcerptRange, MultiBuffer};
-use project::Project;
+use project::{
+    file_patch::{FilePatch, ProjectPatch},
+    Project,
+};
 use smol::stream::StreamExt;
 use std::{any::TypeId, ops::Range, rc::Rc, time::Duration};
 use text::ToOffset;
@@ -20,6 +29,9 @@ pub struct ProposedChangesEditor {
     multibuffer: Entity<MultiBuffer>,
     title: SharedString,
     buffer_entries: Vec<BufferEntry>,
+    file_patches: Vec<FilePatchEntry>,
+    file_patch_rows: Vec<FilePatchRow>,
+    project: Option<Entity<Project>>,
     _recalculate_diffs_task: Task<Option<()>>,
     recalculate_diffs_tx: mpsc::UnboundedSender<RecalculateDiff>,
 }
@@ -29,6 +41,23 @@ pub struct ProposedChangeLocation<T> {
     pub ranges: Vec<Range<T>>,
 }
 
+/// A patch to a file with no open buffer, proposed without opening it.
+struct FilePatchEntry {
+    patch: FilePatch,
+    /// Whether the patch couldn't be applied, as its file changed since it was made.
+    conflicted: bool,
+}
+
+/// A row of the list of file patches: the path of a patched file, or a line its patch changes.
+enum FilePatchRow {
+    File(usize),
+    Line {
+        row: u32,
+        text: SharedString,
+        deleted: bool,
+    },
+}
+
 struct BufferEntry {
     base: Entity<Buffer>,
     branch: Entity<Buffer>,
@@ -63,7 +92,7 @@ impl ProposedChangesEditor {
         let mut this = Self {
             editor: cx.new(|cx| {
                 let mut editor =
-                    Editor::for_multibuffer(multibuffer.clone(), project, true, window, cx);
+                    Editor::for_multibuffer(multibuffer.clone(), project.clone(), true, window, cx);
                 editor.set_expand_all_diff_hunks(cx);
                 editor.set_completion_provider(None);
                 editor.clear_code_action_providers();
@@ -77,6 +106,9 @@ impl ProposedChangesEditor {
             multibuffer,
             title: title.into(),
             buffer_entries: Vec::new(),
+            file_patches: Vec::new(),
+            file_patch_rows: Vec::new(),
+            project,
             recalculate_diffs_tx,
             _recalculate_diffs_task: cx.spawn_in(window, |this, mut cx| async move {
                 let mut buffers_to_diff = HashSet::default();
@@ -183,21 +215,10 @@ impl ProposedChangesEditor {
                 branch_buffer = entry.branch.clone();
                 buffer_entries.push(entry);
             } else {
-                branch_buffer = location.buffer.update(cx, |buffer, cx| buffer.branch(cx));
-                new_diffs.push(cx.new(|cx| {
-                    let mut diff = BufferDiff::new(branch_buffer.read(cx));
-                    let _ = diff.set_base_text(
-                        location.buffer.clone(),
-                        branch_buffer.read(cx).text_snapshot(),
-                        cx,
-                    );
-                    diff
-                }));
-                buffer_entries.push(BufferEntry {
-                    branch: branch_buffer.clone(),
-                    base: location.buffer.clone(),
-                    _subscription: cx.subscribe(&branch_buffer, Self::on_buffer_event),
-                });
+                let (entry, diff) = Self::new_buffer_entry(&location.buffer, cx);
+                branch_buffer = entry.branch.clone();
+                new_diffs.push(diff);
+                buffer_entries.push(entry);
             }
 
             self.multibuffer.update(cx, |multibuffer, cx| {
@@ -223,6 +244,334 @@ impl ProposedChangesEditor {
         });
     }
 
+    /// Proposes changes to one more buffer, returning the branch buffer to make them in.
+    fn add_location<T: ToOffset>(
+        &mut self,
+        location: ProposedChangeLocation<T>,
+        cx: &mut Context<Self>,
+    ) -> Entity<Buffer> {
+        let (entry, diff) = Self::new_buffer_entry(&location.buffer, cx);
+        let branch_buffer = entry.branch.clone();
+        self.buffer_entries.push(entry);
+        self.multibuffer.update(cx, |multibuffer, cx| {
+            multibuffer.push_excerpts(
+                branch_buffer.clone(),
+                location.ranges.into_iter().map(|range| ExcerptRange {
+                    context: range,
+                    primary: None,
+                }),
+                cx,
+            );
+        });
+        self.editor.update(cx, |editor, cx| {
+            editor
+                .buffer
+                .update(cx, |buffer, cx| buffer.add_diff(diff, cx))
+        });
+        branch_buffer
+    }
+
+    fn new_buffer_entry(
+        base_buffer: &Entity<Buffer>,
+        cx: &mut Context<Self>,
+    ) -> (BufferEntry, Entity<BufferDiff>) {
+        let branch_buffer = base_buffer.update(cx, |buffer, cx| buffer.branch(cx));
+        let diff = cx.new(|cx| {
+            let mut diff = BufferDiff::new(branch_buffer.read(cx));
+            let _ = diff.set_base_text(
+                base_buffer.clone(),
+                branch_buffer.read(cx).text_snapshot(),
+                cx,
+            );
+            diff
+        });
+        let entry = BufferEntry {
+            base: base_buffer.clone(),
+            _subscription: cx.subscribe(&branch_buffer, Self::on_buffer_event),
+            branch: branch_buffer,
+        };
+        (entry, diff)
+    }
+
+    /// Proposes patches to files with no open buffer, listed above the changes to buffers.
+    /// They're applied on disk along with the other changes, unless they're opened from the
+    /// list first, which proposes them in the buffers of their files instead.
+    pub fn set_file_patches(&mut self, file_patches: Vec<FilePatch>, cx: &mut Context<Self>) {
+        self.file_patches = file_patches
+            .into_iter()
+            .map(|patch| FilePatchEntry {
+                patch,
+                conflicted: false,
+            })
+            .collect();
+        self.file_patches_changed(cx);
+    }
+
+    /// Lists the file patches again, with the lines their hunks change before and after them.
+    fn file_patches_changed(&mut self, cx: &mut Context<Self>) {
+        self.file_patch_rows.clear();
+        for (ix, entry) in self.file_patches.iter().enumerate() {
+            self.file_patch_rows.push(FilePatchRow::File(ix));
+            for hunk in &entry.patch.hunks {
+                for (text, deleted) in [(&hunk.old_text, true), (&hunk.new_text, false)] {
+                    self.file_patch_rows
+                        .extend(text.split('\n').zip(hunk.start_row..).map(|(line, row)| {
+                            FilePatchRow::Line {
+                                row,
+                                text: SharedString::from(line.to_string()),
+                                deleted,
+                            }
+                        }));
+                }
+            }
+        }
+        cx.notify();
+    }
+
+    /// The absolute paths of the proposed file patches, along with whether they're conflicted.
+    #[cfg(test)]
+    pub(crate) fn file_patch_paths(&self) -> Vec<(std::path::PathBuf, bool)> {
+        self.file_patches
+            .iter()
+            .map(|entry| (entry.patch.abs_path.clone(), entry.conflicted))
+            .collect()
+    }
+
+    /// Opens the buffer of the file patch at the index, and proposes its edits in it.
+    pub(crate) fn open_file_patch(
+        &mut self,
+        ix: usize,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let (Some(project), Some(entry)) = (self.project.clone(), self.file_patches.get(ix)) else {
+            return;
+        };
+        let patch = entry.patch.clone();
+        let open_buffer = project.update(cx, |project, cx| {
+            project.open_buffer(patch.project_path.clone(), cx)
+        });
+        cx.spawn_in(window, |this, mut cx| async move {
+            let buffer = open_buffer.await?;
+            this.update(&mut cx, |this, cx| {
+                let Some(ix) = this
+                    .file_patches
+                    .iter()
+                    .position(|entry| entry.patch.abs_path == patch.abs_path)
+                else {
+                    return;
+                };
+                let Some(edits) = patch.edits_in_buffer(buffer.read(cx)) else {
+                    this.file_patches[ix].conflicted = true;
+                    this.file_patches_changed(cx);
+                    return;
+                };
+                this.file_patches.remove(ix);
+                this.file_patches_changed(cx);
+
+                let ranges = excerpt_ranges(
+                    patch.hunks.iter().map(|hunk| {
+                        let end_row = hunk.start_row + hunk.old_text.matches('\n').count() as u32;
+                        hunk.start_row..end_row
+                    }),
+                    buffer.read(cx).max_point(),
+                );
+                let branch_buffer =
+                    this.add_location(ProposedChangeLocation { buffer, ranges }, cx);
+                branch_buffer.update(cx, |branch_buffer, cx| {
+                    branch_buffer.edit(edits, None, cx);
+                });
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    /// Applies the file patches on disk, before the editor applies the changes to buffers.
+    /// The patches of the files that changed since are kept, marked as conflicted. The files
+    /// are written outside of any transaction, so unlike the changes to buffers, the patches
+    /// can't be undone.
+    pub(crate) fn apply_all_file_patches(
+        &mut self,
+        _: &ApplyAllDiffHunks,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(project) = self.project.clone() else {
+            return;
+        };
+        if self.file_patches.is_empty() {
+            return;
+        }
+        let file_patches = self
+            .file_patches
+            .drain(..)
+            .map(|entry| entry.patch)
+            .collect();
+        let apply = project.update(cx, |project, cx| {
+            project.apply_file_patches(file_patches, cx)
+        });
+        self.file_patches_changed(cx);
+        cx.spawn_in(window, |this, mut cx| async move {
+            let unapplied_patches = apply.await?;
+            this.update(&mut cx, |this, cx| {
+                this.file_patches
+                    .extend(unapplied_patches.into_iter().map(|patch| FilePatchEntry {
+                        patch,
+                        conflicted: true,
+                    }));
+                this.file_patches_changed(cx);
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    fn render_file_patches(
+        &mut self,
+        range: Range<usize>,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Vec<AnyElement> {
+        range
+            .filter_map(|row_ix| match self.file_patch_rows.get(row_ix)? {
+                FilePatchRow::File(ix) => {
+                    let ix = *ix;
+                    let entry = self.file_patches.get(ix)?;
+                    Some(
+                        h_flex()
+                            .id(("file-patch", ix))
+                            .px_2()
+                            .gap_2()
+                            .child(
+                                Label::new(
+                                    entry.patch.project_path.path.to_string_lossy().to_string(),
+                                )
+                                .size(LabelSize::Small),
+                            )
+                            .when(entry.conflicted, |this| {
+                                this.child(
+                                    Label::new("Changed on disk")
+                                        .size(LabelSize::Small)
+                                        .color(Color::Warning),
+                                )
+                            })
+                            .child(div().flex_1())
+                            .child(
+                                Button::new(("open-file-patch", ix), "Open")
+                                    .label_size(LabelSize::Small)
+                                    .on_click(cx.listener(move |this, _, window, cx| {
+                                        this.open_file_patch(ix, window, cx)
+                                    })),
+                            )
+                            .into_any_element(),
+                    )
+                }
+                FilePatchRow::Line { row, text, deleted } => Some(
+                    h_flex()
+                        .px_2()
+                        .gap_2()
+                        .child(
+                            Label::new((row + 1).to_string())
+                                .size(LabelSize::Small)
+                                .color(Color::Muted)
+                                .buffer_font(cx),
+                        )
+                        .child(
+                            Label::new(format!("{} {text}", if *deleted { '-' } else { '+' }))
+                                .size(LabelSize::Small)
+                                .color(if *deleted {
+                                    Color::Deleted
+                                } else {
+                                    Color::Created
+                                })
+                                .buffer_font(cx)
+                                .single_line(),
+                        )
+                        .into_any_element(),
+                ),
+            })
+            .collect()
+    }
+
+    /// Prevents the proposed changes from being edited, so that they can only be applied or
+    /// rejected.
+    pub fn set_read_only(&mut self, read_only: bool, cx: &mut Context<Self>) {
+        self.editor
+            .update(cx, |editor, _| editor.set_read_only(read_only));
+    }
+
+    /// Merges the changes proposed to the buffer into it, and stops showing them.
+    pub fn apply_changes_in_buffer(
+        &mut self,
+        base_buffer: &Entity<Buffer>,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(branch_buffer) = self.branch_buffer_for_base(base_buffer) {
+            branch_buffer.update(cx, |branch_buffer, cx| {
+                branch_buffer.merge_into_base(Vec::new(), cx)
+            });
+        }
+        self.remove_buffer(base_buffer, cx);
+    }
+
+    /// Stops showing the changes proposed to the buffer, leaving it unchanged.
+    pub fn reject_changes_in_buffer(
+        &mut self,
+        base_buffer: &Entity<Buffer>,
+        cx: &mut Context<Self>,
+    ) {
+        self.remove_buffer(base_buffer, cx);
+    }
+
+    fn remove_buffer(&mut self, base_buffer: &Entity<Buffer>, cx: &mut Context<Self>) {
+        let Some(ix) = self
+            .buffer_entries
+            .iter()
+            .position(|entry| &entry.base == base_buffer)
+        else {
+            return;
+        };
+        let entry = self.buffer_entries.remove(ix);
+        let branch_buffer_id = entry.branch.read(cx).remote_id();
+        self.multibuffer.update(cx, |multibuffer, cx| {
+            let excerpt_ids = multibuffer
+                .excerpts_for_buffer(branch_buffer_id, cx)
+                .into_iter()
+                .map(|(excerpt_id, _)| excerpt_id)
+                .collect::<Vec<_>>();
+            multibuffer.remove_excerpts(excerpt_ids, cx);
+        });
+        cx.notify();
+    }
+
+    /// The buffer whose changes are shown at the newest cursor.
+    fn base_buffer_at_cursor(&self, cx: &App) -> Option<Entity<Buffer>> {
+        let head = self.editor.read(cx).selections.newest_anchor().head();
+        let branch_buffer = self.multibuffer.read(cx).buffer(head.buffer_id?)?;
+        branch_buffer.read(cx).base_buffer()
+    }
+
+    fn apply_diff_hunks_in_file(
+        &mut self,
+        _: &ApplyDiffHunksInFile,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(base_buffer) = self.base_buffer_at_cursor(cx) {
+            self.apply_changes_in_buffer(&base_buffer, cx);
+        }
+    }
+
+    fn reject_diff_hunks_in_file(
+        &mut self,
+        _: &RejectDiffHunksInFile,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(base_buffer) = self.base_buffer_at_cursor(cx) {
+            self.reject_changes_in_buffer(&base_buffer, cx);
+        }
+    }
+
     pub fn recalculate_all_buffer_diffs(&self) {
         for (ix, entry) in self.buffer_entries.iter().enumerate().rev() {
             self.recalculate_diffs_tx
@@ -263,11 +612,42 @@ impl ProposedChangesEditor {
 }
 
 impl Render for ProposedChangesEditor {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
-        div()
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let file_patch_count = self.file_patches.len();
+        let file_patch_row_count = self.file_patch_rows.len();
+        v_flex()
             .size_full()
             .key_context("ProposedChangesEditor")
-            .child(self.editor.clone())
+            .on_action(cx.listener(Self::apply_diff_hunks_in_file))
+            .on_action(cx.listener(Self::reject_diff_hunks_in_file))
+            .capture_action(cx.listener(Self::apply_all_file_patches))
+            .when(file_patch_count > 0, |this| {
+                this.child(
+                    v_flex()
+                        .py_1()
+                        .border_b_1()
+                        .border_color(cx.theme().colors().border_variant)
+                        .child(
+                            div().px_2().child(
+                                Label::new(format!(
+                                    "{file_patch_count} files not opened, patched on disk when applied, which can't be undone"
+                                ))
+                                .size(LabelSize::Small)
+                                .color(Color::Muted),
+                            ),
+                        )
+                        .child(
+                            uniform_list(
+                                cx.entity().clone(),
+                                "file-patches",
+                                file_patch_row_count,
+                                Self::render_file_patches,
+                            )
+                            .max_h(rems(12.)),
+                        ),
+                )
+            })
+            .child(div().flex_1().min_h_0().child(self.editor.clone()))
     }
 }
 
@@ -381,18 +761,47 @@ impl ProposedChangesEditorToolbar {
 
 impl Render for ProposedChangesEditorToolbar {
     fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        let button_like = ButtonLike::new("apply-changes").child(Label::new("Apply All"));
+        h_flex()
+            .gap_1()
+            .child(self.render_button(
+                "reject-file-changes",
+                "Reject File",
+                &RejectDiffHunksInFile,
+                window,
+                cx,
+            ))
+            .child(self.render_button(
+                "apply-file-changes",
+                "Apply File",
+                &ApplyDiffHunksInFile,
+                window,
+                cx,
+            ))
+            .child(self.render_button("apply-changes", "Apply All", &ApplyAllDiffHunks, window, cx))
+    }
+}
+
+impl ProposedChangesEditorToolbar {
+    fn render_button(
+        &self,
+        id: &'static str,
+        label: &'static str,
+        action: &dyn Action,
+        window: &mut Window,
+        cx: &App,
+    ) -> ButtonLike {
+        let button_like = ButtonLike::new(id).child(Label::new(label));
 
         match &self.current_editor {
             Some(editor) => {
                 let focus_handle = editor.focus_handle(cx);
-                let keybinding =
-                    KeyBinding::for_action_in(&ApplyAllDiffHunks, &focus_handle, window)
-                        .map(|binding| binding.into_any_element());
+                let keybinding = KeyBinding::for_action_in(action, &focus_handle, window)
+                    .map(|binding| binding.into_any_element());
+                let action = action.boxed_clone();
 
                 button_like.children(keybinding).on_click({
                     move |_event, window, cx| {
-                        focus_handle.dispatch_action(&ApplyAllDiffHunks, window, cx)
+                        focus_handle.dispatch_action(action.as_ref(), window, cx)
                     }
                 })
             }
@@ -510,8 +919,9 @@ impl SemanticsProvider for BranchBufferSemanticsProvider {
         _: &Entity<Buffer>,
         _: text::Anchor,
         _: String,
+        _: bool,
         _: &mut App,
-    ) -> Option<Task<gpui::Result<project::ProjectTransaction>>> {
+    ) -> Option<Task<gpui::Result<ProjectPatch>>> {
         None
     }
 }
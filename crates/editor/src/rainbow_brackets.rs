@@ -0,0 +1,306 @@
+//! Colors bracket pairs by their nesting depth, cycling through the theme's accent colors.
+//!
+//! Depths are computed from the bracket queries of the buffer's syntax tree. Buffers without
+//! a syntax tree fall back to scanning the text for `()`, `[]` and `{}`, starting at the top
+//! of the visible range. Only the visible ranges are recomputed, when the editor scrolls to
+//! other rows or when its buffers are edited or reparsed, and the excerpts whose range, text
+//! and syntax tree stayed the same keep their depths.
+
+use crate::{Editor, EditorMode, EditorSettings};
+use collections::HashMap;
+use gpui::{Context, HighlightStyle};
+use language::{BufferSnapshot, Point};
+use multi_buffer::{ExcerptId, ToPoint as _};
+use settings::Settings;
+use std::{any::TypeId, ops::Range};
+use text::Bias;
+use theme::ActiveTheme;
+
+/// The number of depths colored before the colors repeat.
+const RAINBOW_BRACKET_COLOR_COUNT: usize = 6;
+
+/// The key of the text highlights for the brackets at a depth, modulo the color count.
+struct RainbowBracket<const DEPTH: usize>;
+
+fn highlight_key(depth: usize) -> TypeId {
+    match depth % RAINBOW_BRACKET_COLOR_COUNT {
+        0 => TypeId::of::<RainbowBracket<0>>(),
+        1 => TypeId::of::<RainbowBracket<1>>(),
+        2 => TypeId::of::<RainbowBracket<2>>(),
+        3 => TypeId::of::<RainbowBracket<3>>(),
+        4 => TypeId::of::<RainbowBracket<4>>(),
+        _ => TypeId::of::<RainbowBracket<5>>(),
+    }
+}
+
+/// A pair of brackets, and the number of pairs enclosing it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct BracketDepth {
+    open: Range<usize>,
+    close: Range<usize>,
+    depth: usize,
+}
+
+/// The bracket pairs colored in the visible range of each excerpt, along with what they were
+/// computed from, so that they're only computed again when it changes.
+pub(crate) struct RainbowBrackets {
+    theme_generation: usize,
+    excerpts: HashMap<ExcerptId, ExcerptBrackets>,
+}
+
+struct ExcerptBrackets {
+    version: clock::Global,
+    non_text_state_update_count: usize,
+    range: Range<usize>,
+    depths: Vec<BracketDepth>,
+}
+
+impl Editor {
+    pub(crate) fn refresh_rainbow_brackets(&mut self, cx: &mut Context<Self>) {
+        if !EditorSettings::get_global(cx).rainbow_brackets || self.mode != EditorMode::Full {
+            if self.rainbow_brackets.take().is_some() {
+                self.display_map.update(cx, |display_map, _| {
+                    for depth in 0..RAINBOW_BRACKET_COLOR_COUNT {
+                        display_map.clear_highlights(highlight_key(depth));
+                    }
+                });
+                cx.notify();
+            }
+            return;
+        }
+
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let visible_start = self.scroll_manager.anchor().anchor.to_point(&snapshot);
+        let visible_start = Point::new(visible_start.row, 0);
+        let visible_end = snapshot.clip_point(
+            visible_start + Point::new(self.visible_line_count().unwrap_or(0.).ceil() as u32, 0),
+            Bias::Left,
+        );
+
+        // The depths of an excerpt are reused while its visible range, the text of its buffer
+        // and its syntax tree are the same.
+        let theme_generation = cx.theme_generation();
+        let mut previous_excerpts = self
+            .rainbow_brackets
+            .as_mut()
+            .map(|brackets| std::mem::take(&mut brackets.excerpts))
+            .unwrap_or_default();
+        let mut changed = self.rainbow_brackets.as_ref().map_or(true, |brackets| {
+            brackets.theme_generation != theme_generation
+        });
+        let mut excerpts = HashMap::default();
+        for (buffer, range, excerpt_id) in
+            snapshot.range_to_buffer_ranges(visible_start..visible_end)
+        {
+            let previous = previous_excerpts.remove(&excerpt_id).filter(|previous| {
+                previous.range == range
+                    && previous.non_text_state_update_count == buffer.non_text_state_update_count()
+                    && previous.version == *buffer.version()
+            });
+            let excerpt_brackets = match previous {
+                Some(previous) => previous,
+                None => {
+                    changed = true;
+                    ExcerptBrackets {
+                        version: buffer.version().clone(),
+                        non_text_state_update_count: buffer.non_text_state_update_count(),
+                        depths: bracket_depths(buffer, range.clone()),
+                        range,
+                    }
+                }
+            };
+            excerpts.insert(excerpt_id, excerpt_brackets);
+        }
+        changed |= !previous_excerpts.is_empty();
+        self.rainbow_brackets = Some(RainbowBrackets {
+            theme_generation,
+            excerpts,
+        });
+        if !changed {
+            return;
+        }
+
+        let mut ranges_by_depth = vec![Vec::new(); RAINBOW_BRACKET_COLOR_COUNT];
+        for (excerpt_id, excerpt_brackets) in &self.rainbow_brackets.as_ref().unwrap().excerpts {
+            let Some(buffer) = snapshot.buffer_for_excerpt(*excerpt_id) else {
+                continue;
+            };
+            for bracket in &excerpt_brackets.depths {
+                let ranges = &mut ranges_by_depth[bracket.depth % RAINBOW_BRACKET_COLOR_COUNT];
+                for range in [&bracket.open, &bracket.close] {
+                    let start =
+                        snapshot.anchor_in_excerpt(*excerpt_id, buffer.anchor_after(range.start));
+                    let end =
+                        snapshot.anchor_in_excerpt(*excerpt_id, buffer.anchor_before(range.end));
+                    if let Some((start, end)) = start.zip(end) {
+                        ranges.push(start..end);
+                    }
+                }
+            }
+        }
+
+        let accents = cx.theme().accents().clone();
+        self.display_map.update(cx, |display_map, _| {
+            for (depth, mut ranges) in ranges_by_depth.into_iter().enumerate() {
+                ranges.sort_by(|a, b| a.start.cmp(&b.start, &snapshot));
+                display_map.highlight_text(
+                    highlight_key(depth),
+                    ranges,
+                    HighlightStyle {
+                        color: Some(accents.color_for_index(depth as u32)),
+                        ..HighlightStyle::default()
+                    },
+                );
+            }
+        });
+        cx.notify();
+    }
+}
+
+/// Returns the bracket pairs overlapping the range, with their nesting depth.
+fn bracket_depths(buffer: &BufferSnapshot, range: Range<usize>) -> Vec<BracketDepth> {
+    if buffer.syntax_layers().next().is_none() {
+        return scan_bracket_depths(buffer, range);
+    }
+
+    // Only brackets are colored, not the other delimiters of the bracket queries, like quotes.
+    let mut pairs = buffer
+        .bracket_ranges(range)
+        .filter(|(open, _)| matches!(buffer.chars_at(open.start).next(), Some('(' | '[' | '{')))
+        .collect::<Vec<_>>();
+    // Enclosing pairs come first, so that they are on the stack when their children are visited.
+    pairs.sort_by(|(open_a, close_a), (open_b, close_b)| {
+        open_a
+            .start
+            .cmp(&open_b.start)
+            .then_with(|| close_b.end.cmp(&close_a.end))
+    });
+    pairs.dedup();
+
+    let mut enclosing_ends = Vec::<usize>::new();
+    let mut depths = Vec::with_capacity(pairs.len());
+    for (open, close) in pairs {
+        while enclosing_ends
+            .last()
+            .map_or(false, |enclosing_end| *enclosing_end <= open.start)
+        {
+            enclosing_ends.pop();
+        }
+        depths.push(BracketDepth {
+            depth: enclosing_ends.len(),
+            open,
+            close: close.clone(),
+        });
+        enclosing_ends.push(close.end);
+    }
+    depths
+}
+
+/// Matches brackets in the text of the range, ignoring the syntax of the buffer.
+fn scan_bracket_depths(buffer: &BufferSnapshot, range: Range<usize>) -> Vec<BracketDepth> {
+    let mut open_brackets = Vec::<(char, usize)>::new();
+    let mut depths = Vec::new();
+    let mut offset = range.start;
+    for chunk in buffer.text_for_range(range) {
+        for (ix, c) in chunk.char_indices() {
+            let ix = offset + ix;
+            match c {
+                '(' | '[' | '{' => open_brackets.push((c, ix)),
+                ')' | ']' | '}' => {
+                    let opening = match c {
+                        ')' => '(',
+                        ']' => '[',
+                        _ => '{',
+                    };
+                    if open_brackets
+                        .last()
+                        .map_or(false, |(open, _)| *open == opening)
+                    {
+                        let (_, open_ix) = open_brackets.pop().unwrap();
+                        depths.push(BracketDepth {
+                            open: open_ix..open_ix + 1,
+                            close: ix..ix + 1,
+                            depth: open_brackets.len(),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        offset += chunk.len();
+    }
+    depths.sort_by_key(|bracket| bracket.open.start);
+    depths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{editor_lsp_test_context::rust_lang, editor_test_context::EditorTestContext};
+    use gpui::{AppContext as _, TestAppContext};
+    use language::Buffer;
+    use settings::SettingsStore;
+
+    #[gpui::test]
+    fn test_bracket_depths(cx: &mut TestAppContext) {
+        let text = "fn a(b: [u8; 2]) { if c { d(e[0]) } }";
+        let buffer = cx.new(|cx| Buffer::local(text, cx).with_language(rust_lang(), cx));
+        cx.run_until_parked();
+        let snapshot = buffer.read_with(cx, |buffer, _| buffer.snapshot());
+        let depths = bracket_depths(&snapshot, 0..text.len())
+            .into_iter()
+            .map(|bracket| (&text[bracket.open.clone()], bracket.depth))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            depths,
+            [("(", 0), ("[", 1), ("{", 0), ("{", 1), ("(", 2), ("[", 3)]
+        );
+
+        // Without a syntax tree, brackets are matched by scanning the text.
+        let text = "a(b[c] {d}) e) (f";
+        let buffer = cx.new(|cx| Buffer::local(text, cx));
+        let snapshot = buffer.read_with(cx, |buffer, _| buffer.snapshot());
+        let depths = bracket_depths(&snapshot, 0..text.len())
+            .into_iter()
+            .map(|bracket| (bracket.open.start, bracket.close.start, bracket.depth))
+            .collect::<Vec<_>>();
+        assert_eq!(depths, [(1, 10, 0), (3, 5, 1), (7, 9, 1)]);
+    }
+
+    #[gpui::test]
+    async fn test_rainbow_brackets_follow_edits(cx: &mut TestAppContext) {
+        crate::editor_tests::init_test(cx, |_| {});
+        cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|store, cx| {
+                store.update_user_settings::<EditorSettings>(cx, |settings| {
+                    settings.rainbow_brackets = Some(true);
+                });
+            });
+        });
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.update_editor(|editor, window, cx| editor.set_visible_line_count(10., window, cx));
+
+        let colored_brackets = |cx: &mut EditorTestContext| {
+            cx.update_editor(|editor, _, cx| {
+                editor.refresh_rainbow_brackets(cx);
+                let mut brackets = editor
+                    .rainbow_brackets
+                    .iter()
+                    .flat_map(|brackets| brackets.excerpts.values())
+                    .flat_map(|excerpt| &excerpt.depths)
+                    .map(|bracket| (bracket.open.start, bracket.depth))
+                    .collect::<Vec<_>>();
+                brackets.sort();
+                brackets
+            })
+        };
+
+        cx.set_state("a(ˇb)");
+        assert_eq!(colored_brackets(&mut cx), [(1, 0)]);
+        // Refreshing without changes keeps the depths.
+        assert_eq!(colored_brackets(&mut cx), [(1, 0)]);
+
+        cx.set_state("a(b(ˇc))");
+        assert_eq!(colored_brackets(&mut cx), [(1, 0), (3, 1)]);
+    }
+}
@@ -18,7 +18,7 @@ use language::{Bias, Point};
 pub use scroll_amount::ScrollAmount;
 use settings::Settings;
 use std::{
-    cmp::Ordering,
+    cmp::{self, Ordering},
     time::{Duration, Instant},
 };
 use util::ResultExt;
@@ -224,6 +224,15 @@ impl ScrollManager {
         self.anchor.scroll_position(snapshot)
     }
 
+    /// Returns the maximum scroll position of the top of a viewport fitting
+    /// `height_in_lines` lines, when the last row of the buffer is `max_row`.
+    pub(crate) fn max_scroll_top(&self, max_row: f32, height_in_lines: f32, cx: &App) -> f32 {
+        let rows_beyond_last_line = EditorSettings::get_global(cx)
+            .scroll_beyond_last_line
+            .rows_beyond_last_line(height_in_lines, self.vertical_scroll_margin);
+        (max_row - height_in_lines + 1. + rows_beyond_last_line).max(0.)
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn set_scroll_position(
         &mut self,
@@ -248,25 +257,12 @@ impl ScrollManager {
             )
         } else {
             let scroll_top = scroll_position.y;
-            let scroll_top = match EditorSettings::get_global(cx).scroll_beyond_last_line {
-                ScrollBeyondLastLine::OnePage => scroll_top,
-                ScrollBeyondLastLine::Off => {
-                    if let Some(height_in_lines) = self.visible_line_count {
-                        let max_row = map.max_point().row().0 as f32;
-                        scroll_top.min(max_row - height_in_lines + 1.).max(0.)
-                    } else {
-                        scroll_top
-                    }
-                }
-                ScrollBeyondLastLine::VerticalScrollMargin => {
-                    if let Some(height_in_lines) = self.visible_line_count {
-                        let max_row = map.max_point().row().0 as f32;
-                        scroll_top
-                            .min(max_row - height_in_lines + 1. + self.vertical_scroll_margin)
-                            .max(0.)
-                    } else {
-                        scroll_top
-                    }
+            let scroll_beyond_last_line = EditorSettings::get_global(cx).scroll_beyond_last_line;
+            let scroll_top = match (scroll_beyond_last_line, self.visible_line_count) {
+                (ScrollBeyondLastLine::OnePage, _) | (_, None) => scroll_top,
+                (_, Some(height_in_lines)) => {
+                    let max_row = map.max_point().row().0 as f32;
+                    scroll_top.min(self.max_scroll_top(max_row, height_in_lines, cx))
                 }
             };
 
@@ -426,12 +422,20 @@ impl Editor {
         cx: &mut Context<Self>,
     ) {
         let opened_first_time = self.scroll_manager.visible_line_count.is_none();
+        let lines_changed = self.scroll_manager.visible_line_count != Some(lines);
         self.scroll_manager.visible_line_count = Some(lines);
+        if lines_changed {
+            let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+            self.hint_visible_rows(&display_map, cx);
+        }
         if opened_first_time {
             cx.spawn_in(window, |editor, mut cx| async move {
                 editor
                     .update(&mut cx, |editor, cx| {
-                        editor.refresh_inlay_hints(InlayHintRefreshReason::NewLinesShown, cx)
+                        editor.refresh_inlay_hints(InlayHintRefreshReason::NewLinesShown, cx);
+                        editor.refresh_rainbow_brackets(cx);
+                        editor.refresh_soft_wrap_exclusions(cx);
+                        editor.refresh_word_diff_highlights(cx);
                     })
                     .ok()
             })
@@ -514,8 +518,29 @@ impl Editor {
             window,
             cx,
         );
+        self.hint_visible_rows(&display_map, cx);
 
         self.refresh_inlay_hints(InlayHintRefreshReason::NewLinesShown, cx);
+        self.refresh_rainbow_brackets(cx);
+        self.refresh_soft_wrap_exclusions(cx);
+        self.refresh_word_diff_highlights(cx);
+    }
+
+    /// Hints the display map with the rows on screen, so that rewrapping after large
+    /// edits lays them out first.
+    fn hint_visible_rows(&mut self, display_map: &DisplaySnapshot, cx: &mut Context<Self>) {
+        let Some(visible_line_count) = self.scroll_manager.visible_line_count else {
+            return;
+        };
+        let scroll_top = self.scroll_manager.anchor.scroll_position(display_map).y;
+        let start_row = DisplayRow(scroll_top as u32);
+        let end_row = DisplayRow(cmp::min(
+            (scroll_top + visible_line_count).ceil() as u32,
+            display_map.max_point().row().next_row().0,
+        ));
+        self.display_map.update(cx, |map, cx| {
+            map.set_visible_rows(start_row..end_row, display_map, cx)
+        });
     }
 
     pub fn scroll_position(&self, cx: &mut Context<Self>) -> gpui::Point<f32> {
@@ -544,6 +569,8 @@ impl Editor {
             window,
             cx,
         );
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        self.hint_visible_rows(&display_map, cx);
     }
 
     pub(crate) fn set_scroll_anchor_remote(
@@ -569,6 +596,8 @@ impl Editor {
             window,
             cx,
         );
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        self.hint_visible_rows(&display_map, cx);
     }
 
     pub fn scroll_screen(
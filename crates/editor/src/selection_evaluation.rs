@@ -0,0 +1,448 @@
+//! Evaluation of the selected text, like a calculator in the margin of the buffer.
+//!
+//! Once `editor::ToggleSelectionEvaluation` turned it on, each non-empty selection is evaluated
+//! as it changes, and the result is shown as virtual text after the selection: `2 * 21 = 42`.
+//! `editor::InsertSelectionEvaluation` inserts the results after their selections, whether or
+//! not they're shown.
+//!
+//! The text is evaluated by the first of the editor's [`SelectionEvaluator`]s that applies to it.
+//! The built-in ones convert Unix timestamps to dates and dates to Unix timestamps, evaluate
+//! arithmetic expressions, and convert integers between bases. More can be registered with
+//! [`Editor::register_selection_evaluator`].
+
+use crate::{
+    actions::{InsertSelectionEvaluation, ToggleSelectionEvaluation},
+    Editor, Inlay, InlayId,
+};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SecondsFormat};
+use gpui::{Context, Window};
+use std::{iter::Peekable, rc::Rc, str::Chars};
+use util::post_inc;
+
+/// Selections longer than this aren't evaluated.
+const MAX_EVALUATED_LEN: usize = 256;
+
+/// Evaluates selected text.
+pub trait SelectionEvaluator {
+    /// The result of evaluating the text, or `None` when the evaluator doesn't apply to it.
+    fn evaluate(&self, text: &str) -> Option<String>;
+}
+
+pub(crate) struct SelectionEvaluation {
+    enabled: bool,
+    evaluators: Vec<Rc<dyn SelectionEvaluator>>,
+    inlay_ids: Vec<InlayId>,
+}
+
+impl Default for SelectionEvaluation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            evaluators: vec![
+                Rc::new(TimestampEvaluator),
+                Rc::new(ArithmeticEvaluator),
+                Rc::new(BaseEvaluator),
+            ],
+            inlay_ids: Vec::new(),
+        }
+    }
+}
+
+/// The result of evaluating a selection, to be shown or inserted after it.
+struct Evaluation {
+    end: usize,
+    text: String,
+}
+
+impl Editor {
+    pub fn toggle_selection_evaluation(
+        &mut self,
+        _: &ToggleSelectionEvaluation,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.selection_evaluation.enabled = !self.selection_evaluation.enabled;
+        self.refresh_selection_evaluation(cx);
+    }
+
+    pub fn insert_selection_evaluation(
+        &mut self,
+        _: &InsertSelectionEvaluation,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let evaluations = self.evaluate_selections(cx);
+        if evaluations.is_empty() {
+            return;
+        }
+
+        // Leave the cursors after the inserted results, as if they had been typed.
+        let mut delta = 0;
+        let mut new_selections = Vec::new();
+        let mut evaluations_iter = evaluations.iter().peekable();
+        for selection in self.selections.all::<usize>(cx) {
+            let mut range = selection.start + delta..selection.end + delta;
+            if let Some(evaluation) =
+                evaluations_iter.next_if(|evaluation| evaluation.end == selection.end)
+            {
+                delta += evaluation.text.len();
+                range = selection.end + delta..selection.end + delta;
+            }
+            new_selections.push(range);
+        }
+
+        self.transact(window, cx, |editor, window, cx| {
+            editor.buffer.update(cx, |buffer, cx| {
+                buffer.edit(
+                    evaluations
+                        .into_iter()
+                        .map(|evaluation| (evaluation.end..evaluation.end, evaluation.text)),
+                    None,
+                    cx,
+                )
+            });
+            editor.change_selections(None, window, cx, |selections| {
+                selections.select_ranges(new_selections)
+            });
+        });
+    }
+
+    /// Adds an evaluator of the selections, taking precedence over those registered before.
+    pub fn register_selection_evaluator(
+        &mut self,
+        evaluator: Rc<dyn SelectionEvaluator>,
+        cx: &mut Context<Self>,
+    ) {
+        self.selection_evaluation.evaluators.insert(0, evaluator);
+        self.refresh_selection_evaluation(cx);
+    }
+
+    /// Shows the results of evaluating the selections after them, when enabled.
+    pub(crate) fn refresh_selection_evaluation(&mut self, cx: &mut Context<Self>) {
+        if !self.selection_evaluation.enabled && self.selection_evaluation.inlay_ids.is_empty() {
+            return;
+        }
+
+        let evaluations = if self.selection_evaluation.enabled {
+            self.evaluate_selections(cx)
+        } else {
+            Vec::new()
+        };
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let inlays = evaluations
+            .into_iter()
+            .map(|evaluation| {
+                Inlay::evaluation(
+                    post_inc(&mut self.next_inlay_id),
+                    snapshot.anchor_after(evaluation.end),
+                    evaluation.text,
+                )
+            })
+            .collect::<Vec<_>>();
+        let new_inlay_ids = inlays.iter().map(|inlay| inlay.id).collect();
+        let old_inlay_ids =
+            std::mem::replace(&mut self.selection_evaluation.inlay_ids, new_inlay_ids);
+        self.splice_inlays(&old_inlay_ids, inlays, cx);
+    }
+
+    fn evaluate_selections(&self, cx: &mut Context<Self>) -> Vec<Evaluation> {
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        self.selections
+            .all::<usize>(cx)
+            .into_iter()
+            .filter(|selection| {
+                !selection.is_empty() && selection.end - selection.start <= MAX_EVALUATED_LEN
+            })
+            .filter_map(|selection| {
+                let text = snapshot
+                    .text_for_range(selection.start..selection.end)
+                    .collect::<String>();
+                let text = text.trim();
+                // Selected calculations may already end with an equals sign.
+                let (expression, separator) = match text.strip_suffix('=') {
+                    Some(expression) => (expression.trim_end(), " "),
+                    None => (text, " = "),
+                };
+                let result = self
+                    .selection_evaluation
+                    .evaluators
+                    .iter()
+                    .find_map(|evaluator| evaluator.evaluate(expression))?;
+                Some(Evaluation {
+                    end: selection.end,
+                    text: format!("{separator}{result}"),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Evaluates arithmetic expressions of numbers, with `+`, `-`, `*`, `/`, `%`, `^` and
+/// parentheses. A lone number isn't an expression.
+struct ArithmeticEvaluator;
+
+impl SelectionEvaluator for ArithmeticEvaluator {
+    fn evaluate(&self, text: &str) -> Option<String> {
+        let mut parser = ArithmeticParser {
+            chars: text.chars().peekable(),
+            operation_count: 0,
+        };
+        let value = parser.expression()?;
+        parser.skip_whitespace();
+        if parser.chars.peek().is_some() || parser.operation_count == 0 || !value.is_finite() {
+            return None;
+        }
+        Some(format_number(value))
+    }
+}
+
+struct ArithmeticParser<'a> {
+    chars: Peekable<Chars<'a>>,
+    operation_count: usize,
+}
+
+impl ArithmeticParser<'_> {
+    fn expression(&mut self) -> Option<f64> {
+        let mut value = self.term()?;
+        while let Some(operator) = self.next_operator(&['+', '-']) {
+            let rhs = self.term()?;
+            value = match operator {
+                '+' => value + rhs,
+                _ => value - rhs,
+            };
+        }
+        Some(value)
+    }
+
+    fn term(&mut self) -> Option<f64> {
+        let mut value = self.unary()?;
+        while let Some(operator) = self.next_operator(&['*', '/', '%']) {
+            let rhs = self.unary()?;
+            value = match operator {
+                '*' => value * rhs,
+                '/' => value / rhs,
+                _ => value % rhs,
+            };
+        }
+        Some(value)
+    }
+
+    fn unary(&mut self) -> Option<f64> {
+        self.skip_whitespace();
+        if self.chars.next_if_eq(&'-').is_some() {
+            return Some(-self.unary()?);
+        }
+        let base = self.primary()?;
+        if self.next_operator(&['^']).is_some() {
+            // Exponentiation is right-associative, and binds tighter than negation on its left.
+            return Some(base.powf(self.unary()?));
+        }
+        Some(base)
+    }
+
+    fn primary(&mut self) -> Option<f64> {
+        self.skip_whitespace();
+        if self.chars.next_if_eq(&'(').is_some() {
+            let value = self.expression()?;
+            self.skip_whitespace();
+            self.chars.next_if_eq(&')')?;
+            return Some(value);
+        }
+
+        let mut number = String::new();
+        while let Some(c) = self
+            .chars
+            .next_if(|c| c.is_ascii_alphanumeric() || *c == '.' || *c == '_')
+        {
+            number.push(c);
+        }
+        match parse_integer(&number) {
+            Some(integer) => Some(integer as f64),
+            None => number.parse().ok(),
+        }
+    }
+
+    fn next_operator(&mut self, operators: &[char]) -> Option<char> {
+        self.skip_whitespace();
+        let operator = self.chars.next_if(|c| operators.contains(c))?;
+        self.operation_count += 1;
+        Some(operator)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+}
+
+/// Converts Unix timestamps, in seconds or milliseconds, to UTC dates, and dates to Unix
+/// timestamps.
+struct TimestampEvaluator;
+
+impl SelectionEvaluator for TimestampEvaluator {
+    fn evaluate(&self, text: &str) -> Option<String> {
+        if text.bytes().all(|byte| byte.is_ascii_digit()) {
+            let timestamp = text.parse::<i64>().ok()?;
+            // Only the lengths of the timestamps of recent and upcoming dates, to leave other
+            // integers to the base conversion.
+            return match text.len() {
+                10 => Some(
+                    DateTime::from_timestamp(timestamp, 0)?
+                        .to_rfc3339_opts(SecondsFormat::Secs, true),
+                ),
+                13 => Some(
+                    DateTime::from_timestamp_millis(timestamp)?
+                        .to_rfc3339_opts(SecondsFormat::Millis, true),
+                ),
+                _ => None,
+            };
+        }
+
+        let timestamp = if let Ok(date_time) = DateTime::parse_from_rfc3339(text) {
+            date_time.timestamp()
+        } else if let Ok(date_time) = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S") {
+            date_time.and_utc().timestamp()
+        } else {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()?
+                .and_hms_opt(0, 0, 0)?
+                .and_utc()
+                .timestamp()
+        };
+        Some(timestamp.to_string())
+    }
+}
+
+/// Converts integers to the other bases among decimal, hexadecimal, octal and binary.
+struct BaseEvaluator;
+
+impl SelectionEvaluator for BaseEvaluator {
+    fn evaluate(&self, text: &str) -> Option<String> {
+        let integer = parse_integer(text)?;
+        let prefix = text.get(..2).map(str::to_ascii_lowercase);
+        let conversions = [
+            (None, integer.to_string()),
+            (Some("0x"), format!("{integer:#x}")),
+            (Some("0o"), format!("{integer:#o}")),
+            (Some("0b"), format!("{integer:#b}")),
+        ];
+        let conversions = conversions
+            .into_iter()
+            .filter(|(base_prefix, _)| match base_prefix {
+                Some(base_prefix) => prefix.as_deref() != Some(base_prefix),
+                None => text
+                    .bytes()
+                    .any(|byte| !byte.is_ascii_digit() && byte != b'_'),
+            })
+            .map(|(_, conversion)| conversion)
+            .collect::<Vec<_>>();
+        Some(conversions.join(", "))
+    }
+}
+
+/// Parses a decimal integer, or a hexadecimal, octal or binary one with its prefix, allowing
+/// `_` separators between digits.
+fn parse_integer(text: &str) -> Option<u128> {
+    let (digits, radix) = match text.get(..2).map(str::to_ascii_lowercase).as_deref() {
+        Some("0x") => (&text[2..], 16),
+        Some("0o") => (&text[2..], 8),
+        Some("0b") => (&text[2..], 2),
+        _ => (text, 10),
+    };
+    if digits.starts_with('_') || digits.ends_with('_') {
+        return None;
+    }
+    let digits = digits.replace('_', "");
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    u128::from_str_radix(&digits, radix).ok()
+}
+
+/// Formats a number as an integer when it's whole, dropping the rounding errors of floats
+/// otherwise.
+fn format_number(value: f64) -> String {
+    if value.fract() == 0. && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+    let rounded = (value * 1e12).round() / 1e12;
+    if rounded == 0. {
+        format!("{value:e}")
+    } else {
+        rounded.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+    use gpui::TestAppContext;
+
+    #[gpui::test]
+    async fn test_selection_evaluation(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state("total: «2 + 3 * 4ˇ»\nhex: «0xffˇ»\n");
+
+        cx.update_editor(|editor, window, cx| {
+            editor.toggle_selection_evaluation(&ToggleSelectionEvaluation, window, cx);
+            assert_eq!(
+                editor.display_text(cx),
+                "total: 2 + 3 * 4 = 14\nhex: 0xff = 255, 0o377, 0b11111111\n"
+            );
+        });
+
+        // The results follow the selections.
+        cx.update_editor(|editor, window, cx| {
+            editor.change_selections(None, window, cx, |selections| {
+                selections.select_ranges([7..12])
+            });
+            assert_eq!(editor.display_text(cx), "total: 2 + 3 = 5 * 4\nhex: 0xff\n");
+        });
+
+        cx.update_editor(|editor, window, cx| {
+            editor.insert_selection_evaluation(&InsertSelectionEvaluation, window, cx);
+            editor.toggle_selection_evaluation(&ToggleSelectionEvaluation, window, cx);
+        });
+        cx.assert_editor_state("total: 2 + 3 = 5ˇ * 4\nhex: 0xff\n");
+        cx.update_editor(|editor, _, cx| {
+            assert_eq!(editor.display_text(cx), editor.text(cx));
+        });
+    }
+
+    #[test]
+    fn test_evaluators() {
+        let evaluate = |text: &str| {
+            SelectionEvaluation::default()
+                .evaluators
+                .iter()
+                .find_map(|evaluator| evaluator.evaluate(text))
+        };
+
+        assert_eq!(evaluate("(1 + 2) * -3 ^ 2").as_deref(), Some("-27"));
+        assert_eq!(evaluate("2 ^ 3 ^ 2").as_deref(), Some("512"));
+        assert_eq!(evaluate("0.1 + 0.2").as_deref(), Some("0.3"));
+        assert_eq!(evaluate("10 % 4 + 0x10").as_deref(), Some("18"));
+        assert_eq!(evaluate("1 / 0"), None);
+        assert_eq!(evaluate("1 +"), None);
+        assert_eq!(evaluate("-1"), None);
+
+        assert_eq!(
+            evaluate("1700000000").as_deref(),
+            Some("2023-11-14T22:13:20Z")
+        );
+        assert_eq!(
+            evaluate("1700000000123").as_deref(),
+            Some("2023-11-14T22:13:20.123Z")
+        );
+        assert_eq!(
+            evaluate("2023-11-14T22:13:20Z").as_deref(),
+            Some("1700000000")
+        );
+        assert_eq!(evaluate("2024-01-01").as_deref(), Some("1704067200"));
+
+        assert_eq!(evaluate("255").as_deref(), Some("0xff, 0o377, 0b11111111"));
+        assert_eq!(evaluate("0b1_0000").as_deref(), Some("16, 0x10, 0o20"));
+        assert_eq!(evaluate("word"), None);
+    }
+}
@@ -0,0 +1,157 @@
+//! Keeps the lines of the regions a language marks as never soft-wrapped, like Markdown
+//! tables, from being wrapped, so that they overflow horizontally instead.
+//!
+//! The regions are the matches of the `nowrap` queries of the buffers' languages. They are
+//! passed to the wrap map as anchor ranges, so that they follow edits until they're
+//! recomputed. Only the rows around the visible ones are queried, in the background, when they
+//! scroll into view or when their buffer is edited or reparsed, and the regions found in the
+//! other rows are kept.
+
+use crate::Editor;
+use collections::HashMap;
+use gpui::{Context, Task};
+use language::{Point, ToOffset as _};
+use multi_buffer::{Anchor, ExcerptId, ToPoint as _};
+use std::ops::Range;
+use text::Bias;
+
+#[derive(Default)]
+pub(crate) struct SoftWrapExclusions {
+    /// The regions excluded from soft wrapping, sorted.
+    ranges: Vec<Range<Anchor>>,
+    /// The range of each excerpt the regions were queried in.
+    queried: HashMap<ExcerptId, QueriedRange>,
+    task: Option<Task<()>>,
+}
+
+/// A range of an excerpt's buffer the regions were queried in, along with the state of the
+/// buffer they were queried at.
+struct QueriedRange {
+    version: clock::Global,
+    non_text_state_update_count: usize,
+    range: Range<usize>,
+}
+
+impl Editor {
+    pub(crate) fn refresh_soft_wrap_exclusions(&mut self, cx: &mut Context<Self>) {
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let visible_rows = self.visible_line_count().unwrap_or(0.).ceil() as u32;
+        let visible_start = self.scroll_manager.anchor().anchor.to_point(&snapshot);
+
+        // Forget the regions of the excerpts that were removed, or no longer exclude them.
+        let exclusions = &mut self.soft_wrap_exclusions;
+        let previous_len = exclusions.queried.len();
+        exclusions.queried.retain(|excerpt_id, _| {
+            snapshot
+                .buffer_for_excerpt(*excerpt_id)
+                .map_or(false, |buffer| {
+                    buffer.settings_at(0, cx).soft_wrap_exclusions
+                })
+        });
+        let changed = exclusions.queried.len() != previous_len;
+        if changed {
+            exclusions
+                .ranges
+                .retain(|range| exclusions.queried.contains_key(&range.start.excerpt_id));
+        }
+
+        // Query the rows around the visible ones too, so that scrolling shows them unwrapped.
+        let start = Point::new(visible_start.row.saturating_sub(visible_rows), 0);
+        let end = snapshot.clip_point(
+            Point::new(visible_start.row + 2 * visible_rows + 1, 0),
+            Bias::Left,
+        );
+        let stale_ranges = snapshot
+            .range_to_buffer_ranges(start..end)
+            .into_iter()
+            .filter(|(buffer, range, excerpt_id)| {
+                buffer.settings_at(range.start, cx).soft_wrap_exclusions
+                    && exclusions.queried.get(excerpt_id).map_or(true, |queried| {
+                        queried.version != *buffer.version()
+                            || queried.non_text_state_update_count
+                                != buffer.non_text_state_update_count()
+                            || range.start < queried.range.start
+                            || queried.range.end < range.end
+                    })
+            })
+            .map(|(buffer, range, excerpt_id)| (buffer.clone(), range, excerpt_id))
+            .collect::<Vec<_>>();
+        if stale_ranges.is_empty() {
+            if changed {
+                exclusions.task = None;
+                let ranges = exclusions.ranges.clone();
+                if self
+                    .display_map
+                    .update(cx, |map, cx| map.set_no_wrap_ranges(ranges, cx))
+                {
+                    cx.notify();
+                }
+            }
+            return;
+        }
+
+        let new_ranges = cx.background_executor().spawn({
+            let snapshot = snapshot.clone();
+            async move {
+                let mut new_ranges = Vec::new();
+                for (buffer, range, excerpt_id) in &stale_ranges {
+                    let ranges = buffer
+                        .no_wrap_ranges(range.clone())
+                        .filter_map(|range| {
+                            let start = snapshot
+                                .anchor_in_excerpt(*excerpt_id, buffer.anchor_after(range.start));
+                            let end = snapshot
+                                .anchor_in_excerpt(*excerpt_id, buffer.anchor_before(range.end));
+                            Some(start?..end?)
+                        })
+                        .collect::<Vec<_>>();
+                    new_ranges.extend(ranges);
+                }
+                (stale_ranges, new_ranges)
+            }
+        });
+        exclusions.task = Some(cx.spawn(|this, mut cx| async move {
+            let (stale_ranges, new_ranges) = new_ranges.await;
+            this.update(&mut cx, |this, cx| {
+                let snapshot = this.buffer.read(cx).snapshot(cx);
+                let exclusions = &mut this.soft_wrap_exclusions;
+
+                // The regions overlapping the queried ranges are replaced by the new ones.
+                exclusions.ranges.retain(|range| {
+                    let Some(buffer) = snapshot.buffer_for_excerpt(range.start.excerpt_id) else {
+                        return false;
+                    };
+                    !stale_ranges.iter().any(|(_, queried_range, excerpt_id)| {
+                        range.start.excerpt_id == *excerpt_id
+                            && range.start.text_anchor.to_offset(buffer) <= queried_range.end
+                            && queried_range.start <= range.end.text_anchor.to_offset(buffer)
+                    })
+                });
+                exclusions.ranges.extend(new_ranges);
+                for (buffer, range, excerpt_id) in stale_ranges {
+                    exclusions.queried.insert(
+                        excerpt_id,
+                        QueriedRange {
+                            version: buffer.version().clone(),
+                            non_text_state_update_count: buffer.non_text_state_update_count(),
+                            range,
+                        },
+                    );
+                }
+                exclusions
+                    .ranges
+                    .sort_by(|a, b| a.start.cmp(&b.start, &snapshot));
+                exclusions.ranges.dedup();
+
+                let ranges = exclusions.ranges.clone();
+                if this
+                    .display_map
+                    .update(cx, |map, cx| map.set_no_wrap_ranges(ranges, cx))
+                {
+                    cx.notify();
+                }
+            })
+            .ok();
+        }));
+    }
+}
@@ -0,0 +1,706 @@
+//! Editing of the string literals under the cursors.
+//!
+//! A string literal is the innermost syntax node enclosing the cursor whose kind is a string,
+//! like `string_literal` or `string`, rather than a part of one, like `string_content`. How it's
+//! delimited and escaped comes from the `string_literals` of its language's config: the quotes it
+//! can be written with, and the prefix of raw strings.
+//!
+//! - `editor::CycleStringQuotes` rewrites a string with the next of its language's quotes,
+//!   unescaping the old quote and escaping the new one.
+//! - `editor::ToggleRawString` converts an escaped string into a raw one and back, adding `#`s
+//!   around a raw string containing its quote in languages that allow it.
+//! - `editor::EditStringLiteral` opens the unescaped contents of a string in a buffer of their
+//!   own, in the language injected into the string, or in JSON when they are JSON. Edits made
+//!   there are written back into the string, escaped, as edits of the characters they change.
+
+use crate::{
+    actions::{CycleStringQuotes, EditStringLiteral, ToggleRawString},
+    Editor,
+};
+use gpui::{AppContext as _, Context, Entity, Window};
+use language::{Buffer, BufferEvent, Language, StringLiteralConfig};
+use multi_buffer::{MultiBuffer, MultiBufferSnapshot, ToOffset as _};
+use similar::{ChangeTag, TextDiff};
+use std::{iter::Peekable, ops::Range, str::Chars, sync::Arc};
+use workspace::SplitDirection;
+
+/// A string literal of a multibuffer.
+struct StringLiteral {
+    range: Range<usize>,
+    content: Range<usize>,
+    /// The letters before the opening quote, like `r` or `b`.
+    prefix: String,
+    /// The number of `#`s between the prefix and the opening quote.
+    hashes: usize,
+    quote: String,
+    raw: bool,
+    /// Whether the literal is a format string, with replacement fields between braces.
+    format: bool,
+    language: Arc<Language>,
+    config: StringLiteralConfig,
+}
+
+impl Editor {
+    pub fn cycle_string_quotes(
+        &mut self,
+        _: &CycleStringQuotes,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.rewrite_string_literals(StringLiteral::with_next_quote, window, cx);
+    }
+
+    pub fn toggle_raw_string(
+        &mut self,
+        _: &ToggleRawString,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.rewrite_string_literals(StringLiteral::toggled_raw, window, cx);
+    }
+
+    /// Replaces the string literals under the selections with their rewritten form, leaving
+    /// those that can't be rewritten as they are.
+    fn rewrite_string_literals(
+        &mut self,
+        rewrite: fn(&StringLiteral, &str) -> Option<String>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let mut rewritten_ranges = Vec::<Range<usize>>::new();
+        let mut edits = Vec::<(Range<usize>, String)>::new();
+        for selection in self.selections.all::<usize>(cx) {
+            let Some(literal) = string_literal_at(&snapshot, selection.head()) else {
+                continue;
+            };
+            if rewritten_ranges.contains(&literal.range) {
+                continue;
+            }
+            let content = snapshot
+                .text_for_range(literal.content.clone())
+                .collect::<String>();
+            let Some(new_text) = rewrite(&literal, &content) else {
+                continue;
+            };
+            let old_text = snapshot
+                .text_for_range(literal.range.clone())
+                .collect::<String>();
+            edits.extend(changed_character_edits(
+                literal.range.start,
+                &old_text,
+                &new_text,
+            ));
+            rewritten_ranges.push(literal.range);
+        }
+        if edits.is_empty() {
+            return;
+        }
+
+        self.transact(window, cx, |editor, _, cx| {
+            editor
+                .buffer
+                .update(cx, |buffer, cx| buffer.edit(edits, None, cx));
+        });
+    }
+
+    /// Opens the contents of the string literal under the newest selection in a buffer of their
+    /// own, next to the editor.
+    pub fn edit_string_literal(
+        &mut self,
+        _: &EditStringLiteral,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(workspace) = self.workspace() else {
+            return;
+        };
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let head = self.selections.newest::<usize>(cx).head();
+        let Some(literal) = string_literal_at(&snapshot, head) else {
+            return;
+        };
+        let content = snapshot
+            .text_for_range(literal.content.clone())
+            .collect::<String>();
+        let content = if literal.raw {
+            content
+        } else {
+            unescape(&content)
+        };
+
+        let injected_language = snapshot
+            .language_at(literal.content.start)
+            .filter(|language| language.name() != literal.language.name())
+            .cloned();
+        let languages = self
+            .project
+            .as_ref()
+            .map(|project| project.read(cx).languages().clone());
+        let literal_range =
+            snapshot.anchor_before(literal.range.start)..snapshot.anchor_after(literal.range.end);
+        let title = format!("{} string", literal.language.name());
+        let project = self.project.clone();
+
+        cx.spawn_in(window, |editor, mut cx| async move {
+            let mut language = injected_language;
+            if language.is_none() && is_json(&content) {
+                if let Some(languages) = languages {
+                    language = languages.language_for_name("JSON").await.ok();
+                }
+            }
+
+            editor.update_in(&mut cx, |_, window, cx| {
+                let buffer = cx.new(|cx| {
+                    let buffer = Buffer::local(content, cx);
+                    match language {
+                        Some(language) => buffer.with_language(language, cx),
+                        None => buffer,
+                    }
+                });
+                cx.subscribe(&buffer, move |editor, buffer, event, cx| {
+                    if let BufferEvent::Edited = event {
+                        let snapshot = editor.buffer.read(cx).snapshot(cx);
+                        let range = literal_range.start.to_offset(&snapshot)
+                            ..literal_range.end.to_offset(&snapshot);
+                        let old_text = snapshot.text_for_range(range.clone()).collect::<String>();
+                        let new_text = literal.with_content(&buffer.read(cx).text());
+                        let edits = changed_character_edits(range.start, &old_text, &new_text);
+                        editor
+                            .buffer
+                            .update(cx, |multibuffer, cx| multibuffer.edit(edits, None, cx));
+                        // The edits are saved into the string.
+                        buffer.update(cx, |buffer, cx| {
+                            let version = buffer.version();
+                            buffer.did_save(version, None, cx)
+                        });
+                    }
+                })
+                .detach();
+
+                let multibuffer = cx.new(|cx| MultiBuffer::singleton(buffer, cx).with_title(title));
+                let string_editor =
+                    cx.new(|cx| Editor::for_multibuffer(multibuffer, project, false, window, cx));
+                workspace.update(cx, |workspace, cx| {
+                    workspace.split_item(SplitDirection::Right, Box::new(string_editor), window, cx)
+                });
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+}
+
+/// The edits replacing the old text starting at an offset with the new one, only editing the
+/// characters that change, so that the selections and anchors around the others stay where
+/// they are.
+fn changed_character_edits(
+    mut offset: usize,
+    old_text: &str,
+    new_text: &str,
+) -> Vec<(Range<usize>, String)> {
+    let mut edits = Vec::<(Range<usize>, String)>::new();
+    let mut moved_since_edit = true;
+    for change in TextDiff::from_chars(old_text, new_text).iter_all_changes() {
+        let value = change.value();
+        match change.tag() {
+            ChangeTag::Equal => {
+                offset += value.len();
+                moved_since_edit = true;
+            }
+            ChangeTag::Delete => {
+                if moved_since_edit {
+                    edits.push((offset..offset + value.len(), String::new()));
+                } else if let Some((range, _)) = edits.last_mut() {
+                    range.end = offset + value.len();
+                }
+                offset += value.len();
+                moved_since_edit = false;
+            }
+            ChangeTag::Insert => {
+                if moved_since_edit {
+                    edits.push((offset..offset, value.to_string()));
+                } else if let Some((_, text)) = edits.last_mut() {
+                    text.push_str(value);
+                }
+                moved_since_edit = false;
+            }
+        }
+    }
+    edits
+}
+
+/// The string literal enclosing an offset, in a language whose string literals are configured.
+fn string_literal_at(snapshot: &MultiBufferSnapshot, offset: usize) -> Option<StringLiteral> {
+    let mut excerpt = snapshot.excerpt_containing(offset..offset)?;
+    let buffer = excerpt.buffer();
+    let buffer_offset = excerpt.map_offset_to_buffer(offset);
+    let mut node = buffer.syntax_ancestor(buffer_offset..buffer_offset)?;
+    while !is_string_kind(node.kind()) {
+        node = node.parent()?;
+    }
+
+    // The literal belongs to the innermost language at its opening quote, as the ones injected
+    // into it only start with its contents.
+    let buffer_range = node.byte_range();
+    let language = buffer.language_at(buffer_range.start)?.clone();
+    let config = language.config().string_literals.clone()?;
+    let text = buffer
+        .text_for_range(buffer_range.clone())
+        .collect::<String>();
+
+    let quote_start = text.find(|c| config.quotes.iter().any(|quote| quote.starts_with(c)))?;
+    let (letters, hashes) =
+        text[..quote_start].split_at(text[..quote_start].trim_end_matches('#').len());
+    if !letters.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let single_quote = config
+        .quotes
+        .iter()
+        .find(|quote| text[quote_start..].starts_with(quote.as_str()))?;
+    let triple_quote = single_quote.repeat(3);
+    let quote = if text.len() >= quote_start + 2 * triple_quote.len()
+        && text[quote_start..].starts_with(&triple_quote)
+    {
+        triple_quote
+    } else {
+        single_quote.clone()
+    };
+    let suffix = format!("{quote}{hashes}");
+    if text.len() < quote_start + quote.len() + suffix.len() || !text.ends_with(&suffix) {
+        return None;
+    }
+    let has_prefix = |prefix: &Option<String>| {
+        prefix.as_ref().is_some_and(|prefix| {
+            letters
+                .to_ascii_lowercase()
+                .contains(&prefix.to_ascii_lowercase())
+        })
+    };
+    let raw = has_prefix(&config.raw_prefix);
+    let format = has_prefix(&config.format_prefix);
+
+    let content_range =
+        buffer_range.start + quote_start + quote.len()..buffer_range.end - suffix.len();
+    Some(StringLiteral {
+        range: excerpt.map_range_from_buffer(buffer_range),
+        content: excerpt.map_range_from_buffer(content_range),
+        prefix: letters.to_string(),
+        hashes: hashes.len(),
+        quote,
+        raw,
+        format,
+        language,
+        config,
+    })
+}
+
+/// Whether a syntax node is a whole string literal, rather than a part of one.
+fn is_string_kind(kind: &str) -> bool {
+    kind.contains("string")
+        && !["content", "fragment", "start", "end", "concatenated"]
+            .iter()
+            .any(|part| kind.contains(part))
+}
+
+fn is_json(text: &str) -> bool {
+    let text = text.trim();
+    (text.starts_with('{') || text.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(text).is_ok()
+}
+
+impl StringLiteral {
+    fn is_triple_quoted(&self) -> bool {
+        !self.config.quotes.contains(&self.quote)
+    }
+
+    fn literal(&self, prefix: &str, hashes: usize, quote: &str, content: &str) -> String {
+        let hashes = "#".repeat(hashes);
+        format!("{prefix}{hashes}{quote}{content}{quote}{hashes}")
+    }
+
+    /// The literal with the next quote of its language, if its content can be written with it.
+    fn with_next_quote(&self, content: &str) -> Option<String> {
+        let quotes = &self.config.quotes;
+        let ix = quotes
+            .iter()
+            .position(|quote| self.quote.starts_with(quote.as_str()))?;
+        let next_quote = &quotes[(ix + 1) % quotes.len()];
+        if *next_quote == quotes[ix] {
+            return None;
+        }
+        // The replacement fields of format strings are kept as they are, so they can't contain
+        // the new quote.
+        if replacement_field_chars(content, self.format)
+            .iter()
+            .any(|(ch, in_field)| *in_field && next_quote.starts_with(*ch))
+        {
+            return None;
+        }
+
+        if self.raw || self.is_triple_quoted() {
+            let quote = if self.is_triple_quoted() {
+                next_quote.repeat(3)
+            } else {
+                next_quote.clone()
+            };
+            if content.contains(quote.as_str()) || content.ends_with(next_quote.as_str()) {
+                return None;
+            }
+            Some(self.literal(&self.prefix, self.hashes, &quote, content))
+        } else {
+            let content = requote(content, &quotes[ix], next_quote, self.format);
+            Some(self.literal(&self.prefix, self.hashes, next_quote, &content))
+        }
+    }
+
+    /// The literal converted between its escaped and raw forms, if its content can be written
+    /// in the other one.
+    fn toggled_raw(&self, content: &str) -> Option<String> {
+        let raw_prefix = self.config.raw_prefix.as_deref()?;
+        if self.raw {
+            let start = self
+                .prefix
+                .to_ascii_lowercase()
+                .find(&raw_prefix.to_ascii_lowercase())?;
+            let mut prefix = self.prefix.clone();
+            prefix.replace_range(start..start + raw_prefix.len(), "");
+            let content = escape(content, &self.quote, self.is_triple_quoted());
+            Some(self.literal(&prefix, 0, &self.quote, &content))
+        } else {
+            let content = unescape(content);
+            let hashes = self.raw_hashes(&content, 0)?;
+            let prefix = format!("{}{raw_prefix}", self.prefix);
+            Some(self.literal(&prefix, hashes, &self.quote, &content))
+        }
+    }
+
+    /// The literal in its current form with other content, escaped for it.
+    fn with_content(&self, content: &str) -> String {
+        if self.raw {
+            if let Some(hashes) = self.raw_hashes(content, self.hashes) {
+                return self.literal(&self.prefix, hashes, &self.quote, content);
+            }
+        }
+        let prefix = match self.config.raw_prefix.as_deref() {
+            Some(raw_prefix) if self.raw => self.prefix.replacen(raw_prefix, "", 1),
+            _ => self.prefix.clone(),
+        };
+        let content = escape(content, &self.quote, self.is_triple_quoted());
+        self.literal(&prefix, 0, &self.quote, &content)
+    }
+
+    /// The number of `#`s, at least `min_hashes`, that a raw string with the content needs
+    /// around its quotes, if it can be written as a raw string at all.
+    fn raw_hashes(&self, content: &str, min_hashes: usize) -> Option<usize> {
+        if self.config.raw_hashes {
+            return (min_hashes..).find(|hashes| {
+                !content.contains(&format!("{}{}", self.quote, "#".repeat(*hashes)))
+            });
+        }
+        let quote_char = self.quote.chars().next()?;
+        let fits = !content.contains(quote_char)
+            && !content.ends_with('\\')
+            && (self.is_triple_quoted() || !content.contains('\n'));
+        fits.then_some(0)
+    }
+}
+
+/// Escapes the backslashes, quotes and line breaks of a string's content.
+fn escape(content: &str, quote: &str, multiline: bool) -> String {
+    let mut escaped = String::with_capacity(content.len());
+    for ch in content.chars() {
+        match ch {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' if !multiline => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            ch if quote.starts_with(ch) => {
+                escaped.push('\\');
+                escaped.push(ch);
+            }
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Resolves the escape sequences of a string's content. Sequences that aren't known are kept.
+fn unescape(content: &str) -> String {
+    let mut unescaped = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('t') => unescaped.push('\t'),
+            Some('0') => unescaped.push('\0'),
+            Some(ch @ ('\\' | '"' | '\'' | '`')) => unescaped.push(ch),
+            Some('x') => push_code_point(&mut unescaped, "\\x", hex_digits(&mut chars, 2)),
+            Some('u') if chars.peek() == Some(&'{') => {
+                chars.next();
+                let digits = hex_digits(&mut chars, 6);
+                if chars.next_if_eq(&'}').is_some() {
+                    push_code_point(&mut unescaped, "\\u", digits);
+                } else {
+                    unescaped.push_str("\\u{");
+                    unescaped.push_str(&digits);
+                }
+            }
+            Some('u') => push_code_point(&mut unescaped, "\\u", hex_digits(&mut chars, 4)),
+            Some(ch) => {
+                unescaped.push('\\');
+                unescaped.push(ch);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+fn hex_digits(chars: &mut Peekable<Chars>, max_len: usize) -> String {
+    let mut digits = String::new();
+    while digits.len() < max_len {
+        match chars.next_if(|ch| ch.is_ascii_hexdigit()) {
+            Some(digit) => digits.push(digit),
+            None => break,
+        }
+    }
+    digits
+}
+
+fn push_code_point(text: &mut String, escape: &str, digits: String) {
+    match u32::from_str_radix(&digits, 16)
+        .ok()
+        .and_then(char::from_u32)
+    {
+        Some(ch) => text.push(ch),
+        None => {
+            text.push_str(escape);
+            text.push_str(&digits);
+        }
+    }
+}
+
+/// The characters of a string's content, along with whether they're in a replacement field of a
+/// format string, outside of which `{{` and `}}` are escaped braces.
+fn replacement_field_chars(content: &str, format: bool) -> Vec<(char, bool)> {
+    let mut field_chars = Vec::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut depth = 0;
+    while let Some(ch) = chars.next() {
+        if format {
+            match ch {
+                '{' | '}' if depth == 0 && chars.next_if_eq(&ch).is_some() => {
+                    field_chars.extend([(ch, false), (ch, false)]);
+                    continue;
+                }
+                '{' => {
+                    depth += 1;
+                    field_chars.push((ch, true));
+                    continue;
+                }
+                '}' if depth > 0 => {
+                    depth -= 1;
+                    field_chars.push((ch, true));
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        field_chars.push((ch, depth > 0));
+    }
+    field_chars
+}
+
+/// Rewrites the content of a string delimited by one quote for another: the escaped old quotes
+/// are unescaped and the new ones are escaped. The replacement fields of format strings are
+/// code, which is kept as it is.
+fn requote(content: &str, old_quote: &str, new_quote: &str, format: bool) -> String {
+    let mut requoted = String::with_capacity(content.len());
+    let mut chars = replacement_field_chars(content, format)
+        .into_iter()
+        .peekable();
+    while let Some((ch, in_field)) = chars.next() {
+        if in_field {
+            requoted.push(ch);
+        } else if ch == '\\' {
+            match chars.next_if(|(_, in_field)| !in_field) {
+                Some((ch, _)) if old_quote.starts_with(ch) => requoted.push(ch),
+                Some((ch, _)) => {
+                    requoted.push('\\');
+                    requoted.push(ch);
+                }
+                None => requoted.push('\\'),
+            }
+        } else {
+            if new_quote.starts_with(ch) {
+                requoted.push('\\');
+            }
+            requoted.push(ch);
+        }
+    }
+    requoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+    use gpui::{TestAppContext, VisualContext as _, VisualTestContext};
+    use language::LanguageConfig;
+    use project::{FakeFs, Project};
+    use workspace::Workspace;
+
+    fn rust_lang() -> Arc<Language> {
+        Arc::new(Language::new(
+            LanguageConfig {
+                name: "Rust".into(),
+                string_literals: Some(StringLiteralConfig {
+                    quotes: vec!["\"".into()],
+                    raw_prefix: Some("r".into()),
+                    raw_hashes: true,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            Some(tree_sitter_rust::LANGUAGE.into()),
+        ))
+    }
+
+    #[gpui::test]
+    async fn test_rewrite_string_literals(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        let rust = rust_lang();
+        let typescript = Arc::new(Language::new(
+            LanguageConfig {
+                name: "TypeScript".into(),
+                string_literals: Some(StringLiteralConfig {
+                    quotes: vec!["\"".into(), "'".into()],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            Some(tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into()),
+        ));
+
+        cx.update_buffer(|buffer, cx| buffer.set_language(Some(rust), cx));
+        cx.set_state(r#"let a = "one \"twˇo\"\\";"#);
+        cx.run_until_parked();
+        cx.update_editor(|editor, window, cx| {
+            editor.toggle_raw_string(&ToggleRawString, window, cx)
+        });
+        cx.assert_editor_state(r##"let a = r#"one "twˇo"\"#;"##);
+        cx.update_editor(|editor, window, cx| {
+            editor.toggle_raw_string(&ToggleRawString, window, cx)
+        });
+        cx.assert_editor_state(r#"let a = "one \"twˇo\"\\";"#);
+
+        cx.update_buffer(|buffer, cx| buffer.set_language(Some(typescript), cx));
+        cx.set_state(r#"let a = "it's \"quoˇted\"";"#);
+        cx.run_until_parked();
+        cx.update_editor(|editor, window, cx| {
+            editor.cycle_string_quotes(&CycleStringQuotes, window, cx)
+        });
+        cx.assert_editor_state(r#"let a = 'it\'s "quoˇted"';"#);
+        cx.update_editor(|editor, window, cx| {
+            editor.cycle_string_quotes(&CycleStringQuotes, window, cx)
+        });
+        cx.assert_editor_state(r#"let a = "it's \"quoˇted\"";"#);
+    }
+
+    #[gpui::test]
+    async fn test_edit_string_literal(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let fs = FakeFs::new(cx.executor());
+        let project = Project::test(fs, [], cx).await;
+        let workspace =
+            cx.add_window(|window, cx| Workspace::test_new(project.clone(), window, cx));
+        let cx = &mut VisualTestContext::from_window(*workspace, cx);
+        let buffer = project.update(cx, |project, cx| {
+            project.create_local_buffer(r#"let a = "say \"hi\" now";"#, Some(rust_lang()), cx)
+        });
+        let editor = cx.new_window_entity(|window, cx| {
+            Editor::for_buffer(buffer, Some(project.clone()), window, cx)
+        });
+        workspace
+            .update(cx, |workspace, window, cx| {
+                workspace.add_item_to_active_pane(Box::new(editor.clone()), None, true, window, cx)
+            })
+            .unwrap();
+        cx.run_until_parked();
+
+        let now_offset = editor.update(cx, |editor, cx| editor.text(cx).find("now").unwrap());
+        editor.update_in(cx, |editor, window, cx| {
+            editor.change_selections(None, window, cx, |s| {
+                s.select_ranges([now_offset..now_offset])
+            });
+            editor.edit_string_literal(&EditStringLiteral, window, cx);
+        });
+        cx.run_until_parked();
+        let string_editor = workspace
+            .update(cx, |workspace, _, cx| {
+                workspace.active_item_as::<Editor>(cx).unwrap()
+            })
+            .unwrap();
+        assert_ne!(string_editor, editor);
+        assert_eq!(
+            string_editor.update(cx, |editor, cx| editor.text(cx)),
+            r#"say "hi" now"#
+        );
+
+        // The edits are written back escaped, as edits of the characters they change, so the
+        // selections in the string don't move with the rest of it.
+        string_editor.update_in(cx, |editor, window, cx| {
+            editor.change_selections(None, window, cx, |s| s.select_ranges([7..7]));
+            editor.insert(" \"there\"", window, cx);
+        });
+        cx.run_until_parked();
+        editor.update(cx, |editor, cx| {
+            let text = editor.text(cx);
+            assert_eq!(text, r#"let a = "say \"hi \"there\"\" now";"#);
+            assert_eq!(
+                editor.selections.newest::<usize>(cx).head(),
+                text.find("now").unwrap()
+            );
+        });
+        assert!(!string_editor.update(cx, |editor, cx| editor.is_dirty(cx)));
+    }
+
+    #[test]
+    fn test_escape_and_unescape() {
+        let content = "tab\t \"quote\" back\\slash \u{e9}";
+        assert_eq!(
+            escape(content, "\"", false),
+            r#"tab\t \"quote\" back\\slash é"#
+        );
+        assert_eq!(unescape(&escape(content, "\"", false)), content);
+        assert_eq!(unescape(r"\x41é\u{1F600}\d"), "Aé😀\\d");
+    }
+
+    #[test]
+    fn test_requote() {
+        assert_eq!(
+            requote(r#"it's \"quoted\""#, "\"", "'", false),
+            r#"it\'s "quoted""#
+        );
+        // The replacement fields of format strings are kept as they are.
+        assert_eq!(
+            requote(r#"{d['key']!r} and "{{it\'s}}""#, "'", "\"", true),
+            r#"{d['key']!r} and \"{{it's}}\""#
+        );
+        assert_eq!(
+            replacement_field_chars("{{a}} {b:{c}}", true)
+                .into_iter()
+                .filter(|(_, in_field)| *in_field)
+                .map(|(ch, _)| ch)
+                .collect::<String>(),
+            "{b:{c}}"
+        );
+    }
+}
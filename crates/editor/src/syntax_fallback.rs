@@ -0,0 +1,140 @@
+//! Keeps pathological files responsive while their syntax highlighting is slow.
+//!
+//! When the visible rows span more than [`MAX_LIVE_HIGHLIGHT_BYTES`], as in minified files, the
+//! editor stops querying the syntax trees while laying out text. The syntax highlights of the
+//! region around the visible rows are computed in the background instead, then applied on a
+//! later frame, and rows are laid out with their other highlights and diagnostics until then.
+//! After an edit, the highlights are moved along with the text, dropping those of the edited
+//! text, until the region is highlighted again. Once the visible rows span less than half of
+//! the budget, the editor goes back to highlighting text while laying it out.
+
+use crate::{display_map::PrecomputedSyntaxHighlights, Editor};
+use gpui::{Context, Task};
+use multi_buffer::MultiBufferSnapshot;
+use std::{ops::Range, sync::Arc};
+use text::Bias;
+
+/// How many bytes of visible rows may be highlighted by querying the syntax trees.
+pub(crate) const MAX_LIVE_HIGHLIGHT_BYTES: usize = 256 * 1024;
+
+/// How the syntax of the visible rows is highlighted while laying them out.
+#[derive(Clone, Debug, Default)]
+pub(crate) enum SyntaxHighlighting {
+    /// The rows are highlighted by querying the syntax trees.
+    #[default]
+    Live,
+    /// The rows are highlighted from the precomputed highlights, when they are current.
+    Deferred(Arc<PrecomputedSyntaxHighlights>),
+}
+
+#[derive(Default)]
+pub(crate) struct SyntaxFallback {
+    deferred: bool,
+    highlights: Option<Arc<PrecomputedSyntaxHighlights>>,
+    /// The range being highlighted in the background, and the edit count it's highlighted at.
+    pending: Option<(Range<usize>, usize, Task<()>)>,
+}
+
+impl SyntaxFallback {
+    pub(crate) fn highlighting(&self) -> SyntaxHighlighting {
+        if self.deferred {
+            SyntaxHighlighting::Deferred(
+                self.highlights
+                    .clone()
+                    .unwrap_or_else(|| Arc::new(PrecomputedSyntaxHighlights::empty())),
+            )
+        } else {
+            SyntaxHighlighting::Live
+        }
+    }
+}
+
+impl Editor {
+    /// Returns how to highlight the syntax of the visible range, deferring it when the range
+    /// is too long, and highlights the region around it in the background when deferred,
+    /// unless its current highlights are already computed or being computed.
+    pub(crate) fn refresh_syntax_highlighting(
+        &mut self,
+        visible_range: Range<usize>,
+        buffer: &MultiBufferSnapshot,
+        cx: &mut Context<Self>,
+    ) -> SyntaxHighlighting {
+        let fallback = &mut self.syntax_fallback;
+        let visible_len = visible_range.len();
+        if !fallback.deferred && visible_len > MAX_LIVE_HIGHLIGHT_BYTES {
+            log::debug!("deferring syntax highlighting of {visible_len} visible bytes");
+            fallback.deferred = true;
+        } else if fallback.deferred && visible_len <= MAX_LIVE_HIGHLIGHT_BYTES / 2 {
+            log::debug!("highlighting syntax live again for {visible_len} visible bytes");
+            fallback.deferred = false;
+            fallback.highlights = None;
+            fallback.pending = None;
+        }
+        if !fallback.deferred {
+            return SyntaxHighlighting::Live;
+        }
+
+        if let Some(highlights) = fallback.highlights.as_mut() {
+            if !highlights.is_current(buffer) {
+                *highlights = Arc::new(highlights.interpolate(buffer));
+            }
+        }
+        let covers_visible_range = |range: &Range<usize>| {
+            range.start <= visible_range.start && visible_range.end <= range.end
+        };
+        let is_highlighted = fallback.highlights.as_ref().map_or(false, |highlights| {
+            highlights.is_current(buffer) && covers_visible_range(&highlights.range)
+        });
+        let is_pending = fallback
+            .pending
+            .as_ref()
+            .map_or(false, |(range, edit_count, _)| {
+                *edit_count == buffer.edit_count() && covers_visible_range(range)
+            });
+        if !is_highlighted && !is_pending {
+            let range = highlighted_region(&visible_range, buffer);
+            let buffer = buffer.clone();
+            let edit_count = buffer.edit_count();
+            let task = cx.spawn({
+                let range = range.clone();
+                |this, mut cx| async move {
+                    let highlights = cx
+                        .background_executor()
+                        .spawn(async move { PrecomputedSyntaxHighlights::compute(&buffer, range) })
+                        .await;
+                    this.update(&mut cx, |this, cx| {
+                        let fallback = &mut this.syntax_fallback;
+                        fallback.pending = None;
+                        if fallback.deferred {
+                            fallback.highlights = Some(Arc::new(highlights));
+                            cx.notify();
+                        }
+                    })
+                    .ok();
+                }
+            });
+            fallback.pending = Some((range, edit_count, task));
+        }
+        fallback.highlighting()
+    }
+}
+
+/// Returns the region to highlight around the visible range, including as many rows before and
+/// after it as it spans, so that scrolling is highlighted, up to the byte budget on each side.
+fn highlighted_region(visible_range: &Range<usize>, buffer: &MultiBufferSnapshot) -> Range<usize> {
+    let start_row = buffer.offset_to_point(visible_range.start).row;
+    let end_row = buffer.offset_to_point(visible_range.end).row;
+    let visible_rows = end_row - start_row;
+    let start = buffer
+        .point_to_offset(language::Point::new(
+            start_row.saturating_sub(visible_rows),
+            0,
+        ))
+        .max(visible_range.start.saturating_sub(MAX_LIVE_HIGHLIGHT_BYTES));
+    let end = buffer
+        .point_to_offset(
+            language::Point::new(end_row + visible_rows + 1, 0).min(buffer.max_point()),
+        )
+        .min(visible_range.end + MAX_LIVE_HIGHLIGHT_BYTES);
+    buffer.clip_offset(start, Bias::Left)..buffer.clip_offset(end, Bias::Right)
+}
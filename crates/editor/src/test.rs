@@ -1,5 +1,6 @@
 pub mod editor_lsp_test_context;
 pub mod editor_test_context;
+pub mod layout_snapshot;
 
 use std::sync::LazyLock;
 
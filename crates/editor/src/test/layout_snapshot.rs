@@ -0,0 +1,196 @@
+//! Golden tests of what editors display.
+//!
+//! [`EditorTestContext::layout_snapshot`] renders the display rows of an editor as text, once
+//! folds, soft wraps, inlays and tabs are applied, with the selections and background highlights
+//! marked in them and the positions of the cursors in pixels.
+//! [`EditorTestContext::assert_layout_golden`] compares it with a golden file of
+//! `test_data/golden/layout`, which is written instead when the tests run with
+//! `UPDATE_GOLDEN=true`.
+//!
+//! Layouts are only deterministic when the text is laid out without fonts, in an app created by
+//! [`gpui::TestAppContext::new_app_with_fixed_width_text`], where every character is half an em
+//! wide.
+
+use super::editor_test_context::EditorTestContext;
+use crate::{DisplayPoint, DisplayRow};
+use collections::BTreeMap;
+use std::{fmt::Write as _, path::PathBuf};
+
+/// A marker inserted into a display row, in the order of the markers at the same column.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Marker {
+    HighlightEnd,
+    SelectionEnd { head: bool },
+    Cursor,
+    SelectionStart { head: bool },
+    HighlightStart,
+}
+
+impl Marker {
+    fn text(self) -> &'static str {
+        match self {
+            Marker::HighlightEnd => "›",
+            Marker::SelectionEnd { head: true } => "ˇ»",
+            Marker::SelectionEnd { head: false } => "»",
+            Marker::Cursor => "ˇ",
+            Marker::SelectionStart { head: true } => "«ˇ",
+            Marker::SelectionStart { head: false } => "«",
+            Marker::HighlightStart => "‹",
+        }
+    }
+}
+
+impl EditorTestContext {
+    /// Renders the display rows of the editor, each after the number of its buffer line when it
+    /// starts one. Selections are marked like in [`Self::set_state`], and background highlights
+    /// are between `‹` and `›`. The rows are followed by the display point of each cursor and
+    /// its horizontal position in the laid out row.
+    pub fn layout_snapshot(&mut self) -> String {
+        self.update_editor(|editor, window, cx| {
+            let highlights = editor.all_text_background_highlights(window, cx);
+            let text_layout_details = editor.text_layout_details(window);
+            let (snapshot, selections) = editor.selections.all_display(cx);
+
+            let mut markers = BTreeMap::<DisplayPoint, Vec<Marker>>::default();
+            let mut push_marker = |point: DisplayPoint, marker: Marker| {
+                markers.entry(point).or_default().push(marker);
+            };
+            for (range, _) in &highlights {
+                push_marker(range.start, Marker::HighlightStart);
+                push_marker(range.end, Marker::HighlightEnd);
+            }
+            for selection in &selections {
+                if selection.is_empty() {
+                    push_marker(selection.head(), Marker::Cursor);
+                } else {
+                    push_marker(
+                        selection.start,
+                        Marker::SelectionStart {
+                            head: selection.reversed,
+                        },
+                    );
+                    push_marker(
+                        selection.end,
+                        Marker::SelectionEnd {
+                            head: !selection.reversed,
+                        },
+                    );
+                }
+            }
+
+            let mut layout = String::new();
+            let rows = (0..=snapshot.max_point().row().0).map(DisplayRow);
+            for (row, row_info) in rows.zip(snapshot.row_infos(DisplayRow(0))) {
+                let mut line = snapshot.line(row);
+                let row_markers =
+                    markers.range(DisplayPoint::new(row, 0)..=DisplayPoint::new(row, u32::MAX));
+                for (point, point_markers) in row_markers.rev() {
+                    let mut point_markers = point_markers.clone();
+                    point_markers.sort();
+                    let text = point_markers
+                        .into_iter()
+                        .map(Marker::text)
+                        .collect::<String>();
+                    line.insert_str(point.column() as usize, &text);
+                }
+                let line_number = row_info
+                    .buffer_row
+                    .map(|buffer_row| (buffer_row + 1).to_string())
+                    .unwrap_or_default();
+                let row = format!("{line_number:>3} │ {line}");
+                writeln!(layout, "{}", row.trim_end()).unwrap();
+            }
+
+            layout.push('\n');
+            for selection in &selections {
+                let head = selection.head();
+                let x = snapshot.x_for_display_point(head, &text_layout_details);
+                writeln!(
+                    layout,
+                    "cursor {}:{} at x = {}px",
+                    head.row().0,
+                    head.column(),
+                    x.0
+                )
+                .unwrap();
+            }
+            layout
+        })
+    }
+
+    /// Asserts that the layout of the editor is the one of the golden file with the given name.
+    #[track_caller]
+    pub fn assert_layout_golden(&mut self, golden_name: &str) {
+        let layout = self.layout_snapshot();
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("test_data");
+        path.push("golden");
+        path.push("layout");
+        path.push(format!("{}.txt", golden_name));
+
+        let update = std::env::var("UPDATE_GOLDEN")
+            .map(|val| val.to_ascii_lowercase() == "true")
+            .unwrap_or(false);
+
+        if update {
+            std::fs::create_dir_all(path.parent().unwrap())
+                .expect("could not create golden layout directory");
+            std::fs::write(&path, layout).expect("could not write out golden layout");
+        } else {
+            let golden = std::fs::read_to_string(&path)
+                .unwrap_or_else(|_| {
+                    panic!(
+                        "could not read golden layout at {:?}. Did you run the test with UPDATE_GOLDEN=true before?",
+                        path
+                    )
+                })
+                .replace("\r\n", "\n");
+            pretty_assertions::assert_eq!(layout, golden, "unexpected layout of {:?}", path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+    use gpui::TestAppContext;
+    use language::language_settings::SoftWrap;
+
+    enum TestHighlight {}
+
+    #[gpui::test]
+    async fn test_soft_wrap_layout(cx: &mut TestAppContext) {
+        let mut cx = cx.new_app_with_fixed_width_text();
+        init_test(&mut cx, |settings| {
+            settings.defaults.soft_wrap = Some(SoftWrap::PreferredLineLength);
+            settings.defaults.preferred_line_length = Some(20);
+        });
+        let mut cx = EditorTestContext::new(&mut cx).await;
+
+        cx.set_state(
+            "The quick brown fox jumps over the «lazyˇ» dog\nfn main() {\n    ˇprintln!();\n}\n",
+        );
+        cx.run_until_parked();
+        cx.assert_layout_golden("soft_wrap");
+    }
+
+    #[gpui::test]
+    async fn test_fold_and_highlight_layout(cx: &mut TestAppContext) {
+        let mut cx = cx.new_app_with_fixed_width_text();
+        init_test(&mut cx, |_| {});
+        let mut cx = EditorTestContext::new(&mut cx).await;
+
+        cx.set_state("fn ˇmain() {\n    let x = 1;\n}\n");
+        cx.update_editor(|editor, window, cx| {
+            editor.fold_ranges(vec![11..27], false, window, cx);
+            let snapshot = editor.buffer().read(cx).snapshot(cx);
+            let name = snapshot.anchor_after(3)..snapshot.anchor_before(7);
+            editor.highlight_background::<TestHighlight>(
+                &[name],
+                |colors| colors.editor_document_highlight_read_background,
+                cx,
+            );
+        });
+        cx.assert_layout_golden("fold_and_highlight");
+    }
+}
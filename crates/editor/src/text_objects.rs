@@ -0,0 +1,528 @@
+//! Selection of the text inside or around the objects enclosing the selections: pairs of brackets
+//! or quotes, HTML-like tags, and the items of comma separated lists.
+//!
+//! Objects are resolved through the syntax tree first. Pairs are the brackets of the language's
+//! brackets query, and tags are the syntax nodes that start and end with tags of the same name.
+//! Where the syntax tree has no such pair, like in plain text or in strings, pairs are found by
+//! scanning the text around the selection instead, matching nested brackets, and quotes on the
+//! same line. The items of a list are separated by the commas between its brackets that are
+//! outside of nested brackets, strings and comments. An object is only selected when it's larger
+//! than the selection, so that repeating a command selects the enclosing objects one by one. The
+//! vim layer resolves its `vim::Enclosing` text objects the same way.
+
+use crate::{
+    actions::{SelectAround, SelectInside},
+    scroll::Autoscroll,
+    Editor,
+};
+use gpui::{Context, Window};
+use language::{BufferSnapshot, Point, SelectionGoal};
+use multi_buffer::MultiBufferSnapshot;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::{iter, ops::Range};
+
+/// An object enclosing a selection.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SurroundingObject {
+    /// The innermost pair of parentheses, square brackets or curly brackets.
+    #[default]
+    AnyBrackets,
+    Parentheses,
+    SquareBrackets,
+    CurlyBrackets,
+    /// The innermost pair of single, double or back quotes.
+    AnyQuotes,
+    SingleQuotes,
+    DoubleQuotes,
+    BackQuotes,
+    /// An HTML-like element, whose tags are inside or around the object.
+    Tag,
+    /// An item of the list between the innermost brackets, followed or preceded by its comma
+    /// around the object.
+    Argument,
+}
+
+impl SurroundingObject {
+    fn pairs(self) -> &'static [(char, char)] {
+        match self {
+            Self::AnyBrackets | Self::Argument => &[('(', ')'), ('[', ']'), ('{', '}')],
+            Self::Parentheses => &[('(', ')')],
+            Self::SquareBrackets => &[('[', ']')],
+            Self::CurlyBrackets => &[('{', '}')],
+            Self::AnyQuotes => &[('\'', '\''), ('"', '"'), ('`', '`')],
+            Self::SingleQuotes => &[('\'', '\'')],
+            Self::DoubleQuotes => &[('"', '"')],
+            Self::BackQuotes => &[('`', '`')],
+            Self::Tag => &[],
+        }
+    }
+}
+
+impl Editor {
+    pub fn select_inside(
+        &mut self,
+        action: &SelectInside,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.select_surrounding_object(action.object, false, window, cx);
+    }
+
+    pub fn select_around(
+        &mut self,
+        action: &SelectAround,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.select_surrounding_object(action.object, true, window, cx);
+    }
+
+    fn select_surrounding_object(
+        &mut self,
+        object: SurroundingObject,
+        around: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let selections = self
+            .selections
+            .all::<usize>(cx)
+            .into_iter()
+            .map(|mut selection| {
+                if let Some(range) =
+                    surrounding_object_range(&snapshot, selection.range(), object, around)
+                {
+                    selection.start = range.start;
+                    selection.end = range.end;
+                    selection.goal = SelectionGoal::None;
+                }
+                selection
+            })
+            .collect::<Vec<_>>();
+        self.change_selections(Some(Autoscroll::fit()), window, cx, |s| {
+            s.select(selections)
+        });
+    }
+}
+
+/// Returns the range inside or around the innermost object of the given kind that encloses the
+/// range and is larger than it, if any.
+pub fn surrounding_object_range(
+    snapshot: &MultiBufferSnapshot,
+    range: Range<usize>,
+    object: SurroundingObject,
+    around: bool,
+) -> Option<Range<usize>> {
+    let mut excerpt = snapshot.excerpt_containing(range.clone())?;
+    let buffer = excerpt.buffer();
+    let excerpt_range = excerpt.buffer_range();
+    let range = excerpt.map_range_to_buffer(range);
+    let encloses = |candidate: &Range<usize>| {
+        candidate.start <= range.start
+            && range.end <= candidate.end
+            && (*candidate != range || range.is_empty())
+    };
+
+    let result = match object {
+        SurroundingObject::Tag => tag_range(buffer, range.clone(), around, &encloses)?,
+        SurroundingObject::Argument => {
+            let argument = |open: &Range<usize>, close: &Range<usize>| {
+                argument_range(buffer, open.end..close.start, range.clone(), around)
+            };
+            let (open, close) = enclosing_pair(
+                buffer,
+                &excerpt_range,
+                &range,
+                object.pairs(),
+                &|open, close| argument(open, close).map_or(false, |argument| encloses(&argument)),
+            )?;
+            argument(&open, &close)?
+        }
+        _ => {
+            let pair_range = |open: &Range<usize>, close: &Range<usize>| {
+                if around {
+                    open.start..close.end
+                } else {
+                    open.end..close.start
+                }
+            };
+            let (open, close) = enclosing_pair(
+                buffer,
+                &excerpt_range,
+                &range,
+                object.pairs(),
+                &|open, close| encloses(&pair_range(open, close)),
+            )?;
+            pair_range(&open, &close)
+        }
+    };
+
+    excerpt
+        .contains_buffer_range(result.clone())
+        .then(|| excerpt.map_range_from_buffer(result))
+}
+
+/// Returns the innermost pair of the given delimiters that encloses the range and is accepted,
+/// from the syntax tree or else from the text.
+fn enclosing_pair(
+    buffer: &BufferSnapshot,
+    excerpt_range: &Range<usize>,
+    range: &Range<usize>,
+    pairs: &[(char, char)],
+    accept: &dyn Fn(&Range<usize>, &Range<usize>) -> bool,
+) -> Option<(Range<usize>, Range<usize>)> {
+    let is_pair = |open: &Range<usize>, close: &Range<usize>| {
+        let open_char = buffer.chars_at(open.start).next();
+        let close_char = buffer.reversed_chars_at(close.end).next();
+        pairs.iter().any(|&(open_marker, close_marker)| {
+            open_char == Some(open_marker)
+                && close_char == Some(close_marker)
+                && open.len() == open_marker.len_utf8()
+                && close.len() == close_marker.len_utf8()
+        })
+    };
+    let syntax_filter = |open: Range<usize>, close: Range<usize>| {
+        excerpt_range.start <= open.start
+            && close.end <= excerpt_range.end
+            && range.start < close.end
+            && is_pair(&open, &close)
+            && accept(&open, &close)
+    };
+    if let Some(pair) =
+        buffer.innermost_enclosing_bracket_ranges(range.clone(), Some(&syntax_filter))
+    {
+        return Some(pair);
+    }
+
+    pairs
+        .iter()
+        .filter_map(|&(open_marker, close_marker)| {
+            if open_marker == close_marker {
+                scanned_quotes(buffer, excerpt_range, range, open_marker, accept)
+            } else {
+                scanned_brackets(
+                    buffer,
+                    excerpt_range,
+                    range,
+                    open_marker,
+                    close_marker,
+                    accept,
+                )
+            }
+        })
+        .min_by_key(|(open, close)| close.end - open.start)
+}
+
+/// Finds enclosing brackets by matching the nested brackets around the range.
+fn scanned_brackets(
+    buffer: &BufferSnapshot,
+    excerpt_range: &Range<usize>,
+    range: &Range<usize>,
+    open_marker: char,
+    close_marker: char,
+    accept: &dyn Fn(&Range<usize>, &Range<usize>) -> bool,
+) -> Option<(Range<usize>, Range<usize>)> {
+    // A range starting on an open bracket is enclosed in it.
+    let mut offset = range.start;
+    if buffer.chars_at(offset).next() == Some(open_marker) {
+        offset += open_marker.len_utf8();
+    }
+
+    let mut depth = 0;
+    for ch in buffer.reversed_chars_at(offset) {
+        if offset <= excerpt_range.start {
+            break;
+        }
+        offset -= ch.len_utf8();
+        if ch == close_marker {
+            depth += 1;
+        } else if ch == open_marker {
+            if depth > 0 {
+                depth -= 1;
+                continue;
+            }
+            let open = offset..offset + ch.len_utf8();
+            let close = matching_close(buffer, excerpt_range, open.end, open_marker, close_marker)?;
+            if range.end <= close.end && range.start < close.end && accept(&open, &close) {
+                return Some((open, close));
+            }
+        }
+    }
+    None
+}
+
+fn matching_close(
+    buffer: &BufferSnapshot,
+    excerpt_range: &Range<usize>,
+    mut offset: usize,
+    open_marker: char,
+    close_marker: char,
+) -> Option<Range<usize>> {
+    let mut depth = 0;
+    for ch in buffer.chars_at(offset) {
+        if offset >= excerpt_range.end {
+            break;
+        }
+        if ch == open_marker {
+            depth += 1;
+        } else if ch == close_marker {
+            if depth == 0 {
+                return Some(offset..offset + ch.len_utf8());
+            }
+            depth -= 1;
+        }
+        offset += ch.len_utf8();
+    }
+    None
+}
+
+/// Finds enclosing quotes by pairing the unescaped quotes of the line of the range in order.
+fn scanned_quotes(
+    buffer: &BufferSnapshot,
+    excerpt_range: &Range<usize>,
+    range: &Range<usize>,
+    quote: char,
+    accept: &dyn Fn(&Range<usize>, &Range<usize>) -> bool,
+) -> Option<(Range<usize>, Range<usize>)> {
+    let row = buffer.offset_to_point(range.start).row;
+    let line_start = buffer.point_to_offset(Point::new(row, 0));
+    let line_end = line_start + buffer.line_len(row) as usize;
+    if range.end > line_end {
+        return None;
+    }
+
+    let mut quotes = Vec::new();
+    let mut offset = line_start;
+    let mut escaped = false;
+    for ch in buffer.chars_for_range(line_start..line_end) {
+        if ch == quote && !escaped && excerpt_range.contains(&offset) {
+            quotes.push(offset..offset + ch.len_utf8());
+        }
+        escaped = ch == '\\' && !escaped;
+        offset += ch.len_utf8();
+    }
+    quotes
+        .chunks_exact(2)
+        .map(|pair| (pair[0].clone(), pair[1].clone()))
+        .find(|(open, close)| {
+            open.start <= range.start
+                && range.end <= close.end
+                && range.start < close.end
+                && accept(open, close)
+        })
+}
+
+/// Returns the innermost syntax node enclosing the range that starts and ends with tags of the
+/// same name.
+fn tag_range(
+    buffer: &BufferSnapshot,
+    range: Range<usize>,
+    around: bool,
+    encloses: &dyn Fn(&Range<usize>) -> bool,
+) -> Option<Range<usize>> {
+    fn tag_name(chars: impl Iterator<Item = char>, prefix: &str) -> Option<String> {
+        let mut chars = chars.peekable();
+        for expected in prefix.chars() {
+            chars.next_if_eq(&expected)?;
+        }
+        let name = chars
+            .take_while(|c| c.is_alphanumeric() || matches!(c, ':' | '-' | '_' | '.'))
+            .collect::<String>();
+        (!name.is_empty()).then_some(name)
+    }
+
+    let layer = buffer.syntax_layer_at(range.start)?;
+    let mut node = layer
+        .node()
+        .descendant_for_byte_range(range.start, range.end)?;
+    loop {
+        if node.child_count() >= 2 {
+            let open_tag = node.child(0)?;
+            let close_tag = node.child(node.child_count() - 1)?;
+            let open_name = tag_name(buffer.chars_for_range(open_tag.byte_range()), "<");
+            let close_name = tag_name(buffer.chars_for_range(close_tag.byte_range()), "</");
+            if open_name.is_some() && open_name == close_name {
+                let candidate = if around {
+                    open_tag.start_byte()..close_tag.end_byte()
+                } else {
+                    open_tag.end_byte()..close_tag.start_byte()
+                };
+                if encloses(&candidate) {
+                    return Some(candidate);
+                }
+            }
+        }
+        node = node.parent()?;
+    }
+}
+
+/// Returns the item of the list in the given range that contains the range, without the
+/// whitespace around it.
+fn argument_range(
+    buffer: &BufferSnapshot,
+    list: Range<usize>,
+    range: Range<usize>,
+    around: bool,
+) -> Option<Range<usize>> {
+    let separators = list_separators(buffer, list.clone());
+    let item_starts = iter::once(list.start).chain(separators.iter().map(|offset| offset + 1));
+    let item_ends = separators.iter().copied().chain(iter::once(list.end));
+    let items = item_starts.zip(item_ends).collect::<Vec<_>>();
+    let ix = items
+        .iter()
+        .position(|&(start, end)| start <= range.start && range.end <= end)?;
+    let item = |ix: usize| {
+        let (start, end) = items[ix];
+        trim_whitespace(buffer, start..end)
+    };
+
+    let argument = item(ix);
+    if argument.is_empty() {
+        return None;
+    }
+    Some(if !around {
+        argument
+    } else if ix + 1 < items.len() {
+        argument.start..item(ix + 1).start
+    } else if ix > 0 {
+        item(ix - 1).end..argument.end
+    } else {
+        argument
+    })
+}
+
+/// Returns the offsets of the commas in the range that are outside of nested brackets, strings
+/// and comments.
+fn list_separators(buffer: &BufferSnapshot, range: Range<usize>) -> Vec<usize> {
+    let in_string_or_comment = |offset: usize| {
+        buffer
+            .language_scope_at(offset)
+            .and_then(|scope| {
+                scope
+                    .override_name()
+                    .map(|name| name == "string" || name == "comment")
+            })
+            .unwrap_or(false)
+    };
+
+    let mut separators = Vec::new();
+    let mut depth = 0_usize;
+    let mut offset = range.start;
+    for ch in buffer.chars_for_range(range) {
+        if matches!(ch, '(' | '[' | '{' | ')' | ']' | '}' | ',') && !in_string_or_comment(offset) {
+            match ch {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth = depth.saturating_sub(1),
+                _ if depth == 0 => separators.push(offset),
+                _ => {}
+            }
+        }
+        offset += ch.len_utf8();
+    }
+    separators
+}
+
+fn trim_whitespace(buffer: &BufferSnapshot, range: Range<usize>) -> Range<usize> {
+    let whitespace_len = |c: char| c.is_whitespace().then(|| c.len_utf8());
+    let start = range.start
+        + buffer
+            .chars_for_range(range.clone())
+            .map_while(whitespace_len)
+            .sum::<usize>();
+    let end = range.end
+        - buffer
+            .reversed_chars_for_range(start..range.end)
+            .map_while(whitespace_len)
+            .sum::<usize>();
+    start..end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+    use gpui::TestAppContext;
+    use indoc::indoc;
+
+    #[gpui::test]
+    async fn test_select_surrounding_objects(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        let mut assert_selection = |object, around, before: &str, after: &str| {
+            cx.set_state(before);
+            cx.update_editor(|editor, window, cx| {
+                if around {
+                    editor.select_around(&SelectAround { object }, window, cx)
+                } else {
+                    editor.select_inside(&SelectInside { object }, window, cx)
+                }
+            });
+            cx.assert_editor_state(after);
+        };
+
+        assert_selection(
+            SurroundingObject::Parentheses,
+            false,
+            "call(one, (twˇo), three)",
+            "call(one, («twoˇ»), three)",
+        );
+        assert_selection(
+            SurroundingObject::Parentheses,
+            true,
+            "call(one, (twˇo), three)",
+            "call(one, «(two)ˇ», three)",
+        );
+        // Selecting an object again selects the enclosing one.
+        assert_selection(
+            SurroundingObject::AnyBrackets,
+            false,
+            "call[one, «(two)ˇ», three]",
+            "call[«one, (two), threeˇ»]",
+        );
+        assert_selection(
+            SurroundingObject::CurlyBrackets,
+            false,
+            indoc! {"
+                fn main() {
+                    let x = ˇ1;
+                }"},
+            indoc! {"
+                fn main() {«
+                    let x = 1;
+                ˇ»}"},
+        );
+        assert_selection(
+            SurroundingObject::DoubleQuotes,
+            false,
+            r#"say("a, \"bˇ\"", "c")"#,
+            r#"say("«a, \"b\"ˇ»", "c")"#,
+        );
+        assert_selection(
+            SurroundingObject::AnyQuotes,
+            true,
+            "say 'quoˇted' text",
+            "say «'quoted'ˇ» text",
+        );
+        assert_selection(
+            SurroundingObject::Argument,
+            false,
+            "call(one, twˇo, three)",
+            "call(one, «twoˇ», three)",
+        );
+        assert_selection(
+            SurroundingObject::Argument,
+            true,
+            "call(one, twˇo, three)",
+            "call(one, «two, ˇ»three)",
+        );
+        assert_selection(
+            SurroundingObject::Argument,
+            true,
+            "call(one, (a, b), thrˇee)",
+            "call(one, (a, b)«, threeˇ»)",
+        );
+    }
+}
@@ -0,0 +1,123 @@
+//! Paints typed characters before the line containing them is shaped again.
+//!
+//! When text is typed at a single cursor, the next frame reuses the layout the line had in the
+//! previous frame, splicing the shaped characters into it and shifting the glyphs that follow
+//! them, whether or not the display map already contains the typed text. Another frame is then
+//! requested, which lays the line out again from the display map. The delay between keystrokes
+//! and the frames painting them is recorded in [`TypingLatency`], which logs it periodically.
+
+use crate::{display_map::DisplayRow, Editor, EditorMode};
+use gpui::{App, ShapedLine};
+use multi_buffer::Anchor;
+use std::time::{Duration, Instant};
+
+/// The number of keystrokes over which the typing latency is logged.
+const TYPING_LATENCY_SAMPLES: usize = 128;
+
+#[derive(Default)]
+pub(crate) struct TypeAhead {
+    /// The text typed since the last frame.
+    pub(crate) pending: Option<PendingTypeAhead>,
+    /// The layout of the line containing the newest cursor, in the last frame.
+    pub(crate) last_line: Option<TypeAheadLine>,
+    pub(crate) latency: TypingLatency,
+}
+
+pub(crate) struct PendingTypeAhead {
+    /// The position before the typed text.
+    pub(crate) position: Anchor,
+    pub(crate) text: String,
+    /// When the text started being typed, until it's first painted.
+    pub(crate) typed_at: Option<Instant>,
+}
+
+pub(crate) struct TypeAheadLine {
+    pub(crate) row: DisplayRow,
+    pub(crate) line: ShapedLine,
+}
+
+/// The delays between keystrokes and the frames painting the text they typed, logged every
+/// [`TYPING_LATENCY_SAMPLES`] keystrokes.
+#[derive(Default)]
+pub(crate) struct TypingLatency {
+    samples: Vec<Duration>,
+}
+
+impl TypingLatency {
+    pub(crate) fn record(&mut self, latency: Duration) {
+        self.samples.push(latency);
+        if self.samples.len() == TYPING_LATENCY_SAMPLES {
+            if let Some((mean, max)) = self.mean().zip(self.max()) {
+                log::debug!(
+                    "typing latency over {TYPING_LATENCY_SAMPLES} keystrokes: mean {mean:?}, max {max:?}"
+                );
+            }
+            self.samples.clear();
+        }
+    }
+
+    /// The mean of the latencies recorded since they were last logged.
+    fn mean(&self) -> Option<Duration> {
+        let count = u32::try_from(self.samples.len()).ok().filter(|c| *c > 0)?;
+        Some(self.samples.iter().sum::<Duration>() / count)
+    }
+
+    /// The largest of the latencies recorded since they were last logged.
+    fn max(&self) -> Option<Duration> {
+        self.samples.iter().max().copied()
+    }
+}
+
+impl Editor {
+    /// Records text about to be typed at the cursor, so that the next frame can paint it
+    /// without shaping its line again.
+    pub(crate) fn record_type_ahead(&mut self, text: &str, cx: &App) {
+        let typed_at = Instant::now();
+        let selection = self.selections.newest_anchor();
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let eligible = self.mode == EditorMode::Full
+            && self.selections.count() == 1
+            && selection.start.cmp(&selection.end, &snapshot).is_eq()
+            && !text.is_empty()
+            && !text.chars().any(char::is_whitespace);
+        if !eligible {
+            self.type_ahead.pending = None;
+            return;
+        }
+
+        // Keystrokes typed before the next frame are painted together.
+        if let Some(pending) = self.type_ahead.pending.as_mut() {
+            pending.text.push_str(text);
+            pending.typed_at.get_or_insert(typed_at);
+            return;
+        }
+        let position = snapshot.anchor_before(selection.head());
+        self.type_ahead.pending = Some(PendingTypeAhead {
+            position,
+            text: text.to_string(),
+            typed_at: Some(typed_at),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typing_latency() {
+        let mut latency = TypingLatency::default();
+        assert_eq!(latency.mean(), None);
+        for ms in 1..TYPING_LATENCY_SAMPLES as u64 {
+            latency.record(Duration::from_millis(ms));
+        }
+        assert_eq!(latency.max(), Some(Duration::from_millis(127)));
+        assert_eq!(latency.mean(), Some(Duration::from_millis(64)));
+
+        // The latencies are cleared once logged.
+        latency.record(Duration::from_millis(128));
+        assert_eq!(latency.mean(), None);
+        latency.record(Duration::from_millis(1));
+        assert_eq!(latency.max(), Some(Duration::from_millis(1)));
+    }
+}
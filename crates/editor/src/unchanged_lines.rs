@@ -0,0 +1,121 @@
+//! Labels of the unchanged lines collapsed between the excerpts of diff views.
+//!
+//! Diff views only put the changed hunks and a few lines of context around them in excerpts, so
+//! that opening a large diff doesn't lay out the unchanged parts of its buffers. The boundaries
+//! between excerpts show how many lines of the buffer they skip, as `N unchanged lines`. Clicking
+//! the label expands the excerpts on both of its sides by the `expand_excerpt_lines` setting, so
+//! that the skipped lines are only materialized as they're asked for.
+
+use crate::{Editor, EditorSettings};
+use gpui::Context;
+use multi_buffer::{ExcerptId, ExcerptInfo, ExpandExcerptDirection};
+use settings::Settings as _;
+use text::ToPoint as _;
+
+impl Editor {
+    /// Shows the number of unchanged lines skipped between excerpts.
+    pub fn set_show_unchanged_line_counts(&mut self) {
+        self.show_unchanged_line_counts = true;
+    }
+
+    /// Expands the excerpts around unchanged lines towards each other, without making them
+    /// overlap.
+    pub(crate) fn expand_unchanged_lines(
+        &mut self,
+        prev_excerpt: ExcerptId,
+        next_excerpt: ExcerptId,
+        unchanged_rows: u32,
+        cx: &mut Context<Self>,
+    ) {
+        let lines = EditorSettings::get_global(cx).expand_excerpt_lines;
+        let down = lines.min(unchanged_rows.div_ceil(2));
+        let up = lines.min(unchanged_rows - down);
+        self.buffer.update(cx, |buffer, cx| {
+            buffer.expand_excerpts([prev_excerpt], down, ExpandExcerptDirection::Down, cx);
+            buffer.expand_excerpts([next_excerpt], up, ExpandExcerptDirection::Up, cx);
+        });
+    }
+}
+
+/// The rows of the buffer between two excerpts of it.
+pub(crate) fn unchanged_rows_between(
+    prev_excerpt: &ExcerptInfo,
+    next_excerpt: &ExcerptInfo,
+) -> u32 {
+    let prev_end = prev_excerpt
+        .range
+        .context
+        .end
+        .to_point(&prev_excerpt.buffer);
+    let next_start = next_excerpt
+        .range
+        .context
+        .start
+        .to_point(&next_excerpt.buffer);
+    next_start.row.saturating_sub(prev_end.row + 1)
+}
+
+/// The rows of the buffer above its first excerpt.
+pub(crate) fn unchanged_rows_above(first_excerpt: &ExcerptInfo) -> u32 {
+    first_excerpt
+        .range
+        .context
+        .start
+        .to_point(&first_excerpt.buffer)
+        .row
+}
+
+/// The rows of the buffer below its last excerpt.
+pub(crate) fn unchanged_rows_below(last_excerpt: &ExcerptInfo) -> u32 {
+    let end = last_excerpt
+        .range
+        .context
+        .end
+        .to_point(&last_excerpt.buffer);
+    last_excerpt.buffer.max_point().row - end.row
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{editor_tests::init_test, test::build_editor};
+    use gpui::{AppContext as _, TestAppContext};
+    use language::{Buffer, Capability, Point};
+    use multi_buffer::{ExcerptRange, MultiBuffer};
+    use util::test::sample_text;
+
+    #[gpui::test]
+    fn test_expand_unchanged_lines(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let buffer = cx.new(|cx| Buffer::local(sample_text(10, 1, 'a'), cx));
+        let multibuffer = cx.new(|cx| {
+            let mut multibuffer = MultiBuffer::new(Capability::ReadWrite);
+            multibuffer.push_excerpts(
+                buffer.clone(),
+                [
+                    ExcerptRange {
+                        context: Point::new(0, 0)..Point::new(0, 1),
+                        primary: None,
+                    },
+                    ExcerptRange {
+                        context: Point::new(9, 0)..Point::new(9, 1),
+                        primary: None,
+                    },
+                ],
+                cx,
+            );
+            multibuffer
+        });
+        let excerpt_ids = multibuffer.read_with(cx, |multibuffer, _| multibuffer.excerpt_ids());
+        let (editor, cx) = cx.add_window_view(|window, cx| build_editor(multibuffer, window, cx));
+
+        editor.update(cx, |editor, cx| {
+            // The excerpts grow towards each other by the `expand_excerpt_lines` setting.
+            editor.expand_unchanged_lines(excerpt_ids[0], excerpt_ids[1], 8, cx);
+            assert_eq!(editor.text(cx), "a\nb\nc\nd\ng\nh\ni\nj");
+
+            // They stop growing once they meet.
+            editor.expand_unchanged_lines(excerpt_ids[0], excerpt_ids[1], 2, cx);
+            assert_eq!(editor.text(cx), "a\nb\nc\nd\ne\nf\ng\nh\ni\nj");
+        });
+    }
+}
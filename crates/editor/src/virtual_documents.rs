@@ -0,0 +1,154 @@
+//! Editors of the documents opened by URI rather than by path, like `git://HEAD~1/src/main.rs`.
+//!
+//! The documents are loaded by the providers of [`project::VirtualDocumentProviders`]. Their tabs
+//! are titled with the name of their path followed by their origin, and they take part in the
+//! navigation history like the editors of files. They aren't serialized with the workspace, since
+//! restoring them would mean loading their URIs again.
+
+use crate::{actions::OpenVirtualDocument, Editor};
+use anyhow::{Context as _, Result};
+use gpui::{AppContext as _, Context, Entity, Task, Window};
+use multi_buffer::MultiBuffer;
+use url::Url;
+use workspace::{notifications::DetachAndPromptErr as _, Workspace};
+
+impl Editor {
+    pub(crate) fn open_virtual_document_for_action(
+        workspace: &mut Workspace,
+        action: &OpenVirtualDocument,
+        window: &mut Window,
+        cx: &mut Context<Workspace>,
+    ) {
+        let uri = Url::parse(&action.uri).with_context(|| format!("parsing {:?}", action.uri));
+        let open = match uri {
+            Ok(uri) => Self::open_virtual_document(workspace, uri, window, cx),
+            Err(error) => Task::ready(Err(error)),
+        };
+        open.detach_and_prompt_err("Failed to open document", window, cx, |error, _, _| {
+            Some(format!("{error:#}"))
+        });
+    }
+
+    /// Opens the document of a URI in the active pane, or activates the editor already showing it.
+    pub fn open_virtual_document(
+        workspace: &mut Workspace,
+        uri: Url,
+        window: &mut Window,
+        cx: &mut Context<Workspace>,
+    ) -> Task<Result<Entity<Editor>>> {
+        let project = workspace.project().clone();
+        let open = project.update(cx, |project, cx| project.open_virtual_document(uri, cx));
+
+        cx.spawn_in(window, |workspace, mut cx| async move {
+            let document = open.await?;
+            workspace.update_in(&mut cx, |workspace, window, cx| {
+                let existing_editor = workspace.items_of_type::<Editor>(cx).find(|editor| {
+                    editor.read(cx).buffer().read(cx).as_singleton().as_ref()
+                        == Some(&document.buffer)
+                });
+                if let Some(editor) = existing_editor {
+                    workspace.activate_item(&editor, true, true, window, cx);
+                    return editor;
+                }
+
+                let multibuffer = cx.new(|cx| {
+                    MultiBuffer::singleton(document.buffer, cx)
+                        .with_title(document.title.to_string())
+                });
+                let editor = cx.new(|cx| {
+                    Editor::for_multibuffer(multibuffer, Some(project), false, window, cx)
+                });
+                workspace.add_item_to_active_pane(Box::new(editor.clone()), None, true, window, cx);
+                editor
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{actions::OpenVirtualDocument, editor_tests::init_test, Editor};
+    use gpui::{TestAppContext, VisualTestContext};
+    use project::{FakeFs, Project};
+    use serde_json::json;
+    use std::path::Path;
+    use url::Url;
+    use workspace::Workspace;
+
+    #[gpui::test]
+    async fn test_open_virtual_document(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        cx.update(project::virtual_documents::init);
+
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".git": {},
+                "src": {
+                    "main.rs": "fn main() {}\n",
+                }
+            }),
+        )
+        .await;
+        fs.set_head_for_repo(
+            Path::new("/dir/.git"),
+            &[("src/main.rs".into(), "fn old_main() {}\n".into())],
+        );
+        fs.set_revision_for_repo(
+            Path::new("/dir/.git"),
+            "HEAD^",
+            &[("src/main.rs".into(), "fn older_main() {}\n".into())],
+        );
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let workspace =
+            cx.add_window(|window, cx| Workspace::test_new(project.clone(), window, cx));
+        let cx = &mut VisualTestContext::from_window(*workspace, cx);
+        cx.run_until_parked();
+
+        let uri = Url::parse("git://HEAD/src/main.rs").unwrap();
+        let editor = workspace
+            .update(cx, |workspace, window, cx| {
+                Editor::open_virtual_document(workspace, uri.clone(), window, cx)
+            })
+            .unwrap()
+            .await
+            .unwrap();
+        editor.update(cx, |editor, cx| {
+            assert_eq!(editor.text(cx), "fn old_main() {}\n");
+            assert_eq!(editor.title(cx), "main.rs (HEAD)");
+            assert!(editor.read_only(cx));
+            let buffer = editor.buffer().read(cx).as_singleton().unwrap();
+            assert!(project.read(cx).is_virtual_document(&buffer));
+        });
+
+        // Opening the URI again activates the same editor.
+        let reopened = workspace
+            .update(cx, |workspace, window, cx| {
+                Editor::open_virtual_document(workspace, uri, window, cx)
+            })
+            .unwrap()
+            .await
+            .unwrap();
+        assert_eq!(reopened, editor);
+        workspace
+            .update(cx, |workspace, _, cx| {
+                assert_eq!(workspace.items_of_type::<Editor>(cx).count(), 1);
+            })
+            .unwrap();
+
+        // Documents can be opened by the URIs given to an action.
+        cx.dispatch_action(OpenVirtualDocument {
+            uri: "git:/src/main.rs?revision=HEAD^".to_string(),
+        });
+        cx.run_until_parked();
+        workspace
+            .update(cx, |workspace, _, cx| {
+                assert_eq!(workspace.items_of_type::<Editor>(cx).count(), 2);
+                let editor = workspace.active_item_as::<Editor>(cx).unwrap();
+                assert_eq!(editor.read(cx).text(cx), "fn older_main() {}\n");
+                assert_eq!(editor.read(cx).title(cx), "main.rs (HEAD^)");
+            })
+            .unwrap();
+    }
+}
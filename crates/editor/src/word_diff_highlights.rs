@@ -0,0 +1,253 @@
+//! Highlights the words that changed within the modified lines of expanded diff hunks, both in
+//! the deleted text shown above the hunk and in the buffer text that replaced it.
+//!
+//! The changed words are found with [`buffer_diff::word_diff`], in the background, for the
+//! hunks in the rows around the visible ones, when they scroll into view or when the diff hunks
+//! are toggled or the buffers or their diffs change. The changed words of a hunk are kept until
+//! the hunk or its text changes, so that only the hunks that changed are diffed again. Hunks
+//! larger than [`MAX_WORD_DIFF_HUNK_LEN`] are only highlighted as a whole, like the other hunks.
+
+use crate::Editor;
+use buffer_diff::{word_diff, DiffHunkStatus};
+use gpui::{Context, Task};
+use language::{Point, ToOffset as _};
+use multi_buffer::{Anchor, ExcerptId, MultiBufferDiffHunk, MultiBufferSnapshot, ToPoint as _};
+use std::ops::Range;
+use text::{Bias, BufferId};
+
+/// The longest text, on either side of a hunk, whose changed words are highlighted.
+const MAX_WORD_DIFF_HUNK_LEN: usize = 8 * 1024;
+
+/// The key of the highlights of the changed words of the deleted text.
+enum DeletedWordHighlights {}
+
+/// The key of the highlights of the changed words of the buffer text.
+enum InsertedWordHighlights {}
+
+#[derive(Default)]
+pub(crate) struct WordDiffHighlights {
+    /// The changed words of the hunks around the visible rows.
+    hunks: Vec<HunkWords>,
+    task: Option<Task<()>>,
+}
+
+/// The changed words of a hunk, along with the state of the texts they were found in.
+struct HunkWords {
+    excerpt_id: ExcerptId,
+    buffer_range: Range<text::Anchor>,
+    diff_base_byte_range: Range<usize>,
+    buffer_version: clock::Global,
+    base_text: (BufferId, clock::Global),
+    deleted_ranges: Vec<Range<Anchor>>,
+    inserted_ranges: Vec<Range<Anchor>>,
+}
+
+impl HunkWords {
+    /// Whether the words were found in the hunk, and its texts didn't change since.
+    fn is_valid_for(&self, hunk: &MultiBufferDiffHunk, snapshot: &MultiBufferSnapshot) -> bool {
+        let (Some(buffer), Some(base_text)) = (
+            snapshot.buffer_for_excerpt(hunk.excerpt_id),
+            snapshot
+                .diff_for_buffer_id(hunk.buffer_id)
+                .and_then(|diff| diff.base_text()),
+        ) else {
+            return false;
+        };
+        self.excerpt_id == hunk.excerpt_id
+            && self.buffer_range == hunk.buffer_range
+            && self.diff_base_byte_range == hunk.diff_base_byte_range
+            && self.base_text == (base_text.remote_id(), base_text.version().clone())
+            && !buffer.has_edits_since_in_range(&self.buffer_version, hunk.buffer_range.clone())
+    }
+}
+
+impl Editor {
+    pub(crate) fn refresh_word_diff_highlights(&mut self, cx: &mut Context<Self>) {
+        let multi_buffer = self.buffer.read(cx);
+        let snapshot = multi_buffer.snapshot(cx);
+        let visible_rows = self.visible_line_count().unwrap_or(0.).ceil() as u32;
+        let visible_start = self.scroll_manager.anchor().anchor.to_point(&snapshot);
+        let start = Point::new(visible_start.row.saturating_sub(visible_rows), 0);
+        let end = snapshot.clip_point(
+            Point::new(visible_start.row + 2 * visible_rows + 1, 0),
+            Bias::Left,
+        );
+        let hunks = snapshot
+            .diff_hunks_in_range(start..end)
+            .filter(|hunk| matches!(hunk.status(), DiffHunkStatus::Modified(_)))
+            .filter(|hunk| {
+                let start = snapshot.anchor_in_excerpt(hunk.excerpt_id, hunk.buffer_range.start);
+                let end = snapshot.anchor_in_excerpt(hunk.excerpt_id, hunk.buffer_range.end);
+                start.zip(end).map_or(false, |(start, end)| {
+                    multi_buffer.has_expanded_diff_hunks_in_ranges(&[start..end], cx)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Keep the words of the hunks that didn't change, and only diff the other ones.
+        let highlights = &mut self.word_diff_highlights;
+        let mut current_hunks = Vec::with_capacity(hunks.len());
+        let mut stale_hunks = Vec::new();
+        for hunk in hunks {
+            match highlights
+                .hunks
+                .iter()
+                .position(|words| words.is_valid_for(&hunk, &snapshot))
+            {
+                Some(ix) => current_hunks.push(highlights.hunks.swap_remove(ix)),
+                None => stale_hunks.push(hunk),
+            }
+        }
+        // The words of the hunks that changed or scrolled out of view are dropped.
+        let changed = !highlights.hunks.is_empty();
+        highlights.hunks = current_hunks;
+        if stale_hunks.is_empty() {
+            if changed {
+                highlights.task = None;
+                self.update_word_diff_highlights(cx);
+            }
+            return;
+        }
+
+        let new_hunks = cx.background_executor().spawn(async move {
+            stale_hunks
+                .into_iter()
+                .filter_map(|hunk| hunk_words(hunk, &snapshot))
+                .collect::<Vec<_>>()
+        });
+        highlights.task = Some(cx.spawn(|this, mut cx| async move {
+            let new_hunks = new_hunks.await;
+            this.update(&mut cx, |this, cx| {
+                this.word_diff_highlights.hunks.extend(new_hunks);
+                this.update_word_diff_highlights(cx);
+            })
+            .ok();
+        }));
+    }
+
+    fn update_word_diff_highlights(&mut self, cx: &mut Context<Self>) {
+        let hunks = &self.word_diff_highlights.hunks;
+        let deleted_ranges = hunks
+            .iter()
+            .flat_map(|words| words.deleted_ranges.iter().cloned())
+            .collect::<Vec<_>>();
+        let inserted_ranges = hunks
+            .iter()
+            .flat_map(|words| words.inserted_ranges.iter().cloned())
+            .collect::<Vec<_>>();
+        self.highlight_background::<DeletedWordHighlights>(
+            &deleted_ranges,
+            |colors| colors.version_control_deleted.opacity(0.3),
+            cx,
+        );
+        self.highlight_background::<InsertedWordHighlights>(
+            &inserted_ranges,
+            |colors| colors.version_control_added.opacity(0.3),
+            cx,
+        );
+    }
+}
+
+/// Finds the changed words of the hunk, unless it's too large.
+fn hunk_words(hunk: MultiBufferDiffHunk, snapshot: &MultiBufferSnapshot) -> Option<HunkWords> {
+    let base_text = snapshot
+        .diff_for_buffer_id(hunk.buffer_id)
+        .and_then(|diff| diff.base_text())?;
+    let buffer = snapshot.buffer_for_excerpt(hunk.excerpt_id)?;
+    let base_range = hunk.diff_base_byte_range.clone();
+    let buffer_range =
+        hunk.buffer_range.start.to_offset(buffer)..hunk.buffer_range.end.to_offset(buffer);
+    let mut words = HunkWords {
+        excerpt_id: hunk.excerpt_id,
+        buffer_range: hunk.buffer_range.clone(),
+        diff_base_byte_range: base_range.clone(),
+        buffer_version: buffer.version().clone(),
+        base_text: (base_text.remote_id(), base_text.version().clone()),
+        deleted_ranges: Vec::new(),
+        inserted_ranges: Vec::new(),
+    };
+    if base_range.len() > MAX_WORD_DIFF_HUNK_LEN || buffer_range.len() > MAX_WORD_DIFF_HUNK_LEN {
+        return Some(words);
+    }
+
+    let old_text = base_text
+        .text_for_range(base_range.clone())
+        .collect::<String>();
+    let new_text = buffer
+        .text_for_range(buffer_range.clone())
+        .collect::<String>();
+    let (old_ranges, new_ranges) = word_diff(&old_text, &new_text);
+
+    let hunk_start = snapshot.anchor_in_excerpt(hunk.excerpt_id, hunk.buffer_range.start)?;
+    words.deleted_ranges = old_ranges
+        .into_iter()
+        .map(|range| {
+            let start = base_text.anchor_after(base_range.start + range.start);
+            let end = base_text.anchor_before(base_range.start + range.end);
+            Anchor {
+                diff_base_anchor: Some(start),
+                ..hunk_start
+            }..Anchor {
+                diff_base_anchor: Some(end),
+                ..hunk_start
+            }
+        })
+        .collect();
+    words.inserted_ranges = new_ranges
+        .into_iter()
+        .filter_map(|range| {
+            let start = buffer.anchor_after(buffer_range.start + range.start);
+            let end = buffer.anchor_before(buffer_range.start + range.end);
+            Some(
+                snapshot.anchor_in_excerpt(hunk.excerpt_id, start)?
+                    ..snapshot.anchor_in_excerpt(hunk.excerpt_id, end)?,
+            )
+        })
+        .collect();
+    Some(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        actions::ExpandAllHunkDiffs, editor_tests::init_test,
+        test::editor_test_context::EditorTestContext,
+    };
+    use gpui::TestAppContext;
+    use indoc::indoc;
+
+    #[gpui::test]
+    async fn test_word_diff_highlights(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state(indoc! {"
+            let y = 1;ˇ
+            let z = 2;
+        "});
+        cx.set_diff_base("let x = 1;\nlet z = 2;\n");
+        cx.assert_editor_background_highlights::<InsertedWordHighlights>("");
+
+        cx.update_editor(|editor, window, cx| {
+            editor.expand_all_diff_hunks(&ExpandAllHunkDiffs, window, cx)
+        });
+        cx.run_until_parked();
+        cx.assert_editor_background_highlights::<DeletedWordHighlights>(indoc! {"
+            let «x» = 1;
+            let y = 1;
+            let z = 2;
+        "});
+        cx.assert_editor_background_highlights::<InsertedWordHighlights>(indoc! {"
+            let x = 1;
+            let «y» = 1;
+            let z = 2;
+        "});
+
+        cx.update_editor(|editor, _, cx| {
+            editor.clear_expanded_diff_hunks(cx);
+        });
+        cx.run_until_parked();
+        cx.assert_editor_background_highlights::<DeletedWordHighlights>("");
+        cx.assert_editor_background_highlights::<InsertedWordHighlights>("");
+    }
+}
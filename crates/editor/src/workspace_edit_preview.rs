@@ -0,0 +1,145 @@
+//! Previews of the edits of code actions, renames and formatting, before they are applied.
+//!
+//! With `preview_workspace_edits` enabled, the edits a language server made are undone and
+//! proposed instead, in a read-only diff multibuffer, where they can be applied or rejected file
+//! by file. Only text edits are previewed: files a code action created, renamed or deleted were
+//! already changed on disk by the time its edits are shown. Edits are only previewed in local
+//! projects that aren't shared, so that undoing them and applying them again stays local.
+//!
+//! Renames don't open the files they edit that aren't open yet: their edits are listed as file
+//! patches above the diff, and made on disk when all the changes are applied, unless a file is
+//! opened from the list, which proposes its edits in its buffer instead.
+
+use crate::{
+    proposed_changes_editor::{ProposedChangeLocation, ProposedChangesEditor},
+    Editor, EditorSettings,
+};
+use anyhow::Result;
+use gpui::{App, AppContext as _, AsyncWindowContext, WeakEntity};
+use language::{Point, ToPoint as _};
+use project::{file_patch::ProjectPatch, Project, ProjectTransaction};
+use settings::Settings as _;
+use std::ops::Range;
+use workspace::Workspace;
+
+/// How many rows around each edit are shown in the preview.
+const PREVIEW_CONTEXT_ROWS: u32 = 3;
+
+impl Editor {
+    /// Whether the edits made to the project are previewed before they're applied. They're
+    /// only previewed in local projects that aren't shared, since undoing them and applying
+    /// them again from the preview would be sent to collaborators.
+    pub(crate) fn previews_workspace_edits(project: &Project, cx: &App) -> bool {
+        EditorSettings::get_global(cx).preview_workspace_edits
+            && project.is_local()
+            && !project.is_shared()
+    }
+
+    /// Undoes the edits of the transaction, and proposes them in a preview.
+    pub(crate) async fn preview_project_transaction(
+        workspace: WeakEntity<Workspace>,
+        transaction: ProjectTransaction,
+        title: String,
+        cx: AsyncWindowContext,
+    ) -> Result<()> {
+        let project_patch = ProjectPatch {
+            transaction,
+            file_patches: Vec::new(),
+        };
+        Self::preview_project_patch(workspace, project_patch, title, cx).await
+    }
+
+    /// Undoes the edits of the transaction, and proposes them in a preview along with the
+    /// patches to files with no open buffer, whose buffers are only opened from the preview.
+    pub(crate) async fn preview_project_patch(
+        workspace: WeakEntity<Workspace>,
+        project_patch: ProjectPatch,
+        title: String,
+        mut cx: AsyncWindowContext,
+    ) -> Result<()> {
+        let ProjectPatch {
+            transaction,
+            mut file_patches,
+        } = project_patch;
+        file_patches.sort_unstable_by(|a, b| a.project_path.path.cmp(&b.project_path.path));
+        let mut entries = transaction.0.into_iter().collect::<Vec<_>>();
+        cx.update(|_, cx| {
+            entries.sort_unstable_by_key(|(buffer, _)| {
+                buffer.read(cx).file().map(|file| file.path().clone())
+            })
+        })?;
+
+        let mut changes = Vec::new();
+        for (buffer, transaction) in entries {
+            let diff = buffer.update(&mut cx, |buffer, cx| {
+                let edited_text = buffer.text();
+                buffer.undo_transaction(transaction.id, cx);
+                buffer.forget_transaction(transaction.id);
+                buffer.diff(edited_text, cx)
+            })?;
+            let diff = diff.await;
+            if diff.edits.is_empty() {
+                continue;
+            }
+
+            let ranges = buffer.read_with(&cx, |buffer, _| {
+                excerpt_ranges(
+                    diff.edits.iter().map(|(range, _)| {
+                        range.start.to_point(buffer).row..range.end.to_point(buffer).row
+                    }),
+                    buffer.max_point(),
+                )
+            })?;
+            changes.push((buffer, diff, ranges));
+        }
+        if changes.is_empty() && file_patches.is_empty() {
+            return Ok(());
+        }
+
+        workspace.update_in(&mut cx, |workspace, window, cx| {
+            let project = workspace.project().clone();
+            let locations = changes
+                .iter()
+                .map(|(buffer, _, ranges)| ProposedChangeLocation {
+                    buffer: buffer.clone(),
+                    ranges: ranges.clone(),
+                })
+                .collect();
+            let preview = cx.new(|cx| {
+                let mut preview =
+                    ProposedChangesEditor::new(title, locations, Some(project), window, cx);
+                for (buffer, diff, _) in changes {
+                    if let Some(branch_buffer) = preview.branch_buffer_for_base(&buffer) {
+                        branch_buffer.update(cx, |branch_buffer, cx| {
+                            branch_buffer.apply_diff(diff, cx);
+                        });
+                    }
+                }
+                preview.set_file_patches(file_patches, cx);
+                preview.set_read_only(true, cx);
+                preview
+            });
+            workspace.add_item_to_active_pane(Box::new(preview), None, true, window, cx);
+        })?;
+
+        Ok(())
+    }
+}
+
+/// Returns the ranges of the excerpts showing the rows with the given ranges, and the rows
+/// around them.
+pub(crate) fn excerpt_ranges(
+    rows: impl IntoIterator<Item = Range<u32>>,
+    max_point: Point,
+) -> Vec<Range<Point>> {
+    let mut ranges: Vec<Range<Point>> = Vec::new();
+    for rows in rows {
+        let start = Point::new(rows.start.saturating_sub(PREVIEW_CONTEXT_ROWS), 0);
+        let end = Point::new(rows.end + PREVIEW_CONTEXT_ROWS + 1, 0).min(max_point);
+        match ranges.last_mut() {
+            Some(last) if last.end >= start => last.end = last.end.max(end),
+            _ => ranges.push(start..end),
+        }
+    }
+    ranges
+}
@@ -0,0 +1,102 @@
+//! Keeps the wrap width in step with the width of the text area, as the gutter grows and shrinks.
+//!
+//! The gutter widens when the line numbers gain a digit, or when indicators like the ones of
+//! runnables or diagnostics appear, which narrows the text area. The element reports the width
+//! of the text area on every layout. A narrower text area is applied to the wrap map right away,
+//! so that no line overflows it. A wider one is applied right away when it's wider by a few
+//! columns, and otherwise once it stayed wider for a moment, so that a gutter flipping between
+//! two widths, like when editing around line 1000, doesn't rewrap the buffers on every flip.
+
+use crate::Editor;
+use gpui::{Context, Pixels, Task};
+use std::time::Duration;
+
+/// How many columns wider the text area must get to be applied right away.
+const WRAP_WIDTH_HYSTERESIS_COLUMNS: f32 = 2.;
+
+/// How long a slightly wider text area must stay so to be applied.
+const WRAP_WIDTH_SETTLE_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Default)]
+pub(crate) struct WrapWidthFeedback {
+    /// The width of the text area last applied to the wrap map.
+    applied: Option<Pixels>,
+    /// Whether the text area stayed wider than the applied width for long enough.
+    settled: bool,
+    settle_task: Option<Task<()>>,
+}
+
+impl Editor {
+    /// The width to wrap the text area at, given its width in this layout.
+    pub(crate) fn wrap_width_for_text_area(
+        &mut self,
+        width: Pixels,
+        em_advance: Pixels,
+        cx: &mut Context<Self>,
+    ) -> Pixels {
+        let feedback = &mut self.wrap_width_feedback;
+        let applies = feedback.applied.map_or(true, |applied| {
+            width <= applied
+                || width - applied >= em_advance * WRAP_WIDTH_HYSTERESIS_COLUMNS
+                || feedback.settled
+        });
+        if applies {
+            feedback.applied = Some(width);
+            feedback.settled = false;
+            feedback.settle_task = None;
+            return width;
+        }
+
+        if feedback.settle_task.is_none() {
+            feedback.settle_task = Some(cx.spawn(|this, mut cx| async move {
+                cx.background_executor()
+                    .timer(WRAP_WIDTH_SETTLE_DELAY)
+                    .await;
+                this.update(&mut cx, |this, cx| {
+                    this.wrap_width_feedback.settled = true;
+                    cx.notify();
+                })
+                .ok();
+            }));
+        }
+        feedback.applied.unwrap_or(width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::build_editor};
+    use gpui::{px, TestAppContext};
+    use multi_buffer::MultiBuffer;
+
+    #[gpui::test]
+    fn test_wrap_width_hysteresis(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let editor = cx.add_window(|window, cx| {
+            let buffer = MultiBuffer::build_simple("one\ntwo", cx);
+            build_editor(buffer, window, cx)
+        });
+        let em_advance = px(10.);
+        let wrap_width = |width, cx: &mut TestAppContext| {
+            editor
+                .update(cx, |editor, _, cx| {
+                    editor.wrap_width_for_text_area(px(width), em_advance, cx)
+                })
+                .unwrap()
+        };
+
+        assert_eq!(wrap_width(500., cx), px(500.));
+        // Narrower text areas apply right away.
+        assert_eq!(wrap_width(490., cx), px(490.));
+        // Slightly wider ones apply once they stayed wider.
+        assert_eq!(wrap_width(500., cx), px(490.));
+        assert_eq!(wrap_width(490., cx), px(490.));
+        assert_eq!(wrap_width(500., cx), px(490.));
+        cx.executor().advance_clock(WRAP_WIDTH_SETTLE_DELAY);
+        cx.run_until_parked();
+        assert_eq!(wrap_width(500., cx), px(500.));
+        // Much wider ones apply right away.
+        assert_eq!(wrap_width(520., cx), px(520.));
+    }
+}
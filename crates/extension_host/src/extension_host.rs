@@ -1,5 +1,6 @@
 pub mod extension_settings;
 pub mod headless_host;
+mod startup_cache;
 pub mod wasm_host;
 
 #[cfg(test)]
@@ -43,6 +44,7 @@ use remote::SshRemoteClient;
 use semantic_version::SemanticVersion;
 use serde::{Deserialize, Serialize};
 use settings::Settings;
+use startup_cache::StartupCache;
 use std::ops::RangeInclusive;
 use std::str::FromStr;
 use std::{
@@ -175,6 +177,50 @@ pub struct ExtensionIndexLanguageEntry {
     pub grammar: Option<Arc<str>>,
 }
 
+/// The version of the on-disk extension index. Bump it when the way the index is
+/// built changes, so that indices written by previous versions are rebuilt.
+const EXTENSION_INDEX_VERSION: u32 = 1;
+
+/// The version of the on-disk [`ExtensionResourceCache`].
+const EXTENSION_RESOURCE_CACHE_VERSION: u32 = 1;
+
+/// What the index learnt from the language configs and theme files of the installed
+/// extensions, keyed by the path of each file. Rebuilding the index reuses it for the
+/// files whose content didn't change, instead of parsing them again.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct ExtensionResourceCache {
+    languages: BTreeMap<PathBuf, CachedResource<CachedLanguageConfig>>,
+    themes: BTreeMap<PathBuf, CachedResource<Vec<String>>>,
+    icon_themes: BTreeMap<PathBuf, CachedResource<Vec<String>>>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct CachedResource<T> {
+    content_hash: String,
+    value: T,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct CachedLanguageConfig {
+    name: LanguageName,
+    matcher: LanguageMatcher,
+    hidden: bool,
+    grammar: Option<Arc<str>>,
+}
+
+impl ExtensionResourceCache {
+    fn get<'a, T>(
+        resources: &'a BTreeMap<PathBuf, CachedResource<T>>,
+        path: &Path,
+        content_hash: &str,
+    ) -> Option<&'a T> {
+        resources
+            .get(path)
+            .filter(|resource| resource.content_hash == content_hash)
+            .map(|resource| &resource.value)
+    }
+}
+
 actions!(zed, [ReloadExtensions]);
 
 pub fn init(
@@ -268,22 +314,26 @@ impl ExtensionStore {
         // The extensions store maintains an index file, which contains a complete
         // list of the installed extensions and the resources that they provide.
         // This index is loaded synchronously on startup.
-        let (index_content, index_metadata, extensions_metadata) =
-            cx.background_executor().block(async {
-                futures::join!(
-                    this.fs.load(&this.index_path),
-                    this.fs.metadata(&this.index_path),
-                    this.fs.metadata(&this.installed_dir),
-                )
-            });
+        let index_cache = StartupCache::<ExtensionIndex>::new(
+            this.fs.clone(),
+            this.index_path.clone(),
+            EXTENSION_INDEX_VERSION,
+        );
+        let (index, index_metadata, extensions_metadata) = cx.background_executor().block(async {
+            futures::join!(
+                index_cache.load(),
+                this.fs.metadata(&this.index_path),
+                this.fs.metadata(&this.installed_dir),
+            )
+        });
 
         // Normally, there is no need to rebuild the index. But if the index file
-        // is invalid or is out-of-date according to the filesystem mtimes, then
-        // it must be asynchronously rebuilt.
+        // is invalid, was written by another version, or is out-of-date according
+        // to the filesystem mtimes, then it must be asynchronously rebuilt.
         let mut extension_index = ExtensionIndex::default();
         let mut extension_index_needs_rebuild = true;
-        if let Ok(index_content) = index_content {
-            if let Some(index) = serde_json::from_str(&index_content).log_err() {
+        match index {
+            Ok(index) => {
                 extension_index = index;
                 if let (Ok(Some(index_metadata)), Ok(Some(extensions_metadata))) =
                     (index_metadata, extensions_metadata)
@@ -296,6 +346,7 @@ impl ExtensionStore {
                     }
                 }
             }
+            Err(error) => log::info!("rebuilding the extension index: {error:#}"),
         }
 
         // Immediately load all of the extensions in the initial manifest. If the
@@ -1303,10 +1354,18 @@ impl ExtensionStore {
         let work_dir = self.wasm_host.work_dir.clone();
         let extensions_dir = self.installed_dir.clone();
         let index_path = self.index_path.clone();
+        let resource_cache_path = index_path.with_file_name("index_resources.json");
         let proxy = self.proxy.clone();
         cx.background_executor().spawn(async move {
             let start_time = Instant::now();
             let mut index = ExtensionIndex::default();
+            let resource_cache = StartupCache::<ExtensionResourceCache>::new(
+                fs.clone(),
+                resource_cache_path,
+                EXTENSION_RESOURCE_CACHE_VERSION,
+            );
+            let previous_resources = resource_cache.load().await.unwrap_or_default();
+            let mut resources = ExtensionResourceCache::default();
 
             fs.create_dir(&work_dir).await.log_err();
             fs.create_dir(&extensions_dir).await.log_err();
@@ -1329,6 +1388,8 @@ impl ExtensionStore {
                         fs.clone(),
                         extension_dir,
                         &mut index,
+                        &previous_resources,
+                        &mut resources,
                         proxy.clone(),
                     )
                     .await
@@ -1336,12 +1397,13 @@ impl ExtensionStore {
                 }
             }
 
-            if let Ok(index_json) = serde_json::to_string_pretty(&index) {
-                fs.save(&index_path, &index_json.as_str().into(), Default::default())
-                    .await
-                    .context("failed to save extension index")
-                    .log_err();
-            }
+            resource_cache.store(&resources).await.log_err();
+
+            StartupCache::new(fs.clone(), index_path, EXTENSION_INDEX_VERSION)
+                .store(&index)
+                .await
+                .context("failed to save extension index")
+                .log_err();
 
             log::info!("rebuilt extension index in {:?}", start_time.elapsed());
             index
@@ -1352,6 +1414,8 @@ impl ExtensionStore {
         fs: Arc<dyn Fs>,
         extension_dir: PathBuf,
         index: &mut ExtensionIndex,
+        previous_resources: &ExtensionResourceCache,
+        resources: &mut ExtensionResourceCache,
         proxy: Arc<ExtensionHostProxy>,
     ) -> Result<()> {
         let mut extension_manifest = ExtensionManifest::load(fs.clone(), &extension_dir).await?;
@@ -1377,8 +1441,32 @@ impl ExtensionStore {
                 if !fs_metadata.is_dir {
                     continue;
                 }
-                let config = fs.load(&language_path.join("config.toml")).await?;
-                let config = ::toml::from_str::<LanguageConfig>(&config)?;
+                let config_path = language_path.join("config.toml");
+                let config = fs.load(&config_path).await?;
+                let content_hash = startup_cache::content_hash(&config);
+                let config = match ExtensionResourceCache::get(
+                    &previous_resources.languages,
+                    &config_path,
+                    &content_hash,
+                ) {
+                    Some(config) => config.clone(),
+                    None => {
+                        let config = ::toml::from_str::<LanguageConfig>(&config)?;
+                        CachedLanguageConfig {
+                            name: config.name,
+                            matcher: config.matcher,
+                            hidden: config.hidden,
+                            grammar: config.grammar,
+                        }
+                    }
+                };
+                resources.languages.insert(
+                    config_path,
+                    CachedResource {
+                        content_hash,
+                        value: config.clone(),
+                    },
+                );
 
                 let relative_path = relative_path.to_path_buf();
                 if !extension_manifest.languages.contains(&relative_path) {
@@ -1405,13 +1493,34 @@ impl ExtensionStore {
                     continue;
                 };
 
-                let Some(theme_families) = proxy
-                    .list_theme_names(theme_path.clone(), fs.clone())
-                    .await
-                    .log_err()
-                else {
+                let Some(content) = fs.load(&theme_path).await.log_err() else {
                     continue;
                 };
+                let content_hash = startup_cache::content_hash(&content);
+                let theme_families = match ExtensionResourceCache::get(
+                    &previous_resources.themes,
+                    &theme_path,
+                    &content_hash,
+                ) {
+                    Some(theme_families) => theme_families.clone(),
+                    None => {
+                        let Some(theme_families) = proxy
+                            .list_theme_names(theme_path.clone(), fs.clone())
+                            .await
+                            .log_err()
+                        else {
+                            continue;
+                        };
+                        theme_families
+                    }
+                };
+                resources.themes.insert(
+                    theme_path.clone(),
+                    CachedResource {
+                        content_hash,
+                        value: theme_families.clone(),
+                    },
+                );
 
                 let relative_path = relative_path.to_path_buf();
                 if !extension_manifest.themes.contains(&relative_path) {
@@ -1437,13 +1546,34 @@ impl ExtensionStore {
                     continue;
                 };
 
-                let Some(icon_theme_families) = proxy
-                    .list_icon_theme_names(icon_theme_path.clone(), fs.clone())
-                    .await
-                    .log_err()
-                else {
+                let Some(content) = fs.load(&icon_theme_path).await.log_err() else {
                     continue;
                 };
+                let content_hash = startup_cache::content_hash(&content);
+                let icon_theme_families = match ExtensionResourceCache::get(
+                    &previous_resources.icon_themes,
+                    &icon_theme_path,
+                    &content_hash,
+                ) {
+                    Some(icon_theme_families) => icon_theme_families.clone(),
+                    None => {
+                        let Some(icon_theme_families) = proxy
+                            .list_icon_theme_names(icon_theme_path.clone(), fs.clone())
+                            .await
+                            .log_err()
+                        else {
+                            continue;
+                        };
+                        icon_theme_families
+                    }
+                };
+                resources.icon_themes.insert(
+                    icon_theme_path.clone(),
+                    CachedResource {
+                        content_hash,
+                        value: icon_theme_families.clone(),
+                    },
+                );
 
                 let relative_path = relative_path.to_path_buf();
                 if !extension_manifest.icon_themes.contains(&relative_path) {
@@ -0,0 +1,142 @@
+use anyhow::{anyhow, Context as _, Result};
+use fs::Fs;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::value::RawValue;
+use sha2::{Digest, Sha256};
+use std::{marker::PhantomData, path::PathBuf, sync::Arc};
+
+/// A file caching the result of some expensive startup work, so that it can be
+/// reused by the next launch instead of being recomputed.
+///
+/// The cached value is stored along with a format version and the hash of its
+/// content. A cache file written with another version, or whose content doesn't
+/// match its hash (e.g. because it was only partially written), is ignored.
+pub struct StartupCache<T> {
+    fs: Arc<dyn Fs>,
+    path: PathBuf,
+    version: u32,
+    _value: PhantomData<fn() -> T>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile<'a> {
+    version: u32,
+    content_hash: String,
+    #[serde(borrow)]
+    value: &'a RawValue,
+}
+
+impl<T: Serialize + DeserializeOwned> StartupCache<T> {
+    pub fn new(fs: Arc<dyn Fs>, path: PathBuf, version: u32) -> Self {
+        Self {
+            fs,
+            path,
+            version,
+            _value: PhantomData,
+        }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Loads the cached value, failing if it is missing, was written with
+    /// another version or doesn't match its content hash.
+    pub async fn load(&self) -> Result<T> {
+        let content = self.fs.load(&self.path).await?;
+        self.parse(&content)
+    }
+
+    pub async fn store(&self, value: &T) -> Result<()> {
+        let content = self.serialize(value)?;
+        self.fs
+            .save(&self.path, &content.as_str().into(), Default::default())
+            .await
+            .with_context(|| format!("failed to save startup cache {:?}", self.path))
+    }
+
+    fn parse(&self, content: &str) -> Result<T> {
+        let file = serde_json::from_str::<CacheFile>(content)
+            .with_context(|| format!("invalid startup cache {:?}", self.path))?;
+        if file.version != self.version {
+            return Err(anyhow!(
+                "startup cache {:?} has version {}, expected {}",
+                self.path,
+                file.version,
+                self.version
+            ));
+        }
+        if file.content_hash != content_hash(file.value.get()) {
+            return Err(anyhow!(
+                "startup cache {:?} doesn't match its content hash",
+                self.path
+            ));
+        }
+        Ok(serde_json::from_str(file.value.get())?)
+    }
+
+    fn serialize(&self, value: &T) -> Result<String> {
+        let value = serde_json::to_string_pretty(value)?;
+        let value = RawValue::from_string(value)?;
+        Ok(serde_json::to_string_pretty(&CacheFile {
+            version: self.version,
+            content_hash: content_hash(value.get()),
+            value: &value,
+        })?)
+    }
+}
+
+/// Returns the hash of some content, to check whether it changed since it was cached.
+pub fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fs::FakeFs;
+    use gpui::TestAppContext;
+    use std::collections::BTreeMap;
+
+    #[gpui::test]
+    async fn test_startup_cache(cx: &mut TestAppContext) {
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree("/cache", serde_json::json!({})).await;
+        let cache = StartupCache::<BTreeMap<String, u32>>::new(
+            fs.clone(),
+            PathBuf::from("/cache/index.json"),
+            2,
+        );
+        assert!(cache.load().await.is_err());
+
+        let value = BTreeMap::from_iter([("one".to_string(), 1), ("two".to_string(), 2)]);
+        cache.store(&value).await.unwrap();
+        assert_eq!(cache.load().await.unwrap(), value);
+
+        // Caches written with another version are ignored.
+        let other_version =
+            StartupCache::<BTreeMap<String, u32>>::new(fs.clone(), cache.path().clone(), 1);
+        assert!(other_version.load().await.is_err());
+
+        // Caches whose content changed behind our back are ignored.
+        let content = fs.load(cache.path()).await.unwrap();
+        fs.save(
+            cache.path(),
+            &content.replace("\"two\": 2", "\"two\": 3").as_str().into(),
+            Default::default(),
+        )
+        .await
+        .unwrap();
+        assert!(cache.load().await.is_err());
+
+        // Files in the format of a plain index are ignored.
+        fs.save(
+            cache.path(),
+            &serde_json::to_string(&value).unwrap().as_str().into(),
+            Default::default(),
+        )
+        .await
+        .unwrap();
+        assert!(cache.load().await.is_err());
+    }
+}
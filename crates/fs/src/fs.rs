@@ -28,12 +28,14 @@ use git::repository::{GitRepository, RealGitRepository};
 use gpui::{App, Global, ReadGlobal};
 use rope::Rope;
 use serde::{Deserialize, Serialize};
-use smol::io::AsyncWriteExt;
 use std::{
     io::{self, Write},
     path::{Component, Path, PathBuf},
     pin::Pin,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering::SeqCst},
+        Arc,
+    },
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use tempfile::{NamedTempFile, TempDir};
@@ -130,6 +132,9 @@ pub trait Fs: Send + Sync {
     fn open_repo(&self, abs_dot_git: &Path) -> Option<Arc<dyn GitRepository>>;
     fn is_fake(&self) -> bool;
     async fn is_case_sensitive(&self) -> Result<bool>;
+    /// Whether the path is on a network file system, whose changes may not be reported by
+    /// [`Fs::watch`]. File systems in user space aren't counted, since most of them are local.
+    async fn is_network_mount(&self, path: &Path) -> bool;
 
     #[cfg(any(test, feature = "test-support"))]
     fn as_fake(&self) -> Arc<FakeFs> {
@@ -240,6 +245,7 @@ impl From<MTime> for proto::Timestamp {
 pub struct RealFs {
     git_hosting_provider_registry: Arc<GitHostingProviderRegistry>,
     git_binary_path: Option<PathBuf>,
+    atomic_save: AtomicBool,
 }
 
 pub trait FileHandle: Send + Sync + std::fmt::Debug {
@@ -298,8 +304,15 @@ impl RealFs {
         Self {
             git_hosting_provider_registry,
             git_binary_path,
+            atomic_save: AtomicBool::new(true),
         }
     }
+
+    /// Sets whether [`Fs::save`] writes files beside them and moves them into place, rather
+    /// than writing them in place.
+    pub fn set_atomic_save(&self, atomic_save: bool) {
+        self.atomic_save.store(atomic_save, SeqCst);
+    }
 }
 
 #[async_trait::async_trait]
@@ -552,13 +565,38 @@ impl Fs for RealFs {
         if let Some(path) = path.parent() {
             self.create_dir(path).await?;
         }
-        let file = smol::fs::File::create(path).await?;
-        let mut writer = smol::io::BufWriter::with_capacity(buffer_size, file);
-        for chunk in chunks(text, line_ending) {
-            writer.write_all(chunk.as_bytes()).await?;
-        }
-        writer.flush().await?;
-        Ok(())
+
+        // Symlinks are resolved so that the link itself isn't replaced.
+        let path = smol::fs::canonicalize(path)
+            .await
+            .unwrap_or_else(|_| path.to_path_buf());
+        let metadata = smol::fs::metadata(&path).await.ok();
+        let atomic_save = self.atomic_save.load(SeqCst);
+        let text = text.clone();
+        smol::unblock(move || {
+            // Files with other hard links are written in place, since replacing them would
+            // unlink them from the others.
+            #[cfg(unix)]
+            let is_hard_linked = metadata
+                .as_ref()
+                .map_or(false, |metadata| metadata.nlink() > 1);
+            #[cfg(not(unix))]
+            let is_hard_linked = false;
+            if atomic_save && !is_hard_linked {
+                match save_atomically(&path, &text, line_ending, metadata.as_ref(), buffer_size) {
+                    Ok(()) => return Ok(()),
+                    Err(error) => {
+                        log::warn!(
+                            "failed to save {path:?} atomically, writing it in place: {error:#}"
+                        )
+                    }
+                }
+            }
+            let mut file = std::fs::File::create(&path)?;
+            write_chunks(&mut file, &text, line_ending, buffer_size)?;
+            Ok::<(), anyhow::Error>(())
+        })
+        .await
     }
 
     async fn canonicalize(&self, path: &Path) -> Result<PathBuf> {
@@ -805,6 +843,60 @@ impl Fs for RealFs {
         temp_dir.close()?;
         case_sensitive
     }
+
+    async fn is_network_mount(&self, path: &Path) -> bool {
+        let path = path.to_path_buf();
+        smol::unblock(move || is_network_mount(&path)).await
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn is_network_mount(path: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    // The magic numbers of `statfs(2)`.
+    const NETWORK_FILE_SYSTEMS: &[u32] = &[
+        0x6969,     // NFS
+        0x517b,     // SMB
+        0xff534d42, // CIFS
+        0xfe534d42, // SMB2
+        0x564c,     // NCP
+        0x5346414f, // AFS
+        0x6b414653, // kAFS
+        0x47504653, // GPFS
+    ];
+
+    let Ok(path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let mut stat = unsafe { std::mem::zeroed::<libc::statfs>() };
+    if unsafe { libc::statfs(path.as_ptr(), &mut stat) } != 0 {
+        return false;
+    }
+    NETWORK_FILE_SYSTEMS.contains(&(stat.f_type as u32))
+}
+
+#[cfg(target_os = "macos")]
+fn is_network_mount(path: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let mut stat = unsafe { std::mem::zeroed::<libc::statfs>() };
+    if unsafe { libc::statfs(path.as_ptr(), &mut stat) } != 0 {
+        return false;
+    }
+    let file_system = unsafe { std::ffi::CStr::from_ptr(stat.f_fstypename.as_ptr()) };
+    matches!(
+        file_system.to_bytes(),
+        b"nfs" | b"smbfs" | b"afpfs" | b"webdav" | b"cifs"
+    )
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn is_network_mount(_: &Path) -> bool {
+    false
 }
 
 #[cfg(not(any(target_os = "linux", target_os = "freebsd")))]
@@ -838,6 +930,7 @@ struct FakeFsState {
     metadata_call_count: usize,
     read_dir_call_count: usize,
     moves: std::collections::HashMap<u64, PathBuf>,
+    network_mounts: Vec<PathBuf>,
 }
 
 #[cfg(any(test, feature = "test-support"))]
@@ -1023,6 +1116,7 @@ impl FakeFs {
                 read_dir_call_count: 0,
                 metadata_call_count: 0,
                 moves: Default::default(),
+                network_mounts: Vec::new(),
             }),
         });
 
@@ -1151,6 +1245,11 @@ impl FakeFs {
         entry.file_content(&path).cloned()
     }
 
+    /// Makes [`Fs::is_network_mount`] return true for the path and its descendants.
+    pub fn set_network_mount(&self, path: impl Into<PathBuf>) {
+        self.state.lock().network_mounts.push(path.into());
+    }
+
     pub fn pause_events(&self) {
         self.state.lock().events_paused = true;
     }
@@ -1292,6 +1391,19 @@ impl FakeFs {
         });
     }
 
+    pub fn set_revision_for_repo(
+        &self,
+        dot_git: &Path,
+        revision: &str,
+        contents: &[(RepoPath, String)],
+    ) {
+        self.with_git_state(dot_git, false, |state| {
+            state
+                .revision_contents
+                .insert(revision.to_string(), contents.iter().cloned().collect());
+        });
+    }
+
     pub fn set_blame_for_repo(&self, dot_git: &Path, blames: Vec<(RepoPath, git::blame::Blame)>) {
         self.with_git_state(dot_git, true, |state| {
             state.blames.clear();
@@ -1959,12 +2071,64 @@ impl Fs for FakeFs {
         Ok(true)
     }
 
+    async fn is_network_mount(&self, path: &Path) -> bool {
+        self.state
+            .lock()
+            .network_mounts
+            .iter()
+            .any(|mount| path.starts_with(mount))
+    }
+
     #[cfg(any(test, feature = "test-support"))]
     fn as_fake(&self) -> Arc<FakeFs> {
         self.this.upgrade().unwrap()
     }
 }
 
+/// Writes the text beside the file and moves it into place, so that an interrupted save, e.g.
+/// when quitting, leaves the previous contents rather than a truncated file. The permissions
+/// and the owner of the file are kept.
+fn save_atomically(
+    path: &Path,
+    text: &Rope,
+    line_ending: LineEnding,
+    metadata: Option<&std::fs::Metadata>,
+    buffer_size: usize,
+) -> Result<()> {
+    let mut tmp_file = NamedTempFile::new_in(path.parent().unwrap_or(paths::temp_dir()))?;
+    write_chunks(tmp_file.as_file_mut(), text, line_ending, buffer_size)?;
+    if let Some(metadata) = metadata {
+        tmp_file.as_file().set_permissions(metadata.permissions())?;
+        #[cfg(unix)]
+        {
+            let tmp_metadata = tmp_file.as_file().metadata()?;
+            if (tmp_metadata.uid(), tmp_metadata.gid()) != (metadata.uid(), metadata.gid()) {
+                std::os::unix::fs::fchown(
+                    tmp_file.as_file(),
+                    Some(metadata.uid()),
+                    Some(metadata.gid()),
+                )?;
+            }
+        }
+    }
+    tmp_file.as_file().sync_all()?;
+    tmp_file.persist(path)?;
+    Ok(())
+}
+
+fn write_chunks(
+    file: &mut std::fs::File,
+    text: &Rope,
+    line_ending: LineEnding,
+    buffer_size: usize,
+) -> io::Result<()> {
+    let mut writer = io::BufWriter::with_capacity(buffer_size, file);
+    for chunk in chunks(text, line_ending) {
+        writer.write_all(chunk.as_bytes())?;
+    }
+    writer.flush()
+}
+
 fn chunks(rope: &Rope, line_ending: LineEnding) -> impl Iterator<Item = &str> {
     rope.chunks().flat_map(move |chunk| {
         let mut newline = false;
@@ -2146,4 +2310,57 @@ mod tests {
             "D",
         );
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_real_fs_save_through_symlink() {
+        use std::os::unix::fs::PermissionsExt as _;
+
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("script.sh");
+        let link_path = dir.path().join("link.sh");
+        std::fs::write(&file_path, "old").unwrap();
+        std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::os::unix::fs::symlink(&file_path, &link_path).unwrap();
+
+        let fs = RealFs::new(Default::default(), None);
+        smol::block_on(fs.save(&link_path, &"new\ntext".into(), LineEnding::Unix)).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&file_path).unwrap(), "new\ntext");
+        assert!(std::fs::symlink_metadata(&link_path)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(
+            std::fs::metadata(&file_path).unwrap().permissions().mode() & 0o777,
+            0o755
+        );
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_real_fs_save_in_place() {
+        use std::os::unix::fs::MetadataExt as _;
+
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("file.txt");
+        let link_path = dir.path().join("hard-link.txt");
+        std::fs::write(&file_path, "old").unwrap();
+        std::fs::hard_link(&file_path, &link_path).unwrap();
+        let inode = std::fs::metadata(&file_path).unwrap().ino();
+
+        // Files with other hard links are written in place.
+        let fs = RealFs::new(Default::default(), None);
+        smol::block_on(fs.save(&file_path, &"new".into(), LineEnding::Unix)).unwrap();
+        assert_eq!(std::fs::read_to_string(&link_path).unwrap(), "new");
+        assert_eq!(std::fs::metadata(&file_path).unwrap().ino(), inode);
+
+        // All files are written in place when atomic saves are disabled.
+        std::fs::remove_file(&link_path).unwrap();
+        fs.set_atomic_save(false);
+        smol::block_on(fs.save(&file_path, &"newer".into(), LineEnding::Unix)).unwrap();
+        assert_eq!(std::fs::read_to_string(&file_path).unwrap(), "newer");
+        assert_eq!(std::fs::metadata(&file_path).unwrap().ino(), inode);
+    }
 }
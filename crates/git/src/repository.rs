@@ -92,6 +92,10 @@ pub trait GitRepository: Send + Sync {
     /// Note that for symlink entries, this will return the contents of the symlink, not the target.
     fn load_committed_text(&self, path: &RepoPath) -> Option<String>;
 
+    /// Returns the contents of an entry in the tree of the given revision, like `HEAD~1` or a
+    /// commit SHA, or None if the revision has no entry for the given path.
+    fn load_revision_text(&self, revision: &str, path: &RepoPath) -> Result<Option<String>>;
+
     fn set_index_text(&self, path: &RepoPath, content: Option<String>) -> anyhow::Result<()>;
 
     /// Returns the URL of the remote with the given name.
@@ -266,6 +270,19 @@ impl GitRepository for RealGitRepository {
         Some(content)
     }
 
+    fn load_revision_text(&self, revision: &str, path: &RepoPath) -> Result<Option<String>> {
+        let repo = self.repository.lock();
+        let tree = repo
+            .revparse_single(revision)
+            .with_context(|| format!("resolving revision {revision:?}"))?
+            .peel_to_tree()?;
+        let Ok(entry) = tree.get_path(path) else {
+            return Ok(None);
+        };
+        let content = repo.find_blob(entry.id())?.content().to_owned();
+        Ok(Some(String::from_utf8(content)?))
+    }
+
     fn set_index_text(&self, path: &RepoPath, content: Option<String>) -> anyhow::Result<()> {
         let working_directory = self
             .repository
@@ -542,6 +559,8 @@ pub struct FakeGitRepositoryState {
     pub path: PathBuf,
     pub event_emitter: smol::channel::Sender<PathBuf>,
     pub head_contents: HashMap<RepoPath, String>,
+    /// The contents of the revisions other than `HEAD`, by their names.
+    pub revision_contents: HashMap<String, HashMap<RepoPath, String>>,
     pub index_contents: HashMap<RepoPath, String>,
     pub blames: HashMap<RepoPath, Blame>,
     pub statuses: HashMap<RepoPath, FileStatus>,
@@ -561,6 +580,7 @@ impl FakeGitRepositoryState {
             path,
             event_emitter,
             head_contents: Default::default(),
+            revision_contents: Default::default(),
             index_contents: Default::default(),
             blames: Default::default(),
             statuses: Default::default(),
@@ -583,6 +603,19 @@ impl GitRepository for FakeGitRepository {
         state.head_contents.get(path.as_ref()).cloned()
     }
 
+    fn load_revision_text(&self, revision: &str, path: &RepoPath) -> Result<Option<String>> {
+        let state = self.state.lock();
+        match revision {
+            "HEAD" => Ok(state.head_contents.get(path.as_ref()).cloned()),
+            _ => Ok(state
+                .revision_contents
+                .get(revision)
+                .ok_or_else(|| anyhow!("unknown revision {revision:?}"))?
+                .get(path.as_ref())
+                .cloned()),
+        }
+    }
+
     fn set_index_text(&self, path: &RepoPath, content: Option<String>) -> anyhow::Result<()> {
         let mut state = self.state.lock();
         if let Some(content) = content {
@@ -1,16 +1,37 @@
 mod providers;
+mod settings;
 
 use std::sync::Arc;
 
+use ::settings::{Settings, SettingsStore};
+use anyhow::anyhow;
 use git::repository::GitRepository;
 use git::GitHostingProviderRegistry;
-use gpui::App;
+use gpui::{App, Global};
+use util::ResultExt;
 
 pub use crate::providers::*;
+pub use crate::settings::*;
+
+/// The names of the providers registered from the settings.
+#[derive(Default)]
+struct CustomProviderNames(Vec<String>);
+
+impl Global for CustomProviderNames {}
 
 /// Initializes the Git hosting providers.
-pub fn init(cx: &App) {
-    let provider_registry = GitHostingProviderRegistry::global(cx);
+pub fn init(cx: &mut App) {
+    GitHostingProviderSettings::register(cx);
+
+    register_built_in_providers(&GitHostingProviderRegistry::global(cx));
+
+    register_custom_providers(cx);
+    cx.observe_global::<SettingsStore>(register_custom_providers)
+        .detach();
+}
+
+/// Registers the providers built into Zed.
+fn register_built_in_providers(provider_registry: &GitHostingProviderRegistry) {
     provider_registry.register_hosting_provider(Arc::new(Bitbucket));
     provider_registry.register_hosting_provider(Arc::new(Codeberg));
     provider_registry.register_hosting_provider(Arc::new(Gitee));
@@ -19,6 +40,42 @@ pub fn init(cx: &App) {
     provider_registry.register_hosting_provider(Arc::new(Sourcehut));
 }
 
+/// Registers the providers configured in the settings, replacing the ones
+/// registered from previous settings.
+///
+/// Providers named like an already registered one, e.g. a built-in provider,
+/// are rejected rather than replacing it.
+fn register_custom_providers(cx: &mut App) {
+    let provider_registry = GitHostingProviderRegistry::global(cx);
+    let configs = GitHostingProviderSettings::get_global(cx)
+        .git_hosting_providers
+        .clone();
+    let previous_names = cx.default_global::<CustomProviderNames>();
+    for name in previous_names.0.drain(..) {
+        provider_registry.unregister_hosting_provider(&name);
+    }
+    for config in &configs {
+        let provider = Custom::from_config(config).and_then(|provider| {
+            let name = provider.name();
+            if provider_registry
+                .list_hosting_providers()
+                .iter()
+                .any(|registered| registered.name() == name)
+            {
+                Err(anyhow!(
+                    "a Git hosting provider named {name:?} is already registered"
+                ))
+            } else {
+                Ok(provider)
+            }
+        });
+        if let Some(provider) = provider.log_err() {
+            previous_names.0.push(provider.name());
+            provider_registry.register_hosting_provider(Arc::new(provider));
+        }
+    }
+}
+
 /// Registers additional Git hosting providers.
 ///
 /// These require information from the Git repository to construct, so their
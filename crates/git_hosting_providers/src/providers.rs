@@ -1,5 +1,6 @@
 mod bitbucket;
 mod codeberg;
+mod custom;
 mod gitee;
 mod github;
 mod gitlab;
@@ -7,6 +8,7 @@ mod sourcehut;
 
 pub use bitbucket::*;
 pub use codeberg::*;
+pub use custom::*;
 pub use gitee::*;
 pub use github::*;
 pub use gitlab::*;
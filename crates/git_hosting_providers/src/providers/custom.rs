@@ -0,0 +1,231 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use url::Url;
+
+use git::{
+    BuildCommitPermalinkParams, BuildPermalinkParams, GitHostingProvider, ParsedGitRemote,
+    RemoteUrl,
+};
+
+use crate::settings::{GitHostingProviderConfig, GitHostingProviderStyle};
+
+/// A Git hosting provider configured in the settings, building its permalinks
+/// from URL templates.
+#[derive(Debug)]
+pub struct Custom {
+    name: String,
+    base_url: Url,
+    permalink_template: String,
+    commit_permalink_template: String,
+    line_template: String,
+    line_range_template: String,
+}
+
+impl Custom {
+    pub fn from_config(config: &GitHostingProviderConfig) -> Result<Self> {
+        let base_url = Url::parse(&config.base_url)?;
+        let host = base_url
+            .host_str()
+            .ok_or_else(|| anyhow!("URL {:?} has no host", config.base_url))?;
+        let (permalink_template, commit_permalink_template, line_range_template) =
+            match config.style {
+                GitHostingProviderStyle::Github => (
+                    "{base_url}/{owner}/{repo}/blob/{sha}/{path}",
+                    "{base_url}/{owner}/{repo}/commit/{sha}",
+                    "L{start_line}-L{end_line}",
+                ),
+                GitHostingProviderStyle::Gitlab => (
+                    "{base_url}/{owner}/{repo}/-/blob/{sha}/{path}",
+                    "{base_url}/{owner}/{repo}/-/commit/{sha}",
+                    "L{start_line}-{end_line}",
+                ),
+            };
+
+        Ok(Self {
+            name: config.name.clone().unwrap_or_else(|| host.to_string()),
+            permalink_template: config
+                .permalink_template
+                .clone()
+                .unwrap_or_else(|| permalink_template.to_string()),
+            commit_permalink_template: config
+                .commit_permalink_template
+                .clone()
+                .unwrap_or_else(|| commit_permalink_template.to_string()),
+            line_template: config
+                .line_template
+                .clone()
+                .unwrap_or_else(|| "L{line}".to_string()),
+            line_range_template: config
+                .line_range_template
+                .clone()
+                .unwrap_or_else(|| line_range_template.to_string()),
+            base_url,
+        })
+    }
+
+    fn expand(&self, template: &str, remote: &ParsedGitRemote, sha: &str, path: &str) -> Url {
+        let url = template
+            .replace("{base_url}", self.base_url.as_str().trim_end_matches('/'))
+            .replace("{owner}", &remote.owner)
+            .replace("{repo}", &remote.repo)
+            .replace("{sha}", sha)
+            .replace("{path}", path);
+        Url::parse(&url).unwrap_or_else(|_| self.base_url.clone())
+    }
+}
+
+impl GitHostingProvider for Custom {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn base_url(&self) -> Url {
+        self.base_url.clone()
+    }
+
+    fn supports_avatars(&self) -> bool {
+        false
+    }
+
+    fn format_line_number(&self, line: u32) -> String {
+        self.line_template.replace("{line}", &line.to_string())
+    }
+
+    fn format_line_numbers(&self, start_line: u32, end_line: u32) -> String {
+        self.line_range_template
+            .replace("{start_line}", &start_line.to_string())
+            .replace("{end_line}", &end_line.to_string())
+    }
+
+    fn parse_remote_url(&self, url: &str) -> Option<ParsedGitRemote> {
+        let url = RemoteUrl::from_str(url).ok()?;
+
+        let host = url.host_str()?;
+        if host != self.base_url.host_str()? {
+            return None;
+        }
+
+        let mut path_segments = url.path_segments()?.collect::<Vec<_>>();
+        let repo = path_segments.pop()?.trim_end_matches(".git");
+        let owner = path_segments.join("/");
+
+        Some(ParsedGitRemote {
+            owner: owner.into(),
+            repo: repo.into(),
+        })
+    }
+
+    fn build_commit_permalink(
+        &self,
+        remote: &ParsedGitRemote,
+        params: BuildCommitPermalinkParams,
+    ) -> Url {
+        let BuildCommitPermalinkParams { sha } = params;
+        self.expand(&self.commit_permalink_template, remote, sha, "")
+    }
+
+    fn build_permalink(&self, remote: ParsedGitRemote, params: BuildPermalinkParams) -> Url {
+        let BuildPermalinkParams {
+            sha,
+            path,
+            selection,
+        } = params;
+
+        let mut permalink = self.expand(&self.permalink_template, &remote, sha, path);
+        permalink.set_fragment(
+            selection
+                .map(|selection| self.line_fragment(&selection))
+                .as_deref(),
+        );
+        permalink
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_build_custom_permalink() {
+        let provider = Custom::from_config(&GitHostingProviderConfig {
+            base_url: "https://git.example.com".into(),
+            style: GitHostingProviderStyle::Gitlab,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(provider.name(), "git.example.com");
+
+        let remote = provider
+            .parse_remote_url("git@git.example.com:group/subgroup/zed.git")
+            .unwrap();
+        assert_eq!(
+            remote,
+            ParsedGitRemote {
+                owner: "group/subgroup".into(),
+                repo: "zed".into(),
+            }
+        );
+        assert_eq!(
+            provider.parse_remote_url("git@github.com:zed-industries/zed.git"),
+            None
+        );
+
+        let permalink = provider.build_permalink(
+            remote,
+            BuildPermalinkParams {
+                sha: "e6ebe7974deb6bb6cc0e2595c8ec31f0c71084b7",
+                path: "crates/editor/src/git/permalink.rs",
+                selection: Some(23..47),
+            },
+        );
+        assert_eq!(
+            permalink.to_string(),
+            "https://git.example.com/group/subgroup/zed/-/blob/e6ebe7974deb6bb6cc0e2595c8ec31f0c71084b7/crates/editor/src/git/permalink.rs#L24-48"
+        );
+    }
+
+    #[test]
+    fn test_build_custom_permalink_from_templates() {
+        let provider = Custom::from_config(&GitHostingProviderConfig {
+            base_url: "https://code.example.com/".into(),
+            name: Some("Example".into()),
+            permalink_template: Some("{base_url}/{owner}/{repo}/src/{sha}/{path}".into()),
+            commit_permalink_template: Some("{base_url}/{owner}/{repo}/commits/{sha}".into()),
+            line_template: Some("line-{line}".into()),
+            line_range_template: Some("line-{start_line}:{end_line}".into()),
+            ..Default::default()
+        })
+        .unwrap();
+        let remote = ParsedGitRemote {
+            owner: "zed-industries".into(),
+            repo: "zed".into(),
+        };
+
+        let permalink = provider.build_commit_permalink(
+            &remote,
+            BuildCommitPermalinkParams {
+                sha: "b2efec9824c45fcc90c9a7eb107a50d1772a60aa",
+            },
+        );
+        assert_eq!(
+            permalink.to_string(),
+            "https://code.example.com/zed-industries/zed/commits/b2efec9824c45fcc90c9a7eb107a50d1772a60aa"
+        );
+
+        let permalink = provider.build_permalink(
+            remote,
+            BuildPermalinkParams {
+                sha: "b2efec9824c45fcc90c9a7eb107a50d1772a60aa",
+                path: "crates/zed/src/main.rs",
+                selection: Some(6..6),
+            },
+        );
+        assert_eq!(
+            permalink.to_string(),
+            "https://code.example.com/zed-industries/zed/src/b2efec9824c45fcc90c9a7eb107a50d1772a60aa/crates/zed/src/main.rs#line-7"
+        );
+    }
+}
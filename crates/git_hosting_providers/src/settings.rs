@@ -0,0 +1,69 @@
+use anyhow::Result;
+use gpui::App;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use settings::{Settings, SettingsSources};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct GitHostingProviderSettings {
+    /// Additional Git hosting providers, for self-hosted remotes.
+    ///
+    /// Default: []
+    #[serde(default)]
+    pub git_hosting_providers: Vec<GitHostingProviderConfig>,
+}
+
+/// A self-hosted Git hosting provider, whose permalinks are built from URL templates.
+///
+/// Templates can contain the `{base_url}`, `{owner}`, `{repo}`, `{sha}` and `{path}`
+/// placeholders, and line templates the `{line}`, `{start_line}` and `{end_line}` ones.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize, JsonSchema)]
+pub struct GitHostingProviderConfig {
+    /// The URL of the provider, e.g. `https://git.example.com`. Remotes on its host
+    /// are handled by this provider.
+    pub base_url: String,
+    /// The name of the provider, which must differ from the names of the
+    /// built-in providers and of the other configured ones.
+    ///
+    /// Default: the host of `base_url`
+    #[serde(default)]
+    pub name: Option<String>,
+    /// The provider whose URL patterns are used when no template is given.
+    ///
+    /// Default: github
+    #[serde(default)]
+    pub style: GitHostingProviderStyle,
+    /// The template of permalinks to a file. A fragment built from the line
+    /// templates is appended to it for selections.
+    #[serde(default)]
+    pub permalink_template: Option<String>,
+    /// The template of permalinks to a commit.
+    #[serde(default)]
+    pub commit_permalink_template: Option<String>,
+    /// The template of the fragment pointing to a single line.
+    #[serde(default)]
+    pub line_template: Option<String>,
+    /// The template of the fragment pointing to a range of lines.
+    #[serde(default)]
+    pub line_range_template: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GitHostingProviderStyle {
+    /// URLs like GitHub's, e.g. `{base_url}/{owner}/{repo}/blob/{sha}/{path}#L1-L2`.
+    #[default]
+    Github,
+    /// URLs like GitLab's, e.g. `{base_url}/{owner}/{repo}/-/blob/{sha}/{path}#L1-2`.
+    Gitlab,
+}
+
+impl Settings for GitHostingProviderSettings {
+    const KEY: Option<&'static str> = None;
+
+    type FileContent = Self;
+
+    fn load(sources: SettingsSources<Self::FileContent>, _: &mut App) -> Result<Self> {
+        sources.json_merge()
+    }
+}
@@ -129,6 +129,7 @@ impl ProjectDiff {
             );
             diff_display_editor.set_distinguish_unstaged_diff_hunks();
             diff_display_editor.set_expand_all_diff_hunks(cx);
+            diff_display_editor.set_show_unchanged_line_counts();
             diff_display_editor.register_addon(GitPanelAddon {
                 git_panel: git_panel.clone(),
             });
@@ -17,6 +17,47 @@ pub(crate) struct SelectionStats {
     pub lines: usize,
     pub characters: usize,
     pub selections: usize,
+    /// Width (in characters) and height (in rows) of the selected block, when
+    /// the selections are single-line ranges spanning the same columns on
+    /// consecutive rows, as produced by a rectangular (column) selection.
+    pub rectangle: Option<(u32, u32)>,
+}
+
+impl SelectionStats {
+    fn rectangle_for(
+        selections: &[Selection<Point>],
+        snapshot: &MultiBufferSnapshot,
+    ) -> Option<(u32, u32)> {
+        if selections.len() < 2 {
+            return None;
+        }
+
+        let char_column = |point: Point| {
+            snapshot
+                .text_summary_for_range::<text::TextSummary, _>(Point::new(point.row, 0)..point)
+                .chars as u32
+        };
+
+        let mut columns = None;
+        let mut previous_row = None;
+        for selection in selections {
+            if selection.is_empty() || selection.start.row != selection.end.row {
+                return None;
+            }
+            if previous_row.map_or(false, |row| row + 1 != selection.start.row) {
+                return None;
+            }
+            previous_row = Some(selection.start.row);
+
+            let selection_columns = (char_column(selection.start), char_column(selection.end));
+            if *columns.get_or_insert(selection_columns) != selection_columns {
+                return None;
+            }
+        }
+
+        let (start_column, end_column) = columns?;
+        Some((end_column - start_column, selections.len() as u32))
+    }
 }
 
 pub struct CursorPosition {
@@ -101,7 +142,10 @@ impl CursorPosition {
                                 let mut last_selection = None::<Selection<Point>>;
                                 let snapshot = editor.buffer().read(cx).snapshot(cx);
                                 if snapshot.excerpts().count() > 0 {
-                                    for selection in editor.selections.all::<Point>(cx) {
+                                    let selections = editor.selections.all::<Point>(cx);
+                                    cursor_position.selected_count.rectangle =
+                                        SelectionStats::rectangle_for(&selections, &snapshot);
+                                    for selection in selections {
                                         let selection_summary = snapshot
                                             .text_summary_for_range::<text::TextSummary, _>(
                                                 selection.start..selection.end,
@@ -152,18 +196,25 @@ impl CursorPosition {
             lines,
             characters,
             selections,
+            rectangle,
         } = self.selected_count;
         let format = LineIndicatorFormat::get(None, cx);
         let is_short_format = format == &LineIndicatorFormat::Short;
-        let lines = (lines > 1).then_some((lines, "line"));
+        // A rectangular selection is better described by its dimensions than
+        // by the number of lines it touches.
+        let lines = (lines > 1 && rectangle.is_none()).then_some((lines, "line"));
         let selections = (selections > 1).then_some((selections, "selection"));
         let characters = (characters > 0).then_some((characters, "character"));
-        if (None, None, None) == (characters, selections, lines) {
+        if (None, None, None, None) == (characters, selections, lines, rectangle) {
             // Nothing to display.
             return;
         }
         write!(text, " (").unwrap();
         let mut wrote_once = false;
+        if let Some((width, height)) = rectangle {
+            write!(text, "{width}×{height}").unwrap();
+            wrote_once = true;
+        }
         for (count, name) in [selections, lines, characters].into_iter().flatten() {
             if wrote_once {
                 write!(text, ", ").unwrap();
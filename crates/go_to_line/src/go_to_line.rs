@@ -456,6 +456,7 @@ mod tests {
                     lines: 0,
                     characters: 0,
                     selections: 1,
+                    rectangle: None,
                 },
                 workspace
                     .status_bar()
@@ -477,6 +478,7 @@ mod tests {
                     lines: 1,
                     characters: 3,
                     selections: 1,
+                    rectangle: None,
                 },
                 workspace
                     .status_bar()
@@ -490,6 +492,102 @@ mod tests {
         });
     }
 
+    #[gpui::test]
+    async fn test_rectangular_selection_stats(cx: &mut TestAppContext) {
+        init_test(cx);
+
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                "a.rs": "ēlo你好\nabcdef\nghijkl\n"
+            }),
+        )
+        .await;
+
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) =
+            cx.add_window_view(|window, cx| Workspace::test_new(project.clone(), window, cx));
+        workspace.update_in(cx, |workspace, window, cx| {
+            let cursor_position = cx.new(|_| CursorPosition::new(workspace));
+            workspace.status_bar().update(cx, |status_bar, cx| {
+                status_bar.add_right_item(cursor_position, window, cx);
+            });
+        });
+
+        let worktree_id = workspace.update(cx, |workspace, cx| {
+            workspace.project().update(cx, |project, cx| {
+                project.worktrees(cx).next().unwrap().read(cx).id()
+            })
+        });
+        let _buffer = project
+            .update(cx, |project, cx| project.open_local_buffer("/dir/a.rs", cx))
+            .await
+            .unwrap();
+        let editor = workspace
+            .update_in(cx, |workspace, window, cx| {
+                workspace.open_path((worktree_id, "a.rs"), None, true, window, cx)
+            })
+            .await
+            .unwrap()
+            .downcast::<Editor>()
+            .unwrap();
+
+        let selection_stats = |cx: &mut VisualTestContext| {
+            workspace.update(cx, |workspace, cx| {
+                *workspace
+                    .status_bar()
+                    .read(cx)
+                    .item_of_type::<CursorPosition>()
+                    .expect("missing cursor position item")
+                    .read(cx)
+                    .selection_stats()
+            })
+        };
+
+        // Columns are measured in characters, so the multibyte first line
+        // lines up with the ASCII ones below it.
+        editor.update_in(cx, |editor, window, cx| {
+            editor.change_selections(None, window, cx, |s| {
+                s.select_ranges([
+                    Point::new(0, "ē".len() as u32)..Point::new(0, "ēlo你".len() as u32),
+                    Point::new(1, 1)..Point::new(1, 4),
+                    Point::new(2, 1)..Point::new(2, 4),
+                ])
+            });
+        });
+        cx.executor().advance_clock(Duration::from_millis(200));
+        assert_eq!(
+            selection_stats(cx),
+            SelectionStats {
+                lines: 3,
+                characters: 9,
+                selections: 3,
+                rectangle: Some((3, 3)),
+            }
+        );
+
+        // Ragged selections are not a rectangle.
+        editor.update_in(cx, |editor, window, cx| {
+            editor.change_selections(None, window, cx, |s| {
+                s.select_ranges([
+                    Point::new(1, 1)..Point::new(1, 4),
+                    Point::new(2, 1)..Point::new(2, 5),
+                ])
+            });
+        });
+        cx.executor().advance_clock(Duration::from_millis(200));
+        assert_eq!(
+            selection_stats(cx),
+            SelectionStats {
+                lines: 2,
+                characters: 7,
+                selections: 2,
+                rectangle: None,
+            }
+        );
+    }
+
     #[gpui::test]
     async fn test_unicode_line_numbers(cx: &mut TestAppContext) {
         init_test(cx);
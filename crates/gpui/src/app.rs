@@ -25,24 +25,27 @@ use collections::{FxHashMap, FxHashSet, HashMap, VecDeque};
 pub use context::*;
 pub use entity_map::*;
 use http_client::HttpClient;
+use idle::IdleScheduler;
+pub use idle::DEFAULT_IDLE_INTERVAL;
 #[cfg(any(test, feature = "test-support"))]
 pub use test_context::*;
 use util::ResultExt;
 
 use crate::{
     current_platform, hash, init_app_menus, Action, ActionBuildError, ActionRegistry, Any, AnyView,
-    AnyWindowHandle, AppContext, Asset, AssetSource, BackgroundExecutor, Bounds, ClipboardItem,
-    DispatchPhase, DisplayId, EventEmitter, FocusHandle, FocusMap, ForegroundExecutor, Global,
-    KeyBinding, Keymap, Keystroke, LayoutId, Menu, MenuItem, OwnedMenu, PathPromptOptions, Pixels,
-    Platform, PlatformDisplay, Point, PromptBuilder, PromptHandle, PromptLevel, Render,
-    RenderablePromptHandle, Reservation, ScreenCaptureSource, SharedString, SubscriberSet,
-    Subscription, SvgRenderer, Task, TextSystem, Window, WindowAppearance, WindowHandle, WindowId,
-    WindowInvalidator,
+    AnyWindowHandle, AppContext, Asset, AssetSource, BackgroundExecutor, BindingExplanation,
+    Bounds, ClipboardItem, DispatchPhase, DisplayId, EventEmitter, FocusHandle, FocusMap,
+    ForegroundExecutor, Global, KeyBinding, KeyContext, Keymap, Keystroke, LayoutId, Menu,
+    MenuItem, OwnedMenu, PathPromptOptions, Pixels, Platform, PlatformDisplay, Point,
+    PromptBuilder, PromptHandle, PromptLevel, Render, RenderablePromptHandle, Reservation,
+    ScreenCaptureSource, SharedString, SubscriberSet, Subscription, SvgRenderer, Task, TextSystem,
+    Window, WindowAppearance, WindowHandle, WindowId, WindowInvalidator,
 };
 
 mod async_context;
 mod context;
 mod entity_map;
+mod idle;
 #[cfg(any(test, feature = "test-support"))]
 mod test_context;
 
@@ -266,6 +269,7 @@ pub struct App {
     pub(crate) window_invalidators_by_entity:
         FxHashMap<EntityId, FxHashMap<WindowId, WindowInvalidator>>,
     pub(crate) tracked_entities: FxHashMap<WindowId, FxHashSet<EntityId>>,
+    idle_scheduler: IdleScheduler,
     #[cfg(any(test, feature = "test-support", debug_assertions))]
     pub(crate) name: Option<&'static str>,
 }
@@ -287,6 +291,7 @@ impl App {
         let text_system = Arc::new(TextSystem::new(platform.text_system()));
         let entities = EntityMap::new();
         let keyboard_layout = SharedString::from(platform.keyboard_layout());
+        let idle_scheduler = IdleScheduler::new(executor.now());
 
         let app = Rc::new_cyclic(|this| AppCell {
             app: RefCell::new(App {
@@ -328,6 +333,7 @@ impl App {
                 layout_id_buffer: Default::default(),
                 propagate_event: true,
                 prompt_builder: Some(PromptBuilder::Default),
+                idle_scheduler,
 
                 #[cfg(any(test, feature = "test-support", debug_assertions))]
                 name: None,
@@ -1334,6 +1340,16 @@ impl App {
         RefCell::borrow(&self.keymap).all_bindings_for_input(input)
     }
 
+    /// Lists every binding matching the given input, explaining which one is dispatched in the
+    /// given context stack. See [`Keymap::explain_bindings_for_input`].
+    pub fn explain_bindings_for_input(
+        &self,
+        input: &[Keystroke],
+        context_stack: &[KeyContext],
+    ) -> Vec<BindingExplanation> {
+        RefCell::borrow(&self.keymap).explain_bindings_for_input(input, context_stack)
+    }
+
     /// Get all non-internal actions that have been registered, along with their schemas.
     pub fn action_schemas(
         &self,
@@ -0,0 +1,157 @@
+use crate::App;
+use futures::channel::oneshot;
+use std::{
+    collections::VecDeque,
+    future::Future,
+    time::{Duration, Instant},
+};
+
+/// How long the application must go without input to be considered idle, by default.
+pub const DEFAULT_IDLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The delay between two turns of idle work, which lets the work of a turn finish
+/// before the next one starts, and input be processed in between.
+const IDLE_TURN_DELAY: Duration = Duration::from_millis(10);
+
+/// Schedules low-priority work for when the application is idle. See [`App::when_idle`].
+pub(crate) struct IdleScheduler {
+    interval: Duration,
+    last_input: Instant,
+    waiters: VecDeque<oneshot::Sender<()>>,
+    running: bool,
+}
+
+impl IdleScheduler {
+    pub(crate) fn new(now: Instant) -> Self {
+        Self {
+            interval: DEFAULT_IDLE_INTERVAL,
+            last_input: now,
+            waiters: VecDeque::new(),
+            running: false,
+        }
+    }
+}
+
+impl App {
+    /// Returns a future that resolves once the application has received no input for
+    /// the idle interval.
+    ///
+    /// Deferred work, like indexing or cache warming, should await it before each of its
+    /// small steps: the work then pauses as soon as the user interacts with the application
+    /// again, and resumes once they stop. Waiters are resumed one at a time, in the order
+    /// they started waiting.
+    pub fn when_idle(&mut self) -> impl Future<Output = ()> {
+        let (tx, rx) = oneshot::channel();
+        if self.idle_scheduler.interval.is_zero() {
+            tx.send(()).ok();
+        } else {
+            self.idle_scheduler.waiters.push_back(tx);
+            self.run_idle_scheduler();
+        }
+        async move {
+            rx.await.ok();
+        }
+    }
+
+    /// Sets how long the application must go without input to be considered idle.
+    /// A zero interval runs idle work right away.
+    pub fn set_idle_interval(&mut self, interval: Duration) {
+        self.idle_scheduler.interval = interval;
+        if interval.is_zero() {
+            for waiter in self.idle_scheduler.waiters.drain(..) {
+                waiter.send(()).ok();
+            }
+        }
+    }
+
+    pub fn idle_interval(&self) -> Duration {
+        self.idle_scheduler.interval
+    }
+
+    /// Whether the application has received no input for the idle interval.
+    pub fn is_idle(&self) -> bool {
+        self.background_executor
+            .now()
+            .saturating_duration_since(self.idle_scheduler.last_input)
+            >= self.idle_scheduler.interval
+    }
+
+    pub(crate) fn record_input(&mut self) {
+        self.idle_scheduler.last_input = self.background_executor.now();
+    }
+
+    fn run_idle_scheduler(&mut self) {
+        if self.idle_scheduler.running {
+            return;
+        }
+        self.idle_scheduler.running = true;
+        self.spawn(|cx| async move {
+            loop {
+                let delay = cx.update(|cx| {
+                    let now = cx.background_executor.now();
+                    let scheduler = &mut cx.idle_scheduler;
+                    scheduler.waiters.retain(|waiter| !waiter.is_canceled());
+                    if scheduler.waiters.is_empty() {
+                        scheduler.running = false;
+                        return None;
+                    }
+
+                    let idle_for = now.saturating_duration_since(scheduler.last_input);
+                    if idle_for >= scheduler.interval {
+                        if let Some(waiter) = scheduler.waiters.pop_front() {
+                            waiter.send(()).ok();
+                        }
+                        Some(IDLE_TURN_DELAY)
+                    } else {
+                        Some(scheduler.interval - idle_for)
+                    }
+                });
+                let Ok(Some(delay)) = delay else {
+                    break;
+                };
+                cx.background_executor().timer(delay).await;
+            }
+        })
+        .detach();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::TestAppContext;
+    use std::{cell::Cell, rc::Rc, time::Duration};
+
+    #[crate::test]
+    fn test_when_idle(cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            cx.set_idle_interval(Duration::from_secs(1));
+            cx.record_input();
+        });
+
+        let turns = Rc::new(Cell::new(0));
+        for _ in 0..2 {
+            let idle = cx.update(|cx| cx.when_idle());
+            let turns = turns.clone();
+            cx.foreground_executor()
+                .spawn(async move {
+                    idle.await;
+                    turns.set(turns.get() + 1);
+                })
+                .detach();
+        }
+
+        cx.executor().advance_clock(Duration::from_millis(500));
+        assert_eq!(turns.get(), 0);
+
+        // Input postpones the idle work.
+        cx.update(|cx| cx.record_input());
+        cx.executor().advance_clock(Duration::from_millis(900));
+        assert_eq!(turns.get(), 0);
+
+        // Waiters are resumed one turn at a time.
+        cx.executor().advance_clock(Duration::from_millis(100));
+        assert_eq!(turns.get(), 1);
+        cx.executor().advance_clock(Duration::from_millis(10));
+        assert_eq!(turns.get(), 2);
+    }
+}
@@ -3,9 +3,9 @@ use crate::{
     BackgroundExecutor, BorrowAppContext, Bounds, ClipboardItem, DrawPhase, Drawable, Element,
     Empty, EventEmitter, ForegroundExecutor, Global, InputEvent, Keystroke, Modifiers,
     ModifiersChangedEvent, MouseButton, MouseDownEvent, MouseMoveEvent, MouseUpEvent, Pixels,
-    Platform, Point, Render, Result, Size, Task, TestDispatcher, TestPlatform,
-    TestScreenCaptureSource, TestWindow, TextSystem, VisualContext, Window, WindowBounds,
-    WindowHandle, WindowOptions,
+    Platform, PlatformTextSystem, Point, Render, Result, Size, Task, TestDispatcher, TestPlatform,
+    TestScreenCaptureSource, TestTextSystem, TestWindow, TextSystem, VisualContext, Window,
+    WindowBounds, WindowHandle, WindowOptions,
 };
 use anyhow::{anyhow, bail};
 use futures::{channel::oneshot, Stream, StreamExt};
@@ -114,16 +114,32 @@ impl AppContext for TestAppContext {
 impl TestAppContext {
     /// Creates a new `TestAppContext`. Usually you can rely on `#[gpui::test]` to do this for you.
     pub fn new(dispatcher: TestDispatcher, fn_name: Option<&'static str>) -> Self {
+        Self::build(dispatcher, fn_name, None)
+    }
+
+    fn build(
+        dispatcher: TestDispatcher,
+        fn_name: Option<&'static str>,
+        text_system: Option<Arc<dyn PlatformTextSystem>>,
+    ) -> Self {
         let arc_dispatcher = Arc::new(dispatcher.clone());
         let background_executor = BackgroundExecutor::new(arc_dispatcher.clone());
         let foreground_executor = ForegroundExecutor::new(arc_dispatcher);
-        let platform = TestPlatform::new(background_executor.clone(), foreground_executor.clone());
+        let platform = TestPlatform::with_text_system(
+            background_executor.clone(),
+            foreground_executor.clone(),
+            text_system,
+        );
         let asset_source = Arc::new(());
         let http_client = http_client::FakeHttpClient::with_404_response();
         let text_system = Arc::new(TextSystem::new(platform.text_system()));
 
+        let app = App::new_app(platform.clone(), asset_source, http_client);
+        // Run idle work right away, so that tests don't need to advance the clock for it.
+        app.borrow_mut().set_idle_interval(Duration::ZERO);
+
         Self {
-            app: App::new_app(platform.clone(), asset_source, http_client),
+            app,
             background_executor,
             foreground_executor,
             dispatcher: dispatcher.clone(),
@@ -149,6 +165,17 @@ impl TestAppContext {
         Self::new(self.dispatcher.clone(), self.fn_name)
     }
 
+    /// Returns a new `TestAppContext` re-using the same executors, whose text is laid out
+    /// without fonts: every character has the same advance of half an em, and every font the
+    /// same metrics. Layouts computed in it are the same on every platform.
+    pub fn new_app_with_fixed_width_text(&self) -> TestAppContext {
+        Self::build(
+            self.dispatcher.clone(),
+            self.fn_name,
+            Some(Arc::new(TestTextSystem::new())),
+        )
+    }
+
     /// Called by the test helper to end the test.
     /// public so the macro can call it.
     pub fn quit(&self) {
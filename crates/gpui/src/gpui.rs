@@ -91,6 +91,7 @@ mod shared_uri;
 mod style;
 mod styled;
 mod subscription;
+mod supervisor;
 mod svg_renderer;
 mod taffy;
 #[cfg(any(test, feature = "test-support"))]
@@ -146,6 +147,7 @@ pub use smol::Timer;
 pub use style::*;
 pub use styled::*;
 pub use subscription::*;
+pub use supervisor::*;
 use svg_renderer::*;
 pub use taffy::{AvailableSpace, LayoutId};
 #[cfg(any(test, feature = "test-support"))]
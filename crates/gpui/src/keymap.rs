@@ -14,6 +14,34 @@ use std::any::TypeId;
 #[derive(Copy, Clone, Eq, PartialEq, Default)]
 pub struct KeymapVersion(usize);
 
+/// How a binding that matches some input is resolved, see [`Keymap::explain_bindings_for_input`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BindingResolution {
+    /// The binding has the highest precedence, so its action is dispatched first.
+    Dispatched,
+    /// The binding is enabled, but its action is only dispatched if the actions of the
+    /// bindings with a higher precedence aren't handled.
+    Fallback,
+    /// The binding is a `null` binding, disabling the bindings with a lower precedence.
+    Disables,
+    /// The binding is disabled by a `null` binding with a higher precedence.
+    Disabled,
+    /// The input is a prefix of the binding's keystrokes.
+    Pending,
+    /// The binding's context predicate doesn't match the context stack.
+    ContextMismatch,
+}
+
+/// A binding matching some input, along with how it is resolved.
+#[derive(Clone, Debug)]
+pub struct BindingExplanation {
+    pub binding: KeyBinding,
+    pub resolution: BindingResolution,
+    /// The number of contexts of the stack for which the binding's predicate matches,
+    /// which determines its precedence.
+    pub context_depth: Option<usize>,
+}
+
 /// A collection of key bindings for the user's application.
 #[derive(Default)]
 pub struct Keymap {
@@ -177,6 +205,61 @@ impl Keymap {
         (bindings, is_pending.unwrap_or_default())
     }
 
+    /// Lists every binding matching the given input, explaining which one is dispatched in the
+    /// given context and why the others aren't. The bindings that are enabled in the context are
+    /// listed first, in the precedence order of [`Self::bindings_for_input`].
+    pub fn explain_bindings_for_input(
+        &self,
+        input: &[Keystroke],
+        context_stack: &[KeyContext],
+    ) -> Vec<BindingExplanation> {
+        let mut enabled = Vec::new();
+        let mut other = Vec::new();
+        for binding in self.bindings().rev() {
+            let Some(pending) = binding.match_keystrokes(input) else {
+                continue;
+            };
+            let context_depth = (0..=context_stack.len())
+                .rev()
+                .find(|depth| self.binding_enabled(binding, &context_stack[0..*depth]));
+            match context_depth {
+                Some(depth) if !pending => enabled.push((binding, depth)),
+                _ => other.push(BindingExplanation {
+                    binding: binding.clone(),
+                    resolution: if context_depth.is_some() {
+                        BindingResolution::Pending
+                    } else {
+                        BindingResolution::ContextMismatch
+                    },
+                    context_depth,
+                }),
+            }
+        }
+        enabled.sort_by(|a, b| a.1.cmp(&b.1).reverse());
+
+        let mut explanations = Vec::with_capacity(enabled.len() + other.len());
+        let mut disabled = false;
+        for (binding, depth) in enabled {
+            let resolution = if disabled {
+                BindingResolution::Disabled
+            } else if is_no_action(&*binding.action) {
+                disabled = true;
+                BindingResolution::Disables
+            } else if explanations.is_empty() {
+                BindingResolution::Dispatched
+            } else {
+                BindingResolution::Fallback
+            };
+            explanations.push(BindingExplanation {
+                binding: binding.clone(),
+                resolution,
+                context_depth: Some(depth),
+            });
+        }
+        explanations.extend(other);
+        explanations
+    }
+
     /// Check if the given binding is enabled, given a certain key context.
     fn binding_enabled(&self, binding: &KeyBinding, context: &[KeyContext]) -> bool {
         // If binding has a context predicate, it must match the current context,
@@ -274,6 +357,65 @@ mod tests {
             .is_empty());
     }
 
+    #[test]
+    fn test_explain_bindings_for_input() {
+        let bindings = [
+            KeyBinding::new("ctrl-a", ActionAlpha {}, None),
+            KeyBinding::new("ctrl-a", ActionBeta {}, Some("pane")),
+            KeyBinding::new("ctrl-a", ActionGamma {}, Some("editor")),
+            KeyBinding::new("ctrl-a ctrl-b", ActionDelta {}, Some("editor")),
+            KeyBinding::new("ctrl-a", NoAction {}, Some("workspace")),
+            KeyBinding::new("ctrl-a", ActionDelta {}, Some("terminal"))
+                .with_meta(KeyBindingMetaIndex(1)),
+        ];
+
+        let mut keymap = Keymap::default();
+        keymap.add_bindings(bindings.clone());
+
+        let context_stack = [
+            KeyContext::parse("workspace").unwrap(),
+            KeyContext::parse("pane").unwrap(),
+            KeyContext::parse("editor").unwrap(),
+        ];
+        let explanations = keymap
+            .explain_bindings_for_input(&[Keystroke::parse("ctrl-a").unwrap()], &context_stack);
+        let resolutions = explanations
+            .iter()
+            .map(|explanation| {
+                (
+                    explanation.binding.action().name(),
+                    explanation.resolution,
+                    explanation.context_depth,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            resolutions,
+            [
+                (
+                    ActionGamma {}.name(),
+                    BindingResolution::Dispatched,
+                    Some(3)
+                ),
+                (ActionAlpha {}.name(), BindingResolution::Fallback, Some(3)),
+                (ActionBeta {}.name(), BindingResolution::Fallback, Some(2)),
+                (NoAction {}.name(), BindingResolution::Disables, Some(1)),
+                (
+                    ActionDelta {}.name(),
+                    BindingResolution::ContextMismatch,
+                    None
+                ),
+                (ActionDelta {}.name(), BindingResolution::Pending, Some(3)),
+            ]
+        );
+        assert_eq!(explanations[4].binding.meta(), Some(KeyBindingMetaIndex(1)));
+
+        // The explanation agrees with the bindings that are dispatched.
+        let (dispatched, _) =
+            keymap.bindings_for_input(&[Keystroke::parse("ctrl-a").unwrap()], &context_stack);
+        assert_eq!(dispatched.len(), 3);
+    }
+
     #[test]
     fn test_bindings_for_action() {
         let bindings = [
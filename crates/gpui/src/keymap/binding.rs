@@ -10,14 +10,21 @@ pub struct KeyBinding {
     pub(crate) action: Box<dyn Action>,
     pub(crate) keystrokes: SmallVec<[Keystroke; 2]>,
     pub(crate) context_predicate: Option<Rc<KeyBindingContextPredicate>>,
+    pub(crate) meta: Option<KeyBindingMetaIndex>,
 }
 
+/// An opaque identifier the application can attach to a binding, e.g. to
+/// record which keymap file it was loaded from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct KeyBindingMetaIndex(pub u32);
+
 impl Clone for KeyBinding {
     fn clone(&self) -> Self {
         KeyBinding {
             action: self.action.boxed_clone(),
             keystrokes: self.keystrokes.clone(),
             context_predicate: self.context_predicate.clone(),
+            meta: self.meta,
         }
     }
 }
@@ -59,6 +66,7 @@ impl KeyBinding {
             keystrokes,
             action,
             context_predicate,
+            meta: None,
         })
     }
 
@@ -91,6 +99,17 @@ impl KeyBinding {
     pub fn predicate(&self) -> Option<Rc<KeyBindingContextPredicate>> {
         self.context_predicate.as_ref().map(|rc| rc.clone())
     }
+
+    /// Attach application-defined metadata to this binding
+    pub fn with_meta(mut self, meta: KeyBindingMetaIndex) -> Self {
+        self.meta = Some(meta);
+        self
+    }
+
+    /// Get the application-defined metadata attached to this binding
+    pub fn meta(&self) -> Option<KeyBindingMetaIndex> {
+        self.meta
+    }
 }
 
 impl std::fmt::Debug for KeyBinding {
@@ -99,6 +118,7 @@ impl std::fmt::Debug for KeyBinding {
             .field("keystrokes", &self.keystrokes)
             .field("context_predicate", &self.context_predicate)
             .field("action", &self.action.name())
+            .field("meta", &self.meta)
             .finish()
     }
 }
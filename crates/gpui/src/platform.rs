@@ -28,9 +28,9 @@ mod windows;
 
 use crate::{
     point, Action, AnyWindowHandle, App, AsyncWindowContext, BackgroundExecutor, Bounds,
-    DevicePixels, DispatchEventResult, Font, FontId, FontMetrics, FontRun, ForegroundExecutor,
-    GlyphId, GpuSpecs, ImageSource, Keymap, LineLayout, Pixels, PlatformInput, Point,
-    RenderGlyphParams, RenderImage, RenderImageParams, RenderSvgParams, ScaledPixels, Scene,
+    DevicePixels, DispatchEventResult, ExternalPaths, Font, FontId, FontMetrics, FontRun,
+    ForegroundExecutor, GlyphId, GpuSpecs, ImageSource, Keymap, LineLayout, Pixels, PlatformInput,
+    Point, RenderGlyphParams, RenderImage, RenderImageParams, RenderSvgParams, ScaledPixels, Scene,
     SharedString, Size, SvgRenderer, SvgSize, Task, TaskLabel, Window, DEFAULT_WINDOW_SIZE,
 };
 use anyhow::{anyhow, Result};
@@ -1241,13 +1241,17 @@ pub struct ClipboardItem {
     entries: Vec<ClipboardEntry>,
 }
 
-/// Either a ClipboardString or a ClipboardImage
+/// Either a ClipboardString, a ClipboardImage, the paths of copied files, or HTML
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum ClipboardEntry {
     /// A string entry
     String(ClipboardString),
     /// An image entry
     Image(Image),
+    /// The paths of files copied from a file manager
+    ExternalPaths(ExternalPaths),
+    /// HTML for rich text targets, alongside a string entry with its plain text
+    Html(String),
 }
 
 impl ClipboardItem {
@@ -1284,6 +1288,21 @@ impl ClipboardItem {
         }
     }
 
+    /// Create a new ClipboardItem::ExternalPaths with the paths of the given files
+    pub fn new_external_paths(paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        Self {
+            entries: vec![ClipboardEntry::ExternalPaths(ExternalPaths(
+                paths.into_iter().collect(),
+            ))],
+        }
+    }
+
+    /// Adds HTML to the item, for the targets that paste rich text
+    pub fn with_html(mut self, html: String) -> Self {
+        self.entries.push(ClipboardEntry::Html(html));
+        self
+    }
+
     /// Concatenates together all the ClipboardString entries in the item.
     /// Returns None if there were no ClipboardString entries.
     pub fn text(&self) -> Option<String> {
@@ -1304,17 +1323,57 @@ impl ClipboardItem {
         }
     }
 
-    /// If this item is one ClipboardEntry::String, returns its metadata.
+    /// Concatenates together all the ClipboardString entries in the item, like
+    /// [`ClipboardItem::text`], or, if there were none, returns the paths of the copied files,
+    /// one per line. Returns None if there were no paths either.
+    pub fn text_or_paths(&self) -> Option<String> {
+        self.text().or_else(|| {
+            let paths = self.external_paths()?;
+            Some(
+                paths
+                    .paths()
+                    .iter()
+                    .map(|path| path.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            )
+        })
+    }
+
+    /// If this item is one ClipboardEntry::String, besides its HTML, returns its metadata.
     #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
     pub fn metadata(&self) -> Option<&String> {
-        match self.entries().first() {
-            Some(ClipboardEntry::String(clipboard_string)) if self.entries.len() == 1 => {
-                clipboard_string.metadata.as_ref()
-            }
+        self.string()?.metadata.as_ref()
+    }
+
+    /// If this item is one ClipboardEntry::String, besides its HTML, returns it.
+    pub fn string(&self) -> Option<&ClipboardString> {
+        let mut entries = self
+            .entries
+            .iter()
+            .filter(|entry| !matches!(entry, ClipboardEntry::Html(_)));
+        match (entries.next(), entries.next()) {
+            (Some(ClipboardEntry::String(clipboard_string)), None) => Some(clipboard_string),
             _ => None,
         }
     }
 
+    /// Returns the paths of the files this item was copied from, if any.
+    pub fn external_paths(&self) -> Option<&ExternalPaths> {
+        self.entries.iter().find_map(|entry| match entry {
+            ClipboardEntry::ExternalPaths(paths) => Some(paths),
+            _ => None,
+        })
+    }
+
+    /// Returns the HTML of this item, if any.
+    pub fn html(&self) -> Option<&str> {
+        self.entries.iter().find_map(|entry| match entry {
+            ClipboardEntry::Html(html) => Some(html.as_str()),
+            _ => None,
+        })
+    }
+
     /// Get the item's entries
     pub fn entries(&self) -> &[ClipboardEntry] {
         &self.entries
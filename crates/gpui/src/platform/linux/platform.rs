@@ -525,8 +525,42 @@ impl<P: LinuxClient + 'static> Platform for P {
         self.with_common(|common| common.appearance)
     }
 
-    fn register_url_scheme(&self, _: &str) -> Task<anyhow::Result<()>> {
-        Task::ready(Err(anyhow!("register_url_scheme unimplemented")))
+    fn register_url_scheme(&self, scheme: &str) -> Task<anyhow::Result<()>> {
+        let scheme = scheme.to_string();
+        self.background_executor().spawn(async move {
+            let exe_path = env::current_exe()?;
+            let applications_dir = env::var_os("XDG_DATA_HOME")
+                .map(PathBuf::from)
+                .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+                .context("neither XDG_DATA_HOME nor HOME is set")?
+                .join("applications");
+            std::fs::create_dir_all(&applications_dir)
+                .with_context(|| format!("creating {applications_dir:?}"))?;
+
+            // A hidden desktop entry only opening the URLs of the scheme, so that it doesn't
+            // replace the one of the installed application.
+            let desktop_file_name = format!("{scheme}-url-handler.desktop");
+            let desktop_entry = format!(
+                "[Desktop Entry]\n\
+                 Type=Application\n\
+                 Name={scheme} URL Handler\n\
+                 Exec=\"{}\" %u\n\
+                 MimeType=x-scheme-handler/{scheme};\n\
+                 NoDisplay=true\n",
+                exe_path.display()
+            );
+            std::fs::write(applications_dir.join(&desktop_file_name), desktop_entry)
+                .context("writing the desktop entry of the URL scheme")?;
+
+            let status = Command::new("xdg-mime")
+                .arg("default")
+                .arg(&desktop_file_name)
+                .arg(format!("x-scheme-handler/{scheme}"))
+                .status()
+                .context("running xdg-mime")?;
+            anyhow::ensure!(status.success(), "xdg-mime failed: {status}");
+            Ok(())
+        })
     }
 
     fn write_to_primary(&self, item: ClipboardItem) {
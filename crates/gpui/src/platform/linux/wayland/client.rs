@@ -73,7 +73,7 @@ use crate::platform::linux::{
     get_xkb_compose_state, is_within_click_distance, open_uri_internal, read_fd,
     reveal_path_internal,
     wayland::{
-        clipboard::{Clipboard, DataOffer, FILE_LIST_MIME_TYPE, TEXT_MIME_TYPE},
+        clipboard::{Clipboard, DataOffer, FILE_LIST_MIME_TYPE},
         cursor::Cursor,
         serial::{SerialKind, SerialTracker},
         window::WaylandWindow,
@@ -749,11 +749,13 @@ impl LinuxClient for WaylandClient {
             return;
         };
         if state.mouse_focused_window.is_some() || state.keyboard_focused_window.is_some() {
+            let mime_types = state.clipboard.offered_mime_types(&item);
             state.clipboard.set_primary(item);
             let serial = state.serial_tracker.get(SerialKind::KeyPress);
             let data_source = primary_selection_manager.create_source(&state.globals.qh, ());
-            data_source.offer(state.clipboard.self_mime());
-            data_source.offer(TEXT_MIME_TYPE.to_string());
+            for mime_type in mime_types {
+                data_source.offer(mime_type);
+            }
             primary_selection.set_selection(Some(&data_source), serial);
         }
     }
@@ -767,11 +769,13 @@ impl LinuxClient for WaylandClient {
             return;
         };
         if state.mouse_focused_window.is_some() || state.keyboard_focused_window.is_some() {
+            let mime_types = state.clipboard.offered_mime_types(&item);
             state.clipboard.set(item);
             let serial = state.serial_tracker.get(SerialKind::KeyPress);
             let data_source = data_device_manager.create_data_source(&state.globals.qh, ());
-            data_source.offer(state.clipboard.self_mime());
-            data_source.offer(TEXT_MIME_TYPE.to_string());
+            for mime_type in mime_types {
+                data_source.offer(mime_type);
+            }
             data_device.set_selection(Some(&data_source), serial);
         }
     }
@@ -6,17 +6,21 @@ use std::{
 
 use calloop::{LoopHandle, PostAction};
 use filedescriptor::Pipe;
+use http_client::Url;
+use smallvec::SmallVec;
 use strum::IntoEnumIterator;
+use util::ResultExt;
 use wayland_client::{protocol::wl_data_offer::WlDataOffer, Connection};
 use wayland_protocols::wp::primary_selection::zv1::client::zwp_primary_selection_offer_v1::ZwpPrimarySelectionOfferV1;
 
 use crate::{
-    hash, platform::linux::platform::read_fd, ClipboardEntry, ClipboardItem, Image, ImageFormat,
-    WaylandClientStatePtr,
+    hash, platform::linux::platform::read_fd, ClipboardEntry, ClipboardItem, ExternalPaths, Image,
+    ImageFormat, WaylandClientStatePtr,
 };
 
 pub(crate) const TEXT_MIME_TYPE: &str = "text/plain;charset=utf-8";
 pub(crate) const FILE_LIST_MIME_TYPE: &str = "text/uri-list";
+pub(crate) const HTML_MIME_TYPE: &str = "text/html";
 
 /// Text mime types that we'll accept from other programs.
 pub(crate) const ALLOWED_TEXT_MIME_TYPES: [&str; 2] = ["text/plain;charset=utf-8", "UTF8_STRING"];
@@ -114,7 +118,35 @@ impl<T: ReceiveData> DataOffer<T> {
         // copying from eg: firefox inserts a lot of blank
         // lines, and that is super annoying.
         let result = text_content.replace("\r\n", "\n");
-        Some(ClipboardItem::new_string(result))
+        let mut item = ClipboardItem::new_string(result);
+        if self.has_mime_type(HTML_MIME_TYPE) {
+            if let Some(html) = self
+                .read_bytes(connection, HTML_MIME_TYPE)
+                .and_then(|bytes| String::from_utf8(bytes).log_err())
+            {
+                item.entries.push(ClipboardEntry::Html(html));
+            }
+        }
+        Some(item)
+    }
+
+    fn read_external_paths(&self, connection: &Connection) -> Option<ClipboardItem> {
+        if !self.has_mime_type(FILE_LIST_MIME_TYPE) {
+            return None;
+        }
+        let bytes = self.read_bytes(connection, FILE_LIST_MIME_TYPE)?;
+        let file_list = String::from_utf8(bytes).log_err()?;
+        let paths: SmallVec<[_; 2]> = file_list
+            .lines()
+            .filter_map(|path| Url::parse(path).log_err())
+            .filter_map(|url| url.to_file_path().log_err())
+            .collect();
+        if paths.is_empty() {
+            return None;
+        }
+        Some(ClipboardItem {
+            entries: vec![ClipboardEntry::ExternalPaths(ExternalPaths(paths))],
+        })
     }
 
     fn read_image(&self, connection: &Connection) -> Option<ClipboardItem> {
@@ -185,19 +217,36 @@ impl Clipboard {
         self.self_mime.clone()
     }
 
-    pub fn send(&self, _mime_type: String, fd: OwnedFd) {
-        if let Some(text) = self.contents.as_ref().and_then(|contents| contents.text()) {
-            self.send_internal(fd, text.as_bytes().to_owned());
+    /// The mime types the item is offered in to other programs: its text, and its HTML or the
+    /// list of its copied files when it has them.
+    pub fn offered_mime_types(&self, item: &ClipboardItem) -> Vec<String> {
+        let mut mime_types = vec![self.self_mime(), TEXT_MIME_TYPE.to_string()];
+        if item.html().is_some() {
+            mime_types.push(HTML_MIME_TYPE.to_string());
         }
+        if item.external_paths().is_some() {
+            mime_types.push(FILE_LIST_MIME_TYPE.to_string());
+        }
+        mime_types
     }
 
-    pub fn send_primary(&self, _mime_type: String, fd: OwnedFd) {
-        if let Some(text) = self
+    pub fn send(&self, mime_type: String, fd: OwnedFd) {
+        if let Some(bytes) = self
+            .contents
+            .as_ref()
+            .and_then(|contents| bytes_for_mime_type(contents, &mime_type))
+        {
+            self.send_internal(fd, bytes);
+        }
+    }
+
+    pub fn send_primary(&self, mime_type: String, fd: OwnedFd) {
+        if let Some(bytes) = self
             .primary_contents
             .as_ref()
-            .and_then(|contents| contents.text())
+            .and_then(|contents| bytes_for_mime_type(contents, &mime_type))
         {
-            self.send_internal(fd, text.as_bytes().to_owned());
+            self.send_internal(fd, bytes);
         }
     }
 
@@ -212,7 +261,8 @@ impl Clipboard {
         }
 
         let item = offer
-            .read_text(&self.connection)
+            .read_external_paths(&self.connection)
+            .or_else(|| offer.read_text(&self.connection))
             .or_else(|| offer.read_image(&self.connection))?;
 
         self.cached_read = Some(item.clone());
@@ -230,7 +280,8 @@ impl Clipboard {
         }
 
         let item = offer
-            .read_text(&self.connection)
+            .read_external_paths(&self.connection)
+            .or_else(|| offer.read_text(&self.connection))
             .or_else(|| offer.read_image(&self.connection))?;
 
         self.cached_primary_read = Some(item.clone());
@@ -266,3 +317,20 @@ impl Clipboard {
             .unwrap();
     }
 }
+
+/// Returns the contents of the item in the mime type, falling back to its text.
+fn bytes_for_mime_type(item: &ClipboardItem, mime_type: &str) -> Option<Vec<u8>> {
+    match mime_type {
+        HTML_MIME_TYPE => item.html().map(|html| html.as_bytes().to_owned()),
+        FILE_LIST_MIME_TYPE => item.external_paths().map(|paths| {
+            paths
+                .paths()
+                .iter()
+                .filter_map(|path| Url::from_file_path(path).ok())
+                .map(|url| format!("{url}\r\n"))
+                .collect::<String>()
+                .into_bytes()
+        }),
+        _ => item.text_or_paths().map(String::into_bytes),
+    }
+}
@@ -27,7 +27,7 @@ use x11rb::{
     protocol::xinput::ConnectionExt,
     protocol::xkb::ConnectionExt as _,
     protocol::xproto::{
-        AtomEnum, ChangeWindowAttributesAux, ClientMessageData, ClientMessageEvent,
+        Atom, AtomEnum, ChangeWindowAttributesAux, ClientMessageData, ClientMessageEvent,
         ConnectionExt as _, EventMask, KeyPressEvent,
     },
     protocol::{randr, render, xinput, xkb, xproto, Event},
@@ -58,10 +58,10 @@ use crate::platform::{
     LinuxCommon, PlatformWindow,
 };
 use crate::{
-    modifiers_from_xinput_info, point, px, AnyWindowHandle, Bounds, ClipboardItem, CursorStyle,
-    DisplayId, FileDropEvent, Keystroke, Modifiers, ModifiersChangedEvent, MouseButton, Pixels,
-    Platform, PlatformDisplay, PlatformInput, Point, RequestFrameOptions, ScaledPixels,
-    ScrollDelta, Size, TouchPhase, WindowParams, X11Window,
+    modifiers_from_xinput_info, point, px, AnyWindowHandle, Bounds, ClipboardEntry, ClipboardItem,
+    CursorStyle, DisplayId, FileDropEvent, Keystroke, Modifiers, ModifiersChangedEvent,
+    MouseButton, Pixels, Platform, PlatformDisplay, PlatformInput, Point, RequestFrameOptions,
+    ScaledPixels, ScrollDelta, Size, TouchPhase, WindowParams, X11Window,
 };
 
 /// Value for DeviceId parameters which selects all devices.
@@ -1476,6 +1476,8 @@ impl LinuxClient for X11Client {
         reveal_path_internal(self.background_executor(), path, None);
     }
 
+    // The clipboard serves a single target per selection, so copied files are written as their
+    // paths, and the HTML of the item is only kept for pasting it in this app.
     fn write_to_primary(&self, item: crate::ClipboardItem) {
         let state = self.0.borrow_mut();
         state
@@ -1483,7 +1485,7 @@ impl LinuxClient for X11Client {
             .store(
                 state.clipboard.setter.atoms.primary,
                 state.clipboard.setter.atoms.utf8_string,
-                item.text().unwrap_or_default().as_bytes(),
+                item.text_or_paths().unwrap_or_default().as_bytes(),
             )
             .ok();
     }
@@ -1495,7 +1497,7 @@ impl LinuxClient for X11Client {
             .store(
                 state.clipboard.setter.atoms.clipboard,
                 state.clipboard.setter.atoms.utf8_string,
-                item.text().unwrap_or_default().as_bytes(),
+                item.text_or_paths().unwrap_or_default().as_bytes(),
             )
             .ok();
         state.clipboard_item.replace(item);
@@ -1503,16 +1505,7 @@ impl LinuxClient for X11Client {
 
     fn read_from_primary(&self) -> Option<crate::ClipboardItem> {
         let state = self.0.borrow_mut();
-        state
-            .clipboard
-            .load(
-                state.clipboard.getter.atoms.primary,
-                state.clipboard.getter.atoms.utf8_string,
-                state.clipboard.getter.atoms.property,
-                Duration::from_secs(3),
-            )
-            .map(|text| crate::ClipboardItem::new_string(String::from_utf8(text).unwrap()))
-            .ok()
+        read_selection(&state.clipboard, state.clipboard.getter.atoms.primary)
     }
 
     fn read_from_clipboard(&self) -> Option<crate::ClipboardItem> {
@@ -1531,16 +1524,7 @@ impl LinuxClient for X11Client {
         {
             return state.clipboard_item.clone();
         }
-        state
-            .clipboard
-            .load(
-                state.clipboard.getter.atoms.clipboard,
-                state.clipboard.getter.atoms.utf8_string,
-                state.clipboard.getter.atoms.property,
-                Duration::from_secs(3),
-            )
-            .map(|text| crate::ClipboardItem::new_string(String::from_utf8(text).unwrap()))
-            .ok()
+        read_selection(&state.clipboard, state.clipboard.getter.atoms.clipboard)
     }
 
     fn run(&self) {
@@ -1938,3 +1922,59 @@ fn make_scroll_wheel_event(
         touch_phase: TouchPhase::default(),
     }
 }
+
+/// Reads a selection owned by another program, as the copied files it's offered as, or as its
+/// text along with its HTML.
+fn read_selection(clipboard: &x11_clipboard::Clipboard, selection: Atom) -> Option<ClipboardItem> {
+    let getter = &clipboard.getter;
+    let load = |target: Atom| {
+        clipboard
+            .load(
+                selection,
+                target,
+                getter.atoms.property,
+                Duration::from_secs(3),
+            )
+            .ok()
+    };
+    let targets = load(getter.atoms.targets)
+        .map(|bytes| {
+            bytes
+                .chunks_exact(4)
+                .map(|atom| Atom::from_ne_bytes([atom[0], atom[1], atom[2], atom[3]]))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let offered_target = |name: &str| {
+        getter
+            .get_atom(name)
+            .ok()
+            .filter(|atom| targets.contains(atom))
+    };
+
+    if let Some(file_list) = offered_target("text/uri-list")
+        .and_then(load)
+        .and_then(|bytes| String::from_utf8(bytes).log_err())
+    {
+        let paths: SmallVec<[_; 2]> = file_list
+            .lines()
+            .filter_map(|path| Url::parse(path).log_err())
+            .filter_map(|url| url.to_file_path().log_err())
+            .collect();
+        if !paths.is_empty() {
+            return Some(ClipboardItem {
+                entries: vec![ClipboardEntry::ExternalPaths(crate::ExternalPaths(paths))],
+            });
+        }
+    }
+
+    let text = String::from_utf8(load(getter.atoms.utf8_string)?).log_err()?;
+    let mut item = ClipboardItem::new_string(text);
+    if let Some(html) = offered_target("text/html")
+        .and_then(load)
+        .and_then(|bytes| String::from_utf8(bytes).log_err())
+    {
+        item.entries.push(ClipboardEntry::Html(html));
+    }
+    Some(item)
+}
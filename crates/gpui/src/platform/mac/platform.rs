@@ -5,24 +5,24 @@ use super::{
 };
 use crate::{
     hash, Action, AnyWindowHandle, BackgroundExecutor, ClipboardEntry, ClipboardItem,
-    ClipboardString, CursorStyle, ForegroundExecutor, Image, ImageFormat, Keymap, MacDispatcher,
-    MacDisplay, MacWindow, Menu, MenuItem, PathPromptOptions, Platform, PlatformDisplay,
-    PlatformTextSystem, PlatformWindow, Result, ScreenCaptureSource, SemanticVersion, Task,
-    WindowAppearance, WindowParams,
+    ClipboardString, CursorStyle, ExternalPaths, ForegroundExecutor, Image, ImageFormat, Keymap,
+    MacDispatcher, MacDisplay, MacWindow, Menu, MenuItem, PathPromptOptions, Platform,
+    PlatformDisplay, PlatformTextSystem, PlatformWindow, Result, ScreenCaptureSource,
+    SemanticVersion, Task, WindowAppearance, WindowParams,
 };
 use anyhow::{anyhow, Context as _};
 use block::ConcreteBlock;
 use cocoa::{
     appkit::{
         NSApplication, NSApplicationActivationPolicy::NSApplicationActivationPolicyRegular,
-        NSEventModifierFlags, NSMenu, NSMenuItem, NSModalResponse, NSOpenPanel, NSPasteboard,
-        NSPasteboardTypePNG, NSPasteboardTypeRTF, NSPasteboardTypeRTFD, NSPasteboardTypeString,
-        NSPasteboardTypeTIFF, NSSavePanel, NSWindow,
+        NSEventModifierFlags, NSFilenamesPboardType, NSMenu, NSMenuItem, NSModalResponse,
+        NSOpenPanel, NSPasteboard, NSPasteboardTypePNG, NSPasteboardTypeRTF, NSPasteboardTypeRTFD,
+        NSPasteboardTypeString, NSPasteboardTypeTIFF, NSSavePanel, NSWindow,
     },
     base::{id, nil, selector, BOOL, NO, YES},
     foundation::{
-        NSArray, NSAutoreleasePool, NSBundle, NSData, NSInteger, NSProcessInfo, NSRange, NSString,
-        NSUInteger, NSURL,
+        NSArray, NSAutoreleasePool, NSBundle, NSData, NSFastEnumeration, NSInteger, NSProcessInfo,
+        NSRange, NSString, NSUInteger, NSURL,
     },
 };
 use core_foundation::{
@@ -44,6 +44,7 @@ use objc::{
 };
 use parking_lot::Mutex;
 use ptr::null_mut;
+use smallvec::SmallVec;
 use std::{
     cell::Cell,
     convert::TryInto,
@@ -965,10 +966,16 @@ impl Platform for MacPlatform {
     fn write_to_clipboard(&self, item: ClipboardItem) {
         use crate::ClipboardEntry;
 
+        let html = item.html().map(ToOwned::to_owned);
+        let entries = item
+            .entries
+            .into_iter()
+            .filter(|entry| !matches!(entry, ClipboardEntry::Html(_)))
+            .collect::<Vec<_>>();
         unsafe {
             // We only want to use NSAttributedString if there are multiple entries to write.
-            if item.entries.len() <= 1 {
-                match item.entries.first() {
+            if entries.len() <= 1 {
+                match entries.first() {
                     Some(entry) => match entry {
                         ClipboardEntry::String(string) => {
                             self.write_plaintext_to_clipboard(string);
@@ -976,6 +983,11 @@ impl Platform for MacPlatform {
                         ClipboardEntry::Image(image) => {
                             self.write_image_to_clipboard(image);
                         }
+                        ClipboardEntry::ExternalPaths(paths) => {
+                            self.write_external_paths_to_clipboard(paths);
+                        }
+                        // HTML is written below, along with the other entries.
+                        ClipboardEntry::Html(_) => {}
                     },
                     None => {
                         // Writing an empty list of entries just clears the clipboard.
@@ -990,7 +1002,7 @@ impl Platform for MacPlatform {
                         // TODO can we skip this? Or at least part of it?
                         .init_attributed_string(NSString::alloc(nil).init_str(""));
 
-                    for entry in item.entries {
+                    for entry in entries {
                         if let ClipboardEntry::String(ClipboardString { text, metadata: _ }) = entry
                         {
                             let to_append = NSAttributedString::alloc(nil)
@@ -1034,6 +1046,18 @@ impl Platform for MacPlatform {
                     .pasteboard
                     .setString_forType(plain_text, NSPasteboardTypeString);
             }
+
+            if let Some(html) = html {
+                let state = self.0.lock();
+                let html_bytes = NSData::dataWithBytes_length_(
+                    nil,
+                    html.as_ptr() as *const c_void,
+                    html.len() as u64,
+                );
+                state
+                    .pasteboard
+                    .setData_forType(html_bytes, ns_string("public.html"));
+            }
         }
     }
 
@@ -1041,25 +1065,41 @@ impl Platform for MacPlatform {
         let state = self.0.lock();
         let pasteboard = state.pasteboard;
 
-        // First, see if it's a string.
         unsafe {
             let types: id = pasteboard.types();
-            let string_type: id = ns_string("public.utf8-plain-text");
 
+            // Files copied from a file manager also come with their names as strings.
+            if msg_send![types, containsObject: NSFilenamesPboardType] {
+                if let Some(paths) = read_external_paths_from_pasteboard(pasteboard) {
+                    return Some(ClipboardItem {
+                        entries: vec![ClipboardEntry::ExternalPaths(paths)],
+                    });
+                }
+            }
+
+            // Then, see if it's a string.
+            let string_type: id = ns_string("public.utf8-plain-text");
             if msg_send![types, containsObject: string_type] {
                 let data = pasteboard.dataForType(string_type);
-                if data == nil {
+                let mut item = if data == nil {
                     return None;
                 } else if data.bytes().is_null() {
                     // https://developer.apple.com/documentation/foundation/nsdata/1410616-bytes?language=objc
                     // "If the length of the NSData object is 0, this property returns nil."
-                    return Some(self.read_string_from_clipboard(&state, &[]));
+                    self.read_string_from_clipboard(&state, &[])
                 } else {
                     let bytes =
                         slice::from_raw_parts(data.bytes() as *mut u8, data.length() as usize);
 
-                    return Some(self.read_string_from_clipboard(&state, bytes));
+                    self.read_string_from_clipboard(&state, bytes)
+                };
+                if let Some(html) = self.read_from_pasteboard(pasteboard, ns_string("public.html"))
+                {
+                    item.entries.push(ClipboardEntry::Html(
+                        String::from_utf8_lossy(html).into_owned(),
+                    ));
                 }
+                return Some(item);
             }
 
             // If it wasn't a string, try the various supported image types.
@@ -1248,6 +1288,25 @@ impl MacPlatform {
         }
     }
 
+    unsafe fn write_external_paths_to_clipboard(&self, paths: &ExternalPaths) {
+        let state = self.0.lock();
+        state.pasteboard.clearContents();
+
+        let filenames = NSArray::arrayWithObjects(
+            nil,
+            &paths
+                .paths()
+                .iter()
+                .map(|path| ns_string(&path.to_string_lossy()))
+                .collect::<Vec<_>>(),
+        );
+        let _: BOOL = msg_send![
+            state.pasteboard,
+            setPropertyList: filenames
+            forType: NSFilenamesPboardType
+        ];
+    }
+
     unsafe fn write_image_to_clipboard(&self, image: &Image) {
         let state = self.0.lock();
         state.pasteboard.clearContents();
@@ -1290,6 +1349,21 @@ fn try_clipboard_image(pasteboard: id, format: ImageFormat) -> Option<ClipboardI
     }
 }
 
+unsafe fn read_external_paths_from_pasteboard(pasteboard: id) -> Option<ExternalPaths> {
+    let filenames = pasteboard.propertyListForType(NSFilenamesPboardType);
+    if filenames == nil {
+        return None;
+    }
+    let paths = filenames
+        .iter()
+        .map(|filename| {
+            let filename = CStr::from_ptr(filename.UTF8String());
+            PathBuf::from(filename.to_string_lossy().into_owned())
+        })
+        .collect::<SmallVec<_>>();
+    (!paths.is_empty()).then(|| ExternalPaths(paths))
+}
+
 unsafe fn path_from_objc(path: id) -> PathBuf {
     let len = msg_send![path, lengthOfBytesUsingEncoding: NSUTF8StringEncoding];
     let bytes = path.UTF8String() as *const u8;
@@ -1,11 +1,13 @@
 mod dispatcher;
 mod display;
 mod platform;
+mod text_system;
 mod window;
 
 pub(crate) use dispatcher::*;
 pub(crate) use display::*;
 pub(crate) use platform::*;
+pub(crate) use text_system::*;
 pub(crate) use window::*;
 
 pub use platform::TestScreenCaptureSource;
@@ -72,6 +72,16 @@ pub(crate) struct TestPrompts {
 
 impl TestPlatform {
     pub fn new(executor: BackgroundExecutor, foreground_executor: ForegroundExecutor) -> Rc<Self> {
+        Self::with_text_system(executor, foreground_executor, None)
+    }
+
+    /// Creates a test platform laying out text with the given text system, rather than with the
+    /// text system of the platform the tests run on.
+    pub fn with_text_system(
+        executor: BackgroundExecutor,
+        foreground_executor: ForegroundExecutor,
+        text_system: Option<Arc<dyn PlatformTextSystem>>,
+    ) -> Rc<Self> {
         #[cfg(target_os = "windows")]
         let bitmap_factory = unsafe {
             windows::Win32::System::Ole::OleInitialize(None)
@@ -82,17 +92,23 @@ impl TestPlatform {
             )
         };
 
-        #[cfg(target_os = "macos")]
-        let text_system = Arc::new(crate::platform::mac::MacTextSystem::new());
+        let text_system = text_system.unwrap_or_else(|| {
+            #[cfg(target_os = "macos")]
+            let text_system: Arc<dyn PlatformTextSystem> =
+                Arc::new(crate::platform::mac::MacTextSystem::new());
 
-        #[cfg(any(target_os = "linux", target_os = "freebsd"))]
-        let text_system = Arc::new(crate::platform::linux::CosmicTextSystem::new());
+            #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+            let text_system: Arc<dyn PlatformTextSystem> =
+                Arc::new(crate::platform::linux::CosmicTextSystem::new());
 
-        #[cfg(target_os = "windows")]
-        let text_system = Arc::new(
-            crate::platform::windows::DirectWriteTextSystem::new(&bitmap_factory)
-                .expect("Unable to initialize direct write."),
-        );
+            #[cfg(target_os = "windows")]
+            let text_system: Arc<dyn PlatformTextSystem> = Arc::new(
+                crate::platform::windows::DirectWriteTextSystem::new(&bitmap_factory)
+                    .expect("Unable to initialize direct write."),
+            );
+
+            text_system
+        });
 
         Rc::new_cyclic(|weak| TestPlatform {
             background_executor: executor,
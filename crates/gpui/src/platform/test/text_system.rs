@@ -0,0 +1,144 @@
+use crate::{
+    point, px, size, Bounds, DevicePixels, Font, FontId, FontMetrics, FontRun, GlyphId, LineLayout,
+    Pixels, PlatformTextSystem, RenderGlyphParams, ShapedGlyph, ShapedRun, Size,
+};
+use anyhow::{anyhow, Result};
+use parking_lot::Mutex;
+use std::borrow::Cow;
+
+const UNITS_PER_EM: u32 = 1000;
+
+/// The advance of every glyph, in font units: half an em.
+const GLYPH_ADVANCE: f32 = 500.;
+
+const FONT_METRICS: FontMetrics = FontMetrics {
+    units_per_em: UNITS_PER_EM,
+    ascent: 800.,
+    descent: -200.,
+    line_gap: 0.,
+    underline_position: -100.,
+    underline_thickness: 50.,
+    cap_height: 700.,
+    x_height: 500.,
+    bounding_box: Bounds {
+        origin: crate::Point { x: 0., y: -200. },
+        size: Size {
+            width: GLYPH_ADVANCE,
+            height: 1000.,
+        },
+    },
+};
+
+/// A text system for tests that lays out text without any fonts: every font has the same
+/// metrics, and every character is a glyph of the same advance. The layout of text is then the
+/// same on every platform, whatever fonts are installed.
+pub(crate) struct TestTextSystem {
+    fonts: Mutex<Vec<Font>>,
+}
+
+impl TestTextSystem {
+    pub(crate) fn new() -> Self {
+        Self {
+            fonts: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl PlatformTextSystem for TestTextSystem {
+    fn add_fonts(&self, _fonts: Vec<Cow<'static, [u8]>>) -> Result<()> {
+        Ok(())
+    }
+
+    fn all_font_names(&self) -> Vec<String> {
+        let mut names = self
+            .fonts
+            .lock()
+            .iter()
+            .map(|font| font.family.to_string())
+            .collect::<Vec<_>>();
+        names.dedup();
+        names
+    }
+
+    fn font_id(&self, descriptor: &Font) -> Result<FontId> {
+        let mut fonts = self.fonts.lock();
+        let ix = match fonts.iter().position(|font| font == descriptor) {
+            Some(ix) => ix,
+            None => {
+                fonts.push(descriptor.clone());
+                fonts.len() - 1
+            }
+        };
+        Ok(FontId(ix))
+    }
+
+    fn font_metrics(&self, _font_id: FontId) -> FontMetrics {
+        FONT_METRICS
+    }
+
+    fn typographic_bounds(&self, _font_id: FontId, _glyph_id: GlyphId) -> Result<Bounds<f32>> {
+        Ok(FONT_METRICS.bounding_box)
+    }
+
+    fn advance(&self, _font_id: FontId, _glyph_id: GlyphId) -> Result<Size<f32>> {
+        Ok(size(GLYPH_ADVANCE, 0.))
+    }
+
+    fn glyph_for_char(&self, _font_id: FontId, ch: char) -> Option<GlyphId> {
+        Some(GlyphId(ch as u32))
+    }
+
+    fn glyph_raster_bounds(&self, _params: &RenderGlyphParams) -> Result<Bounds<DevicePixels>> {
+        Ok(Bounds::default())
+    }
+
+    fn rasterize_glyph(
+        &self,
+        params: &RenderGlyphParams,
+        _raster_bounds: Bounds<DevicePixels>,
+    ) -> Result<(Size<DevicePixels>, Vec<u8>)> {
+        Err(anyhow!(
+            "glyphs of test fonts can't be rasterized: {:?}",
+            params
+        ))
+    }
+
+    fn layout_line(&self, text: &str, font_size: Pixels, runs: &[FontRun]) -> LineLayout {
+        let advance = font_size * (GLYPH_ADVANCE / UNITS_PER_EM as f32);
+        let mut x = px(0.);
+        let mut run_start = 0;
+        let runs = runs
+            .iter()
+            .map(|run| {
+                let run_end = run_start + run.len;
+                let glyphs = text[run_start..run_end]
+                    .char_indices()
+                    .map(|(ix, ch)| {
+                        let glyph = ShapedGlyph {
+                            id: GlyphId(ch as u32),
+                            position: point(x, px(0.)),
+                            index: run_start + ix,
+                            is_emoji: false,
+                        };
+                        x += advance;
+                        glyph
+                    })
+                    .collect();
+                run_start = run_end;
+                ShapedRun {
+                    font_id: run.font_id,
+                    glyphs,
+                }
+            })
+            .collect();
+
+        LineLayout {
+            font_size,
+            width: x,
+            ascent: FONT_METRICS.ascent(font_size),
+            descent: -FONT_METRICS.descent(font_size),
+            runs,
+            len: text.len(),
+        }
+    }
+}
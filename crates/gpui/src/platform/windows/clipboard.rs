@@ -1,8 +1,9 @@
-use std::sync::LazyLock;
+use std::{path::PathBuf, sync::LazyLock};
 
 use anyhow::Result;
 use collections::{FxHashMap, FxHashSet};
 use itertools::Itertools;
+use smallvec::SmallVec;
 use util::ResultExt;
 use windows::Win32::{
     Foundation::HANDLE,
@@ -20,7 +21,8 @@ use windows::Win32::{
 use windows_core::PCWSTR;
 
 use crate::{
-    hash, ClipboardEntry, ClipboardItem, ClipboardString, Image, ImageFormat, SmartGlobal,
+    hash, ClipboardEntry, ClipboardItem, ClipboardString, ExternalPaths, Image, ImageFormat,
+    SmartGlobal,
 };
 
 // https://learn.microsoft.com/en-us/windows/win32/api/shellapi/nf-shellapi-dragqueryfilew
@@ -39,6 +41,8 @@ static CLIPBOARD_PNG_FORMAT: LazyLock<u32> =
     LazyLock::new(|| register_clipboard_format(windows::core::w!("PNG")));
 static CLIPBOARD_JPG_FORMAT: LazyLock<u32> =
     LazyLock::new(|| register_clipboard_format(windows::core::w!("JFIF")));
+static CLIPBOARD_HTML_FORMAT: LazyLock<u32> =
+    LazyLock::new(|| register_clipboard_format(windows::core::w!("HTML Format")));
 
 // Helper maps and sets
 static FORMATS_MAP: LazyLock<FxHashMap<u32, ClipboardFormatType>> = LazyLock::new(|| {
@@ -123,13 +127,17 @@ fn format_to_type(item_format: u32) -> &'static ClipboardFormatType {
     FORMATS_MAP.get(&item_format).unwrap()
 }
 
-// Currently, we only write the first item.
+// Currently, we only write the first item, along with the HTML of the item.
 fn write_to_clipboard_inner(item: ClipboardItem) -> Result<()> {
     unsafe {
         OpenClipboard(None)?;
         EmptyClipboard()?;
     }
-    match item.entries().first() {
+    let first_entry = item
+        .entries()
+        .iter()
+        .find(|entry| !matches!(entry, ClipboardEntry::Html(_)));
+    match first_entry {
         Some(entry) => match entry {
             ClipboardEntry::String(string) => {
                 write_string_to_clipboard(string)?;
@@ -137,14 +145,51 @@ fn write_to_clipboard_inner(item: ClipboardItem) -> Result<()> {
             ClipboardEntry::Image(image) => {
                 write_image_to_clipboard(image)?;
             }
+            ClipboardEntry::ExternalPaths(_) => {
+                write_string_to_clipboard(&ClipboardString::new(
+                    item.text_or_paths().unwrap_or_default(),
+                ))?;
+            }
+            ClipboardEntry::Html(_) => unreachable!(),
         },
         None => {
             // Writing an empty list of entries just clears the clipboard.
         }
     }
+    if let Some(html) = item.html() {
+        write_html_to_clipboard(html)?;
+    }
     Ok(())
 }
 
+// https://learn.microsoft.com/en-us/windows/win32/dataxchg/html-clipboard-format
+const HTML_FRAGMENT_START: &str = "<html><body><!--StartFragment-->";
+const HTML_FRAGMENT_END: &str = "<!--EndFragment--></body></html>";
+
+/// Writes the HTML in the "HTML Format", whose header gives the byte offsets of the document
+/// and of the fragment it holds.
+fn write_html_to_clipboard(html: &str) -> Result<()> {
+    // The offsets are padded to a fixed width, so that the header's length doesn't depend on them.
+    let header = |start_html: usize,
+                  end_html: usize,
+                  start_fragment: usize,
+                  end_fragment: usize| {
+        format!(
+            "Version:0.9\r\nStartHTML:{start_html:010}\r\nEndHTML:{end_html:010}\r\nStartFragment:{start_fragment:010}\r\nEndFragment:{end_fragment:010}\r\n"
+        )
+    };
+    let start_html = header(0, 0, 0, 0).len();
+    let start_fragment = start_html + HTML_FRAGMENT_START.len();
+    let end_fragment = start_fragment + html.len();
+    let end_html = end_fragment + HTML_FRAGMENT_END.len();
+    let mut data = header(start_html, end_html, start_fragment, end_fragment);
+    data.push_str(HTML_FRAGMENT_START);
+    data.push_str(html);
+    data.push_str(HTML_FRAGMENT_END);
+    data.push('\0');
+    set_data_to_clipboard(data.as_bytes(), *CLIPBOARD_HTML_FORMAT)
+}
+
 fn write_string_to_clipboard(item: &ClipboardString) -> Result<()> {
     let encode_wide = item.text.encode_utf16().chain(Some(0)).collect_vec();
     set_data_to_clipboard(&encode_wide, CF_UNICODETEXT.0 as u32)?;
@@ -219,11 +264,17 @@ fn convert_image_to_png_format(bytes: &[u8], image_format: ImageFormat) -> Resul
 
 fn read_from_clipboard_inner() -> Option<ClipboardItem> {
     unsafe { OpenClipboard(None) }.log_err()?;
-    with_best_match_format(|item_format| match format_to_type(item_format) {
+    let mut item = with_best_match_format(|item_format| match format_to_type(item_format) {
         ClipboardFormatType::Text => read_string_from_clipboard(),
         ClipboardFormatType::Image => read_image_from_clipboard(item_format),
         ClipboardFormatType::Files => read_files_from_clipboard(),
-    })
+    })?;
+    if item.string().is_some() {
+        if let Some(html) = read_html_from_clipboard() {
+            item.entries.push(ClipboardEntry::Html(html));
+        }
+    }
+    Some(item)
 }
 
 // Here, we enumerate all formats on the clipboard and find the first one that we can process.
@@ -316,6 +367,30 @@ fn read_metadata_from_clipboard() -> Option<String> {
     Some(String::from_utf16_lossy(unsafe { text.as_wide() }))
 }
 
+/// Reads the fragment of the HTML written in the "HTML Format".
+fn read_html_from_clipboard() -> Option<String> {
+    if unsafe { IsClipboardFormatAvailable(*CLIPBOARD_HTML_FORMAT).is_err() } {
+        return None;
+    }
+    let global =
+        SmartGlobal::from_raw_ptr(unsafe { GetClipboardData(*CLIPBOARD_HTML_FORMAT).log_err() }?.0);
+    let data_ptr = global.lock();
+    let data_size = global.size();
+    let bytes = unsafe { std::slice::from_raw_parts(data_ptr as *const u8, data_size) };
+    let data = String::from_utf8_lossy(bytes);
+    let offset = |name: &str| -> Option<usize> {
+        let value = data
+            .split("\r\n")
+            .find_map(|line| line.strip_prefix(name))?;
+        value.trim().parse().ok()
+    };
+    let start_fragment = offset("StartFragment:")?;
+    let end_fragment = offset("EndFragment:")?;
+    bytes
+        .get(start_fragment..end_fragment)
+        .map(|fragment| String::from_utf8_lossy(fragment).into_owned())
+}
+
 fn read_image_from_clipboard(format: u32) -> Option<ClipboardEntry> {
     let image_format = format_number_to_image_format(format)?;
     read_image_for_type(format, *image_format)
@@ -340,14 +415,11 @@ fn read_files_from_clipboard() -> Option<ClipboardEntry> {
     let global =
         SmartGlobal::from_raw_ptr(unsafe { GetClipboardData(CF_HDROP.0 as u32).log_err() }?.0);
     let hdrop = HDROP(global.lock());
-    let mut filenames = String::new();
+    let mut paths = SmallVec::new();
     with_file_names(hdrop, |file_name| {
-        filenames.push_str(&file_name);
+        paths.push(PathBuf::from(file_name));
     });
-    Some(ClipboardEntry::String(ClipboardString {
-        text: filenames,
-        metadata: None,
-    }))
+    Some(ClipboardEntry::ExternalPaths(ExternalPaths(paths)))
 }
 
 impl From<ImageFormat> for image::ImageFormat {
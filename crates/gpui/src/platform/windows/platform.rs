@@ -22,7 +22,9 @@ use windows::{
             Imaging::{CLSID_WICImagingFactory, IWICImagingFactory},
         },
         Security::Credentials::*,
-        System::{Com::*, LibraryLoader::*, Ole::*, SystemInformation::*, Threading::*},
+        System::{
+            Com::*, LibraryLoader::*, Ole::*, Registry::*, SystemInformation::*, Threading::*,
+        },
         UI::{Input::KeyboardAndMouse::*, Shell::*, WindowsAndMessaging::*},
     },
     UI::ViewManagement::UISettings,
@@ -596,9 +598,42 @@ impl Platform for WindowsPlatform {
         })
     }
 
-    fn register_url_scheme(&self, _: &str) -> Task<anyhow::Result<()>> {
-        Task::ready(Err(anyhow!("register_url_scheme unimplemented")))
+    fn register_url_scheme(&self, scheme: &str) -> Task<anyhow::Result<()>> {
+        let scheme = scheme.to_string();
+        self.background_executor().spawn(async move {
+            let exe_path = std::env::current_exe()?;
+            let key = format!("Software\\Classes\\{scheme}");
+            set_registry_value(&key, None, &format!("URL:{scheme}"))?;
+            set_registry_value(&key, Some("URL Protocol"), "")?;
+            set_registry_value(
+                &format!("{key}\\shell\\open\\command"),
+                None,
+                &format!("\"{}\" \"%1\"", exe_path.display()),
+            )?;
+            Ok(())
+        })
+    }
+}
+
+/// Sets a string value of a key under `HKEY_CURRENT_USER`, creating the key if needed.
+fn set_registry_value(key: &str, value_name: Option<&str>, value: &str) -> Result<()> {
+    let data = value.encode_utf16().chain(Some(0)).collect_vec();
+    let value_name = value_name.map(HSTRING::from);
+    unsafe {
+        RegSetKeyValueW(
+            HKEY_CURRENT_USER,
+            &HSTRING::from(key),
+            value_name
+                .as_ref()
+                .map_or(PCWSTR::null(), |name| PCWSTR(name.as_ptr())),
+            REG_SZ.0,
+            Some(data.as_ptr().cast()),
+            (data.len() * std::mem::size_of::<u16>()) as u32,
+        )
+        .ok()
+        .context("writing registry value")?;
     }
+    Ok(())
 }
 
 impl Drop for WindowsPlatform {
@@ -0,0 +1,222 @@
+//! Supervision of the tasks running long-lived background work.
+//!
+//! A task is supervised by running its future with [`supervised`], which catches the panics of
+//! the future and resolves to a [`TaskPanic`] instead. Its owner then recovers from the panic,
+//! either by restarting the task from its latest state, as decided by a [`TaskSupervisor`], or
+//! by degrading the feature the task implemented, and reports it with
+//! [`App::report_task_panic`], which logs it and notifies the handlers registered with
+//! [`App::on_task_panic`].
+//!
+//! Panic hooks still run for the panics of supervised tasks, before they're caught, and can use
+//! [`supervised_task_name`] to tell them apart from the ones that aren't recovered from.
+
+use crate::{App, Global, SharedString};
+use std::{
+    any::Any,
+    cell::RefCell,
+    fmt,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+thread_local! {
+    static SUPERVISED_TASK: RefCell<Option<SharedString>> = const { RefCell::new(None) };
+}
+
+/// Returns the name of the supervised task being polled on the current thread, if any.
+pub fn supervised_task_name() -> Option<SharedString> {
+    SUPERVISED_TASK.with_borrow(|task_name| task_name.clone())
+}
+
+/// Runs the given future as the supervised task with the given name, resolving to its output,
+/// or to the panic that stopped it.
+pub fn supervised<F: Future>(
+    task_name: impl Into<SharedString>,
+    future: F,
+) -> impl Future<Output = Result<F::Output, TaskPanic>> {
+    Supervised {
+        task_name: task_name.into(),
+        future: Box::pin(future),
+    }
+}
+
+struct Supervised<F> {
+    task_name: SharedString,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Supervised<F> {
+    type Output = Result<F::Output, TaskPanic>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let task_name = self.task_name.clone();
+        let outer_task_name = SUPERVISED_TASK.replace(Some(task_name.clone()));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| self.future.as_mut().poll(cx)));
+        SUPERVISED_TASK.set(outer_task_name);
+        match result {
+            Ok(poll) => poll.map(Ok),
+            Err(payload) => Poll::Ready(Err(TaskPanic {
+                task_name,
+                message: panic_message(payload.as_ref()),
+            })),
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Box<Any>".to_string())
+}
+
+/// The panic that stopped a supervised task.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TaskPanic {
+    /// The name the task was supervised with.
+    pub task_name: SharedString,
+    /// The message of the panic.
+    pub message: String,
+}
+
+impl fmt::Display for TaskPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} panicked: {}", self.task_name, self.message)
+    }
+}
+
+impl std::error::Error for TaskPanic {}
+
+/// How the owner of a supervised task recovered from its panic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskRecovery {
+    /// The task was restarted from the latest state of its owner.
+    Restarted,
+    /// The task wasn't restarted, and the feature it implemented is unavailable.
+    Degraded,
+}
+
+/// Decides whether to restart a supervised task after a panic: it's restarted until it
+/// panicked [`Self::MAX_RESTARTS`] times without completing in between, since it would then
+/// most likely panic again, after which its feature is degraded.
+#[derive(Debug, Default)]
+pub struct TaskSupervisor {
+    restarts: usize,
+}
+
+impl TaskSupervisor {
+    /// The number of times a task is restarted after panicking in a row.
+    pub const MAX_RESTARTS: usize = 3;
+
+    /// Records that the task completed, which resets its restarts.
+    pub fn task_completed(&mut self) {
+        self.restarts = 0;
+    }
+
+    /// Reports the panic of the task, returning whether it is to be restarted.
+    pub fn task_panicked(&mut self, panic: &TaskPanic, cx: &mut App) -> TaskRecovery {
+        let recovery = if self.restarts < Self::MAX_RESTARTS {
+            self.restarts += 1;
+            TaskRecovery::Restarted
+        } else {
+            TaskRecovery::Degraded
+        };
+        cx.report_task_panic(panic, recovery);
+        recovery
+    }
+}
+
+#[derive(Default)]
+struct TaskPanicHandlers(Vec<Rc<dyn Fn(&TaskPanic, TaskRecovery, &mut App)>>);
+
+impl Global for TaskPanicHandlers {}
+
+impl App {
+    /// Registers a handler called with the panics of supervised tasks, once their owners
+    /// recovered from them.
+    pub fn on_task_panic(
+        &mut self,
+        handler: impl Fn(&TaskPanic, TaskRecovery, &mut App) + 'static,
+    ) {
+        self.default_global::<TaskPanicHandlers>()
+            .0
+            .push(Rc::new(handler));
+    }
+
+    /// Logs the panic of a supervised task, and calls the handlers registered with
+    /// [`Self::on_task_panic`].
+    pub fn report_task_panic(&mut self, panic: &TaskPanic, recovery: TaskRecovery) {
+        match recovery {
+            TaskRecovery::Restarted => log::error!("{panic}, restarting it"),
+            TaskRecovery::Degraded => log::error!("{panic}, not restarting it"),
+        }
+        let handlers = self
+            .try_global::<TaskPanicHandlers>()
+            .map(|handlers| handlers.0.clone())
+            .unwrap_or_default();
+        for handler in handlers {
+            handler(panic, recovery, self);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TestAppContext;
+    use std::cell::RefCell;
+
+    #[crate::test]
+    async fn test_supervised_task_panics(cx: &mut TestAppContext) {
+        let completed = cx
+            .background_executor
+            .spawn(supervised("completing task", async { 1 }))
+            .await;
+        assert_eq!(completed, Ok(1));
+
+        let panicked = cx
+            .background_executor
+            .spawn(supervised("panicking task", async {
+                assert_eq!(supervised_task_name(), Some("panicking task".into()));
+                panic!("oh no");
+            }))
+            .await;
+        let panic = panicked.unwrap_err();
+        assert_eq!(panic.task_name, "panicking task");
+        assert_eq!(panic.message, "oh no");
+        assert_eq!(supervised_task_name(), None);
+
+        let reports = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let reports = reports.clone();
+            cx.on_task_panic(move |panic, recovery, _| {
+                reports
+                    .borrow_mut()
+                    .push((panic.task_name.clone(), recovery));
+            });
+
+            let mut supervisor = TaskSupervisor::default();
+            for _ in 0..TaskSupervisor::MAX_RESTARTS {
+                assert_eq!(
+                    supervisor.task_panicked(&panic, cx),
+                    TaskRecovery::Restarted
+                );
+            }
+            assert_eq!(supervisor.task_panicked(&panic, cx), TaskRecovery::Degraded);
+            supervisor.task_completed();
+            assert_eq!(
+                supervisor.task_panicked(&panic, cx),
+                TaskRecovery::Restarted
+            );
+        });
+        assert_eq!(reports.borrow().len(), TaskSupervisor::MAX_RESTARTS + 2);
+        assert_eq!(
+            reports.borrow()[TaskSupervisor::MAX_RESTARTS],
+            ("panicking task".into(), TaskRecovery::Degraded)
+        );
+    }
+}
@@ -29,7 +29,10 @@ use std::{
     fmt::{Debug, Display, Formatter},
     hash::{Hash, Hasher},
     ops::{Deref, DerefMut, Range},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicUsize, Ordering::SeqCst},
+        Arc,
+    },
 };
 
 /// An opaque identifier for a specific font.
@@ -43,6 +46,14 @@ pub struct FontFamilyId(pub usize);
 
 pub(crate) const SUBPIXEL_VARIANTS: u8 = 4;
 
+/// The family tried last when resolving a font, which is installed with the platform.
+#[cfg(target_os = "macos")]
+const LAST_RESORT_FONT_FAMILY: &str = ".SystemUIFont";
+#[cfg(target_os = "windows")]
+const LAST_RESORT_FONT_FAMILY: &str = "Segoe UI";
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const LAST_RESORT_FONT_FAMILY: &str = "Liberation Sans";
+
 /// The GPUI text rendering sub system.
 pub struct TextSystem {
     platform_text_system: Arc<dyn PlatformTextSystem>,
@@ -52,6 +63,9 @@ pub struct TextSystem {
     wrapper_pool: Mutex<FxHashMap<FontIdWithSize, Vec<LineWrapper>>>,
     font_runs_pool: Mutex<Vec<Vec<FontRun>>>,
     fallback_font_stack: SmallVec<[Font; 2]>,
+    script_fallbacks: RwLock<FxHashMap<FontScript, Vec<SharedString>>>,
+    script_fallback_font_ids: RwLock<FxHashMap<FontId, ScriptFallbackFontIds>>,
+    script_fallbacks_version: AtomicUsize,
 }
 
 impl TextSystem {
@@ -73,6 +87,9 @@ impl TextSystem {
                 font("Noto Sans"), // KDE
                 font("DejaVu Sans")
             ],
+            script_fallbacks: RwLock::default(),
+            script_fallback_font_ids: RwLock::default(),
+            script_fallbacks_version: AtomicUsize::new(0),
         }
     }
 
@@ -138,23 +155,127 @@ impl TextSystem {
     ///
     /// Panics if the font and none of the fallbacks can be resolved.
     pub fn resolve_font(&self, font: &Font) -> FontId {
+        self.try_resolve_font(font)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Resolves the specified font, trying the font's own fallbacks, then the
+    /// default font stack and finally a font of the platform if it fails to load.
+    pub fn try_resolve_font(&self, font: &Font) -> Result<FontId> {
         if let Ok(font_id) = self.font_id(font) {
-            return font_id;
+            return Ok(font_id);
         }
-        for fallback in &self.fallback_font_stack {
-            if let Ok(font_id) = self.font_id(fallback) {
-                return font_id;
+
+        let configured_fallbacks = font
+            .fallbacks
+            .iter()
+            .flat_map(|fallbacks| fallbacks.fallback_list())
+            .map(|family| Font {
+                family: family.clone().into(),
+                ..font.clone()
+            });
+        let fallbacks = configured_fallbacks
+            .chain(self.fallback_font_stack.iter().cloned())
+            .chain(std::iter::once(self::font(LAST_RESORT_FONT_FAMILY)));
+        for fallback in fallbacks {
+            if let Ok(font_id) = self.font_id(&fallback) {
+                return Ok(font_id);
             }
         }
 
-        panic!(
+        Err(anyhow!(
             "failed to resolve font '{}' or any of the fallbacks: {}",
             font.family,
-            self.fallback_font_stack
+            font.fallbacks
                 .iter()
-                .map(|fallback| &fallback.family)
+                .flat_map(|fallbacks| fallbacks.fallback_list())
+                .map(|family| family.as_str())
+                .chain(
+                    self.fallback_font_stack
+                        .iter()
+                        .map(|fallback| fallback.family.as_ref())
+                )
+                .chain(std::iter::once(LAST_RESORT_FONT_FAMILY))
                 .join(", ")
-        );
+        ))
+    }
+
+    /// Sets the font families used, in order of preference, for characters of a
+    /// given script that the primary font has no glyphs for.
+    pub fn set_script_fallbacks(&self, fallbacks: FxHashMap<FontScript, Vec<SharedString>>) {
+        let mut script_fallbacks = self.script_fallbacks.write();
+        if *script_fallbacks != fallbacks {
+            *script_fallbacks = fallbacks;
+            self.script_fallback_font_ids.write().clear();
+            self.script_fallbacks_version.fetch_add(1, SeqCst);
+        }
+    }
+
+    /// Returns a number that changes whenever the script fallbacks change, so that text
+    /// shaped or wrapped with the previous fallbacks can be laid out again.
+    pub fn script_fallbacks_version(&self) -> usize {
+        self.script_fallbacks_version.load(SeqCst)
+    }
+
+    /// Returns the script fallback fonts resolved with the style of the given font.
+    pub(crate) fn script_fallback_font_ids(
+        &self,
+        font: &Font,
+        font_id: FontId,
+    ) -> ScriptFallbackFontIds {
+        if let Some(font_ids) = self.script_fallback_font_ids.read().get(&font_id) {
+            return font_ids.clone();
+        }
+
+        let font_ids = self
+            .script_fallbacks
+            .read()
+            .iter()
+            .map(|(script, families)| {
+                let font_ids = families
+                    .iter()
+                    .filter_map(|family| {
+                        self.font_id(&Font {
+                            family: family.clone(),
+                            ..font.clone()
+                        })
+                        .ok()
+                    })
+                    .collect();
+                (*script, font_ids)
+            })
+            .collect();
+        let font_ids = ScriptFallbackFontIds(Arc::new(font_ids));
+        self.script_fallback_font_ids
+            .write()
+            .insert(font_id, font_ids.clone());
+        font_ids
+    }
+
+    /// Appends runs covering `text` in the given font, switching to a script
+    /// fallback font for characters the font has no glyphs for.
+    pub(crate) fn push_font_runs(&self, text: &str, font: &Font, font_runs: &mut Vec<FontRun>) {
+        fn push_run(font_runs: &mut Vec<FontRun>, len: usize, font_id: FontId) {
+            if let Some(last_run) = font_runs.last_mut() {
+                if last_run.font_id == font_id {
+                    last_run.len += len;
+                    return;
+                }
+            }
+            font_runs.push(FontRun { len, font_id });
+        }
+
+        let font_id = self.resolve_font(font);
+        let fallbacks = self.script_fallback_font_ids(font, font_id);
+        if fallbacks.is_empty() {
+            push_run(font_runs, text.len(), font_id);
+            return;
+        }
+
+        for c in text.chars() {
+            let font_id = fallbacks.font_id_for_char(font_id, c, &*self.platform_text_system);
+            push_run(font_runs, c.len_utf8(), font_id);
+        }
     }
 
     /// Get the bounding box for the given font and font size.
@@ -266,19 +387,31 @@ impl TextSystem {
 
     /// Returns a handle to a line wrapper, for the given font and font size.
     pub fn line_wrapper(self: &Arc<Self>, font: Font, font_size: Pixels) -> LineWrapperHandle {
+        self.try_line_wrapper(font, font_size)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Like [`Self::line_wrapper`], but returns an error instead of panicking
+    /// when neither the font nor any of its fallbacks can be resolved.
+    pub fn try_line_wrapper(
+        self: &Arc<Self>,
+        font: Font,
+        font_size: Pixels,
+    ) -> Result<LineWrapperHandle> {
+        let font_id = self.try_resolve_font(&font)?;
         let lock = &mut self.wrapper_pool.lock();
-        let font_id = self.resolve_font(&font);
         let wrappers = lock
             .entry(FontIdWithSize { font_id, font_size })
             .or_default();
-        let wrapper = wrappers.pop().unwrap_or_else(|| {
+        let mut wrapper = wrappers.pop().unwrap_or_else(|| {
             LineWrapper::new(font_id, font_size, self.platform_text_system.clone())
         });
+        wrapper.set_script_fallbacks(self.script_fallback_font_ids(&font, font_id));
 
-        LineWrapperHandle {
+        Ok(LineWrapperHandle {
             wrapper: Some(wrapper),
             text_system: self.clone(),
-        }
+        })
     }
 
     /// Get the rasterized size and location of a specific, rendered glyph.
@@ -320,6 +453,11 @@ impl WindowTextSystem {
         }
     }
 
+    /// Sets the scale factor of the window, which the laid out lines are keyed on.
+    pub(crate) fn set_scale_factor(&self, scale_factor: f32) {
+        self.line_layout_cache.set_scale_factor(scale_factor)
+    }
+
     pub(crate) fn layout_index(&self) -> LineLayoutIndex {
         self.line_layout_cache.layout_index()
     }
@@ -332,6 +470,17 @@ impl WindowTextSystem {
         self.line_layout_cache.truncate_layouts(index)
     }
 
+    /// The number of laid out lines cached for the window.
+    pub fn cached_line_count(&self) -> usize {
+        self.line_layout_cache.cached_line_count()
+    }
+
+    /// Drops the cached layouts of the lines drawn in the last frames, which the next frame would
+    /// reuse, to free their memory. The next frame lays its lines out again.
+    pub fn evict_line_layouts(&self) {
+        self.line_layout_cache.evict()
+    }
+
     /// Shape the given line, at the given font_size, for painting to the screen.
     /// Subsets of the line can be styled independently with the `runs` parameter.
     ///
@@ -401,7 +550,6 @@ impl WindowTextSystem {
         let mut process_line = |line_text: SharedString| {
             let line_end = line_start + line_text.len();
 
-            let mut last_font: Option<Font> = None;
             let mut decoration_runs = SmallVec::<[DecorationRun; 32]>::new();
             let mut run_start = line_start;
             while run_start < line_end {
@@ -411,15 +559,8 @@ impl WindowTextSystem {
 
                 let run_len_within_line = cmp::min(line_end, run_start + run.len) - run_start;
 
-                if last_font == Some(run.font.clone()) {
-                    font_runs.last_mut().unwrap().len += run_len_within_line;
-                } else {
-                    last_font = Some(run.font.clone());
-                    font_runs.push(FontRun {
-                        len: run_len_within_line,
-                        font_id: self.resolve_font(&run.font),
-                    });
-                }
+                let run_text = &line_text[run_start - line_start..][..run_len_within_line];
+                self.push_font_runs(run_text, &run.font, &mut font_runs);
 
                 if decoration_runs.last().map_or(false, |last_run| {
                     last_run.color == run.color
@@ -516,18 +657,11 @@ impl WindowTextSystem {
         SharedString: From<Text>,
     {
         let mut font_runs = self.font_runs_pool.lock().pop().unwrap_or_default();
+        let mut run_start = 0;
         for run in runs.iter() {
-            let font_id = self.resolve_font(&run.font);
-            if let Some(last_run) = font_runs.last_mut() {
-                if last_run.font_id == font_id {
-                    last_run.len += run.len;
-                    continue;
-                }
-            }
-            font_runs.push(FontRun {
-                len: run.len,
-                font_id,
-            });
+            let run_text = &text.as_ref()[run_start..run_start + run.len];
+            self.push_font_runs(run_text, &run.font, &mut font_runs);
+            run_start += run.len;
         }
 
         let layout = self
@@ -541,6 +675,42 @@ impl WindowTextSystem {
     }
 }
 
+/// The fonts to use for characters of each [`FontScript`] that a primary font
+/// has no glyphs for, shared by shaping and wrapping so that both agree.
+#[derive(Clone, Default)]
+pub(crate) struct ScriptFallbackFontIds(Arc<FxHashMap<FontScript, SmallVec<[FontId; 2]>>>);
+
+impl ScriptFallbackFontIds {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.values().all(|font_ids| font_ids.is_empty())
+    }
+
+    pub(crate) fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Returns the font that should be used to render the given character,
+    /// preferring the primary font whenever it has a glyph for it.
+    pub(crate) fn font_id_for_char(
+        &self,
+        font_id: FontId,
+        c: char,
+        text_system: &dyn PlatformTextSystem,
+    ) -> FontId {
+        let Some(fallbacks) = FontScript::for_char(c).and_then(|script| self.0.get(&script)) else {
+            return font_id;
+        };
+        if fallbacks.is_empty() || text_system.glyph_for_char(font_id, c).is_some() {
+            return font_id;
+        }
+        fallbacks
+            .iter()
+            .copied()
+            .find(|fallback| text_system.glyph_for_char(*fallback, c).is_some())
+            .unwrap_or(font_id)
+    }
+}
+
 #[derive(Hash, Eq, PartialEq)]
 struct FontIdWithSize {
     font_id: FontId,
@@ -19,3 +19,77 @@ impl FontFallbacks {
         FontFallbacks(Arc::new(fonts))
     }
 }
+
+/// A group of related writing systems that can be assigned its own fallback
+/// fonts, for characters the primary font has no glyphs for.
+#[derive(
+    Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Deserialize, Serialize, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum FontScript {
+    /// Chinese, Japanese and Korean characters, including full-width forms.
+    Cjk,
+    /// Pictographic emoji.
+    Emoji,
+    /// Arrows, mathematical operators, box drawing and other technical symbols.
+    Symbols,
+    /// The Arabic script.
+    Arabic,
+    /// The Hebrew script.
+    Hebrew,
+    /// The Cyrillic script.
+    Cyrillic,
+    /// The Greek script.
+    Greek,
+    /// The Devanagari script.
+    Devanagari,
+    /// The Thai script.
+    Thai,
+}
+
+impl FontScript {
+    /// Returns the script the given character belongs to, if it belongs to one
+    /// that supports dedicated fallback fonts.
+    pub fn for_char(c: char) -> Option<Self> {
+        if c.is_ascii() {
+            return None;
+        }
+
+        let script = match c as u32 {
+            0x0370..=0x03FF | 0x1F00..=0x1FFF => Self::Greek,
+            0x0400..=0x052F => Self::Cyrillic,
+            0x0590..=0x05FF | 0xFB1D..=0xFB4F => Self::Hebrew,
+            0x0600..=0x06FF | 0x0750..=0x077F | 0x08A0..=0x08FF | 0xFB50..=0xFDFF => Self::Arabic,
+            0xFE70..=0xFEFF => Self::Arabic,
+            0x0900..=0x097F => Self::Devanagari,
+            0x0E00..=0x0E7F => Self::Thai,
+            0x2190..=0x2BFF => Self::Symbols,
+            0x2E80..=0x2FDF | 0x3000..=0x33FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF => Self::Cjk,
+            0xA960..=0xA97F | 0xAC00..=0xD7AF | 0xF900..=0xFAFF | 0xFE30..=0xFE4F => Self::Cjk,
+            0xFF00..=0xFFEF | 0x20000..=0x3FFFF => Self::Cjk,
+            0x1F000..=0x1FAFF => Self::Emoji,
+            _ => return None,
+        };
+        Some(script)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_font_script_for_char() {
+        assert_eq!(FontScript::for_char('a'), None);
+        assert_eq!(FontScript::for_char('é'), None);
+        assert_eq!(FontScript::for_char('中'), Some(FontScript::Cjk));
+        assert_eq!(FontScript::for_char('か'), Some(FontScript::Cjk));
+        assert_eq!(FontScript::for_char('한'), Some(FontScript::Cjk));
+        assert_eq!(FontScript::for_char('😀'), Some(FontScript::Emoji));
+        assert_eq!(FontScript::for_char('→'), Some(FontScript::Symbols));
+        assert_eq!(FontScript::for_char('ж'), Some(FontScript::Cyrillic));
+        assert_eq!(FontScript::for_char('λ'), Some(FontScript::Greek));
+        assert_eq!(FontScript::for_char('ש'), Some(FontScript::Hebrew));
+        assert_eq!(FontScript::for_char('ب'), Some(FontScript::Arabic));
+    }
+}
@@ -59,6 +59,35 @@ impl ShapedLine {
         self
     }
 
+    /// Returns this line with `inserted` placed at `index`, without shaping the line again.
+    /// The inserted text takes the decorations of the text before it. See
+    /// [`LineLayout::with_insertion`].
+    pub fn with_insertion(&self, index: usize, inserted: &ShapedLine) -> ShapedLine {
+        let text = format!(
+            "{}{}{}",
+            &self.text[..index],
+            inserted.text,
+            &self.text[index..]
+        );
+
+        let mut decoration_runs = self.decoration_runs.clone();
+        let mut run_end = 0;
+        let extended_run = decoration_runs.iter_mut().find(|run| {
+            run_end += run.len as usize;
+            run_end >= index
+        });
+        match extended_run {
+            Some(run) => run.len += inserted.len() as u32,
+            None => decoration_runs.extend(inserted.decoration_runs.iter().cloned()),
+        }
+
+        ShapedLine {
+            layout: Arc::new(self.layout.with_insertion(index, &inserted.layout)),
+            text: text.into(),
+            decoration_runs,
+        }
+    }
+
     /// Paint the line of text to the window.
     pub fn paint(
         &self,
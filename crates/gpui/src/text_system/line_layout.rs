@@ -6,7 +6,10 @@ use std::{
     borrow::Borrow,
     hash::{Hash, Hasher},
     ops::Range,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU32, Ordering::SeqCst},
+        Arc,
+    },
 };
 
 use super::LineWrapper;
@@ -125,6 +128,75 @@ impl LineLayout {
         None
     }
 
+    /// Returns this layout with the glyphs of `inserted` placed at `index`, and the glyphs
+    /// following them shifted by the width of the insertion.
+    ///
+    /// Kerning and shaping across the boundaries of the insertion are ignored, which is good
+    /// enough to show typed text until the line is laid out again.
+    pub fn with_insertion(&self, index: usize, inserted: &LineLayout) -> LineLayout {
+        let shift =
+            |run: &ShapedRun, glyphs: &[ShapedGlyph], dx: Pixels, dindex: usize| ShapedRun {
+                font_id: run.font_id,
+                glyphs: glyphs
+                    .iter()
+                    .map(|glyph| ShapedGlyph {
+                        position: point(glyph.position.x + dx, glyph.position.y),
+                        index: glyph.index + dindex,
+                        ..glyph.clone()
+                    })
+                    .collect(),
+            };
+        let x = self.x_for_index(index);
+        let inserted_runs = || {
+            inserted
+                .runs
+                .iter()
+                .map(move |run| shift(run, &run.glyphs, x, index))
+        };
+
+        let mut runs = Vec::with_capacity(self.runs.len() + inserted.runs.len() + 1);
+        let mut did_insert = false;
+        for run in &self.runs {
+            if did_insert {
+                runs.push(shift(run, &run.glyphs, inserted.width, inserted.len));
+                continue;
+            }
+
+            let split = run
+                .glyphs
+                .iter()
+                .position(|glyph| glyph.index >= index)
+                .unwrap_or(run.glyphs.len());
+            if split == run.glyphs.len() {
+                runs.push(run.clone());
+                continue;
+            }
+            if split > 0 {
+                runs.push(shift(run, &run.glyphs[..split], px(0.), 0));
+            }
+            runs.extend(inserted_runs());
+            runs.push(shift(
+                run,
+                &run.glyphs[split..],
+                inserted.width,
+                inserted.len,
+            ));
+            did_insert = true;
+        }
+        if !did_insert {
+            runs.extend(inserted_runs());
+        }
+
+        LineLayout {
+            font_size: self.font_size,
+            width: self.width + inserted.width,
+            ascent: self.ascent.max(inserted.ascent),
+            descent: self.descent.max(inserted.descent),
+            runs,
+            len: self.len + inserted.len,
+        }
+    }
+
     fn compute_wrap_boundaries(
         &self,
         text: &str,
@@ -393,6 +465,10 @@ pub(crate) struct LineLayoutCache {
     previous_frame: Mutex<FrameCache>,
     current_frame: RwLock<FrameCache>,
     platform_text_system: Arc<dyn PlatformTextSystem>,
+    /// The bits of the scale factor of the window the lines are laid out for. Layouts are keyed
+    /// on it, so that lines are laid out again when the window moves to a display with another
+    /// scale factor.
+    scale_factor: AtomicU32,
 }
 
 #[derive(Default)]
@@ -415,9 +491,14 @@ impl LineLayoutCache {
             previous_frame: Mutex::default(),
             current_frame: RwLock::default(),
             platform_text_system,
+            scale_factor: AtomicU32::new(1f32.to_bits()),
         }
     }
 
+    pub fn set_scale_factor(&self, scale_factor: f32) {
+        self.scale_factor.store(scale_factor.to_bits(), SeqCst);
+    }
+
     pub fn layout_index(&self) -> LineLayoutIndex {
         let frame = self.current_frame.read();
         LineLayoutIndex {
@@ -455,6 +536,29 @@ impl LineLayoutCache {
             .truncate(index.wrapped_lines_index);
     }
 
+    /// The number of laid out lines, wrapped or not, cached for the current frame and kept from
+    /// the previous one.
+    pub fn cached_line_count(&self) -> usize {
+        let previous_frame = self.previous_frame.lock();
+        let current_frame = self.current_frame.read();
+        previous_frame.lines.len()
+            + previous_frame.wrapped_lines.len()
+            + current_frame.lines.len()
+            + current_frame.wrapped_lines.len()
+    }
+
+    /// Drops the cached layouts of the lines of the current and the previous frame. The keys of
+    /// the lines the frames used are kept, so that views reusing their layouts find nothing to
+    /// reuse, rather than indices out of range, and lay their lines out again.
+    pub fn evict(&self) {
+        let mut previous_frame = self.previous_frame.lock();
+        let mut current_frame = self.current_frame.write();
+        for frame in [&mut *previous_frame, &mut *current_frame] {
+            frame.lines = FxHashMap::default();
+            frame.wrapped_lines = FxHashMap::default();
+        }
+    }
+
     pub fn finish_frame(&self) {
         let mut prev_frame = self.previous_frame.lock();
         let mut curr_frame = self.current_frame.write();
@@ -477,11 +581,13 @@ impl LineLayoutCache {
         Text: AsRef<str>,
         SharedString: From<Text>,
     {
+        let scale_factor = self.scale_factor.load(SeqCst);
         let key = &CacheKeyRef {
             text: text.as_ref(),
             font_size,
             runs,
             wrap_width,
+            scale_factor,
         } as &dyn AsCacheKeyRef;
 
         let current_frame = self.current_frame.upgradable_read();
@@ -516,6 +622,7 @@ impl LineLayoutCache {
                 font_size,
                 runs: SmallVec::from(runs),
                 wrap_width,
+                scale_factor,
             });
 
             let mut current_frame = self.current_frame.write();
@@ -538,11 +645,13 @@ impl LineLayoutCache {
         Text: AsRef<str>,
         SharedString: From<Text>,
     {
+        let scale_factor = self.scale_factor.load(SeqCst);
         let key = &CacheKeyRef {
             text: text.as_ref(),
             font_size,
             runs,
             wrap_width: None,
+            scale_factor,
         } as &dyn AsCacheKeyRef;
 
         let current_frame = self.current_frame.upgradable_read();
@@ -566,6 +675,7 @@ impl LineLayoutCache {
                 font_size,
                 runs: SmallVec::from(runs),
                 wrap_width: None,
+                scale_factor,
             });
             current_frame.lines.insert(key.clone(), layout.clone());
             current_frame.used_lines.push(key);
@@ -591,6 +701,7 @@ struct CacheKey {
     font_size: Pixels,
     runs: SmallVec<[FontRun; 1]>,
     wrap_width: Option<Pixels>,
+    scale_factor: u32,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
@@ -599,6 +710,7 @@ struct CacheKeyRef<'a> {
     font_size: Pixels,
     runs: &'a [FontRun],
     wrap_width: Option<Pixels>,
+    scale_factor: u32,
 }
 
 impl<'a> PartialEq for (dyn AsCacheKeyRef + 'a) {
@@ -622,6 +734,7 @@ impl AsCacheKeyRef for CacheKey {
             font_size: self.font_size,
             runs: self.runs.as_slice(),
             wrap_width: self.wrap_width,
+            scale_factor: self.scale_factor,
         }
     }
 }
@@ -649,3 +762,62 @@ impl<'a> AsCacheKeyRef for CacheKeyRef<'a> {
         *self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(text_len: usize, advance: f32, first_glyph: u32) -> LineLayout {
+        LineLayout {
+            font_size: px(10.),
+            width: px(advance * text_len as f32),
+            ascent: px(8.),
+            descent: px(2.),
+            runs: vec![ShapedRun {
+                font_id: FontId(0),
+                glyphs: (0..text_len)
+                    .map(|index| ShapedGlyph {
+                        id: GlyphId(first_glyph + index as u32),
+                        position: point(px(advance * index as f32), px(0.)),
+                        index,
+                        is_emoji: false,
+                    })
+                    .collect(),
+            }],
+            len: text_len,
+        }
+    }
+
+    #[test]
+    fn test_layout_with_insertion() {
+        let line = layout(4, 10., 0);
+        let inserted = layout(2, 5., 100);
+
+        let spliced = line.with_insertion(1, &inserted);
+        let glyphs = spliced
+            .runs
+            .iter()
+            .flat_map(|run| &run.glyphs)
+            .map(|glyph| (glyph.id.0, glyph.index, glyph.position.x))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            glyphs,
+            [
+                (0, 0, px(0.)),
+                (100, 1, px(10.)),
+                (101, 2, px(15.)),
+                (1, 3, px(20.)),
+                (2, 4, px(30.)),
+                (3, 5, px(40.)),
+            ]
+        );
+        assert_eq!(spliced.width, px(50.));
+        assert_eq!(spliced.len, 6);
+        assert_eq!(spliced.x_for_index(3), px(20.));
+
+        // Insertions at the end are appended.
+        let spliced = line.with_insertion(4, &inserted);
+        assert_eq!(spliced.x_for_index(5), px(45.));
+        assert_eq!(spliced.index_for_x(px(41.)), Some(4));
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{px, FontId, FontRun, Pixels, PlatformTextSystem, SharedString, TextRun};
+use crate::{
+    px, text_system::ScriptFallbackFontIds, FontId, FontRun, Pixels, PlatformTextSystem,
+    SharedString, TextRun,
+};
 use collections::HashMap;
 use std::{iter, sync::Arc};
 
@@ -7,6 +10,7 @@ pub struct LineWrapper {
     platform_text_system: Arc<dyn PlatformTextSystem>,
     pub(crate) font_id: FontId,
     pub(crate) font_size: Pixels,
+    script_fallbacks: ScriptFallbackFontIds,
     cached_ascii_char_widths: [Option<Pixels>; 128],
     cached_other_char_widths: HashMap<char, Pixels>,
 }
@@ -24,11 +28,21 @@ impl LineWrapper {
             platform_text_system: text_system,
             font_id,
             font_size,
+            script_fallbacks: ScriptFallbackFontIds::default(),
             cached_ascii_char_widths: [None; 128],
             cached_other_char_widths: HashMap::default(),
         }
     }
 
+    /// Measures characters the font has no glyphs for using the same script
+    /// fallback fonts as shaping, so wrap boundaries match the rendered text.
+    pub(crate) fn set_script_fallbacks(&mut self, script_fallbacks: ScriptFallbackFontIds) {
+        if !self.script_fallbacks.ptr_eq(&script_fallbacks) {
+            self.script_fallbacks = script_fallbacks;
+            self.cached_other_char_widths.clear();
+        }
+    }
+
     /// Wrap a line of text to the given width with this wrapper's font and font size.
     pub fn wrap_line<'a>(
         &'a mut self,
@@ -98,6 +112,34 @@ impl LineWrapper {
         })
     }
 
+    /// Wrap a line of text at a fixed number of characters per row, treating every
+    /// character as wide as `m` in this wrapper's font.
+    ///
+    /// Unlike [`Self::wrap_line`], this neither measures individual characters nor
+    /// looks for word boundaries, so it runs in constant time per character and is
+    /// suitable for pathologically long lines where only an approximate layout is
+    /// affordable.
+    pub fn wrap_line_fixed_width<'a>(
+        &'a mut self,
+        line: &'a str,
+        wrap_width: Pixels,
+    ) -> impl Iterator<Item = Boundary> + 'a {
+        let columns_per_row = (wrap_width / self.width_for_char('m')).floor().max(1.) as usize;
+        let mut column = 0;
+        line.char_indices().filter_map(move |(ix, c)| {
+            if c == '\n' {
+                return None;
+            }
+            if column == columns_per_row {
+                column = 1;
+                Some(Boundary::new(ix, 0))
+            } else {
+                column += 1;
+                None
+            }
+        })
+    }
+
     /// Truncate a line of text to the given width with this wrapper's font and font size.
     pub fn truncate_line(
         &mut self,
@@ -183,13 +225,16 @@ impl LineWrapper {
     fn compute_width_for_char(&self, c: char) -> Pixels {
         let mut buffer = [0; 4];
         let buffer = c.encode_utf8(&mut buffer);
+        let font_id =
+            self.script_fallbacks
+                .font_id_for_char(self.font_id, c, &*self.platform_text_system);
         self.platform_text_system
             .layout_line(
                 buffer,
                 self.font_size,
                 &[FontRun {
                     len: buffer.len(),
-                    font_id: self.font_id,
+                    font_id,
                 }],
             )
             .width
@@ -272,6 +317,34 @@ mod tests {
             .collect()
     }
 
+    #[test]
+    fn test_wrap_line_fixed_width() {
+        let mut wrapper = build_wrapper();
+
+        assert_eq!(
+            wrapper
+                .wrap_line_fixed_width("aa bbb cccc ddddd eeee", px(72.))
+                .collect::<Vec<_>>(),
+            &[
+                Boundary::new(7, 0),
+                Boundary::new(14, 0),
+                Boundary::new(21, 0)
+            ],
+        );
+        assert_eq!(
+            wrapper
+                .wrap_line_fixed_width("aaaaaaa\n", px(72.))
+                .collect::<Vec<_>>(),
+            &[],
+        );
+        assert_eq!(
+            wrapper
+                .wrap_line_fixed_width("aaa", px(1.))
+                .collect::<Vec<_>>(),
+            &[Boundary::new(1, 0), Boundary::new(2, 0)],
+        );
+    }
+
     #[test]
     fn test_wrap_line() {
         let mut wrapper = build_wrapper();
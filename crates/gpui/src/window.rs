@@ -633,6 +633,7 @@ pub struct Window {
     mouse_hit_test: HitTest,
     modifiers: Modifiers,
     scale_factor: f32,
+    pub(crate) scale_factor_observers: SubscriberSet<(), AnyObserver>,
     pub(crate) bounds_observers: SubscriberSet<(), AnyObserver>,
     appearance: WindowAppearance,
     pub(crate) appearance_observers: SubscriberSet<(), AnyObserver>,
@@ -646,6 +647,7 @@ pub struct Window {
     focus_enabled: bool,
     pending_input: Option<PendingInput>,
     pending_modifier: ModifierState,
+    dispatching_key_repeat: bool,
     pub(crate) pending_input_observers: SubscriberSet<(), AnyObserver>,
     prompt: Option<RenderablePromptHandle>,
 }
@@ -744,6 +746,7 @@ impl Window {
         let scale_factor = platform_window.scale_factor();
         let appearance = platform_window.appearance();
         let text_system = Arc::new(WindowTextSystem::new(cx.text_system().clone()));
+        text_system.set_scale_factor(scale_factor);
         let invalidator = WindowInvalidator::new();
         let active = Rc::new(Cell::new(platform_window.is_active()));
         let hovered = Rc::new(Cell::new(platform_window.is_hovered()));
@@ -917,6 +920,7 @@ impl Window {
             mouse_hit_test: HitTest::default(),
             modifiers,
             scale_factor,
+            scale_factor_observers: SubscriberSet::new(),
             bounds_observers: SubscriberSet::new(),
             appearance,
             appearance_observers: SubscriberSet::new(),
@@ -930,6 +934,7 @@ impl Window {
             focus_enabled: true,
             pending_input: None,
             pending_modifier: ModifierState::default(),
+            dispatching_key_repeat: false,
             pending_input_observers: SubscriberSet::new(),
             prompt: None,
         })
@@ -1300,12 +1305,20 @@ impl Window {
     }
 
     fn bounds_changed(&mut self, cx: &mut App) {
-        self.scale_factor = self.platform_window.scale_factor();
+        let scale_factor = self.platform_window.scale_factor();
+        let scale_factor_changed = scale_factor != self.scale_factor;
+        self.scale_factor = scale_factor;
         self.viewport_size = self.platform_window.content_size();
         self.display_id = self.platform_window.display().map(|display| display.id());
 
         self.refresh();
 
+        if scale_factor_changed {
+            self.text_system.set_scale_factor(scale_factor);
+            self.scale_factor_observers
+                .clone()
+                .retain(&(), |callback| callback(self, cx));
+        }
         self.bounds_observers
             .clone()
             .retain(&(), |callback| callback(self, cx));
@@ -1507,6 +1520,11 @@ impl Window {
         self.modifiers
     }
 
+    /// Whether the key event being dispatched, with its actions, repeats a key held down.
+    pub fn is_dispatching_key_repeat(&self) -> bool {
+        self.dispatching_key_repeat
+    }
+
     fn complete_frame(&self) {
         self.platform_window.completed_frame();
     }
@@ -3029,6 +3047,7 @@ impl Window {
     #[profiling::function]
     pub fn dispatch_event(&mut self, event: PlatformInput, cx: &mut App) -> DispatchEventResult {
         self.last_input_timestamp.set(Instant::now());
+        cx.record_input();
         // Handlers may set this to false by calling `stop_propagation`.
         cx.propagate_event = true;
         // Handlers may set this to true by calling `prevent_default`.
@@ -3112,7 +3131,11 @@ impl Window {
         if let Some(any_mouse_event) = event.mouse_event() {
             self.dispatch_mouse_event(any_mouse_event, cx);
         } else if let Some(any_key_event) = event.keyboard_event() {
+            self.dispatching_key_repeat = any_key_event
+                .downcast_ref::<KeyDownEvent>()
+                .map_or(false, |event| event.is_held);
             self.dispatch_key_event(any_key_event, cx);
+            self.dispatching_key_repeat = false;
         }
 
         DispatchEventResult {
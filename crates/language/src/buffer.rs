@@ -1,3 +1,10 @@
+mod file_merge;
+mod operation_log;
+mod syntax_scopes;
+
+pub use file_merge::{FileMerge, FileMergeConflicts};
+use syntax_scopes::SyntaxScopes;
+
 pub use crate::{
     diagnostic_set::DiagnosticSet,
     highlight_map::{HighlightId, HighlightMap},
@@ -6,7 +13,9 @@ pub use crate::{
 };
 use crate::{
     diagnostic_set::{DiagnosticEntry, DiagnosticGroup},
-    language_settings::{language_settings, LanguageSettings},
+    language_settings::{
+        all_language_settings, language_settings, DisabledFeatures, LanguageSettings,
+    },
     markdown::parse_markdown,
     outline::OutlineItem,
     syntax_map::{
@@ -14,7 +23,7 @@ use crate::{
         SyntaxMapMatches, SyntaxSnapshot, ToTreeSitterPoint,
     },
     task_context::RunnableRange,
-    LanguageScope, Outline, OutlineConfig, RunnableCapture, RunnableTag, TextObject,
+    LanguageScope, Modeline, Outline, OutlineConfig, RunnableCapture, RunnableTag, TextObject,
     TreeSitterOptions,
 };
 use anyhow::{anyhow, Context as _, Result};
@@ -25,8 +34,9 @@ use collections::HashMap;
 use fs::MTime;
 use futures::channel::oneshot;
 use gpui::{
-    AnyElement, App, AppContext as _, Context, Entity, EventEmitter, HighlightStyle, Pixels,
-    SharedString, StyledText, Task, TaskLabel, TextStyle, Window,
+    supervised, AnyElement, App, AppContext as _, Context, Entity, EventEmitter, HighlightStyle,
+    Pixels, SharedString, StyledText, Task, TaskLabel, TaskPanic, TaskRecovery, TaskSupervisor,
+    TextStyle, Window,
 };
 use lsp::{LanguageServerId, NumberOrString};
 use parking_lot::Mutex;
@@ -63,7 +73,7 @@ pub use text::{
     Anchor, Bias, Buffer as TextBuffer, BufferId, BufferSnapshot as TextBufferSnapshot, Edit,
     OffsetRangeExt, OffsetUtf16, Patch, Point, PointUtf16, Rope, Selection, SelectionGoal,
     Subscription, TextDimension, TextSummary, ToOffset, ToOffsetUtf16, ToPoint, ToPointUtf16,
-    Transaction, TransactionId, Unclipped,
+    Transaction, TransactionId, TransactionMetadata, Unclipped,
 };
 use theme::{ActiveTheme as _, SyntaxTheme};
 #[cfg(any(test, feature = "test-support"))]
@@ -108,12 +118,23 @@ pub struct Buffer {
     was_dirty_before_starting_transaction: Option<bool>,
     reload_task: Option<Task<Result<()>>>,
     language: Option<Arc<Language>>,
+    modeline: Option<Arc<Modeline>>,
     autoindent_requests: Vec<Arc<AutoindentRequest>>,
     pending_autoindent: Option<Task<()>>,
     sync_parse_timeout: Duration,
     syntax_map: Mutex<SyntaxMap>,
+    syntax_scopes: Mutex<Arc<SyntaxScopes>>,
     parsing_in_background: bool,
+    parse_supervisor: TaskSupervisor,
+    /// Whether parsing panicked too many times in a row, in which case the buffer isn't
+    /// parsed anymore.
+    parsing_degraded: bool,
+    syntax_tree_evicted: bool,
+    /// Whether a snapshot of the buffer, and so its syntax tree, was taken since the last
+    /// time [`Buffer::evict_syntax_tree`] was called.
+    syntax_queried: Cell<bool>,
     parse_status: (watch::Sender<ParseStatus>, watch::Receiver<ParseStatus>),
+    disabled_features: DisabledFeatures,
     non_text_state_update_count: usize,
     diagnostics: SmallVec<[(LanguageServerId, DiagnosticSet); 2]>,
     remote_selections: TreeMap<ReplicaId, SelectionSet>,
@@ -124,6 +145,12 @@ pub struct Buffer {
     deferred_ops: OperationQueue<Operation>,
     capability: Capability,
     has_conflict: bool,
+    /// The text of the file when it was last merged with the buffer's edits, which the next
+    /// merge starts from, as the buffer never had that text.
+    merged_file_text: Option<Rope>,
+    /// The modification time of the file and the version of the buffer when a merge of the
+    /// two last found conflicts.
+    conflicting_merge: Option<(Option<MTime>, clock::Global)>,
     /// Memoize calls to has_changes_since(saved_version).
     /// The contents of a cell are (self.version, has_changes) at the time of a last call.
     has_unsaved_edits: Cell<(clock::Global, bool)>,
@@ -150,7 +177,9 @@ pub struct BufferSnapshot {
     diagnostics: SmallVec<[(LanguageServerId, DiagnosticSet); 2]>,
     remote_selections: TreeMap<ReplicaId, SelectionSet>,
     language: Option<Arc<Language>>,
+    modeline: Option<Arc<Modeline>>,
     non_text_state_update_count: usize,
+    syntax_scopes: Arc<SyntaxScopes>,
 }
 
 /// The kind and amount of indentation in a particular line. For now,
@@ -229,6 +258,9 @@ pub struct Diagnostic {
     pub is_unnecessary: bool,
     /// Data from language server that produced this diagnostic. Passed back to the LS when we request code actions for this diagnostic.
     pub data: Option<Value>,
+    /// The locations in other files related to the diagnostic, along with their messages. The
+    /// related locations in the diagnostic's own file are the other diagnostics of its group.
+    pub related_information: Vec<lsp::DiagnosticRelatedInformation>,
 }
 
 /// TODO - move this into the `project` crate and make it private.
@@ -939,6 +971,7 @@ impl Buffer {
         let saved_mtime = file.as_ref().and_then(|file| file.disk_state().mtime());
         let snapshot = buffer.snapshot();
         let syntax_map = Mutex::new(SyntaxMap::new(&snapshot));
+        let modeline = Modeline::detect(snapshot.as_rope()).map(Arc::new);
         Self {
             saved_mtime,
             saved_version: buffer.version(),
@@ -952,13 +985,20 @@ impl Buffer {
             file,
             capability,
             syntax_map,
+            syntax_scopes: Default::default(),
             parsing_in_background: false,
+            parse_supervisor: TaskSupervisor::default(),
+            parsing_degraded: false,
+            syntax_tree_evicted: false,
+            syntax_queried: Cell::new(false),
             non_text_state_update_count: 0,
             sync_parse_timeout: Duration::from_millis(1),
             parse_status: async_watch::channel(ParseStatus::Idle),
+            disabled_features: Default::default(),
             autoindent_requests: Default::default(),
             pending_autoindent: Default::default(),
             language: None,
+            modeline,
             remote_selections: Default::default(),
             diagnostics: Default::default(),
             diagnostics_timestamp: Default::default(),
@@ -967,6 +1007,8 @@ impl Buffer {
             completion_triggers_timestamp: Default::default(),
             deferred_ops: OperationQueue::new(),
             has_conflict: false,
+            merged_file_text: None,
+            conflicting_merge: None,
             _subscriptions: Vec::new(),
         }
     }
@@ -989,6 +1031,7 @@ impl Buffer {
                 let language_registry = language_registry.clone();
                 syntax.reparse(&text, language_registry, language);
             }
+            let modeline = Modeline::detect(text.as_rope()).map(Arc::new);
             BufferSnapshot {
                 text,
                 syntax,
@@ -996,7 +1039,9 @@ impl Buffer {
                 diagnostics: Default::default(),
                 remote_selections: Default::default(),
                 language,
+                modeline,
                 non_text_state_update_count: 0,
+                syntax_scopes: Default::default(),
             }
         }
     }
@@ -1014,7 +1059,9 @@ impl Buffer {
             diagnostics: Default::default(),
             remote_selections: Default::default(),
             language: None,
+            modeline: None,
             non_text_state_update_count: 0,
+            syntax_scopes: Default::default(),
         }
     }
 
@@ -1035,6 +1082,7 @@ impl Buffer {
             let language_registry = language_registry.clone();
             syntax.reparse(&text, language_registry, language);
         }
+        let modeline = Modeline::detect(text.as_rope()).map(Arc::new);
         BufferSnapshot {
             text,
             syntax,
@@ -1042,7 +1090,9 @@ impl Buffer {
             diagnostics: Default::default(),
             remote_selections: Default::default(),
             language,
+            modeline,
             non_text_state_update_count: 0,
+            syntax_scopes: Default::default(),
         }
     }
 
@@ -1053,6 +1103,8 @@ impl Buffer {
         let mut syntax_map = self.syntax_map.lock();
         syntax_map.interpolate(&text);
         let syntax = syntax_map.snapshot();
+        let syntax_scopes = self.syntax_scopes(text.version());
+        self.syntax_queried.set(true);
 
         BufferSnapshot {
             text,
@@ -1061,7 +1113,9 @@ impl Buffer {
             remote_selections: self.remote_selections.clone(),
             diagnostics: self.diagnostics.clone(),
             language: self.language.clone(),
+            modeline: self.modeline.clone(),
             non_text_state_update_count: self.non_text_state_update_count,
+            syntax_scopes,
         }
     }
 
@@ -1241,6 +1295,8 @@ impl Buffer {
         self.non_text_state_update_count += 1;
         self.syntax_map.lock().clear(&self.text);
         self.language = language;
+        self.parse_supervisor = TaskSupervisor::default();
+        self.parsing_degraded = false;
         self.reparse(cx);
         cx.emit(BufferEvent::LanguageChanged);
     }
@@ -1274,6 +1330,8 @@ impl Buffer {
         self.has_unsaved_edits
             .set((self.saved_version().clone(), false));
         self.has_conflict = false;
+        self.merged_file_text = None;
+        self.conflicting_merge = None;
         self.saved_mtime = mtime;
         cx.emit(BufferEvent::Saved);
         cx.notify();
@@ -1341,6 +1399,8 @@ impl Buffer {
             .set((self.saved_version.clone(), false));
         self.text.set_line_ending(line_ending);
         self.saved_mtime = mtime;
+        self.merged_file_text = None;
+        self.conflicting_merge = None;
         cx.emit(BufferEvent::Reloaded);
         cx.notify();
     }
@@ -1368,7 +1428,14 @@ impl Buffer {
             file_changed = true;
         };
 
+        let path_changed = self
+            .file
+            .as_ref()
+            .map_or(true, |old_file| new_file.path() != old_file.path());
         self.file = Some(new_file);
+        if path_changed {
+            self.refresh_disabled_features(cx);
+        }
         if file_changed {
             self.non_text_state_update_count += 1;
             if was_dirty != self.is_dirty() {
@@ -1379,6 +1446,43 @@ impl Buffer {
         }
     }
 
+    /// Returns the expensive features disabled for this buffer's file by the
+    /// `disabled_features` setting.
+    pub fn disabled_features(&self) -> DisabledFeatures {
+        self.disabled_features
+    }
+
+    /// Evaluates the `disabled_features` setting for this buffer's path. This is done
+    /// when the buffer is opened, and again whenever its path changes.
+    pub fn refresh_disabled_features(&mut self, cx: &mut Context<Self>) {
+        let disabled_features = self
+            .file
+            .as_ref()
+            .map_or_else(DisabledFeatures::default, |file| {
+                all_language_settings(Some(file), cx).disabled_features_for_path(file.path())
+            });
+        if disabled_features == self.disabled_features {
+            return;
+        }
+
+        let old_disabled_features = mem::replace(&mut self.disabled_features, disabled_features);
+        if disabled_features.syntax_highlighting && !old_disabled_features.syntax_highlighting {
+            self.syntax_map.lock().clear(&self.text);
+            self.non_text_state_update_count += 1;
+            cx.emit(BufferEvent::Reparsed);
+        } else if !disabled_features.syntax_highlighting
+            && old_disabled_features.syntax_highlighting
+        {
+            self.reparse(cx);
+        }
+        if disabled_features.diagnostics && !self.diagnostics.is_empty() {
+            self.diagnostics.clear();
+            self.non_text_state_update_count += 1;
+            cx.emit(BufferEvent::DiagnosticsUpdated);
+        }
+        cx.notify();
+    }
+
     pub fn base_buffer(&self) -> Option<Entity<Self>> {
         Some(self.branch_state.as_ref()?.base_buffer.clone())
     }
@@ -1388,6 +1492,11 @@ impl Buffer {
         self.language.as_ref()
     }
 
+    /// Returns the settings configured by modelines in this [`Buffer`]'s contents.
+    pub fn modeline(&self) -> Option<&Arc<Modeline>> {
+        self.modeline.as_ref()
+    }
+
     /// Returns the [`Language`] at the given location.
     pub fn language_at<D: ToOffset>(&self, position: D) -> Option<Arc<Language>> {
         let offset = position.to_offset(self);
@@ -1399,6 +1508,29 @@ impl Buffer {
             .or_else(|| self.language.clone())
     }
 
+    /// Returns the settings for the language at the given location, in effect in its syntax
+    /// scope.
+    pub fn settings_at<'a, D: ToOffset>(
+        &'a self,
+        position: D,
+        cx: &'a App,
+    ) -> Cow<'a, LanguageSettings> {
+        let offset = position.to_offset(self);
+        let mut settings = language_settings(
+            self.language_at(offset).map(|l| l.name()),
+            self.file.as_ref(),
+            cx,
+        );
+        if !settings.scopes.is_empty() {
+            let scope = self.snapshot().syntax_scope_at(offset);
+            settings = settings_in_scope(settings, scope);
+        }
+        if let Some(modeline) = &self.modeline {
+            modeline.apply(settings.to_mut());
+        }
+        settings
+    }
+
     /// An integer version number that accounts for all updates besides
     /// the buffer's text itself (which is versioned via a version vector).
     pub fn non_text_state_update_count(&self) -> usize {
@@ -1417,6 +1549,39 @@ impl Buffer {
         self.syntax_map.lock().contains_unknown_injections()
     }
 
+    /// Drops the syntax tree of the buffer to free its memory, until the buffer is edited or
+    /// [`Buffer::restore_syntax_tree`] parses it again. Returns whether there was a tree to drop.
+    ///
+    /// The tree is kept while it's still queried: it's only dropped when no snapshot of the
+    /// buffer was taken since the last call.
+    pub fn evict_syntax_tree(&mut self, cx: &mut Context<Self>) -> bool {
+        if self.syntax_tree_evicted
+            || self.parsing_in_background
+            || self.language.is_none()
+            || self.disabled_features.syntax_highlighting
+            || self.syntax_queried.replace(false)
+        {
+            return false;
+        }
+        self.syntax_map.lock().clear(&self.text);
+        self.syntax_tree_evicted = true;
+        self.non_text_state_update_count += 1;
+        cx.emit(BufferEvent::Reparsed);
+        true
+    }
+
+    /// Whether the syntax tree of the buffer was dropped by [`Buffer::evict_syntax_tree`].
+    pub fn is_syntax_tree_evicted(&self) -> bool {
+        self.syntax_tree_evicted
+    }
+
+    /// Parses the buffer again if its syntax tree was evicted.
+    pub fn restore_syntax_tree(&mut self, cx: &mut Context<Self>) {
+        if self.syntax_tree_evicted {
+            self.reparse(cx);
+        }
+    }
+
     #[cfg(test)]
     pub fn set_sync_parse_timeout(&mut self, timeout: Duration) {
         self.sync_parse_timeout = timeout;
@@ -1446,7 +1611,10 @@ impl Buffer {
     /// for the same buffer, we only initiate a new parse if we are not already
     /// parsing in the background.
     pub fn reparse(&mut self, cx: &mut Context<Self>) {
-        if self.parsing_in_background {
+        if self.parsing_in_background
+            || self.parsing_degraded
+            || self.disabled_features.syntax_highlighting
+        {
             return;
         }
         let language = if let Some(language) = self.language.clone() {
@@ -1454,9 +1622,11 @@ impl Buffer {
         } else {
             return;
         };
+        self.syntax_tree_evicted = false;
 
         let text = self.text_snapshot();
         let parsed_version = self.version();
+        let parsed_resync_count = text.resync_count();
 
         let mut syntax_map = self.syntax_map.lock();
         syntax_map.interpolate(&text);
@@ -1467,10 +1637,10 @@ impl Buffer {
         let parse_task = cx.background_executor().spawn({
             let language = language.clone();
             let language_registry = language_registry.clone();
-            async move {
+            supervised("syntax parser", async move {
                 syntax_snapshot.reparse(&text, language_registry, language);
                 syntax_snapshot
-            }
+            })
         });
 
         self.parse_status.0.send(ParseStatus::Parsing).unwrap();
@@ -1478,14 +1648,32 @@ impl Buffer {
             .background_executor()
             .block_with_timeout(self.sync_parse_timeout, parse_task)
         {
-            Ok(new_syntax_snapshot) => {
+            Ok(Ok(new_syntax_snapshot)) => {
                 self.did_finish_parsing(new_syntax_snapshot, cx);
             }
+            Ok(Err(panic)) => self.recover_from_parse_panic(panic, cx),
             Err(parse_task) => {
                 self.parsing_in_background = true;
                 cx.spawn(move |this, mut cx| async move {
-                    let new_syntax_map = parse_task.await;
+                    let new_syntax_map = match parse_task.await {
+                        Ok(new_syntax_map) => new_syntax_map,
+                        Err(panic) => {
+                            this.update(&mut cx, |this, cx| {
+                                this.parsing_in_background = false;
+                                this.recover_from_parse_panic(panic, cx);
+                            })
+                            .ok();
+                            return;
+                        }
+                    };
                     this.update(&mut cx, move |this, cx| {
+                        if this.resync_count() != parsed_resync_count {
+                            // The text was replaced while it was parsed, so the syntax tree
+                            // doesn't match it.
+                            this.parsing_in_background = false;
+                            this.reparse(cx);
+                            return;
+                        }
                         let grammar_changed =
                             this.language.as_ref().map_or(true, |current_language| {
                                 !Arc::ptr_eq(&language, current_language)
@@ -1511,7 +1699,26 @@ impl Buffer {
         }
     }
 
+    /// Parses the buffer again after the parse stopped by the given panic, from its latest
+    /// syntax snapshot, which the parse didn't get to update. Once parsing panicked too many
+    /// times in a row, the buffer isn't parsed anymore, as if its syntax highlighting was
+    /// disabled.
+    fn recover_from_parse_panic(&mut self, panic: TaskPanic, cx: &mut Context<Self>) {
+        self.parse_status.0.send(ParseStatus::Idle).unwrap();
+        match self.parse_supervisor.task_panicked(&panic, cx) {
+            TaskRecovery::Restarted => self.reparse(cx),
+            TaskRecovery::Degraded => {
+                self.parsing_degraded = true;
+                self.syntax_map.lock().clear(&self.text);
+                self.non_text_state_update_count += 1;
+                cx.emit(BufferEvent::Reparsed);
+                cx.notify();
+            }
+        }
+    }
+
     fn did_finish_parsing(&mut self, syntax_snapshot: SyntaxSnapshot, cx: &mut Context<Self>) {
+        self.parse_supervisor.task_completed();
         self.non_text_state_update_count += 1;
         self.syntax_map.lock().did_parse(syntax_snapshot);
         self.request_autoindent(cx);
@@ -2069,6 +2276,15 @@ impl Buffer {
         self.text.forget_transaction(transaction_id);
     }
 
+    /// Describes where the edits of a transaction in the buffer's undo history came from.
+    pub fn set_transaction_metadata(
+        &mut self,
+        transaction_id: TransactionId,
+        metadata: TransactionMetadata,
+    ) {
+        self.text.set_transaction_metadata(transaction_id, metadata);
+    }
+
     /// Manually merge two adjacent transactions in the buffer's undo history.
     pub fn merge_transactions(&mut self, transaction: TransactionId, destination: TransactionId) {
         self.text.merge_transactions(transaction, destination);
@@ -2101,6 +2317,20 @@ impl Buffer {
         self.text.give_up_waiting();
     }
 
+    /// Replaces the text of this replica with the one of another replica of the buffer, at the
+    /// same version, after their fingerprints showed that they diverged. See
+    /// [`text::Buffer::resync`].
+    pub fn resync(&mut self, replica: &mut Buffer, cx: &mut Context<Self>) {
+        self.text.resync(&mut replica.text);
+        self.saved_version = replica.saved_version.clone();
+        self.saved_mtime = replica.saved_mtime;
+        self.non_text_state_update_count += 1;
+        self.syntax_map.lock().clear(&self.text);
+        self.reparse(cx);
+        cx.emit(BufferEvent::Edited);
+        cx.notify();
+    }
+
     /// Stores a set of selections that should be broadcasted to all of the buffer's replicas.
     pub fn set_active_selections(
         &mut self,
@@ -2295,6 +2525,11 @@ impl Buffer {
         }
 
         self.reparse(cx);
+        if self.edits_since::<Point>(old_version).any(|edit| {
+            Modeline::searches_rows(self.as_rope(), edit.new.start.row..=edit.new.end.row)
+        }) {
+            self.modeline = Modeline::detect(self.as_rope()).map(Arc::new);
+        }
 
         cx.emit(BufferEvent::Edited);
         if was_dirty != self.is_dirty() {
@@ -2523,7 +2758,7 @@ impl Buffer {
     ) {
         if lamport_timestamp > self.diagnostics_timestamp {
             let ix = self.diagnostics.binary_search_by_key(&server_id, |e| e.0);
-            if diagnostics.is_empty() {
+            if diagnostics.is_empty() || self.disabled_features.diagnostics {
                 if let Ok(ix) = ix {
                     self.diagnostics.remove(ix);
                 }
@@ -2605,6 +2840,22 @@ impl Buffer {
         undone
     }
 
+    /// Undoes the changes made by tools, such as formatters, since the last manual edit.
+    pub fn undo_to_last_manual_edit(&mut self, cx: &mut Context<Self>) -> bool {
+        let was_dirty = self.is_dirty();
+        let old_version = self.version.clone();
+
+        let operations = self.text.undo_to_last_manual_edit();
+        let undone = !operations.is_empty();
+        for operation in operations {
+            self.send_operation(Operation::Buffer(operation), true, cx);
+        }
+        if undone {
+            self.did_edit(&old_version, was_dirty, cx)
+        }
+        undone
+    }
+
     pub fn undo_operations(&mut self, counts: HashMap<Lamport, u32>, cx: &mut Context<Buffer>) {
         let was_dirty = self.is_dirty();
         let operation = self.text.undo_operations(counts);
@@ -3060,10 +3311,22 @@ impl BufferSnapshot {
     /// returned in chunks where each chunk has a single syntax highlighting style and
     /// diagnostic status.
     pub fn chunks<T: ToOffset>(&self, range: Range<T>, language_aware: bool) -> BufferChunks {
+        self.chunks_with_syntax(range, language_aware, language_aware)
+    }
+
+    /// Like [`Self::chunks`], but only queries the syntax trees for the highlights of
+    /// language-aware chunks when `highlight_syntax` is true. The chunks are still annotated
+    /// with diagnostics otherwise, e.g. when their highlights were computed ahead of time.
+    pub fn chunks_with_syntax<T: ToOffset>(
+        &self,
+        range: Range<T>,
+        language_aware: bool,
+        highlight_syntax: bool,
+    ) -> BufferChunks {
         let range = range.start.to_offset(self)..range.end.to_offset(self);
 
         let mut syntax = None;
-        if language_aware {
+        if language_aware && highlight_syntax {
             syntax = Some(self.get_highlights(range.clone()));
         }
         // We want to look at diagnostic spans only when iterating over language-annotated chunks.
@@ -3133,17 +3396,32 @@ impl BufferSnapshot {
             .or(self.language.as_ref())
     }
 
-    /// Returns the settings for the language at the given location.
+    /// Returns the settings for the language at the given location, in effect in its syntax
+    /// scope.
     pub fn settings_at<'a, D: ToOffset>(
         &'a self,
         position: D,
         cx: &'a App,
     ) -> Cow<'a, LanguageSettings> {
-        language_settings(
-            self.language_at(position).map(|l| l.name()),
+        let offset = position.to_offset(self);
+        let mut settings = language_settings(
+            self.language_at(offset).map(|l| l.name()),
             self.file.as_ref(),
             cx,
-        )
+        );
+        if !settings.scopes.is_empty() {
+            let scope = self.syntax_scope_at(offset);
+            settings = settings_in_scope(settings, scope);
+        }
+        if let Some(modeline) = &self.modeline {
+            modeline.apply(settings.to_mut());
+        }
+        settings
+    }
+
+    /// Returns the settings configured by modelines in the buffer's contents.
+    pub fn modeline(&self) -> Option<&Arc<Modeline>> {
+        self.modeline.as_ref()
     }
 
     pub fn char_classifier_at<T: ToOffset>(&self, point: T) -> CharClassifier {
@@ -3816,6 +4094,39 @@ impl BufferSnapshot {
         })
     }
 
+    /// Returns the ranges of any matches of the nowrap query, which are regions whose lines
+    /// should overflow horizontally rather than be soft-wrapped.
+    pub fn no_wrap_ranges<T: ToOffset>(
+        &self,
+        range: Range<T>,
+    ) -> impl Iterator<Item = Range<usize>> + '_ {
+        let offset_range = range.start.to_offset(self)..range.end.to_offset(self);
+        let mut syntax_matches = self.syntax.matches(offset_range, self, |grammar| {
+            grammar.no_wrap_config.as_ref().map(|config| &config.query)
+        });
+
+        let configs = syntax_matches
+            .grammars()
+            .iter()
+            .map(|grammar| grammar.no_wrap_config.as_ref())
+            .collect::<Vec<_>>();
+
+        iter::from_fn(move || {
+            let no_wrap_range = syntax_matches
+                .peek()
+                .and_then(|mat| {
+                    configs[mat.grammar_index].and_then(|config| {
+                        mat.captures
+                            .iter()
+                            .find(|capture| capture.index == config.no_wrap_capture_ix)
+                    })
+                })
+                .map(|mat| mat.node.byte_range());
+            syntax_matches.advance();
+            no_wrap_range
+        })
+    }
+
     pub fn injections_intersecting_range<T: ToOffset>(
         &self,
         range: Range<T>,
@@ -4123,6 +4434,18 @@ impl BufferSnapshot {
     }
 }
 
+/// Returns the settings in effect in the syntax scope.
+fn settings_in_scope<'a>(
+    settings: Cow<'a, LanguageSettings>,
+    scope: Option<LanguageScope>,
+) -> Cow<'a, LanguageSettings> {
+    let scope_name = scope.as_ref().and_then(|scope| scope.override_name());
+    match settings {
+        Cow::Borrowed(settings) => Cow::Borrowed(settings.in_scope(scope_name)),
+        Cow::Owned(settings) => Cow::Owned(settings.in_scope(scope_name).clone()),
+    }
+}
+
 fn indent_size_for_line(text: &text::BufferSnapshot, row: u32) -> IndentSize {
     indent_size_for_text(text.chars_at(Point::new(row, 0)))
 }
@@ -4152,7 +4475,9 @@ impl Clone for BufferSnapshot {
             remote_selections: self.remote_selections.clone(),
             diagnostics: self.diagnostics.clone(),
             language: self.language.clone(),
+            modeline: self.modeline.clone(),
             non_text_state_update_count: self.non_text_state_update_count,
+            syntax_scopes: self.syntax_scopes.clone(),
         }
     }
 }
@@ -4241,8 +4566,8 @@ impl<'a> BufferChunks<'a> {
             }
 
             highlights.captures.set_byte_range(self.range.clone());
-            self.initialize_diagnostic_endpoints();
         }
+        self.initialize_diagnostic_endpoints();
     }
 
     fn initialize_diagnostic_endpoints(&mut self) {
@@ -4438,6 +4763,7 @@ impl Default for Diagnostic {
             is_disk_based: false,
             is_unnecessary: false,
             data: None,
+            related_information: Vec::new(),
         }
     }
 }
@@ -4575,6 +4901,7 @@ pub(crate) fn contiguous_ranges(
 #[derive(Default, Debug)]
 pub struct CharClassifier {
     scope: Option<LanguageScope>,
+    word_characters: Option<Arc<[char]>>,
     for_completion: bool,
     ignore_punctuation: bool,
 }
@@ -4583,11 +4910,21 @@ impl CharClassifier {
     pub fn new(scope: Option<LanguageScope>) -> Self {
         Self {
             scope,
+            word_characters: None,
             for_completion: false,
             ignore_punctuation: false,
         }
     }
 
+    /// Treats the given characters as part of words, in addition to the word
+    /// characters of the language scope.
+    pub fn with_word_characters(self, word_characters: Option<Arc<[char]>>) -> Self {
+        Self {
+            word_characters,
+            ..self
+        }
+    }
+
     pub fn for_completion(self, for_completion: bool) -> Self {
         Self {
             for_completion,
@@ -4621,6 +4958,14 @@ impl CharClassifier {
             return CharKind::Word;
         }
 
+        if self
+            .word_characters
+            .as_ref()
+            .map_or(false, |characters| characters.contains(&c))
+        {
+            return CharKind::Word;
+        }
+
         if let Some(scope) = &self.scope {
             if let Some(characters) = scope.word_characters() {
                 if characters.contains(&c) {
@@ -0,0 +1,470 @@
+//! Merges of a buffer's unsaved edits with the changes made to its file on disk.
+//!
+//! Both the buffer's text and the file's text are diffed line by line against the text they
+//! started from, the one the buffer was last loaded or saved with. Lines changed on one side
+//! only are taken from that side, and so are lines both sides changed the same way. These
+//! changes are made to the buffer right away. Lines both sides changed differently are a
+//! conflict, left out of the buffer: the merged text is returned with each conflict written
+//! between conflict markers, for the user to resolve elsewhere:
+//!
+//! ```text
+//! <<<<<<< Buffer
+//! the buffer's lines
+//! =======
+//! the file's lines
+//! >>>>>>> Disk
+//! ```
+//!
+//! Once the merge is clean, or its conflicts are resolved, the buffer no longer conflicts with
+//! its file, its own edits are still unsaved, and the file's text is the base of the next merge.
+
+use super::{Buffer, BufferEvent, LineEnding};
+use anyhow::{anyhow, ensure, Result};
+use fs::MTime;
+use gpui::{App, Context, Task};
+use similar::{DiffTag, TextDiff};
+use std::{ops::Range, sync::Arc};
+use text::Rope;
+
+const CONFLICT_START: &str = "<<<<<<< Buffer\n";
+const CONFLICT_SEPARATOR: &str = "=======\n";
+const CONFLICT_END: &str = ">>>>>>> Disk\n";
+
+/// The merge of a buffer with its file, computed by [`Buffer::merge_with_file`].
+pub struct FileMerge {
+    /// The edits making the changes that don't conflict.
+    edits: Vec<(Range<usize>, Arc<str>)>,
+    /// The merged text with the conflicts between conflict markers, if there are any.
+    conflict_text: Option<String>,
+    conflict_count: usize,
+    version: clock::Global,
+    file_text: Rope,
+    mtime: Option<MTime>,
+}
+
+impl FileMerge {
+    /// The number of regions that the buffer and its file changed differently.
+    pub fn conflict_count(&self) -> usize {
+        self.conflict_count
+    }
+}
+
+/// The conflicts left by a merge of a buffer with its file, returned by
+/// [`Buffer::apply_file_merge`] and resolved by [`Buffer::resolve_file_merge`].
+#[derive(Clone)]
+pub struct FileMergeConflicts {
+    text: String,
+    conflict_count: usize,
+    /// The version of the buffer once the changes that don't conflict were made.
+    version: clock::Global,
+    file_text: Rope,
+    mtime: Option<MTime>,
+}
+
+impl FileMergeConflicts {
+    /// The buffer's text with the conflicting lines of both sides between conflict markers.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn conflict_count(&self) -> usize {
+        self.conflict_count
+    }
+}
+
+impl Buffer {
+    /// Merges the buffer's text with the text of its file on disk.
+    pub fn merge_with_file(&self, cx: &App) -> Task<Result<FileMerge>> {
+        let Some(file) = self.file.as_ref().and_then(|file| file.as_local()) else {
+            return Task::ready(Err(anyhow!("buffer has no file on disk to merge with")));
+        };
+        let mtime = file.disk_state().mtime();
+        let load = file.load(cx);
+        let base_text = self.merge_base();
+        let snapshot = self.text.snapshot();
+
+        cx.background_executor().spawn(async move {
+            let mut file_text = load.await?;
+            LineEnding::normalize(&mut file_text);
+            let text = snapshot.text();
+            let merge = merge_lines(&base_text.to_string(), &text, &file_text);
+            let conflict_count = merge.edits.iter().filter(|edit| edit.is_conflict).count();
+            let conflict_text = (conflict_count > 0).then(|| merge.text(&text));
+            Ok(FileMerge {
+                edits: merge
+                    .edits
+                    .into_iter()
+                    .filter(|edit| !edit.is_conflict)
+                    .map(|edit| (edit.range, edit.new_text))
+                    .collect(),
+                conflict_text,
+                conflict_count,
+                version: snapshot.version().clone(),
+                file_text: Rope::from(file_text.as_str()),
+                mtime,
+            })
+        })
+    }
+
+    /// Makes the changes of a merge with the buffer's file that don't conflict with the
+    /// buffer's edits, as a single transaction, returning the conflicts left to resolve. The
+    /// buffer must not have changed since the merge was computed.
+    pub fn apply_file_merge(
+        &mut self,
+        merge: FileMerge,
+        cx: &mut Context<Self>,
+    ) -> Result<Option<FileMergeConflicts>> {
+        ensure!(
+            self.version == merge.version,
+            "the buffer changed since its merge was computed"
+        );
+        let was_dirty = self.is_dirty();
+        self.finalize_last_transaction();
+        self.start_transaction();
+        self.edit(merge.edits, None, cx);
+        self.end_transaction(cx);
+        self.finalize_last_transaction();
+
+        let conflicts = if let Some(text) = merge.conflict_text {
+            self.conflicting_merge = Some((merge.mtime, self.version.clone()));
+            Some(FileMergeConflicts {
+                text,
+                conflict_count: merge.conflict_count,
+                version: self.version.clone(),
+                file_text: merge.file_text,
+                mtime: merge.mtime,
+            })
+        } else {
+            self.merged_file_text = Some(merge.file_text);
+            self.saved_mtime = merge.mtime;
+            self.has_conflict = false;
+            None
+        };
+        if was_dirty != self.is_dirty() {
+            cx.emit(BufferEvent::DirtyChanged);
+        }
+        cx.notify();
+        Ok(conflicts)
+    }
+
+    /// Replaces the buffer's text with the resolution of the conflicts of its merge, as a
+    /// single transaction, after which the buffer no longer conflicts with its file. Neither
+    /// the buffer nor its file must have changed since the merge, and the resolution must have
+    /// no conflict markers left.
+    pub fn resolve_file_merge(
+        &mut self,
+        conflicts: &FileMergeConflicts,
+        resolved_text: &str,
+        cx: &mut Context<Self>,
+    ) -> Result<()> {
+        ensure!(
+            self.version == conflicts.version,
+            "the buffer changed since it was merged with its file"
+        );
+        ensure!(
+            self.file_mtime() == conflicts.mtime,
+            "the file changed again since it was merged with the buffer"
+        );
+        ensure!(
+            !has_conflict_markers(resolved_text),
+            "the merge still has conflict markers"
+        );
+
+        let was_dirty = self.is_dirty();
+        let edits = line_edits(&self.text(), resolved_text);
+        self.finalize_last_transaction();
+        self.start_transaction();
+        self.edit(edits, None, cx);
+        self.end_transaction(cx);
+        self.finalize_last_transaction();
+        self.merged_file_text = Some(conflicts.file_text.clone());
+        self.saved_mtime = conflicts.mtime;
+        self.has_conflict = false;
+        self.conflicting_merge = None;
+        if was_dirty != self.is_dirty() {
+            cx.emit(BufferEvent::DirtyChanged);
+        }
+        cx.notify();
+        Ok(())
+    }
+
+    /// Whether merging the buffer with its file is known to conflict, because a merge found
+    /// conflicts and neither the buffer nor its file changed since.
+    pub fn has_known_merge_conflicts(&self) -> bool {
+        self.conflicting_merge
+            .as_ref()
+            .is_some_and(|(mtime, version)| *version == self.version && *mtime == self.file_mtime())
+    }
+
+    fn file_mtime(&self) -> Option<MTime> {
+        self.file.as_ref()?.disk_state().mtime()
+    }
+
+    /// The text that the buffer and its file both started from.
+    fn merge_base(&self) -> Rope {
+        self.merged_file_text
+            .clone()
+            .unwrap_or_else(|| self.text.rope_for_version(&self.saved_version))
+    }
+}
+
+struct LineMerge {
+    /// The edits turning the buffer's text into the merged text, sorted.
+    edits: Vec<MergeEdit>,
+}
+
+struct MergeEdit {
+    range: Range<usize>,
+    new_text: Arc<str>,
+    /// Whether the edit writes both sides of a conflict between conflict markers.
+    is_conflict: bool,
+}
+
+impl LineMerge {
+    /// The merged text, given the buffer's text.
+    fn text(&self, ours: &str) -> String {
+        let mut text = String::with_capacity(ours.len());
+        let mut last_offset = 0;
+        for edit in &self.edits {
+            text.push_str(&ours[last_offset..edit.range.start]);
+            text.push_str(&edit.new_text);
+            last_offset = edit.range.end;
+        }
+        text.push_str(&ours[last_offset..]);
+        text
+    }
+}
+
+/// Lines of the base text replaced by lines of one side.
+struct Hunk {
+    base: Range<usize>,
+    new: Range<usize>,
+}
+
+fn merge_lines(base: &str, ours: &str, theirs: &str) -> LineMerge {
+    let our_diff = TextDiff::from_lines(base, ours);
+    let their_diff = TextDiff::from_lines(base, theirs);
+    let our_lines = our_diff.new_slices();
+    let their_lines = their_diff.new_slices();
+    let our_offsets = our_lines
+        .iter()
+        .scan(0, |offset, line| {
+            *offset += line.len();
+            Some(*offset)
+        })
+        .collect::<Vec<_>>();
+    let our_offset = |line: usize| line.checked_sub(1).map_or(0, |ix| our_offsets[ix]);
+
+    let hunks = |diff: &TextDiff<'_, '_, '_, str>| {
+        diff.ops()
+            .iter()
+            .filter_map(|op| {
+                let (tag, base, new) = op.as_tag_tuple();
+                (tag != DiffTag::Equal).then_some(Hunk { base, new })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .peekable()
+    };
+    let mut our_hunks = hunks(&our_diff);
+    let mut their_hunks = hunks(&their_diff);
+
+    let mut edits = Vec::new();
+    // How many more lines the buffer's text has than the base text, before the current region.
+    let mut our_delta = 0isize;
+    loop {
+        // Gather the hunks of both sides that overlap or touch into a region of the base text.
+        let first_is_ours = match (our_hunks.peek(), their_hunks.peek()) {
+            (Some(ours), Some(theirs)) => ours.base.start <= theirs.base.start,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
+        let mut our_region = Vec::new();
+        let mut their_region = Vec::new();
+        let (first, region) = if first_is_ours {
+            (our_hunks.next(), &mut our_region)
+        } else {
+            (their_hunks.next(), &mut their_region)
+        };
+        let Some(first) = first else {
+            break;
+        };
+        let start = first.base.start;
+        let mut end = first.base.end;
+        region.push(first);
+        loop {
+            if let Some(hunk) = our_hunks.next_if(|hunk| hunk.base.start <= end) {
+                end = end.max(hunk.base.end);
+                our_region.push(hunk);
+            } else if let Some(hunk) = their_hunks.next_if(|hunk| hunk.base.start <= end) {
+                end = end.max(hunk.base.end);
+                their_region.push(hunk);
+            } else {
+                break;
+            }
+        }
+
+        let our_range = side_range(&our_region, start..end).unwrap_or_else(|| {
+            (start as isize + our_delta) as usize..(end as isize + our_delta) as usize
+        });
+        our_delta = our_range.end as isize - end as isize;
+        let Some(their_range) = side_range(&their_region, start..end) else {
+            // Only the buffer changed these lines.
+            continue;
+        };
+
+        let their_text = their_lines[their_range].concat();
+        let (new_text, is_conflict) = if our_region.is_empty() {
+            (their_text, false)
+        } else {
+            let our_text = our_lines[our_range.clone()].concat();
+            if our_text == their_text {
+                continue;
+            }
+            let mut conflict = String::from(CONFLICT_START);
+            push_lines(&mut conflict, &our_text);
+            conflict.push_str(CONFLICT_SEPARATOR);
+            push_lines(&mut conflict, &their_text);
+            conflict.push_str(CONFLICT_END);
+            (conflict, true)
+        };
+        edits.push(MergeEdit {
+            range: our_offset(our_range.start)..our_offset(our_range.end),
+            new_text: Arc::from(new_text),
+            is_conflict,
+        });
+    }
+
+    LineMerge { edits }
+}
+
+/// The lines of one side covering a region of the base text, given the side's hunks in it.
+fn side_range(hunks: &[Hunk], region: Range<usize>) -> Option<Range<usize>> {
+    let first = hunks.first()?;
+    let last = hunks.last()?;
+    Some(
+        first.new.start - (first.base.start - region.start)
+            ..last.new.end + (region.end - last.base.end),
+    )
+}
+
+fn push_lines(text: &mut String, lines: &str) {
+    text.push_str(lines);
+    if !lines.is_empty() && !lines.ends_with('\n') {
+        text.push('\n');
+    }
+}
+
+fn has_conflict_markers(text: &str) -> bool {
+    text.lines().any(|line| {
+        [CONFLICT_START, CONFLICT_SEPARATOR, CONFLICT_END]
+            .iter()
+            .any(|marker| line == marker.trim_end())
+    })
+}
+
+/// The edits turning the old text into the new one, replacing whole lines.
+fn line_edits(old: &str, new: &str) -> Vec<(Range<usize>, Arc<str>)> {
+    let diff = TextDiff::from_lines(old, new);
+    let old_lines = diff.old_slices();
+    let new_lines = diff.new_slices();
+    let mut old_offset = 0;
+    let mut edits = Vec::new();
+    for op in diff.ops() {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        let old_len = old_lines[old_range]
+            .iter()
+            .map(|line| line.len())
+            .sum::<usize>();
+        if tag != DiffTag::Equal {
+            edits.push((
+                old_offset..old_offset + old_len,
+                Arc::from(new_lines[new_range].concat()),
+            ));
+        }
+        old_offset += old_len;
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merge(base: &str, ours: &str, theirs: &str) -> (String, usize) {
+        let merge = merge_lines(base, ours, theirs);
+        let conflict_count = merge.edits.iter().filter(|edit| edit.is_conflict).count();
+        (merge.text(ours), conflict_count)
+    }
+
+    #[test]
+    fn test_merge_insertions() {
+        assert_eq!(
+            merge("a\nb\nc\n", "a\nb\nc\nd\n", "z\na\nb\nc\n"),
+            ("z\na\nb\nc\nd\n".to_string(), 0)
+        );
+        assert_eq!(
+            merge("a\nc\n", "a\nc\n", "a\nb\nc\n"),
+            ("a\nb\nc\n".to_string(), 0)
+        );
+    }
+
+    #[test]
+    fn test_merge_deletions() {
+        assert_eq!(
+            merge("a\nb\nc\n", "b\nc\n", "a\nb\n"),
+            ("b\n".to_string(), 0)
+        );
+        // A line deleted on one side and changed on the other is a conflict.
+        assert_eq!(
+            merge("a\nb\nc\n", "a\nc\n", "a\nB\nc\n"),
+            (
+                "a\n<<<<<<< Buffer\n=======\nB\n>>>>>>> Disk\nc\n".to_string(),
+                1
+            )
+        );
+    }
+
+    #[test]
+    fn test_merge_without_trailing_newline() {
+        assert_eq!(
+            merge("a\nb\nc", "A\nb\nc", "a\nb\nC"),
+            ("A\nb\nC".to_string(), 0)
+        );
+        assert_eq!(
+            merge("a", "b", "c"),
+            (
+                "<<<<<<< Buffer\nb\n=======\nc\n>>>>>>> Disk\n".to_string(),
+                1
+            )
+        );
+    }
+
+    #[test]
+    fn test_merge_same_line() {
+        // Both sides changing a line the same way isn't a conflict.
+        assert_eq!(
+            merge("a\nb\nc\n", "a\nB\nc\n", "a\nB\nc\n"),
+            ("a\nB\nc\n".to_string(), 0)
+        );
+        assert_eq!(
+            merge("a\nb\nc\nd\ne\n", "a\nB\nc\nd\nE\n", "a\nb2\nc\nd\ne\n"),
+            (
+                "a\n<<<<<<< Buffer\nB\n=======\nb2\n>>>>>>> Disk\nc\nd\nE\n".to_string(),
+                1
+            )
+        );
+    }
+
+    #[test]
+    fn test_line_edits() {
+        let old = "a\nb\nc\n";
+        let new = "a\nB\nc\nd\n";
+        let mut text = old.to_string();
+        for (range, new_text) in line_edits(old, new).into_iter().rev() {
+            text.replace_range(range, &new_text);
+        }
+        assert_eq!(text, new);
+    }
+}
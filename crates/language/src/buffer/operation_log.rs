@@ -0,0 +1,398 @@
+//! Persistence of buffers as their base text and the log of the operations applied to it.
+//!
+//! Replaying the operations, instead of setting the final text, rebuilds the buffer exactly:
+//! anchors taken before it was persisted still resolve, and its undo history and dirty state
+//! are restored.
+//!
+//! Once the buffer was saved, the text loaded from disk when restoring it is the saved text
+//! rather than the base text, which the operations don't apply to. The undo history of a buffer
+//! without unsaved changes is then persisted by [`Buffer::undo_history_log`], as a log starting
+//! from the saved text whose operations are the edits of each transaction.
+
+use super::{Buffer, TextBuffer};
+use crate::proto;
+use anyhow::{anyhow, Result};
+use gpui::{App, Context, Task};
+use std::{ops::Range, time::Instant};
+use text::Rope;
+
+/// The number of operations beyond which the log is compacted.
+const MAX_LOGGED_OPERATIONS: usize = 4096;
+
+/// The number of transactions of the undo stack, and of the redo stack, kept by compaction.
+const MAX_COMPACTED_TRANSACTIONS: usize = 128;
+
+impl Buffer {
+    /// Serializes the buffer as its base text and the operations applied to it, from which
+    /// [`Buffer::restore_operation_log`] rebuilds the buffer.
+    ///
+    /// The log has to start from the text that will be loaded from disk when restoring it.
+    /// Once the buffer was saved or reloaded, or when the log grows too long, it is compacted to
+    /// start from the saved text, keeping the last [`MAX_COMPACTED_TRANSACTIONS`] transactions
+    /// of the undo and redo stacks.
+    pub fn operation_log(&self, cx: &App) -> Task<proto::BufferOperationLog> {
+        let snapshot = self.text.snapshot();
+        let base_text = self.base_text().clone();
+        let operations = self.text.operations().clone();
+        let (undo_stack, redo_stack) = self.text.undo_history();
+        let saved_version = self.saved_version.clone();
+        let replica_id = self.replica_id();
+
+        cx.background_executor().spawn(async move {
+            let line_ending = proto::serialize_line_ending(snapshot.line_ending()) as i32;
+            let base_buffer = |base_text: Rope| {
+                TextBuffer::new_normalized(
+                    replica_id,
+                    snapshot.remote_id(),
+                    snapshot.line_ending(),
+                    base_text,
+                )
+            };
+            let saved_text = snapshot.rope_for_version(&saved_version);
+            let saved_text_fingerprint = saved_text.fingerprint().to_u64();
+
+            // Once the buffer was saved or reloaded, the log has to start from the saved text.
+            let base_version = base_buffer(base_text.clone()).version();
+            if saved_version == base_version && operations.len() <= MAX_LOGGED_OPERATIONS {
+                return proto::BufferOperationLog {
+                    base_text: base_text.to_string(),
+                    line_ending,
+                    operations: operations
+                        .iter()
+                        .map(|(_, operation)| {
+                            proto::serialize_operation(&crate::Operation::Buffer(operation.clone()))
+                        })
+                        .collect(),
+                    undo_stack: undo_stack
+                        .iter()
+                        .map(proto::serialize_transaction)
+                        .collect(),
+                    redo_stack: redo_stack
+                        .iter()
+                        .map(proto::serialize_transaction)
+                        .collect(),
+                    saved_version: proto::serialize_version(&saved_version),
+                    saved_text_fingerprint,
+                };
+            }
+
+            let mut history = base_buffer(base_text);
+            history.apply_ops(operations.iter().map(|(_, operation)| operation.clone()));
+            history.restore_undo_history(undo_stack, redo_stack, Instant::now());
+            let (buffer, saved_version) = compact_history(history, saved_text);
+            compacted_log(&buffer, &saved_version, saved_text_fingerprint)
+        })
+    }
+
+    /// Serializes the undo history of a buffer without unsaved changes, as a log starting from
+    /// its saved text whose operations are the edits of the last [`MAX_COMPACTED_TRANSACTIONS`]
+    /// transactions of its undo and redo stacks. [`Buffer::restore_operation_log`] restores it
+    /// on the buffer of the file, as long as the file still has the saved text.
+    pub fn undo_history_log(&self, cx: &App) -> Task<proto::BufferOperationLog> {
+        let snapshot = self.text.snapshot();
+        let base_text = self.base_text().clone();
+        let operations = self.text.operations().clone();
+        let (undo_stack, redo_stack) = self.text.undo_history();
+        let saved_version = self.saved_version.clone();
+        let replica_id = self.replica_id();
+
+        cx.background_executor().spawn(async move {
+            let saved_text = snapshot.rope_for_version(&saved_version);
+            let saved_text_fingerprint = saved_text.fingerprint().to_u64();
+            let mut history = TextBuffer::new_normalized(
+                replica_id,
+                snapshot.remote_id(),
+                snapshot.line_ending(),
+                base_text,
+            );
+            history.apply_ops(operations.iter().map(|(_, operation)| operation.clone()));
+            history.restore_undo_history(undo_stack, redo_stack, Instant::now());
+            let (buffer, saved_version) = compact_history(history, saved_text);
+            compacted_log(&buffer, &saved_version, saved_text_fingerprint)
+        })
+    }
+
+    /// Replays a log produced by [`Buffer::operation_log`], restoring the text, undo history
+    /// and dirty state the buffer had when the log was taken.
+    ///
+    /// The buffer must not have been edited, and its text must be the base text of the log.
+    pub fn restore_operation_log(
+        &mut self,
+        log: proto::BufferOperationLog,
+        cx: &mut Context<Self>,
+    ) -> Result<()> {
+        if !self.text.operations().is_empty() || self.base_text().to_string() != log.base_text {
+            return Err(anyhow!(
+                "the operation log doesn't start from the text of the buffer"
+            ));
+        }
+        let operations = log
+            .operations
+            .into_iter()
+            .map(|operation| match proto::deserialize_operation(operation)? {
+                crate::Operation::Buffer(operation) => Ok(operation),
+                _ => Err(anyhow!("the operation log contains a non-text operation")),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let undo_stack = log
+            .undo_stack
+            .into_iter()
+            .map(proto::deserialize_transaction)
+            .collect::<Result<Vec<_>>>()?;
+        let redo_stack = log
+            .redo_stack
+            .into_iter()
+            .map(proto::deserialize_transaction)
+            .collect::<Result<Vec<_>>>()?;
+
+        // The operations restore the buffer as it was before it was closed, so they aren't
+        // sent to collaborators, which only get to see the buffer once it's restored.
+        let was_dirty = self.is_dirty();
+        let old_version = self.version();
+        self.text.apply_ops(operations);
+        if self.text.has_deferred_ops() {
+            return Err(anyhow!("the operation log is missing operations"));
+        }
+        if let Some(line_ending) = rpc::proto::LineEnding::from_i32(log.line_ending) {
+            self.text
+                .set_line_ending(proto::deserialize_line_ending(line_ending));
+        }
+        self.text
+            .restore_undo_history(undo_stack, redo_stack, Instant::now());
+        self.saved_version = proto::deserialize_version(&log.saved_version);
+        self.has_unsaved_edits
+            .set((self.version(), self.has_edits_since(&self.saved_version)));
+        self.did_edit(&old_version, was_dirty, cx);
+        Ok(())
+    }
+}
+
+/// Rebuilds the history of a buffer on a new buffer whose base text is the saved text, going
+/// through the texts of the last [`MAX_COMPACTED_TRANSACTIONS`] transactions of its undo and
+/// redo stacks as new transactions. Returns the new buffer and the version at which it has the
+/// saved text.
+fn compact_history(mut history: TextBuffer, saved_text: Rope) -> (TextBuffer, clock::Global) {
+    // The edits redoing each transaction of the redo stack, in order.
+    let mut redo_steps = Vec::new();
+    while redo_steps.len() < MAX_COMPACTED_TRANSACTIONS {
+        let old_version = history.version();
+        if history.redo().is_none() {
+            break;
+        }
+        redo_steps.push(
+            history
+                .edits_since::<usize>(&old_version)
+                .map(|edit| {
+                    (
+                        edit.old,
+                        history.text_for_range(edit.new).collect::<String>(),
+                    )
+                })
+                .collect::<Vec<_>>(),
+        );
+    }
+    for _ in 0..redo_steps.len() {
+        history.undo();
+    }
+    // The edits redoing each transaction of the undo stack, from the most recent one.
+    let mut undo_steps = Vec::new();
+    while undo_steps.len() < MAX_COMPACTED_TRANSACTIONS {
+        let old_snapshot = history.snapshot();
+        if history.undo().is_none() {
+            break;
+        }
+        undo_steps.push(
+            history
+                .edits_since::<usize>(old_snapshot.version())
+                .map(|edit| {
+                    (
+                        edit.new,
+                        old_snapshot.text_for_range(edit.old).collect::<String>(),
+                    )
+                })
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    let saved_fingerprint = saved_text.fingerprint();
+    let mut buffer = TextBuffer::new_normalized(
+        history.replica_id(),
+        history.remote_id(),
+        history.line_ending(),
+        saved_text,
+    );
+    let mut saved_version = buffer.version();
+    let transact = |buffer: &mut TextBuffer, edits: Vec<(Range<usize>, String)>| {
+        buffer.start_transaction();
+        buffer.edit(edits);
+        let transaction_id = buffer
+            .end_transaction()
+            .map(|(transaction_id, _)| transaction_id);
+        buffer.finalize_last_transaction();
+        transaction_id
+    };
+
+    // The oldest kept text is reached without a transaction, since there's nothing to undo to.
+    let (range, new_text) = changed_range(&buffer.text(), &history.text());
+    if !range.is_empty() || !new_text.is_empty() {
+        if let Some(transaction_id) = transact(&mut buffer, vec![(range, new_text)]) {
+            buffer.forget_transaction(transaction_id);
+        }
+    }
+    for edits in undo_steps.into_iter().rev() {
+        transact(&mut buffer, edits);
+        if buffer.fingerprint() == saved_fingerprint {
+            saved_version = buffer.version();
+        }
+    }
+    let redo_count = redo_steps.len();
+    for edits in redo_steps {
+        transact(&mut buffer, edits);
+    }
+    for _ in 0..redo_count {
+        buffer.undo();
+    }
+    (buffer, saved_version)
+}
+
+/// Serializes a buffer rebuilt by [`compact_history`].
+fn compacted_log(
+    buffer: &TextBuffer,
+    saved_version: &clock::Global,
+    saved_text_fingerprint: u64,
+) -> proto::BufferOperationLog {
+    let (undo_stack, redo_stack) = buffer.undo_history();
+    proto::BufferOperationLog {
+        base_text: buffer.base_text().to_string(),
+        line_ending: proto::serialize_line_ending(buffer.line_ending()) as i32,
+        operations: buffer
+            .operations()
+            .iter()
+            .map(|(_, operation)| {
+                proto::serialize_operation(&crate::Operation::Buffer(operation.clone()))
+            })
+            .collect(),
+        undo_stack: undo_stack
+            .iter()
+            .map(proto::serialize_transaction)
+            .collect(),
+        redo_stack: redo_stack
+            .iter()
+            .map(proto::serialize_transaction)
+            .collect(),
+        saved_version: proto::serialize_version(saved_version),
+        saved_text_fingerprint,
+    }
+}
+
+/// Returns the smallest edit turning `old_text` into `new_text`.
+fn changed_range(old_text: &str, new_text: &str) -> (Range<usize>, String) {
+    let mut prefix_len = old_text
+        .bytes()
+        .zip(new_text.bytes())
+        .take_while(|(old, new)| old == new)
+        .count();
+    while !old_text.is_char_boundary(prefix_len) {
+        prefix_len -= 1;
+    }
+    let mut suffix_len = old_text[prefix_len..]
+        .bytes()
+        .rev()
+        .zip(new_text[prefix_len..].bytes().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    while !old_text.is_char_boundary(old_text.len() - suffix_len) {
+        suffix_len -= 1;
+    }
+    (
+        prefix_len..old_text.len() - suffix_len,
+        new_text[prefix_len..new_text.len() - suffix_len].to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::{AppContext as _, TestAppContext};
+
+    #[gpui::test]
+    async fn test_compacted_operation_log(cx: &mut TestAppContext) {
+        let buffer = cx.new(|cx| Buffer::local("", cx));
+        buffer.update(cx, |buffer, cx| {
+            for _ in 0..MAX_LOGGED_OPERATIONS {
+                buffer.edit([(buffer.len()..buffer.len(), "a")], None, cx);
+                buffer.finalize_last_transaction();
+            }
+            buffer.undo(cx);
+        });
+        let log = buffer
+            .update(cx, |buffer, cx| buffer.operation_log(cx))
+            .await;
+        assert_eq!(log.base_text, "");
+        assert!(log.operations.len() < MAX_LOGGED_OPERATIONS);
+
+        let restored = cx.new(|cx| Buffer::local("", cx));
+        restored.update(cx, |restored, cx| {
+            restored.restore_operation_log(log, cx).unwrap();
+            assert_eq!(restored.text(), "a".repeat(MAX_LOGGED_OPERATIONS - 1));
+            assert!(restored.is_dirty());
+
+            // The most recent transactions can still be undone and redone.
+            restored.redo(cx);
+            assert_eq!(restored.text(), "a".repeat(MAX_LOGGED_OPERATIONS));
+            assert!(restored.redo(cx).is_none());
+            let mut undo_count = 0;
+            while restored.undo(cx).is_some() {
+                undo_count += 1;
+            }
+            assert_eq!(undo_count, MAX_COMPACTED_TRANSACTIONS + 1);
+            assert_eq!(
+                restored.text(),
+                "a".repeat(MAX_LOGGED_OPERATIONS - MAX_COMPACTED_TRANSACTIONS - 1)
+            );
+        });
+    }
+
+    #[gpui::test]
+    async fn test_operation_log_after_save(cx: &mut TestAppContext) {
+        let buffer = cx.new(|cx| Buffer::local("one", cx));
+        buffer.update(cx, |buffer, cx| {
+            buffer.edit([(buffer.len()..buffer.len(), " two")], None, cx);
+            buffer.finalize_last_transaction();
+            buffer.did_save(buffer.version(), None, cx);
+            buffer.edit([(buffer.len()..buffer.len(), " three")], None, cx);
+            buffer.finalize_last_transaction();
+        });
+        let log = buffer
+            .update(cx, |buffer, cx| buffer.operation_log(cx))
+            .await;
+        assert_eq!(log.base_text, "one two");
+
+        let restored = cx.new(|cx| Buffer::local("one two", cx));
+        restored.update(cx, |restored, cx| {
+            restored.restore_operation_log(log, cx).unwrap();
+            assert_eq!(restored.text(), "one two three");
+            assert!(restored.is_dirty());
+
+            // The edits made before and after saving can still be undone and redone.
+            restored.undo(cx);
+            assert_eq!(restored.text(), "one two");
+            assert!(!restored.is_dirty());
+            restored.undo(cx);
+            assert_eq!(restored.text(), "one");
+            assert!(restored.is_dirty());
+            assert!(restored.undo(cx).is_none());
+            restored.redo(cx);
+            restored.redo(cx);
+            assert_eq!(restored.text(), "one two three");
+        });
+    }
+
+    #[test]
+    fn test_changed_range() {
+        assert_eq!(changed_range("abc", "abc"), (3..3, String::new()));
+        assert_eq!(changed_range("abcd", "axyd"), (1..3, "xy".to_string()));
+        assert_eq!(changed_range("aéb", "aèb"), (1..3, "è".to_string()));
+        assert_eq!(changed_range("", "new"), (0..0, "new".to_string()));
+    }
+}
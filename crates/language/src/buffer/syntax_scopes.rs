@@ -0,0 +1,145 @@
+//! The syntax scopes of the lines of a buffer, by which the settings in effect at a position are
+//! resolved.
+//!
+//! The scope of a position is the [`LanguageScope`] of the smallest syntax node containing it,
+//! whose override name, like `string` or `comment`, selects the `scopes` settings applying
+//! there. It can only change at the boundaries of syntax nodes, so the scopes of a line are
+//! looked up once, at those boundaries and between them, the first time the scope of one of its
+//! positions is requested. They are then shared by the snapshots of the buffer until it is
+//! edited or reparsed.
+
+use super::{Buffer, BufferSnapshot};
+use crate::LanguageScope;
+use collections::HashMap;
+use parking_lot::Mutex;
+use std::{ops::Range, sync::Arc};
+use text::{Point, ToOffset};
+
+/// The scopes of the lines of a buffer, in a given state of the buffer.
+#[derive(Default)]
+pub(crate) struct SyntaxScopes {
+    version: clock::Global,
+    non_text_state_update_count: usize,
+    lines: Mutex<HashMap<u32, Arc<[ScopeRun]>>>,
+}
+
+/// Consecutive offsets of a line that are all in the same scope.
+struct ScopeRun {
+    offsets: Range<usize>,
+    scope: Option<LanguageScope>,
+}
+
+impl Buffer {
+    /// Returns the scopes of the buffer in the state of the given version, reusing those of the
+    /// previous snapshots when the buffer didn't change since they were taken.
+    pub(crate) fn syntax_scopes(&self, version: &clock::Global) -> Arc<SyntaxScopes> {
+        let mut syntax_scopes = self.syntax_scopes.lock();
+        if syntax_scopes.version != *version
+            || syntax_scopes.non_text_state_update_count != self.non_text_state_update_count
+        {
+            *syntax_scopes = Arc::new(SyntaxScopes {
+                version: version.clone(),
+                non_text_state_update_count: self.non_text_state_update_count,
+                lines: Default::default(),
+            });
+        }
+        syntax_scopes.clone()
+    }
+}
+
+impl BufferSnapshot {
+    /// Returns the same scope as [`Self::language_scope_at`], looked up in the scopes of the
+    /// position's line, which are computed once for all of its positions.
+    pub fn syntax_scope_at<D: ToOffset>(&self, position: D) -> Option<LanguageScope> {
+        let offset = position.to_offset(self);
+        let row = self.offset_to_point(offset).row;
+        let cached_runs = self.syntax_scopes.lines.lock().get(&row).cloned();
+        let runs = cached_runs.unwrap_or_else(|| {
+            let runs: Arc<[ScopeRun]> = self.line_scope_runs(row).into();
+            self.syntax_scopes.lines.lock().insert(row, runs.clone());
+            runs
+        });
+
+        let ix = runs.partition_point(|run| run.offsets.end <= offset);
+        runs.get(ix)
+            .filter(|run| run.offsets.contains(&offset))
+            .and_then(|run| run.scope.clone())
+    }
+
+    fn line_scope_runs(&self, row: u32) -> Vec<ScopeRun> {
+        let line_start = self.point_to_offset(Point::new(row, 0));
+        let line_end = self.point_to_offset(Point::new(row, self.line_len(row)));
+
+        // The scope of a position is found by descending into the nodes containing it,
+        // including at their ends, so it can only change at the ends of the nodes of the line.
+        let mut boundaries = vec![line_start, line_end];
+        for layer in self
+            .syntax
+            .layers_for_range(line_start..line_end, &self.text, false)
+        {
+            let mut cursor = layer.node().walk();
+            'nodes: loop {
+                let node_range = cursor.node().byte_range();
+                if node_range.start <= line_end {
+                    if node_range.end >= line_start {
+                        boundaries.extend([node_range.start, node_range.end]);
+                        if cursor.goto_first_child_for_byte(line_start).is_some() {
+                            continue;
+                        }
+                    }
+                    if cursor.goto_next_sibling() {
+                        continue;
+                    }
+                }
+                loop {
+                    if !cursor.goto_parent() {
+                        break 'nodes;
+                    }
+                    if cursor.goto_next_sibling() {
+                        continue 'nodes;
+                    }
+                }
+            }
+        }
+        boundaries.retain(|offset| (line_start..=line_end).contains(offset));
+        boundaries.sort_unstable();
+        boundaries.dedup();
+
+        let mut runs = Vec::<ScopeRun>::new();
+        let mut push_run = |offsets: Range<usize>, scope: Option<LanguageScope>| {
+            if let Some(last_run) = runs.last_mut() {
+                if last_run.offsets.end == offsets.start && same_scope(&last_run.scope, &scope) {
+                    last_run.offsets.end = offsets.end;
+                    return;
+                }
+            }
+            runs.push(ScopeRun { offsets, scope });
+        };
+        for (ix, &boundary) in boundaries.iter().enumerate() {
+            push_run(boundary..boundary + 1, self.language_scope_at(boundary));
+
+            // The positions between two boundaries are all in the same scope as the first one.
+            if let Some(&next_boundary) = boundaries.get(ix + 1) {
+                let first_inner_offset =
+                    boundary + self.chars_at(boundary).next().map_or(1, char::len_utf8);
+                if first_inner_offset < next_boundary {
+                    push_run(
+                        boundary + 1..next_boundary,
+                        self.language_scope_at(first_inner_offset),
+                    );
+                }
+            }
+        }
+        runs
+    }
+}
+
+fn same_scope(a: &Option<LanguageScope>, b: &Option<LanguageScope>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            Arc::ptr_eq(&a.language, &b.language) && a.override_id == b.override_id
+        }
+        (None, None) => true,
+        _ => false,
+    }
+}
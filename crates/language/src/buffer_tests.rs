@@ -1,6 +1,7 @@
 use super::*;
 use crate::language_settings::{
-    AllLanguageSettings, AllLanguageSettingsContent, LanguageSettingsContent,
+    AllLanguageSettings, AllLanguageSettingsContent, DisabledFeature, DisabledFeatures,
+    LanguageSettingsContent,
 };
 use crate::Buffer;
 use clock::ReplicaId;
@@ -173,6 +174,56 @@ async fn test_first_line_pattern(cx: &mut TestAppContext) {
     );
 }
 
+#[gpui::test]
+fn test_shebang_and_modeline_configuration(cx: &mut App) {
+    init_settings(cx, |_| {});
+
+    let registry = Arc::new(LanguageRegistry::test(cx.background_executor().clone()));
+    for (name, suffix) in [("Python", "py"), ("Ruby", "rb")] {
+        registry.add(Arc::new(Language::new(
+            LanguageConfig {
+                name: LanguageName::new(name),
+                matcher: LanguageMatcher {
+                    path_suffixes: vec![suffix.to_string()],
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            None,
+        )));
+    }
+
+    let language_for = |path: &str, content: &str, cx: &App| {
+        registry
+            .language_for_file(&file(path), Some(&content.into()), cx)
+            .map(|language| language.name())
+    };
+    assert_eq!(
+        language_for("bin/script", "#!/usr/bin/env python3\n", cx),
+        Some("Python".into())
+    );
+    assert_eq!(
+        language_for("lib/script.py", "# vim: ft=ruby\n", cx),
+        Some("Ruby".into())
+    );
+    assert_eq!(
+        language_for("script", "# -*- mode: python -*-\n", cx),
+        Some("Python".into())
+    );
+
+    let buffer = cx.new(|cx| Buffer::local("# vim: ts=2 noet tw=60\nfn main() {}\n", cx));
+    let snapshot = buffer.read(cx).snapshot();
+    let settings = snapshot.settings_at(0, cx);
+    assert_eq!(settings.tab_size.get(), 2);
+    assert!(settings.hard_tabs);
+    assert_eq!(settings.preferred_line_length, 60);
+
+    buffer.update(cx, |buffer, cx| buffer.edit([(0..22, "")], None, cx));
+    let snapshot = buffer.read(cx).snapshot();
+    assert_eq!(snapshot.modeline(), None);
+    assert_eq!(snapshot.settings_at(0, cx).tab_size.get(), 4);
+}
+
 #[gpui::test]
 async fn test_language_for_file_with_custom_file_types(cx: &mut TestAppContext) {
     cx.update(|cx| {
@@ -256,6 +307,50 @@ fn file(path: &str) -> Arc<dyn File> {
     })
 }
 
+#[gpui::test]
+fn test_disabled_features(cx: &mut App) {
+    init_settings(cx, |settings| {
+        settings.disabled_features.extend([
+            (
+                "**/*.min.rs".into(),
+                vec![
+                    DisabledFeature::SyntaxHighlighting,
+                    DisabledFeature::Diagnostics,
+                ],
+            ),
+            ("**/*.rs".into(), vec![DisabledFeature::InlayHints]),
+        ]);
+    });
+
+    cx.new(|cx| {
+        let mut buffer = Buffer::local("fn a() {}", cx).with_language(Arc::new(rust_lang()), cx);
+        assert_eq!(buffer.disabled_features(), DisabledFeatures::default());
+
+        buffer.file_updated(file("src/app.min.rs"), cx);
+        assert_eq!(
+            buffer.disabled_features(),
+            DisabledFeatures {
+                syntax_highlighting: true,
+                diagnostics: true,
+                inlay_hints: true,
+                soft_wrap: false,
+            }
+        );
+        assert!(buffer.snapshot().syntax_layers().next().is_none());
+
+        // The features are re-evaluated when the buffer's path changes.
+        buffer.file_updated(file("src/app.rs"), cx);
+        assert_eq!(
+            buffer.disabled_features(),
+            DisabledFeatures {
+                inlay_hints: true,
+                ..Default::default()
+            }
+        );
+        buffer
+    });
+}
+
 #[gpui::test]
 fn test_edit_events(cx: &mut gpui::App) {
     let mut now = Instant::now();
@@ -375,6 +470,84 @@ async fn test_apply_diff(cx: &mut TestAppContext) {
     });
 }
 
+#[gpui::test]
+async fn test_operation_log(cx: &mut TestAppContext) {
+    let text = "one\ntwo\nthree\n";
+    let buffer = cx.new(|cx| Buffer::local(text, cx));
+    buffer.update(cx, |buffer, cx| {
+        buffer.edit([(0..3, "ONE")], None, cx);
+        buffer.finalize_last_transaction();
+        buffer.edit([(8..13, "THREE")], None, cx);
+        buffer.finalize_last_transaction();
+        buffer.undo(cx);
+    });
+    let log = buffer
+        .update(cx, |buffer, cx| buffer.operation_log(cx))
+        .await;
+
+    let restored = cx.new(|cx| Buffer::local(text, cx));
+    restored.update(cx, |restored, cx| {
+        restored.restore_operation_log(log.clone(), cx).unwrap();
+        assert_eq!(restored.text(), "ONE\ntwo\nthree\n");
+        assert!(restored.is_dirty());
+
+        restored.redo(cx);
+        assert_eq!(restored.text(), "ONE\ntwo\nTHREE\n");
+        restored.undo(cx);
+        restored.undo(cx);
+        assert_eq!(restored.text(), text);
+        assert!(!restored.is_dirty());
+    });
+
+    // The log only applies to the text it started from.
+    let changed = cx.new(|cx| Buffer::local("changed on disk\n", cx));
+    changed.update(cx, |changed, cx| {
+        assert!(changed.restore_operation_log(log, cx).is_err());
+    });
+
+    // Once saved, the log starts from the saved text, without the undo history.
+    buffer.update(cx, |buffer, cx| {
+        buffer.did_save(buffer.version(), None, cx);
+        buffer.edit([(4..7, "TWO")], None, cx);
+    });
+    let log = buffer
+        .update(cx, |buffer, cx| buffer.operation_log(cx))
+        .await;
+    assert_eq!(log.base_text, "ONE\ntwo\nthree\n");
+    let restored = cx.new(|cx| Buffer::local("ONE\ntwo\nthree\n", cx));
+    restored.update(cx, |restored, cx| {
+        restored.restore_operation_log(log, cx).unwrap();
+        assert_eq!(restored.text(), "ONE\nTWO\nthree\n");
+        assert!(restored.is_dirty());
+        assert!(restored.undo(cx).is_none());
+    });
+
+    // The undo history of a saved buffer is restored when its file still has the saved text.
+    buffer.update(cx, |buffer, cx| {
+        buffer.undo(cx);
+        buffer.did_save(buffer.version(), None, cx);
+    });
+    let log = buffer
+        .update(cx, |buffer, cx| buffer.undo_history_log(cx))
+        .await;
+    assert_eq!(log.base_text, "ONE\ntwo\nthree\n");
+    let restored = cx.new(|cx| Buffer::local("ONE\ntwo\nthree\n", cx));
+    restored.update(cx, |restored, cx| {
+        restored.restore_operation_log(log.clone(), cx).unwrap();
+        assert_eq!(restored.text(), "ONE\ntwo\nthree\n");
+        assert!(!restored.is_dirty());
+        restored.redo(cx);
+        assert_eq!(restored.text(), "ONE\nTWO\nthree\n");
+        restored.undo(cx);
+        restored.undo(cx);
+        assert_eq!(restored.text(), text);
+    });
+    let changed = cx.new(|cx| Buffer::local("ONE\ntwo\nthree\nfour\n", cx));
+    changed.update(cx, |changed, cx| {
+        assert!(changed.restore_operation_log(log, cx).is_err());
+    });
+}
+
 #[gpui::test(iterations = 10)]
 async fn test_normalize_whitespace(cx: &mut gpui::TestAppContext) {
     let text = [
@@ -448,6 +621,44 @@ async fn test_normalize_whitespace(cx: &mut gpui::TestAppContext) {
     });
 }
 
+#[gpui::test]
+async fn test_evicting_syntax_tree(cx: &mut gpui::TestAppContext) {
+    let buffer =
+        cx.new(|cx| Buffer::local("fn a() {}", cx).with_language(Arc::new(rust_lang()), cx));
+    cx.executor().run_until_parked();
+
+    // The tree of a buffer that is still queried is kept.
+    buffer.update(cx, |buffer, cx| {
+        buffer.snapshot();
+        assert!(!buffer.evict_syntax_tree(cx));
+        assert!(buffer.evict_syntax_tree(cx));
+        assert!(!buffer.evict_syntax_tree(cx));
+        assert!(buffer.is_syntax_tree_evicted());
+        let snapshot = buffer.snapshot();
+        assert!(snapshot.syntax.layers(buffer.as_text_snapshot()).is_empty());
+        buffer.restore_syntax_tree(cx);
+    });
+    cx.executor().run_until_parked();
+    assert!(!buffer.update(cx, |buffer, _| buffer.is_syntax_tree_evicted()));
+    assert_eq!(
+        get_tree_sexp(&buffer, cx),
+        "(source_file (function_item name: (identifier) parameters: (parameters) body: (block)))"
+    );
+
+    // Edits parse an evicted buffer again.
+    buffer.update(cx, |buffer, cx| {
+        buffer.evict_syntax_tree(cx);
+        assert!(buffer.evict_syntax_tree(cx));
+        buffer.edit([(0..0, "\n")], None, cx);
+    });
+    cx.executor().run_until_parked();
+    assert!(!buffer.update(cx, |buffer, _| buffer.is_syntax_tree_evicted()));
+    assert_eq!(
+        get_tree_sexp(&buffer, cx),
+        "(source_file (function_item name: (identifier) parameters: (parameters) body: (block)))"
+    );
+}
+
 #[gpui::test]
 async fn test_reparse(cx: &mut gpui::TestAppContext) {
     let text = "fn a() {}";
@@ -2345,6 +2556,103 @@ fn test_language_scope_at_with_rust(cx: &mut App) {
     });
 }
 
+#[gpui::test]
+fn test_settings_at_in_syntax_scopes(cx: &mut App) {
+    init_settings(cx, |settings| {
+        settings.defaults.preferred_line_length = Some(100);
+        settings.defaults.scopes = Some(
+            [(
+                "comment".into(),
+                LanguageSettingsContent {
+                    preferred_line_length: Some(72),
+                    ..Default::default()
+                },
+            )]
+            .into_iter()
+            .collect(),
+        );
+        settings.languages.insert(
+            "Rust".into(),
+            LanguageSettingsContent {
+                scopes: Some(
+                    [(
+                        "string".into(),
+                        LanguageSettingsContent {
+                            use_autoclose: Some(false),
+                            ..Default::default()
+                        },
+                    )]
+                    .into_iter()
+                    .collect(),
+                ),
+                ..Default::default()
+            },
+        );
+    });
+
+    cx.new(|cx| {
+        let language = Language::new(
+            LanguageConfig {
+                name: "Rust".into(),
+                ..Default::default()
+            },
+            Some(tree_sitter_rust::LANGUAGE.into()),
+        )
+        .with_override_query(
+            r#"
+                (string_literal) @string
+                (line_comment) @comment.inclusive
+            "#,
+        )
+        .unwrap();
+
+        let text = r#"
+            fn main() {
+                // Greet
+                let s = "hello"; // twice
+            }
+        "#
+        .unindent();
+
+        let buffer = Buffer::local(text.clone(), cx).with_language(Arc::new(language), cx);
+        let snapshot = buffer.snapshot();
+
+        // The scopes looked up by line are the ones looked up by position.
+        for offset in 0..=text.len() {
+            assert_eq!(
+                snapshot
+                    .syntax_scope_at(offset)
+                    .as_ref()
+                    .and_then(|scope| scope.override_name().map(ToOwned::to_owned)),
+                snapshot
+                    .language_scope_at(offset)
+                    .as_ref()
+                    .and_then(|scope| scope.override_name().map(ToOwned::to_owned)),
+                "scope at offset {offset}"
+            );
+        }
+
+        let settings = snapshot.settings_at(text.find("let").unwrap(), cx);
+        assert_eq!(settings.preferred_line_length, 100);
+        assert!(settings.use_autoclose);
+
+        // The scope settings of the defaults apply to every language, and those of
+        // `comment` to its `comment.inclusive` scope.
+        let settings = snapshot.settings_at(text.find("Greet").unwrap(), cx);
+        assert_eq!(settings.preferred_line_length, 72);
+        assert!(settings.use_autoclose);
+        let settings = snapshot.settings_at(text.find("twice").unwrap(), cx);
+        assert_eq!(settings.preferred_line_length, 72);
+
+        // The scope settings of a language use its own settings.
+        let settings = snapshot.settings_at(text.find("ello").unwrap(), cx);
+        assert_eq!(settings.preferred_line_length, 100);
+        assert!(!settings.use_autoclose);
+
+        buffer
+    });
+}
+
 #[gpui::test]
 fn test_language_scope_at_with_combined_injections(cx: &mut App) {
     init_settings(cx, |_| {});
@@ -3061,6 +3369,162 @@ fn test_random_collaboration(cx: &mut App, mut rng: StdRng) {
     }
 }
 
+#[gpui::test(iterations = 50)]
+async fn test_random_collaboration_syntax_trees(cx: &mut TestAppContext, mut rng: StdRng) {
+    let peers = env::var("PEERS")
+        .map(|i| i.parse().expect("invalid `PEERS` variable"))
+        .unwrap_or(3);
+    let operations = env::var("OPERATIONS")
+        .map(|i| i.parse().expect("invalid `OPERATIONS` variable"))
+        .unwrap_or(10);
+
+    let language = Arc::new(rust_lang());
+    let base_text = "fn main() {\n    let x = [1, 2];\n    if x.is_empty() { return; }\n}\n";
+    let network = Arc::new(Mutex::new(Network::new(rng.clone())));
+    network.lock().set_drop_probability(rng.gen_range(0.0..0.5));
+    let base_buffer = cx.new(|cx| Buffer::local(base_text, cx));
+
+    let mut buffers = Vec::new();
+    for i in 0..peers {
+        let buffer = cx.new(|cx| {
+            let state = base_buffer.read(cx).to_proto(cx);
+            let ops = cx
+                .background_executor()
+                .block(base_buffer.read(cx).serialize_ops(None, cx));
+            let mut buffer =
+                Buffer::from_proto(i as ReplicaId, Capability::ReadWrite, state, None).unwrap();
+            buffer.apply_ops(
+                ops.into_iter()
+                    .map(|op| proto::deserialize_operation(op).unwrap()),
+                cx,
+            );
+            buffer.set_language(Some(language.clone()), cx);
+            let network = network.clone();
+            cx.subscribe(&cx.entity(), move |buffer, _, event, _| {
+                if let BufferEvent::Operation {
+                    operation,
+                    is_local: true,
+                } = event
+                {
+                    network.lock().broadcast(
+                        buffer.replica_id(),
+                        vec![proto::serialize_operation(operation)],
+                    );
+                }
+            })
+            .detach();
+            buffer
+        });
+        buffers.push(buffer);
+        network.lock().add_peer(i as ReplicaId);
+    }
+
+    let mut mutation_count = operations;
+    loop {
+        let replica_index = rng.gen_range(0..peers);
+        let replica_id = replica_index as ReplicaId;
+        let buffer = &buffers[replica_index];
+        match rng.gen_range(0..100) {
+            0..=49 if mutation_count != 0 => {
+                buffer.update(cx, |buffer, cx| {
+                    buffer.randomly_edit(&mut rng, 3, cx);
+                    log::info!("buffer {} text: {:?}", buffer.replica_id(), buffer.text());
+                });
+                mutation_count -= 1;
+            }
+            50..=59 if mutation_count != 0 => {
+                buffer.update(cx, |buffer, cx| buffer.randomly_undo_redo(&mut rng, cx));
+                mutation_count -= 1;
+            }
+            60..=89 if network.lock().has_unreceived(replica_id) => {
+                let ops = network
+                    .lock()
+                    .receive(replica_id)
+                    .into_iter()
+                    .map(|op| proto::deserialize_operation(op).unwrap())
+                    .collect::<Vec<_>>();
+                log::info!(
+                    "peer {} applying {} ops from the network",
+                    replica_id,
+                    ops.len()
+                );
+                buffer.update(cx, |buffer, cx| buffer.apply_ops(ops, cx));
+            }
+            90..=99 => {
+                let other_index = rng.gen_range(0..peers);
+                log::info!(
+                    "peer {} synchronizing with peer {}",
+                    replica_id,
+                    other_index
+                );
+                synchronize_buffers(buffer, &buffers[other_index], cx).await;
+            }
+            _ => {}
+        }
+
+        // Reparse between some of the operations, so that the trees are edited incrementally
+        // by operations arriving in different orders on each replica.
+        if rng.gen_bool(0.3) {
+            cx.executor().run_until_parked();
+        }
+
+        if mutation_count == 0 && network.lock().is_idle() {
+            break;
+        }
+    }
+
+    log::info!(
+        "{} messages were dropped",
+        network.lock().dropped_message_count()
+    );
+    for buffer in &buffers {
+        for other_buffer in &buffers {
+            synchronize_buffers(buffer, other_buffer, cx).await;
+        }
+    }
+    cx.executor().run_until_parked();
+
+    // Every replica's tree matches the tree of its text parsed from scratch.
+    let text = buffers[0].read_with(cx, |buffer, _| buffer.text());
+    let reparsed_buffer =
+        cx.new(|cx| Buffer::local(text.as_str(), cx).with_language(language.clone(), cx));
+    cx.executor().run_until_parked();
+    let expected_tree = get_tree_sexp(&reparsed_buffer, cx);
+    for buffer in &buffers {
+        buffer.read_with(cx, |buffer, _| {
+            assert_eq!(buffer.text(), text);
+            assert!(!buffer.is_parsing());
+        });
+        assert_eq!(
+            get_tree_sexp(buffer, cx),
+            expected_tree,
+            "replica {} syntax tree != tree of its reparsed text",
+            buffer.read_with(cx, |buffer, _| buffer.replica_id())
+        );
+    }
+}
+
+/// Applies the operations observed by one replica that another replica is missing.
+async fn synchronize_buffers(
+    buffer: &Entity<Buffer>,
+    other_buffer: &Entity<Buffer>,
+    cx: &mut TestAppContext,
+) {
+    let version = buffer.read_with(cx, |buffer, _| buffer.version());
+    let ops = other_buffer
+        .read_with(cx, |other_buffer, cx| {
+            other_buffer.serialize_ops(Some(version), cx)
+        })
+        .await;
+    buffer.update(cx, |buffer, cx| {
+        buffer.apply_ops(
+            ops.into_iter()
+                .map(|op| proto::deserialize_operation(op).unwrap()),
+            cx,
+        )
+    });
+}
+
 #[test]
 fn test_contiguous_ranges() {
     assert_eq!(
@@ -3079,6 +3543,35 @@ fn test_contiguous_ranges() {
     );
 }
 
+#[test]
+fn test_merge_diagnostics() {
+    let diagnostic = |range: Range<usize>, source: &str, severity, message: &str| DiagnosticEntry {
+        range,
+        diagnostic: Diagnostic {
+            source: Some(source.to_string()),
+            severity,
+            message: message.to_string(),
+            ..Default::default()
+        },
+    };
+    let merged = merge_diagnostics([
+        diagnostic(0..4, "spell", DiagnosticSeverity::INFORMATION, "typo"),
+        diagnostic(0..4, "rustc", DiagnosticSeverity::ERROR, "unresolved name"),
+        diagnostic(0..4, "clippy", DiagnosticSeverity::ERROR, "unresolved name"),
+        diagnostic(6..8, "rustc", DiagnosticSeverity::WARNING, "unused"),
+    ]);
+
+    assert_eq!(merged.len(), 2);
+    assert_eq!(merged[0].range, 0..4);
+    assert_eq!(merged[0].severity(), DiagnosticSeverity::ERROR);
+    assert_eq!(
+        merged[0].message(),
+        "rustc, clippy: unresolved name\nspell: typo"
+    );
+    assert_eq!(merged[1].range, 6..8);
+    assert_eq!(merged[1].message(), "rustc: unused");
+}
+
 #[gpui::test(iterations = 500)]
 fn test_trailing_whitespace_ranges(mut rng: StdRng) {
     // Generate a random multi-line string containing
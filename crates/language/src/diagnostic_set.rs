@@ -1,7 +1,7 @@
 use crate::{range_to_lsp, Diagnostic};
 use anyhow::Result;
 use collections::HashMap;
-use lsp::LanguageServerId;
+use lsp::{DiagnosticSeverity, LanguageServerId};
 use serde::Serialize;
 use std::{
     cmp::{Ordering, Reverse},
@@ -44,6 +44,86 @@ pub struct DiagnosticGroup<T> {
     pub primary_ix: usize,
 }
 
+/// The diagnostics reported at the same range of the buffer, by one or more
+/// sources, shown as one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MergedDiagnostic<T> {
+    /// The range of the buffer where the diagnostics apply.
+    pub range: Range<T>,
+    /// The diagnostics, most severe first. The ones reporting the same message
+    /// at the same severity are deduplicated into one, whose source lists all
+    /// of their sources.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl<T> MergedDiagnostic<T> {
+    /// The most severe of the diagnostics' severities.
+    pub fn severity(&self) -> DiagnosticSeverity {
+        self.diagnostics[0].severity
+    }
+
+    /// The messages of the diagnostics, one per line, labeled with their sources.
+    pub fn message(&self) -> String {
+        let mut message = String::new();
+        for (ix, diagnostic) in self.diagnostics.iter().enumerate() {
+            if ix > 0 {
+                message.push('\n');
+            }
+            if let Some(source) = &diagnostic.source {
+                message.push_str(source);
+                message.push_str(": ");
+            }
+            message.push_str(&diagnostic.message);
+        }
+        message
+    }
+}
+
+/// Merges the diagnostics reported at the same range, so that overlapping
+/// reports of different sources, like language servers, linters and spell
+/// checkers, are shown as one. The merged diagnostics keep the order in which
+/// their ranges first appear.
+pub fn merge_diagnostics<T: PartialEq>(
+    entries: impl IntoIterator<Item = DiagnosticEntry<T>>,
+) -> Vec<MergedDiagnostic<T>> {
+    let mut merged = Vec::<MergedDiagnostic<T>>::new();
+    for DiagnosticEntry { range, diagnostic } in entries {
+        let Some(existing) = merged.iter_mut().find(|merged| merged.range == range) else {
+            merged.push(MergedDiagnostic {
+                range,
+                diagnostics: vec![diagnostic],
+            });
+            continue;
+        };
+
+        let duplicate = existing.diagnostics.iter_mut().find(|existing| {
+            existing.severity == diagnostic.severity && existing.message == diagnostic.message
+        });
+        match duplicate {
+            Some(duplicate) => {
+                if let Some(source) = diagnostic.source {
+                    match &mut duplicate.source {
+                        Some(sources) => {
+                            if !sources.split(", ").any(|existing| existing == source) {
+                                sources.push_str(", ");
+                                sources.push_str(&source);
+                            }
+                        }
+                        None => duplicate.source = Some(source),
+                    }
+                }
+            }
+            None => {
+                let ix = existing
+                    .diagnostics
+                    .partition_point(|existing| existing.severity <= diagnostic.severity);
+                existing.diagnostics.insert(ix, diagnostic);
+            }
+        }
+    }
+    merged
+}
+
 impl DiagnosticGroup<Anchor> {
     /// Converts the entries in this [`DiagnosticGroup`] to a different buffer coordinate type.
     pub fn resolve<O: FromAnchor>(&self, buffer: &text::BufferSnapshot) -> DiagnosticGroup<O> {
@@ -70,6 +70,41 @@ impl Default for HighlightMap {
     }
 }
 
+/// The highlight map of a grammar, recomputed lazily when the theme changes.
+#[derive(Default)]
+pub(crate) struct ThemedHighlightMap {
+    map: HighlightMap,
+    /// The generation of the theme the map was computed for.
+    generation: usize,
+    /// The newer theme the map must be recomputed for when it's next used, and its generation.
+    stale_for: Option<(usize, Arc<SyntaxTheme>)>,
+}
+
+impl ThemedHighlightMap {
+    pub(crate) fn set_theme(&mut self, capture_names: &[&str], theme: &SyntaxTheme) {
+        self.map = HighlightMap::new(capture_names, theme);
+        self.stale_for = None;
+    }
+
+    pub(crate) fn invalidate(&mut self, theme: Arc<SyntaxTheme>, generation: usize) {
+        let newest_generation = self
+            .stale_for
+            .as_ref()
+            .map_or(self.generation, |(generation, _)| *generation);
+        if generation > newest_generation {
+            self.stale_for = Some((generation, theme));
+        }
+    }
+
+    pub(crate) fn get(&mut self, capture_names: &[&str]) -> &HighlightMap {
+        if let Some((generation, theme)) = self.stale_for.take() {
+            self.map = HighlightMap::new(capture_names, &theme);
+            self.generation = generation;
+        }
+        &self.map
+    }
+}
+
 impl Default for HighlightId {
     fn default() -> Self {
         DEFAULT_SYNTAX_HIGHLIGHT_ID
@@ -108,4 +143,35 @@ mod tests {
         assert_eq!(map.get(1).name(&theme), Some("function.async"));
         assert_eq!(map.get(2).name(&theme), Some("variable.builtin"));
     }
+
+    #[test]
+    fn test_themed_highlight_map() {
+        let theme = |names: &[&str]| {
+            Arc::new(SyntaxTheme {
+                highlights: names
+                    .iter()
+                    .map(|name| (name.to_string(), rgba(0x100000ff).into()))
+                    .collect(),
+            })
+        };
+        let capture_names = &["function", "variable"];
+        let mut map = ThemedHighlightMap::default();
+
+        // Invalidated maps are recomputed with the newest theme when they're next used.
+        let first_theme = theme(&["variable"]);
+        let second_theme = theme(&["variable", "function"]);
+        map.invalidate(first_theme, 1);
+        map.invalidate(second_theme.clone(), 2);
+        assert_eq!(
+            map.get(capture_names).get(0).name(&second_theme),
+            Some("function")
+        );
+
+        // Older themes don't invalidate them.
+        map.invalidate(theme(&[]), 1);
+        assert_eq!(
+            map.get(capture_names).get(1).name(&second_theme),
+            Some("variable")
+        );
+    }
 }
@@ -11,6 +11,7 @@ mod diagnostic_set;
 mod highlight_map;
 mod language_registry;
 pub mod language_settings;
+mod modeline;
 mod outline;
 pub mod proto;
 mod syntax_map;
@@ -30,9 +31,11 @@ use fs::Fs;
 use futures::Future;
 use gpui::{App, AsyncApp, Entity, SharedString, Task};
 pub use highlight_map::HighlightMap;
+use highlight_map::ThemedHighlightMap;
 use http_client::HttpClient;
 pub use language_registry::{LanguageName, LoadedLanguage};
 use lsp::{CodeActionKind, InitializeParams, LanguageServerBinary, LanguageServerBinaryOptions};
+pub use modeline::{shebang_interpreter, Modeline};
 use parking_lot::Mutex;
 use regex::Regex;
 use schemars::{
@@ -70,7 +73,7 @@ use util::serde::default_true;
 
 pub use buffer::Operation;
 pub use buffer::*;
-pub use diagnostic_set::{DiagnosticEntry, DiagnosticGroup};
+pub use diagnostic_set::{merge_diagnostics, DiagnosticEntry, DiagnosticGroup, MergedDiagnostic};
 pub use language_registry::{
     AvailableLanguage, LanguageNotFound, LanguageQueries, LanguageRegistry,
     LanguageServerBinaryStatus, QUERY_FILENAME_PREFIXES,
@@ -570,6 +573,10 @@ pub struct LanguageConfig {
     #[serde(default)]
     #[schemars(schema_with = "bracket_pair_config_json_schema")]
     pub brackets: BracketPairConfig,
+    /// Pairs of delimiters that selections can be surrounded with, in addition
+    /// to the bracket pairs.
+    #[serde(default)]
+    pub surround_pairs: Vec<SurroundPair>,
     /// If set to true, auto indentation uses last non empty line to determine
     /// the indentation level for a new line.
     #[serde(default = "auto_indent_using_last_non_empty_line_default")]
@@ -603,6 +610,9 @@ pub struct LanguageConfig {
     /// Starting and closing characters of a block comment.
     #[serde(default)]
     pub block_comment: Option<(Arc<str>, Arc<str>)>,
+    /// How string literals are written, for the commands editing the string under the cursor.
+    #[serde(default)]
+    pub string_literals: Option<StringLiteralConfig>,
     /// A list of language servers that are allowed to run on subranges of a given language.
     #[serde(default)]
     pub scope_opt_in_language_servers: Vec<LanguageServerName>,
@@ -702,6 +712,7 @@ impl Default for LanguageConfig {
             grammar: None,
             matcher: LanguageMatcher::default(),
             brackets: Default::default(),
+            surround_pairs: Default::default(),
             auto_indent_using_last_non_empty_line: auto_indent_using_last_non_empty_line_default(),
             auto_indent_on_paste: None,
             increase_indent_pattern: Default::default(),
@@ -709,6 +720,7 @@ impl Default for LanguageConfig {
             autoclose_before: Default::default(),
             line_comments: Default::default(),
             block_comment: Default::default(),
+            string_literals: None,
             scope_opt_in_language_servers: Default::default(),
             overrides: Default::default(),
             word_characters: Default::default(),
@@ -838,6 +850,48 @@ pub struct BracketPair {
     pub newline: bool,
 }
 
+/// How the string literals of a language are delimited and escaped.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+pub struct StringLiteralConfig {
+    /// The quotes delimiting strings that only differ by the quote they escape, which a string
+    /// can be converted between, like `"` and `'` in Python.
+    pub quotes: Vec<String>,
+    /// The prefix of raw strings, whose backslashes don't escape anything, like `r` in Rust.
+    #[serde(default)]
+    pub raw_prefix: Option<String>,
+    /// Whether raw strings can be delimited by `#`s after their prefix and before their closing
+    /// quote, so that they can contain quotes, like in Rust.
+    #[serde(default)]
+    pub raw_hashes: bool,
+    /// The prefix of format strings, whose replacement fields between braces are code rather
+    /// than content, like `f` in Python.
+    #[serde(default)]
+    pub format_prefix: Option<String>,
+}
+
+/// A pair of delimiters, possibly spanning several characters like `<!-- -->`,
+/// that selections can be surrounded with.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+pub struct SurroundPair {
+    /// The delimiter inserted before the selection.
+    pub start: String,
+    /// The delimiter inserted after the selection.
+    pub end: String,
+    /// The text that surrounds the selections with this pair when typed.
+    /// Defaults to `start` if it is a single character.
+    #[serde(default)]
+    pub trigger: Option<String>,
+}
+
+impl SurroundPair {
+    pub fn trigger(&self) -> Option<&str> {
+        self.trigger.as_deref().or_else(|| {
+            let mut chars = self.start.chars();
+            (chars.next().is_some() && chars.next().is_none()).then_some(self.start.as_str())
+        })
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
 pub(crate) struct LanguageId(usize);
 
@@ -871,6 +925,7 @@ pub struct Grammar {
     pub(crate) highlights_query: Option<Query>,
     pub(crate) brackets_config: Option<BracketConfig>,
     pub(crate) redactions_config: Option<RedactionConfig>,
+    pub(crate) no_wrap_config: Option<NoWrapConfig>,
     pub(crate) runnable_config: Option<RunnableConfig>,
     pub(crate) indents_config: Option<IndentConfig>,
     pub outline_config: Option<OutlineConfig>,
@@ -878,7 +933,7 @@ pub struct Grammar {
     pub embedding_config: Option<EmbeddingConfig>,
     pub(crate) injection_config: Option<InjectionConfig>,
     pub(crate) override_config: Option<OverrideConfig>,
-    pub(crate) highlight_map: Mutex<HighlightMap>,
+    pub(crate) highlight_map: Mutex<ThemedHighlightMap>,
 }
 
 struct IndentConfig {
@@ -960,6 +1015,11 @@ struct RedactionConfig {
     pub redaction_capture_ix: u32,
 }
 
+struct NoWrapConfig {
+    pub query: Query,
+    pub no_wrap_capture_ix: u32,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 enum RunnableCapture {
     Named(SharedString),
@@ -1021,6 +1081,7 @@ impl Language {
                     injection_config: None,
                     override_config: None,
                     redactions_config: None,
+                    no_wrap_config: None,
                     runnable_config: None,
                     error_query: Query::new(&ts_language, "(ERROR) @error").unwrap(),
                     ts_language,
@@ -1083,6 +1144,11 @@ impl Language {
                 .with_redaction_query(query.as_ref())
                 .context("Error loading redaction query")?;
         }
+        if let Some(query) = queries.no_wrap {
+            self = self
+                .with_no_wrap_query(query.as_ref())
+                .context("Error loading nowrap query")?;
+        }
         if let Some(query) = queries.runnables {
             self = self
                 .with_runnable_query(query.as_ref())
@@ -1448,6 +1514,25 @@ impl Language {
         Ok(self)
     }
 
+    pub fn with_no_wrap_query(mut self, source: &str) -> anyhow::Result<Self> {
+        let grammar = self
+            .grammar_mut()
+            .ok_or_else(|| anyhow!("cannot mutate grammar"))?;
+
+        let query = Query::new(&grammar.ts_language, source)?;
+        let mut no_wrap_capture_ix = None;
+        get_capture_indices(&query, &mut [("nowrap", &mut no_wrap_capture_ix)]);
+
+        if let Some(no_wrap_capture_ix) = no_wrap_capture_ix {
+            grammar.no_wrap_config = Some(NoWrapConfig {
+                query,
+                no_wrap_capture_ix,
+            });
+        }
+
+        Ok(self)
+    }
+
     fn grammar_mut(&mut self) -> Option<&mut Grammar> {
         Arc::get_mut(self.grammar.as_mut()?)
     }
@@ -1511,12 +1596,22 @@ impl Language {
     pub fn set_theme(&self, theme: &SyntaxTheme) {
         if let Some(grammar) = self.grammar.as_ref() {
             if let Some(highlights_query) = &grammar.highlights_query {
-                *grammar.highlight_map.lock() =
-                    HighlightMap::new(highlights_query.capture_names(), theme);
+                grammar
+                    .highlight_map
+                    .lock()
+                    .set_theme(highlights_query.capture_names(), theme);
             }
         }
     }
 
+    /// Sets the theme of the given generation, to compute the highlight map with when it's
+    /// next used.
+    pub fn invalidate_theme(&self, theme: Arc<SyntaxTheme>, generation: usize) {
+        if let Some(grammar) = self.grammar.as_ref() {
+            grammar.highlight_map.lock().invalidate(theme, generation);
+        }
+    }
+
     pub fn grammar(&self) -> Option<&Arc<Grammar>> {
         self.grammar.as_ref()
     }
@@ -1608,6 +1703,20 @@ impl LanguageScope {
             })
     }
 
+    /// Returns the pairs that selections can be surrounded with: the enabled
+    /// bracket pairs that allow surrounding, then the language's surround pairs.
+    pub fn surround_pairs(&self) -> Vec<SurroundPair> {
+        self.brackets()
+            .filter(|(pair, enabled)| *enabled && pair.surround)
+            .map(|(pair, _)| SurroundPair {
+                start: pair.start.clone(),
+                end: pair.end.clone(),
+                trigger: None,
+            })
+            .chain(self.language.config.surround_pairs.iter().cloned())
+            .collect()
+    }
+
     pub fn should_autoclose_before(&self, c: char) -> bool {
         c.is_whitespace() || self.language.config.autoclose_before.contains(c)
     }
@@ -1687,7 +1796,11 @@ impl Grammar {
     }
 
     pub fn highlight_map(&self) -> HighlightMap {
-        self.highlight_map.lock().clone()
+        let capture_names = self
+            .highlights_query
+            .as_ref()
+            .map_or(&[][..], |query| query.capture_names());
+        self.highlight_map.lock().get(capture_names).clone()
     }
 
     pub fn highlight_id_for_name(&self, name: &str) -> Option<HighlightId> {
@@ -1695,7 +1808,7 @@ impl Grammar {
             .highlights_query
             .as_ref()?
             .capture_index_for_name(name)?;
-        Some(self.highlight_map.lock().get(capture_id))
+        Some(self.highlight_map().get(capture_id))
     }
 }
 
@@ -2,9 +2,10 @@ use crate::{
     language_settings::{
         all_language_settings, AllLanguageSettingsContent, LanguageSettingsContent,
     },
+    shebang_interpreter,
     task_context::ContextProvider,
     with_parser, CachedLspAdapter, File, Language, LanguageConfig, LanguageId, LanguageMatcher,
-    LanguageServerName, LspAdapter, ToolchainLister, PLAIN_TEXT,
+    LanguageServerName, LspAdapter, Modeline, ToolchainLister, PLAIN_TEXT,
 };
 use anyhow::{anyhow, Context as _, Result};
 use collections::{hash_map, HashMap, HashSet};
@@ -113,6 +114,8 @@ struct LanguageRegistryState {
     loading_languages: HashMap<LanguageId, Vec<oneshot::Sender<Result<Arc<Language>>>>>,
     subscription: (watch::Sender<()>, watch::Receiver<()>),
     theme: Option<Arc<Theme>>,
+    /// The number of times the theme changed.
+    theme_generation: usize,
     version: usize,
     reload_count: usize,
 
@@ -192,6 +195,7 @@ pub const QUERY_FILENAME_PREFIXES: &[(
     ("injections", |q| &mut q.injections),
     ("overrides", |q| &mut q.overrides),
     ("redactions", |q| &mut q.redactions),
+    ("nowrap", |q| &mut q.no_wrap),
     ("runnables", |q| &mut q.runnables),
     ("textobjects", |q| &mut q.text_objects),
 ];
@@ -207,6 +211,7 @@ pub struct LanguageQueries {
     pub injections: Option<Cow<'static, str>>,
     pub overrides: Option<Cow<'static, str>>,
     pub redactions: Option<Cow<'static, str>>,
+    pub no_wrap: Option<Cow<'static, str>>,
     pub runnables: Option<Cow<'static, str>>,
     pub text_objects: Option<Cow<'static, str>>,
 }
@@ -237,6 +242,7 @@ impl LanguageRegistry {
                 available_lsp_adapters: HashMap::default(),
                 subscription: watch::channel(),
                 theme: Default::default(),
+                theme_generation: 0,
                 version: 0,
                 reload_count: 0,
 
@@ -565,11 +571,22 @@ impl LanguageRegistry {
         self.state.read().reload_count
     }
 
+    /// Sets the theme the languages highlight with. Their highlight maps are recomputed when
+    /// they're next used, so that switching themes only recomputes the ones in use.
     pub fn set_theme(&self, theme: Arc<Theme>) {
         let mut state = self.state.write();
+        if state
+            .theme
+            .as_ref()
+            .map_or(false, |current_theme| Arc::ptr_eq(current_theme, &theme))
+        {
+            return;
+        }
+
+        state.theme_generation += 1;
         state.theme = Some(theme.clone());
         for language in &state.languages {
-            language.set_theme(theme.syntax());
+            language.invalidate_theme(theme.syntax().clone(), state.theme_generation);
         }
     }
 
@@ -652,6 +669,13 @@ impl LanguageRegistry {
         }
     }
 
+    /// Returns the name of the language of the file at the path, based on its name only and
+    /// without loading the language.
+    pub fn language_name_for_file_path(self: &Arc<Self>, path: &Path) -> Option<LanguageName> {
+        self.language_for_file_internal(path, None, None)
+            .map(|language| language.name)
+    }
+
     fn language_for_file_internal(
         self: &Arc<Self>,
         path: &Path,
@@ -665,6 +689,25 @@ impl LanguageRegistry {
         let extension = filename.and_then(|filename| filename.split('.').last());
         let path_suffixes = [extension, filename, path.to_str()];
         let empty = GlobSet::empty();
+        let first_line = content.map(|content| {
+            let end = content.clip_point(Point::new(0, 256), Bias::Left);
+            let end = content.point_to_offset(end);
+            content.chunks_in_range(0..end).collect::<String>()
+        });
+        let first_line = first_line.as_deref();
+        let interpreter = first_line.and_then(shebang_interpreter);
+        let modeline_language = content
+            .and_then(Modeline::detect)
+            .and_then(|modeline| modeline.language);
+        let matches_name_or_suffix =
+            |name: &str, language_name: &LanguageName, config: &LanguageMatcher| {
+                let name = UniCase::new(name);
+                UniCase::new(language_name.as_ref()) == name
+                    || config
+                        .path_suffixes
+                        .iter()
+                        .any(|suffix| UniCase::new(suffix.as_str()) == name)
+            };
 
         self.find_matching_language(move |language_name, config| {
             let path_matches_default_suffix = config
@@ -678,18 +721,23 @@ impl LanguageRegistry {
                 .iter()
                 .map(|suffix| suffix.unwrap_or(""))
                 .any(|suffix| custom_suffixes.is_match(suffix));
-            let content_matches = content.zip(config.first_line_pattern.as_ref()).map_or(
-                false,
-                |(content, pattern)| {
-                    let end = content.clip_point(Point::new(0, 256), Bias::Left);
-                    let end = content.point_to_offset(end);
-                    let text = content.chunks_in_range(0..end).collect::<String>();
-                    pattern.is_match(&text)
-                },
-            );
-            if path_matches_custom_suffix {
+            let content_matches = first_line
+                .zip(config.first_line_pattern.as_ref())
+                .map_or(false, |(first_line, pattern)| pattern.is_match(first_line));
+            let shebang_matches = interpreter.map_or(false, |interpreter| {
+                matches_name_or_suffix(interpreter, language_name, config)
+            });
+            let modeline_matches =
+                modeline_language
+                    .as_deref()
+                    .map_or(false, |modeline_language| {
+                        matches_name_or_suffix(modeline_language, language_name, config)
+                    });
+            if modeline_matches {
+                3
+            } else if path_matches_custom_suffix {
                 2
-            } else if path_matches_default_suffix || content_matches {
+            } else if path_matches_default_suffix || content_matches || shebang_matches {
                 1
             } else {
                 0
@@ -978,7 +1026,7 @@ impl LanguageRegistryState {
 
     fn add(&mut self, language: Arc<Language>) {
         if let Some(theme) = self.theme.as_ref() {
-            language.set_theme(theme.syntax());
+            language.invalidate_theme(theme.syntax().clone(), self.theme_generation);
         }
         self.language_settings.languages.insert(
             language.name(),
@@ -9,7 +9,7 @@ use ec4rs::{
     Properties as EditorconfigProperties,
 };
 use globset::{Glob, GlobMatcher, GlobSet, GlobSetBuilder};
-use gpui::App;
+use gpui::{App, FontFeatures};
 use itertools::{Either, Itertools};
 use schemars::{
     schema::{InstanceType, ObjectValidation, Schema, SchemaObject, SingleOrVec},
@@ -64,6 +64,49 @@ pub struct AllLanguageSettings {
     defaults: LanguageSettings,
     languages: HashMap<LanguageName, LanguageSettings>,
     pub(crate) file_types: HashMap<Arc<str>, GlobSet>,
+    disabled_features: Vec<(GlobMatcher, DisabledFeatures)>,
+}
+
+/// An expensive feature that can be disabled for the files matching some globs,
+/// see [`AllLanguageSettings::disabled_features_for_path`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DisabledFeature {
+    SoftWrap,
+    /// Disables syntax highlighting, along with the other features relying on the
+    /// syntax tree, since the file isn't parsed.
+    SyntaxHighlighting,
+    InlayHints,
+    Diagnostics,
+}
+
+/// The features disabled for a file.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct DisabledFeatures {
+    pub soft_wrap: bool,
+    pub syntax_highlighting: bool,
+    pub inlay_hints: bool,
+    pub diagnostics: bool,
+}
+
+impl DisabledFeatures {
+    fn insert(&mut self, feature: DisabledFeature) {
+        match feature {
+            DisabledFeature::SoftWrap => self.soft_wrap = true,
+            DisabledFeature::SyntaxHighlighting => self.syntax_highlighting = true,
+            DisabledFeature::InlayHints => self.inlay_hints = true,
+            DisabledFeature::Diagnostics => self.diagnostics = true,
+        }
+    }
+
+    fn union(self, other: Self) -> Self {
+        Self {
+            soft_wrap: self.soft_wrap || other.soft_wrap,
+            syntax_highlighting: self.syntax_highlighting || other.syntax_highlighting,
+            inlay_hints: self.inlay_hints || other.inlay_hints,
+            diagnostics: self.diagnostics || other.diagnostics,
+        }
+    }
 }
 
 /// The settings for a particular language.
@@ -79,6 +122,9 @@ pub struct LanguageSettings {
     /// The column at which to soft-wrap lines, for buffers where soft-wrap
     /// is enabled.
     pub preferred_line_length: u32,
+    /// Whether the regions the language marks as never soft-wrapped, like
+    /// Markdown tables, overflow horizontally instead of being wrapped.
+    pub soft_wrap_exclusions: bool,
     // Whether to show wrap guides (vertical rulers) in the editor.
     // Setting this to true will show a guide at the 'preferred_line_length' value
     // if softwrap is set to 'preferred_line_length', and will show any
@@ -86,6 +132,12 @@ pub struct LanguageSettings {
     pub show_wrap_guides: bool,
     /// Character counts at which to show wrap guides (vertical rulers) in the editor.
     pub wrap_guides: Vec<usize>,
+    /// Extra characters that word motions and double-click selection treat as
+    /// part of a word.
+    pub word_characters: Vec<char>,
+    /// The OpenType features to use for this language's buffers, overriding
+    /// the buffer font's features.
+    pub font_features: Option<FontFeatures>,
     /// Indent guide related settings.
     pub indent_guides: IndentGuideSettings,
     /// Whether or not to perform a buffer format before saving.
@@ -130,10 +182,16 @@ pub struct LanguageSettings {
     pub use_on_type_format: bool,
     /// Whether indentation of pasted content should be adjusted based on the context.
     pub auto_indent_on_paste: bool,
+    /// Whether to fix up the commas separating list items around pasted text and joined lines.
+    pub repair_delimiters: bool,
     // Controls how the editor handles the autoclosed characters.
     pub always_treat_brackets_as_autoclosed: bool,
     /// Which code actions to run on save
     pub code_actions_on_format: HashMap<String, bool>,
+    /// Which code actions to run on save, before formatting, even if formatting is off.
+    pub code_actions_on_save: HashMap<String, bool>,
+    /// How long to wait for the code actions on save before saving without them.
+    pub code_actions_on_save_timeout_ms: u64,
     /// Whether to perform linked edits
     pub linked_edits: bool,
     /// Task configuration for this language.
@@ -144,12 +202,57 @@ pub struct LanguageSettings {
     /// Whether to display inline and alongside documentation for items in the
     /// completions menu.
     pub show_completion_documentation: bool,
+    /// The settings in effect in syntax scopes, by the name of the scope.
+    #[serde(skip)]
+    pub scopes: HashMap<String, Arc<LanguageSettings>>,
+    /// The contents of the `scopes` settings merged into these settings, in order,
+    /// which are resolved into `scopes` once all of them were merged.
+    #[serde(skip)]
+    scope_contents: Vec<(String, LanguageSettingsContent)>,
 }
 
 impl LanguageSettings {
     /// A token representing the rest of the available language servers.
     const REST_OF_LANGUAGE_SERVERS: &'static str = "...";
 
+    /// Returns the settings in effect in the syntax scope with the given name,
+    /// which are those of the longest prefix of the name that has scope settings,
+    /// or these settings when none has.
+    pub fn in_scope(&self, scope_name: Option<&str>) -> &LanguageSettings {
+        let Some(mut scope_name) = scope_name else {
+            return self;
+        };
+        loop {
+            if let Some(settings) = self.scopes.get(scope_name) {
+                return settings;
+            }
+            match scope_name.rsplit_once('.') {
+                Some((prefix, _)) => scope_name = prefix,
+                None => return self,
+            }
+        }
+    }
+
+    /// Resolves the scope settings merged into these settings, each scope's
+    /// contents overriding these settings.
+    fn resolve_scopes(&mut self) {
+        let mut scopes = HashMap::<String, LanguageSettings>::default();
+        for (scope_name, content) in &self.scope_contents {
+            let settings = scopes.entry(scope_name.clone()).or_insert_with(|| {
+                let mut settings = self.clone();
+                settings.scope_contents.clear();
+                settings
+            });
+            merge_settings(settings, content);
+            settings.scope_contents.clear();
+        }
+        self.scope_contents.clear();
+        self.scopes = scopes
+            .into_iter()
+            .map(|(scope_name, settings)| (scope_name, Arc::new(settings)))
+            .collect();
+    }
+
     /// Returns the customized list of language servers from the list of
     /// available language servers.
     pub fn customized_language_servers(
@@ -262,6 +365,10 @@ pub struct AllLanguageSettingsContent {
     /// with languages.
     #[serde(default)]
     pub file_types: HashMap<Arc<str>, Vec<String>>,
+    /// An object whose keys are globs, and whose values are the expensive
+    /// features to disable for the files matching those globs.
+    #[serde(default)]
+    pub disabled_features: HashMap<String, Vec<DisabledFeature>>,
 }
 
 /// The settings for a particular language.
@@ -289,6 +396,12 @@ pub struct LanguageSettingsContent {
     /// Default: 80
     #[serde(default)]
     pub preferred_line_length: Option<u32>,
+    /// Whether the regions the language marks as never soft-wrapped, like
+    /// Markdown tables, overflow horizontally instead of being wrapped.
+    ///
+    /// Default: false
+    #[serde(default)]
+    pub soft_wrap_exclusions: Option<bool>,
     /// Whether to show wrap guides in the editor. Setting this to true will
     /// show a guide at the 'preferred_line_length' value if softwrap is set to
     /// 'preferred_line_length', and will show any additional guides as specified
@@ -302,6 +415,18 @@ pub struct LanguageSettingsContent {
     /// Default: []
     #[serde(default)]
     pub wrap_guides: Option<Vec<usize>>,
+    /// Extra characters that word motions and double-click selection treat as
+    /// part of a word, in addition to the language's own word characters.
+    ///
+    /// Default: []
+    #[serde(default)]
+    pub word_characters: Option<Vec<char>>,
+    /// The OpenType features to use for this language's buffers, overriding
+    /// the buffer font's features.
+    ///
+    /// Default: null
+    #[serde(default)]
+    pub font_features: Option<FontFeatures>,
     /// Indent guide related settings.
     #[serde(default)]
     pub indent_guides: Option<IndentGuideSettings>,
@@ -402,6 +527,18 @@ pub struct LanguageSettingsContent {
     ///
     /// Default: {} (or {"source.organizeImports": true} for Go).
     pub code_actions_on_format: Option<HashMap<String, bool>>,
+    /// Which code actions to run on save, like `source.organizeImports` or
+    /// `source.fixAll`, before formatting. Unlike `code_actions_on_format`,
+    /// these are run even if formatting is off.
+    ///
+    /// Default: {}
+    pub code_actions_on_save: Option<HashMap<String, bool>>,
+    /// How long to wait, in milliseconds, for the code actions on save, before
+    /// saving without the ones that haven't been applied yet, so that a slow
+    /// language server can't block saving.
+    ///
+    /// Default: 2000
+    pub code_actions_on_save_timeout_ms: Option<u64>,
     /// Whether to perform linked edits of associated ranges, if the language server supports it.
     /// For example, when editing opening <html> tag, the contents of the closing </html> tag will be edited as well.
     ///
@@ -411,6 +548,12 @@ pub struct LanguageSettingsContent {
     ///
     /// Default: true
     pub auto_indent_on_paste: Option<bool>,
+    /// Whether to fix up the commas separating list items when pasting into lists and
+    /// joining lines of lists: a missing comma is added between items, and a trailing
+    /// comma pasted as the last item of a list that had none is removed.
+    ///
+    /// Default: false
+    pub repair_delimiters: Option<bool>,
     /// Task configuration for this language.
     ///
     /// Default: {}
@@ -425,6 +568,15 @@ pub struct LanguageSettingsContent {
     ///
     /// Default: true
     pub show_completion_documentation: Option<bool>,
+    /// Settings overriding these ones in the syntax scopes of the language, by
+    /// the name of the scope, like `string` or `comment`. A scope whose name has
+    /// no settings uses those of its name's prefix, so `comment.inclusive` uses
+    /// the settings of `comment`. The settings of scopes can't have scopes.
+    ///
+    /// Example: {"string": {"use_autoclose": false}}
+    ///
+    /// Default: {}
+    pub scopes: Option<HashMap<String, LanguageSettingsContent>>,
 }
 
 /// The contents of the edit prediction settings.
@@ -903,12 +1055,25 @@ impl AllLanguageSettings {
         if let Some(editorconfig_properties) = editorconfig_properties {
             let mut settings = settings.clone();
             merge_with_editorconfig(&mut settings, &editorconfig_properties);
+            for scope_settings in settings.scopes.values_mut() {
+                merge_with_editorconfig(Arc::make_mut(scope_settings), &editorconfig_properties);
+            }
             Cow::Owned(settings)
         } else {
             Cow::Borrowed(settings)
         }
     }
 
+    /// Returns the features disabled for the given path by the `disabled_features` setting.
+    pub fn disabled_features_for_path(&self, path: &Path) -> DisabledFeatures {
+        self.disabled_features
+            .iter()
+            .filter(|(glob, _)| glob.is_match(path))
+            .fold(DisabledFeatures::default(), |disabled, (_, features)| {
+                disabled.union(*features)
+            })
+    }
+
     /// Returns whether edit predictions are enabled for the given path.
     pub fn inline_completions_enabled_for_path(&self, path: &Path) -> bool {
         !self
@@ -1039,6 +1204,7 @@ impl settings::Settings for AllLanguageSettings {
             .ok_or_else(Self::missing_default)?;
 
         let mut file_types: HashMap<Arc<str>, GlobSet> = HashMap::default();
+        let mut disabled_features = default_value.disabled_features.clone();
 
         for (language, suffixes) in &default_value.file_types {
             let mut builder = GlobSetBuilder::new();
@@ -1087,6 +1253,13 @@ impl settings::Settings for AllLanguageSettings {
                 );
             }
 
+            disabled_features.extend(
+                user_settings
+                    .disabled_features
+                    .iter()
+                    .map(|(glob, features)| (glob.clone(), features.clone())),
+            );
+
             for (language, suffixes) in &user_settings.file_types {
                 let mut builder = GlobSetBuilder::new();
 
@@ -1107,6 +1280,11 @@ impl settings::Settings for AllLanguageSettings {
             }
         }
 
+        defaults.resolve_scopes();
+        for language_settings in languages.values_mut() {
+            language_settings.resolve_scopes();
+        }
+
         Ok(Self {
             edit_predictions: EditPredictionSettings {
                 provider: if let Some(provider) = edit_prediction_provider {
@@ -1125,6 +1303,16 @@ impl settings::Settings for AllLanguageSettings {
             defaults,
             languages,
             file_types,
+            disabled_features: disabled_features
+                .iter()
+                .map(|(glob, features)| {
+                    let mut disabled = DisabledFeatures::default();
+                    for feature in features {
+                        disabled.insert(*feature);
+                    }
+                    Ok((Glob::new(glob)?.compile_matcher(), disabled))
+                })
+                .collect::<Result<_>>()?,
         })
     }
 
@@ -1190,17 +1378,31 @@ fn merge_settings(settings: &mut LanguageSettings, src: &LanguageSettingsContent
     merge(&mut settings.use_auto_surround, src.use_auto_surround);
     merge(&mut settings.use_on_type_format, src.use_on_type_format);
     merge(&mut settings.auto_indent_on_paste, src.auto_indent_on_paste);
+    merge(&mut settings.repair_delimiters, src.repair_delimiters);
     merge(
         &mut settings.always_treat_brackets_as_autoclosed,
         src.always_treat_brackets_as_autoclosed,
     );
     merge(&mut settings.show_wrap_guides, src.show_wrap_guides);
     merge(&mut settings.wrap_guides, src.wrap_guides.clone());
+    merge(&mut settings.word_characters, src.word_characters.clone());
+    merge(
+        &mut settings.font_features,
+        src.font_features.clone().map(Some),
+    );
     merge(&mut settings.indent_guides, src.indent_guides);
     merge(
         &mut settings.code_actions_on_format,
         src.code_actions_on_format.clone(),
     );
+    merge(
+        &mut settings.code_actions_on_save,
+        src.code_actions_on_save.clone(),
+    );
+    merge(
+        &mut settings.code_actions_on_save_timeout_ms,
+        src.code_actions_on_save_timeout_ms,
+    );
     merge(&mut settings.linked_edits, src.linked_edits);
     merge(&mut settings.tasks, src.tasks.clone());
 
@@ -1208,6 +1410,7 @@ fn merge_settings(settings: &mut LanguageSettings, src: &LanguageSettingsContent
         &mut settings.preferred_line_length,
         src.preferred_line_length,
     );
+    merge(&mut settings.soft_wrap_exclusions, src.soft_wrap_exclusions);
     merge(&mut settings.formatter, src.formatter.clone());
     merge(&mut settings.prettier, src.prettier.clone());
     merge(&mut settings.format_on_save, src.format_on_save.clone());
@@ -1246,6 +1449,13 @@ fn merge_settings(settings: &mut LanguageSettings, src: &LanguageSettingsContent
         &mut settings.show_completion_documentation,
         src.show_completion_documentation,
     );
+    if let Some(scopes) = &src.scopes {
+        settings.scope_contents.extend(
+            scopes
+                .iter()
+                .map(|(scope_name, content)| (scope_name.clone(), content.clone())),
+        );
+    }
 }
 
 /// Allows to enable/disable formatting with Prettier
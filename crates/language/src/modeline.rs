@@ -0,0 +1,266 @@
+use crate::language_settings::{LanguageSettings, SoftWrap};
+use std::{num::NonZeroU32, ops::RangeInclusive};
+use text::{Point, Rope};
+
+/// How many lines at the start and at the end of a file are searched for modelines.
+const MODELINE_SEARCH_LINES: u32 = 5;
+
+/// Lines longer than this are not searched for modelines.
+const MAX_MODELINE_LEN: u32 = 512;
+
+/// Settings configured by Vim (`vim: ts=2 sw=2 et`) or Emacs
+/// (`-*- mode: python; tab-width: 4 -*-`) modelines in a file's contents.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Modeline {
+    /// The name of the file's language, as written in the modeline.
+    pub language: Option<String>,
+    pub tab_size: Option<NonZeroU32>,
+    pub hard_tabs: Option<bool>,
+    pub soft_wrap: Option<SoftWrap>,
+    pub preferred_line_length: Option<u32>,
+}
+
+impl Modeline {
+    /// Searches the first and last lines of the given text for modelines. When
+    /// several modelines set the same option, the last one wins.
+    pub fn detect(text: &Rope) -> Option<Self> {
+        let max_row = text.max_point().row;
+        let leading_rows = 0..MODELINE_SEARCH_LINES.min(max_row + 1);
+        let trailing_rows = (max_row + 1)
+            .saturating_sub(MODELINE_SEARCH_LINES)
+            .max(leading_rows.end)..max_row + 1;
+
+        let mut modeline = Self::default();
+        let mut line = String::new();
+        for row in leading_rows.chain(trailing_rows) {
+            let line_len = text.line_len(row);
+            if line_len > MAX_MODELINE_LEN {
+                continue;
+            }
+            let start = text.point_to_offset(Point::new(row, 0));
+            let end = text.point_to_offset(Point::new(row, line_len));
+            line.clear();
+            line.extend(text.chunks_in_range(start..end));
+            if let Some(line_modeline) = Self::parse(&line) {
+                modeline.merge(line_modeline);
+            }
+        }
+
+        (modeline != Self::default()).then_some(modeline)
+    }
+
+    /// Returns whether any of the given rows of the text are searched for modelines, so that
+    /// the modelines need to be detected again once they're edited.
+    pub fn searches_rows(text: &Rope, rows: RangeInclusive<u32>) -> bool {
+        let max_row = text.max_point().row;
+        *rows.start() < MODELINE_SEARCH_LINES || *rows.end() + MODELINE_SEARCH_LINES > max_row
+    }
+
+    /// Parses a single line containing a Vim or an Emacs modeline.
+    pub fn parse(line: &str) -> Option<Self> {
+        Self::parse_emacs(line).or_else(|| Self::parse_vim(line))
+    }
+
+    /// Overrides the given settings with the ones configured by this modeline.
+    pub fn apply(&self, settings: &mut LanguageSettings) {
+        if let Some(tab_size) = self.tab_size {
+            settings.tab_size = tab_size;
+        }
+        if let Some(hard_tabs) = self.hard_tabs {
+            settings.hard_tabs = hard_tabs;
+        }
+        if let Some(soft_wrap) = self.soft_wrap {
+            settings.soft_wrap = soft_wrap;
+        }
+        if let Some(preferred_line_length) = self.preferred_line_length {
+            settings.preferred_line_length = preferred_line_length;
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.language = other.language.or(self.language.take());
+        self.tab_size = other.tab_size.or(self.tab_size);
+        self.hard_tabs = other.hard_tabs.or(self.hard_tabs);
+        self.soft_wrap = other.soft_wrap.or(self.soft_wrap);
+        self.preferred_line_length = other.preferred_line_length.or(self.preferred_line_length);
+    }
+
+    fn parse_vim(line: &str) -> Option<Self> {
+        let options = ["vim:", "vi:", "Vim:", "ex:"].iter().find_map(|marker| {
+            line.match_indices(marker).find_map(|(ix, _)| {
+                let preceded_by_whitespace = line[..ix]
+                    .chars()
+                    .next_back()
+                    .map_or(true, char::is_whitespace);
+                preceded_by_whitespace.then(|| &line[ix + marker.len()..])
+            })
+        })?;
+
+        // Either `vim: set ts=2 sw=2:` or `vim: ts=2:sw=2`.
+        let options = options.trim_start();
+        let options: Vec<&str> = if let Some(set_options) = options
+            .strip_prefix("set ")
+            .or_else(|| options.strip_prefix("se "))
+        {
+            set_options
+                .split(':')
+                .next()
+                .unwrap_or_default()
+                .split_whitespace()
+                .collect()
+        } else {
+            options
+                .split(|c: char| c == ':' || c.is_whitespace())
+                .filter(|option| !option.is_empty())
+                .collect()
+        };
+
+        let mut modeline = Self::default();
+        let mut tab_stop = None;
+        let mut shift_width = None;
+        for option in options {
+            let (name, value) = option.split_once('=').unwrap_or((option, ""));
+            match name {
+                "ts" | "tabstop" => tab_stop = value.parse::<NonZeroU32>().ok(),
+                "sw" | "shiftwidth" => shift_width = value.parse::<NonZeroU32>().ok(),
+                "et" | "expandtab" => modeline.hard_tabs = Some(false),
+                "noet" | "noexpandtab" => modeline.hard_tabs = Some(true),
+                "tw" | "textwidth" => {
+                    modeline.preferred_line_length = value.parse().ok().filter(|width| *width > 0)
+                }
+                "wrap" => modeline.soft_wrap = Some(SoftWrap::EditorWidth),
+                "nowrap" => modeline.soft_wrap = Some(SoftWrap::None),
+                "ft" | "filetype" | "syn" | "syntax" if !value.is_empty() => {
+                    modeline.language = Some(value.to_string())
+                }
+                _ => {}
+            }
+        }
+        // Zed uses the same size for tabs and indentation, and the indentation
+        // size is what ends up being visible when editing.
+        modeline.tab_size = shift_width.or(tab_stop);
+        Some(modeline)
+    }
+
+    fn parse_emacs(line: &str) -> Option<Self> {
+        let (_, rest) = line.split_once("-*-")?;
+        let (variables, _) = rest.split_once("-*-")?;
+        let variables = variables.trim();
+
+        let mut modeline = Self::default();
+        if !variables.contains(':') {
+            if !variables.is_empty() {
+                modeline.language = Some(variables.to_string());
+            }
+            return Some(modeline);
+        }
+
+        for variable in variables.split(';') {
+            let Some((name, value)) = variable.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "mode" if !value.is_empty() => modeline.language = Some(value.to_string()),
+                "tab-width" => modeline.tab_size = value.parse().ok(),
+                "indent-tabs-mode" => match value {
+                    "t" => modeline.hard_tabs = Some(true),
+                    "nil" => modeline.hard_tabs = Some(false),
+                    _ => {}
+                },
+                "fill-column" => {
+                    modeline.preferred_line_length = value.parse().ok().filter(|width| *width > 0)
+                }
+                _ => {}
+            }
+        }
+        Some(modeline)
+    }
+}
+
+/// Returns the name of the interpreter in the given shebang line, without its
+/// version suffix: `python` for `#!/usr/bin/env -S python3.12 -u`.
+pub fn shebang_interpreter(first_line: &str) -> Option<&str> {
+    let command = first_line.strip_prefix("#!")?;
+    let mut words = command.split_whitespace();
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        interpreter = words.find(|word| !word.starts_with('-') && !word.contains('='))?;
+    }
+    let interpreter = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    (!interpreter.is_empty()).then_some(interpreter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_modelines() {
+        assert_eq!(
+            Modeline::parse("// vim: set ts=8 sw=2 et tw=100 ft=rust:"),
+            Some(Modeline {
+                language: Some("rust".into()),
+                tab_size: NonZeroU32::new(2),
+                hard_tabs: Some(false),
+                soft_wrap: None,
+                preferred_line_length: Some(100),
+            })
+        );
+        assert_eq!(
+            Modeline::parse("# vim:ts=4:noet:nowrap"),
+            Some(Modeline {
+                tab_size: NonZeroU32::new(4),
+                hard_tabs: Some(true),
+                soft_wrap: Some(SoftWrap::None),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            Modeline::parse("# -*- mode: python; tab-width: 4; indent-tabs-mode: nil -*-"),
+            Some(Modeline {
+                language: Some("python".into()),
+                tab_size: NonZeroU32::new(4),
+                hard_tabs: Some(false),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            Modeline::parse(";; -*- lisp -*-"),
+            Some(Modeline {
+                language: Some("lisp".into()),
+                ..Default::default()
+            })
+        );
+        assert_eq!(Modeline::parse("let regex: Regex;"), None);
+        assert_eq!(Modeline::parse("see svim: ts=2"), None);
+
+        let text = Rope::from("#!/bin/sh\n# vim: ts=2\n\n\n\n\n\n\n\n# vim: sw=4 et\n");
+        assert_eq!(
+            Modeline::detect(&text),
+            Some(Modeline {
+                tab_size: NonZeroU32::new(4),
+                hard_tabs: Some(false),
+                ..Default::default()
+            })
+        );
+        assert_eq!(Modeline::detect(&Rope::from("fn main() {}\n")), None);
+
+        let text = Rope::from("\n".repeat(20).as_str());
+        assert!(Modeline::searches_rows(&text, 4..=4));
+        assert!(!Modeline::searches_rows(&text, 5..=15));
+        assert!(Modeline::searches_rows(&text, 10..=16));
+    }
+
+    #[test]
+    fn test_shebang_interpreter() {
+        assert_eq!(shebang_interpreter("#!/bin/bash"), Some("bash"));
+        assert_eq!(shebang_interpreter("#!/usr/bin/python3.12"), Some("python"));
+        assert_eq!(
+            shebang_interpreter("#!/usr/bin/env -S NODE_ENV=test node --inspect"),
+            Some("node")
+        );
+        assert_eq!(shebang_interpreter("#!"), None);
+        assert_eq!(shebang_interpreter("// not a shebang"), None);
+    }
+}
@@ -9,7 +9,7 @@ use serde_json::Value;
 use std::{ops::Range, str::FromStr, sync::Arc};
 use text::*;
 
-pub use proto::{BufferState, Operation};
+pub use proto::{BufferOperationLog, BufferState, Operation};
 
 /// Deserializes a `[text::LineEnding]` from the RPC representation.
 pub fn deserialize_line_ending(message: proto::LineEnding) -> text::LineEnding {
@@ -217,6 +217,9 @@ pub fn serialize_diagnostics<'a>(
             is_disk_based: entry.diagnostic.is_disk_based,
             is_unnecessary: entry.diagnostic.is_unnecessary,
             data: entry.diagnostic.data.as_ref().map(|data| data.to_string()),
+            related_information: (!entry.diagnostic.related_information.is_empty())
+                .then(|| serde_json::to_string(&entry.diagnostic.related_information).ok())
+                .flatten(),
         })
         .collect()
 }
@@ -406,6 +409,12 @@ pub fn deserialize_diagnostics(
             } else {
                 None
             };
+            let related_information =
+                if let Some(related_information) = diagnostic.related_information {
+                    serde_json::from_str(&related_information).ok()?
+                } else {
+                    Vec::new()
+                };
             Some(DiagnosticEntry {
                 range: deserialize_anchor(diagnostic.start?)?..deserialize_anchor(diagnostic.end?)?,
                 diagnostic: Diagnostic {
@@ -424,6 +433,7 @@ pub fn deserialize_diagnostics(
                     is_disk_based: diagnostic.is_disk_based,
                     is_unnecessary: diagnostic.is_unnecessary,
                     data,
+                    related_information,
                 },
             })
         })
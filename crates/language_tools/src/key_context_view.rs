@@ -1,10 +1,13 @@
+use editor::{Editor, MultiBuffer};
 use gpui::{
-    actions, Action, App, AppContext as _, Entity, EventEmitter, FocusHandle, Focusable,
-    KeyBindingContextPredicate, KeyContext, Keystroke, MouseButton, Render, Subscription,
+    actions, Action, App, AppContext as _, BindingExplanation, BindingResolution, Entity,
+    EventEmitter, FocusHandle, Focusable, KeyBindingContextPredicate, KeyContext, Keystroke,
+    MouseButton, Render, Subscription, WeakEntity,
 };
 use itertools::Itertools;
 use serde_json::json;
-use settings::get_key_equivalents;
+use settings::{get_key_equivalents, KeybindSource};
+use std::fmt::Write as _;
 use ui::{
     div, h_flex, px, v_flex, ButtonCommon, Clickable, Context, FluentBuilder, InteractiveElement,
     Label, LabelCommon, LabelSize, ParentElement, SharedString, StatefulInteractiveElement, Styled,
@@ -18,7 +21,8 @@ actions!(debug, [OpenKeyContextView]);
 pub fn init(cx: &mut App) {
     cx.observe_new(|workspace: &mut Workspace, _, _| {
         workspace.register_action(|workspace, _: &OpenKeyContextView, window, cx| {
-            let key_context_view = cx.new(|cx| KeyContextView::new(window, cx));
+            let workspace_handle = workspace.weak_handle();
+            let key_context_view = cx.new(|cx| KeyContextView::new(workspace_handle, window, cx));
             workspace.split_item(
                 SplitDirection::Right,
                 Box::new(key_context_view),
@@ -31,7 +35,9 @@ pub fn init(cx: &mut App) {
 }
 
 struct KeyContextView {
+    workspace: WeakEntity<Workspace>,
     pending_keystrokes: Option<Vec<Keystroke>>,
+    last_input: Option<(Vec<Keystroke>, Vec<KeyContext>)>,
     last_keystrokes: Option<SharedString>,
     last_possibilities: Vec<(SharedString, SharedString, Option<bool>)>,
     context_stack: Vec<KeyContext>,
@@ -40,13 +46,18 @@ struct KeyContextView {
 }
 
 impl KeyContextView {
-    pub fn new(window: &mut Window, cx: &mut Context<Self>) -> Self {
+    pub fn new(
+        workspace: WeakEntity<Workspace>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
         let sub1 = cx.observe_keystrokes(|this, e, window, cx| {
             let mut pending = this.pending_keystrokes.take().unwrap_or_default();
             pending.push(e.keystroke.clone());
             let mut possibilities = cx.all_bindings_for_input(&pending);
             possibilities.reverse();
             this.context_stack = window.context_stack();
+            this.last_input = Some((pending.clone(), this.context_stack.clone()));
             this.last_keystrokes = Some(
                 json!(pending.iter().map(|p| p.unparse()).join(" "))
                     .to_string()
@@ -101,8 +112,10 @@ impl KeyContextView {
         });
 
         Self {
+            workspace,
             context_stack: Vec::new(),
             pending_keystrokes: None,
+            last_input: None,
             last_keystrokes: None,
             last_possibilities: Vec::new(),
             focus_handle: cx.focus_handle(),
@@ -135,6 +148,34 @@ impl KeyContextView {
         false
     }
 
+    /// Opens a report of every binding for the last keystrokes, in precedence order, noting
+    /// which keymap each one comes from and which bindings shadow the others.
+    fn open_troubleshooting_report(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some((keystrokes, context_stack)) = self.last_input.clone() else {
+            return;
+        };
+        let explanations = cx.explain_bindings_for_input(&keystrokes, &context_stack);
+        let report = keybinding_report(&keystrokes, &context_stack, &explanations);
+        self.workspace
+            .update(cx, |workspace, cx| {
+                let project = workspace.project().clone();
+                let buffer = project.update(cx, |project, cx| {
+                    project.create_local_buffer(&report, None, cx)
+                });
+                let buffer = cx.new(|cx| {
+                    MultiBuffer::singleton(buffer, cx).with_title("Keybinding Report".into())
+                });
+                let editor = cx.new(|cx| {
+                    let mut editor =
+                        Editor::for_multibuffer(buffer, Some(project), true, window, cx);
+                    editor.set_read_only(true);
+                    editor
+                });
+                workspace.add_item_to_active_pane(Box::new(editor), None, true, window, cx);
+            })
+            .ok();
+    }
+
     fn action_matches(&self, a: &Option<Box<dyn Action>>, b: &dyn Action) -> bool {
         if let Some(last_action) = a {
             last_action.partial_eq(b)
@@ -166,7 +207,7 @@ impl Item for KeyContextView {
     where
         Self: Sized,
     {
-        Some(cx.new(|cx| KeyContextView::new(window, cx)))
+        Some(cx.new(|cx| KeyContextView::new(self.workspace.clone(), window, cx)))
     }
 }
 
@@ -264,7 +305,19 @@ impl Render for KeyContextView {
                 )
             })
             .when_some(self.last_keystrokes.as_ref(), |el, keystrokes| {
-                el.child(Label::new(format!("Typed: {}", keystrokes)).ml_4())
+                el.child(
+                    h_flex()
+                        .gap_4()
+                        .ml_4()
+                        .child(Label::new(format!("Typed: {}", keystrokes)))
+                        .child(
+                            Button::new("troubleshoot", "Troubleshoot")
+                                .style(ButtonStyle::Filled)
+                                .on_click(cx.listener(|this, _, window, cx| {
+                                    this.open_troubleshooting_report(window, cx)
+                                })),
+                        ),
+                )
                     .children(
                         self.last_possibilities
                             .iter()
@@ -297,3 +350,85 @@ impl Render for KeyContextView {
             })
     }
 }
+
+fn keybinding_report(
+    keystrokes: &[Keystroke],
+    context_stack: &[KeyContext],
+    explanations: &[BindingExplanation],
+) -> String {
+    let source_name = |explanation: &BindingExplanation| {
+        explanation
+            .binding
+            .meta()
+            .and_then(KeybindSource::from_meta)
+            .map_or("Unknown", KeybindSource::name)
+    };
+
+    let mut report = String::new();
+    writeln!(
+        report,
+        "Keystrokes: {}",
+        keystrokes.iter().map(|k| k.unparse()).join(" ")
+    )
+    .ok();
+    writeln!(report, "\nContext stack, from the outermost context:").ok();
+    for context in context_stack {
+        writeln!(report, "  {context:?}").ok();
+    }
+
+    writeln!(report, "\nBindings, in precedence order:").ok();
+    if explanations.is_empty() {
+        writeln!(report, "  No binding matches these keystrokes.").ok();
+    }
+    let dispatched = explanations
+        .iter()
+        .find(|explanation| explanation.resolution == BindingResolution::Dispatched);
+    let disabling = explanations
+        .iter()
+        .find(|explanation| explanation.resolution == BindingResolution::Disables);
+    for explanation in explanations {
+        let predicate = explanation.binding.predicate().map_or_else(
+            || "(no context)".to_string(),
+            |predicate| predicate.to_string(),
+        );
+        let resolution = match explanation.resolution {
+            BindingResolution::Dispatched => "dispatched".to_string(),
+            BindingResolution::Fallback => match dispatched {
+                Some(dispatched) if source_name(dispatched) != source_name(explanation) => {
+                    format!(
+                        "shadowed by the {} binding, dispatched if its action isn't handled",
+                        source_name(dispatched)
+                    )
+                }
+                _ => "fallback, dispatched if the actions above aren't handled".to_string(),
+            },
+            BindingResolution::Disables => "disables the bindings below".to_string(),
+            BindingResolution::Disabled => match disabling {
+                Some(disabling) => format!("disabled by the {} binding", source_name(disabling)),
+                None => "disabled".to_string(),
+            },
+            BindingResolution::Pending => "waits for more keystrokes".to_string(),
+            BindingResolution::ContextMismatch => "context doesn't match".to_string(),
+        };
+        let mut action_name = explanation.binding.action().name();
+        if action_name == "zed::NoAction" {
+            action_name = "(null)";
+        }
+        writeln!(
+            report,
+            "  [{}] {} {} in {}: {}",
+            source_name(explanation),
+            explanation
+                .binding
+                .keystrokes()
+                .iter()
+                .map(|k| k.unparse())
+                .join(" "),
+            action_name,
+            predicate,
+            resolution
+        )
+        .ok();
+    }
+    report
+}
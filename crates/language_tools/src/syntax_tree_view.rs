@@ -271,7 +271,12 @@ impl SyntaxTreeView {
         }
 
         let node = cursor.node();
-        row.child(if node.is_named() {
+        row.child(if node.is_error() {
+            Label::new(node.kind()).color(Color::Error)
+        } else if node.is_missing() {
+            // Missing nodes are inserted by the parser to recover from syntax errors.
+            Label::new(format!("MISSING \"{}\"", node.kind())).color(Color::Error)
+        } else if node.is_named() {
             Label::new(node.kind()).color(Color::Default)
         } else {
             Label::new(format!("\"{}\"", node.kind())).color(Color::Created)
@@ -0,0 +1,3 @@
+(pipe_table) @nowrap
+
+(fenced_code_block) @nowrap
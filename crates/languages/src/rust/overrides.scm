@@ -6,3 +6,4 @@
   (line_comment)
   (block_comment)
 ] @comment.inclusive
+(macro_invocation) @macro
@@ -6,7 +6,9 @@ pub use lsp_types::*;
 use anyhow::{anyhow, Context as _, Result};
 use collections::HashMap;
 use futures::{channel::oneshot, io::BufWriter, select, AsyncRead, AsyncWrite, Future, FutureExt};
-use gpui::{App, AsyncApp, BackgroundExecutor, SharedString, Task};
+use gpui::{
+    supervised, App, AsyncApp, BackgroundExecutor, SharedString, Task, TaskPanic, TaskRecovery,
+};
 use parking_lot::{Mutex, RwLock};
 use postage::{barrier, prelude::Stream};
 use schemars::{
@@ -26,6 +28,7 @@ use std::{
     ffi::{OsStr, OsString},
     fmt,
     io::Write,
+    mem,
     ops::DerefMut,
     path::PathBuf,
     pin::Pin,
@@ -48,6 +51,7 @@ const SERVER_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
 type NotificationHandler = Box<dyn Send + FnMut(Option<RequestId>, Value, AsyncApp)>;
 type ResponseHandler = Box<dyn Send + FnOnce(Result<String, Error>)>;
 type IoHandler = Box<dyn Send + FnMut(IoKind, &str)>;
+type IoPanicHandler = Box<dyn Send + FnOnce(&TaskPanic, &mut App)>;
 
 /// Kind of language server stdio given to an IO handler.
 #[derive(Debug, Clone, Copy)]
@@ -92,6 +96,7 @@ pub struct LanguageServer {
     notification_handlers: Arc<Mutex<HashMap<&'static str, NotificationHandler>>>,
     response_handlers: Arc<Mutex<Option<HashMap<RequestId, ResponseHandler>>>>,
     io_handlers: Arc<Mutex<HashMap<i32, IoHandler>>>,
+    io_panic_handlers: Arc<Mutex<Vec<IoPanicHandler>>>,
     executor: BackgroundExecutor,
     #[allow(clippy::type_complexity)]
     io_tasks: Mutex<Option<(Task<Option<()>>, Task<Option<()>>)>>,
@@ -336,10 +341,35 @@ impl LanguageServer {
         code_action_kinds: Option<Vec<CodeActionKind>>,
         cx: AsyncApp,
     ) -> Result<Self> {
-        let working_dir = if root_path.is_dir() {
-            root_path
-        } else {
-            root_path.parent().unwrap_or_else(|| Path::new("/"))
+        Self::new_in_working_dir(
+            stderr_capture,
+            server_id,
+            server_name,
+            binary,
+            root_path,
+            None,
+            code_action_kinds,
+            cx,
+        )
+    }
+
+    /// Starts a language server process in the given working directory, or in the root path
+    /// when it's `None`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_in_working_dir(
+        stderr_capture: Arc<Mutex<Option<String>>>,
+        server_id: LanguageServerId,
+        server_name: LanguageServerName,
+        binary: LanguageServerBinary,
+        root_path: &Path,
+        working_dir: Option<&Path>,
+        code_action_kinds: Option<Vec<CodeActionKind>>,
+        cx: AsyncApp,
+    ) -> Result<Self> {
+        let working_dir = match working_dir {
+            Some(working_dir) => working_dir,
+            None if root_path.is_dir() => root_path,
+            None => root_path.parent().unwrap_or_else(|| Path::new("/")),
         };
 
         log::info!(
@@ -423,22 +453,31 @@ impl LanguageServer {
         let response_handlers =
             Arc::new(Mutex::new(Some(HashMap::<_, ResponseHandler>::default())));
         let io_handlers = Arc::new(Mutex::new(HashMap::default()));
+        let io_panic_handlers = Arc::new(Mutex::new(Vec::new()));
 
         let stdout_input_task = cx.spawn({
             let on_unhandled_notification = on_unhandled_notification.clone();
             let notification_handlers = notification_handlers.clone();
             let response_handlers = response_handlers.clone();
             let io_handlers = io_handlers.clone();
-            move |cx| {
-                Self::handle_input(
+            let io_panic_handlers = io_panic_handlers.clone();
+            let task_name = format!("input handler of language server {server_name}");
+            move |cx| async move {
+                let input = Self::handle_input(
                     stdout,
                     on_unhandled_notification,
                     notification_handlers,
-                    response_handlers,
+                    response_handlers.clone(),
                     io_handlers,
-                    cx,
+                    cx.clone(),
+                )
+                .log_err();
+                Self::report_io_panic(
+                    supervised(task_name, input).await,
+                    &response_handlers,
+                    &io_panic_handlers,
+                    &cx,
                 )
-                .log_err()
             }
         });
         let stderr_input_task = stderr
@@ -452,7 +491,8 @@ impl LanguageServer {
             let (stdout, stderr) = futures::join!(stdout_input_task, stderr_input_task);
             stdout.or(stderr)
         });
-        let output_task = cx.background_executor().spawn({
+        let output_task = cx.background_executor().spawn(supervised(
+            format!("output handler of language server {server_name}"),
             Self::handle_output(
                 stdin,
                 outbound_rx,
@@ -460,7 +500,19 @@ impl LanguageServer {
                 response_handlers.clone(),
                 io_handlers.clone(),
             )
-            .log_err()
+            .log_err(),
+        ));
+        let output_task = cx.spawn({
+            let response_handlers = response_handlers.clone();
+            let io_panic_handlers = io_panic_handlers.clone();
+            |cx| async move {
+                Self::report_io_panic(
+                    output_task.await,
+                    &response_handlers,
+                    &io_panic_handlers,
+                    &cx,
+                )
+            }
         });
 
         let configuration = DidChangeConfigurationParams {
@@ -473,6 +525,7 @@ impl LanguageServer {
             notification_handlers,
             response_handlers,
             io_handlers,
+            io_panic_handlers,
             name: server_name,
             process_name: binary
                 .path
@@ -494,6 +547,45 @@ impl LanguageServer {
         }
     }
 
+    /// Returns the output of an IO task, reporting the panic that stopped it, after which the
+    /// language server can't be communicated with anymore: the requests waiting for responses
+    /// fail, and the handlers registered with [`Self::on_io_panic`] are called.
+    fn report_io_panic(
+        output: Result<Option<()>, TaskPanic>,
+        response_handlers: &Mutex<Option<HashMap<RequestId, ResponseHandler>>>,
+        io_panic_handlers: &Mutex<Vec<IoPanicHandler>>,
+        cx: &AsyncApp,
+    ) -> Option<()> {
+        let panic = match output {
+            Ok(output) => return output,
+            Err(panic) => panic,
+        };
+        Self::fail_pending_requests(response_handlers, &panic.to_string());
+        let io_panic_handlers = mem::take(&mut *io_panic_handlers.lock());
+        cx.update(|cx| {
+            cx.report_task_panic(&panic, TaskRecovery::Degraded);
+            for handler in io_panic_handlers {
+                handler(&panic, cx);
+            }
+        })
+        .ok();
+        None
+    }
+
+    /// Fails the requests waiting for responses, which won't be answered anymore, along with
+    /// the next requests.
+    fn fail_pending_requests(
+        response_handlers: &Mutex<Option<HashMap<RequestId, ResponseHandler>>>,
+        message: &str,
+    ) {
+        let pending_handlers = response_handlers.lock().take();
+        for handler in pending_handlers.into_iter().flat_map(HashMap::into_values) {
+            handler(Err(Error {
+                message: message.to_string(),
+            }));
+        }
+    }
+
     /// List of code action kinds this language server reports being able to emit.
     pub fn code_action_kinds(&self) -> Option<Vec<CodeActionKind>> {
         self.code_action_kinds.clone()
@@ -515,9 +607,7 @@ impl LanguageServer {
         let stdout = BufReader::new(stdout);
         let _clear_response_handlers = util::defer({
             let response_handlers = response_handlers.clone();
-            move || {
-                response_handlers.lock().take();
-            }
+            move || Self::fail_pending_requests(&response_handlers, "server shut down")
         });
         let mut input_handler = input_handler::LspStdoutHandler::new(
             stdout,
@@ -591,9 +681,7 @@ impl LanguageServer {
         let mut stdin = BufWriter::new(stdin);
         let _clear_response_handlers = util::defer({
             let response_handlers = response_handlers.clone();
-            move || {
-                response_handlers.lock().take();
-            }
+            move || Self::fail_pending_requests(&response_handlers, "server shut down")
         });
         let mut content_len_buffer = Vec::new();
         while let Ok(message) = outbound_rx.recv().await {
@@ -926,6 +1014,15 @@ impl LanguageServer {
         }
     }
 
+    /// Registers a handler called when a task reading or writing the messages of the server
+    /// panicked, after which the server can't be communicated with anymore.
+    pub fn on_io_panic<F>(&self, f: F)
+    where
+        F: 'static + Send + FnOnce(&TaskPanic, &mut App),
+    {
+        self.io_panic_handlers.lock().push(Box::new(f));
+    }
+
     /// Removes a request handler registers via [`Self::on_request`].
     pub fn remove_request_handler<T: request::Request>(&self) {
         self.notification_handlers.lock().remove(T::METHOD);
@@ -1527,6 +1624,46 @@ mod tests {
         }
     }
 
+    #[gpui::test]
+    async fn test_io_panic(cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            release_channel::init(SemanticVersion::default(), cx);
+        });
+        let (server, _fake) = FakeLanguageServer::new(
+            LanguageServerId(0),
+            LanguageServerBinary {
+                path: "path/to/language-server".into(),
+                arguments: vec![],
+                env: None,
+            },
+            "the-lsp".to_string(),
+            Default::default(),
+            cx.to_async(),
+        );
+        let reported_panic = Arc::new(Mutex::new(None));
+        server.on_io_panic({
+            let reported_panic = reported_panic.clone();
+            move |panic, _| *reported_panic.lock() = Some(panic.clone())
+        });
+
+        // The fake server doesn't answer the request, which fails once the IO panicked.
+        let response = server.request::<request::Shutdown>(());
+        let panic = TaskPanic {
+            task_name: "input handler of language server the-lsp".into(),
+            message: "oh no".into(),
+        };
+        let output = LanguageServer::report_io_panic(
+            Err(panic.clone()),
+            &server.response_handlers,
+            &server.io_panic_handlers,
+            &cx.to_async(),
+        );
+        assert_eq!(output, None);
+        assert_eq!(response.await.unwrap_err().to_string(), panic.to_string());
+        assert_eq!(*reported_panic.lock(), Some(panic));
+        assert!(server.request::<request::Shutdown>(()).await.is_err());
+    }
+
     #[gpui::test]
     async fn test_fake(cx: &mut TestAppContext) {
         cx.update(|cx| {
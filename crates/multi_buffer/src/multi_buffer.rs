@@ -415,6 +415,7 @@ pub struct MultiBufferChunks<'a> {
     excerpt_offset_range: Range<ExcerptOffset>,
     excerpt_chunks: Option<ExcerptChunks<'a>>,
     language_aware: bool,
+    highlight_syntax: bool,
 }
 
 pub struct ReversedMultiBufferChunks<'a> {
@@ -2277,14 +2278,10 @@ impl MultiBuffer {
     }
 
     pub fn settings_at<'a, T: ToOffset>(&self, point: T, cx: &'a App) -> Cow<'a, LanguageSettings> {
-        let mut language = None;
-        let mut file = None;
-        if let Some((buffer, offset)) = self.point_to_buffer_offset(point, cx) {
-            let buffer = buffer.read(cx);
-            language = buffer.language_at(offset);
-            file = buffer.file();
+        match self.point_to_buffer_offset(point, cx) {
+            Some((buffer, offset)) => buffer.read(cx).settings_at(offset, cx),
+            None => language_settings(None, None, cx),
         }
-        language_settings(language.map(|l| l.name()), file, cx)
     }
 
     pub fn for_each_buffer(&self, mut f: impl FnMut(&Entity<Buffer>)) {
@@ -2703,28 +2700,35 @@ impl MultiBuffer {
             let buffer = buffer.read(cx);
             let buffer_id = buffer.remote_id();
 
+            // Resynchronizing a buffer replaces its text without changing its version, so the
+            // whole excerpt is replaced.
+            let buffer_resynced = buffer.resync_count() != old_excerpt.buffer.resync_count();
             let mut new_excerpt;
-            if buffer_edited {
-                edits.extend(
-                    buffer
-                        .edits_since_in_range::<usize>(
-                            old_excerpt.buffer.version(),
-                            old_excerpt.range.context.clone(),
-                        )
-                        .map(|edit| {
-                            let excerpt_old_start = cursor.start().1;
-                            let excerpt_new_start =
-                                ExcerptOffset::new(new_excerpts.summary().text.len);
-                            let old_start = excerpt_old_start + ExcerptOffset::new(edit.old.start);
-                            let old_end = excerpt_old_start + ExcerptOffset::new(edit.old.end);
-                            let new_start = excerpt_new_start + ExcerptOffset::new(edit.new.start);
-                            let new_end = excerpt_new_start + ExcerptOffset::new(edit.new.end);
-                            Edit {
-                                old: old_start..old_end,
-                                new: new_start..new_end,
-                            }
-                        }),
-                );
+            if buffer_edited || buffer_resynced {
+                if !buffer_resynced {
+                    edits.extend(
+                        buffer
+                            .edits_since_in_range::<usize>(
+                                old_excerpt.buffer.version(),
+                                old_excerpt.range.context.clone(),
+                            )
+                            .map(|edit| {
+                                let excerpt_old_start = cursor.start().1;
+                                let excerpt_new_start =
+                                    ExcerptOffset::new(new_excerpts.summary().text.len);
+                                let old_start =
+                                    excerpt_old_start + ExcerptOffset::new(edit.old.start);
+                                let old_end = excerpt_old_start + ExcerptOffset::new(edit.old.end);
+                                let new_start =
+                                    excerpt_new_start + ExcerptOffset::new(edit.new.start);
+                                let new_end = excerpt_new_start + ExcerptOffset::new(edit.new.end);
+                                Edit {
+                                    old: old_start..old_end,
+                                    new: new_start..new_end,
+                                }
+                            }),
+                    );
+                }
 
                 new_excerpt = Excerpt::new(
                     old_excerpt.id,
@@ -2734,6 +2738,16 @@ impl MultiBuffer {
                     old_excerpt.range.clone(),
                     old_excerpt.has_trailing_newline,
                 );
+                if buffer_resynced {
+                    let excerpt_old_start = cursor.start().1;
+                    let excerpt_new_start = ExcerptOffset::new(new_excerpts.summary().text.len);
+                    edits.push(Edit {
+                        old: excerpt_old_start
+                            ..excerpt_old_start + ExcerptOffset::new(old_excerpt.text_summary.len),
+                        new: excerpt_new_start
+                            ..excerpt_new_start + ExcerptOffset::new(new_excerpt.text_summary.len),
+                    });
+                }
             } else {
                 new_excerpt = old_excerpt.clone();
                 new_excerpt.buffer = buffer.snapshot();
@@ -3870,16 +3884,24 @@ impl MultiBufferSnapshot {
         &self,
         start: T,
         for_completion: bool,
+    ) -> (Range<usize>, Option<CharKind>) {
+        let start = start.to_offset(self);
+        let classifier = self
+            .char_classifier_at(start)
+            .for_completion(for_completion);
+        self.surrounding_word_with_classifier(start, &classifier)
+    }
+
+    pub fn surrounding_word_with_classifier<T: ToOffset>(
+        &self,
+        start: T,
+        classifier: &CharClassifier,
     ) -> (Range<usize>, Option<CharKind>) {
         let mut start = start.to_offset(self);
         let mut end = start;
         let mut next_chars = self.chars_at(start).peekable();
         let mut prev_chars = self.reversed_chars_at(start).peekable();
 
-        let classifier = self
-            .char_classifier_at(start)
-            .for_completion(for_completion);
-
         let word_kind = cmp::max(
             prev_chars.peek().copied().map(|c| classifier.kind(c)),
             next_chars.peek().copied().map(|c| classifier.kind(c)),
@@ -3994,6 +4016,17 @@ impl MultiBufferSnapshot {
     }
 
     pub fn chunks<T: ToOffset>(&self, range: Range<T>, language_aware: bool) -> MultiBufferChunks {
+        self.chunks_with_syntax(range, language_aware, language_aware)
+    }
+
+    /// Like [`Self::chunks`], but only highlights the syntax of language-aware chunks when
+    /// `highlight_syntax` is true, as with [`BufferSnapshot::chunks_with_syntax`].
+    pub fn chunks_with_syntax<T: ToOffset>(
+        &self,
+        range: Range<T>,
+        language_aware: bool,
+        highlight_syntax: bool,
+    ) -> MultiBufferChunks {
         let mut chunks = MultiBufferChunks {
             excerpt_offset_range: ExcerptOffset::new(0)..ExcerptOffset::new(0),
             range: 0..0,
@@ -4004,6 +4037,7 @@ impl MultiBufferSnapshot {
             excerpt_chunks: None,
             buffer_chunk: None,
             language_aware,
+            highlight_syntax,
         };
         let range = range.start.to_offset(self)..range.end.to_offset(self);
         chunks.seek(range);
@@ -5570,13 +5604,10 @@ impl MultiBufferSnapshot {
         point: T,
         cx: &'a App,
     ) -> Cow<'a, LanguageSettings> {
-        let mut language = None;
-        let mut file = None;
-        if let Some((buffer, offset)) = self.point_to_buffer_offset(point) {
-            language = buffer.language_at(offset);
-            file = buffer.file();
+        match self.point_to_buffer_offset(point) {
+            Some((buffer, offset)) => buffer.settings_at(offset, cx),
+            None => language_settings(None, None, cx),
         }
-        language_settings(language.map(|l| l.name()), file, cx)
     }
 
     pub fn language_scope_at<T: ToOffset>(&self, point: T) -> Option<LanguageScope> {
@@ -6476,7 +6507,12 @@ impl Excerpt {
         }
     }
 
-    fn chunks_in_range(&self, range: Range<usize>, language_aware: bool) -> ExcerptChunks {
+    fn chunks_in_range(
+        &self,
+        range: Range<usize>,
+        language_aware: bool,
+        highlight_syntax: bool,
+    ) -> ExcerptChunks {
         let content_start = self.range.context.start.to_offset(&self.buffer);
         let chunks_start = content_start + range.start;
         let chunks_end = content_start + cmp::min(range.end, self.text_summary.len);
@@ -6490,7 +6526,11 @@ impl Excerpt {
             0
         };
 
-        let content_chunks = self.buffer.chunks(chunks_start..chunks_end, language_aware);
+        let content_chunks = self.buffer.chunks_with_syntax(
+            chunks_start..chunks_end,
+            language_aware,
+            highlight_syntax,
+        );
 
         ExcerptChunks {
             excerpt_id: self.id,
@@ -7103,6 +7143,7 @@ impl<'a> MultiBufferChunks<'a> {
                     (self.excerpt_offset_range.start - excerpt_start).value
                         ..(self.excerpt_offset_range.end - excerpt_start).value,
                     self.language_aware,
+                    self.highlight_syntax,
                 ));
             }
         } else {
@@ -7123,6 +7164,7 @@ impl<'a> MultiBufferChunks<'a> {
                 self.excerpt_chunks = Some(excerpt.chunks_in_range(
                     0..(self.excerpt_offset_range.end - *self.excerpts.start()).value,
                     self.language_aware,
+                    self.highlight_syntax,
                 ));
             }
         }
@@ -7220,7 +7262,11 @@ impl<'a> Iterator for MultiBufferChunks<'a> {
                     chunks
                 } else {
                     let base_buffer = &self.diffs.get(&buffer_id)?.base_text()?;
-                    base_buffer.chunks(base_text_start..base_text_end, self.language_aware)
+                    base_buffer.chunks_with_syntax(
+                        base_text_start..base_text_end,
+                        self.language_aware,
+                        self.highlight_syntax,
+                    )
                 };
 
                 let chunk = if let Some(chunk) = chunks.next() {
@@ -98,6 +98,23 @@ fn test_remote(cx: &mut App) {
     assert_eq!(snapshot.text(), "abc");
 }
 
+#[gpui::test]
+fn test_resynced_buffer(cx: &mut App) {
+    let buffer_id = BufferId::new(1).unwrap();
+    let buffer = cx.new(|_| Buffer::remote(buffer_id, 1, Capability::ReadWrite, "one\ntwo\n"));
+    let multibuffer = cx.new(|cx| MultiBuffer::singleton(buffer.clone(), cx));
+    assert_eq!(multibuffer.read(cx).snapshot(cx).text(), "one\ntwo\n");
+
+    // A replica at the same version, whose text differs.
+    let replica = cx.new(|_| Buffer::remote(buffer_id, 1, Capability::ReadWrite, "one\nthree\n"));
+    replica.update(cx, |replica, cx| {
+        buffer.update(cx, |buffer, cx| buffer.resync(replica, cx))
+    });
+    let snapshot = multibuffer.read(cx).snapshot(cx);
+    assert_eq!(snapshot.text(), "one\nthree\n");
+    assert_consistent_line_numbers(&snapshot);
+}
+
 #[gpui::test]
 fn test_excerpt_boundaries_and_clipping(cx: &mut App) {
     let buffer_1 = cx.new(|cx| Buffer::local(sample_text(6, 6, 'a'), cx));
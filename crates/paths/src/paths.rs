@@ -125,6 +125,31 @@ pub fn database_dir() -> &'static PathBuf {
     DATABASE_DIR.get_or_init(|| support_dir().join("db"))
 }
 
+/// Returns the path to the local history directory.
+///
+/// This is where the contents of dirty buffers that were closed without saving are kept.
+pub fn local_history_dir() -> &'static PathBuf {
+    static LOCAL_HISTORY_DIR: OnceLock<PathBuf> = OnceLock::new();
+    LOCAL_HISTORY_DIR.get_or_init(|| support_dir().join("local_history"))
+}
+
+/// Returns the path to the scratchpads directory.
+///
+/// This is where the contents of scratchpad buffers are kept between sessions.
+pub fn scratchpads_dir() -> &'static PathBuf {
+    static SCRATCHPADS_DIR: OnceLock<PathBuf> = OnceLock::new();
+    SCRATCHPADS_DIR.get_or_init(|| support_dir().join("scratchpads"))
+}
+
+/// Returns the path to the control sockets directory.
+///
+/// This is where the sockets external tools drive Zed through are created, only accessible to
+/// the current user.
+pub fn control_sockets_dir() -> &'static PathBuf {
+    static CONTROL_SOCKETS_DIR: OnceLock<PathBuf> = OnceLock::new();
+    CONTROL_SOCKETS_DIR.get_or_init(|| temp_dir().join("control_sockets"))
+}
+
 /// Returns the path to the crashes directory, if it exists for the current platform.
 pub fn crashes_dir() -> &'static Option<PathBuf> {
     static CRASHES_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
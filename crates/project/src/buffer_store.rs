@@ -2,12 +2,11 @@ use crate::{
     lsp_store::OpenLspBufferHandle,
     search::SearchQuery,
     worktree_store::{WorktreeStore, WorktreeStoreEvent},
-    ProjectItem as _, ProjectPath,
+    ProjectItem as _, ProjectPath, ProjectSettings,
 };
 use ::git::{parse_git_remote_url, BuildPermalinkParams, GitHostingProviderRegistry};
 use anyhow::{anyhow, bail, Context as _, Result};
 use buffer_diff::{BufferDiff, BufferDiffEvent};
-use client::Client;
 use collections::{hash_map, HashMap, HashSet};
 use fs::Fs;
 use futures::{channel::oneshot, future::Shared, Future, FutureExt as _, StreamExt};
@@ -28,6 +27,7 @@ use rpc::{
     AnyProtoClient, ErrorExt as _, TypedEnvelope,
 };
 use serde::Deserialize;
+use settings::Settings as _;
 use smol::channel::Receiver;
 use std::{
     io,
@@ -39,7 +39,7 @@ use std::{
     time::Instant,
 };
 use text::BufferId;
-use util::{debug_panic, maybe, ResultExt as _, TryFutureExt};
+use util::{debug_panic, maybe, post_inc, ResultExt as _, TryFutureExt};
 use worktree::{File, PathChange, ProjectEntryId, UpdatedGitRepositoriesSet, Worktree, WorktreeId};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -56,6 +56,9 @@ pub struct BufferStore {
     #[allow(clippy::type_complexity)]
     loading_diffs:
         HashMap<(BufferId, DiffKind), Shared<Task<Result<Entity<BufferDiff>, Arc<anyhow::Error>>>>>,
+    /// The saves whose files are still being written, by the order they were started.
+    saving_buffers: HashMap<usize, Shared<Task<Result<(), Arc<anyhow::Error>>>>>,
+    next_save_id: usize,
     worktree_store: Entity<WorktreeStore>,
     opened_buffers: HashMap<BufferId, OpenBuffer>,
     downstream_client: Option<(AnyProtoClient, u64)>,
@@ -202,6 +205,10 @@ impl BufferDiffState {
         let index_changed = self.index_changed;
         let head_changed = self.head_changed;
         let language_changed = self.language_changed;
+        let diff_algorithm = ProjectSettings::get_global(cx)
+            .git
+            .diff_algorithm
+            .unwrap_or_default();
         let index_matches_head = match (self.index_text.as_ref(), self.head_text.as_ref()) {
             (Some(index), Some(head)) => Arc::ptr_eq(index, head),
             (None, None) => true,
@@ -218,6 +225,7 @@ impl BufferDiffState {
                     language_changed,
                     language.clone(),
                     language_registry.clone(),
+                    diff_algorithm,
                     &mut cx,
                 )
                 .await?;
@@ -249,6 +257,7 @@ impl BufferDiffState {
                             language_changed,
                             language.clone(),
                             language_registry.clone(),
+                            diff_algorithm,
                             &mut cx,
                         )
                         .await?
@@ -310,6 +319,9 @@ struct RemoteBufferStore {
     loading_remote_buffers_by_id: HashMap<BufferId, Entity<Buffer>>,
     remote_buffer_listeners:
         HashMap<BufferId, Vec<oneshot::Sender<Result<Entity<Buffer>, anyhow::Error>>>>,
+    /// The buffers that diverged from the host's, whose text is replaced once their state is
+    /// received again.
+    resyncing_buffer_ids: HashSet<BufferId>,
     worktree_store: Entity<WorktreeStore>,
 }
 
@@ -1256,6 +1268,8 @@ impl BufferStore {
             shared_buffers: Default::default(),
             loading_buffers: Default::default(),
             loading_diffs: Default::default(),
+            saving_buffers: Default::default(),
+            next_save_id: 0,
             worktree_store,
         }
     }
@@ -1271,6 +1285,7 @@ impl BufferStore {
                 shared_with_me: Default::default(),
                 loading_remote_buffers_by_id: Default::default(),
                 remote_buffer_listeners: Default::default(),
+                resyncing_buffer_ids: Default::default(),
                 project_id: remote_id,
                 upstream_client,
                 worktree_store: worktree_store.clone(),
@@ -1279,6 +1294,8 @@ impl BufferStore {
             opened_buffers: Default::default(),
             loading_buffers: Default::default(),
             loading_diffs: Default::default(),
+            saving_buffers: Default::default(),
+            next_save_id: 0,
             shared_buffers: Default::default(),
             worktree_store,
         }
@@ -1526,10 +1543,11 @@ impl BufferStore {
         buffer: Entity<Buffer>,
         cx: &mut Context<Self>,
     ) -> Task<Result<()>> {
-        match &mut self.state {
+        let save = match &mut self.state {
             BufferStoreState::Local(this) => this.save_buffer(buffer, cx),
             BufferStoreState::Remote(this) => this.save_remote_buffer(buffer.clone(), None, cx),
-        }
+        };
+        self.track_save(save, cx)
     }
 
     pub fn save_buffer_as(
@@ -1545,11 +1563,39 @@ impl BufferStore {
                 this.save_remote_buffer(buffer.clone(), Some(path.to_proto()), cx)
             }
         };
-        cx.spawn(|this, mut cx| async move {
+        let save = cx.spawn(|this, mut cx| async move {
             task.await?;
             this.update(&mut cx, |_, cx| {
                 cx.emit(BufferStoreEvent::BufferChangedFilePath { buffer, old_file });
             })
+        });
+        self.track_save(save, cx)
+    }
+
+    /// Keeps the save running until its file is written, even if the returned task is
+    /// dropped, so that [`BufferStore::wait_for_saves`] can wait for it.
+    fn track_save(&mut self, save: Task<Result<()>>, cx: &mut Context<Self>) -> Task<Result<()>> {
+        let save_id = post_inc(&mut self.next_save_id);
+        let save = cx
+            .spawn(move |this, mut cx| async move {
+                let result = save.await;
+                this.update(&mut cx, |this, _| {
+                    this.saving_buffers.remove(&save_id);
+                })
+                .ok();
+                result.map_err(Arc::new)
+            })
+            .shared();
+        self.saving_buffers.insert(save_id, save.clone());
+        cx.background_executor()
+            .spawn(async move { save.await.map_err(|e| anyhow!("{e}")) })
+    }
+
+    /// Returns a task that completes once the files of the saves in progress are written.
+    pub fn wait_for_saves(&self, cx: &App) -> Task<()> {
+        let saves = self.saving_buffers.values().cloned().collect::<Vec<_>>();
+        cx.background_executor().spawn(async move {
+            futures::future::join_all(saves).await;
         })
     }
 
@@ -1713,6 +1759,7 @@ impl BufferStore {
     }
 
     fn add_buffer(&mut self, buffer_entity: Entity<Buffer>, cx: &mut Context<Self>) -> Result<()> {
+        buffer_entity.update(cx, |buffer, cx| buffer.refresh_disabled_features(cx));
         let buffer = buffer_entity.read(cx);
         let language = buffer.language().cloned();
         let language_registry = buffer.language_registry();
@@ -1825,6 +1872,35 @@ impl BufferStore {
         }
     }
 
+    /// Drops the git texts the diffs of the buffer were computed from, once none of its diffs
+    /// is open anymore. They're loaded again along with the next diff opened for the buffer.
+    /// Returns whether there were texts to drop.
+    pub fn release_diff_bases(&mut self, buffer_id: BufferId, cx: &mut Context<Self>) -> bool {
+        let is_loading = |kind| self.loading_diffs.contains_key(&(buffer_id, kind));
+        if is_loading(DiffKind::Unstaged) || is_loading(DiffKind::Uncommitted) {
+            return false;
+        }
+        let Some(OpenBuffer::Complete { diff_state, .. }) = self.opened_buffers.get(&buffer_id)
+        else {
+            return false;
+        };
+        diff_state.update(cx, |diff_state, _| {
+            let is_open = |diff: &Option<WeakEntity<BufferDiff>>| {
+                diff.as_ref().map_or(false, |diff| diff.upgrade().is_some())
+            };
+            if is_open(&diff_state.unstaged_diff)
+                || is_open(&diff_state.uncommitted_diff)
+                || !diff_state.diff_updated_futures.is_empty()
+                || (diff_state.head_text.is_none() && diff_state.index_text.is_none())
+            {
+                return false;
+            }
+            diff_state.head_text = None;
+            diff_state.index_text = None;
+            true
+        })
+    }
+
     pub fn buffer_version_info(&self, cx: &App) -> (Vec<proto::BufferVersion>, Vec<BufferId>) {
         let buffers = self
             .buffers()
@@ -1833,6 +1909,7 @@ impl BufferStore {
                 proto::BufferVersion {
                     id: buffer.remote_id().into(),
                     version: language::proto::serialize_version(&buffer.version),
+                    fingerprint: Some(buffer.fingerprint().to_u64()),
                 }
             })
             .collect();
@@ -2042,22 +2119,37 @@ impl BufferStore {
     pub fn handle_synchronize_buffers(
         &mut self,
         envelope: TypedEnvelope<proto::SynchronizeBuffers>,
+        guest_id: proto::PeerId,
         cx: &mut Context<Self>,
-        client: Arc<Client>,
+        client: AnyProtoClient,
     ) -> Result<proto::SynchronizeBuffersResponse> {
         let project_id = envelope.payload.project_id;
         let mut response = proto::SynchronizeBuffersResponse {
             buffers: Default::default(),
-        };
-        let Some(guest_id) = envelope.original_sender_id else {
-            anyhow::bail!("missing original_sender_id on SynchronizeBuffers request");
+            diverged_buffer_ids: Default::default(),
         };
 
         self.shared_buffers.entry(guest_id).or_default().clear();
         for buffer in envelope.payload.buffers {
             let buffer_id = BufferId::new(buffer.id)?;
             let remote_version = language::proto::deserialize_version(&buffer.version);
+            let remote_fingerprint = buffer.fingerprint;
             if let Some(buffer) = self.get(buffer_id) {
+                let diverged = {
+                    let buffer = buffer.read(cx);
+                    buffer.version == remote_version
+                        && remote_fingerprint.map_or(false, |fingerprint| {
+                            fingerprint != buffer.fingerprint().to_u64()
+                        })
+                };
+                if diverged {
+                    // The guest replaces its replica once it opens the buffer again, which sends
+                    // it the buffer's state since it's no longer shared with the guest.
+                    log::error!("buffer {buffer_id} diverged from the replica of {guest_id}");
+                    response.diverged_buffer_ids.push(buffer_id.into());
+                    continue;
+                }
+
                 self.shared_buffers
                     .entry(guest_id)
                     .or_default()
@@ -2072,6 +2164,7 @@ impl BufferStore {
                 response.buffers.push(proto::BufferVersion {
                     id: buffer_id.into(),
                     version: language::proto::serialize_version(&buffer.version),
+                    fingerprint: Some(buffer.fingerprint().to_u64()),
                 });
 
                 let operations = buffer.serialize_ops(Some(remote_version), cx);
@@ -2143,12 +2236,39 @@ impl BufferStore {
         if let Some(buffer) =
             remote.handle_create_buffer_for_peer(envelope, replica_id, capability, cx)?
         {
-            self.add_buffer(buffer, cx)?;
+            let buffer_id = buffer.read(cx).remote_id();
+            let diverged_buffer = if remote.resyncing_buffer_ids.remove(&buffer_id) {
+                self.get(buffer_id)
+            } else {
+                None
+            };
+            if let Some(diverged_buffer) = diverged_buffer {
+                buffer.update(cx, |replica, cx| {
+                    diverged_buffer.update(cx, |buffer, cx| buffer.resync(replica, cx))
+                });
+            } else {
+                self.add_buffer(buffer, cx)?;
+            }
         }
 
         Ok(())
     }
 
+    /// Requests the state of a buffer whose replica diverged from the host's, so that its text is
+    /// replaced once it's received.
+    pub fn resync_diverged_buffer(&mut self, buffer_id: BufferId, cx: &App) {
+        let Some(remote) = self.as_remote_mut() else {
+            return;
+        };
+        remote.resyncing_buffer_ids.insert(buffer_id);
+        cx.background_executor()
+            .spawn(remote.upstream_client.request(proto::OpenBufferById {
+                project_id: remote.project_id,
+                id: buffer_id.into(),
+            }))
+            .detach();
+    }
+
     pub async fn handle_update_buffer_file(
         this: Entity<Self>,
         envelope: TypedEnvelope<proto::UpdateBufferFile>,
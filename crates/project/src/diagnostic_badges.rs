@@ -0,0 +1,267 @@
+use crate::{
+    lsp_store::{LspStore, LspStoreEvent},
+    DiagnosticSummary, ProjectPath,
+};
+use collections::HashMap;
+use gpui::{Context, Entity, Subscription};
+use lsp::DiagnosticSeverity;
+
+/// The number of errors and warnings in a file, or in all the files of a directory.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DiagnosticBadge {
+    pub error_count: usize,
+    pub warning_count: usize,
+}
+
+impl DiagnosticBadge {
+    /// Returns the most severe kind of diagnostic counted by this badge.
+    pub fn max_severity(&self) -> Option<DiagnosticSeverity> {
+        if self.error_count > 0 {
+            Some(DiagnosticSeverity::ERROR)
+        } else if self.warning_count > 0 {
+            Some(DiagnosticSeverity::WARNING)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of diagnostics with the [`Self::max_severity`].
+    pub fn count(&self) -> usize {
+        if self.error_count > 0 {
+            self.error_count
+        } else {
+            self.warning_count
+        }
+    }
+
+    fn from_summary(summary: &DiagnosticSummary) -> Self {
+        Self {
+            error_count: summary.error_count,
+            warning_count: summary.warning_count,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Aggregates the diagnostic summaries of the project's files, so that problem
+/// badges can be shown next to files, and next to the directories containing them.
+///
+/// The badges are updated incrementally as the diagnostics of each file change, and only
+/// recomputed from all the summaries when a language server finishes or is removed.
+pub struct DiagnosticBadges {
+    lsp_store: Entity<LspStore>,
+    /// The badges of the files, without those of the directories.
+    file_badges: HashMap<ProjectPath, DiagnosticBadge>,
+    badges: HashMap<ProjectPath, DiagnosticBadge>,
+    _subscription: Subscription,
+}
+
+impl DiagnosticBadges {
+    pub fn new(lsp_store: Entity<LspStore>, cx: &mut Context<Self>) -> Self {
+        let subscription = cx.subscribe(&lsp_store, |this, _, event, cx| match event {
+            LspStoreEvent::DiagnosticsUpdated { path, .. } => this.update_path(path, cx),
+            LspStoreEvent::DiskBasedDiagnosticsFinished { .. }
+            | LspStoreEvent::LanguageServerRemoved(_) => this.recompute(cx),
+            _ => {}
+        });
+        let mut this = Self {
+            lsp_store,
+            file_badges: HashMap::default(),
+            badges: HashMap::default(),
+            _subscription: subscription,
+        };
+        this.recompute(cx);
+        this
+    }
+
+    /// Returns the badge of a file, or the combined badge of the files in a
+    /// directory. The root of a worktree has an empty path.
+    pub fn badge_for_path(&self, path: &ProjectPath) -> Option<DiagnosticBadge> {
+        self.badges.get(path).copied()
+    }
+
+    /// Returns the badges of all files and directories containing diagnostics.
+    pub fn badges(&self) -> impl Iterator<Item = (&ProjectPath, &DiagnosticBadge)> {
+        self.badges.iter()
+    }
+
+    fn update_path(&mut self, path: &ProjectPath, cx: &mut Context<Self>) {
+        let summary = self
+            .lsp_store
+            .read(cx)
+            .diagnostic_summary_for_path(path, false, cx);
+        if set_file_badge(
+            &mut self.file_badges,
+            &mut self.badges,
+            path,
+            DiagnosticBadge::from_summary(&summary),
+        ) {
+            cx.notify();
+        }
+    }
+
+    fn recompute(&mut self, cx: &mut Context<Self>) {
+        let (file_badges, badges) = aggregate_badges(
+            self.lsp_store
+                .read(cx)
+                .diagnostic_summaries(false, cx)
+                .map(|(path, _, summary)| (path, summary)),
+        );
+        self.file_badges = file_badges;
+        if badges != self.badges {
+            self.badges = badges;
+            cx.notify();
+        }
+    }
+}
+
+/// Returns the badges of the files, and those of the files and directories, from the
+/// summaries of the files' diagnostics.
+fn aggregate_badges(
+    summaries: impl IntoIterator<Item = (ProjectPath, DiagnosticSummary)>,
+) -> (
+    HashMap<ProjectPath, DiagnosticBadge>,
+    HashMap<ProjectPath, DiagnosticBadge>,
+) {
+    let mut file_badges = HashMap::<ProjectPath, DiagnosticBadge>::default();
+    for (path, summary) in summaries {
+        if summary.is_empty() {
+            continue;
+        }
+        let badge = file_badges.entry(path).or_default();
+        badge.error_count += summary.error_count;
+        badge.warning_count += summary.warning_count;
+    }
+
+    let mut badges = HashMap::<ProjectPath, DiagnosticBadge>::default();
+    for (path, badge) in &file_badges {
+        update_ancestors(&mut badges, path, DiagnosticBadge::default(), *badge);
+    }
+    (file_badges, badges)
+}
+
+/// Replaces the badge of a file, updating those of the directories containing it, and
+/// returns whether it changed.
+fn set_file_badge(
+    file_badges: &mut HashMap<ProjectPath, DiagnosticBadge>,
+    badges: &mut HashMap<ProjectPath, DiagnosticBadge>,
+    path: &ProjectPath,
+    badge: DiagnosticBadge,
+) -> bool {
+    let old_badge = if badge.is_empty() {
+        file_badges.remove(path)
+    } else {
+        file_badges.insert(path.clone(), badge)
+    };
+    let old_badge = old_badge.unwrap_or_default();
+    if old_badge == badge {
+        return false;
+    }
+    update_ancestors(badges, path, old_badge, badge);
+    true
+}
+
+/// Replaces the counts of the old badge of a file with those of its new badge, in its own
+/// badge and the badges of the directories containing it, removing those that become empty.
+fn update_ancestors(
+    badges: &mut HashMap<ProjectPath, DiagnosticBadge>,
+    path: &ProjectPath,
+    old_badge: DiagnosticBadge,
+    new_badge: DiagnosticBadge,
+) {
+    for ancestor in path.path.ancestors() {
+        let ancestor = ProjectPath {
+            worktree_id: path.worktree_id,
+            path: ancestor.into(),
+        };
+        let badge = badges.entry(ancestor.clone()).or_default();
+        badge.error_count = badge.error_count + new_badge.error_count - old_badge.error_count;
+        badge.warning_count =
+            badge.warning_count + new_badge.warning_count - old_badge.warning_count;
+        if badge.is_empty() {
+            badges.remove(&ancestor);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use worktree::WorktreeId;
+
+    #[test]
+    fn test_aggregate_badges() {
+        let worktree_id = WorktreeId::from_usize(1);
+        let project_path = |path: &str| ProjectPath {
+            worktree_id,
+            path: Path::new(path).into(),
+        };
+        let summary = |error_count, warning_count| DiagnosticSummary {
+            error_count,
+            warning_count,
+        };
+
+        let (mut file_badges, mut badges) = aggregate_badges([
+            (project_path("src/main.rs"), summary(0, 2)),
+            // Summaries of different language servers for the same file are combined.
+            (project_path("src/main.rs"), summary(1, 0)),
+            (project_path("src/lib/mod.rs"), summary(0, 3)),
+            (project_path("README.md"), summary(0, 0)),
+        ]);
+
+        let badge = |path| badges.get(&project_path(path)).copied();
+        assert_eq!(badge("src/main.rs"), Some(summary_badge(1, 2)));
+        assert_eq!(badge("src/lib"), Some(summary_badge(0, 3)));
+        assert_eq!(badge("src"), Some(summary_badge(1, 5)));
+        assert_eq!(badge(""), Some(summary_badge(1, 5)));
+        assert_eq!(badge("README.md"), None);
+        assert_eq!(file_badges.len(), 2);
+
+        // Updating the badge of a file updates those of its directories.
+        let main_rs = project_path("src/main.rs");
+        assert!(set_file_badge(
+            &mut file_badges,
+            &mut badges,
+            &main_rs,
+            summary_badge(0, 1)
+        ));
+        assert!(!set_file_badge(
+            &mut file_badges,
+            &mut badges,
+            &main_rs,
+            summary_badge(0, 1)
+        ));
+        let lib_rs = project_path("src/lib/mod.rs");
+        assert!(set_file_badge(
+            &mut file_badges,
+            &mut badges,
+            &lib_rs,
+            DiagnosticBadge::default()
+        ));
+        let badge = |path| badges.get(&project_path(path)).copied();
+        assert_eq!(badge("src/main.rs"), Some(summary_badge(0, 1)));
+        assert_eq!(badge("src/lib/mod.rs"), None);
+        assert_eq!(badge("src/lib"), None);
+        assert_eq!(badge(""), Some(summary_badge(0, 1)));
+        assert_eq!(file_badges.len(), 1);
+
+        assert_eq!(
+            summary_badge(1, 5).max_severity(),
+            Some(DiagnosticSeverity::ERROR)
+        );
+        assert_eq!(summary_badge(1, 5).count(), 1);
+        assert_eq!(summary_badge(0, 3).count(), 3);
+        assert_eq!(summary_badge(0, 0).max_severity(), None);
+    }
+
+    fn summary_badge(error_count: usize, warning_count: usize) -> DiagnosticBadge {
+        DiagnosticBadge {
+            error_count,
+            warning_count,
+        }
+    }
+}
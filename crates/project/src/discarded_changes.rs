@@ -0,0 +1,182 @@
+use anyhow::{Context as _, Result};
+use fs::{Fs, RemoveOptions};
+use futures::StreamExt as _;
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use util::ResultExt;
+
+/// How many discarded buffers are kept. The oldest ones are removed first.
+const MAX_DISCARDED_CHANGES: usize = 50;
+
+/// How long discarded buffers are kept.
+const DISCARDED_CHANGES_EXPIRY: Duration = Duration::from_secs(60 * 60 * 24 * 7);
+
+/// The contents of a dirty buffer that was closed without saving.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiscardedChange {
+    /// The path of the buffer's file, if it had one.
+    pub path: Option<PathBuf>,
+    pub text: String,
+    /// When the changes were discarded, in milliseconds since the Unix epoch.
+    pub discarded_at: i64,
+}
+
+/// A journal of discarded changes, stored as one JSON file per discarded buffer
+/// so that the edits can be recovered after the buffer was closed.
+#[derive(Clone)]
+pub struct DiscardedChangesJournal {
+    fs: Arc<dyn Fs>,
+    dir: PathBuf,
+}
+
+impl DiscardedChangesJournal {
+    pub fn new(fs: Arc<dyn Fs>, dir: PathBuf) -> Self {
+        Self { fs, dir }
+    }
+
+    /// Writes the change to the journal, removing the oldest entries beyond the limit,
+    /// and those that expired before it was discarded.
+    pub async fn record(&self, change: &DiscardedChange, discriminant: u64) -> Result<()> {
+        self.fs.create_dir(&self.dir).await?;
+        let entry_path = self
+            .dir
+            .join(format!("{}-{discriminant}.json", change.discarded_at));
+        self.fs
+            .atomic_write(entry_path, serde_json::to_string(change)?)
+            .await?;
+
+        let entries = self.entry_paths().await?;
+        for entry_path in entries.iter().skip(MAX_DISCARDED_CHANGES) {
+            self.remove(entry_path).await.log_err();
+        }
+        self.remove_expired(change.discarded_at).await
+    }
+
+    /// Removes the entries discarded longer than a week before the given time, in
+    /// milliseconds since the Unix epoch.
+    pub async fn remove_expired(&self, now: i64) -> Result<()> {
+        let expired_before = now.saturating_sub(DISCARDED_CHANGES_EXPIRY.as_millis() as i64);
+        for entry_path in self.entry_paths().await? {
+            if discarded_at(&entry_path).map_or(false, |discarded_at| discarded_at < expired_before)
+            {
+                self.remove(&entry_path).await.log_err();
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the journal's entries, most recently discarded first.
+    pub async fn entries(&self) -> Result<Vec<(PathBuf, DiscardedChange)>> {
+        let mut entries = Vec::new();
+        for entry_path in self.entry_paths().await? {
+            let change = self
+                .fs
+                .load(&entry_path)
+                .await
+                .and_then(|content| {
+                    serde_json::from_str::<DiscardedChange>(&content)
+                        .with_context(|| format!("parsing discarded changes {entry_path:?}"))
+                })
+                .log_err();
+            if let Some(change) = change {
+                entries.push((entry_path, change));
+            }
+        }
+        entries.sort_by_key(|(_, change)| std::cmp::Reverse(change.discarded_at));
+        Ok(entries)
+    }
+
+    /// Removes an entry, e.g. once its changes have been recovered.
+    pub async fn remove(&self, entry_path: &Path) -> Result<()> {
+        self.fs
+            .remove_file(
+                entry_path,
+                RemoveOptions {
+                    recursive: false,
+                    ignore_if_not_exists: true,
+                },
+            )
+            .await
+    }
+
+    /// Returns the paths of the entries, most recently discarded first.
+    async fn entry_paths(&self) -> Result<Vec<PathBuf>> {
+        if !self.fs.is_dir(&self.dir).await {
+            return Ok(Vec::new());
+        }
+        let mut entry_paths = Vec::new();
+        let mut read_dir = self.fs.read_dir(&self.dir).await?;
+        while let Some(entry_path) = read_dir.next().await {
+            let entry_path = entry_path?;
+            if entry_path.extension().map_or(false, |ext| ext == "json") {
+                entry_paths.push(entry_path);
+            }
+        }
+        entry_paths.sort_by_key(|entry_path| std::cmp::Reverse(discarded_at(entry_path)));
+        Ok(entry_paths)
+    }
+}
+
+fn discarded_at(entry_path: &Path) -> Option<i64> {
+    entry_path
+        .file_stem()?
+        .to_str()?
+        .split('-')
+        .next()?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fs::FakeFs;
+    use gpui::TestAppContext;
+
+    #[gpui::test]
+    async fn test_discarded_changes_journal(cx: &mut TestAppContext) {
+        let fs = FakeFs::new(cx.executor());
+        let journal = DiscardedChangesJournal::new(fs, PathBuf::from("/history"));
+        assert_eq!(journal.entries().await.unwrap(), Vec::new());
+
+        for discarded_at in 0..MAX_DISCARDED_CHANGES as i64 + 2 {
+            let change = DiscardedChange {
+                path: Some(PathBuf::from("/code/main.rs")),
+                text: format!("edit {discarded_at}"),
+                discarded_at,
+            };
+            journal.record(&change, 1).await.unwrap();
+        }
+
+        let entries = journal.entries().await.unwrap();
+        assert_eq!(entries.len(), MAX_DISCARDED_CHANGES);
+        assert_eq!(
+            entries[0].1.text,
+            format!("edit {}", MAX_DISCARDED_CHANGES + 1)
+        );
+        assert_eq!(entries.last().unwrap().1.text, "edit 2");
+
+        journal.remove(&entries[0].0).await.unwrap();
+        let entries = journal.entries().await.unwrap();
+        assert_eq!(entries[0].1.text, format!("edit {}", MAX_DISCARDED_CHANGES));
+
+        let expiry = DISCARDED_CHANGES_EXPIRY.as_millis() as i64;
+        journal.remove_expired(expiry + 3).await.unwrap();
+        let entries = journal.entries().await.unwrap();
+        assert_eq!(entries.len(), MAX_DISCARDED_CHANGES - 2);
+        assert_eq!(entries.last().unwrap().1.text, "edit 3");
+
+        let change = DiscardedChange {
+            path: None,
+            text: "latest edit".into(),
+            discarded_at: 2 * expiry,
+        };
+        journal.record(&change, 1).await.unwrap();
+        let entry_path = PathBuf::from(format!("/history/{}-1.json", change.discarded_at));
+        assert_eq!(journal.entries().await.unwrap(), [(entry_path, change)]);
+    }
+}
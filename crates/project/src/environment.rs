@@ -10,6 +10,7 @@ use worktree::WorktreeId;
 use crate::{
     project_settings::{DirenvSettings, ProjectSettings},
     worktree_store::{WorktreeStore, WorktreeStoreEvent},
+    worktree_trust::{self, WorktreeTrust},
 };
 
 pub struct ProjectEnvironment {
@@ -32,6 +33,8 @@ impl ProjectEnvironment {
                 }
             })
             .detach();
+            cx.observe_global::<WorktreeTrust>(|this: &mut Self, _| this.environments.clear())
+                .detach();
 
             Self {
                 worktree_store: worktree_store.clone(),
@@ -131,16 +134,17 @@ impl ProjectEnvironment {
         worktree_abs_path: Arc<Path>,
         cx: &Context<Self>,
     ) -> Task<Option<HashMap<String, String>>> {
-        let load_direnv = ProjectSettings::get_global(cx).load_direnv.clone();
+        let (environment_dir, load_direnv) = shell_environment_source(
+            worktree_abs_path.clone(),
+            ProjectSettings::get_global(cx).load_direnv.clone(),
+            worktree_trust::is_path_trusted(&worktree_abs_path, cx),
+        );
 
         cx.spawn(|this, mut cx| async move {
             let (mut shell_env, error_message) = cx
                 .background_executor()
-                .spawn({
-                    let worktree_abs_path = worktree_abs_path.clone();
-                    async move {
-                        load_worktree_shell_environment(&worktree_abs_path, &load_direnv).await
-                    }
+                .spawn(async move {
+                    load_worktree_shell_environment(&environment_dir, &load_direnv).await
                 })
                 .await;
 
@@ -199,6 +203,25 @@ impl EnvironmentErrorMessage {
     }
 }
 
+/// Returns the path to load the shell environment of a worktree in, and how to load the
+/// direnv configuration there. Loading it runs the shell hooks and `.envrc` files of that
+/// directory, so untrusted worktrees get the environment of the home directory, where
+/// direnv is only loaded by the user's own shell hooks.
+fn shell_environment_source(
+    worktree_abs_path: Arc<Path>,
+    load_direnv: DirenvSettings,
+    trusted: bool,
+) -> (Arc<Path>, DirenvSettings) {
+    if trusted {
+        (worktree_abs_path, load_direnv)
+    } else {
+        (
+            Arc::from(paths::home_dir().as_path()),
+            DirenvSettings::ShellHook,
+        )
+    }
+}
+
 async fn load_worktree_shell_environment(
     worktree_abs_path: &Path,
     load_direnv: &DirenvSettings,
@@ -367,3 +390,23 @@ async fn load_shell_environment(
 
     (Some(parsed_env), direnv_error)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_untrusted_shell_environment_source() {
+        let worktree_abs_path: Arc<Path> = Arc::from(Path::new("/code/untrusted"));
+
+        let (dir, load_direnv) =
+            shell_environment_source(worktree_abs_path.clone(), DirenvSettings::Direct, true);
+        assert_eq!(dir, worktree_abs_path);
+        assert!(matches!(load_direnv, DirenvSettings::Direct));
+
+        let (dir, load_direnv) =
+            shell_environment_source(worktree_abs_path.clone(), DirenvSettings::Direct, false);
+        assert_eq!(dir.as_ref(), paths::home_dir().as_path());
+        assert!(matches!(load_direnv, DirenvSettings::ShellHook));
+    }
+}
@@ -0,0 +1,390 @@
+use crate::{buffer_store::BufferStore, worktree_store::WorktreeStore, ProjectPath};
+use anyhow::Result;
+use fs::Fs;
+use futures::future::join_all;
+use gpui::{Context, Entity, EventEmitter, Task};
+use language::{language_settings::AllLanguageSettings, LanguageRegistry, LineEnding, Rope};
+use settings::{Settings as _, SettingsLocation};
+use std::{num::NonZeroU32, path::PathBuf, sync::Arc};
+use util::ResultExt;
+
+/// Files larger than this are not audited.
+const MAX_AUDITED_FILE_SIZE: u64 = 1024 * 1024;
+
+/// How many files are processed before progress is reported.
+const AUDIT_BATCH_SIZE: usize = 256;
+
+/// How many leading bytes are searched for NUL bytes to detect binary files.
+const BINARY_DETECTION_LEN: usize = 8192;
+
+const BYTE_ORDER_MARK: &[u8] = b"\xEF\xBB\xBF";
+
+/// Formatting inconsistencies found in a text file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FileFormatIssues {
+    pub crlf_line_endings: bool,
+    pub tab_indentation: bool,
+    pub byte_order_mark: bool,
+    /// The file isn't valid UTF-8, so it can't be converted.
+    pub invalid_utf8: bool,
+}
+
+impl FileFormatIssues {
+    /// Inspects the contents of a file, returning `None` for binary files.
+    pub fn detect(content: &[u8]) -> Option<Self> {
+        let prefix = &content[..content.len().min(BINARY_DETECTION_LEN)];
+        if prefix.contains(&0) {
+            return None;
+        }
+
+        let byte_order_mark = content.starts_with(BYTE_ORDER_MARK);
+        let content = content.strip_prefix(BYTE_ORDER_MARK).unwrap_or(content);
+        Some(Self {
+            crlf_line_endings: content.windows(2).any(|window| window == b"\r\n"),
+            tab_indentation: content.starts_with(b"\t")
+                || content.windows(2).any(|window| window == b"\n\t"),
+            byte_order_mark,
+            invalid_utf8: std::str::from_utf8(content).is_err(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// How the audited files are rewritten.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FileConversion {
+    /// Converts every line ending to the given one.
+    pub line_ending: Option<LineEnding>,
+    /// Replaces leading tabs with as many spaces as the tab size of each file.
+    pub replace_tabs: bool,
+    pub remove_byte_order_mark: bool,
+}
+
+impl FileConversion {
+    /// Whether converting a file with the given issues would change it.
+    pub fn affects(&self, issues: &FileFormatIssues) -> bool {
+        !issues.invalid_utf8
+            && ((self.line_ending == Some(LineEnding::Unix) && issues.crlf_line_endings)
+                || (self.line_ending == Some(LineEnding::Windows) && !issues.crlf_line_endings)
+                || (self.replace_tabs && issues.tab_indentation)
+                || (self.remove_byte_order_mark && issues.byte_order_mark))
+    }
+
+    /// Returns the converted text, with `\n` line endings, along with the line
+    /// ending it should be saved with.
+    fn apply(&self, text: &str, tab_size: NonZeroU32) -> (String, LineEnding) {
+        let line_ending = self.line_ending.unwrap_or_else(|| LineEnding::detect(text));
+        let mut text = text.to_string();
+        let byte_order_mark = text.starts_with('\u{feff}');
+        if byte_order_mark {
+            text.remove(0);
+        }
+        LineEnding::normalize(&mut text);
+
+        if self.replace_tabs {
+            let indent = " ".repeat(tab_size.get() as usize);
+            text = text
+                .split_inclusive('\n')
+                .map(|line| {
+                    let content = line.trim_start_matches('\t');
+                    indent.repeat(line.len() - content.len()) + content
+                })
+                .collect();
+        }
+        if byte_order_mark && !self.remove_byte_order_mark {
+            text.insert(0, '\u{feff}');
+        }
+        (text, line_ending)
+    }
+}
+
+/// A file with formatting issues.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditedFile {
+    pub path: ProjectPath,
+    pub issues: FileFormatIssues,
+    /// Whether the file is converted by [`FileAudit::convert`].
+    pub included: bool,
+    abs_path: PathBuf,
+}
+
+pub enum FileAuditEvent {
+    Progress { processed: usize, total: usize },
+    Finished,
+}
+
+impl EventEmitter<FileAuditEvent> for FileAudit {}
+
+/// Reports the files of all local worktrees with CRLF line endings, tab
+/// indentation, byte order marks or invalid UTF-8, and converts them in bulk.
+pub struct FileAudit {
+    buffer_store: Entity<BufferStore>,
+    languages: Arc<LanguageRegistry>,
+    fs: Arc<dyn Fs>,
+    files: Vec<AuditedFile>,
+    task: Option<Task<()>>,
+}
+
+impl FileAudit {
+    pub fn new(
+        worktree_store: Entity<WorktreeStore>,
+        buffer_store: Entity<BufferStore>,
+        languages: Arc<LanguageRegistry>,
+        fs: Arc<dyn Fs>,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let mut paths = Vec::new();
+        for worktree in worktree_store.read(cx).visible_worktrees(cx) {
+            let worktree = worktree.read(cx);
+            if !worktree.is_local() {
+                continue;
+            }
+            let worktree_id = worktree.id();
+            let snapshot = worktree.snapshot();
+            paths.extend(
+                snapshot
+                    .files(false, 0)
+                    .filter(|entry| entry.size <= MAX_AUDITED_FILE_SIZE && !entry.is_fifo)
+                    .filter_map(|entry| {
+                        let abs_path = snapshot.absolutize(&entry.path).log_err()?;
+                        let path = ProjectPath {
+                            worktree_id,
+                            path: entry.path.clone(),
+                        };
+                        Some((path, abs_path))
+                    }),
+            );
+        }
+
+        let mut this = Self {
+            buffer_store,
+            languages,
+            fs,
+            files: Vec::new(),
+            task: None,
+        };
+        this.task = Some(this.audit(paths, cx));
+        this
+    }
+
+    /// Returns the files with issues found so far, ordered by path.
+    pub fn files(&self) -> &[AuditedFile] {
+        &self.files
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.task.is_some()
+    }
+
+    /// Includes the given file in, or excludes it from, the conversion.
+    pub fn set_included(&mut self, path: &ProjectPath, included: bool, cx: &mut Context<Self>) {
+        if let Ok(ix) = self.files.binary_search_by(|file| file.path.cmp(path)) {
+            self.files[ix].included = included;
+            cx.notify();
+        }
+    }
+
+    /// Returns the files that [`FileAudit::convert`] would rewrite.
+    pub fn preview<'a>(
+        &'a self,
+        conversion: &'a FileConversion,
+    ) -> impl Iterator<Item = &'a AuditedFile> {
+        self.files
+            .iter()
+            .filter(|file| file.included && conversion.affects(&file.issues))
+    }
+
+    /// Rewrites the included files, then audits them again. Files open in buffers with unsaved
+    /// changes are skipped, so that the changes aren't lost, and returned. Tabs are replaced
+    /// according to the tab size of each file's language and location.
+    pub fn convert(
+        &mut self,
+        conversion: FileConversion,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Vec<ProjectPath>>> {
+        if self.task.is_some() {
+            return Task::ready(Err(anyhow::anyhow!("the audit is still running")));
+        }
+
+        let buffer_store = self.buffer_store.read(cx);
+        let (skipped_files, files): (Vec<_>, Vec<_>) =
+            self.preview(&conversion).partition(|file| {
+                buffer_store
+                    .get_by_path(&file.path, cx)
+                    .map_or(false, |buffer| buffer.read(cx).is_dirty())
+            });
+        let skipped_paths = skipped_files
+            .into_iter()
+            .map(|file| file.path.clone())
+            .collect::<Vec<_>>();
+        let files = files
+            .into_iter()
+            .map(|file| {
+                let tab_size = self.tab_size(&file.path, cx);
+                (file.path.clone(), file.abs_path.clone(), tab_size)
+            })
+            .collect::<Vec<_>>();
+        let fs = self.fs.clone();
+        let executor = cx.background_executor().clone();
+        cx.spawn(|this, mut cx| async move {
+            let total = files.len();
+            let mut processed = 0;
+            for batch in files.chunks(AUDIT_BATCH_SIZE) {
+                let conversions = batch.iter().map(|(_, abs_path, tab_size)| {
+                    let fs = fs.clone();
+                    let abs_path = abs_path.clone();
+                    let tab_size = *tab_size;
+                    executor.spawn(async move {
+                        let text = fs.load(&abs_path).await?;
+                        let (text, line_ending) = conversion.apply(&text, tab_size);
+                        fs.save(&abs_path, &Rope::from(text.as_str()), line_ending)
+                            .await
+                    })
+                });
+                for result in join_all(conversions).await {
+                    result.log_err();
+                }
+                processed += batch.len();
+                this.update(&mut cx, |_, cx| {
+                    cx.emit(FileAuditEvent::Progress { processed, total })
+                })?;
+            }
+
+            let paths = files
+                .into_iter()
+                .map(|(path, abs_path, _)| (path, abs_path))
+                .collect();
+            let task = this.update(&mut cx, |this, cx| this.audit(paths, cx))?;
+            this.update(&mut cx, |this, _| this.task = Some(task))?;
+            Ok(skipped_paths)
+        })
+    }
+
+    /// Returns the tab size configured for the file, in its language and at its location.
+    fn tab_size(&self, path: &ProjectPath, cx: &Context<Self>) -> NonZeroU32 {
+        let language = self.languages.language_name_for_file_path(&path.path);
+        let location = SettingsLocation {
+            worktree_id: path.worktree_id,
+            path: &path.path,
+        };
+        AllLanguageSettings::get(Some(location), cx)
+            .language(Some(location), language.as_ref(), cx)
+            .tab_size
+    }
+
+    fn audit(&mut self, paths: Vec<(ProjectPath, PathBuf)>, cx: &mut Context<Self>) -> Task<()> {
+        let fs = self.fs.clone();
+        let executor = cx.background_executor().clone();
+        cx.spawn(|this, mut cx| async move {
+            let total = paths.len();
+            let mut processed = 0;
+            for batch in paths.chunks(AUDIT_BATCH_SIZE) {
+                let audits = batch.iter().map(|(path, abs_path)| {
+                    let fs = fs.clone();
+                    let path = path.clone();
+                    let abs_path = abs_path.clone();
+                    executor.spawn(async move {
+                        let content = fs.load_bytes(&abs_path).await.ok()?;
+                        let issues = FileFormatIssues::detect(&content)?;
+                        Some(AuditedFile {
+                            path,
+                            issues,
+                            included: true,
+                            abs_path,
+                        })
+                    })
+                });
+                let audited_files = join_all(audits).await;
+                processed += batch.len();
+
+                let updated = this.update(&mut cx, |this, cx| {
+                    for (path, _) in batch {
+                        if let Ok(ix) = this.files.binary_search_by(|file| file.path.cmp(path)) {
+                            this.files.remove(ix);
+                        }
+                    }
+                    for file in audited_files.into_iter().flatten() {
+                        if file.issues.is_empty() {
+                            continue;
+                        }
+                        if let Err(ix) = this.files.binary_search_by(|f| f.path.cmp(&file.path)) {
+                            this.files.insert(ix, file);
+                        }
+                    }
+                    cx.emit(FileAuditEvent::Progress { processed, total });
+                    cx.notify();
+                });
+                if updated.is_err() {
+                    return;
+                }
+            }
+
+            this.update(&mut cx, |this, cx| {
+                this.task = None;
+                cx.emit(FileAuditEvent::Finished);
+                cx.notify();
+            })
+            .ok();
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_format_issues() {
+        assert_eq!(
+            FileFormatIssues::detect(b"\xEF\xBB\xBFfn main() {\r\n\tlet x = 1;\r\n}"),
+            Some(FileFormatIssues {
+                crlf_line_endings: true,
+                tab_indentation: true,
+                byte_order_mark: true,
+                invalid_utf8: false,
+            })
+        );
+        assert_eq!(
+            FileFormatIssues::detect(b"caf\xE9\n"),
+            Some(FileFormatIssues {
+                invalid_utf8: true,
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            FileFormatIssues::detect(b"let tab = '\t';\n"),
+            Some(FileFormatIssues::default())
+        );
+        assert_eq!(FileFormatIssues::detect(b"\x7fELF\0\0"), None);
+    }
+
+    #[test]
+    fn test_file_conversion() {
+        let tab_size = NonZeroU32::new(4).unwrap();
+        let conversion = FileConversion {
+            line_ending: Some(LineEnding::Unix),
+            replace_tabs: true,
+            remove_byte_order_mark: true,
+        };
+        assert_eq!(
+            conversion.apply("\u{feff}fn main() {\r\n\t\tx = '\t';\r\n}", tab_size),
+            (
+                "fn main() {\n        x = '\t';\n}".to_string(),
+                LineEnding::Unix
+            )
+        );
+
+        let conversion = FileConversion {
+            line_ending: None,
+            replace_tabs: false,
+            remove_byte_order_mark: false,
+        };
+        assert_eq!(
+            conversion.apply("\u{feff}a\r\nb\r\n", tab_size),
+            ("\u{feff}a\nb\n".to_string(), LineEnding::Windows)
+        );
+    }
+}
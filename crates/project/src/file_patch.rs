@@ -0,0 +1,233 @@
+//! Edits to the files of a project with no open buffer, made on disk through the file system.
+//!
+//! A rename can touch hundreds of files, and opening a buffer for each of them would load,
+//! parse and keep all of them in memory. The edits to the files with no open buffer can instead
+//! be returned as [`FilePatch`]es, holding the lines they change to preview them.
+//! [`Project::apply_file_patches`] then reads, patches and writes the files one at a time,
+//! skipping those that changed since their patch was made. A patch can also be made in the
+//! buffer of its file, once it is opened, with [`FilePatch::edits_in_buffer`].
+
+use crate::{Project, ProjectPath, ProjectTransaction};
+use anyhow::Result;
+use fs::{Fs, MTime};
+use gpui::{Context, Task};
+use language::{Buffer, File as _};
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use text::{Anchor, Bias, BufferId, LineEnding, Point, PointUtf16, Rope, Unclipped};
+
+/// The edits made to a project, in buffers and in the files with no open buffer.
+#[derive(Debug, Default)]
+pub struct ProjectPatch {
+    pub transaction: ProjectTransaction,
+    pub file_patches: Vec<FilePatch>,
+}
+
+/// Edits to a file with no open buffer, to be made on disk.
+#[derive(Clone, Debug)]
+pub struct FilePatch {
+    pub project_path: ProjectPath,
+    pub abs_path: PathBuf,
+    /// The modification time of the file when the patch was made, which it must still have
+    /// for the patch to be applied.
+    pub mtime: Option<MTime>,
+    /// The edits, sorted and clipped to the text of the file.
+    pub edits: Vec<(Range<PointUtf16>, Arc<str>)>,
+    pub hunks: Vec<FilePatchHunk>,
+}
+
+/// Lines of a file changed by a [`FilePatch`], before and after it is applied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FilePatchHunk {
+    pub start_row: u32,
+    pub old_text: String,
+    pub new_text: String,
+}
+
+impl FilePatch {
+    /// Reads the file, returning the patch making the edits to it.
+    pub async fn read(
+        fs: &dyn Fs,
+        project_path: ProjectPath,
+        abs_path: PathBuf,
+        edits: Vec<(Range<Unclipped<PointUtf16>>, String)>,
+    ) -> Result<Self> {
+        let mtime = fs.metadata(&abs_path).await?.map(|metadata| metadata.mtime);
+        let (text, _) = load_text(fs, &abs_path).await?;
+        let mut edits = edits
+            .into_iter()
+            .map(|(range, new_text)| {
+                let start = text.clip_point_utf16(range.start, Bias::Left);
+                let end = text.clip_point_utf16(range.end, Bias::Left);
+                (start..end, Arc::from(new_text))
+            })
+            .collect::<Vec<_>>();
+        edits.sort_by_key(|(range, _)| range.start);
+        let hunks = hunks(&text, &edits);
+        Ok(Self {
+            project_path,
+            abs_path,
+            mtime,
+            edits,
+            hunks,
+        })
+    }
+
+    /// Adds edits to the patch, made to the file after its other edits, as when the file is
+    /// edited again by the same workspace edit.
+    pub(crate) async fn extend(
+        &mut self,
+        fs: &dyn Fs,
+        edits: Vec<(Range<Unclipped<PointUtf16>>, String)>,
+    ) -> Result<()> {
+        let (text, _) = load_text(fs, &self.abs_path).await?;
+        let mut buffer = text::Buffer::new(0, BufferId::new(1)?, text.to_string());
+        let version = buffer.version();
+        buffer.edit(self.edits.iter().cloned());
+        let edits = edits
+            .into_iter()
+            .map(|(range, new_text)| {
+                let start = buffer.clip_point_utf16(range.start, Bias::Left);
+                let end = buffer.clip_point_utf16(range.end, Bias::Left);
+                (start..end, new_text)
+            })
+            .collect::<Vec<_>>();
+        buffer.edit(edits);
+        self.edits = buffer
+            .edits_since::<PointUtf16>(&version)
+            .map(|edit| {
+                let new_text = buffer.text_for_range(edit.new).collect::<String>();
+                (edit.old, Arc::from(new_text))
+            })
+            .collect();
+        self.hunks = hunks(&text, &self.edits);
+        Ok(())
+    }
+
+    /// Returns the edits of the patch in the buffer of its file, unless the buffer has unsaved
+    /// changes, or the file changed since the patch was made.
+    pub fn edits_in_buffer(&self, buffer: &Buffer) -> Option<Vec<(Range<Anchor>, Arc<str>)>> {
+        let mtime = buffer.file()?.disk_state().mtime();
+        if buffer.is_dirty() || mtime != self.mtime {
+            return None;
+        }
+        Some(
+            self.edits
+                .iter()
+                .map(|(range, new_text)| {
+                    let start = buffer.clip_point_utf16(Unclipped(range.start), Bias::Left);
+                    let end = buffer.clip_point_utf16(Unclipped(range.end), Bias::Left);
+                    (
+                        buffer.anchor_after(start)..buffer.anchor_before(end),
+                        new_text.clone(),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// Makes the edits of the patch to its file, returning whether they were made, which they
+    /// aren't when the file changed since the patch was made.
+    async fn apply(&self, fs: &dyn Fs) -> Result<bool> {
+        let mtime = fs
+            .metadata(&self.abs_path)
+            .await?
+            .map(|metadata| metadata.mtime);
+        if mtime != self.mtime {
+            return Ok(false);
+        }
+
+        let (text, line_ending) = load_text(fs, &self.abs_path).await?;
+        let mut new_text = String::with_capacity(text.len());
+        let mut last_offset = 0;
+        for (range, edit_text) in &self.edits {
+            let start = text.point_utf16_to_offset(range.start);
+            let end = text.point_utf16_to_offset(range.end);
+            new_text.extend(text.chunks_in_range(last_offset..start));
+            new_text.push_str(edit_text);
+            last_offset = end;
+        }
+        new_text.extend(text.chunks_in_range(last_offset..text.len()));
+        fs.save(&self.abs_path, &Rope::from(new_text), line_ending)
+            .await?;
+        Ok(true)
+    }
+}
+
+impl Project {
+    /// Applies the patches to their files one at a time, returning those that weren't
+    /// applied, because their file changed since they were made or couldn't be written, or
+    /// was opened since in a buffer now having unsaved changes. Unlike edits made in buffers,
+    /// the patches can't be undone once applied.
+    pub fn apply_file_patches(
+        &self,
+        file_patches: Vec<FilePatch>,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Vec<FilePatch>>> {
+        let fs = self.fs.clone();
+        cx.spawn(|this, mut cx| async move {
+            let mut unapplied_patches = Vec::new();
+            for file_patch in file_patches {
+                let has_unsaved_changes = this.update(&mut cx, |this, cx| {
+                    this.buffer_store
+                        .read(cx)
+                        .get_by_path(&file_patch.project_path, cx)
+                        .map_or(false, |buffer| buffer.read(cx).is_dirty())
+                })?;
+                let applied = !has_unsaved_changes
+                    && file_patch
+                        .apply(fs.as_ref())
+                        .await
+                        .inspect_err(|error| {
+                            log::error!("failed to patch {:?}: {error:#}", file_patch.abs_path)
+                        })
+                        .unwrap_or(false);
+                if !applied {
+                    unapplied_patches.push(file_patch);
+                }
+            }
+            Ok(unapplied_patches)
+        })
+    }
+}
+
+async fn load_text(fs: &dyn Fs, abs_path: &Path) -> Result<(Rope, LineEnding)> {
+    let mut text = fs.load(abs_path).await?;
+    let line_ending = LineEnding::detect(&text);
+    LineEnding::normalize(&mut text);
+    Ok((Rope::from(text), line_ending))
+}
+
+/// Returns the lines changed by the sorted edits, merging the edits of the same lines.
+fn hunks(text: &Rope, edits: &[(Range<PointUtf16>, Arc<str>)]) -> Vec<FilePatchHunk> {
+    let mut hunks = Vec::new();
+    let mut edits = edits.iter().peekable();
+    while let Some((first_range, _)) = edits.peek() {
+        let start_row = first_range.start.row;
+        let mut end_row = first_range.end.row;
+        let line_start = text.point_to_offset(Point::new(start_row, 0));
+        let mut new_text = String::new();
+        let mut last_offset = line_start;
+        while let Some((range, edit_text)) = edits.next_if(|(range, _)| range.start.row <= end_row)
+        {
+            end_row = end_row.max(range.end.row);
+            let start = text.point_utf16_to_offset(range.start);
+            let end = text.point_utf16_to_offset(range.end);
+            new_text.extend(text.chunks_in_range(last_offset..start));
+            new_text.push_str(edit_text);
+            last_offset = end;
+        }
+        let line_end = text.point_to_offset(Point::new(end_row, text.line_len(end_row)));
+        let old_text = text.chunks_in_range(line_start..line_end).collect();
+        new_text.extend(text.chunks_in_range(last_offset..line_end));
+        hunks.push(FilePatchHunk {
+            start_row,
+            old_text,
+            new_text,
+        });
+    }
+    hunks
+}
@@ -1,7 +1,7 @@
 use crate::buffer_store::BufferStore;
 use crate::worktree_store::{WorktreeStore, WorktreeStoreEvent};
 use crate::{Project, ProjectPath};
-use anyhow::{Context as _, Result};
+use anyhow::{anyhow, Context as _, Result};
 use client::ProjectId;
 use futures::channel::{mpsc, oneshot};
 use futures::StreamExt as _;
@@ -762,6 +762,24 @@ impl Repository {
         }
     }
 
+    /// Loads the contents of a path in the tree of the given revision.
+    pub fn load_revision_text(
+        &self,
+        revision: &str,
+        path: RepoPath,
+        cx: &Context<Self>,
+    ) -> Task<Result<Option<String>>> {
+        let revision = revision.to_string();
+        match self.git_repo.clone() {
+            GitRepo::Local(git_repository) => cx
+                .background_executor()
+                .spawn(async move { git_repository.load_revision_text(&revision, &path) }),
+            GitRepo::Remote { .. } => Task::ready(Err(anyhow!(
+                "loading revisions of remote repositories isn't supported"
+            ))),
+        }
+    }
+
     pub fn stage_entries(&self, entries: Vec<RepoPath>) -> oneshot::Receiver<Result<()>> {
         let (result_tx, result_rx) = futures::channel::oneshot::channel();
         if entries.is_empty() {
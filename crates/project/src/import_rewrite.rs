@@ -0,0 +1,519 @@
+//! Textual rewriting of path-based imports, used to update the files referencing a
+//! renamed file or directory when no language server does it through
+//! `workspace/willRenameFiles`.
+//!
+//! Two kinds of imports are understood:
+//! - Relative module specifiers in JavaScript and TypeScript files, e.g.
+//!   `import { a } from "./a"` or `require("../a.js")`.
+//! - Rust module declarations and paths, e.g. `mod a;`, `crate::b::a` or `super::a`.
+//!   Rust modules are only renamed in place, since moving one to another directory
+//!   requires moving its declaration too. Inline modules, e.g. `mod a { .. }`, don't
+//!   refer to a file and are left alone.
+//!
+//! Matches within comments and string literals aren't imports, and are skipped.
+
+use crate::{Project, ProjectTransaction};
+use anyhow::Result;
+use gpui::{AsyncApp, Entity, WeakEntity};
+use regex::Regex;
+use std::{
+    ops::Range,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, LazyLock},
+};
+use util::ResultExt;
+use worktree::Worktree;
+
+const SCRIPT_EXTENSIONS: &[&str] = &["js", "jsx", "mjs", "cjs", "ts", "tsx", "mts", "cts"];
+
+/// The files whose module declarations are the parents of the modules in their directory.
+const RUST_MODULE_ROOTS: &[&str] = &["mod.rs", "lib.rs", "main.rs"];
+
+static SCRIPT_IMPORT_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"\b(?:from|import|require)\s*\(?\s*['"](\.{1,2}/[^'"\r\n]*)['"]"#).unwrap()
+});
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ImportSyntax {
+    Script,
+    Rust,
+}
+
+impl ImportSyntax {
+    pub(crate) fn for_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        if SCRIPT_EXTENSIONS.contains(&extension) {
+            Some(Self::Script)
+        } else if extension == "rs" {
+            Some(Self::Rust)
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether the files of the worktree can import the entry at `path` with a syntax whose imports
+/// are rewritten: the entry is such a file, or a directory containing some.
+pub(crate) fn may_be_imported(worktree: &Worktree, path: &Path, is_dir: bool) -> bool {
+    if is_dir {
+        worktree
+            .traverse_from_path(true, false, false, path)
+            .take_while(|entry| entry.path.starts_with(path))
+            .any(|entry| ImportSyntax::for_path(&entry.path).is_some())
+    } else {
+        ImportSyntax::for_path(path).is_some()
+    }
+}
+
+/// Updates the imports referencing `old_path` in the worktree's files, now that it
+/// was renamed to `new_path`. Both paths are relative to the worktree's root.
+///
+/// Only the files with the syntax of the renamed file are searched, or the files with any of
+/// the syntaxes when a directory was renamed. The edited buffers are left dirty, so that the
+/// edits can be reviewed before saving.
+pub(crate) async fn rewrite_imports(
+    project: WeakEntity<Project>,
+    worktree: Entity<Worktree>,
+    old_path: Arc<Path>,
+    new_path: Arc<Path>,
+    mut cx: AsyncApp,
+) -> Result<ProjectTransaction> {
+    let mut project_transaction = ProjectTransaction::default();
+    let (worktree_id, root_path, candidates) = worktree.read_with(&cx, |worktree, _| {
+        let is_dir = worktree
+            .entry_for_path(&new_path)
+            .map_or(false, |entry| entry.is_dir());
+        let renamed_syntax = ImportSyntax::for_path(&old_path);
+        let candidates = if !worktree.is_local() || (!is_dir && renamed_syntax.is_none()) {
+            Vec::new()
+        } else {
+            worktree
+                .files(false, 0)
+                .filter(|entry| {
+                    let syntax = ImportSyntax::for_path(&entry.path);
+                    syntax.is_some() && (is_dir || syntax == renamed_syntax)
+                })
+                .map(|entry| entry.path.clone())
+                .collect::<Vec<_>>()
+        };
+        (worktree.id(), worktree.abs_path(), candidates)
+    })?;
+    let fs = project.read_with(&cx, |project, _| project.fs().clone())?;
+
+    for path in candidates {
+        // Most files don't import the renamed path: only open buffers for the ones that do.
+        let Some(text) = fs.load(&root_path.join(&path)).await.log_err() else {
+            continue;
+        };
+        if import_edits(&path, &text, &old_path, &new_path).is_empty() {
+            continue;
+        }
+
+        let buffer = project
+            .update(&mut cx, |project, cx| {
+                project.open_buffer((worktree_id, path.clone()), cx)
+            })?
+            .await?;
+        let transaction = buffer.update(&mut cx, |buffer, cx| {
+            // The buffer's text may differ from the file's, if it has unsaved changes.
+            let edits = import_edits(&path, &buffer.text(), &old_path, &new_path);
+            if edits.is_empty() {
+                return None;
+            }
+            buffer.finalize_last_transaction();
+            buffer.start_transaction();
+            buffer.edit(edits, None, cx);
+            buffer.end_transaction(cx)?;
+            buffer.finalize_last_transaction().cloned()
+        })?;
+        if let Some(transaction) = transaction {
+            project_transaction.0.insert(buffer, transaction);
+        }
+    }
+    Ok(project_transaction)
+}
+
+/// Returns the edits updating the imports of the file at `path`, after the file or
+/// directory at `old_path` was renamed to `new_path`. All paths are relative to the
+/// same root, and `path` is the file's path after the rename.
+pub(crate) fn import_edits(
+    path: &Path,
+    text: &str,
+    old_path: &Path,
+    new_path: &Path,
+) -> Vec<(Range<usize>, String)> {
+    let Some(syntax) = ImportSyntax::for_path(path) else {
+        return Vec::new();
+    };
+    let excluded_ranges = comment_and_string_ranges(syntax, text);
+    let is_excluded = |offset: usize| {
+        let ix = excluded_ranges.partition_point(|range| range.end <= offset);
+        excluded_ranges
+            .get(ix)
+            .map_or(false, |range| range.start <= offset)
+    };
+    match syntax {
+        ImportSyntax::Script => script_import_edits(path, text, old_path, new_path, is_excluded),
+        ImportSyntax::Rust => rust_import_edits(path, text, old_path, new_path, is_excluded),
+    }
+}
+
+/// Returns the sorted ranges of the comments and string literals of the text.
+fn comment_and_string_ranges(syntax: ImportSyntax, text: &str) -> Vec<Range<usize>> {
+    let bytes = text.as_bytes();
+    let mut ranges = Vec::new();
+    let mut ix = 0;
+    while ix < bytes.len() {
+        let start = ix;
+        let end = match (bytes[ix], bytes.get(ix + 1)) {
+            (b'/', Some(b'/')) => text[ix..].find('\n').map_or(bytes.len(), |len| ix + len),
+            (b'/', Some(b'*')) => text[ix + 2..]
+                .find("*/")
+                .map_or(bytes.len(), |len| ix + 2 + len + 2),
+            (b'"', _) => string_end(bytes, ix, b'"'),
+            (b'\'' | b'`', _) if syntax == ImportSyntax::Script => string_end(bytes, ix, bytes[ix]),
+            // In Rust, quotes start character literals, e.g. `'"'`, or lifetimes.
+            (b'\'', _) => match (bytes.get(ix + 1), bytes.get(ix + 2)) {
+                (Some(b'\\'), _) => string_end(bytes, ix, b'\''),
+                (Some(_), Some(b'\'')) => ix + 3,
+                _ => {
+                    ix += 1;
+                    continue;
+                }
+            },
+            (b'r', Some(b'"' | b'#')) if syntax == ImportSyntax::Rust => {
+                let hashes = bytes[ix + 1..].iter().take_while(|b| **b == b'#').count();
+                if bytes.get(ix + 1 + hashes) == Some(&b'"') {
+                    let terminator = format!("\"{}", "#".repeat(hashes));
+                    let content_start = ix + 2 + hashes;
+                    text[content_start..]
+                        .find(&terminator)
+                        .map_or(bytes.len(), |len| content_start + len + terminator.len())
+                } else {
+                    ix += 1;
+                    continue;
+                }
+            }
+            _ => {
+                ix += 1;
+                continue;
+            }
+        };
+        ranges.push(start..end);
+        ix = end;
+    }
+    ranges
+}
+
+/// Returns the end of the string literal starting with the quote at `start`.
+fn string_end(bytes: &[u8], start: usize, quote: u8) -> usize {
+    let mut ix = start + 1;
+    while ix < bytes.len() {
+        match bytes[ix] {
+            b'\\' => ix += 2,
+            byte if byte == quote => return ix + 1,
+            _ => ix += 1,
+        }
+    }
+    bytes.len()
+}
+
+fn script_import_edits(
+    path: &Path,
+    text: &str,
+    old_path: &Path,
+    new_path: &Path,
+    is_excluded: impl Fn(usize) -> bool,
+) -> Vec<(Range<usize>, String)> {
+    // The specifiers of a file that was moved along with the renamed path are relative
+    // to its previous location.
+    let previous_path = renamed_path(path, new_path, old_path).unwrap_or_else(|| path.into());
+    let importer_moved = previous_path != path;
+    let previous_dir = previous_path.parent().unwrap_or(Path::new(""));
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    let mut edits = Vec::new();
+    for captures in SCRIPT_IMPORT_REGEX.captures_iter(text) {
+        if is_excluded(captures.get(0).unwrap().start()) {
+            continue;
+        }
+        let specifier = captures.get(1).unwrap();
+        let target = normalize(&previous_dir.join(specifier.as_str()));
+        let renamed_target = renamed_script_target(&target, old_path, new_path);
+        if renamed_target.is_none() && !importer_moved {
+            continue;
+        }
+        let new_specifier = relative_specifier(dir, &renamed_target.unwrap_or(target));
+        if new_specifier != specifier.as_str() {
+            edits.push((specifier.range(), new_specifier));
+        }
+    }
+    edits
+}
+
+/// Returns where an import target points after the rename, accounting for specifiers
+/// that omit the file's extension.
+fn renamed_script_target(target: &Path, old_path: &Path, new_path: &Path) -> Option<PathBuf> {
+    if let Some(renamed) = renamed_path(target, old_path, new_path) {
+        return Some(renamed);
+    }
+    let is_script = |path: &Path| ImportSyntax::for_path(path) == Some(ImportSyntax::Script);
+    if is_script(old_path) && target == old_path.with_extension("") {
+        if is_script(new_path) {
+            Some(new_path.with_extension(""))
+        } else {
+            Some(new_path.to_path_buf())
+        }
+    } else {
+        None
+    }
+}
+
+fn rust_import_edits(
+    path: &Path,
+    text: &str,
+    old_path: &Path,
+    new_path: &Path,
+    is_excluded: impl Fn(usize) -> bool,
+) -> Vec<(Range<usize>, String)> {
+    let (Some(old_name), Some(new_name)) = (rust_module_name(old_path), rust_module_name(new_path))
+    else {
+        return Vec::new();
+    };
+    let Some(module_dir) = old_path
+        .parent()
+        .filter(|dir| Some(*dir) == new_path.parent())
+    else {
+        return Vec::new();
+    };
+    let old_name = regex::escape(old_name);
+
+    let mut patterns = Vec::new();
+    if is_rust_parent_module(path, module_dir) {
+        patterns.push(format!(r"\bmod\s+({old_name})\s*;"));
+        patterns.push(format!(r"\bself::({old_name})\b"));
+    } else if path.parent() == Some(module_dir) {
+        patterns.push(format!(r"\bsuper::({old_name})\b"));
+    }
+    if let Some((src_dir, module_path)) = rust_module_path(module_dir) {
+        if path.starts_with(&src_dir) {
+            let module_path = module_path
+                .iter()
+                .map(|name| format!("{}::", regex::escape(name)))
+                .collect::<String>();
+            patterns.push(format!(r"\bcrate::{module_path}({old_name})\b"));
+        }
+    }
+
+    let mut edits = Vec::new();
+    for pattern in patterns {
+        let Some(regex) = Regex::new(&pattern).log_err() else {
+            continue;
+        };
+        for captures in regex.captures_iter(text) {
+            if !is_excluded(captures.get(0).unwrap().start()) {
+                edits.push((captures.get(1).unwrap().range(), new_name.to_string()));
+            }
+        }
+    }
+    edits.sort_by_key(|(range, _)| range.start);
+    edits
+}
+
+/// The name of the module defined by a Rust file or directory.
+fn rust_module_name(path: &Path) -> Option<&str> {
+    let name = match path.extension() {
+        Some(extension) if extension == "rs" => path.file_stem()?.to_str()?,
+        Some(_) => return None,
+        None => path.file_name()?.to_str()?,
+    };
+    let is_identifier = name
+        .chars()
+        .next()
+        .map_or(false, |c| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+    is_identifier.then_some(name)
+}
+
+/// Whether the file declares the modules defined in `module_dir`.
+fn is_rust_parent_module(path: &Path, module_dir: &Path) -> bool {
+    if path.parent() == Some(module_dir) {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .map_or(false, |name| RUST_MODULE_ROOTS.contains(&name))
+    } else {
+        module_dir.file_name().map_or(false, |dir_name| {
+            path.parent() == module_dir.parent()
+                && path
+                    .extension()
+                    .map_or(false, |extension| extension == "rs")
+                && path.file_stem() == Some(dir_name)
+        })
+    }
+}
+
+/// Returns the crate's `src` directory and the path of the module defined in
+/// `module_dir` within the crate.
+fn rust_module_path(module_dir: &Path) -> Option<(PathBuf, Vec<&str>)> {
+    let components = module_dir
+        .components()
+        .map(|component| component.as_os_str().to_str())
+        .collect::<Option<Vec<_>>>()?;
+    let src_ix = components.iter().rposition(|name| *name == "src")?;
+    let src_dir = components[..=src_ix].iter().collect::<PathBuf>();
+    Some((src_dir, components[src_ix + 1..].to_vec()))
+}
+
+/// Maps a path under `old_path` to the corresponding path under `new_path`.
+fn renamed_path(path: &Path, old_path: &Path, new_path: &Path) -> Option<PathBuf> {
+    let suffix = path.strip_prefix(old_path).ok()?;
+    if suffix.as_os_str().is_empty() {
+        Some(new_path.to_path_buf())
+    } else {
+        Some(new_path.join(suffix))
+    }
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+fn relative_specifier(from_dir: &Path, target: &Path) -> String {
+    let relative = pathdiff::diff_paths(target, from_dir).unwrap_or_else(|| target.into());
+    let relative = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    if relative.starts_with("../") {
+        relative
+    } else {
+        format!("./{relative}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(path: &str, text: &str, old_path: &str, new_path: &str) -> String {
+        let mut text = text.to_string();
+        let edits = import_edits(
+            Path::new(path),
+            &text,
+            Path::new(old_path),
+            Path::new(new_path),
+        );
+        for (range, new_text) in edits.into_iter().rev() {
+            text.replace_range(range, &new_text);
+        }
+        text
+    }
+
+    #[test]
+    fn test_script_import_edits() {
+        let text = r#"import { a } from "./utils/a";
+import b from '../lib/b.js';
+const c = require("./utils/a.ts");
+export * from "./utils/other";
+"#;
+        assert_eq!(
+            rewrite("app/main.ts", text, "app/utils/a.ts", "app/helpers/a2.ts"),
+            r#"import { a } from "./helpers/a2";
+import b from '../lib/b.js';
+const c = require("./helpers/a2.ts");
+export * from "./utils/other";
+"#
+        );
+
+        // Renaming a directory updates the imports of the files it contains.
+        assert_eq!(
+            rewrite("app/main.ts", text, "app/utils", "app/helpers"),
+            r#"import { a } from "./helpers/a";
+import b from '../lib/b.js';
+const c = require("./helpers/a.ts");
+export * from "./helpers/other";
+"#
+        );
+
+        // The imports of a moved file are relative to its new location.
+        assert_eq!(
+            rewrite(
+                "app/nested/main.ts",
+                text,
+                "app/main.ts",
+                "app/nested/main.ts"
+            ),
+            r#"import { a } from "../utils/a";
+import b from '../../lib/b.js';
+const c = require("../utils/a.ts");
+export * from "../utils/other";
+"#
+        );
+
+        // Comments and strings aren't imports.
+        let text = r#"// import { a } from "./utils/a";
+/* require("./utils/a") */
+const s = 'import a from "./utils/a"';
+const t = `from "./utils/a"`;
+import { a } from "./utils/a";
+"#;
+        assert_eq!(
+            rewrite("app/main.ts", text, "app/utils/a.ts", "app/helpers/a.ts"),
+            text.replace(
+                "\nimport { a } from \"./utils/a\"",
+                "\nimport { a } from \"./helpers/a\""
+            )
+        );
+    }
+
+    #[test]
+    fn test_rust_import_edits() {
+        let lib = "mod foo;\npub mod foobar;\nuse crate::foo::Foo;\nuse self::foo::Bar;\n";
+        assert_eq!(
+            rewrite("src/lib.rs", lib, "src/foo.rs", "src/bar.rs"),
+            "mod bar;\npub mod foobar;\nuse crate::bar::Foo;\nuse self::bar::Bar;\n"
+        );
+        assert_eq!(
+            rewrite(
+                "src/baz.rs",
+                "use super::foo::Foo;\n",
+                "src/foo.rs",
+                "src/bar.rs"
+            ),
+            "use super::bar::Foo;\n"
+        );
+        assert_eq!(
+            rewrite(
+                "src/a.rs",
+                "pub mod foo;\nuse crate::a::foo;\n",
+                "src/a/foo",
+                "src/a/bar"
+            ),
+            "pub mod bar;\nuse crate::a::bar;\n"
+        );
+
+        // Inline modules, comments and strings aren't imports.
+        let text = "mod foo {}\n// use crate::foo;\n/* mod foo; */\nlet c = '\"';\nlet s = \"crate::foo\";\nlet r = r#\"self::foo\"#;\nuse crate::foo::Foo;\n";
+        assert_eq!(
+            rewrite("src/lib.rs", text, "src/foo.rs", "src/bar.rs"),
+            text.replace("use crate::foo::Foo", "use crate::bar::Foo")
+        );
+
+        // Modules moved to another directory aren't rewritten.
+        assert_eq!(
+            rewrite("src/lib.rs", lib, "src/foo.rs", "src/a/foo.rs"),
+            lib
+        );
+    }
+}
@@ -1,10 +1,11 @@
 mod signature_help;
 
 use crate::{
+    file_patch::ProjectPatch,
     lsp_store::{LocalLspStore, LspStore},
     CodeAction, CoreCompletion, DocumentHighlight, Hover, HoverBlock, HoverBlockKind, InlayHint,
     InlayHintLabel, InlayHintLabelPart, InlayHintLabelPartTooltip, InlayHintTooltip, Location,
-    LocationLink, MarkupContent, PrepareRenameResponse, ProjectTransaction, ResolveState,
+    LocationLink, MarkupContent, PrepareRenameResponse, ResolveState,
 };
 use anyhow::{anyhow, Context as _, Result};
 use async_trait::async_trait;
@@ -168,6 +169,9 @@ pub(crate) struct PerformRename {
     pub position: PointUtf16,
     pub new_name: String,
     pub push_to_history: bool,
+    /// Whether the files with no open buffer are patched on disk instead of being opened to
+    /// edit them. Only honored by local projects.
+    pub patch_unopened_files: bool,
 }
 
 #[derive(Debug)]
@@ -424,7 +428,7 @@ impl LspCommand for PrepareRename {
 
 #[async_trait(?Send)]
 impl LspCommand for PerformRename {
-    type Response = ProjectTransaction;
+    type Response = ProjectPatch;
     type LspRequest = lsp::request::Rename;
     type ProtoRequest = proto::PerformRename;
 
@@ -453,21 +457,23 @@ impl LspCommand for PerformRename {
         buffer: Entity<Buffer>,
         server_id: LanguageServerId,
         mut cx: AsyncApp,
-    ) -> Result<ProjectTransaction> {
+    ) -> Result<ProjectPatch> {
         if let Some(edit) = message {
             let (lsp_adapter, lsp_server) =
                 language_server_for_buffer(&lsp_store, &buffer, server_id, &mut cx)?;
-            LocalLspStore::deserialize_workspace_edit(
+            LocalLspStore::deserialize_workspace_edit_with_file_patches(
                 lsp_store,
                 edit,
+                "rename",
                 self.push_to_history,
+                self.patch_unopened_files,
                 lsp_adapter,
                 lsp_server,
                 &mut cx,
             )
             .await
         } else {
-            Ok(ProjectTransaction::default())
+            Ok(ProjectPatch::default())
         }
     }
 
@@ -502,18 +508,19 @@ impl LspCommand for PerformRename {
             position: buffer.update(&mut cx, |buffer, _| position.to_point_utf16(buffer))?,
             new_name: message.new_name,
             push_to_history: false,
+            patch_unopened_files: false,
         })
     }
 
     fn response_to_proto(
-        response: ProjectTransaction,
+        response: ProjectPatch,
         lsp_store: &mut LspStore,
         peer_id: PeerId,
         _: &clock::Global,
         cx: &mut App,
     ) -> proto::PerformRenameResponse {
         let transaction = lsp_store.buffer_store().update(cx, |buffer_store, cx| {
-            buffer_store.serialize_project_transaction_for_peer(response, peer_id, cx)
+            buffer_store.serialize_project_transaction_for_peer(response.transaction, peer_id, cx)
         });
         proto::PerformRenameResponse {
             transaction: Some(transaction),
@@ -526,17 +533,21 @@ impl LspCommand for PerformRename {
         lsp_store: Entity<LspStore>,
         _: Entity<Buffer>,
         mut cx: AsyncApp,
-    ) -> Result<ProjectTransaction> {
+    ) -> Result<ProjectPatch> {
         let message = message
             .transaction
             .ok_or_else(|| anyhow!("missing transaction"))?;
-        lsp_store
+        let transaction = lsp_store
             .update(&mut cx, |lsp_store, cx| {
                 lsp_store.buffer_store().update(cx, |buffer_store, cx| {
                     buffer_store.deserialize_project_transaction(message, self.push_to_history, cx)
                 })
             })?
-            .await
+            .await?;
+        Ok(ProjectPatch {
+            transaction,
+            file_patches: Vec::new(),
+        })
     }
 
     fn buffer_id_from_proto(message: &proto::PerformRename) -> Result<BufferId> {
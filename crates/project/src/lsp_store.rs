@@ -2,6 +2,7 @@ use crate::{
     buffer_store::{BufferStore, BufferStoreEvent},
     deserialize_code_actions,
     environment::ProjectEnvironment,
+    file_patch::{FilePatch, ProjectPatch},
     lsp_command::{self, *},
     lsp_ext_command,
     prettier_store::{self, PrettierStore, PrettierStoreEvent},
@@ -19,7 +20,7 @@ use client::{proto, TypedEnvelope};
 use collections::{btree_map, BTreeMap, BTreeSet, HashMap, HashSet};
 use futures::{
     future::{join_all, Shared},
-    select,
+    select, select_biased,
     stream::FuturesUnordered,
     AsyncWriteExt, Future, FutureExt, StreamExt,
 };
@@ -39,7 +40,7 @@ use language::{
     CompletionDocumentation, Diagnostic, DiagnosticEntry, DiagnosticSet, Diff, File as _, Language,
     LanguageName, LanguageRegistry, LanguageServerBinaryStatus, LanguageToolchainStore, LocalFile,
     LspAdapter, LspAdapterDelegate, Patch, PointUtf16, TextBufferSnapshot, ToOffset, ToPointUtf16,
-    Transaction, Unclipped,
+    Transaction, TransactionMetadata, Unclipped,
 };
 use lsp::{
     notification::DidRenameFiles, CodeActionKind, CompletionContext, DiagnosticSeverity,
@@ -74,6 +75,7 @@ use std::{
     iter, mem,
     ops::{ControlFlow, Range},
     path::{self, Path, PathBuf},
+    pin::pin,
     rc::Rc,
     str,
     sync::Arc,
@@ -205,8 +207,11 @@ impl LocalLspStore {
             }),
             cx,
         );
-        let lsp = project_settings.lsp.get(&adapter.name);
+        let lsp = valid_lsp_settings(project_settings, &adapter.name);
         let override_options = lsp.and_then(|s| s.initialization_options.clone());
+        let working_dir = lsp
+            .and_then(|s| s.binary.as_ref()?.working_directory.as_ref())
+            .map(|directory| root_path.join(directory));
 
         let stderr_capture = Arc::new(Mutex::new(Some(String::new())));
 
@@ -242,12 +247,13 @@ impl LocalLspStore {
                     return Ok(server);
                 }
 
-                lsp::LanguageServer::new(
+                lsp::LanguageServer::new_in_working_dir(
                     stderr_capture,
                     server_id,
                     server_name,
                     binary,
                     &root_path,
+                    working_dir.as_deref(),
                     adapter.code_action_kinds(),
                     cx,
                 )
@@ -459,23 +465,26 @@ impl LocalLspStore {
         allow_binary_download: bool,
         cx: &mut Context<LspStore>,
     ) -> Task<Result<LanguageServerBinary>> {
-        let settings = ProjectSettings::get(
-            Some(SettingsLocation {
-                worktree_id: delegate.worktree_id(),
-                path: Path::new(""),
-            }),
-            cx,
+        let settings = valid_lsp_settings(
+            ProjectSettings::get(
+                Some(SettingsLocation {
+                    worktree_id: delegate.worktree_id(),
+                    path: Path::new(""),
+                }),
+                cx,
+            ),
+            &adapter.name,
         )
-        .lsp
-        .get(&adapter.name)
         .and_then(|s| s.binary.clone());
 
         if settings.as_ref().is_some_and(|b| b.path.is_some()) {
             let settings = settings.unwrap();
             return cx.spawn(|_, _| async move {
+                let mut env = delegate.shell_env().await;
+                env.extend(settings.env.unwrap_or_default());
                 Ok(LanguageServerBinary {
                     path: PathBuf::from(&settings.path.unwrap()),
-                    env: Some(delegate.shell_env().await),
+                    env: Some(env),
                     arguments: settings
                         .arguments
                         .unwrap_or_default()
@@ -507,12 +516,14 @@ impl LocalLspStore {
             delegate.update_status(adapter.name.clone(), LanguageServerBinaryStatus::None);
 
             let mut binary = binary_result?;
-            if let Some(arguments) = settings.and_then(|b| b.arguments) {
+            let settings = settings.unwrap_or_default();
+            if let Some(arguments) = settings.arguments {
                 binary.arguments = arguments.into_iter().map(Into::into).collect();
             }
 
             let mut shell_env = delegate.shell_env().await;
             shell_env.extend(binary.env.unwrap_or_default());
+            shell_env.extend(settings.env.unwrap_or_default());
             binary.env = Some(shell_env);
             Ok(binary)
         })
@@ -527,6 +538,20 @@ impl LocalLspStore {
     ) {
         let name = language_server.name();
         let server_id = language_server.server_id();
+        // The server can't be communicated with anymore once the tasks handling its messages
+        // panicked, so it's shown as having failed.
+        language_server.on_io_panic({
+            let delegate = delegate.clone();
+            let name = name.clone();
+            move |panic, _| {
+                delegate.update_status(
+                    name,
+                    LanguageServerBinaryStatus::Failed {
+                        error: format!("Stopped communicating with the server, as the {panic}"),
+                    },
+                );
+            }
+        });
         language_server
             .on_notification::<lsp::notification::PublishDiagnostics, _>({
                 let adapter = adapter.clone();
@@ -1177,8 +1202,43 @@ impl LocalLspStore {
 
             let initial_transaction_id = whitespace_transaction_id;
 
-            // Apply the `code_actions_on_format` before we run the formatter.
-            let code_actions = deserialize_code_actions(&settings.code_actions_on_format);
+            // Apply the `code_actions_on_save` first, giving up on the ones that take too long
+            // so that a slow language server doesn't hold the save.
+            let code_actions_on_save = if trigger == FormatTrigger::Save {
+                deserialize_code_actions(&settings.code_actions_on_save)
+            } else {
+                Vec::new()
+            };
+            if !code_actions_on_save.is_empty() {
+                let deadline = cx
+                    .background_executor()
+                    .timer(Duration::from_millis(
+                        settings.code_actions_on_save_timeout_ms,
+                    ))
+                    .shared();
+                let timed_out = Self::execute_code_actions_on_servers(
+                    &lsp_store,
+                    &adapters_and_servers,
+                    code_actions_on_save.clone(),
+                    &buffer.handle,
+                    push_to_history,
+                    &mut project_transaction,
+                    Some(deadline),
+                    &mut cx,
+                )
+                .await?;
+                if timed_out {
+                    log::warn!(
+                        "timed out after {}ms running code actions on save",
+                        settings.code_actions_on_save_timeout_ms
+                    );
+                }
+            }
+
+            // Apply the `code_actions_on_format` before we run the formatter, except for the
+            // ones that already ran on save.
+            let mut code_actions = deserialize_code_actions(&settings.code_actions_on_format);
+            code_actions.retain(|kind| !code_actions_on_save.contains(kind));
             #[allow(clippy::nonminimal_bool)]
             if !code_actions.is_empty()
                 && !(trigger == FormatTrigger::Save && settings.format_on_save == FormatOnSave::Off)
@@ -1190,6 +1250,7 @@ impl LocalLspStore {
                     &buffer.handle,
                     push_to_history,
                     &mut project_transaction,
+                    None,
                     &mut cx,
                 )
                 .await?;
@@ -1335,6 +1396,7 @@ impl LocalLspStore {
                             &buffer.handle,
                             push_to_history,
                             project_transaction,
+                            None,
                             cx,
                         )
                         .await?;
@@ -1399,6 +1461,15 @@ impl LocalLspStore {
         }
 
         buffer.handle.update(cx, |b, _cx| {
+            if let Some(transaction_id) = initial_transaction_id {
+                b.set_transaction_metadata(
+                    transaction_id,
+                    TransactionMetadata {
+                        command: None,
+                        tool: Some("formatter".into()),
+                    },
+                );
+            }
             if let Some(transaction) = b.finalize_last_transaction().cloned() {
                 if !push_to_history {
                     b.forget_transaction(transaction.id);
@@ -1918,6 +1989,13 @@ impl LocalLspStore {
         }
     }
 
+    /// Applies the code actions of the given kinds, returning whether it gave up on some of
+    /// them because the deadline passed.
+    ///
+    /// Only waiting for the language servers to return or resolve the actions is given up on:
+    /// the actions being applied when the deadline passes are applied in full, so that no edit
+    /// is left out of the project transaction.
+    #[allow(clippy::too_many_arguments)]
     async fn execute_code_actions_on_servers(
         this: &WeakEntity<LspStore>,
         adapters_and_servers: &[(Arc<CachedLspAdapter>, Arc<LanguageServer>)],
@@ -1925,26 +2003,30 @@ impl LocalLspStore {
         buffer: &Entity<Buffer>,
         push_to_history: bool,
         project_transaction: &mut ProjectTransaction,
+        deadline: Option<Shared<Task<()>>>,
         cx: &mut AsyncApp,
-    ) -> Result<(), anyhow::Error> {
+    ) -> Result<bool, anyhow::Error> {
         for (lsp_adapter, language_server) in adapters_and_servers.iter() {
             let code_actions = code_actions.clone();
 
-            let actions = this
-                .update(cx, move |this, cx| {
-                    let request = GetCodeActions {
-                        range: text::Anchor::MIN..text::Anchor::MAX,
-                        kinds: Some(code_actions),
-                    };
-                    let server = LanguageServerToQuery::Other(language_server.server_id());
-                    this.request_lsp(buffer.clone(), server, request, cx)
-                })?
-                .await?;
+            let actions = this.update(cx, move |this, cx| {
+                let request = GetCodeActions {
+                    range: text::Anchor::MIN..text::Anchor::MAX,
+                    kinds: Some(code_actions),
+                };
+                let server = LanguageServerToQuery::Other(language_server.server_id());
+                this.request_lsp(buffer.clone(), server, request, cx)
+            })?;
+            let Some(actions) = before_deadline(actions, &deadline).await else {
+                return Ok(true);
+            };
 
-            for mut action in actions {
-                Self::try_resolve_code_action(language_server, &mut action)
-                    .await
-                    .context("resolving a formatting code action")?;
+            for mut action in actions? {
+                let resolve = Self::try_resolve_code_action(language_server, &mut action);
+                let Some(resolved) = before_deadline(resolve, &deadline).await else {
+                    return Ok(true);
+                };
+                resolved.context("resolving a formatting code action")?;
 
                 if let Some(edit) = action.lsp_action.edit {
                     if edit.changes.is_none() && edit.document_changes.is_none() {
@@ -1954,6 +2036,7 @@ impl LocalLspStore {
                     let new = Self::deserialize_workspace_edit(
                         this.upgrade().ok_or_else(|| anyhow!("project dropped"))?,
                         edit,
+                        "formatter",
                         push_to_history,
                         lsp_adapter.clone(),
                         language_server.clone(),
@@ -1993,7 +2076,7 @@ impl LocalLspStore {
             }
         }
 
-        Ok(())
+        Ok(false)
     }
 
     pub async fn deserialize_text_edits(
@@ -2143,14 +2226,45 @@ impl LocalLspStore {
         })
     }
 
+    /// Applies a workspace edit. The resulting transactions are attributed to the
+    /// given tool in the undo history.
     pub(crate) async fn deserialize_workspace_edit(
         this: Entity<LspStore>,
         edit: lsp::WorkspaceEdit,
+        tool: &'static str,
         push_to_history: bool,
         lsp_adapter: Arc<CachedLspAdapter>,
         language_server: Arc<LanguageServer>,
         cx: &mut AsyncApp,
     ) -> Result<ProjectTransaction> {
+        let project_patch = Self::deserialize_workspace_edit_with_file_patches(
+            this,
+            edit,
+            tool,
+            push_to_history,
+            false,
+            lsp_adapter,
+            language_server,
+            cx,
+        )
+        .await?;
+        Ok(project_patch.transaction)
+    }
+
+    /// Deserializes the workspace edit like [`Self::deserialize_workspace_edit`], but with
+    /// `patch_unopened_files`, returns the edits to the files of the worktrees with no open
+    /// buffer as [`FilePatch`]es, instead of opening buffers to make them.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn deserialize_workspace_edit_with_file_patches(
+        this: Entity<LspStore>,
+        edit: lsp::WorkspaceEdit,
+        tool: &'static str,
+        push_to_history: bool,
+        patch_unopened_files: bool,
+        lsp_adapter: Arc<CachedLspAdapter>,
+        language_server: Arc<LanguageServer>,
+        cx: &mut AsyncApp,
+    ) -> Result<ProjectPatch> {
         let fs = this.read_with(cx, |this, _| this.as_local().unwrap().fs.clone())?;
 
         let mut operations = Vec::new();
@@ -2174,6 +2288,7 @@ impl LocalLspStore {
         }
 
         let mut project_transaction = ProjectTransaction::default();
+        let mut file_patches = Vec::new();
         for operation in operations {
             match operation {
                 lsp::DocumentChangeOperation::Op(lsp::ResourceOp::Create(op)) => {
@@ -2243,6 +2358,12 @@ impl LocalLspStore {
                 }
 
                 lsp::DocumentChangeOperation::Edit(op) => {
+                    if patch_unopened_files
+                        && Self::patch_unopened_file(&this, &fs, &op, &mut file_patches, cx).await?
+                    {
+                        continue;
+                    }
+
                     let buffer_to_edit = this
                         .update(cx, |this, cx| {
                             this.open_local_buffer_via_lsp(
@@ -2347,7 +2468,14 @@ impl LocalLspStore {
                         for (range, text) in edits {
                             buffer.edit([(range, text)], None, cx);
                         }
-                        let transaction = if buffer.end_transaction(cx).is_some() {
+                        let transaction = if let Some(transaction_id) = buffer.end_transaction(cx) {
+                            buffer.set_transaction_metadata(
+                                transaction_id,
+                                TransactionMetadata {
+                                    command: None,
+                                    tool: Some(tool.into()),
+                                },
+                            );
                             let transaction = buffer.finalize_last_transaction().unwrap().clone();
                             if !push_to_history {
                                 buffer.forget_transaction(transaction.id);
@@ -2366,7 +2494,78 @@ impl LocalLspStore {
             }
         }
 
-        Ok(project_transaction)
+        Ok(ProjectPatch {
+            transaction: project_transaction,
+            file_patches,
+        })
+    }
+
+    /// Adds the edits of the operation to the patch of its file, returning whether they were
+    /// added, which they aren't when the file has an open buffer, or isn't in a worktree. The
+    /// edits of a file edited by an earlier operation are added to its existing patch, since
+    /// a patch can't be applied once its file has changed.
+    async fn patch_unopened_file(
+        this: &Entity<LspStore>,
+        fs: &Arc<dyn Fs>,
+        op: &lsp::TextDocumentEdit,
+        file_patches: &mut Vec<FilePatch>,
+        cx: &mut AsyncApp,
+    ) -> Result<bool> {
+        let abs_path = op
+            .text_document
+            .uri
+            .to_file_path()
+            .map_err(|_| anyhow!("can't convert URI to path"))?;
+        let project_path = this.update(cx, |this, cx| {
+            let (worktree, path) = this.worktree_store.read(cx).find_worktree(&abs_path, cx)?;
+            let project_path = ProjectPath {
+                worktree_id: worktree.read(cx).id(),
+                path: path.into(),
+            };
+            let is_open = this
+                .buffer_store
+                .read(cx)
+                .get_by_path(&project_path, cx)
+                .is_some();
+            (!is_open).then_some(project_path)
+        })?;
+        let Some(project_path) = project_path else {
+            return Ok(false);
+        };
+
+        let mut edits = Vec::new();
+        for edit in &op.edits {
+            let edit = match edit {
+                Edit::Plain(edit) => edit.clone(),
+                Edit::Annotated(edit) => edit.text_edit.clone(),
+                // Snippets are only expanded in the focused buffer.
+                Edit::Snippet(edit) => {
+                    let Ok(snippet) = Snippet::parse(&edit.snippet.value) else {
+                        continue;
+                    };
+                    TextEdit {
+                        range: edit.range,
+                        new_text: snippet.text,
+                    }
+                }
+            };
+            if !edits.contains(&edit) {
+                edits.push(edit);
+            }
+        }
+        let edits = edits
+            .into_iter()
+            .map(|edit| (range_from_lsp(edit.range), edit.new_text))
+            .collect();
+        if let Some(file_patch) = file_patches
+            .iter_mut()
+            .find(|file_patch| file_patch.abs_path == abs_path)
+        {
+            file_patch.extend(fs.as_ref(), edits).await?;
+        } else {
+            file_patches.push(FilePatch::read(fs.as_ref(), project_path, abs_path, edits).await?);
+        }
+        Ok(true)
     }
 
     async fn on_lsp_workspace_edit(
@@ -2385,6 +2584,7 @@ impl LocalLspStore {
         let transaction = Self::deserialize_workspace_edit(
             this.clone(),
             params.edit,
+            "language server",
             true,
             adapter.clone(),
             language_server.clone(),
@@ -3657,6 +3857,7 @@ impl LspStore {
                         return LocalLspStore::deserialize_workspace_edit(
                             this.upgrade().ok_or_else(|| anyhow!("no app present"))?,
                             edit,
+                            "code action",
                             push_to_history,
                             lsp_adapter.clone(),
                             lang_server.clone(),
@@ -4887,6 +5088,44 @@ impl LspStore {
             })
     }
 
+    /// Returns the combined summary of the diagnostics of all language servers for a file of a
+    /// visible worktree, which is empty for ignored files unless `include_ignored` is set.
+    pub fn diagnostic_summary_for_path(
+        &self,
+        path: &ProjectPath,
+        include_ignored: bool,
+        cx: &App,
+    ) -> DiagnosticSummary {
+        let mut summary = DiagnosticSummary::default();
+        let Some(worktree) = self
+            .worktree_store
+            .read(cx)
+            .worktree_for_id(path.worktree_id, cx)
+        else {
+            return summary;
+        };
+        let worktree = worktree.read(cx);
+        if !worktree.is_visible()
+            || (!include_ignored
+                && worktree
+                    .entry_for_path(path.path.as_ref())
+                    .map_or(true, |entry| entry.is_ignored))
+        {
+            return summary;
+        }
+        if let Some(summaries) = self
+            .diagnostic_summaries
+            .get(&path.worktree_id)
+            .and_then(|summaries| summaries.get(&path.path))
+        {
+            for path_summary in summaries.values() {
+                summary.error_count += path_summary.error_count;
+                summary.warning_count += path_summary.warning_count;
+            }
+        }
+        summary
+    }
+
     pub fn on_buffer_edited(
         &mut self,
         buffer: Entity<Buffer>,
@@ -5861,6 +6100,7 @@ impl LspStore {
             &old_abs_path,
             &new_abs_path,
             is_dir,
+            false,
             cx.clone(),
         )
         .await;
@@ -6187,14 +6427,17 @@ impl LspStore {
         });
     }
 
+    /// Asks the language servers watching the renamed path for the edits that should
+    /// accompany the rename, e.g. updated imports, and applies them to the buffers.
     pub(super) fn will_rename_entry(
         this: WeakEntity<Self>,
         worktree_id: WorktreeId,
         old_path: &Path,
         new_path: &Path,
         is_dir: bool,
+        push_to_history: bool,
         cx: AsyncApp,
-    ) -> Task<()> {
+    ) -> Task<ProjectTransaction> {
         let old_uri = lsp::Url::from_file_path(old_path).ok().map(String::from);
         let new_uri = lsp::Url::from_file_path(new_path).ok().map(String::from);
         cx.spawn(move |mut cx| async move {
@@ -6232,14 +6475,14 @@ impl LspStore {
                                 LocalLspStore::deserialize_workspace_edit(
                                     this.upgrade()?,
                                     edit,
-                                    false,
+                                    "rename",
+                                    push_to_history,
                                     adapter.clone(),
                                     language_server.clone(),
                                     &mut cx,
                                 )
                                 .await
-                                .ok();
-                                Some(())
+                                .ok()
                             }
                         });
                         tasks.push(apply_edit);
@@ -6249,11 +6492,15 @@ impl LspStore {
             })
             .ok()
             .flatten();
+            let mut project_transaction = ProjectTransaction::default();
             for task in tasks {
                 // Await on tasks sequentially so that the order of application of edits is deterministic
                 // (at least with regards to the order of registration of language servers)
-                task.await;
+                if let Some(transaction) = task.await {
+                    project_transaction.0.extend(transaction.0);
+                }
             }
+            project_transaction
         })
     }
 
@@ -7314,6 +7561,15 @@ impl LspStore {
                         is_disk_based,
                         is_unnecessary,
                         data: diagnostic.data.clone(),
+                        related_information: diagnostic
+                            .related_information
+                            .iter()
+                            .flatten()
+                            .filter(|info| {
+                                info.location.uri != params.uri && !info.message.is_empty()
+                            })
+                            .cloned()
+                            .collect(),
                     },
                 });
                 if let Some(infos) = &diagnostic.related_information {
@@ -7332,6 +7588,7 @@ impl LspStore {
                                     is_disk_based,
                                     is_unnecessary: false,
                                     data: diagnostic.data.clone(),
+                                    related_information: Vec::new(),
                                 },
                             });
                         }
@@ -8362,6 +8619,20 @@ impl LspAdapter for SshLspAdapter {
     }
 }
 
+/// The settings of the language server, unless they're invalid, in which case they're
+/// ignored rather than starting the server with them.
+fn valid_lsp_settings<'a>(
+    project_settings: &'a ProjectSettings,
+    name: &LanguageServerName,
+) -> Option<&'a LspSettings> {
+    let settings = project_settings.lsp.get(name)?;
+    settings
+        .validate()
+        .with_context(|| format!("invalid settings for language server {name}"))
+        .log_err()?;
+    Some(settings)
+}
+
 pub fn language_server_settings<'a, 'b: 'a>(
     delegate: &'a dyn LspAdapterDelegate,
     language: &LanguageServerName,
@@ -8650,6 +8921,22 @@ fn include_text(server: &lsp::LanguageServer) -> Option<bool> {
     }
 }
 
+/// Waits for the future, unless the deadline passes first.
+async fn before_deadline<T>(
+    future: impl Future<Output = T>,
+    deadline: &Option<Shared<Task<()>>>,
+) -> Option<T> {
+    let Some(deadline) = deadline.clone() else {
+        return Some(future.await);
+    };
+    let mut future = pin!(future.fuse());
+    let mut deadline = deadline.fuse();
+    select_biased! {
+        output = future => Some(output),
+        _ = deadline => None,
+    }
+}
+
 /// Completion items are displayed in a `UniformList`.
 /// Usually, those items are single-line strings, but in LSP responses,
 /// completion items `label`, `detail` and `label_details.description` may contain newlines or long spaces.
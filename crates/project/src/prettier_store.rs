@@ -25,8 +25,8 @@ use smol::stream::StreamExt;
 use util::{ResultExt, TryFutureExt};
 
 use crate::{
-    lsp_store::WorktreeId, worktree_store::WorktreeStore, File, PathChange, ProjectEntryId,
-    Worktree,
+    lsp_store::WorktreeId, worktree_store::WorktreeStore, worktree_trust, File, PathChange,
+    ProjectEntryId, Worktree,
 };
 
 pub struct PrettierStore {
@@ -102,7 +102,10 @@ impl PrettierStore {
         .detach();
     }
 
-    fn prettier_instance_for_buffer(
+    /// Returns the prettier to format the buffer with: the one installed in the buffer's
+    /// project, or the default one when there is none, or when the buffer is in an untrusted
+    /// worktree, whose packages can't be run.
+    pub(crate) fn prettier_instance_for_buffer(
         &mut self,
         buffer: &Entity<Buffer>,
         cx: &mut Context<Self>,
@@ -119,10 +122,14 @@ impl PrettierStore {
             Some((worktree_id, buffer_path)) => {
                 let fs = Arc::clone(&self.fs);
                 let installed_prettiers = self.prettier_instances.keys().cloned().collect();
+                let trusted = worktree_trust::is_path_trusted(&buffer_path, cx);
                 cx.spawn(|lsp_store, mut cx| async move {
                     match cx
                         .background_executor()
                         .spawn(async move {
+                            if !trusted {
+                                return Ok(ControlFlow::Continue(None));
+                            }
                             Prettier::locate_prettier_installation(
                                 fs.as_ref(),
                                 &installed_prettiers,
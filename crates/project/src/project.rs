@@ -2,19 +2,29 @@ pub mod buffer_store;
 mod color_extractor;
 pub mod connection_manager;
 pub mod debounced_delay;
+pub mod diagnostic_badges;
+pub mod discarded_changes;
+pub mod file_audit;
+pub mod file_patch;
 pub mod git;
 pub mod image_store;
+mod import_rewrite;
 pub mod lsp_command;
 pub mod lsp_ext_command;
 pub mod lsp_store;
 pub mod prettier_store;
 pub mod project_settings;
 pub mod search;
+pub mod search_replace;
+pub mod task_detection;
 mod task_inventory;
 pub mod task_store;
 pub mod terminals;
+pub mod todo_store;
 pub mod toolchain_store;
+pub mod virtual_documents;
 pub mod worktree_store;
+pub mod worktree_trust;
 
 #[cfg(test)]
 mod project_tests;
@@ -37,6 +47,8 @@ use clock::ReplicaId;
 use collections::{BTreeSet, HashMap, HashSet};
 use debounced_delay::DebouncedDelay;
 pub use environment::ProjectEnvironment;
+use file_audit::FileAudit;
+use file_patch::ProjectPatch;
 use futures::{
     channel::mpsc::{self, UnboundedReceiver},
     future::try_join_all,
@@ -84,12 +96,13 @@ use snippet::Snippet;
 use snippet_provider::SnippetProvider;
 use std::{
     borrow::Cow,
+    mem,
     ops::Range,
     path::{Component, Path, PathBuf},
     pin::pin,
     str,
     sync::Arc,
-    time::Duration,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use task_store::TaskStore;
 use terminals::Terminals;
@@ -101,6 +114,7 @@ use util::{
 };
 use worktree::{CreatedEntry, Snapshot, Traversal};
 use worktree_store::{WorktreeStore, WorktreeStoreEvent};
+use worktree_trust::WorktreeTrust;
 
 pub use fs::*;
 pub use language::Location;
@@ -115,12 +129,18 @@ pub use worktree::{
 };
 
 pub use buffer_store::ProjectTransaction;
+pub use diagnostic_badges::{DiagnosticBadge, DiagnosticBadges};
+pub use discarded_changes::{DiscardedChange, DiscardedChangesJournal};
 pub use lsp_store::{
     DiagnosticSummary, LanguageServerLogType, LanguageServerProgress, LanguageServerPromptRequest,
     LanguageServerStatus, LanguageServerToQuery, LspStore, LspStoreEvent,
     SERVER_PROGRESS_THROTTLE_TIMEOUT,
 };
+pub use todo_store::{TodoItem, TodoStore};
 pub use toolchain_store::ToolchainStore;
+pub use virtual_documents::{
+    VirtualDocument, VirtualDocumentBuffer, VirtualDocumentProvider, VirtualDocumentProviders,
+};
 const MAX_PROJECT_SEARCH_HISTORY_SIZE: usize = 500;
 const MAX_SEARCH_RESULT_FILES: usize = 5_000;
 const MAX_SEARCH_RESULT_RANGES: usize = 10_000;
@@ -168,6 +188,11 @@ pub struct Project {
     buffer_store: Entity<BufferStore>,
     image_store: Entity<ImageStore>,
     lsp_store: Entity<LspStore>,
+    diagnostic_badges: Entity<DiagnosticBadges>,
+    /// The writes of discarded buffers to the local history that may still be in progress.
+    discarded_changes_writes: Vec<Task<()>>,
+    /// The buffers opened for virtual documents, by their URIs.
+    virtual_documents: HashMap<url::Url, virtual_documents::OpenVirtualDocument>,
     _subscriptions: Vec<gpui::Subscription>,
     buffers_needing_diff: HashSet<WeakEntity<Buffer>>,
     git_diff_debouncer: DebouncedDelay<Self>,
@@ -181,6 +206,7 @@ pub struct Project {
     environment: Entity<ProjectEnvironment>,
     settings_observer: Entity<SettingsObserver>,
     toolchain_store: Option<Entity<ToolchainStore>>,
+    todo_store: Option<Entity<TodoStore>>,
 }
 
 #[derive(Default)]
@@ -273,6 +299,7 @@ pub enum Event {
     RemoteIdChanged(Option<u64>),
     DisconnectedFromHost,
     DisconnectedFromSshRemote,
+    ReconnectedToSshRemote,
     Closed,
     DeletedEntry(WorktreeId, ProjectEntryId),
     CollaboratorUpdated {
@@ -593,6 +620,7 @@ impl Project {
     pub fn init(client: &Arc<Client>, cx: &mut App) {
         connection_manager::init(client.clone(), cx);
         Self::init_settings(cx);
+        virtual_documents::init(cx);
 
         let client: AnyProtoClient = client.clone().into();
         client.add_entity_message_handler(Self::handle_add_collaborator);
@@ -702,6 +730,7 @@ impl Project {
 
             let git_store =
                 cx.new(|cx| GitStore::new(&worktree_store, buffer_store.clone(), None, None, cx));
+            let todo_store = cx.new(|cx| TodoStore::new(worktree_store.clone(), fs.clone(), cx));
 
             cx.subscribe(&lsp_store, Self::on_lsp_store_event).detach();
 
@@ -711,6 +740,9 @@ impl Project {
                 worktree_store,
                 buffer_store,
                 image_store,
+                diagnostic_badges: cx.new(|cx| DiagnosticBadges::new(lsp_store.clone(), cx)),
+                discarded_changes_writes: Vec::new(),
+                virtual_documents: HashMap::default(),
                 lsp_store,
                 join_project_response_message_id: 0,
                 client_state: ProjectClientState::Local,
@@ -740,6 +772,7 @@ impl Project {
                 search_excluded_history: Self::new_search_history(),
 
                 toolchain_store: Some(toolchain_store),
+                todo_store: Some(todo_store),
             }
         })
     }
@@ -840,6 +873,9 @@ impl Project {
                 worktree_store,
                 buffer_store,
                 image_store,
+                diagnostic_badges: cx.new(|cx| DiagnosticBadges::new(lsp_store.clone(), cx)),
+                discarded_changes_writes: Vec::new(),
+                virtual_documents: HashMap::default(),
                 lsp_store,
                 join_project_response_message_id: 0,
                 client_state: ProjectClientState::Local,
@@ -884,6 +920,7 @@ impl Project {
                 search_excluded_history: Self::new_search_history(),
 
                 toolchain_store: Some(toolchain_store),
+                todo_store: None,
             };
 
             // ssh -> local machine handlers
@@ -1070,6 +1107,9 @@ impl Project {
                 image_store,
                 worktree_store: worktree_store.clone(),
                 lsp_store: lsp_store.clone(),
+                diagnostic_badges: cx.new(|cx| DiagnosticBadges::new(lsp_store.clone(), cx)),
+                discarded_changes_writes: Vec::new(),
+                virtual_documents: HashMap::default(),
                 active_entry: None,
                 collaborators: Default::default(),
                 join_project_response_message_id: response.message_id,
@@ -1102,6 +1142,7 @@ impl Project {
                 environment: ProjectEnvironment::new(&worktree_store, None, cx),
                 remotely_created_models: Arc::new(Mutex::new(RemotelyCreatedModels::default())),
                 toolchain_store: None,
+                todo_store: None,
             };
             this.set_role(role, cx);
             for worktree in worktrees {
@@ -1271,6 +1312,11 @@ impl Project {
         self.lsp_store.clone()
     }
 
+    /// Returns the error and warning counts of the project's files and directories.
+    pub fn diagnostic_badges(&self) -> &Entity<DiagnosticBadges> {
+        &self.diagnostic_badges
+    }
+
     pub fn worktree_store(&self) -> Entity<WorktreeStore> {
         self.worktree_store.clone()
     }
@@ -1392,6 +1438,20 @@ impl Project {
         &self.task_store
     }
 
+    /// The TODO comments of the project's files, which are only collected in local projects.
+    pub fn todo_store(&self) -> Option<&Entity<TodoStore>> {
+        self.todo_store.as_ref()
+    }
+
+    /// Starts auditing the line endings, indentation and byte order marks of the project's files.
+    pub fn audit_file_formats(&self, cx: &mut App) -> Entity<FileAudit> {
+        let worktree_store = self.worktree_store.clone();
+        let buffer_store = self.buffer_store.clone();
+        let languages = self.languages.clone();
+        let fs = self.fs.clone();
+        cx.new(|cx| FileAudit::new(worktree_store, buffer_store, languages, fs, cx))
+    }
+
     pub fn snippets(&self) -> &Entity<SnippetProvider> {
         &self.snippets
     }
@@ -1451,6 +1511,29 @@ impl Project {
         self.worktree_store.read(cx).worktree_for_id(id, cx)
     }
 
+    /// Whether the project-local configuration of a worktree may run programs. Remote
+    /// worktrees are trusted by their path on the remote host.
+    pub fn is_worktree_trusted(&self, id: WorktreeId, cx: &App) -> bool {
+        self.worktree_for_id(id, cx).map_or(false, |worktree| {
+            worktree_trust::is_path_trusted(&worktree.read(cx).abs_path(), cx)
+        })
+    }
+
+    /// Trusts, or revokes the trust of, the project's visible worktrees.
+    pub fn set_worktrees_trusted(&self, trusted: bool, cx: &mut App) {
+        let abs_paths = self
+            .visible_worktrees(cx)
+            .map(|worktree| worktree.read(cx).abs_path().to_path_buf())
+            .collect::<Vec<_>>();
+        for abs_path in abs_paths {
+            if trusted {
+                WorktreeTrust::trust(abs_path, cx);
+            } else {
+                WorktreeTrust::revoke(&abs_path, cx);
+            }
+        }
+    }
+
     pub fn worktree_for_entry(
         &self,
         entry_id: ProjectEntryId,
@@ -1564,8 +1647,48 @@ impl Project {
         new_path: impl Into<Arc<Path>>,
         cx: &mut Context<Self>,
     ) -> Task<Result<CreatedEntry>> {
+        let rename = self.rename_entry_internal(entry_id, new_path.into(), false, cx);
+        cx.spawn(|_, _| async move { Ok(rename.await?.0) })
+    }
+
+    /// Renames the project entry like [`Self::rename_entry`], and updates the imports
+    /// that reference it in the project's files.
+    ///
+    /// The edits are requested from the language servers through `workspace/willRenameFiles`.
+    /// When none of them provides any, the relative imports of the languages importing by path
+    /// are rewritten textually. The edits are left unsaved, and are returned so that they can
+    /// be reviewed, or undone.
+    pub fn rename_entry_updating_imports(
+        &mut self,
+        entry_id: ProjectEntryId,
+        new_path: impl Into<Arc<Path>>,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<(CreatedEntry, ProjectTransaction)>> {
+        self.rename_entry_internal(entry_id, new_path.into(), true, cx)
+    }
+
+    /// Whether renaming the project entry may update imports referencing it in the project's
+    /// files, because it's a file of a language importing by path, or a directory containing
+    /// some. The other entries are renamed with [`Self::rename_entry`].
+    pub fn rename_may_update_imports(&self, entry_id: ProjectEntryId, cx: &App) -> bool {
+        !self.entry_is_worktree_root(entry_id, cx)
+            && self
+                .worktree_store
+                .read(cx)
+                .worktree_and_entry_for_id(entry_id, cx)
+                .map_or(false, |(worktree, entry)| {
+                    import_rewrite::may_be_imported(worktree.read(cx), &entry.path, entry.is_dir())
+                })
+    }
+
+    fn rename_entry_internal(
+        &mut self,
+        entry_id: ProjectEntryId,
+        new_path: Arc<Path>,
+        update_imports: bool,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<(CreatedEntry, ProjectTransaction)>> {
         let worktree_store = self.worktree_store.read(cx);
-        let new_path = new_path.into();
         let Some((worktree, old_path, is_dir)) = worktree_store
             .worktree_and_entry_for_id(entry_id, cx)
             .map(|(worktree, entry)| (worktree, entry.path.clone(), entry.is_dir()))
@@ -1577,7 +1700,7 @@ impl Project {
         let is_root_entry = self.entry_is_worktree_root(entry_id, cx);
 
         let lsp_store = self.lsp_store().downgrade();
-        cx.spawn(|_, mut cx| async move {
+        cx.spawn(|project, mut cx| async move {
             let (old_abs_path, new_abs_path) = {
                 let root_path = worktree.update(&mut cx, |this, _| this.abs_path())?;
                 let new_abs_path = if is_root_entry {
@@ -1587,12 +1710,13 @@ impl Project {
                 };
                 (root_path.join(&old_path), new_abs_path)
             };
-            LspStore::will_rename_entry(
+            let mut transaction = LspStore::will_rename_entry(
                 lsp_store.clone(),
                 worktree_id,
                 &old_abs_path,
                 &new_abs_path,
                 is_dir,
+                update_imports,
                 cx.clone(),
             )
             .await;
@@ -1608,7 +1732,18 @@ impl Project {
                     this.did_rename_entry(worktree_id, &old_abs_path, &new_abs_path, is_dir);
                 })
                 .ok();
-            Ok(entry)
+
+            if update_imports && transaction.0.is_empty() && !is_root_entry {
+                transaction = import_rewrite::rewrite_imports(
+                    project,
+                    worktree,
+                    old_path,
+                    new_path,
+                    cx.clone(),
+                )
+                .await?;
+            }
+            Ok((entry, transaction))
         })
     }
 
@@ -1619,7 +1754,6 @@ impl Project {
         cx: &mut Context<Self>,
     ) -> Option<Task<Result<()>>> {
         let worktree = self.worktree_for_entry(entry_id, cx)?;
-        cx.emit(Event::DeletedEntry(worktree.read(cx).id(), entry_id));
         worktree.update(cx, |worktree, cx| {
             worktree.delete_entry(entry_id, trash, cx)
         })
@@ -2243,6 +2377,7 @@ impl Project {
         event: &BufferStoreEvent,
         cx: &mut Context<Self>,
     ) {
+        self.update_polled_files(cx);
         match event {
             BufferStoreEvent::BufferAdded(buffer) => {
                 self.register_buffer(buffer, cx).log_err();
@@ -2263,6 +2398,27 @@ impl Project {
         }
     }
 
+    /// Tells the local worktrees which of their files are open in buffers, so that they're
+    /// checked for changes when the worktrees are polled.
+    fn update_polled_files(&self, cx: &App) {
+        let mut open_files = HashMap::<WorktreeId, Vec<Arc<Path>>>::default();
+        for buffer in self.buffer_store.read(cx).buffers() {
+            if let Some(file) = buffer.read(cx).file() {
+                open_files
+                    .entry(file.worktree_id(cx))
+                    .or_default()
+                    .push(file.path().clone());
+            }
+        }
+        for worktree in self.worktrees(cx) {
+            let worktree = worktree.read(cx);
+            if let Some(local_worktree) = worktree.as_local() {
+                local_worktree
+                    .set_open_files(open_files.remove(&worktree.id()).unwrap_or_default());
+            }
+        }
+    }
+
     fn on_image_store_event(
         &mut self,
         _: Entity<ImageStore>,
@@ -2376,6 +2532,14 @@ impl Project {
                 });
                 cx.emit(Event::DisconnectedFromSshRemote);
             }
+            remote::SshRemoteEvent::Reconnected => {
+                // The unacknowledged messages were sent again, but the requests among them may
+                // have failed meanwhile, so the buffers are synchronized as after rejoining a
+                // shared project.
+                self.enqueue_buffer_ordered_message(BufferOrderedMessage::Resync)
+                    .unwrap();
+                cx.emit(Event::ReconnectedToSshRemote);
+            }
         }
     }
 
@@ -2400,6 +2564,20 @@ impl Project {
                 }),
                 Err(_) => {}
             },
+            SettingsObserverEvent::LocalSettingsRestricted(worktree_id) => {
+                let Some(worktree) = self.worktree_for_id(*worktree_id, cx) else {
+                    return;
+                };
+                let message = format!(
+                    "{:?} is not trusted, so its tasks and the local settings that run programs \
+                    were not loaded. Run `workspace: trust project` to load them.",
+                    worktree.read(cx).abs_path()
+                );
+                cx.emit(Event::Toast {
+                    notification_id: "restricted-local-settings".into(),
+                    message,
+                });
+            }
         }
     }
 
@@ -2503,12 +2681,57 @@ impl Project {
                 .ok();
             }
 
+            BufferEvent::Discarded => self.park_discarded_buffer(&buffer, cx),
+
             _ => {}
         }
 
         None
     }
 
+    /// Returns the journal in which the contents of dirty buffers that were closed
+    /// without saving are kept.
+    pub fn discarded_changes_journal(&self) -> DiscardedChangesJournal {
+        DiscardedChangesJournal::new(self.fs.clone(), paths::local_history_dir().clone())
+    }
+
+    /// Waits for the discarded buffers to be written to the local history.
+    pub fn flush_discarded_changes(&mut self, cx: &App) -> Task<()> {
+        let writes = mem::take(&mut self.discarded_changes_writes);
+        cx.background_executor().spawn(async move {
+            futures::future::join_all(writes).await;
+        })
+    }
+
+    /// Waits for the files of the buffers being saved to be written.
+    pub fn wait_for_saves(&self, cx: &App) -> Task<()> {
+        self.buffer_store.read(cx).wait_for_saves(cx)
+    }
+
+    fn park_discarded_buffer(&mut self, buffer: &Entity<Buffer>, cx: &mut Context<Self>) {
+        let buffer = buffer.read(cx);
+        if !buffer.is_dirty() || self.is_via_collab() {
+            return;
+        }
+
+        let change = DiscardedChange {
+            path: buffer.file().map(|file| match file.as_local() {
+                Some(file) => file.abs_path(cx),
+                None => file.full_path(cx),
+            }),
+            text: buffer.text(),
+            discarded_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_millis() as i64),
+        };
+        let discriminant = buffer.remote_id().to_proto();
+        let journal = self.discarded_changes_journal();
+        let write = cx.background_executor().spawn(async move {
+            journal.record(&change, discriminant).await.log_err();
+        });
+        self.discarded_changes_writes.push(write);
+    }
+
     fn on_image_event(
         &mut self,
         image: Entity<ImageItem>,
@@ -2614,6 +2837,35 @@ impl Project {
         })
     }
 
+    /// Checks the language server settings of the worktrees of the buffers, as the language
+    /// servers would be started with them. The settings of remote projects are checked by
+    /// their host.
+    pub fn validate_language_server_settings(
+        &self,
+        buffers: impl IntoIterator<Item = Entity<Buffer>>,
+        cx: &App,
+    ) -> Result<()> {
+        if !self.is_local() {
+            return Ok(());
+        }
+
+        let worktree_ids = buffers
+            .into_iter()
+            .filter_map(|buffer| Some(buffer.read(cx).file()?.worktree_id(cx)))
+            .collect::<HashSet<_>>();
+        for worktree_id in worktree_ids {
+            ProjectSettings::get(
+                Some(SettingsLocation {
+                    worktree_id,
+                    path: Path::new(""),
+                }),
+                cx,
+            )
+            .validate_lsp_settings()?;
+        }
+        Ok(())
+    }
+
     pub fn cancel_language_server_work_for_buffers(
         &mut self,
         buffers: impl IntoIterator<Item = Entity<Buffer>>,
@@ -3040,6 +3292,21 @@ impl Project {
         new_name: String,
         cx: &mut Context<Self>,
     ) -> Task<Result<ProjectTransaction>> {
+        let rename = self.perform_rename_with_file_patches(buffer, position, new_name, false, cx);
+        cx.spawn(|_, _| async move { Ok(rename.await?.transaction) })
+    }
+
+    /// Renames the symbol like [`Self::perform_rename`], but with `patch_unopened_files`,
+    /// returns the edits to the files with no open buffer as patches to apply with
+    /// [`Self::apply_file_patches`], instead of opening buffers to make them.
+    pub fn perform_rename_with_file_patches<T: ToPointUtf16>(
+        &mut self,
+        buffer: Entity<Buffer>,
+        position: T,
+        new_name: String,
+        patch_unopened_files: bool,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<ProjectPatch>> {
         let push_to_history = true;
         let position = position.to_point_utf16(buffer.read(cx));
         self.request_lsp(
@@ -3049,6 +3316,7 @@ impl Project {
                 position,
                 new_name,
                 push_to_history,
+                patch_unopened_files,
             },
             cx,
         )
@@ -3954,10 +4222,11 @@ impl Project {
         envelope: TypedEnvelope<proto::SynchronizeBuffers>,
         mut cx: AsyncApp,
     ) -> Result<proto::SynchronizeBuffersResponse> {
+        let guest_id = envelope.original_sender_id()?;
         let response = this.update(&mut cx, |this, cx| {
-            let client = this.client.clone();
+            let client = this.client.clone().into();
             this.buffer_store.update(cx, |this, cx| {
-                this.handle_synchronize_buffers(envelope, cx, client)
+                this.handle_synchronize_buffers(envelope, guest_id, cx, client)
             })
         })??;
 
@@ -4078,7 +4347,7 @@ impl Project {
     }
 
     fn synchronize_remote_buffers(&mut self, cx: &mut Context<Self>) -> Task<Result<()>> {
-        let project_id = match self.client_state {
+        let (client, project_id): (AnyProtoClient, _) = match self.client_state {
             ProjectClientState::Remote {
                 sharing_has_stopped,
                 remote_id,
@@ -4089,17 +4358,20 @@ impl Project {
                         "can't synchronize remote buffers on a readonly project"
                     )));
                 } else {
-                    remote_id
+                    (self.client.clone().into(), remote_id)
                 }
             }
             ProjectClientState::Shared { .. } | ProjectClientState::Local => {
-                return Task::ready(Err(anyhow!(
-                    "can't synchronize remote buffers on a local project"
-                )))
+                if let Some(ssh_client) = &self.ssh_client {
+                    (ssh_client.read(cx).proto_client(), SSH_PROJECT_ID)
+                } else {
+                    return Task::ready(Err(anyhow!(
+                        "can't synchronize remote buffers on a local project"
+                    )));
+                }
             }
         };
 
-        let client = self.client.clone();
         cx.spawn(move |this, mut cx| async move {
             let (buffers, incomplete_buffer_ids) = this.update(&mut cx, |this, cx| {
                 this.buffer_store.read(cx).buffer_version_info(cx)
@@ -4147,6 +4419,18 @@ impl Project {
                     .collect::<Vec<_>>()
             })?;
 
+            // Diverged buffers aren't shared with us anymore, so the host sends their state
+            // again when they're opened, which replaces their text.
+            this.update(&mut cx, |this, cx| {
+                this.buffer_store.update(cx, |buffer_store, cx| {
+                    for id in response.diverged_buffer_ids {
+                        if let Some(id) = BufferId::new(id).log_err() {
+                            buffer_store.resync_diverged_buffer(id, cx);
+                        }
+                    }
+                })
+            })?;
+
             // Any incomplete buffers have open requests waiting. Request that the host sends
             // creates these buffers for us again to unblock any waiting futures.
             for id in incomplete_buffer_ids {
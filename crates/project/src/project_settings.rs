@@ -1,4 +1,5 @@
 use anyhow::Context as _;
+use buffer_diff::DiffAlgorithm;
 use collections::HashMap;
 use fs::Fs;
 use gpui::{App, AsyncApp, BorrowAppContext, Context, Entity, EventEmitter};
@@ -17,7 +18,7 @@ use settings::{
     parse_json_with_comments, InvalidSettingsError, LocalSettingsKind, Settings, SettingsLocation,
     SettingsSources, SettingsStore,
 };
-use std::{path::Path, sync::Arc, time::Duration};
+use std::{collections::BTreeMap, path::Path, sync::Arc, time::Duration};
 use task::{TaskTemplates, VsCodeTaskFile};
 use util::ResultExt;
 use worktree::{PathChange, UpdatedEntriesSet, Worktree, WorktreeId};
@@ -25,6 +26,7 @@ use worktree::{PathChange, UpdatedEntriesSet, Worktree, WorktreeId};
 use crate::{
     task_store::TaskStore,
     worktree_store::{WorktreeStore, WorktreeStoreEvent},
+    worktree_trust::{self, WorktreeTrust},
 };
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
@@ -33,6 +35,8 @@ pub struct ProjectSettings {
     ///
     /// The following settings can be overridden for specific language servers:
     /// - initialization_options
+    /// - binary, with the path, arguments, environment variables and working
+    ///   directory to start the language server with
     ///
     /// To override settings for a language, add an entry for that language server's
     /// name to the lsp value.
@@ -55,6 +59,14 @@ pub struct ProjectSettings {
     /// Configuration for session-related features
     #[serde(default)]
     pub session: SessionSettings,
+
+    /// Configuration for collecting TODO comments across the project
+    #[serde(default = "TodoSettings::without_keywords")]
+    pub todos: TodoSettings,
+
+    /// Configuration for restricting project-local configuration of untrusted folders
+    #[serde(default)]
+    pub workspace_trust: WorkspaceTrustSettings,
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
@@ -93,6 +105,11 @@ pub struct GitSettings {
     ///
     /// Default: on
     pub inline_blame: Option<InlineBlameSettings>,
+    /// The algorithm used to compute the hunks shown in the git gutter
+    /// and in the project diff.
+    ///
+    /// Default: myers
+    pub diff_algorithm: Option<DiffAlgorithm>,
 }
 
 impl GitSettings {
@@ -172,6 +189,11 @@ const fn false_value() -> bool {
 pub struct BinarySettings {
     pub path: Option<String>,
     pub arguments: Option<Vec<String>>,
+    /// Environment variables to start the language server with, over the ones of the shell.
+    pub env: Option<HashMap<String, String>>,
+    /// The directory to start the language server in, relative to the root of the worktree.
+    /// Defaults to the root of the worktree.
+    pub working_directory: Option<String>,
     pub ignore_system_version: Option<bool>,
 }
 
@@ -183,6 +205,39 @@ pub struct LspSettings {
     pub settings: Option<serde_json::Value>,
 }
 
+impl LspSettings {
+    /// Checks that the language server can be started with these settings.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let Some(binary) = &self.binary {
+            if binary
+                .path
+                .as_ref()
+                .is_some_and(|path| path.trim().is_empty())
+            {
+                anyhow::bail!("the binary path is empty");
+            }
+            if binary
+                .working_directory
+                .as_ref()
+                .is_some_and(|directory| directory.trim().is_empty())
+            {
+                anyhow::bail!("the working directory is empty");
+            }
+            for name in binary.env.iter().flat_map(|env| env.keys()) {
+                if name.is_empty() || name.contains(['=', '\0']) {
+                    anyhow::bail!("invalid environment variable name {name:?}");
+                }
+            }
+        }
+        if let Some(options) = &self.initialization_options {
+            if !options.is_object() && !options.is_null() {
+                anyhow::bail!("the initialization options are not an object");
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct SessionSettings {
     /// Whether or not to restore unsaved buffers on restart.
@@ -192,13 +247,93 @@ pub struct SessionSettings {
     ///
     /// Default: true
     pub restore_unsaved_buffers: bool,
+    /// Whether or not to restore the undo history of files on restart, when their
+    /// contents didn't change on disk since.
+    ///
+    /// Default: true
+    #[serde(default = "true_value")]
+    pub restore_undo_history: bool,
 }
 
 impl Default for SessionSettings {
     fn default() -> Self {
         Self {
             restore_unsaved_buffers: true,
+            restore_undo_history: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct TodoSettings {
+    /// Whether or not to scan the project for TODO comments. Scanning reads
+    /// every file of the project, so it's off unless enabled.
+    ///
+    /// Default: false
+    #[serde(default)]
+    pub enabled: bool,
+    /// The keywords to look for in comments, along with the severity of the
+    /// comments that contain them. Keywords are matched case-sensitively, and
+    /// replace the default keywords instead of being added to them.
+    ///
+    /// Default: {"TODO": "info", "FIXME": "warning", "XXX": "warning"}
+    #[serde(default)]
+    pub keywords: BTreeMap<String, TodoSeverity>,
+}
+
+impl Default for TodoSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            keywords: BTreeMap::from_iter([
+                ("TODO".to_string(), TodoSeverity::Info),
+                ("FIXME".to_string(), TodoSeverity::Warning),
+                ("XXX".to_string(), TodoSeverity::Warning),
+            ]),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct WorkspaceTrustSettings {
+    /// Whether folders must be trusted before their tasks, and the local settings
+    /// that specify language servers, formatters or other binaries, are loaded.
+    ///
+    /// Default: false
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+impl TodoSettings {
+    /// The settings of a settings file that doesn't configure TODO comments, which leaves
+    /// the keywords of less specific files in place.
+    fn without_keywords() -> Self {
+        Self {
+            keywords: BTreeMap::default(),
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(
+    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl ProjectSettings {
+    /// Checks the settings of every configured language server.
+    pub fn validate_lsp_settings(&self) -> anyhow::Result<()> {
+        for (name, settings) in &self.lsp {
+            settings
+                .validate()
+                .with_context(|| format!("invalid settings for language server {name}"))?;
         }
+        Ok(())
     }
 }
 
@@ -208,7 +343,18 @@ impl Settings for ProjectSettings {
     type FileContent = Self;
 
     fn load(sources: SettingsSources<Self::FileContent>, _: &mut App) -> anyhow::Result<Self> {
-        sources.json_merge()
+        let mut settings: Self = sources.json_merge()?;
+        // Keyword lists replace the less specific ones instead of being merged into them, so
+        // that users can drop default keywords.
+        if let Some(keywords) = sources
+            .defaults_and_customizations()
+            .map(|source| &source.todos.keywords)
+            .filter(|keywords| !keywords.is_empty())
+            .last()
+        {
+            settings.todos.keywords = keywords.clone();
+        }
+        Ok(settings)
     }
 }
 
@@ -220,6 +366,8 @@ pub enum SettingsObserverMode {
 #[derive(Clone, Debug, PartialEq)]
 pub enum SettingsObserverEvent {
     LocalSettingsUpdated(Result<(), InvalidSettingsError>),
+    /// Some of the local configuration of an untrusted worktree wasn't loaded.
+    LocalSettingsRestricted(WorktreeId),
 }
 
 impl EventEmitter<SettingsObserverEvent> for SettingsObserver {}
@@ -230,6 +378,10 @@ pub struct SettingsObserver {
     worktree_store: Entity<WorktreeStore>,
     project_id: u64,
     task_store: Entity<TaskStore>,
+    /// The unrestricted contents of the local configuration files, which are
+    /// applied again when the trust of their worktrees changes.
+    local_contents: HashMap<(WorktreeId, Arc<Path>, LocalSettingsKind), String>,
+    workspace_trust_enabled: bool,
 }
 
 /// SettingsObserver observers changes to .zed/{settings, task}.json files in local worktrees
@@ -250,6 +402,16 @@ impl SettingsObserver {
     ) -> Self {
         cx.subscribe(&worktree_store, Self::on_worktree_store_event)
             .detach();
+        cx.observe_global::<WorktreeTrust>(Self::reapply_local_contents)
+            .detach();
+        cx.observe_global::<SettingsStore>(|this, cx| {
+            let workspace_trust_enabled = ProjectSettings::get_global(cx).workspace_trust.enabled;
+            if workspace_trust_enabled != this.workspace_trust_enabled {
+                this.workspace_trust_enabled = workspace_trust_enabled;
+                this.reapply_local_contents(cx);
+            }
+        })
+        .detach();
 
         Self {
             worktree_store,
@@ -257,6 +419,8 @@ impl SettingsObserver {
             mode: SettingsObserverMode::Local(fs),
             downstream_client: None,
             project_id: 0,
+            local_contents: HashMap::default(),
+            workspace_trust_enabled: ProjectSettings::get_global(cx).workspace_trust.enabled,
         }
     }
 
@@ -271,6 +435,8 @@ impl SettingsObserver {
             mode: SettingsObserverMode::Remote,
             downstream_client: None,
             project_id: 0,
+            local_contents: HashMap::default(),
+            workspace_trust_enabled: false,
         }
     }
 
@@ -358,13 +524,19 @@ impl SettingsObserver {
         event: &WorktreeStoreEvent,
         cx: &mut Context<Self>,
     ) {
-        if let WorktreeStoreEvent::WorktreeAdded(worktree) = event {
-            cx.subscribe(worktree, |this, worktree, event, cx| {
-                if let worktree::Event::UpdatedEntries(changes) = event {
-                    this.update_local_worktree_settings(&worktree, changes, cx)
-                }
-            })
-            .detach()
+        match event {
+            WorktreeStoreEvent::WorktreeAdded(worktree) => cx
+                .subscribe(worktree, |this, worktree, event, cx| {
+                    if let worktree::Event::UpdatedEntries(changes) = event {
+                        this.update_local_worktree_settings(&worktree, changes, cx)
+                    }
+                })
+                .detach(),
+            WorktreeStoreEvent::WorktreeRemoved(_, worktree_id) => {
+                self.local_contents
+                    .retain(|(id, _, _), _| id != worktree_id);
+            }
+            _ => {}
         }
     }
 
@@ -501,6 +673,8 @@ impl SettingsObserver {
         let task_store = self.task_store.clone();
 
         for (directory, kind, file_content) in settings_contents {
+            let file_content =
+                self.restrict_untrusted_content(&worktree, &directory, kind, file_content, cx);
             match kind {
                 LocalSettingsKind::Settings | LocalSettingsKind::Editorconfig => cx
                     .update_global::<SettingsStore, _>(|store, cx| {
@@ -527,7 +701,17 @@ impl SettingsObserver {
                                 log::error!("Failed to set local settings: {e}");
                             }
                             Ok(_) => {
-                                cx.emit(SettingsObserverEvent::LocalSettingsUpdated(Ok(())));
+                                let result = store
+                                    .get::<ProjectSettings>(Some(SettingsLocation {
+                                        worktree_id,
+                                        path: directory.as_ref(),
+                                    }))
+                                    .validate_lsp_settings()
+                                    .map_err(|error| InvalidSettingsError::LocalSettings {
+                                        path: directory.join(local_settings_file_relative_path()),
+                                        message: format!("{error:#}"),
+                                    });
+                                cx.emit(SettingsObserverEvent::LocalSettingsUpdated(result));
                             }
                         }
                     }),
@@ -558,6 +742,64 @@ impl SettingsObserver {
             }
         }
     }
+
+    /// Records the contents of a local configuration file, and returns the part of
+    /// them that can be applied given the trust of the worktree containing it.
+    fn restrict_untrusted_content(
+        &mut self,
+        worktree: &Entity<Worktree>,
+        directory: &Arc<Path>,
+        kind: LocalSettingsKind,
+        content: Option<String>,
+        cx: &mut Context<Self>,
+    ) -> Option<String> {
+        if !matches!(self.mode, SettingsObserverMode::Local(_))
+            || kind == LocalSettingsKind::Editorconfig
+        {
+            return content;
+        }
+
+        let worktree_id = worktree.read(cx).id();
+        let key = (worktree_id, directory.clone(), kind);
+        let Some(content) = content else {
+            self.local_contents.remove(&key);
+            return None;
+        };
+        self.local_contents.insert(key, content.clone());
+        let abs_path = worktree.read(cx).abs_path().join(directory);
+        if worktree_trust::is_path_trusted(&abs_path, cx) {
+            return Some(content);
+        }
+
+        cx.emit(SettingsObserverEvent::LocalSettingsRestricted(worktree_id));
+        match kind {
+            LocalSettingsKind::Settings => {
+                worktree_trust::restrict_local_settings(&content).log_err()
+            }
+            LocalSettingsKind::Tasks => None,
+            LocalSettingsKind::Editorconfig => Some(content),
+        }
+    }
+
+    fn reapply_local_contents(&mut self, cx: &mut Context<Self>) {
+        let mut contents_by_worktree = HashMap::<WorktreeId, Vec<_>>::default();
+        for ((worktree_id, directory, kind), content) in &self.local_contents {
+            contents_by_worktree.entry(*worktree_id).or_default().push((
+                directory.clone(),
+                *kind,
+                Some(content.clone()),
+            ));
+        }
+        for (worktree_id, contents) in contents_by_worktree {
+            let worktree = self
+                .worktree_store
+                .read(cx)
+                .worktree_for_id(worktree_id, cx);
+            if let Some(worktree) = worktree {
+                self.update_settings(worktree, contents, cx);
+            }
+        }
+    }
 }
 
 pub fn local_settings_kind_from_proto(kind: proto::LocalSettingsKind) -> LocalSettingsKind {
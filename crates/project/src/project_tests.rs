@@ -1,4 +1,8 @@
-use crate::{Event, *};
+use crate::{
+    file_audit::FileConversion,
+    project_settings::{ProjectSettings, TodoSeverity},
+    Event, *,
+};
 use buffer_diff::{assert_hunks, DiffHunkSecondaryStatus, DiffHunkStatus};
 use fs::FakeFs;
 use futures::{future, StreamExt};
@@ -21,7 +25,7 @@ use serde_json::json;
 use std::os;
 use std::{str::FromStr, sync::OnceLock};
 
-use std::{mem, num::NonZeroU32, ops::Range, task::Poll};
+use std::{collections::BTreeMap, mem, num::NonZeroU32, ops::Range, task::Poll};
 use task::{ResolvedTask, TaskContext};
 use unindent::Unindent as _;
 use util::{
@@ -394,6 +398,154 @@ async fn test_managing_project_specific_settings(cx: &mut gpui::TestAppContext)
     );
 }
 
+#[gpui::test]
+async fn test_detecting_tasks_of_untrusted_worktrees(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+    enable_workspace_trust(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({ "Cargo.toml": "[package]\nname = \"app\"\n" }),
+    )
+    .await;
+    let project = Project::test(fs, [path!("/dir").as_ref()], cx).await;
+    cx.executor().run_until_parked();
+    let worktree_id = project.update(cx, |project, cx| {
+        project.worktrees(cx).next().unwrap().read(cx).id()
+    });
+    let detected_task_labels = |cx: &mut gpui::TestAppContext| {
+        cx.update(|cx| {
+            project
+                .read(cx)
+                .task_store
+                .read(cx)
+                .task_inventory()
+                .unwrap()
+                .read(cx)
+                .list_tasks(None, None, Some(worktree_id), cx)
+                .into_iter()
+                .filter(|(kind, _)| matches!(kind, TaskSourceKind::Detected { .. }))
+                .map(|(_, template)| template.label)
+                .collect::<Vec<_>>()
+        })
+    };
+    assert_eq!(detected_task_labels(cx), Vec::<String>::new());
+
+    cx.update(|cx| WorktreeTrust::trust(PathBuf::from(path!("/dir")), cx));
+    cx.executor().run_until_parked();
+    assert!(detected_task_labels(cx).contains(&"cargo build -p app".to_string()));
+
+    cx.update(|cx| WorktreeTrust::revoke(Path::new(path!("/dir")), cx));
+    cx.executor().run_until_parked();
+    assert_eq!(detected_task_labels(cx), Vec::<String>::new());
+}
+
+#[gpui::test]
+async fn test_untrusted_worktrees_use_default_prettier(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+    enable_workspace_trust(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "package.json": "{}",
+            "node_modules": { "prettier": { "package.json": "{}" } },
+            "file.ts": "",
+        }),
+    )
+    .await;
+    let project = Project::test(fs, [path!("/dir").as_ref()], cx).await;
+    project.read_with(cx, |project, _| project.languages().add(typescript_lang()));
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/file.ts"), cx)
+        })
+        .await
+        .unwrap();
+    let prettier_store = project.update(cx, |project, cx| {
+        project.lsp_store().read(cx).prettier_store().unwrap()
+    });
+    prettier_store.update(cx, |prettier_store, cx| {
+        prettier_store.install_default_prettier(None, std::iter::empty(), cx)
+    });
+
+    let (prettier_dir, _) = prettier_store
+        .update(cx, |prettier_store, cx| {
+            prettier_store.prettier_instance_for_buffer(&buffer, cx)
+        })
+        .await
+        .unwrap();
+    assert_eq!(prettier_dir, None, "the project's prettier must not be run");
+
+    cx.update(|cx| WorktreeTrust::trust(PathBuf::from(path!("/dir")), cx));
+    let (prettier_dir, _) = prettier_store
+        .update(cx, |prettier_store, cx| {
+            prettier_store.prettier_instance_for_buffer(&buffer, cx)
+        })
+        .await
+        .unwrap();
+    assert_eq!(prettier_dir, Some(PathBuf::from(path!("/dir"))));
+}
+
+#[gpui::test]
+async fn test_validating_project_specific_lsp_settings(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            ".zed": {
+                "settings.json": r#"{
+                    "lsp": { "rust-analyzer": { "binary": { "env": { "A=B": "1" } } } }
+                }"#,
+            },
+            "a.rs": "fn a() {}",
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/a.rs"), cx)
+        })
+        .await
+        .unwrap();
+    cx.executor().run_until_parked();
+    project.read_with(cx, |project, cx| {
+        let error = project
+            .validate_language_server_settings([buffer.clone()], cx)
+            .unwrap_err();
+        assert_eq!(
+            format!("{error:#}"),
+            "invalid settings for language server rust-analyzer: \
+             invalid environment variable name \"A=B\""
+        );
+    });
+
+    fs.insert_file(
+        path!("/dir/.zed/settings.json"),
+        r#"{
+            "lsp": {
+                "rust-analyzer": {
+                    "binary": { "env": { "A": "B" }, "working_directory": "crates" }
+                }
+            }
+        }"#
+        .into(),
+    )
+    .await;
+    cx.executor().run_until_parked();
+    project.read_with(cx, |project, cx| {
+        project
+            .validate_language_server_settings([buffer.clone()], cx)
+            .unwrap();
+    });
+}
+
 #[gpui::test]
 async fn test_managing_language_servers(cx: &mut gpui::TestAppContext) {
     init_test(cx);
@@ -3003,6 +3155,40 @@ async fn test_save_file(cx: &mut gpui::TestAppContext) {
     assert_eq!(new_text, buffer.update(cx, |buffer, _| buffer.text()));
 }
 
+#[gpui::test]
+async fn test_wait_for_saves(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "file1": "the old contents",
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let buffer = project
+        .update(cx, |p, cx| p.open_local_buffer(path!("/dir/file1"), cx))
+        .await
+        .unwrap();
+    buffer.update(cx, |buffer, cx| {
+        buffer.edit([(0..0, "the new contents, and ")], None, cx);
+    });
+
+    // The save goes on after its task is dropped, until its file is written.
+    drop(project.update(cx, |project, cx| project.save_buffer(buffer.clone(), cx)));
+    project
+        .update(cx, |project, cx| project.wait_for_saves(cx))
+        .await;
+    assert_eq!(
+        fs.load(Path::new(path!("/dir/file1"))).await.unwrap(),
+        "the new contents, and the old contents"
+    );
+    buffer.read_with(cx, |buffer, _| assert!(!buffer.is_dirty()));
+}
+
 #[gpui::test(iterations = 30)]
 async fn test_file_changes_multiple_times_on_disk(cx: &mut gpui::TestAppContext) {
     init_test(cx);
@@ -3705,6 +3891,94 @@ async fn test_buffer_file_changes_on_disk(cx: &mut gpui::TestAppContext) {
     });
 }
 
+#[gpui::test]
+async fn test_merge_buffer_with_file(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "the-file": "one\ntwo\nthree\nfour\nfive\n",
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let buffer = project
+        .update(cx, |p, cx| p.open_local_buffer(path!("/dir/the-file"), cx))
+        .await
+        .unwrap();
+
+    // The buffer and the file change different lines.
+    buffer.update(cx, |buffer, cx| buffer.edit([(0..3, "ONE")], None, cx));
+    fs.save(
+        path!("/dir/the-file").as_ref(),
+        &"one\ntwo\nthree\nfour\nFIVE\n".into(),
+        LineEnding::Unix,
+    )
+    .await
+    .unwrap();
+    cx.executor().run_until_parked();
+    buffer.update(cx, |buffer, _| assert!(buffer.has_conflict()));
+
+    let merge = buffer
+        .update(cx, |buffer, cx| buffer.merge_with_file(cx))
+        .await
+        .unwrap();
+    assert_eq!(merge.conflict_count(), 0);
+    buffer.update(cx, |buffer, cx| {
+        assert!(buffer.apply_file_merge(merge, cx).unwrap().is_none());
+        assert_eq!(buffer.text(), "ONE\ntwo\nthree\nfour\nFIVE\n");
+        assert!(buffer.is_dirty());
+        assert!(!buffer.has_conflict());
+    });
+
+    // Then both change the same line, differently. The file's earlier change is the base of
+    // this merge, so it's not a conflict.
+    buffer.update(cx, |buffer, cx| {
+        let offset = buffer.text().find("three").unwrap();
+        buffer.edit([(offset..offset + 5, "3")], None, cx)
+    });
+    fs.save(
+        path!("/dir/the-file").as_ref(),
+        &"one\ntwo\nTHREE\nfour\nFIVE\n".into(),
+        LineEnding::Unix,
+    )
+    .await
+    .unwrap();
+    cx.executor().run_until_parked();
+    let merge = buffer
+        .update(cx, |buffer, cx| buffer.merge_with_file(cx))
+        .await
+        .unwrap();
+    assert_eq!(merge.conflict_count(), 1);
+    let conflicts = buffer.update(cx, |buffer, cx| {
+        let conflicts = buffer.apply_file_merge(merge, cx).unwrap().unwrap();
+        assert_eq!(buffer.text(), "ONE\ntwo\n3\nfour\nFIVE\n");
+        assert!(buffer.has_conflict());
+        assert!(buffer.has_known_merge_conflicts());
+        conflicts
+    });
+    assert_eq!(
+        conflicts.text(),
+        "ONE\ntwo\n<<<<<<< Buffer\n3\n=======\nTHREE\n>>>>>>> Disk\nfour\nFIVE\n"
+    );
+
+    // The conflicts can't be resolved with their markers left in.
+    buffer.update(cx, |buffer, cx| {
+        assert!(buffer
+            .resolve_file_merge(&conflicts, conflicts.text(), cx)
+            .is_err());
+        buffer
+            .resolve_file_merge(&conflicts, "ONE\ntwo\nTHREE\nfour\nFIVE\n", cx)
+            .unwrap();
+        assert_eq!(buffer.text(), "ONE\ntwo\nTHREE\nfour\nFIVE\n");
+        assert!(buffer.is_dirty());
+        assert!(!buffer.has_conflict());
+        assert!(!buffer.has_known_merge_conflicts());
+    });
+}
+
 #[gpui::test]
 async fn test_buffer_line_endings(cx: &mut gpui::TestAppContext) {
     init_test(cx);
@@ -4280,7 +4554,7 @@ async fn test_rename(cx: &mut gpui::TestAppContext) {
 }
 
 #[gpui::test]
-async fn test_search(cx: &mut gpui::TestAppContext) {
+async fn test_rename_with_file_patches(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
     let fs = FakeFs::new(cx.executor());
@@ -4289,62 +4563,311 @@ async fn test_search(cx: &mut gpui::TestAppContext) {
         json!({
             "one.rs": "const ONE: usize = 1;",
             "two.rs": "const TWO: usize = one::ONE + one::ONE;",
-            "three.rs": "const THREE: usize = one::ONE + two::TWO;",
-            "four.rs": "const FOUR: usize = one::ONE + three::THREE;",
+            "three.rs": "fn three() {\n    one::ONE\n}\n"
         }),
     )
     .await;
+
     let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
-    assert_eq!(
-        search(
-            &project,
-            SearchQuery::text(
-                "TWO",
-                false,
-                true,
-                false,
-                Default::default(),
-                Default::default(),
-                None
-            )
-            .unwrap(),
-            cx
-        )
-        .await
-        .unwrap(),
-        HashMap::from_iter([
-            (separator!("dir/two.rs").to_string(), vec![6..9]),
-            (separator!("dir/three.rs").to_string(), vec![37..40])
-        ])
+
+    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
+    language_registry.add(rust_lang());
+    let mut fake_servers = language_registry.register_fake_lsp(
+        "Rust",
+        FakeLspAdapter {
+            capabilities: lsp::ServerCapabilities {
+                rename_provider: Some(lsp::OneOf::Left(true)),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
     );
 
-    let buffer_4 = project
+    let (buffer, _handle) = project
         .update(cx, |project, cx| {
-            project.open_local_buffer(path!("/dir/four.rs"), cx)
+            project.open_local_buffer_with_lsp(path!("/dir/one.rs"), cx)
         })
         .await
         .unwrap();
-    buffer_4.update(cx, |buffer, cx| {
-        let text = "two::TWO";
-        buffer.edit([(20..28, text), (31..43, text)], None, cx);
+
+    let fake_server = fake_servers.next().await.unwrap();
+
+    let response = project.update(cx, |project, cx| {
+        project.perform_rename_with_file_patches(buffer.clone(), 7, "THREE".to_string(), true, cx)
     });
+    fake_server
+        .handle_request::<lsp::request::Rename, _, _>(|_, _| async move {
+            let edit = |row, start, end| {
+                lsp::TextEdit::new(
+                    lsp::Range::new(lsp::Position::new(row, start), lsp::Position::new(row, end)),
+                    "THREE".to_string(),
+                )
+            };
+            Ok(Some(lsp::WorkspaceEdit {
+                changes: Some(
+                    [
+                        (
+                            lsp::Url::from_file_path(path!("/dir/one.rs")).unwrap(),
+                            vec![edit(0, 6, 9)],
+                        ),
+                        (
+                            lsp::Url::from_file_path(path!("/dir/two.rs")).unwrap(),
+                            vec![edit(0, 24, 27), edit(0, 35, 38)],
+                        ),
+                        (
+                            lsp::Url::from_file_path(path!("/dir/three.rs")).unwrap(),
+                            vec![edit(1, 9, 12)],
+                        ),
+                    ]
+                    .into_iter()
+                    .collect(),
+                ),
+                ..Default::default()
+            }))
+        })
+        .next()
+        .await
+        .unwrap();
+    let project_patch = response.await.unwrap();
 
+    // Only the open buffer is edited, and the other files are patched.
+    let transaction = project_patch.transaction.0;
+    assert_eq!(transaction.len(), 1);
     assert_eq!(
-        search(
-            &project,
-            SearchQuery::text(
-                "TWO",
-                false,
-                true,
-                false,
-                Default::default(),
-                Default::default(),
-                None,
-            )
-            .unwrap(),
-            cx
-        )
-        .await
+        transaction
+            .into_keys()
+            .next()
+            .unwrap()
+            .update(cx, |buffer, _| buffer.text()),
+        "const THREE: usize = 1;"
+    );
+    let mut file_patches = project_patch.file_patches;
+    file_patches.sort_by_key(|file_patch| file_patch.abs_path.clone());
+    assert_eq!(
+        file_patches
+            .iter()
+            .map(|file_patch| (
+                file_patch.project_path.path.as_ref(),
+                file_patch.hunks.clone()
+            ))
+            .collect::<Vec<_>>(),
+        [
+            (
+                Path::new("three.rs"),
+                vec![file_patch::FilePatchHunk {
+                    start_row: 1,
+                    old_text: "    one::ONE".to_string(),
+                    new_text: "    one::THREE".to_string(),
+                }]
+            ),
+            (
+                Path::new("two.rs"),
+                vec![file_patch::FilePatchHunk {
+                    start_row: 0,
+                    old_text: "const TWO: usize = one::ONE + one::ONE;".to_string(),
+                    new_text: "const TWO: usize = one::THREE + one::THREE;".to_string(),
+                }]
+            ),
+        ]
+    );
+
+    // A file changed on disk since it was renamed isn't patched.
+    fs.save(
+        path!("/dir/three.rs").as_ref(),
+        &"fn three() {}\n".into(),
+        LineEnding::Unix,
+    )
+    .await
+    .unwrap();
+    let unapplied_patches = project
+        .update(cx, |project, cx| {
+            project.apply_file_patches(file_patches, cx)
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        unapplied_patches
+            .iter()
+            .map(|file_patch| file_patch.abs_path.clone())
+            .collect::<Vec<_>>(),
+        [PathBuf::from(path!("/dir/three.rs"))]
+    );
+    assert_eq!(
+        fs.load(path!("/dir/two.rs").as_ref()).await.unwrap(),
+        "const TWO: usize = one::THREE + one::THREE;"
+    );
+    assert_eq!(
+        fs.load(path!("/dir/three.rs").as_ref()).await.unwrap(),
+        "fn three() {}\n"
+    );
+    project.read_with(cx, |project, cx| {
+        let two_path = ProjectPath {
+            worktree_id: project.worktrees(cx).next().unwrap().read(cx).id(),
+            path: Path::new("two.rs").into(),
+        };
+        assert!(project.get_open_buffer(&two_path, cx).is_none());
+    });
+}
+
+#[gpui::test]
+async fn test_rename_with_file_patches_editing_a_file_twice(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "one.rs": "const ONE: usize = 1;",
+            "two.rs": "const TWO: usize = one::ONE + one::ONE;",
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+
+    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
+    language_registry.add(rust_lang());
+    let mut fake_servers = language_registry.register_fake_lsp(
+        "Rust",
+        FakeLspAdapter {
+            capabilities: lsp::ServerCapabilities {
+                rename_provider: Some(lsp::OneOf::Left(true)),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    );
+
+    let (buffer, _handle) = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer_with_lsp(path!("/dir/one.rs"), cx)
+        })
+        .await
+        .unwrap();
+
+    let fake_server = fake_servers.next().await.unwrap();
+
+    let response = project.update(cx, |project, cx| {
+        project.perform_rename_with_file_patches(buffer.clone(), 7, "THREE".to_string(), true, cx)
+    });
+    fake_server
+        .handle_request::<lsp::request::Rename, _, _>(|_, _| async move {
+            let edit = |start, end| lsp::TextDocumentEdit {
+                text_document: lsp::OptionalVersionedTextDocumentIdentifier {
+                    uri: lsp::Url::from_file_path(path!("/dir/two.rs")).unwrap(),
+                    version: None,
+                },
+                edits: vec![lsp::Edit::Plain(lsp::TextEdit::new(
+                    lsp::Range::new(lsp::Position::new(0, start), lsp::Position::new(0, end)),
+                    "THREE".to_string(),
+                ))],
+            };
+            // The second edit of `two.rs` is made after the first one, which moved its range.
+            Ok(Some(lsp::WorkspaceEdit {
+                document_changes: Some(lsp::DocumentChanges::Edits(vec![
+                    edit(24, 27),
+                    edit(37, 40),
+                ])),
+                ..Default::default()
+            }))
+        })
+        .next()
+        .await
+        .unwrap();
+    let project_patch = response.await.unwrap();
+
+    // Both edits are made by the same patch.
+    let file_patches = project_patch.file_patches;
+    assert_eq!(
+        file_patches
+            .iter()
+            .map(|file_patch| file_patch.hunks.clone())
+            .collect::<Vec<_>>(),
+        [vec![file_patch::FilePatchHunk {
+            start_row: 0,
+            old_text: "const TWO: usize = one::ONE + one::ONE;".to_string(),
+            new_text: "const TWO: usize = one::THREE + one::THREE;".to_string(),
+        }]]
+    );
+    let unapplied_patches = project
+        .update(cx, |project, cx| {
+            project.apply_file_patches(file_patches, cx)
+        })
+        .await
+        .unwrap();
+    assert!(unapplied_patches.is_empty());
+    assert_eq!(
+        fs.load(path!("/dir/two.rs").as_ref()).await.unwrap(),
+        "const TWO: usize = one::THREE + one::THREE;"
+    );
+}
+
+#[gpui::test]
+async fn test_search(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "one.rs": "const ONE: usize = 1;",
+            "two.rs": "const TWO: usize = one::ONE + one::ONE;",
+            "three.rs": "const THREE: usize = one::ONE + two::TWO;",
+            "four.rs": "const FOUR: usize = one::ONE + three::THREE;",
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                "TWO",
+                false,
+                true,
+                false,
+                Default::default(),
+                Default::default(),
+                None
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([
+            (separator!("dir/two.rs").to_string(), vec![6..9]),
+            (separator!("dir/three.rs").to_string(), vec![37..40])
+        ])
+    );
+
+    let buffer_4 = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/four.rs"), cx)
+        })
+        .await
+        .unwrap();
+    buffer_4.update(cx, |buffer, cx| {
+        let text = "two::TWO";
+        buffer.edit([(20..28, text), (31..43, text)], None, cx);
+    });
+
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                "TWO",
+                false,
+                true,
+                false,
+                Default::default(),
+                Default::default(),
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
         .unwrap(),
         HashMap::from_iter([
             (separator!("dir/two.rs").to_string(), vec![6..9]),
@@ -4354,6 +4877,67 @@ async fn test_search(cx: &mut gpui::TestAppContext) {
     );
 }
 
+#[gpui::test]
+async fn test_replace_all(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "one.rs": "fn one() { old_name(1); old_name(2); }\n",
+            "two.rs": "fn two() { other(); }\n",
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let query = SearchQuery::regex(
+        r"old_name\((\d)\)",
+        false,
+        true,
+        false,
+        Default::default(),
+        Default::default(),
+        None,
+    )
+    .unwrap()
+    .with_replacement("new_name($1, None)".to_string());
+
+    let dry_run = project
+        .update(cx, |project, cx| {
+            project.replace_all(query.clone(), true, cx)
+        })
+        .await
+        .unwrap();
+    assert_eq!(dry_run.len(), 1);
+    assert_eq!(dry_run[0].path, PathBuf::from(separator!("dir/one.rs")));
+    assert_eq!(dry_run[0].replacement_count, 2);
+    assert_eq!(dry_run[0].transaction_id, None);
+    assert_eq!(
+        dry_run[0].diff,
+        "--- a/one.rs\n+++ b/one.rs\n@@ -1 +1 @@\n\
+         -fn one() { old_name(1); old_name(2); }\n\
+         +fn one() { new_name(1, None); new_name(2, None); }\n"
+    );
+    let buffer = dry_run[0].buffer.clone();
+    buffer.read_with(cx, |buffer, _| assert!(!buffer.is_dirty()));
+
+    let replacements = project
+        .update(cx, |project, cx| project.replace_all(query, false, cx))
+        .await
+        .unwrap();
+    assert_eq!(replacements.len(), 1);
+    assert!(replacements[0].transaction_id.is_some());
+    buffer.update(cx, |buffer, cx| {
+        assert_eq!(
+            buffer.text(),
+            "fn one() { new_name(1, None); new_name(2, None); }\n"
+        );
+        buffer.undo(cx);
+        assert_eq!(buffer.text(), "fn one() { old_name(1); old_name(2); }\n");
+    });
+}
+
 #[gpui::test]
 async fn test_search_with_inclusions(cx: &mut gpui::TestAppContext) {
     init_test(cx);
@@ -5960,6 +6544,292 @@ async fn search(
         .collect())
 }
 
+#[gpui::test]
+async fn test_todo_store(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+    cx.update(|cx| {
+        SettingsStore::update_global(cx, |settings, cx| {
+            settings.update_user_settings::<ProjectSettings>(cx, |settings| {
+                settings.todos.enabled = true;
+            });
+        })
+    });
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            ".git": {},
+            ".gitignore": "target\n",
+            "a.rs": "// TODO: first\nfn a() {}\n",
+            "b.rs": "fn b() {} // FIXME: second\n",
+            "c.rs": "fn c() {}\n",
+            "target": {
+                "d.rs": "// TODO: ignored\n",
+            },
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let todo_store = project.read_with(cx, |project, _| project.todo_store().unwrap().clone());
+    cx.run_until_parked();
+
+    let worktree_id = project.read_with(cx, |project, cx| {
+        project.worktrees(cx).next().unwrap().read(cx).id()
+    });
+    let todos = |cx: &mut gpui::TestAppContext| {
+        todo_store.read_with(cx, |store, _| {
+            store
+                .todos()
+                .flat_map(|(path, todos)| {
+                    todos.iter().map(|todo| {
+                        assert_eq!(path.worktree_id, worktree_id);
+                        (path.path.to_string_lossy().into_owned(), todo.text.clone())
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+    };
+    assert_eq!(
+        todos(cx),
+        [
+            ("a.rs".to_string(), "first".to_string()),
+            ("b.rs".to_string(), "second".to_string()),
+        ]
+    );
+
+    fs.save(
+        path!("/dir/c.rs").as_ref(),
+        &"// XXX: third\n".into(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    fs.remove_file(path!("/dir/a.rs").as_ref(), Default::default())
+        .await
+        .unwrap();
+    cx.run_until_parked();
+    assert_eq!(
+        todos(cx),
+        [
+            ("b.rs".to_string(), "second".to_string()),
+            ("c.rs".to_string(), "third".to_string()),
+        ]
+    );
+
+    // Configured keywords replace the default ones.
+    cx.update(|cx| {
+        SettingsStore::update_global(cx, |settings, cx| {
+            settings.update_user_settings::<ProjectSettings>(cx, |settings| {
+                settings.todos.keywords =
+                    BTreeMap::from_iter([("XXX".to_string(), TodoSeverity::Error)]);
+            });
+        })
+    });
+    cx.run_until_parked();
+    assert_eq!(todos(cx), [("c.rs".to_string(), "third".to_string())]);
+}
+
+#[gpui::test]
+async fn test_file_audit(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "a.txt": "one\r\ntwo\r\n",
+            "b.txt": "\tindented\n",
+            "c.txt": "clean\n",
+            "d.txt": "\u{feff}bom\r\n",
+            "two": {
+                ".zed": {
+                    "settings.json": r#"{ "tab_size": 2 }"#,
+                },
+                "e.txt": "\tindented\n",
+            },
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    cx.run_until_parked();
+    let audit = project.update(cx, |project, cx| project.audit_file_formats(cx));
+    cx.run_until_parked();
+
+    let audited_paths = |cx: &mut gpui::TestAppContext| {
+        audit.read_with(cx, |audit, _| {
+            assert!(!audit.is_running());
+            audit
+                .files()
+                .iter()
+                .map(|file| file.path.path.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        })
+    };
+    assert_eq!(
+        audited_paths(cx),
+        ["a.txt", "b.txt", "d.txt", separator!("two/e.txt")]
+    );
+
+    // Tabs are replaced according to the tab size at each file's location, and byte order
+    // marks are only removed when asked to.
+    let conversion = FileConversion {
+        line_ending: Some(LineEnding::Unix),
+        replace_tabs: true,
+        remove_byte_order_mark: false,
+    };
+    audit.update(cx, |audit, cx| {
+        let b_path = audit.files()[1].path.clone();
+        audit.set_included(&b_path, false, cx);
+        assert_eq!(audit.preview(&conversion).count(), 3);
+    });
+    let skipped_paths = audit
+        .update(cx, |audit, cx| audit.convert(conversion, cx))
+        .await
+        .unwrap();
+    cx.run_until_parked();
+
+    assert!(skipped_paths.is_empty());
+    assert_eq!(audited_paths(cx), ["b.txt", "d.txt"]);
+    assert_eq!(
+        fs.load(path!("/dir/a.txt").as_ref()).await.unwrap(),
+        "one\ntwo\n"
+    );
+    assert_eq!(
+        fs.load(path!("/dir/d.txt").as_ref()).await.unwrap(),
+        "\u{feff}bom\n"
+    );
+    assert_eq!(
+        fs.load(path!("/dir/two/e.txt").as_ref()).await.unwrap(),
+        "  indented\n"
+    );
+    assert_eq!(
+        fs.load(path!("/dir/b.txt").as_ref()).await.unwrap(),
+        "\tindented\n"
+    );
+
+    // Files open in buffers with unsaved changes are left as they are.
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/b.txt"), cx)
+        })
+        .await
+        .unwrap();
+    buffer.update(cx, |buffer, cx| buffer.edit([(0..0, "// ")], None, cx));
+    audit.update(cx, |audit, cx| {
+        let b_path = audit.files()[0].path.clone();
+        audit.set_included(&b_path, true, cx);
+    });
+    let skipped_paths = audit
+        .update(cx, |audit, cx| audit.convert(conversion, cx))
+        .await
+        .unwrap();
+    cx.run_until_parked();
+
+    assert_eq!(
+        skipped_paths
+            .iter()
+            .map(|path| path.path.to_string_lossy().into_owned())
+            .collect::<Vec<_>>(),
+        ["b.txt"]
+    );
+    assert_eq!(
+        fs.load(path!("/dir/b.txt").as_ref()).await.unwrap(),
+        "\tindented\n"
+    );
+}
+
+#[gpui::test]
+async fn test_open_virtual_document(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+    cx.update(virtual_documents::init);
+
+    let fs = FakeFs::new(cx.background_executor.clone());
+    fs.insert_tree(
+        "/dir",
+        json!({
+            ".git": {},
+            "src": {
+                "main.rs": "fn main() {}\n",
+            }
+        }),
+    )
+    .await;
+    fs.set_head_for_repo(
+        Path::new("/dir/.git"),
+        &[("src/main.rs".into(), "fn old_main() {}\n".into())],
+    );
+
+    let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+    project.read_with(cx, |project, _| project.languages().add(rust_lang()));
+    cx.run_until_parked();
+
+    let uri = Url::parse("git://HEAD/src/main.rs").unwrap();
+    let document = project
+        .update(cx, |project, cx| {
+            project.open_virtual_document(uri.clone(), cx)
+        })
+        .await
+        .unwrap();
+    assert_eq!(document.title.as_ref(), "main.rs (HEAD)");
+    document.buffer.read_with(cx, |buffer, _| {
+        assert_eq!(buffer.text(), "fn old_main() {}\n");
+        assert_eq!(buffer.language().unwrap().name(), "Rust".into());
+        assert!(buffer.read_only());
+        assert!(buffer.file().is_none());
+    });
+
+    // Opening the URI again returns the same buffer.
+    let reopened = project
+        .update(cx, |project, cx| project.open_virtual_document(uri, cx))
+        .await
+        .unwrap();
+    assert_eq!(reopened.buffer, document.buffer);
+
+    // Other revisions are loaded too, from the URI's host or its query, and paths are decoded.
+    fs.set_revision_for_repo(
+        Path::new("/dir/.git"),
+        "HEAD~1",
+        &[("src/main.rs".into(), "fn older_main() {}\n".into())],
+    );
+    fs.set_revision_for_repo(
+        Path::new("/dir/.git"),
+        "origin/main^",
+        &[("src/my lib.rs".into(), "pub fn lib() {}\n".into())],
+    );
+    let document = project
+        .update(cx, |project, cx| {
+            project.open_virtual_document(Url::parse("git://HEAD~1/src/main.rs").unwrap(), cx)
+        })
+        .await
+        .unwrap();
+    assert_eq!(document.title.as_ref(), "main.rs (HEAD~1)");
+    document.buffer.read_with(cx, |buffer, _| {
+        assert_eq!(buffer.text(), "fn older_main() {}\n");
+    });
+    let document = project
+        .update(cx, |project, cx| {
+            project.open_virtual_document(
+                Url::parse("git:/src/my%20lib.rs?revision=origin/main%5E").unwrap(),
+                cx,
+            )
+        })
+        .await
+        .unwrap();
+    assert_eq!(document.title.as_ref(), "my lib.rs (origin/main^)");
+    document.buffer.read_with(cx, |buffer, _| {
+        assert_eq!(buffer.text(), "pub fn lib() {}\n");
+        assert!(buffer.read_only());
+    });
+
+    let error = project
+        .update(cx, |project, cx| {
+            project.open_virtual_document(Url::parse("jdt://contents/Foo.class").unwrap(), cx)
+        })
+        .await;
+    assert!(error.is_err());
+}
+
 pub fn init_test(cx: &mut gpui::TestAppContext) {
     if std::env::var("RUST_LOG").is_ok() {
         env_logger::try_init().ok();
@@ -5974,6 +6844,16 @@ pub fn init_test(cx: &mut gpui::TestAppContext) {
     });
 }
 
+fn enable_workspace_trust(cx: &mut gpui::TestAppContext) {
+    cx.update(|cx| {
+        SettingsStore::update_global(cx, |settings, cx| {
+            settings.update_user_settings::<ProjectSettings>(cx, |settings| {
+                settings.workspace_trust.enabled = true;
+            });
+        });
+    });
+}
+
 fn json_lang() -> Arc<Language> {
     Arc::new(Language::new(
         LanguageConfig {
@@ -0,0 +1,137 @@
+//! Non-interactive replacement of the matches of a search query across the project, for
+//! scripted refactors. The would-be edits can be previewed as unified diffs before they are
+//! applied.
+
+use crate::{
+    search::{SearchQuery, SearchResult},
+    Project,
+};
+use anyhow::{anyhow, Result};
+use gpui::{Context, Entity, Task};
+use language::{Buffer, BufferSnapshot, ToOffset as _};
+use similar::TextDiff;
+use std::{ops::Range, path::PathBuf, sync::Arc};
+use text::TransactionId;
+
+/// The replacements made, or that would be made, in a single buffer.
+#[derive(Debug)]
+pub struct BufferReplacement {
+    pub buffer: Entity<Buffer>,
+    /// The path of the buffer, including the name of its worktree.
+    pub path: PathBuf,
+    pub replacement_count: usize,
+    /// The changes to the buffer, as a unified diff.
+    pub diff: String,
+    /// The transaction of the replacement, unless it was a dry run.
+    pub transaction_id: Option<TransactionId>,
+}
+
+impl Project {
+    /// Replaces every match of the query with its replacement, in the buffers the query
+    /// searches. With `dry_run`, the buffers aren't edited and only the diffs are returned.
+    ///
+    /// The edited buffers are left dirty, with one transaction per buffer. Nothing is
+    /// replaced when the search hits its result limit.
+    pub fn replace_all(
+        &mut self,
+        query: SearchQuery,
+        dry_run: bool,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Vec<BufferReplacement>>> {
+        if query.replacement().is_none() {
+            return Task::ready(Err(anyhow!("the query has no replacement")));
+        }
+        let query = Arc::new(query);
+        let results = self.search((*query).clone(), cx);
+        cx.spawn(|_, mut cx| async move {
+            let mut matches = Vec::new();
+            while let Ok(result) = results.recv().await {
+                match result {
+                    SearchResult::Buffer { buffer, ranges } => matches.push((buffer, ranges)),
+                    SearchResult::LimitReached => {
+                        return Err(anyhow!("the query matches too many places to replace"))
+                    }
+                }
+            }
+
+            let mut replacements = Vec::new();
+            for (buffer, ranges) in matches {
+                let (snapshot, path) = buffer.read_with(&cx, |buffer, cx| {
+                    let path = buffer.file().map(|file| file.full_path(cx));
+                    (buffer.snapshot(), path)
+                })?;
+                let ranges = ranges
+                    .iter()
+                    .map(|range| range.to_offset(&snapshot))
+                    .collect::<Vec<_>>();
+                let query = query.clone();
+                let (edits, diff) = cx
+                    .background_executor()
+                    .spawn(async move { replacement_edits(&query, &snapshot, &ranges) })
+                    .await;
+                if edits.is_empty() {
+                    continue;
+                }
+
+                let replacement_count = edits.len();
+                let transaction_id = if dry_run {
+                    None
+                } else {
+                    buffer.update(&mut cx, |buffer, cx| {
+                        buffer.finalize_last_transaction();
+                        buffer.start_transaction();
+                        buffer.edit(edits, None, cx);
+                        buffer.end_transaction(cx)
+                    })?
+                };
+                replacements.push(BufferReplacement {
+                    buffer,
+                    path: path.unwrap_or_default(),
+                    replacement_count,
+                    diff,
+                    transaction_id,
+                });
+            }
+            Ok(replacements)
+        })
+    }
+}
+
+/// Returns the edits replacing the matches in the buffer, and the resulting diff.
+fn replacement_edits(
+    query: &SearchQuery,
+    snapshot: &BufferSnapshot,
+    ranges: &[Range<usize>],
+) -> (Vec<(Range<usize>, String)>, String) {
+    let edits = ranges
+        .iter()
+        .filter_map(|range| {
+            let text = snapshot.text_for_range(range.clone()).collect::<String>();
+            let replacement = query.replacement_for(&text)?;
+            (replacement != text).then(|| (range.clone(), replacement.into_owned()))
+        })
+        .collect::<Vec<_>>();
+    if edits.is_empty() {
+        return (edits, String::new());
+    }
+
+    let old_text = snapshot.text();
+    let mut new_text = String::with_capacity(old_text.len());
+    let mut offset = 0;
+    for (range, replacement) in &edits {
+        new_text.push_str(&old_text[offset..range.start]);
+        new_text.push_str(replacement);
+        offset = range.end;
+    }
+    new_text.push_str(&old_text[offset..]);
+
+    let path = snapshot
+        .file()
+        .map(|file| file.path().to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let diff = TextDiff::from_lines(&old_text, &new_text)
+        .unified_diff()
+        .header(&format!("a/{path}"), &format!("b/{path}"))
+        .to_string();
+    (edits, diff)
+}
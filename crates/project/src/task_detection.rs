@@ -0,0 +1,270 @@
+//! Detection of the tasks that project manifests, like `Cargo.toml`, `package.json`
+//! or `Makefile`, make available without a `tasks.json` file.
+
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use collections::BTreeSet;
+use gpui::SharedString;
+use task::TaskTemplate;
+
+/// Extracts task templates from a manifest file found in a worktree.
+///
+/// Detectors run on the background executor whenever one of their manifest files
+/// changes, and their tasks are listed in the task picker along with [`TaskDetector::name`].
+pub trait TaskDetector: Send + Sync + 'static {
+    /// A short name shown next to the detected tasks, e.g. `cargo`.
+    fn name(&self) -> SharedString;
+
+    /// The names of the manifest files this detector reads.
+    fn manifest_file_names(&self) -> &'static [&'static str];
+
+    /// Returns the tasks defined by the given manifest contents. The tasks are run
+    /// in the manifest's directory unless they specify a working directory.
+    fn detect_tasks(&self, manifest: &str) -> anyhow::Result<Vec<TaskTemplate>>;
+}
+
+pub fn builtin_task_detectors() -> Vec<Arc<dyn TaskDetector>> {
+    vec![
+        Arc::new(CargoTaskDetector),
+        Arc::new(PackageJsonTaskDetector),
+        Arc::new(MakefileTaskDetector),
+    ]
+}
+
+fn task(label: String, command: &str, args: impl IntoIterator<Item = String>) -> TaskTemplate {
+    TaskTemplate {
+        label,
+        command: command.to_string(),
+        args: args.into_iter().collect(),
+        ..TaskTemplate::default()
+    }
+}
+
+/// Detects build, test and run tasks for Cargo packages and workspaces.
+pub struct CargoTaskDetector;
+
+impl TaskDetector for CargoTaskDetector {
+    fn name(&self) -> SharedString {
+        "cargo".into()
+    }
+
+    fn manifest_file_names(&self) -> &'static [&'static str] {
+        &["Cargo.toml"]
+    }
+
+    fn detect_tasks(&self, manifest: &str) -> anyhow::Result<Vec<TaskTemplate>> {
+        let manifest = manifest
+            .parse::<toml::Table>()
+            .context("parsing Cargo.toml")?;
+        let target_names = |key: &str| {
+            manifest
+                .get(key)
+                .and_then(|targets| targets.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|target| target.get("name")?.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        let mut tasks = Vec::new();
+        let package_name = manifest
+            .get("package")
+            .and_then(|package| package.get("name"))
+            .and_then(|name| name.as_str());
+        if let Some(package_name) = package_name {
+            let package_args = || ["-p".to_string(), package_name.to_string()];
+            tasks.push(task(
+                format!("cargo build -p {package_name}"),
+                "cargo",
+                ["build".to_string()].into_iter().chain(package_args()),
+            ));
+            tasks.push(task(
+                format!("cargo test -p {package_name}"),
+                "cargo",
+                ["test".to_string()].into_iter().chain(package_args()),
+            ));
+
+            let binaries = target_names("bin");
+            if binaries.is_empty() && !manifest.contains_key("lib") {
+                tasks.push(task(
+                    format!("cargo run -p {package_name}"),
+                    "cargo",
+                    ["run".to_string()].into_iter().chain(package_args()),
+                ));
+            }
+            for binary in binaries {
+                tasks.push(task(
+                    format!("cargo run --bin {binary}"),
+                    "cargo",
+                    ["run".to_string(), "--bin".to_string(), binary.to_string()],
+                ));
+            }
+        } else if manifest.contains_key("workspace") {
+            for subcommand in ["build", "test"] {
+                tasks.push(task(
+                    format!("cargo {subcommand} --workspace"),
+                    "cargo",
+                    [subcommand.to_string(), "--workspace".to_string()],
+                ));
+            }
+        }
+
+        for example in target_names("example") {
+            tasks.push(task(
+                format!("cargo run --example {example}"),
+                "cargo",
+                [
+                    "run".to_string(),
+                    "--example".to_string(),
+                    example.to_string(),
+                ],
+            ));
+        }
+        Ok(tasks)
+    }
+}
+
+/// Detects the scripts of npm packages.
+pub struct PackageJsonTaskDetector;
+
+impl TaskDetector for PackageJsonTaskDetector {
+    fn name(&self) -> SharedString {
+        "npm".into()
+    }
+
+    fn manifest_file_names(&self) -> &'static [&'static str] {
+        &["package.json"]
+    }
+
+    fn detect_tasks(&self, manifest: &str) -> anyhow::Result<Vec<TaskTemplate>> {
+        let manifest =
+            serde_json::from_str::<serde_json::Value>(manifest).context("parsing package.json")?;
+        Ok(manifest
+            .get("scripts")
+            .and_then(|scripts| scripts.as_object())
+            .into_iter()
+            .flat_map(|scripts| scripts.keys())
+            .map(|script| {
+                task(
+                    format!("npm run {script}"),
+                    "npm",
+                    ["run".to_string(), script.clone()],
+                )
+            })
+            .collect())
+    }
+}
+
+/// Detects the explicit targets of Makefiles.
+pub struct MakefileTaskDetector;
+
+impl TaskDetector for MakefileTaskDetector {
+    fn name(&self) -> SharedString {
+        "make".into()
+    }
+
+    fn manifest_file_names(&self) -> &'static [&'static str] {
+        &["Makefile", "makefile", "GNUmakefile"]
+    }
+
+    fn detect_tasks(&self, manifest: &str) -> anyhow::Result<Vec<TaskTemplate>> {
+        let mut targets = BTreeSet::default();
+        for line in manifest.lines() {
+            // Recipes and comments start with whitespace and `#` respectively.
+            if line.starts_with(|c: char| c.is_whitespace() || c == '#' || c == '.') {
+                continue;
+            }
+            let Some((names, rest)) = line.split_once(':') else {
+                continue;
+            };
+            // Skip `:=` and `::=` assignments.
+            if rest.starts_with('=') || rest.starts_with(":=") || names.contains('=') {
+                continue;
+            }
+            targets.extend(names.split_whitespace().filter(|name| {
+                !name.contains(['%', '$', '(', ')'])
+                    && name.chars().all(|c| !c.is_control() && c != '\\')
+            }));
+        }
+        Ok(targets
+            .into_iter()
+            .map(|target| task(format!("make {target}"), "make", [target.to_string()]))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(tasks: anyhow::Result<Vec<TaskTemplate>>) -> Vec<String> {
+        tasks.unwrap().into_iter().map(|task| task.label).collect()
+    }
+
+    #[test]
+    fn test_cargo_task_detection() {
+        let manifest = r#"
+            [package]
+            name = "app"
+
+            [[bin]]
+            name = "server"
+
+            [[example]]
+            name = "demo"
+        "#;
+        assert_eq!(
+            labels(CargoTaskDetector.detect_tasks(manifest)),
+            [
+                "cargo build -p app",
+                "cargo test -p app",
+                "cargo run --bin server",
+                "cargo run --example demo",
+            ]
+        );
+        assert_eq!(
+            labels(CargoTaskDetector.detect_tasks("[workspace]\nmembers = [\"crates/*\"]\n")),
+            ["cargo build --workspace", "cargo test --workspace"]
+        );
+        assert!(CargoTaskDetector.detect_tasks("[package").is_err());
+    }
+
+    #[test]
+    fn test_package_json_task_detection() {
+        let manifest = r#"{ "name": "app", "scripts": { "build": "tsc", "test": "vitest" } }"#;
+        let tasks = PackageJsonTaskDetector.detect_tasks(manifest).unwrap();
+        assert_eq!(
+            tasks
+                .iter()
+                .map(|task| (task.label.as_str(), task.args.clone()))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    "npm run build",
+                    vec!["run".to_string(), "build".to_string()]
+                ),
+                ("npm run test", vec!["run".to_string(), "test".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_makefile_task_detection() {
+        let manifest = "
+CC := gcc
+.PHONY: all clean
+all: build
+build test: main.c
+\t$(CC) main.c
+%.o: %.c
+# commented: out
+clean:
+\trm -f *.o
+";
+        assert_eq!(
+            labels(MakefileTaskDetector.detect_tasks(manifest)),
+            ["make all", "make build", "make clean", "make test"]
+        );
+    }
+}
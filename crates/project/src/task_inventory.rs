@@ -3,7 +3,7 @@
 use std::{
     borrow::Cow,
     cmp::{self, Reverse},
-    collections::hash_map,
+    collections::{hash_map, BTreeMap},
     path::{Path, PathBuf},
     sync::Arc,
 };
@@ -28,6 +28,13 @@ use crate::worktree_store::WorktreeStore;
 pub struct Inventory {
     last_scheduled_tasks: VecDeque<(TaskSourceKind, ResolvedTask)>,
     templates_from_settings: ParsedTemplates,
+    detected_templates: HashMap<WorktreeId, BTreeMap<Arc<Path>, DetectedTemplates>>,
+}
+
+#[derive(Debug)]
+struct DetectedTemplates {
+    detector: SharedString,
+    templates: Vec<TaskTemplate>,
 }
 
 #[derive(Debug, Default)]
@@ -54,6 +61,12 @@ pub enum TaskSourceKind {
     },
     /// Languages-specific tasks coming from extensions.
     Language { name: SharedString },
+    /// Tasks detected from a project manifest, like `Cargo.toml` or `package.json`.
+    Detected {
+        id: WorktreeId,
+        manifest_path: PathBuf,
+        detector: SharedString,
+    },
 }
 
 impl TaskSourceKind {
@@ -71,6 +84,11 @@ impl TaskSourceKind {
                 format!("{id_base}_{id}_{}", directory_in_worktree.display())
             }
             TaskSourceKind::Language { name } => format!("language_{name}"),
+            TaskSourceKind::Detected {
+                id,
+                manifest_path,
+                detector,
+            } => format!("detected_{detector}_{id}_{}", manifest_path.display()),
         }
     }
 }
@@ -102,6 +120,7 @@ impl Inventory {
             .chain(global_tasks);
 
         self.worktree_templates_from_settings(worktree)
+            .chain(self.detected_templates(worktree))
             .chain(language_tasks)
             .collect()
     }
@@ -177,6 +196,7 @@ impl Inventory {
             .chain(global_tasks);
         let worktree_tasks = self
             .worktree_templates_from_settings(worktree)
+            .chain(self.detected_templates(worktree))
             .chain(language_tasks);
 
         let new_resolved_tasks = worktree_tasks
@@ -288,6 +308,77 @@ impl Inventory {
         })
     }
 
+    fn detected_templates(
+        &self,
+        worktree: Option<WorktreeId>,
+    ) -> impl '_ + Iterator<Item = (TaskSourceKind, TaskTemplate)> {
+        worktree.into_iter().flat_map(|worktree| {
+            self.detected_templates
+                .get(&worktree)
+                .into_iter()
+                .flatten()
+                .flat_map(move |(manifest_path, detected)| {
+                    detected.templates.iter().map(move |template| {
+                        (
+                            TaskSourceKind::Detected {
+                                id: worktree,
+                                manifest_path: manifest_path.to_path_buf(),
+                                detector: detected.detector.clone(),
+                            },
+                            template.clone(),
+                        )
+                    })
+                })
+        })
+    }
+
+    /// Replaces the tasks detected from the manifest at the given path, or forgets
+    /// about the manifest if no tasks are given. Templates without a working directory
+    /// are run in the manifest's directory.
+    pub(crate) fn update_detected_tasks(
+        &mut self,
+        worktree_id: WorktreeId,
+        manifest_path: Arc<Path>,
+        detector: SharedString,
+        mut templates: Vec<TaskTemplate>,
+    ) {
+        if templates.is_empty() {
+            if let Some(worktree_templates) = self.detected_templates.get_mut(&worktree_id) {
+                worktree_templates.remove(&manifest_path);
+            }
+            return;
+        }
+
+        let manifest_dir = manifest_path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty());
+        let cwd = match manifest_dir {
+            Some(dir) => format!(
+                "{}/{}",
+                VariableName::WorktreeRoot.template_value(),
+                dir.to_string_lossy()
+            ),
+            None => VariableName::WorktreeRoot.template_value(),
+        };
+        for template in &mut templates {
+            template.cwd.get_or_insert_with(|| cwd.clone());
+        }
+        self.detected_templates
+            .entry(worktree_id)
+            .or_default()
+            .insert(
+                manifest_path,
+                DetectedTemplates {
+                    detector,
+                    templates,
+                },
+            );
+    }
+
+    pub(crate) fn remove_detected_tasks(&mut self, worktree_id: WorktreeId) {
+        self.detected_templates.remove(&worktree_id);
+    }
+
     /// Updates in-memory task metadata from the JSON string given.
     /// Will fail if the JSON is not a valid array of objects, but will continue if any object will not parse into a [`TaskTemplate`].
     ///
@@ -356,7 +447,8 @@ fn task_source_kind_preference(kind: &TaskSourceKind) -> u32 {
         TaskSourceKind::Language { .. } => 1,
         TaskSourceKind::UserInput => 2,
         TaskSourceKind::Worktree { .. } => 3,
-        TaskSourceKind::AbsPath { .. } => 4,
+        TaskSourceKind::Detected { .. } => 4,
+        TaskSourceKind::AbsPath { .. } => 5,
     }
 }
 
@@ -1,23 +1,30 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use anyhow::Context as _;
-use collections::HashMap;
+use collections::{HashMap, HashSet};
 use fs::Fs;
 use futures::StreamExt as _;
-use gpui::{App, AsyncApp, Context, Entity, EventEmitter, Task, WeakEntity};
+use gpui::{App, AsyncApp, Context, Entity, EventEmitter, Subscription, Task, WeakEntity};
 use language::{
     proto::{deserialize_anchor, serialize_anchor},
     ContextProvider as _, LanguageToolchainStore, Location,
 };
 use rpc::{proto, AnyProtoClient, TypedEnvelope};
-use settings::{watch_config_file, SettingsLocation};
+use settings::{watch_config_file, SettingsLocation, SettingsStore};
 use task::{TaskContext, TaskVariables, VariableName};
 use text::{BufferId, OffsetRangeExt};
 use util::ResultExt;
+use worktree::{PathChange, UpdatedEntriesSet, WorktreeId};
 
 use crate::{
-    buffer_store::BufferStore, worktree_store::WorktreeStore, BasicContextProvider, Inventory,
-    ProjectEnvironment,
+    buffer_store::BufferStore,
+    task_detection::{builtin_task_detectors, TaskDetector},
+    worktree_store::{WorktreeStore, WorktreeStoreEvent},
+    worktree_trust::{self, WorktreeTrust},
+    BasicContextProvider, Inventory, ProjectEnvironment,
 };
 
 #[allow(clippy::large_enum_variant)] // platform-dependent warning
@@ -33,12 +40,17 @@ pub struct StoreState {
     worktree_store: Entity<WorktreeStore>,
     toolchain_store: Arc<dyn LanguageToolchainStore>,
     _global_task_config_watcher: Task<()>,
+    _subscriptions: Vec<Subscription>,
 }
 
 enum StoreMode {
     Local {
         downstream_client: Option<(AnyProtoClient, u64)>,
         environment: Entity<ProjectEnvironment>,
+        fs: Arc<dyn Fs>,
+        task_detectors: Vec<Arc<dyn TaskDetector>>,
+        /// The worktrees whose manifests weren't read, because they aren't trusted.
+        untrusted_worktrees: HashSet<WorktreeId>,
     },
     Remote {
         upstream_client: AnyProtoClient,
@@ -163,10 +175,18 @@ impl TaskStore {
             mode: StoreMode::Local {
                 downstream_client: None,
                 environment,
+                fs: fs.clone(),
+                task_detectors: builtin_task_detectors(),
+                untrusted_worktrees: HashSet::default(),
             },
             task_inventory: Inventory::new(cx),
             buffer_store,
             toolchain_store,
+            _subscriptions: vec![
+                cx.subscribe(&worktree_store, Self::on_worktree_store_event),
+                cx.observe_global::<WorktreeTrust>(Self::worktree_trust_changed),
+                cx.observe_global::<SettingsStore>(Self::worktree_trust_changed),
+            ],
             worktree_store,
             _global_task_config_watcher: Self::subscribe_to_global_task_file_changes(fs, cx),
         })
@@ -191,6 +211,7 @@ impl TaskStore {
             toolchain_store,
             worktree_store,
             _global_task_config_watcher: Self::subscribe_to_global_task_file_changes(fs, cx),
+            _subscriptions: Vec::new(),
         })
     }
 
@@ -277,6 +298,154 @@ impl TaskStore {
         })
     }
 
+    fn on_worktree_store_event(
+        &mut self,
+        _: Entity<WorktreeStore>,
+        event: &WorktreeStoreEvent,
+        cx: &mut Context<Self>,
+    ) {
+        match event {
+            WorktreeStoreEvent::WorktreeUpdatedEntries(worktree_id, changes) => {
+                self.detect_tasks(*worktree_id, changes, cx)
+            }
+            WorktreeStoreEvent::WorktreeRemoved(_, worktree_id) => {
+                if let TaskStore::Functional(StoreState {
+                    mode:
+                        StoreMode::Local {
+                            untrusted_worktrees,
+                            ..
+                        },
+                    ..
+                }) = self
+                {
+                    untrusted_worktrees.remove(worktree_id);
+                }
+                if let Some(inventory) = self.task_inventory() {
+                    inventory.update(cx, |inventory, _| {
+                        inventory.remove_detected_tasks(*worktree_id)
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Re-runs the task detectors for the manifest files that changed in the given worktree,
+    /// unless it isn't trusted.
+    fn detect_tasks(
+        &mut self,
+        worktree_id: WorktreeId,
+        changes: &UpdatedEntriesSet,
+        cx: &mut Context<Self>,
+    ) {
+        let TaskStore::Functional(StoreState {
+            mode:
+                StoreMode::Local {
+                    fs,
+                    task_detectors,
+                    untrusted_worktrees,
+                    ..
+                },
+            task_inventory,
+            worktree_store,
+            ..
+        }) = self
+        else {
+            return;
+        };
+        let Some(worktree) = worktree_store.read(cx).worktree_for_id(worktree_id, cx) else {
+            return;
+        };
+        let worktree = worktree.read(cx);
+        if !worktree_trust::is_path_trusted(&worktree.abs_path(), cx) {
+            untrusted_worktrees.insert(worktree_id);
+            return;
+        }
+
+        let mut manifests = Vec::new();
+        for (path, entry_id, change) in changes.iter() {
+            let Some(detector) = detector_for_manifest(task_detectors, path) else {
+                continue;
+            };
+            let abs_path = if change == &PathChange::Removed {
+                None
+            } else {
+                worktree
+                    .entry_for_id(*entry_id)
+                    .filter(|entry| entry.is_file() && !entry.is_ignored)
+                    .and_then(|_| worktree.absolutize(path).log_err())
+            };
+            manifests.push((path.clone(), detector, abs_path));
+        }
+        spawn_task_detection(
+            worktree_id,
+            manifests,
+            fs.clone(),
+            task_inventory.clone(),
+            cx,
+        );
+    }
+
+    /// Detects the tasks of the worktrees that became trusted, and removes those of the
+    /// worktrees that aren't trusted anymore.
+    fn worktree_trust_changed(&mut self, cx: &mut Context<Self>) {
+        let TaskStore::Functional(StoreState {
+            mode:
+                StoreMode::Local {
+                    fs,
+                    task_detectors,
+                    untrusted_worktrees,
+                    ..
+                },
+            task_inventory,
+            worktree_store,
+            ..
+        }) = self
+        else {
+            return;
+        };
+
+        let mut newly_trusted = Vec::new();
+        let mut newly_untrusted = Vec::new();
+        for worktree in worktree_store.read(cx).worktrees() {
+            let worktree = worktree.read(cx);
+            let worktree_id = worktree.id();
+            if worktree_trust::is_path_trusted(&worktree.abs_path(), cx) {
+                if untrusted_worktrees.remove(&worktree_id) {
+                    let manifests = worktree
+                        .files(false, 0)
+                        .filter_map(|entry| {
+                            let detector = detector_for_manifest(task_detectors, &entry.path)?;
+                            let abs_path = worktree.absolutize(&entry.path).log_err()?;
+                            Some((entry.path.clone(), detector, Some(abs_path)))
+                        })
+                        .collect::<Vec<_>>();
+                    newly_trusted.push((worktree_id, manifests));
+                }
+            } else if untrusted_worktrees.insert(worktree_id) {
+                newly_untrusted.push(worktree_id);
+            }
+        }
+
+        if !newly_untrusted.is_empty() {
+            task_inventory.update(cx, |inventory, _| {
+                for worktree_id in newly_untrusted {
+                    inventory.remove_detected_tasks(worktree_id);
+                }
+            });
+            cx.refresh_windows();
+        }
+        for (worktree_id, manifests) in newly_trusted {
+            spawn_task_detection(
+                worktree_id,
+                manifests,
+                fs.clone(),
+                task_inventory.clone(),
+                cx,
+            );
+        }
+    }
+
     fn subscribe_to_global_task_file_changes(
         fs: Arc<dyn Fs>,
         cx: &mut Context<'_, Self>,
@@ -313,6 +482,63 @@ impl TaskStore {
     }
 }
 
+fn detector_for_manifest(
+    task_detectors: &[Arc<dyn TaskDetector>],
+    path: &Path,
+) -> Option<Arc<dyn TaskDetector>> {
+    let file_name = path.file_name()?.to_str()?;
+    task_detectors
+        .iter()
+        .find(|detector| detector.manifest_file_names().contains(&file_name))
+        .cloned()
+}
+
+/// Runs the detectors of the given manifests of a worktree in the background, replacing
+/// the detected tasks of the manifests that aren't given a path, or can't be read.
+fn spawn_task_detection(
+    worktree_id: WorktreeId,
+    manifests: Vec<(Arc<Path>, Arc<dyn TaskDetector>, Option<PathBuf>)>,
+    fs: Arc<dyn Fs>,
+    task_inventory: Entity<Inventory>,
+    cx: &mut Context<TaskStore>,
+) {
+    if manifests.is_empty() {
+        return;
+    }
+
+    cx.spawn(|_, mut cx| async move {
+        for (manifest_path, detector, abs_path) in manifests {
+            let templates = match abs_path {
+                Some(abs_path) => match fs.load(&abs_path).await {
+                    Ok(manifest) => {
+                        let detector = detector.clone();
+                        cx.background_executor()
+                            .spawn(async move { detector.detect_tasks(&manifest) })
+                            .await
+                            .with_context(|| format!("detecting tasks in {abs_path:?}"))
+                            .log_err()
+                            .unwrap_or_default()
+                    }
+                    Err(_) => Vec::new(),
+                },
+                None => Vec::new(),
+            };
+            let Ok(()) = task_inventory.update(&mut cx, |inventory, _| {
+                inventory.update_detected_tasks(
+                    worktree_id,
+                    manifest_path,
+                    detector.name(),
+                    templates,
+                )
+            }) else {
+                return;
+            };
+        }
+        cx.update(|cx| cx.refresh_windows()).ok();
+    })
+    .detach();
+}
+
 fn local_task_context_for_location(
     worktree_store: Entity<WorktreeStore>,
     toolchain_store: Arc<dyn LanguageToolchainStore>,
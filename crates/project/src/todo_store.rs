@@ -0,0 +1,421 @@
+use crate::{
+    project_settings::{ProjectSettings, TodoSettings, TodoSeverity},
+    worktree_store::{WorktreeStore, WorktreeStoreEvent},
+    ProjectPath,
+};
+use collections::HashMap;
+use fs::Fs;
+use gpui::{Context, Entity, EventEmitter, Subscription, Task};
+use language::Point;
+use regex::Regex;
+use settings::{Settings as _, SettingsStore};
+use std::{collections::BTreeMap, path::PathBuf, sync::Arc};
+use util::ResultExt;
+use worktree::{PathChange, UpdatedEntriesSet, Worktree, WorktreeId};
+
+/// Files larger than this are not scanned for TODO comments.
+const MAX_SCANNED_FILE_SIZE: u64 = 1024 * 1024;
+
+/// How many files are scanned before the results are published.
+const SCAN_BATCH_SIZE: usize = 256;
+
+/// A comment containing one of the configured TODO keywords.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TodoItem {
+    pub keyword: Arc<str>,
+    pub severity: TodoSeverity,
+    /// The position of the keyword in the file.
+    pub position: Point,
+    /// The rest of the comment after the keyword.
+    pub text: String,
+}
+
+pub enum TodoStoreEvent {
+    TodosUpdated,
+}
+
+impl EventEmitter<TodoStoreEvent> for TodoStore {}
+
+/// Collects the TODO comments of the files in all local worktrees, keeping them
+/// up to date as files change on disk.
+///
+/// Files are scanned in batches while the application is idle, so that scanning
+/// doesn't compete with typing.
+pub struct TodoStore {
+    worktree_store: Entity<WorktreeStore>,
+    fs: Arc<dyn Fs>,
+    settings: TodoSettings,
+    matcher: Option<Arc<TodoMatcher>>,
+    todos_by_path: BTreeMap<ProjectPath, Arc<[TodoItem]>>,
+    pending_paths: BTreeMap<ProjectPath, PathBuf>,
+    scan_task: Option<Task<()>>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl TodoStore {
+    pub fn new(
+        worktree_store: Entity<WorktreeStore>,
+        fs: Arc<dyn Fs>,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let settings = ProjectSettings::get_global(cx).todos.clone();
+        let mut this = Self {
+            matcher: TodoMatcher::new(&settings).map(Arc::new),
+            settings,
+            worktree_store: worktree_store.clone(),
+            fs,
+            todos_by_path: Default::default(),
+            pending_paths: Default::default(),
+            scan_task: None,
+            _subscriptions: vec![
+                cx.subscribe(&worktree_store, Self::on_worktree_store_event),
+                cx.observe_global::<SettingsStore>(Self::settings_changed),
+            ],
+        };
+        this.rescan(cx);
+        this
+    }
+
+    /// Returns the TODO comments of every file that has any, ordered by path.
+    pub fn todos(&self) -> impl Iterator<Item = (&ProjectPath, &[TodoItem])> {
+        self.todos_by_path
+            .iter()
+            .map(|(path, todos)| (path, todos.as_ref()))
+    }
+
+    pub fn todos_for_path(&self, path: &ProjectPath) -> &[TodoItem] {
+        self.todos_by_path
+            .get(path)
+            .map_or(&[], |todos| todos.as_ref())
+    }
+
+    pub fn todo_count(&self) -> usize {
+        self.todos_by_path.values().map(|todos| todos.len()).sum()
+    }
+
+    pub fn is_scanning(&self) -> bool {
+        self.scan_task.is_some()
+    }
+
+    fn settings_changed(&mut self, cx: &mut Context<Self>) {
+        let settings = &ProjectSettings::get_global(cx).todos;
+        if *settings != self.settings {
+            self.settings = settings.clone();
+            self.matcher = TodoMatcher::new(&self.settings).map(Arc::new);
+            self.rescan(cx);
+        }
+    }
+
+    fn rescan(&mut self, cx: &mut Context<Self>) {
+        self.todos_by_path.clear();
+        self.pending_paths.clear();
+        self.scan_task = None;
+        for worktree in self.worktree_store.read(cx).worktrees().collect::<Vec<_>>() {
+            self.scan_worktree(&worktree, cx);
+        }
+        cx.emit(TodoStoreEvent::TodosUpdated);
+        cx.notify();
+    }
+
+    fn on_worktree_store_event(
+        &mut self,
+        _: Entity<WorktreeStore>,
+        event: &WorktreeStoreEvent,
+        cx: &mut Context<Self>,
+    ) {
+        match event {
+            WorktreeStoreEvent::WorktreeAdded(worktree) => self.scan_worktree(worktree, cx),
+            WorktreeStoreEvent::WorktreeRemoved(_, worktree_id) => {
+                self.remove_worktree(*worktree_id, cx)
+            }
+            WorktreeStoreEvent::WorktreeUpdatedEntries(worktree_id, changes) => {
+                self.update_entries(*worktree_id, changes, cx)
+            }
+            _ => {}
+        }
+    }
+
+    fn scan_worktree(&mut self, worktree: &Entity<Worktree>, cx: &mut Context<Self>) {
+        if self.matcher.is_none() {
+            return;
+        }
+        let worktree = worktree.read(cx);
+        if !worktree.is_local() {
+            return;
+        }
+
+        let worktree_id = worktree.id();
+        let snapshot = worktree.snapshot();
+        let paths = snapshot
+            .files(false, 0)
+            .filter(|entry| entry.size <= MAX_SCANNED_FILE_SIZE && !entry.is_fifo)
+            .filter_map(|entry| {
+                let abs_path = snapshot.absolutize(&entry.path).log_err()?;
+                let project_path = ProjectPath {
+                    worktree_id,
+                    path: entry.path.clone(),
+                };
+                Some((project_path, abs_path))
+            })
+            .collect::<Vec<_>>();
+        self.schedule_scan(paths, cx);
+    }
+
+    fn remove_worktree(&mut self, worktree_id: WorktreeId, cx: &mut Context<Self>) {
+        self.pending_paths
+            .retain(|path, _| path.worktree_id != worktree_id);
+        let todo_count = self.todos_by_path.len();
+        self.todos_by_path
+            .retain(|path, _| path.worktree_id != worktree_id);
+        if self.todos_by_path.len() != todo_count {
+            cx.emit(TodoStoreEvent::TodosUpdated);
+            cx.notify();
+        }
+    }
+
+    fn update_entries(
+        &mut self,
+        worktree_id: WorktreeId,
+        changes: &UpdatedEntriesSet,
+        cx: &mut Context<Self>,
+    ) {
+        if self.matcher.is_none() {
+            return;
+        }
+        let Some(worktree) = self
+            .worktree_store
+            .read(cx)
+            .worktree_for_id(worktree_id, cx)
+        else {
+            return;
+        };
+        let worktree = worktree.read(cx);
+        if !worktree.is_local() {
+            return;
+        }
+
+        let mut removed_any = false;
+        let mut paths_to_scan = Vec::new();
+        for (path, entry_id, change) in changes.iter() {
+            let project_path = ProjectPath {
+                worktree_id,
+                path: path.clone(),
+            };
+            let entry = match change {
+                PathChange::Removed => None,
+                PathChange::Added
+                | PathChange::Updated
+                | PathChange::AddedOrUpdated
+                | PathChange::Loaded => worktree.entry_for_id(*entry_id),
+            };
+            match entry {
+                Some(entry)
+                    if entry.is_file()
+                        && !entry.is_ignored
+                        && !entry.is_fifo
+                        && entry.size <= MAX_SCANNED_FILE_SIZE =>
+                {
+                    if let Some(abs_path) = worktree.absolutize(path).log_err() {
+                        paths_to_scan.push((project_path, abs_path));
+                    }
+                }
+                _ => {
+                    self.pending_paths.remove(&project_path);
+                    removed_any |= self.todos_by_path.remove(&project_path).is_some();
+                }
+            }
+        }
+
+        if removed_any {
+            cx.emit(TodoStoreEvent::TodosUpdated);
+            cx.notify();
+        }
+        self.schedule_scan(paths_to_scan, cx);
+    }
+
+    fn schedule_scan(
+        &mut self,
+        paths: impl IntoIterator<Item = (ProjectPath, PathBuf)>,
+        cx: &mut Context<Self>,
+    ) {
+        self.pending_paths.extend(paths);
+        if self.pending_paths.is_empty() || self.scan_task.is_some() {
+            return;
+        }
+
+        self.scan_task = Some(cx.spawn(|this, mut cx| async move {
+            loop {
+                let Ok(idle) = cx.update(|cx| cx.when_idle()) else {
+                    break;
+                };
+                idle.await;
+
+                let Some((fs, matcher, paths)) = this
+                    .update(&mut cx, |this, cx| {
+                        let matcher = this.matcher.clone();
+                        let paths = (0..SCAN_BATCH_SIZE)
+                            .map_while(|_| this.pending_paths.pop_first())
+                            .collect::<Vec<_>>();
+                        if paths.is_empty() || matcher.is_none() {
+                            this.scan_task = None;
+                            cx.notify();
+                            return None;
+                        }
+                        Some((this.fs.clone(), matcher?, paths))
+                    })
+                    .ok()
+                    .flatten()
+                else {
+                    break;
+                };
+
+                let results = cx
+                    .background_executor()
+                    .spawn(async move {
+                        let mut results = Vec::with_capacity(paths.len());
+                        for (project_path, abs_path) in paths {
+                            let todos = match fs.load(&abs_path).await {
+                                Ok(text) => matcher.find_todos(&text),
+                                Err(_) => Vec::new(),
+                            };
+                            results.push((project_path, todos));
+                        }
+                        results
+                    })
+                    .await;
+
+                let updated = this.update(&mut cx, |this, cx| {
+                    for (project_path, todos) in results {
+                        if todos.is_empty() {
+                            this.todos_by_path.remove(&project_path);
+                        } else {
+                            this.todos_by_path.insert(project_path, todos.into());
+                        }
+                    }
+                    cx.emit(TodoStoreEvent::TodosUpdated);
+                    cx.notify();
+                });
+                if updated.is_err() {
+                    break;
+                }
+            }
+        }));
+    }
+}
+
+/// Finds TODO comments in plain text. Comments are recognized by the markers
+/// that start them in most languages, which avoids parsing every file in the
+/// project. The keyword has to be the first word of the comment, and markers
+/// following a quote or a word, like those in strings or URLs, are ignored.
+pub(crate) struct TodoMatcher {
+    regex: Regex,
+    severities: HashMap<String, TodoSeverity>,
+}
+
+impl TodoMatcher {
+    pub(crate) fn new(settings: &TodoSettings) -> Option<Self> {
+        if !settings.enabled || settings.keywords.is_empty() {
+            return None;
+        }
+
+        let keywords = settings
+            .keywords
+            .keys()
+            .map(|keyword| regex::escape(keyword))
+            .collect::<Vec<_>>()
+            .join("|");
+        let regex = Regex::new(&format!(
+            r#"(?m)(?:^|[^\w"'`])(?://+|#+|/\*+|--|;+|<!--|%+|^[ \t]*\*)[ \t!<*]*\b({keywords})\b(?:\([^)\n]*\))?:?[ \t]*([^\n]*)"#
+        ))
+        .log_err()?;
+        let severities = settings
+            .keywords
+            .iter()
+            .map(|(keyword, severity)| (keyword.clone(), *severity))
+            .collect();
+        Some(Self { regex, severities })
+    }
+
+    pub(crate) fn find_todos(&self, text: &str) -> Vec<TodoItem> {
+        let mut todos = Vec::new();
+        let mut position = Point::zero();
+        let mut offset = 0;
+        for captures in self.regex.captures_iter(text) {
+            let (Some(keyword), Some(comment)) = (captures.get(1), captures.get(2)) else {
+                continue;
+            };
+            for line in text[offset..keyword.start()].split_inclusive('\n') {
+                if line.ends_with('\n') {
+                    position = Point::new(position.row + 1, 0);
+                } else {
+                    position.column += line.len() as u32;
+                }
+            }
+            offset = keyword.start();
+
+            let Some(severity) = self.severities.get(keyword.as_str()) else {
+                continue;
+            };
+            let comment = comment.as_str().trim_end();
+            let comment = comment
+                .strip_suffix("*/")
+                .or_else(|| comment.strip_suffix("-->"))
+                .unwrap_or(comment);
+            todos.push(TodoItem {
+                keyword: keyword.as_str().into(),
+                severity: *severity,
+                position,
+                text: comment.trim().to_string(),
+            });
+        }
+        todos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_todos() {
+        let matcher = TodoMatcher::new(&TodoSettings {
+            enabled: true,
+            ..TodoSettings::default()
+        })
+        .unwrap();
+        let text = r#"
+fn main() {
+    // TODO: handle errors
+    let todo = "TODO: not a comment";
+    let url = "https://example.com"; // see the TODO list
+    let marker = "// TODO: in a string";
+    /* FIXME(someone) leaks memory */
+    # XXX
+}
+// TODOS are not keywords
+"#;
+        assert_eq!(
+            matcher.find_todos(text),
+            vec![
+                TodoItem {
+                    keyword: "TODO".into(),
+                    severity: TodoSeverity::Info,
+                    position: Point::new(2, 7),
+                    text: "handle errors".to_string(),
+                },
+                TodoItem {
+                    keyword: "FIXME".into(),
+                    severity: TodoSeverity::Warning,
+                    position: Point::new(6, 7),
+                    text: "leaks memory".to_string(),
+                },
+                TodoItem {
+                    keyword: "XXX".into(),
+                    severity: TodoSeverity::Warning,
+                    position: Point::new(7, 6),
+                    text: String::new(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! Documents that aren't files, opened by their URIs.
+//!
+//! Providers are registered for the schemes of the URIs they load, like decompiled sources or the
+//! documentation of a library. The `git` scheme is provided for the files of past revisions:
+//! `git://HEAD~1/src/main.rs` is `src/main.rs`, relative to the work directory of the active
+//! repository, one commit before `HEAD`. Revisions that can't be the host of a URI, like
+//! `HEAD^` or `origin/main`, are given by a `revision` query parameter instead, as in
+//! `git:/src/main.rs?revision=origin/main`.
+//!
+//! A document is opened as a read-only buffer without a file, whose language is the one of the
+//! path it was loaded for. Opening a URI again returns the same buffer, for as long as it's open.
+
+use crate::Project;
+use anyhow::{anyhow, Result};
+use collections::HashMap;
+use git::repository::RepoPath;
+use gpui::{App, AppContext as _, Context, Entity, Global, SharedString, Task, WeakEntity};
+use language::{Buffer, Capability};
+use std::{path::PathBuf, rc::Rc};
+use url::Url;
+
+/// What a provider loaded for a URI.
+pub struct VirtualDocument {
+    pub text: String,
+    /// The path the document is for, which its language and its title are chosen from.
+    pub path: PathBuf,
+    /// Where the document comes from, like the revision of a file, shown next to its name.
+    pub origin: SharedString,
+}
+
+/// Loads the documents of the URIs of a scheme.
+pub trait VirtualDocumentProvider: 'static {
+    fn load(&self, uri: &Url, project: &Project, cx: &mut App) -> Task<Result<VirtualDocument>>;
+}
+
+/// The providers of virtual documents, by the schemes of their URIs.
+#[derive(Default)]
+pub struct VirtualDocumentProviders {
+    providers: HashMap<String, Rc<dyn VirtualDocumentProvider>>,
+}
+
+impl Global for VirtualDocumentProviders {}
+
+impl VirtualDocumentProviders {
+    /// Registers the provider of the URIs of the scheme, replacing the one registered before.
+    pub fn register(
+        scheme: impl Into<String>,
+        provider: impl VirtualDocumentProvider,
+        cx: &mut App,
+    ) {
+        cx.default_global::<Self>()
+            .providers
+            .insert(scheme.into(), Rc::new(provider));
+    }
+
+    pub fn provider(scheme: &str, cx: &App) -> Option<Rc<dyn VirtualDocumentProvider>> {
+        cx.try_global::<Self>()?.providers.get(scheme).cloned()
+    }
+}
+
+/// The buffer opened for a virtual document.
+#[derive(Clone)]
+pub struct VirtualDocumentBuffer {
+    pub buffer: Entity<Buffer>,
+    /// The name of the document's path, followed by its origin.
+    pub title: SharedString,
+}
+
+/// A virtual document that was opened, for as long as its buffer is open.
+pub(crate) struct OpenVirtualDocument {
+    buffer: WeakEntity<Buffer>,
+    title: SharedString,
+}
+
+pub fn init(cx: &mut App) {
+    VirtualDocumentProviders::register("git", GitRevisionProvider, cx);
+}
+
+impl Project {
+    /// Opens the buffer of the document of a URI, loading it with the provider of its scheme
+    /// unless it's already open.
+    pub fn open_virtual_document(
+        &mut self,
+        uri: Url,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<VirtualDocumentBuffer>> {
+        self.virtual_documents
+            .retain(|_, document| document.buffer.is_upgradable());
+        if let Some(document) = self.virtual_documents.get(&uri) {
+            if let Some(buffer) = document.buffer.upgrade() {
+                let title = document.title.clone();
+                return Task::ready(Ok(VirtualDocumentBuffer { buffer, title }));
+            }
+        }
+        let Some(provider) = VirtualDocumentProviders::provider(uri.scheme(), cx) else {
+            return Task::ready(Err(anyhow!("no provider for {:?} URIs", uri.scheme())));
+        };
+        if !self.is_local() {
+            return Task::ready(Err(anyhow!(
+                "virtual documents can only be opened in local projects"
+            )));
+        }
+
+        let load = provider.load(&uri, self, cx);
+        let languages = self.languages.clone();
+        cx.spawn(|project, mut cx| async move {
+            let document = load.await?;
+            let language = languages.language_for_file_path(&document.path).await.ok();
+            let file_name = document
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| uri.to_string());
+            let title = SharedString::from(format!("{file_name} ({})", document.origin));
+            project.update(&mut cx, |project, cx| {
+                let buffer = project.create_local_buffer(&document.text, language, cx);
+                buffer.update(cx, |buffer, cx| {
+                    buffer.set_capability(Capability::ReadOnly, cx)
+                });
+                project.virtual_documents.insert(
+                    uri,
+                    OpenVirtualDocument {
+                        buffer: buffer.downgrade(),
+                        title: title.clone(),
+                    },
+                );
+                VirtualDocumentBuffer { buffer, title }
+            })
+        })
+    }
+
+    /// Returns whether the buffer was opened for a virtual document.
+    pub fn is_virtual_document(&self, buffer: &Entity<Buffer>) -> bool {
+        self.virtual_documents
+            .values()
+            .any(|document| document.buffer.entity_id() == buffer.entity_id())
+    }
+}
+
+/// Provides the files of past revisions, for `git://<revision>/<path>` and
+/// `git:/<path>?revision=<revision>` URIs.
+struct GitRevisionProvider;
+
+impl VirtualDocumentProvider for GitRevisionProvider {
+    fn load(&self, uri: &Url, project: &Project, cx: &mut App) -> Task<Result<VirtualDocument>> {
+        let revision = uri
+            .query_pairs()
+            .find_map(|(key, value)| (key == "revision").then(|| value.into_owned()))
+            .or_else(|| uri.host_str().map(ToOwned::to_owned))
+            .filter(|revision| !revision.is_empty());
+        let Some(revision) = revision else {
+            return Task::ready(Err(anyhow!("no revision in {uri}")));
+        };
+        let path = match urlencoding::decode(uri.path()) {
+            Ok(path) => PathBuf::from(path.trim_start_matches('/')),
+            Err(error) => return Task::ready(Err(anyhow!("invalid path in {uri}: {error}"))),
+        };
+        let Some(repository) = project.active_repository(cx) else {
+            return Task::ready(Err(anyhow!("no repository to load {uri} from")));
+        };
+        let load = repository.update(cx, |repository, cx| {
+            repository.load_revision_text(&revision, RepoPath::from(path.clone()), cx)
+        });
+        let uri = uri.clone();
+        cx.background_executor().spawn(async move {
+            let text = load
+                .await?
+                .ok_or_else(|| anyhow!("{uri} isn't in its revision"))?;
+            Ok(VirtualDocument {
+                text,
+                path,
+                origin: revision.into(),
+            })
+        })
+    }
+}
@@ -0,0 +1,181 @@
+//! Restricted mode for folders that the user hasn't trusted yet.
+//!
+//! When `workspace_trust` is enabled, a worktree is trusted if its path, or one of its
+//! ancestors, was trusted by the user. The project-local configuration of untrusted
+//! worktrees can't run anything on its own: `.zed/tasks.json` files are ignored, and only
+//! the local settings known not to run programs, such as indentation and wrapping, are
+//! applied until trust is granted. Nor is anything found in their files run: tasks
+//! aren't detected from their manifests, their own prettier isn't started, and their
+//! shell environment isn't loaded in them, since shell hooks and direnv run the
+//! scripts of the directory they are loaded in.
+
+use crate::project_settings::ProjectSettings;
+use collections::BTreeSet;
+use gpui::{App, Global};
+use serde_json::Value;
+use settings::{parse_json_with_comments, Settings};
+use std::path::{Path, PathBuf};
+
+/// The keys of project-local language settings known not to make Zed run programs, at the
+/// top level and in language-specific overrides. Other keys, including ones added later, are
+/// dropped from the settings of untrusted worktrees.
+const SAFE_LANGUAGE_SETTINGS_KEYS: &[&str] = &[
+    "tab_size",
+    "hard_tabs",
+    "soft_wrap",
+    "preferred_line_length",
+    "soft_wrap_exclusions",
+    "show_wrap_guides",
+    "wrap_guides",
+    "word_characters",
+    "font_features",
+    "indent_guides",
+    "remove_trailing_whitespace_on_save",
+    "ensure_final_newline_on_save",
+    "enable_language_server",
+    "show_edit_predictions",
+    "edit_predictions_disabled_in",
+    "show_whitespaces",
+    "extend_comment_on_newline",
+    "inlay_hints",
+    "use_autoclose",
+    "use_auto_surround",
+    "always_treat_brackets_as_autoclosed",
+    "use_on_type_format",
+    "linked_edits",
+    "auto_indent_on_paste",
+    "repair_delimiters",
+    "show_completions_on_input",
+    "show_completion_documentation",
+];
+
+/// The other top-level keys of project-local settings known not to make Zed run programs.
+const SAFE_SETTINGS_KEYS: &[&str] = &[
+    "languages",
+    "file_types",
+    "disabled_features",
+    "git",
+    "todos",
+    "file_scan_exclusions",
+    "file_scan_inclusions",
+    "file_scan_deferrals",
+    "private_files",
+];
+
+/// The paths trusted by the user. Subsystems should query it through
+/// [`is_path_trusted`], which accounts for the `workspace_trust` setting.
+#[derive(Default)]
+pub struct WorktreeTrust {
+    trusted_paths: BTreeSet<PathBuf>,
+}
+
+impl Global for WorktreeTrust {}
+
+impl WorktreeTrust {
+    /// Replaces the trusted paths, e.g. with the ones persisted by a previous session.
+    pub fn set_trusted_paths(paths: impl IntoIterator<Item = PathBuf>, cx: &mut App) {
+        cx.set_global(Self {
+            trusted_paths: paths.into_iter().collect(),
+        });
+    }
+
+    pub fn trusted_paths(cx: &App) -> impl Iterator<Item = &Path> {
+        cx.try_global::<Self>()
+            .into_iter()
+            .flat_map(|trust| trust.trusted_paths.iter().map(PathBuf::as_path))
+    }
+
+    pub fn trust(path: PathBuf, cx: &mut App) {
+        if !cx.default_global::<Self>().trusted_paths.contains(&path) {
+            cx.update_global::<Self, _>(|trust, _| trust.trusted_paths.insert(path));
+        }
+    }
+
+    /// Revokes the trust of a path. Its ancestors remain trusted.
+    pub fn revoke(path: &Path, cx: &mut App) {
+        if cx.default_global::<Self>().trusted_paths.contains(path) {
+            cx.update_global::<Self, _>(|trust, _| trust.trusted_paths.remove(path));
+        }
+    }
+
+    fn contains(&self, abs_path: &Path) -> bool {
+        abs_path
+            .ancestors()
+            .any(|ancestor| self.trusted_paths.contains(ancestor))
+    }
+}
+
+/// Whether project-local configuration found under the given path may run programs.
+pub fn is_path_trusted(abs_path: &Path, cx: &App) -> bool {
+    !ProjectSettings::get_global(cx).workspace_trust.enabled
+        || cx
+            .try_global::<WorktreeTrust>()
+            .map_or(false, |trust| trust.contains(abs_path))
+}
+
+/// Keeps only the settings known not to run programs in the contents of an untrusted local
+/// settings file, both at the top level and in language-specific overrides.
+pub fn restrict_local_settings(content: &str) -> anyhow::Result<String> {
+    let mut settings = parse_json_with_comments::<Value>(content)?;
+    if let Some(settings) = settings.as_object_mut() {
+        settings.retain(|key, _| {
+            SAFE_SETTINGS_KEYS.contains(&key.as_str())
+                || SAFE_LANGUAGE_SETTINGS_KEYS.contains(&key.as_str())
+        });
+        if let Some(languages) = settings.get_mut("languages").and_then(Value::as_object_mut) {
+            for language_settings in languages.values_mut().filter_map(Value::as_object_mut) {
+                language_settings
+                    .retain(|key, _| SAFE_LANGUAGE_SETTINGS_KEYS.contains(&key.as_str()));
+            }
+        }
+    }
+    Ok(settings.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_trusted_ancestors() {
+        let trust = WorktreeTrust {
+            trusted_paths: BTreeSet::from_iter([PathBuf::from("/code/work")]),
+        };
+        assert!(trust.contains(Path::new("/code/work")));
+        assert!(trust.contains(Path::new("/code/work/app/src")));
+        assert!(!trust.contains(Path::new("/code")));
+        assert!(!trust.contains(Path::new("/code/workshop")));
+    }
+
+    #[test]
+    fn test_restrict_local_settings() {
+        let content = r#"{
+            // Comments are allowed in settings files.
+            "tab_size": 2,
+            "lsp": { "rust-analyzer": { "binary": { "path": "./evil" } } },
+            "terminal": { "shell": { "program": "./evil" } },
+            "context_servers": { "evil": { "command": { "path": "./evil", "args": [] } } },
+            "workspace_trust": { "enabled": false },
+            "format_on_save": [{ "external": { "command": "./evil" } }],
+            "languages": {
+                "Rust": {
+                    "formatter": { "external": { "command": "./evil" } },
+                    "tasks": { "variables": { "EVIL": "./evil" } },
+                    "hard_tabs": true
+                }
+            },
+            "git": { "inline_blame": { "enabled": false } }
+        }"#;
+        let restricted: Value =
+            serde_json::from_str(&restrict_local_settings(content).unwrap()).unwrap();
+        assert_eq!(
+            restricted,
+            json!({
+                "tab_size": 2,
+                "languages": { "Rust": { "hard_tabs": true } },
+                "git": { "inline_blame": { "enabled": false } }
+            })
+        );
+    }
+}
@@ -59,7 +59,8 @@ use util::{maybe, paths::compare_paths, ResultExt, TakeUntilExt, TryFutureExt};
 use workspace::{
     dock::{DockPosition, Panel, PanelEvent},
     notifications::{DetachAndPromptErr, NotifyTaskExt},
-    DraggedSelection, OpenInTerminal, PreviewTabsSettings, SelectedEntry, Workspace,
+    prompt_in_workspace, DraggedSelection, OpenInTerminal, PreviewTabsSettings, PromptOptions,
+    SelectedEntry, Workspace,
 };
 use worktree::{CreatedEntry, GitEntry, GitEntryRef};
 
@@ -208,6 +209,7 @@ actions!(
         SelectPrevDiagnostic,
         SelectNextDirectory,
         SelectPrevDirectory,
+        ToggleAutoReveal,
     ]
 );
 
@@ -235,6 +237,11 @@ pub fn init(assets: impl AssetSource, cx: &mut App) {
         workspace.register_action(|workspace, _: &ToggleFocus, window, cx| {
             workspace.toggle_panel_focus::<ProjectPanel>(window, cx);
         });
+        workspace.register_action(|workspace, _: &ToggleAutoReveal, _, cx| {
+            if let Some(panel) = workspace.panel::<ProjectPanel>(cx) {
+                panel.update(cx, |panel, cx| panel.toggle_auto_reveal(cx));
+            }
+        });
     })
     .detach();
 }
@@ -299,6 +306,13 @@ impl ProjectPanel {
                 this.hide_scrollbar(window, cx);
             })
             .detach();
+            cx.observe(project.read(cx).diagnostic_badges(), |this, _, cx| {
+                if ProjectPanelSettings::get_global(cx).show_diagnostics != ShowDiagnostics::Off {
+                    this.update_diagnostics(cx);
+                    cx.notify();
+                }
+            })
+            .detach();
             cx.subscribe(&project, |this, project, event, cx| match event {
                 project::Event::ActiveEntryChanged(Some(entry_id)) => {
                     if ProjectPanelSettings::get_global(cx).auto_reveal_entries {
@@ -312,14 +326,6 @@ impl ProjectPanel {
                 project::Event::ActivateProjectPanel => {
                     cx.emit(PanelEvent::Activate);
                 }
-                project::Event::DiskBasedDiagnosticsFinished { .. }
-                | project::Event::DiagnosticsUpdated { .. } => {
-                    if ProjectPanelSettings::get_global(cx).show_diagnostics != ShowDiagnostics::Off
-                    {
-                        this.update_diagnostics(cx);
-                        cx.notify();
-                    }
-                }
                 project::Event::WorktreeRemoved(id) => {
                     this.expanded_dir_ids.remove(id);
                     this.update_visible_entries(None, cx);
@@ -571,61 +577,30 @@ impl ProjectPanel {
     }
 
     fn update_diagnostics(&mut self, cx: &mut Context<Self>) {
-        let mut diagnostics: HashMap<(WorktreeId, PathBuf), DiagnosticSeverity> =
-            Default::default();
         let show_diagnostics_setting = ProjectPanelSettings::get_global(cx).show_diagnostics;
-
-        if show_diagnostics_setting != ShowDiagnostics::Off {
+        self.diagnostics = if show_diagnostics_setting == ShowDiagnostics::Off {
+            HashMap::default()
+        } else {
             self.project
                 .read(cx)
-                .diagnostic_summaries(false, cx)
-                .filter_map(|(path, _, diagnostic_summary)| {
-                    if diagnostic_summary.error_count > 0 {
-                        Some((path, DiagnosticSeverity::ERROR))
-                    } else if show_diagnostics_setting == ShowDiagnostics::All
-                        && diagnostic_summary.warning_count > 0
-                    {
-                        Some((path, DiagnosticSeverity::WARNING))
+                .diagnostic_badges()
+                .read(cx)
+                .badges()
+                .filter_map(|(project_path, badge)| {
+                    let severity = if badge.error_count > 0 {
+                        DiagnosticSeverity::ERROR
+                    } else if show_diagnostics_setting == ShowDiagnostics::All {
+                        badge.max_severity()?
                     } else {
-                        None
-                    }
+                        return None;
+                    };
+                    Some((
+                        (project_path.worktree_id, project_path.path.to_path_buf()),
+                        severity,
+                    ))
                 })
-                .for_each(|(project_path, diagnostic_severity)| {
-                    let mut path_buffer = PathBuf::new();
-                    Self::update_strongest_diagnostic_severity(
-                        &mut diagnostics,
-                        &project_path,
-                        path_buffer.clone(),
-                        diagnostic_severity,
-                    );
-
-                    for component in project_path.path.components() {
-                        path_buffer.push(component);
-                        Self::update_strongest_diagnostic_severity(
-                            &mut diagnostics,
-                            &project_path,
-                            path_buffer.clone(),
-                            diagnostic_severity,
-                        );
-                    }
-                });
-        }
-        self.diagnostics = diagnostics;
-    }
-
-    fn update_strongest_diagnostic_severity(
-        diagnostics: &mut HashMap<(WorktreeId, PathBuf), DiagnosticSeverity>,
-        project_path: &ProjectPath,
-        path_buffer: PathBuf,
-        diagnostic_severity: DiagnosticSeverity,
-    ) {
-        diagnostics
-            .entry((project_path.worktree_id, path_buffer.clone()))
-            .and_modify(|strongest_diagnostic_severity| {
-                *strongest_diagnostic_severity =
-                    cmp::min(*strongest_diagnostic_severity, diagnostic_severity);
-            })
-            .or_insert(diagnostic_severity);
+                .collect()
+        };
     }
 
     fn serialize(&mut self, cx: &mut Context<Self>) {
@@ -906,6 +881,22 @@ impl ProjectPanel {
         cx.notify();
     }
 
+    /// Toggles whether the panel follows the active item, revealing its file
+    /// right away when turned on.
+    fn toggle_auto_reveal(&mut self, cx: &mut Context<Self>) {
+        let auto_reveal_entries = !ProjectPanelSettings::get_global(cx).auto_reveal_entries;
+        settings::update_settings_file::<ProjectPanelSettings>(
+            self.fs.clone(),
+            cx,
+            move |settings, _| settings.auto_reveal_entries = Some(auto_reveal_entries),
+        );
+        if auto_reveal_entries {
+            if let Some(entry_id) = self.project.read(cx).active_entry() {
+                self.reveal_entry(self.project.clone(), entry_id, true, cx);
+            }
+        }
+    }
+
     fn toggle_expanded(
         &mut self,
         entry_id: ProjectEntryId,
@@ -1099,6 +1090,20 @@ impl ProjectPanel {
         }
     }
 
+    /// Asks whether to update the imports referencing a renamed entry, before renaming it.
+    async fn confirm_import_updates(old_name: &str, cx: &mut AsyncWindowContext) -> Result<bool> {
+        let answer = cx.update(|window, cx| {
+            window.prompt(
+                PromptLevel::Info,
+                &format!("Update the imports of {old_name}?"),
+                Some("The edited files are left unsaved, and opened so that they can be reviewed."),
+                &["Update Imports", "Rename Only"],
+                cx,
+            )
+        })?;
+        Ok(answer.await == Ok(0))
+    }
+
     fn confirm_edit(
         &mut self,
         window: &mut Window,
@@ -1148,8 +1153,45 @@ impl ProjectPanel {
                 return None;
             }
             edited_entry_id = entry.id;
-            edit_task = self.project.update(cx, |project, cx| {
-                project.rename_entry(entry.id, new_path.as_path(), cx)
+            let may_update_imports = self
+                .project
+                .read(cx)
+                .rename_may_update_imports(entry.id, cx);
+            let project = self.project.clone();
+            let workspace = self.workspace.clone();
+            let old_name = entry
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            edit_task = cx.spawn_in(window, |_, mut cx| async move {
+                let update_imports =
+                    may_update_imports && Self::confirm_import_updates(&old_name, &mut cx).await?;
+                if !update_imports {
+                    return project
+                        .update(&mut cx, |project, cx| {
+                            project.rename_entry(entry.id, new_path, cx)
+                        })?
+                        .await;
+                }
+
+                let (new_entry, transaction) = project
+                    .update(&mut cx, |project, cx| {
+                        project.rename_entry_updating_imports(entry.id, new_path, cx)
+                    })?
+                    .await?;
+                if !transaction.0.is_empty() {
+                    workspace.update_in(&mut cx, |workspace, window, cx| {
+                        Editor::open_project_transaction_in_workspace(
+                            workspace,
+                            transaction,
+                            format!("Imports of {old_name}"),
+                            window,
+                            cx,
+                        );
+                    })?;
+                }
+                Ok(new_entry)
             });
         };
 
@@ -1483,26 +1525,53 @@ impl ProjectPanel {
                         )
                     }
                 };
-                Some(window.prompt(PromptLevel::Info, &prompt, None, &[operation, "Cancel"], cx))
+                let options = PromptOptions::new(PromptLevel::Info, prompt)
+                    .choice(operation)
+                    .cancel("Cancel");
+                Some(prompt_in_workspace(&self.workspace, options, window, cx))
             } else {
                 None
             };
             let next_selection = self.find_next_selection_after_deletion(items_to_delete, cx);
             cx.spawn_in(window, |panel, mut cx| async move {
                 if let Some(answer) = answer {
-                    if answer.await != Ok(0) {
+                    if answer.await.ok() != Some(0) {
                         return anyhow::Ok(());
                     }
                 }
-                for (entry_id, _) in file_paths {
-                    panel
-                        .update(&mut cx, |panel, cx| {
+                for (entry_id, name) in file_paths {
+                    let delete_entry = |trash, cx: &mut AsyncWindowContext| {
+                        panel.update(cx, |panel, cx| {
                             panel
                                 .project
                                 .update(cx, |project, cx| project.delete_entry(entry_id, trash, cx))
                                 .context("no such entry")
-                        })??
-                        .await?;
+                        })
+                    };
+                    let Err(error) = delete_entry(trash, &mut cx)??.await else {
+                        continue;
+                    };
+                    if !trash {
+                        return Err(error);
+                    }
+
+                    // Not every file system supports trashing, e.g. network or removable drives.
+                    let answer = panel.update_in(&mut cx, |panel, window, cx| {
+                        let options = PromptOptions::new(
+                            PromptLevel::Warning,
+                            format!("Could not move {name} to the trash."),
+                        )
+                        .detail(format!(
+                            "{error:#}\n\nDo you want to delete it permanently?"
+                        ))
+                        .choice("Delete Permanently")
+                        .cancel("Cancel");
+                        prompt_in_workspace(&panel.workspace, options, window, cx)
+                    })?;
+                    if answer.await.ok() != Some(0) {
+                        break;
+                    }
+                    delete_entry(false, &mut cx)??.await?;
                 }
                 panel.update_in(&mut cx, |panel, window, cx| {
                     if let Some(next_selection) = next_selection {
@@ -2586,6 +2655,14 @@ impl ProjectPanel {
                     }
                 }
             };
+            if let Some(local_worktree) = worktree.read(cx).as_local() {
+                local_worktree.set_expanded_dirs(
+                    expanded_dir_ids
+                        .iter()
+                        .filter_map(|id| snapshot.entry_for_id(*id))
+                        .map(|entry| entry.path.clone()),
+                );
+            }
 
             let mut new_entry_parent_id = None;
             let mut new_entry_kind = EntryKind::Dir;
@@ -6564,6 +6641,66 @@ mod tests {
         ensure_no_open_items_and_panes(&workspace, cx);
     }
 
+    #[gpui::test]
+    async fn test_remove_directory_with_opened_files(cx: &mut gpui::TestAppContext) {
+        init_test_with_editor(cx);
+
+        let fs = FakeFs::new(cx.executor().clone());
+        fs.insert_tree(
+            path!("/src"),
+            json!({
+                "test": {
+                    "first.rs": "// First Rust file",
+                    "second.rs": "// Second Rust file",
+                },
+                "third.rs": "// Third Rust file",
+            }),
+        )
+        .await;
+
+        let project = Project::test(fs.clone(), [path!("/src").as_ref()], cx).await;
+        let workspace =
+            cx.add_window(|window, cx| Workspace::test_new(project.clone(), window, cx));
+        let cx = &mut VisualTestContext::from_window(*workspace, cx);
+        let panel = workspace.update(cx, ProjectPanel::new).unwrap();
+
+        toggle_expand_dir(&panel, "src/test", cx);
+        select_path(&panel, "src/test/first.rs", cx);
+        panel.update_in(cx, |panel, window, cx| panel.open(&Open, window, cx));
+        cx.executor().run_until_parked();
+        workspace
+            .update(cx, |workspace, window, cx| {
+                let editor = workspace
+                    .active_item_as::<Editor>(cx)
+                    .expect("Open item should be an editor");
+                editor.update(cx, |editor, cx| editor.set_text("Unsaved text", window, cx));
+            })
+            .unwrap();
+        select_path(&panel, "src/test/second.rs", cx);
+        panel.update_in(cx, |panel, window, cx| panel.open(&Open, window, cx));
+        cx.executor().run_until_parked();
+        workspace
+            .read_with(cx, |workspace, cx| {
+                assert_eq!(workspace.active_pane().read(cx).items_len(), 2);
+            })
+            .unwrap();
+
+        // Both files are closed, including the one with unsaved changes, which the prompt
+        // warned about.
+        select_path(&panel, "src/test", cx);
+        submit_deletion(&panel, cx);
+        assert_eq!(
+            visible_entries_as_strings(&panel, 0..10, cx),
+            &["v src", "      third.rs  <== selected"]
+        );
+        ensure_no_open_items_and_panes(&workspace, cx);
+        workspace
+            .read_with(cx, |workspace, cx| {
+                assert_eq!(workspace.active_pane().read(cx).items_len(), 0);
+            })
+            .unwrap();
+    }
+
     #[gpui::test]
     async fn test_create_duplicate_items(cx: &mut gpui::TestAppContext) {
         init_test_with_editor(cx);
@@ -7286,6 +7423,54 @@ mod tests {
         );
     }
 
+    #[gpui::test]
+    async fn test_rename_updating_imports(cx: &mut gpui::TestAppContext) {
+        init_test_with_editor(cx);
+
+        let fs = FakeFs::new(cx.executor().clone());
+        fs.insert_tree(
+            "/root",
+            json!({
+                "a.ts": "export const a = 1;\n",
+                "main.ts": "import { a } from \"./a\";\n",
+            }),
+        )
+        .await;
+
+        let project = Project::test(fs.clone(), ["/root".as_ref()], cx).await;
+        let workspace =
+            cx.add_window(|window, cx| Workspace::test_new(project.clone(), window, cx));
+        let cx = &mut VisualTestContext::from_window(*workspace, cx);
+        let panel = workspace.update(cx, ProjectPanel::new).unwrap();
+
+        select_path(&panel, "root/a.ts", cx);
+        panel.update_in(cx, |panel, window, cx| panel.rename(&Rename, window, cx));
+        let confirm = panel.update_in(cx, |panel, window, cx| {
+            panel
+                .filename_editor
+                .update(cx, |editor, cx| editor.set_text("b.ts", window, cx));
+            panel.confirm_edit(window, cx).unwrap()
+        });
+        cx.run_until_parked();
+
+        // Nothing is renamed or edited until the user chose whether to update the imports.
+        assert!(fs.is_file(Path::new("/root/a.ts")).await);
+        cx.simulate_prompt_answer(0);
+        confirm.await.unwrap();
+        assert!(fs.is_file(Path::new("/root/b.ts")).await);
+
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_local_buffer("/root/main.ts", cx)
+            })
+            .await
+            .unwrap();
+        buffer.read_with(cx, |buffer, _| {
+            assert_eq!(buffer.text(), "import { a } from \"./b\";\n");
+            assert!(buffer.is_dirty());
+        });
+    }
+
     #[gpui::test]
     #[cfg_attr(target_os = "windows", ignore)]
     async fn test_rename_root_of_worktree(cx: &mut gpui::TestAppContext) {
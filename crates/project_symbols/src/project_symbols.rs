@@ -1,15 +1,15 @@
 use editor::{scroll::Autoscroll, styled_runs_for_code_label, Bias, Editor};
 use fuzzy::{StringMatch, StringMatchCandidate};
 use gpui::{
-    rems, App, Context, DismissEvent, Entity, FontWeight, ParentElement, StyledText, Task,
-    WeakEntity, Window,
+    div, rems, AnyElement, App, Context, DismissEvent, Entity, FontWeight, IntoElement,
+    ParentElement, Styled, StyledText, Task, WeakEntity, Window,
 };
 use ordered_float::OrderedFloat;
 use picker::{Picker, PickerDelegate};
 use project::{Project, Symbol};
-use std::{borrow::Cow, cmp::Reverse, sync::Arc};
+use std::{borrow::Cow, cmp::Reverse, sync::Arc, time::Duration};
 use theme::ActiveTheme;
-use util::ResultExt;
+use util::{ResultExt, TryFutureExt};
 use workspace::{
     ui::{v_flex, Color, Label, LabelCommon, LabelLike, ListItem, ListItemSpacing, Toggleable},
     Workspace,
@@ -35,6 +35,8 @@ pub fn init(cx: &mut App) {
 
 pub type ProjectSymbols = Entity<Picker<ProjectSymbolsDelegate>>;
 
+const PREVIEW_DEBOUNCE: Duration = Duration::from_millis(50);
+
 pub struct ProjectSymbolsDelegate {
     workspace: WeakEntity<Workspace>,
     project: Entity<Project>,
@@ -44,6 +46,13 @@ pub struct ProjectSymbolsDelegate {
     external_match_candidates: Vec<StringMatchCandidate>,
     show_worktree_root_name: bool,
     matches: Vec<StringMatch>,
+    /// A read-only editor showing the highlighted symbol. It lives outside of any
+    /// pane, so peeking at results neither opens tabs nor records navigation
+    /// history until the user confirms a match. Only this picker needs one: the
+    /// outline picker's matches are in the active editor, which it scrolls to
+    /// and restores on dismissal instead.
+    preview: Option<Entity<Editor>>,
+    update_preview: Task<Option<()>>,
 }
 
 impl ProjectSymbolsDelegate {
@@ -57,9 +66,66 @@ impl ProjectSymbolsDelegate {
             external_match_candidates: Default::default(),
             matches: Default::default(),
             show_worktree_root_name: false,
+            preview: None,
+            update_preview: Task::ready(None),
         }
     }
 
+    fn update_preview(&mut self, window: &mut Window, cx: &mut Context<Picker<Self>>) {
+        let Some(symbol) = self
+            .matches
+            .get(self.selected_match_index)
+            .map(|mat| self.symbols[mat.candidate_id].clone())
+        else {
+            self.preview = None;
+            self.update_preview = Task::ready(None);
+            return;
+        };
+
+        let project = self.project.clone();
+        self.update_preview = cx.spawn_in(window, |picker, mut cx| {
+            async move {
+                cx.background_executor().timer(PREVIEW_DEBOUNCE).await;
+                let buffer = project
+                    .update(&mut cx, |project, cx| {
+                        project.open_buffer_for_symbol(&symbol, cx)
+                    })?
+                    .await?;
+                picker.update_in(&mut cx, |picker, window, cx| {
+                    let position = buffer
+                        .read(cx)
+                        .clip_point_utf16(symbol.range.start, Bias::Left);
+                    let delegate = &mut picker.delegate;
+                    let editor = match &delegate.preview {
+                        Some(editor)
+                            if editor.read(cx).buffer().read(cx).as_singleton().as_ref()
+                                == Some(&buffer) =>
+                        {
+                            editor.clone()
+                        }
+                        _ => {
+                            let editor = cx.new(|cx| {
+                                let mut editor =
+                                    Editor::for_buffer(buffer, Some(project), window, cx);
+                                editor.set_read_only(true);
+                                editor
+                            });
+                            delegate.preview = Some(editor.clone());
+                            editor
+                        }
+                    };
+                    editor.update(cx, |editor, cx| {
+                        editor.change_selections(Some(Autoscroll::center()), window, cx, |s| {
+                            s.select_ranges([position..position])
+                        });
+                    });
+                    cx.notify();
+                })
+            }
+            .log_err()
+        });
+    }
+
     fn filter(&mut self, query: &str, window: &mut Window, cx: &mut Context<Picker<Self>>) {
         const MAX_MATCHES: usize = 100;
         let mut visible_matches = cx.background_executor().block(fuzzy::match_strings(
@@ -159,10 +225,11 @@ impl PickerDelegate for ProjectSymbolsDelegate {
     fn set_selected_index(
         &mut self,
         ix: usize,
-        _window: &mut Window,
-        _cx: &mut Context<Picker<Self>>,
+        window: &mut Window,
+        cx: &mut Context<Picker<Self>>,
     ) {
         self.selected_match_index = ix;
+        self.update_preview(window, cx);
     }
 
     fn update_matches(
@@ -260,6 +327,22 @@ impl PickerDelegate for ProjectSymbolsDelegate {
                 ),
         )
     }
+
+    fn render_footer(
+        &self,
+        _window: &mut Window,
+        cx: &mut Context<Picker<Self>>,
+    ) -> Option<AnyElement> {
+        let preview = self.preview.clone()?;
+        Some(
+            div()
+                .h(rems(16.))
+                .border_t_1()
+                .border_color(cx.theme().colors().border_variant)
+                .child(preview)
+                .into_any_element(),
+        )
+    }
 }
 
 #[cfg(test)]
@@ -388,6 +471,16 @@ mod tests {
             assert_eq!(delegate.matches[1].string, "one");
         });
 
+        // Highlighting a match previews it without opening it in a pane.
+        cx.executor().advance_clock(PREVIEW_DEBOUNCE);
+        cx.run_until_parked();
+        symbols.update(cx, |symbols, _| {
+            assert!(symbols.delegate.preview.is_some());
+        });
+        workspace.update(cx, |workspace, cx| {
+            assert_eq!(workspace.active_pane().read(cx).items_len(), 0);
+        });
+
         // Spawn more updates such that in the end, there are again no matches.
         symbols.update_in(cx, |p, window, cx| {
             p.update_matches("o".to_string(), window, cx);
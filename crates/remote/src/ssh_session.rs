@@ -393,6 +393,10 @@ impl State {
         matches!(self, Self::Reconnecting { .. })
     }
 
+    fn is_connected(&self) -> bool {
+        matches!(self, Self::Connected { .. })
+    }
+
     fn heartbeat_recovered(self) -> Self {
         match self {
             Self::HeartbeatMissed {
@@ -476,6 +480,9 @@ pub struct SshRemoteClient {
 #[derive(Debug)]
 pub enum SshRemoteEvent {
     Disconnected,
+    /// The connection was re-established after being lost, and the messages
+    /// that weren't acknowledged by the server were sent again.
+    Reconnected,
 }
 
 impl EventEmitter<SshRemoteEvent> for SshRemoteClient {}
@@ -791,7 +798,10 @@ impl SshRemoteClient {
                     }
                 });
 
-                if this.state_is(State::is_reconnect_failed) {
+                if this.state_is(State::is_connected) {
+                    cx.emit(SshRemoteEvent::Reconnected);
+                    Ok(())
+                } else if this.state_is(State::is_reconnect_failed) {
                     this.reconnect(cx)
                 } else if this.state_is(State::is_reconnect_exhausted) {
                     Ok(())
@@ -5,7 +5,7 @@ use extension_host::headless_host::HeadlessExtensionStore;
 use fs::Fs;
 use gpui::{App, AppContext as _, AsyncApp, Context, Entity, PromptLevel};
 use http_client::HttpClient;
-use language::{proto::serialize_operation, Buffer, BufferEvent, LanguageRegistry};
+use language::{proto::serialize_operation, Buffer, BufferEvent, BufferId, LanguageRegistry};
 use node_runtime::NodeRuntime;
 use project::{
     buffer_store::{BufferStore, BufferStoreEvent},
@@ -190,7 +190,9 @@ impl HeadlessProject {
         client.add_request_handler(cx.weak_entity(), Self::handle_remove_worktree);
 
         client.add_entity_request_handler(Self::handle_open_buffer_by_path);
+        client.add_entity_request_handler(Self::handle_open_buffer_by_id);
         client.add_entity_request_handler(Self::handle_open_new_buffer);
+        client.add_entity_request_handler(Self::handle_synchronize_buffers);
         client.add_entity_request_handler(Self::handle_find_search_candidates);
         client.add_entity_request_handler(Self::handle_open_server_settings);
 
@@ -434,6 +436,42 @@ impl HeadlessProject {
         })
     }
 
+    pub async fn handle_open_buffer_by_id(
+        this: Entity<Self>,
+        message: TypedEnvelope<proto::OpenBufferById>,
+        mut cx: AsyncApp,
+    ) -> Result<proto::OpenBufferResponse> {
+        let buffer_id = BufferId::new(message.payload.id)?;
+        this.update(&mut cx, |this, cx| {
+            this.buffer_store.update(cx, |buffer_store, cx| {
+                let buffer = buffer_store
+                    .get(buffer_id)
+                    .ok_or_else(|| anyhow!("unknown buffer id {}", buffer_id))?;
+                buffer_store
+                    .create_buffer_for_peer(&buffer, SSH_PEER_ID, cx)
+                    .detach_and_log_err(cx);
+                anyhow::Ok(())
+            })
+        })??;
+
+        Ok(proto::OpenBufferResponse {
+            buffer_id: buffer_id.to_proto(),
+        })
+    }
+
+    pub async fn handle_synchronize_buffers(
+        this: Entity<Self>,
+        message: TypedEnvelope<proto::SynchronizeBuffers>,
+        mut cx: AsyncApp,
+    ) -> Result<proto::SynchronizeBuffersResponse> {
+        this.update(&mut cx, |this, cx| {
+            let client = this.session.clone();
+            this.buffer_store.update(cx, |buffer_store, cx| {
+                buffer_store.handle_synchronize_buffers(message, SSH_PEER_ID, cx, client)
+            })
+        })?
+    }
+
     pub async fn handle_open_new_buffer(
         this: Entity<Self>,
         _message: TypedEnvelope<proto::OpenNewBuffer>,
@@ -23,8 +23,10 @@ use serde_json::json;
 use settings::{initial_server_settings_content, Settings, SettingsLocation, SettingsStore};
 use smol::stream::StreamExt;
 use std::{
+    cell::Cell,
     collections::HashSet,
     path::{Path, PathBuf},
+    rc::Rc,
     sync::Arc,
 };
 use unindent::Unindent as _;
@@ -1061,7 +1063,7 @@ async fn test_reconnect(cx: &mut TestAppContext, server_cx: &mut TestAppContext)
     )
     .await;
 
-    let (project, _headless) = init_test(&fs, cx, server_cx).await;
+    let (project, headless) = init_test(&fs, cx, server_cx).await;
 
     let (worktree, _) = project
         .update(cx, |project, cx| {
@@ -1084,6 +1086,16 @@ async fn test_reconnect(cx: &mut TestAppContext, server_cx: &mut TestAppContext)
         buffer.edit([(ix..ix + 1, "100")], None, cx);
     });
 
+    let reconnections = Rc::new(Cell::new(0));
+    let _subscription = cx.update(|cx| {
+        let reconnections = reconnections.clone();
+        cx.subscribe(&project, move |_, event, _| {
+            if let project::Event::ReconnectedToSshRemote = event {
+                reconnections.set(reconnections.get() + 1);
+            }
+        })
+    });
+
     let client = cx.read(|cx| project.read(cx).ssh_client().unwrap());
     client
         .update(cx, |client, cx| client.simulate_disconnect(cx))
@@ -1100,6 +1112,20 @@ async fn test_reconnect(cx: &mut TestAppContext, server_cx: &mut TestAppContext)
             .unwrap(),
         "fn one() -> usize { 100 }"
     );
+    cx.run_until_parked();
+    assert_eq!(reconnections.get(), 1);
+
+    // The buffers were synchronized with the server after reconnecting.
+    server_cx.run_until_parked();
+    let buffer_id = buffer.read_with(cx, |buffer, _| buffer.remote_id());
+    headless.update(server_cx, |headless, cx| {
+        let buffer_store = headless.buffer_store.read(cx);
+        assert!(buffer_store.has_shared_buffers());
+        assert_eq!(
+            buffer_store.get(buffer_id).unwrap().read(cx).text(),
+            "fn one() -> usize { 100 }"
+        );
+    });
 }
 
 #[gpui::test]
@@ -8,7 +8,9 @@ use fs::{Fs, RealFs};
 use futures::channel::mpsc;
 use futures::{select, select_biased, AsyncRead, AsyncWrite, AsyncWriteExt, FutureExt, SinkExt};
 use git::GitHostingProviderRegistry;
-use gpui::{App, AppContext as _, Context, Entity, SemanticVersion, UpdateGlobal as _};
+use gpui::{
+    supervised_task_name, App, AppContext as _, Context, Entity, SemanticVersion, UpdateGlobal as _,
+};
 use gpui_tokio::Tokio;
 use http_client::{read_proxy_from_env, Uri};
 use language::LanguageRegistry;
@@ -173,6 +175,8 @@ fn init_panic_hook() {
             system_id: None,            // Set on SSH client
             installation_id: None,      // Set on SSH client
             session_id: "".to_string(), // Set on SSH client
+            // The panics of supervised tasks are recovered from by their owners.
+            recovered: supervised_task_name().is_some(),
         };
 
         if let Some(panic_data_json) = serde_json::to_string(&panic_data).log_err() {
@@ -189,7 +193,9 @@ fn init_panic_hook() {
             }
         }
 
-        std::process::abort();
+        if !panic_data.recovered {
+            std::process::abort();
+        }
     }));
 }
 
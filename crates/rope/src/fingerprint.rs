@@ -0,0 +1,137 @@
+use std::ops::{Add, AddAssign};
+
+/// The Mersenne prime 2^61 - 1.
+const MODULUS: u64 = (1 << 61) - 1;
+
+const BASE: u64 = 0x100_0000_01b3;
+
+/// A polynomial hash of a text's bytes.
+///
+/// Fingerprints are maintained in the rope's summaries: the fingerprint of a
+/// concatenation is derived from the fingerprints of its parts, so it doesn't
+/// depend on how the text is split into chunks. Two replicas can therefore
+/// compare fingerprints to cheaply check that they contain the same text.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Fingerprint {
+    hash: u64,
+    /// `BASE` raised to the number of hashed bytes.
+    base_power: u64,
+}
+
+impl Fingerprint {
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let hash = bytes.iter().fold(0, |hash, byte| {
+            reduce(hash as u128 * BASE as u128 + *byte as u128 + 1)
+        });
+        Self {
+            hash,
+            base_power: pow_mod(BASE, bytes.len() as u64),
+        }
+    }
+
+    /// Returns the hash as a number, e.g. to send it to another replica.
+    pub fn to_u64(self) -> u64 {
+        self.hash
+    }
+}
+
+impl Default for Fingerprint {
+    fn default() -> Self {
+        Self {
+            hash: 0,
+            base_power: 1,
+        }
+    }
+}
+
+impl<'a> AddAssign<&'a Self> for Fingerprint {
+    fn add_assign(&mut self, other: &'a Self) {
+        self.hash = add_mod(mul_mod(self.hash, other.base_power), other.hash);
+        self.base_power = mul_mod(self.base_power, other.base_power);
+    }
+}
+
+impl Add for Fingerprint {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self::Output {
+        self += &other;
+        self
+    }
+}
+
+fn add_mod(a: u64, b: u64) -> u64 {
+    let sum = a + b;
+    if sum >= MODULUS {
+        sum - MODULUS
+    } else {
+        sum
+    }
+}
+
+fn mul_mod(a: u64, b: u64) -> u64 {
+    reduce(a as u128 * b as u128)
+}
+
+fn pow_mod(mut base: u64, mut exponent: u64) -> u64 {
+    let mut power = 1;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            power = mul_mod(power, base);
+        }
+        base = mul_mod(base, base);
+        exponent >>= 1;
+    }
+    power
+}
+
+/// Reduces a number lower than `2^122` modulo [`MODULUS`], folding its high bits onto its
+/// low bits since `2^61` is congruent to `1`, instead of dividing.
+fn reduce(value: u128) -> u64 {
+    let folded = (value & MODULUS as u128) as u64 + (value >> 61) as u64;
+    let folded = (folded & MODULUS) + (folded >> 61);
+    if folded >= MODULUS {
+        folded - MODULUS
+    } else {
+        folded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_concatenation() {
+        let text = "fn main() {\n    println!(\"héllo\");\n}\n";
+        let whole = Fingerprint::from_bytes(text.as_bytes());
+        for split in 0..=text.len() {
+            let (left, right) = text.as_bytes().split_at(split);
+            assert_eq!(
+                Fingerprint::from_bytes(left) + Fingerprint::from_bytes(right),
+                whole
+            );
+        }
+        assert_eq!(Fingerprint::default() + whole, whole);
+        assert_ne!(
+            Fingerprint::from_bytes(b"ab"),
+            Fingerprint::from_bytes(b"ba")
+        );
+        assert_ne!(Fingerprint::from_bytes(b"\0"), Fingerprint::default());
+    }
+
+    #[test]
+    fn test_reduce() {
+        let max = MODULUS as u128 - 1;
+        for value in [
+            0,
+            1,
+            MODULUS as u128,
+            max,
+            max * max,
+            max * BASE as u128 + 256,
+        ] {
+            assert_eq!(reduce(value), (value % MODULUS as u128) as u64);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 mod chunk;
+mod fingerprint;
 mod offset_utf16;
 mod point;
 mod point_utf16;
@@ -15,6 +16,7 @@ use std::{
 use sum_tree::{Bias, Dimension, SumTree};
 
 pub use chunk::ChunkSlice;
+pub use fingerprint::Fingerprint;
 pub use offset_utf16::OffsetUtf16;
 pub use point::Point;
 pub use point_utf16::PointUtf16;
@@ -225,6 +227,12 @@ impl Rope {
         self.chunks.summary().text
     }
 
+    /// Returns a hash of the rope's contents, which is maintained as the rope
+    /// is edited and thus doesn't require reading the whole text.
+    pub fn fingerprint(&self) -> Fingerprint {
+        self.chunks.summary().fingerprint
+    }
+
     pub fn len(&self) -> usize {
         self.chunks.extent(&())
     }
@@ -945,6 +953,7 @@ impl sum_tree::Item for Chunk {
     fn summary(&self, _cx: &()) -> Self::Summary {
         ChunkSummary {
             text: self.as_slice().text_summary(),
+            fingerprint: Fingerprint::from_bytes(self.text.as_bytes()),
         }
     }
 }
@@ -952,6 +961,7 @@ impl sum_tree::Item for Chunk {
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct ChunkSummary {
     text: TextSummary,
+    fingerprint: Fingerprint,
 }
 
 impl sum_tree::Summary for ChunkSummary {
@@ -963,6 +973,7 @@ impl sum_tree::Summary for ChunkSummary {
 
     fn add_summary(&mut self, summary: &Self, _: &()) {
         self.text += &summary.text;
+        self.fingerprint += &summary.fingerprint;
     }
 }
 
@@ -1560,6 +1571,10 @@ mod tests {
             expected.replace_range(start_ix..end_ix, &new_text);
 
             assert_eq!(actual.text(), expected);
+            assert_eq!(
+                actual.fingerprint(),
+                Fingerprint::from_bytes(expected.as_bytes())
+            );
             log::info!("text: {:?}", expected);
 
             for _ in 0..5 {
@@ -1,3 +1,4 @@
+mod regex_tester;
 mod registrar;
 
 use crate::{
@@ -14,9 +15,11 @@ use editor::{
 use futures::channel::oneshot;
 use gpui::{
     actions, div, impl_actions, Action, App, ClickEvent, Context, Entity, EventEmitter,
-    FocusHandle, Focusable, Hsla, InteractiveElement as _, IntoElement, KeyContext,
-    ParentElement as _, Render, ScrollHandle, Styled, Subscription, Task, TextStyle, Window,
+    FocusHandle, Focusable, HighlightStyle, Hsla, InteractiveElement as _, IntoElement, KeyContext,
+    ParentElement as _, PromptLevel, Render, ScrollHandle, Styled, Subscription, Task, TextStyle,
+    UnderlineStyle, Window,
 };
+use language::{Bias, Point};
 use project::{
     search::SearchQuery,
     search_history::{SearchHistory, SearchHistoryCursor},
@@ -24,7 +27,7 @@ use project::{
 use schemars::JsonSchema;
 use serde::Deserialize;
 use settings::Settings;
-use std::sync::Arc;
+use std::{any::TypeId, sync::Arc, time::Instant};
 use theme::ThemeSettings;
 
 use ui::{
@@ -38,11 +41,17 @@ use workspace::{
     ToolbarItemEvent, ToolbarItemLocation, ToolbarItemView, Workspace,
 };
 
+use regex_tester::{
+    capture_previews, RegexError, RegexTester, CAPTURE_PREVIEW_ROWS, SLOW_MATCH_DURATION,
+};
 pub use registrar::DivRegistrar;
 use registrar::{ForDeployed, ForDismissed, SearchActionsRegistrar, WithResults};
 
 const MAX_BUFFER_SEARCH_HISTORY_SIZE: usize = 50;
 
+/// Above how many matches turning them into cursors must be confirmed.
+const MAX_MATCHES_TO_SELECT_WITHOUT_CONFIRMATION: usize = 1000;
+
 #[derive(PartialEq, Clone, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct Deploy {
@@ -56,7 +65,19 @@ pub struct Deploy {
 
 impl_actions!(buffer_search, [Deploy]);
 
-actions!(buffer_search, [DeployReplace, Dismiss, FocusEditor]);
+actions!(
+    buffer_search,
+    [
+        DeployReplace,
+        Dismiss,
+        FocusEditor,
+        SelectAllMatchesAndDismiss,
+        ToggleRegexTester
+    ]
+);
+
+/// The highlight of the part of the query a regex error points at.
+enum RegexErrorHighlight {}
 
 impl Deploy {
     pub fn find() -> Self {
@@ -100,6 +121,8 @@ pub struct BufferSearchBar {
     default_options: SearchOptions,
     configured_options: SearchOptions,
     query_contains_error: bool,
+    /// Feedback on the regex being typed, if the regex tester is enabled.
+    regex_tester: Option<RegexTester>,
     dismissed: bool,
     search_history: SearchHistory,
     search_history_cursor: SearchHistoryCursor,
@@ -111,6 +134,72 @@ pub struct BufferSearchBar {
 }
 
 impl BufferSearchBar {
+    fn render_regex_tester(&self, regex_tester: &RegexTester, cx: &App) -> impl IntoElement {
+        let content = if let Some(error) = regex_tester.error.as_ref() {
+            let message = match error.range.as_ref() {
+                Some(range) => {
+                    let query = self.query(cx);
+                    let column = query[..range.start.min(query.len())].chars().count() + 1;
+                    format!("Column {column}: {}", error.message)
+                }
+                None => error.message.clone(),
+            };
+            h_flex().child(
+                Label::new(message)
+                    .size(LabelSize::Small)
+                    .color(Color::Error),
+            )
+        } else if regex_tester.captures.is_empty() {
+            h_flex().child(
+                Label::new("No match near the cursor")
+                    .size(LabelSize::Small)
+                    .color(Color::Muted),
+            )
+        } else {
+            h_flex()
+                .gap_3()
+                .overflow_x_hidden()
+                .children(regex_tester.captures.iter().map(|capture| {
+                    h_flex()
+                        .gap_1()
+                        .child(
+                            Label::new(capture.label.clone())
+                                .size(LabelSize::Small)
+                                .color(Color::Muted),
+                        )
+                        .child(match capture.text.as_ref() {
+                            Some(text) => Label::new(format!("{text:?}")).size(LabelSize::Small),
+                            None => Label::new("unmatched")
+                                .size(LabelSize::Small)
+                                .color(Color::Disabled),
+                        })
+                }))
+        };
+
+        h_flex().gap_3().px_2().child(content).when_some(
+            regex_tester.slow_match_duration,
+            |this, duration| {
+                this.child(
+                    h_flex()
+                        .gap_1()
+                        .child(
+                            Icon::new(IconName::Warning)
+                                .size(IconSize::Small)
+                                .color(Color::Warning),
+                        )
+                        .child(
+                            Label::new(format!(
+                                "Slow pattern: matching took {}ms",
+                                duration.as_millis()
+                            ))
+                            .size(LabelSize::Small)
+                            .color(Color::Warning),
+                        ),
+                )
+            },
+        )
+    }
+
     fn render_text_input(
         &self,
         editor: &Entity<Editor>,
@@ -407,6 +496,12 @@ impl Render for BufferSearchBar {
                     }),
             );
 
+        let regex_tester_line = self
+            .regex_tester
+            .as_ref()
+            .filter(|_| self.search_options.contains(SearchOptions::REGEX))
+            .map(|regex_tester| self.render_regex_tester(regex_tester, cx));
+
         let replace_line = should_show_replace_input.then(|| {
             h_flex()
                 .gap_2()
@@ -488,6 +583,7 @@ impl Render for BufferSearchBar {
             })
             .when(self.supported_options(cx).regex, |this| {
                 this.on_action(cx.listener(Self::toggle_regex))
+                    .on_action(cx.listener(Self::toggle_regex_tester))
             })
             .when(self.supported_options(cx).selection, |this| {
                 this.on_action(cx.listener(Self::toggle_selection))
@@ -510,6 +606,7 @@ impl Render for BufferSearchBar {
                     .w_full()
                 },
             ))
+            .children(regex_tester_line)
             .children(replace_line)
     }
 }
@@ -626,6 +723,15 @@ impl BufferSearchBar {
                 }
             },
         ));
+        registrar.register_handler(WithResults(
+            |this, action: &SelectAllMatchesAndDismiss, window, cx| {
+                if this.supported_options(cx).find_in_results {
+                    cx.propagate();
+                } else {
+                    this.select_all_matches_and_dismiss(action, window, cx);
+                }
+            },
+        ));
         registrar.register_handler(ForDeployed(
             |this, _: &editor::actions::Cancel, window, cx| {
                 this.dismiss(&Dismiss, window, cx);
@@ -676,6 +782,7 @@ impl BufferSearchBar {
             search_options,
             pending_search: None,
             query_contains_error: false,
+            regex_tester: None,
             dismissed: true,
             search_history: SearchHistory::new(
                 Some(MAX_BUFFER_SEARCH_HISTORY_SIZE),
@@ -970,6 +1077,51 @@ impl BufferSearchBar {
         }
     }
 
+    /// Turns the matches into selections, like `SelectAllMatches`, and closes the search bar.
+    /// When there are many matches, asks for confirmation first.
+    fn select_all_matches_and_dismiss(
+        &mut self,
+        _: &SelectAllMatchesAndDismiss,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.dismissed || self.active_match_index.is_none() {
+            return;
+        }
+        let Some(match_count) = self.active_searchable_item.as_ref().and_then(|item| {
+            self.searchable_items_with_matches
+                .get(&item.downgrade())
+                .map(|matches| matches.len())
+        }) else {
+            return;
+        };
+
+        if match_count <= MAX_MATCHES_TO_SELECT_WITHOUT_CONFIRMATION {
+            self.select_all_matches(&SelectAllMatches, window, cx);
+            self.dismiss(&Dismiss, window, cx);
+            return;
+        }
+
+        let answer = window.prompt(
+            PromptLevel::Warning,
+            &format!("Add a cursor at each of the {match_count} matches?"),
+            Some("Editing with this many cursors may be slow."),
+            &["Add Cursors", "Cancel"],
+            cx,
+        );
+        cx.spawn_in(window, |this, mut cx| async move {
+            if answer.await != Ok(0) {
+                return;
+            }
+            this.update_in(&mut cx, |this, window, cx| {
+                this.select_all_matches(&SelectAllMatches, window, cx);
+                this.dismiss(&Dismiss, window, cx);
+            })
+            .ok();
+        })
+        .detach();
+    }
+
     pub fn select_match(
         &mut self,
         direction: Direction,
@@ -1158,6 +1310,7 @@ impl BufferSearchBar {
         let (done_tx, done_rx) = oneshot::channel();
         let query = self.query(cx);
         self.pending_search.take();
+        self.set_regex_error(None, cx);
 
         if let Some(active_searchable_item) = self.active_searchable_item.as_ref() {
             self.query_contains_error = false;
@@ -1173,7 +1326,7 @@ impl BufferSearchBar {
                 } else {
                     if self.search_options.contains(SearchOptions::REGEX) {
                         match SearchQuery::regex(
-                            query,
+                            &query,
                             self.search_options.contains(SearchOptions::WHOLE_WORD),
                             self.search_options.contains(SearchOptions::CASE_SENSITIVE),
                             false,
@@ -1184,6 +1337,12 @@ impl BufferSearchBar {
                             Ok(query) => query.with_replacement(self.replacement(cx)),
                             Err(_) => {
                                 self.query_contains_error = true;
+                                // Report the error of the query as typed, since whole-word
+                                // searches wrap it in word boundaries.
+                                let error = fancy_regex::Regex::new(&query)
+                                    .err()
+                                    .map(|error| RegexError::new(&query, &error));
+                                self.set_regex_error(error, cx);
                                 self.clear_active_searchable_item_matches(window, cx);
                                 cx.notify();
                                 return done_rx;
@@ -1214,13 +1373,21 @@ impl BufferSearchBar {
                 self.active_search = Some(query.clone());
                 let query_text = query.as_str().to_string();
 
+                let search_started_at = Instant::now();
                 let matches = active_searchable_item.find_matches(query, window, cx);
 
                 let active_searchable_item = active_searchable_item.downgrade();
                 self.pending_search = Some(cx.spawn_in(window, |this, mut cx| async move {
                     let matches = matches.await;
+                    let match_duration = search_started_at.elapsed();
 
                     this.update_in(&mut cx, |this, window, cx| {
+                        let is_regex = this.search_options.contains(SearchOptions::REGEX);
+                        if let Some(regex_tester) = this.regex_tester.as_mut() {
+                            regex_tester.slow_match_duration = (is_regex
+                                && match_duration >= SLOW_MATCH_DURATION)
+                                .then_some(match_duration);
+                        }
                         if let Some(active_searchable_item) =
                             WeakSearchableItemHandle::upgrade(active_searchable_item.as_ref(), cx)
                         {
@@ -1266,6 +1433,118 @@ impl BufferSearchBar {
             self.active_match_index = new_index;
             cx.notify();
         }
+        self.update_capture_previews(cx);
+    }
+
+    fn toggle_regex_tester(
+        &mut self,
+        _: &ToggleRegexTester,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.regex_tester.take().is_some() {
+            self.query_editor.update(cx, |query_editor, cx| {
+                query_editor.clear_highlights::<RegexErrorHighlight>(cx)
+            });
+        } else {
+            self.regex_tester = Some(RegexTester::default());
+            self.enable_search_option(SearchOptions::REGEX, window, cx);
+            drop(self.update_matches(false, window, cx));
+        }
+        cx.notify();
+    }
+
+    /// Shows the error of the regex in the query editor, underlining the part of the query
+    /// it points at.
+    fn set_regex_error(&mut self, error: Option<RegexError>, cx: &mut Context<Self>) {
+        let Some(regex_tester) = self.regex_tester.as_mut() else {
+            return;
+        };
+        let range = error.as_ref().and_then(|error| error.range.clone());
+        regex_tester.error = error;
+
+        let error_color = Color::Error.color(cx);
+        self.query_editor.update(cx, |query_editor, cx| {
+            let Some(mut range) = range else {
+                query_editor.clear_highlights::<RegexErrorHighlight>(cx);
+                return;
+            };
+            let snapshot = query_editor.buffer().read(cx).snapshot(cx);
+            // Errors at the end of the query point at its last character.
+            if range.is_empty() {
+                range.start = snapshot
+                    .reversed_chars_at(range.start)
+                    .next()
+                    .map_or(range.start, |c| range.start - c.len_utf8());
+            }
+            query_editor.highlight_text::<RegexErrorHighlight>(
+                vec![snapshot.anchor_after(range.start)..snapshot.anchor_before(range.end)],
+                HighlightStyle {
+                    underline: Some(UnderlineStyle {
+                        color: Some(error_color),
+                        thickness: px(1.),
+                        wavy: true,
+                    }),
+                    ..HighlightStyle::default()
+                },
+                cx,
+            );
+        });
+    }
+
+    /// Previews the capture groups of the regex match nearest the cursor of the searched editor,
+    /// matching the regex around the cursor in the background.
+    fn update_capture_previews(&mut self, cx: &mut Context<Self>) {
+        let Some(regex_tester) = self.regex_tester.as_mut() else {
+            return;
+        };
+        let regex = match self.active_search.as_deref() {
+            Some(SearchQuery::Regex { regex, .. }) => regex.clone(),
+            _ => {
+                regex_tester.captures.clear();
+                regex_tester.captures_task = None;
+                return;
+            }
+        };
+        let Some(editor) = self
+            .active_searchable_item
+            .as_ref()
+            .and_then(|item| item.act_as_type(TypeId::of::<Editor>(), cx))
+            .and_then(|editor| editor.downcast::<Editor>().ok())
+        else {
+            regex_tester.captures.clear();
+            regex_tester.captures_task = None;
+            return;
+        };
+
+        let (text, cursor) = editor.update(cx, |editor, cx| {
+            let cursor = editor.selections.newest::<Point>(cx).head();
+            let snapshot = editor.buffer().read(cx).snapshot(cx);
+            let start = Point::new(cursor.row.saturating_sub(CAPTURE_PREVIEW_ROWS), 0);
+            let end = snapshot.clip_point(
+                Point::new(cursor.row + CAPTURE_PREVIEW_ROWS, u32::MAX),
+                Bias::Left,
+            );
+            let text = snapshot.text_for_range(start..end).collect::<String>();
+            let cursor = snapshot.point_to_offset(cursor) - snapshot.point_to_offset(start);
+            (text, cursor)
+        });
+        let captures = cx
+            .background_executor()
+            .spawn(async move { capture_previews(&regex, &text, cursor) });
+        regex_tester.captures_task = Some(cx.spawn(|this, mut cx| async move {
+            let captures = captures.await;
+            this.update(&mut cx, |this, cx| {
+                if let Some(regex_tester) = this.regex_tester.as_mut() {
+                    regex_tester.captures_task = None;
+                    if captures != regex_tester.captures {
+                        regex_tester.captures = captures;
+                        cx.notify();
+                    }
+                }
+            })
+            .ok();
+        }));
     }
 
     fn tab(&mut self, _: &Tab, window: &mut Window, cx: &mut Context<Self>) {
@@ -2033,6 +2312,58 @@ mod tests {
             .unwrap();
     }
 
+    #[gpui::test]
+    async fn test_search_select_all_matches_and_dismiss(cx: &mut TestAppContext) {
+        init_globals(cx);
+        let many_matches = MAX_MATCHES_TO_SELECT_WITHOUT_CONFIRMATION + 1;
+        let buffer_text = format!("{}c", "ab\n".repeat(many_matches));
+        let buffer = cx.new(|cx| Buffer::local(buffer_text, cx));
+        let cx = cx.add_empty_window();
+        let editor =
+            cx.new_window_entity(|window, cx| Editor::for_buffer(buffer.clone(), None, window, cx));
+        let search_bar = cx.new_window_entity(|window, cx| {
+            let mut search_bar = BufferSearchBar::new(window, cx);
+            search_bar.set_active_pane_item(Some(&editor), window, cx);
+            search_bar
+        });
+        let search = |query: &'static str, cx: &mut VisualTestContext| {
+            search_bar.update_in(cx, |search_bar, window, cx| {
+                search_bar.show(window, cx);
+                search_bar.search(query, None, window, cx)
+            })
+        };
+        let select_all_and_dismiss = |cx: &mut VisualTestContext| {
+            search_bar.update_in(cx, |search_bar, window, cx| {
+                search_bar.activate_current_match(window, cx);
+                search_bar.select_all_matches_and_dismiss(&SelectAllMatchesAndDismiss, window, cx);
+            });
+            cx.run_until_parked();
+        };
+        let selection_count =
+            |cx: &mut VisualTestContext| editor.update(cx, |editor, cx| editor.selections.count());
+
+        search("c", cx).await.unwrap();
+        select_all_and_dismiss(cx);
+        assert!(!cx.has_pending_prompt());
+        assert_eq!(selection_count(cx), 1);
+        assert!(search_bar.read_with(cx, |search_bar, _| search_bar.is_dismissed()));
+
+        // Turning many matches into cursors must be confirmed.
+        search("a", cx).await.unwrap();
+        select_all_and_dismiss(cx);
+        assert!(cx.has_pending_prompt());
+        cx.simulate_prompt_answer(1);
+        cx.run_until_parked();
+        assert_eq!(selection_count(cx), 1);
+        assert!(!search_bar.read_with(cx, |search_bar, _| search_bar.is_dismissed()));
+
+        select_all_and_dismiss(cx);
+        cx.simulate_prompt_answer(0);
+        cx.run_until_parked();
+        assert_eq!(selection_count(cx), many_matches);
+        assert!(search_bar.read_with(cx, |search_bar, _| search_bar.is_dismissed()));
+    }
+
     #[gpui::test]
     async fn test_search_query_with_match_whole_word(cx: &mut TestAppContext) {
         init_globals(cx);
@@ -2552,6 +2883,20 @@ mod tests {
                     Point::new(2, 0)..Point::new(2, 3),
                 ]
             );
+            assert_eq!(
+                editor.out_of_scope_search_highlights(cx),
+                &[
+                    Point::new(0, 0)..Point::new(0, 3),
+                    Point::new(0, 8)..Point::new(0, 11),
+                    Point::new(2, 8)..Point::new(2, 11),
+                    Point::new(3, 0)..Point::new(3, 3),
+                    Point::new(3, 8)..Point::new(3, 11),
+                    Point::new(4, 0)..Point::new(4, 3),
+                    Point::new(4, 8)..Point::new(4, 11),
+                    Point::new(5, 0)..Point::new(5, 3),
+                    Point::new(5, 8)..Point::new(5, 11),
+                ]
+            );
         });
     }
 
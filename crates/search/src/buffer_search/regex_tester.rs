@@ -0,0 +1,238 @@
+//! Feedback on the regex typed in the buffer search bar: where it fails to parse, what its
+//! capture groups match around the cursor, and whether matching it is slow.
+
+use fancy_regex::Regex;
+use gpui::Task;
+use std::{
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+/// Matching the whole item for longer than this is reported as a slow pattern.
+pub(crate) const SLOW_MATCH_DURATION: Duration = Duration::from_millis(300);
+
+/// How many rows around the cursor are searched for the match whose captures are shown.
+pub(crate) const CAPTURE_PREVIEW_ROWS: u32 = 50;
+
+/// The maximum number of matches visited when looking for the match nearest the cursor.
+const MAX_PREVIEW_MATCHES: usize = 1000;
+
+/// How long matches are visited when looking for the match nearest the cursor, after which
+/// the nearest one found so far is shown.
+const MAX_PREVIEW_DURATION: Duration = Duration::from_millis(50);
+
+/// The maximum length of the text shown for a capture group.
+const MAX_CAPTURE_PREVIEW_LEN: usize = 40;
+
+#[derive(Default)]
+pub(crate) struct RegexTester {
+    pub(crate) error: Option<RegexError>,
+    pub(crate) captures: Vec<CapturePreview>,
+    /// Matches the regex in the background to update `captures`.
+    pub(crate) captures_task: Option<Task<()>>,
+    pub(crate) slow_match_duration: Option<Duration>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct RegexError {
+    pub(crate) message: String,
+    /// The byte range of the query the error points at, if known.
+    pub(crate) range: Option<Range<usize>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct CapturePreview {
+    /// `$0` for the whole match, then `$1`, `$2`… or the name of the group.
+    pub(crate) label: String,
+    /// The text matched by the group, or `None` if it didn't participate in the match.
+    pub(crate) text: Option<String>,
+}
+
+impl RegexError {
+    /// Describes why the query failed to compile, locating the error in the query when
+    /// the regex engines report where it is.
+    pub(crate) fn new(query: &str, error: &fancy_regex::Error) -> Self {
+        if let fancy_regex::Error::ParseError(offset, error) = error {
+            let end = query[*offset..]
+                .chars()
+                .next()
+                .map_or(*offset, |c| offset + c.len_utf8());
+            return Self {
+                message: error.to_string(),
+                range: Some(*offset..end),
+            };
+        }
+
+        // Errors in the parts of the pattern compiled by the `regex` crate aren't located by
+        // `fancy_regex`, so parse them again to get the location from the error's diagram.
+        if let Err(regex::Error::Syntax(diagram)) = regex::Regex::new(query) {
+            if let Some(error) = Self::from_diagram(query, &diagram) {
+                return error;
+            }
+        }
+        Self {
+            message: error.to_string(),
+            range: None,
+        }
+    }
+
+    /// Parses the error diagram of the `regex` crate:
+    ///
+    /// ```text
+    /// regex parse error:
+    ///     a(b
+    ///      ^
+    /// error: unclosed group
+    /// ```
+    fn from_diagram(query: &str, diagram: &str) -> Option<Self> {
+        const INDENT: usize = 4;
+
+        let message = diagram
+            .lines()
+            .find_map(|line| line.strip_prefix("error: "))?
+            .to_string();
+        // Multi-line patterns are shown with line numbers, which this doesn't account for.
+        if query.contains('\n') {
+            return Some(Self {
+                message,
+                range: None,
+            });
+        }
+
+        let carets = diagram.lines().find(|line| {
+            line.trim_start().starts_with('^') && line.trim().chars().all(|c| c == '^')
+        });
+        let range = carets.and_then(|carets| {
+            let column = (carets.len() - carets.trim_start().len()).checked_sub(INDENT)?;
+            let len = carets.trim().chars().count();
+            let mut offsets = query
+                .char_indices()
+                .map(|(offset, _)| offset)
+                .chain([query.len()]);
+            let start = offsets.nth(column)?;
+            let end = offsets.nth(len.saturating_sub(1)).unwrap_or(query.len());
+            Some(start..end.max(start))
+        });
+        Some(Self { message, range })
+    }
+}
+
+/// Returns the capture groups of the match nearest to `cursor` in `text`, among the matches
+/// found within [`MAX_PREVIEW_MATCHES`] and [`MAX_PREVIEW_DURATION`].
+pub(crate) fn capture_previews(regex: &Regex, text: &str, cursor: usize) -> Vec<CapturePreview> {
+    let started_at = Instant::now();
+    let distance = |range: &Range<usize>| {
+        if range.contains(&cursor) || range.start == cursor {
+            0
+        } else if range.end <= cursor {
+            cursor - range.end
+        } else {
+            range.start - cursor
+        }
+    };
+
+    let mut nearest = None::<(usize, fancy_regex::Captures)>;
+    for captures in regex.captures_iter(text).take(MAX_PREVIEW_MATCHES) {
+        let Ok(captures) = captures else {
+            break;
+        };
+        let Some(range) = captures.get(0).map(|whole| whole.range()) else {
+            continue;
+        };
+        let distance = distance(&range);
+        if nearest
+            .as_ref()
+            .map_or(true, |(nearest_distance, _)| distance < *nearest_distance)
+        {
+            nearest = Some((distance, captures));
+        }
+        if range.start > cursor || started_at.elapsed() > MAX_PREVIEW_DURATION {
+            break;
+        }
+    }
+
+    let Some((_, captures)) = nearest else {
+        return Vec::new();
+    };
+    regex
+        .capture_names()
+        .enumerate()
+        .map(|(ix, name)| CapturePreview {
+            label: match name {
+                Some(name) => format!("${name}"),
+                None => format!("${ix}"),
+            },
+            text: captures.get(ix).map(|capture| truncate(capture.as_str())),
+        })
+        .collect()
+}
+
+fn truncate(text: &str) -> String {
+    if text.chars().count() <= MAX_CAPTURE_PREVIEW_LEN {
+        text.to_string()
+    } else {
+        let mut truncated = text
+            .chars()
+            .take(MAX_CAPTURE_PREVIEW_LEN - 1)
+            .collect::<String>();
+        truncated.push('…');
+        truncated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regex_errors() {
+        let query = "ab(c";
+        let error = RegexError::new(query, &Regex::new(query).unwrap_err());
+        assert!(error.range.is_some());
+
+        let diagram = "regex parse error:\n    ab[z-a]\n       ^^^\nerror: invalid character class range, the start must be <= the end";
+        assert_eq!(
+            RegexError::from_diagram("ab[z-a]", diagram),
+            Some(RegexError {
+                message: "invalid character class range, the start must be <= the end".into(),
+                range: Some(3..6),
+            })
+        );
+
+        // Columns are counted in characters.
+        let diagram = "regex parse error:\n    é\\q\n     ^^\nerror: unrecognized escape sequence";
+        assert_eq!(
+            RegexError::from_diagram("é\\q", diagram).and_then(|error| error.range),
+            Some(2..4)
+        );
+    }
+
+    #[test]
+    fn test_capture_previews() {
+        let regex = Regex::new(r"(?<key>\w+)=(\d+)?").unwrap();
+        let text = "a=1 bb= ccc=333";
+        let labels_and_texts = |cursor| {
+            capture_previews(&regex, text, cursor)
+                .into_iter()
+                .map(|preview| (preview.label, preview.text))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            labels_and_texts(5),
+            [
+                ("$0".to_string(), Some("bb=".to_string())),
+                ("$key".to_string(), Some("bb".to_string())),
+                ("$2".to_string(), None),
+            ]
+        );
+        assert_eq!(
+            labels_and_texts(text.len()),
+            [
+                ("$0".to_string(), Some("ccc=333".to_string())),
+                ("$key".to_string(), Some("ccc".to_string())),
+                ("$2".to_string(), Some("333".to_string())),
+            ]
+        );
+    }
+}
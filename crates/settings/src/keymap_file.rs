@@ -3,11 +3,13 @@ use collections::{BTreeMap, HashMap, IndexMap};
 use fs::Fs;
 use gpui::{
     Action, ActionBuildError, App, InvalidKeystrokeError, KeyBinding, KeyBindingContextPredicate,
-    NoAction, SharedString, KEYSTROKE_PARSE_EXPECTED_MESSAGE,
+    KeyBindingMetaIndex, NoAction, SharedString, KEYSTROKE_PARSE_EXPECTED_MESSAGE,
 };
 use schemars::{
     gen::{SchemaGenerator, SchemaSettings},
-    schema::{ArrayValidation, InstanceType, Schema, SchemaObject, SubschemaValidation},
+    schema::{
+        ArrayValidation, InstanceType, ObjectValidation, Schema, SchemaObject, SubschemaValidation,
+    },
     JsonSchema,
 };
 use serde::Deserialize;
@@ -92,7 +94,8 @@ impl KeymapSection {
 }
 
 /// Keymap action as a JSON value, since it can either be null for no action, or the name of the
-/// action, or an array of the name of the action and the action input.
+/// action, or an array of the name of the action and the action input, or an object with the name
+/// of the action in its `action` field and the action input in its `args` field.
 ///
 /// Unlike the other json types involved in keymaps (including actions), this doc-comment will not
 /// be included in the generated JSON schema, as it manually defines its `JsonSchema` impl. The
@@ -143,6 +146,37 @@ pub enum KeymapFileLoadResult {
     },
 }
 
+/// The keymap a binding was loaded from. It is attached to the loaded bindings as their
+/// [`KeyBindingMetaIndex`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeybindSource {
+    User,
+    Default,
+    Base,
+    Vim,
+}
+
+impl KeybindSource {
+    pub fn name(self) -> &'static str {
+        match self {
+            KeybindSource::User => "User",
+            KeybindSource::Default => "Default",
+            KeybindSource::Base => "Base",
+            KeybindSource::Vim => "Vim",
+        }
+    }
+
+    pub fn meta(self) -> KeyBindingMetaIndex {
+        KeyBindingMetaIndex(self as u32)
+    }
+
+    pub fn from_meta(meta: KeyBindingMetaIndex) -> Option<Self> {
+        [Self::User, Self::Default, Self::Base, Self::Vim]
+            .into_iter()
+            .find(|source| source.meta() == meta)
+    }
+}
+
 impl KeymapFile {
     pub fn parse(content: &str) -> anyhow::Result<Self> {
         parse_json_with_comments::<Self>(content)
@@ -346,11 +380,35 @@ impl KeymapFile {
                     Some(action_input_string),
                 )
             }
+            Value::Object(fields) => {
+                let Some(Value::String(name)) = fields.get("action") else {
+                    return Err(format!(
+                        "expected an object of the form `{{\"action\": name, \"args\": input}}`, \
+                        but the `action` field is missing or not a string in {}.",
+                        MarkdownString::inline_code(&action.0.to_string())
+                    ));
+                };
+                if let Some(field) = fields.keys().find(|key| *key != "action" && *key != "args") {
+                    return Err(format!(
+                        "unrecognized field {} in {}. Only `action` and `args` are supported.",
+                        inline_code_string(field),
+                        MarkdownString::inline_code(&action.0.to_string())
+                    ));
+                }
+                match fields.get("args") {
+                    Some(action_input) => (
+                        cx.build_action(&name, Some(action_input.clone())),
+                        Some(action_input.to_string()),
+                    ),
+                    None => (cx.build_action(&name, None), None),
+                }
+            }
             Value::String(name) => (cx.build_action(&name, None), None),
             Value::Null => (Ok(NoAction.boxed_clone()), None),
             _ => {
                 return Err(format!(
-                    "expected two-element array of `[name, input]`. \
+                    "expected an action name, a two-element array of `[name, input]`, \
+                    or an object of the form `{{\"action\": name, \"args\": input}}`. \
                     Instead found {}.",
                     MarkdownString::inline_code(&action.0.to_string())
                 ));
@@ -376,7 +434,8 @@ impl KeymapFile {
                 }
                 None => {
                     return Err(format!(
-                        "can't build {} action - it requires input data via [name, input]: {}",
+                        "can't build {} action - it requires input data via [name, input] \
+                        or an `args` field: {}",
                         inline_code_string(&name),
                         MarkdownString::escape(&error.to_string())
                     ))
@@ -540,6 +599,25 @@ impl KeymapFile {
                     if let Some(new_name) = deprecation {
                         add_deprecation_preferred_name(&mut matches_action_name, new_name);
                     }
+                    // Add an alternative for actions with data specified as an
+                    // `{"action": name, "args": data}` object.
+                    let action_with_args = SchemaObject {
+                        instance_type: set(InstanceType::Object),
+                        object: set(ObjectValidation {
+                            properties: [
+                                ("action".to_string(), matches_action_name.clone().into()),
+                                ("args".to_string(), schema.clone().into()),
+                            ]
+                            .into_iter()
+                            .collect(),
+                            required: ["action".to_string(), "args".to_string()]
+                                .into_iter()
+                                .collect(),
+                            additional_properties: Some(Box::new(Schema::Bool(false))),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    };
                     let action_with_input = SchemaObject {
                         instance_type: set(InstanceType::Array),
                         array: set(ArrayValidation {
@@ -551,6 +629,7 @@ impl KeymapFile {
                         ..Default::default()
                     };
                     keymap_action_alternatives.push(action_with_input.into());
+                    keymap_action_alternatives.push(action_with_args.into());
                 }
             }
         }
@@ -612,7 +691,20 @@ fn inline_code_string(text: &str) -> MarkdownString {
 
 #[cfg(test)]
 mod tests {
-    use crate::KeymapFile;
+    use crate::{KeymapFile, KeymapFileLoadResult};
+    use gpui::{impl_actions, TestAppContext};
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+
+    #[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    struct MoveLines {
+        direction: String,
+        #[serde(default)]
+        count: u32,
+    }
+
+    impl_actions!(test_keymap, [MoveLines]);
 
     #[test]
     fn can_deserialize_keymap_with_trailing_comma() {
@@ -628,4 +720,62 @@ mod tests {
         };
         KeymapFile::parse(json).unwrap();
     }
+
+    #[gpui::test]
+    fn test_action_args(cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            let json = indoc::indoc! {r#"[
+                  {
+                    "bindings": {
+                      "alt-up": { "action": "test_keymap::MoveLines", "args": { "direction": "up", "count": 5 } },
+                      "alt-down": ["test_keymap::MoveLines", { "direction": "down" }]
+                    }
+                  }
+                ]"#
+            };
+            let KeymapFileLoadResult::Success { key_bindings } = KeymapFile::load(json, cx) else {
+                panic!("expected the keymap to load");
+            };
+            let actions = key_bindings
+                .iter()
+                .map(|binding| binding.action().as_any().downcast_ref::<MoveLines>().cloned())
+                .collect::<Vec<_>>();
+            assert_eq!(
+                actions,
+                [
+                    Some(MoveLines {
+                        direction: "up".into(),
+                        count: 5
+                    }),
+                    Some(MoveLines {
+                        direction: "down".into(),
+                        count: 0
+                    })
+                ]
+            );
+
+            let json = indoc::indoc! {r#"[
+                  {
+                    "bindings": {
+                      "alt-up": { "action": "test_keymap::MoveLines", "args": { "direction": 1 } },
+                      "alt-down": { "action": "test_keymap::MoveLines", "arguments": {} },
+                      "alt-left": { "args": {} }
+                    }
+                  }
+                ]"#
+            };
+            let KeymapFileLoadResult::SomeFailedToLoad {
+                key_bindings,
+                error_message,
+            } = KeymapFile::load(json, cx)
+            else {
+                panic!("expected the keymap to fail to load");
+            };
+            assert!(key_bindings.is_empty());
+            let error_message = error_message.0;
+            assert!(error_message.contains("can't build"), "{error_message}");
+            assert!(error_message.contains("unrecognized field"), "{error_message}");
+            assert!(error_message.contains("`action` field is missing"), "{error_message}");
+        });
+    }
 }
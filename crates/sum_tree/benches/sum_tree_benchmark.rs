@@ -0,0 +1,88 @@
+use std::ops::Range;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use sum_tree::{Bias, Item, SumTree, Summary};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+struct Count(usize);
+
+impl Summary for Count {
+    type Context = ();
+
+    fn zero(_: &()) -> Self {
+        Count(0)
+    }
+
+    fn add_summary(&mut self, summary: &Self, _: &()) {
+        self.0 += summary.0;
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Number(u32);
+
+impl Item for Number {
+    type Summary = Count;
+
+    fn summary(&self, _: &()) -> Count {
+        Count(1)
+    }
+}
+
+fn generate_random_ranges(mut rng: StdRng, len: usize) -> Vec<Range<Count>> {
+    let range_len = 64;
+    (0..1000)
+        .map(|_| {
+            let start = rng.gen_range(0..len - range_len);
+            Count(start)..Count(start + range_len)
+        })
+        .collect()
+}
+
+fn sum_tree_benchmarks(c: &mut Criterion) {
+    static SIZES: &[usize] = &[10_000, 100_000, 1_000_000];
+
+    let mut group = c.benchmark_group("items in range");
+    for size in SIZES.iter() {
+        let rng = StdRng::seed_from_u64(*size as u64);
+        let tree = SumTree::from_iter((0..*size as u32).map(Number), &());
+        let ranges = generate_random_ranges(rng, *size);
+        group.throughput(Throughput::Elements(ranges.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("cursor", size), &tree, |b, tree| {
+            b.iter(|| {
+                for range in &ranges {
+                    let mut cursor = tree.cursor::<Count>(&());
+                    cursor.seek(&range.start, Bias::Right, &());
+                    let mut sum = 0;
+                    while let Some(item) = cursor.item() {
+                        if *cursor.start() >= range.end {
+                            break;
+                        }
+                        sum += item.0;
+                        cursor.next(&());
+                    }
+                    black_box(sum);
+                }
+            });
+        });
+
+        group.bench_with_input(BenchmarkId::new("iter_items_in", size), &tree, |b, tree| {
+            b.iter(|| {
+                for range in &ranges {
+                    let sum = tree
+                        .iter_items_in::<Count, _>(range.clone(), &())
+                        .map(|(item, _)| item.0)
+                        .sum::<u32>();
+                    black_box(sum);
+                }
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, sum_tree_benchmarks);
+criterion_main!(benches);
@@ -645,6 +645,82 @@ where
     }
 }
 
+/// An iterator over the items intersecting a range of a [`SumTree`], yielding each
+/// item along with the cursor's position at its start.
+///
+/// The cursor seeks to the start of the range once, skipping the subtrees before it
+/// based on their summaries, and the iteration stops at the first item starting at
+/// or after the end of the range. Unlike slicing, this doesn't allocate.
+///
+/// Iterators that read the items in step with another iterator can also look at the
+/// current item with [`ItemsIn::item`], and seek to another range without creating
+/// a new cursor.
+pub struct ItemsIn<'a, T: Item, D, Target> {
+    cursor: Cursor<'a, T, D>,
+    end: Target,
+    cx: &'a <T::Summary as Summary>::Context,
+}
+
+impl<'a, T, D, Target> ItemsIn<'a, T, D, Target>
+where
+    T: Item,
+    D: Dimension<'a, T::Summary>,
+    Target: SeekTarget<'a, T::Summary, D>,
+{
+    pub(crate) fn new(
+        tree: &'a SumTree<T>,
+        range: Range<Target>,
+        cx: &'a <T::Summary as Summary>::Context,
+    ) -> Self {
+        let mut cursor = tree.cursor::<D>(cx);
+        cursor.seek(&range.start, Bias::Right, cx);
+        Self {
+            cursor,
+            end: range.end,
+            cx,
+        }
+    }
+
+    /// Returns the item the iteration is at, or `None` once it's past the end of the range.
+    pub fn item(&self) -> Option<&'a T> {
+        let item = self.cursor.item()?;
+        (self.end.cmp(self.cursor.start(), self.cx) == Ordering::Greater).then_some(item)
+    }
+
+    /// Returns the position of the start of the current item.
+    pub fn start(&self) -> &D {
+        self.cursor.start()
+    }
+
+    /// Returns the position of the end of the current item.
+    pub fn end(&self) -> D {
+        self.cursor.end(self.cx)
+    }
+
+    /// Restarts the iteration at the start of another range, which may be before the
+    /// current item.
+    pub fn seek(&mut self, range: Range<Target>) {
+        self.cursor.seek(&range.start, Bias::Right, self.cx);
+        self.end = range.end;
+    }
+}
+
+impl<'a, T, D, Target> Iterator for ItemsIn<'a, T, D, Target>
+where
+    T: Item,
+    D: Dimension<'a, T::Summary>,
+    Target: SeekTarget<'a, T::Summary, D>,
+{
+    type Item = (&'a T, D);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.item()?;
+        let start = self.cursor.start().clone();
+        self.cursor.next(self.cx);
+        Some((item, start))
+    }
+}
+
 pub struct FilterCursor<'a, F, T: Item, D> {
     cursor: Cursor<'a, T, D>,
     filter_node: F,
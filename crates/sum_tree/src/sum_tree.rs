@@ -2,11 +2,11 @@ mod cursor;
 mod tree_map;
 
 use arrayvec::ArrayVec;
-pub use cursor::{Cursor, FilterCursor, Iter};
+pub use cursor::{Cursor, FilterCursor, ItemsIn, Iter};
 use rayon::prelude::*;
 use std::marker::PhantomData;
 use std::mem;
-use std::{cmp::Ordering, fmt, iter::FromIterator, sync::Arc};
+use std::{cmp::Ordering, fmt, iter::FromIterator, ops::Range, sync::Arc};
 pub use tree_map::{MapSeekTarget, TreeMap, TreeSet};
 
 #[cfg(test)]
@@ -382,6 +382,20 @@ impl<T: Item> SumTree<T> {
         Cursor::new(self, cx)
     }
 
+    /// Returns the items intersecting the given range, along with the position
+    /// of their start in the `D` dimension. See [`ItemsIn`].
+    pub fn iter_items_in<'a, D, Target>(
+        &'a self,
+        range: Range<Target>,
+        cx: &'a <T::Summary as Summary>::Context,
+    ) -> ItemsIn<'a, T, D, Target>
+    where
+        D: Dimension<'a, T::Summary>,
+        Target: SeekTarget<'a, T::Summary, D>,
+    {
+        ItemsIn::new(self, range, cx)
+    }
+
     /// Note: If the summary type requires a non `()` context, then the filter cursor
     /// that is returned cannot be used with Rust's iterators.
     pub fn filter<'a, F, U>(
@@ -1376,6 +1390,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_iter_items_in() {
+        let tree = SumTree::<u8>::from_iter(1..=10, &());
+        let items_in = |range: Range<Count>| {
+            tree.iter_items_in::<IntegersSummary, _>(range, &())
+                .map(|(item, start)| (*item, start.count))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(items_in(Count(2)..Count(5)), vec![(3, 2), (4, 3), (5, 4)]);
+        assert_eq!(items_in(Count(8)..Count(20)), vec![(9, 8), (10, 9)]);
+        assert_eq!(items_in(Count(3)..Count(3)), vec![]);
+        assert_eq!(items_in(Count(20)..Count(30)), vec![]);
+
+        // Items are yielded from the one containing the start of the range.
+        assert_eq!(
+            tree.iter_items_in::<Sum, _>(Sum(4)..Sum(10), &())
+                .map(|(item, start)| (*item, start.0))
+                .collect::<Vec<_>>(),
+            vec![(3, 3), (4, 6)]
+        );
+
+        // The current item can be read before advancing, and the iteration restarted.
+        let mut items = tree.iter_items_in::<Count, _>(Count(5)..Count(7), &());
+        assert_eq!(items.item(), Some(&6));
+        assert_eq!((items.start().0, items.end().0), (5, 6));
+        items.seek(Count(0)..Count(2));
+        assert_eq!(items.map(|(item, _)| *item).collect::<Vec<_>>(), vec![1, 2]);
+    }
+
     #[test]
     fn test_edit() {
         let mut tree = SumTree::<u8>::default();
@@ -13,7 +13,8 @@ use task::{ResolvedTask, RevealTarget, TaskContext, TaskTemplate};
 use ui::{
     div, h_flex, v_flex, ActiveTheme, Button, ButtonCommon, ButtonSize, Clickable, Color,
     FluentBuilder as _, Icon, IconButton, IconButtonShape, IconName, IconSize, IntoElement,
-    KeyBinding, LabelSize, ListItem, ListItemSpacing, RenderOnce, Toggleable, Tooltip,
+    KeyBinding, Label, LabelCommon as _, LabelSize, ListItem, ListItemSpacing, RenderOnce,
+    Toggleable, Tooltip,
 };
 use util::ResultExt;
 use workspace::{tasks::schedule_resolved_task, ModalView, Workspace};
@@ -350,6 +351,23 @@ impl PickerDelegate for TasksModalDelegate {
                 tooltip_label_text.push_str(&resolved.command_label);
             }
         }
+        let provenance = match source_kind {
+            TaskSourceKind::Detected {
+                manifest_path,
+                detector,
+                ..
+            } => {
+                if !tooltip_label_text.trim().is_empty() {
+                    tooltip_label_text.push('\n');
+                }
+                tooltip_label_text.push_str(&format!(
+                    "Detected from {}",
+                    manifest_path.to_string_lossy()
+                ));
+                Some(detector.clone())
+            }
+            _ => None,
+        };
         let tooltip_label = if tooltip_label_text.trim().is_empty() {
             None
         } else {
@@ -369,6 +387,9 @@ impl PickerDelegate for TasksModalDelegate {
             TaskSourceKind::Language { name } => file_icons::FileIcons::get(cx)
                 .get_icon_for_type(&name.to_lowercase(), cx)
                 .map(Icon::from_path),
+            TaskSourceKind::Detected { manifest_path, .. } => {
+                file_icons::FileIcons::get_icon(manifest_path, cx).map(Icon::from_path)
+            }
         }
         .map(|icon| icon.color(Color::Muted).size(IconSize::Small));
         let history_run_icon = if Some(ix) <= self.divider_index {
@@ -430,7 +451,18 @@ impl PickerDelegate for TasksModalDelegate {
                     item
                 })
                 .toggle_state(selected)
-                .child(highlighted_location.render(window, cx)),
+                .child(
+                    h_flex()
+                        .gap_2()
+                        .child(highlighted_location.render(window, cx))
+                        .when_some(provenance, |this, provenance| {
+                            this.child(
+                                Label::new(provenance)
+                                    .size(LabelSize::Small)
+                                    .color(Color::Muted),
+                            )
+                        }),
+                ),
         )
     }
 
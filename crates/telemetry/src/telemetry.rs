@@ -1,7 +1,10 @@
 //! See [Telemetry in Zed](https://zed.dev/docs/telemetry) for additional information.
 use futures::channel::mpsc;
 pub use serde_json;
-use std::sync::OnceLock;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    OnceLock,
+};
 pub use telemetry_events::FlexibleEvent as Event;
 
 /// Macro to create telemetry events and send them to the telemetry queue.
@@ -9,7 +12,8 @@ pub use telemetry_events::FlexibleEvent as Event;
 /// By convention, the name should be "Noun Verbed", e.g. "Keymap Changed"
 /// or "Project Diagnostics Opened".
 ///
-/// The properties can be any value that implements serde::Serialize.
+/// The properties can be any value that implements serde::Serialize. They aren't
+/// evaluated when telemetry is disabled.
 ///
 /// ```
 /// telemetry::event!("Keymap Changed", version = "1.0.0");
@@ -18,25 +22,29 @@ pub use telemetry_events::FlexibleEvent as Event;
 #[macro_export]
 macro_rules! event {
     ($name:expr) => {{
-        let event = $crate::Event {
-            event_type: $name.to_string(),
-            event_properties: std::collections::HashMap::new(),
-        };
-        $crate::send_event(event);
+        if $crate::is_enabled() {
+            let event = $crate::Event {
+                event_type: $name.to_string(),
+                event_properties: std::collections::HashMap::new(),
+            };
+            $crate::send_event(event);
+        }
     }};
     ($name:expr, $($key:ident $(= $value:expr)?),+ $(,)?) => {{
-        let event = $crate::Event {
-            event_type: $name.to_string(),
-            event_properties: std::collections::HashMap::from([
-                $(
-                    (stringify!($key).to_string(),
-                        $crate::serde_json::value::to_value(&$crate::serialize_property!($key $(= $value)?))
-                            .unwrap_or_else(|_| $crate::serde_json::to_value(&()).unwrap())
-                    ),
-                )+
-            ]),
-        };
-        $crate::send_event(event);
+        if $crate::is_enabled() {
+            let event = $crate::Event {
+                event_type: $name.to_string(),
+                event_properties: std::collections::HashMap::from([
+                    $(
+                        (stringify!($key).to_string(),
+                            $crate::serde_json::value::to_value(&$crate::serialize_property!($key $(= $value)?))
+                                .unwrap_or_else(|_| $crate::serde_json::to_value(&()).unwrap())
+                        ),
+                    )+
+                ]),
+            };
+            $crate::send_event(event);
+        }
     }};
 }
 
@@ -51,6 +59,9 @@ macro_rules! serialize_property {
 }
 
 pub fn send_event(event: Event) {
+    if !is_enabled() {
+        return;
+    }
     if let Some(queue) = TELEMETRY_QUEUE.get() {
         queue.unbounded_send(event).ok();
         return;
@@ -61,4 +72,15 @@ pub fn init(tx: mpsc::UnboundedSender<Event>) {
     TELEMETRY_QUEUE.set(tx).ok();
 }
 
+/// Enables or disables the collection of events. Events sent while telemetry is
+/// disabled are dropped immediately rather than being queued.
+pub fn set_enabled(enabled: bool) {
+    TELEMETRY_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    TELEMETRY_ENABLED.load(Ordering::Relaxed)
+}
+
+static TELEMETRY_ENABLED: AtomicBool = AtomicBool::new(true);
 static TELEMETRY_QUEUE: OnceLock<mpsc::UnboundedSender<Event>> = OnceLock::new();
@@ -286,6 +286,9 @@ pub struct Panic {
     pub installation_id: Option<String>,
     /// Identifier unique to each Zed session (differs for each time you open Zed)
     pub session_id: String,
+    /// Whether the panic stopped a supervised task that was recovered from, rather than Zed
+    #[serde(default)]
+    pub recovered: bool,
 }
 
 #[derive(Serialize, Deserialize)]
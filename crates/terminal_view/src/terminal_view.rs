@@ -571,7 +571,10 @@ impl TerminalView {
 
     ///Attempt to paste the clipboard into the terminal
     fn paste(&mut self, _: &Paste, _: &mut Window, cx: &mut Context<Self>) {
-        if let Some(clipboard_string) = cx.read_from_clipboard().and_then(|item| item.text()) {
+        if let Some(clipboard_string) = cx
+            .read_from_clipboard()
+            .and_then(|item| item.text_or_paths())
+        {
             self.terminal
                 .update(cx, |terminal, _cx| terminal.paste(&clipboard_string));
         }
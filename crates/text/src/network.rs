@@ -6,6 +6,8 @@ use collections::{BTreeMap, HashSet};
 pub struct Network<T: Clone, R: rand::Rng> {
     inboxes: BTreeMap<ReplicaId, Vec<Envelope<T>>>,
     disconnected_peers: HashSet<ReplicaId>,
+    drop_probability: f64,
+    dropped_message_count: usize,
     rng: R,
 }
 
@@ -19,6 +21,8 @@ impl<T: Clone, R: rand::Rng> Network<T, R> {
         Network {
             inboxes: BTreeMap::default(),
             disconnected_peers: HashSet::default(),
+            drop_probability: 0.,
+            dropped_message_count: 0,
             rng,
         }
     }
@@ -50,6 +54,17 @@ impl<T: Clone, R: rand::Rng> Network<T, R> {
             .insert(new_replica_id, self.inboxes[&old_replica_id].clone());
     }
 
+    /// Makes every message sent from now on get lost with the given probability,
+    /// independently for each receiver. Peers are expected to recover the lost
+    /// operations by synchronizing with each other.
+    pub fn set_drop_probability(&mut self, drop_probability: f64) {
+        self.drop_probability = drop_probability;
+    }
+
+    pub fn dropped_message_count(&self) -> usize {
+        self.dropped_message_count
+    }
+
     pub fn is_idle(&self) -> bool {
         self.inboxes.values().all(|i| i.is_empty())
     }
@@ -63,6 +78,11 @@ impl<T: Clone, R: rand::Rng> Network<T, R> {
         for (replica, inbox) in self.inboxes.iter_mut() {
             if *replica != sender && !self.disconnected_peers.contains(replica) {
                 for message in &messages {
+                    if self.drop_probability > 0. && self.rng.gen_bool(self.drop_probability) {
+                        self.dropped_message_count += 1;
+                        continue;
+                    }
+
                     // Insert one or more duplicates of this message, potentially *before* the previous
                     // message sent by this peer to simulate out-of-order delivery.
                     for _ in 0..self.rng.gen_range(1..4) {
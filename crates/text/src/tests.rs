@@ -623,6 +623,63 @@ fn test_history() {
     assert_eq!(buffer.text(), "X12cde6");
 }
 
+#[test]
+fn test_transaction_metadata() {
+    let mut buffer = Buffer::new(0, BufferId::new(1).unwrap(), "abc".into());
+    buffer.set_group_interval(Duration::from_secs(0));
+
+    let manual_transaction = buffer.start_transaction().unwrap();
+    buffer.edit([(3..3, "d")]);
+    buffer.end_transaction();
+    assert_eq!(
+        buffer.transaction_metadata(manual_transaction),
+        Some(&TransactionMetadata::default())
+    );
+
+    let format_transaction = buffer.start_transaction().unwrap();
+    buffer.edit([(0..0, " ")]);
+    buffer.end_transaction();
+    let formatter = TransactionMetadata {
+        command: Some("editor::Format".into()),
+        tool: Some("formatter".into()),
+    };
+    buffer.set_transaction_metadata(format_transaction, formatter.clone());
+
+    let rename_transaction = buffer.start_transaction().unwrap();
+    buffer.edit([(1..2, "x")]);
+    buffer.end_transaction();
+    buffer.set_transaction_metadata(
+        rename_transaction,
+        TransactionMetadata {
+            command: None,
+            tool: Some("rename".into()),
+        },
+    );
+    assert_eq!(buffer.text(), " xbcd");
+    assert_eq!(
+        buffer.transaction_metadata(format_transaction),
+        Some(&formatter)
+    );
+    assert_eq!(buffer.history.undo_stack[1].author(), Some(0));
+
+    // Only the edits made by tools since the last manual edit are undone.
+    let operations = buffer.undo_to_last_manual_edit();
+    assert_eq!(operations.len(), 2);
+    assert_eq!(buffer.text(), "abcd");
+
+    // The metadata is kept while the transactions are on the redo stack.
+    assert_eq!(
+        buffer.transaction_metadata(format_transaction),
+        Some(&formatter)
+    );
+    buffer.redo();
+    assert_eq!(buffer.text(), " abcd");
+
+    buffer.undo_to_last_manual_edit();
+    buffer.undo_to_last_manual_edit();
+    assert_eq!(buffer.text(), "abcd");
+}
+
 #[test]
 fn test_finalize_last_transaction() {
     let now = Instant::now();
@@ -724,6 +781,39 @@ fn test_concurrent_edits() {
     assert_eq!(buffer3.text(), "a12c34e56");
 }
 
+#[test]
+fn test_resync() {
+    let mut buffer1 = Buffer::new(1, BufferId::new(1).unwrap(), "abcdef".into());
+    let op = buffer1.edit([(1..2, "12")]);
+
+    // Replicas that applied the same operations to different texts.
+    let mut buffer2 = Buffer::new(2, BufferId::new(1).unwrap(), "abcdef".into());
+    let mut replica = Buffer::new(2, BufferId::new(1).unwrap(), "uvwxyz".into());
+    buffer2.apply_op(op.clone());
+    replica.apply_op(op);
+    assert_eq!(buffer2.version(), replica.version());
+    assert_ne!(buffer2.fingerprint(), replica.fingerprint());
+
+    let anchor = buffer2.anchor_before(4);
+    let subscription = buffer2.subscribe();
+    buffer2.resync(&mut replica);
+    assert_eq!(buffer2.text(), "u12wxyz");
+    assert_eq!(buffer2.resync_count(), 1);
+    assert_eq!(anchor.to_offset(&buffer2), 4);
+    assert_eq!(
+        subscription.consume().into_inner(),
+        [Edit {
+            old: 0..7,
+            new: 0..7
+        }]
+    );
+
+    let op = buffer2.edit([(0..1, "a")]);
+    buffer1.apply_op(op);
+    assert_eq!(buffer1.text(), "a12cdef");
+    assert_eq!(buffer2.text(), "a12wxyz");
+}
+
 #[gpui::test(iterations = 100)]
 fn test_random_concurrent_edits(mut rng: StdRng) {
     let peers = env::var("PEERS")
@@ -796,6 +886,139 @@ fn test_random_concurrent_edits(mut rng: StdRng) {
             "Replica {} text != Replica 0 text",
             buffer.replica_id
         );
+        assert_eq!(buffer.fingerprint(), first_buffer.fingerprint());
+        buffer.check_invariants();
+    }
+}
+
+#[gpui::test(iterations = 100)]
+fn test_random_concurrent_edits_over_lossy_network(mut rng: StdRng) {
+    let peers = env::var("PEERS")
+        .map(|i| i.parse().expect("invalid `PEERS` variable"))
+        .unwrap_or(5);
+    let operations = env::var("OPERATIONS")
+        .map(|i| i.parse().expect("invalid `OPERATIONS` variable"))
+        .unwrap_or(10);
+
+    let base_text_len = rng.gen_range(0..10);
+    let base_text = RandomCharIter::new(&mut rng)
+        .take(base_text_len)
+        .collect::<String>();
+    let mut buffers = Vec::new();
+    let mut anchors = Vec::new();
+    let mut network = Network::new(rng.clone());
+    network.set_drop_probability(rng.gen_range(0.0..0.5));
+
+    for i in 0..peers {
+        let mut buffer = Buffer::new(i as ReplicaId, BufferId::new(1).unwrap(), base_text.clone());
+        buffer.history.group_interval = Duration::from_millis(rng.gen_range(0..=200));
+        buffers.push(buffer);
+        network.add_peer(i as ReplicaId);
+    }
+
+    log::info!("initial text: {:?}", base_text);
+
+    let mut mutation_count = operations;
+    loop {
+        let replica_index = rng.gen_range(0..peers);
+        let replica_id = replica_index as ReplicaId;
+        match rng.gen_range(0..=100) {
+            0..=40 if mutation_count != 0 => {
+                let buffer = &mut buffers[replica_index];
+                let op = buffer.randomly_edit(&mut rng, 5).1;
+                network.broadcast(replica_id, vec![op]);
+                log::info!("buffer {} text: {:?}", replica_id, buffer.text());
+                mutation_count -= 1;
+            }
+            41..=55 if mutation_count != 0 => {
+                let ops = buffers[replica_index].randomly_undo_redo(&mut rng);
+                network.broadcast(replica_id, ops);
+                mutation_count -= 1;
+            }
+            56..=65 => {
+                let buffer = &buffers[replica_index];
+                let offset = buffer.clip_offset(rng.gen_range(0..=buffer.len()), Bias::Left);
+                let bias = if rng.gen() { Bias::Left } else { Bias::Right };
+                log::info!("peer {} anchoring {} with {:?}", replica_id, offset, bias);
+                anchors.push((replica_index, buffer.anchor_at(offset, bias)));
+            }
+            66..=85 if network.has_unreceived(replica_id) => {
+                let ops = network.receive(replica_id);
+                if !ops.is_empty() {
+                    log::info!(
+                        "peer {} applying {} ops from the network.",
+                        replica_id,
+                        ops.len()
+                    );
+                    buffers[replica_index].apply_ops(ops);
+                }
+            }
+            86..=100 => {
+                let other_index = rng.gen_range(0..peers);
+                log::info!(
+                    "peer {} synchronizing with peer {}",
+                    replica_id,
+                    other_index
+                );
+                synchronize(&mut buffers, replica_index, other_index);
+            }
+            _ => {}
+        }
+
+        // Anchors created on a peer must stay valid on that peer as edits arrive.
+        let buffer = &buffers[replica_index];
+        buffer.check_invariants();
+        for (_, anchor) in anchors.iter().filter(|(ix, _)| *ix == replica_index) {
+            assert!(anchor.to_offset(buffer) <= buffer.len());
+        }
+
+        if mutation_count == 0 && network.is_idle() {
+            break;
+        }
+    }
+
+    log::info!("{} messages were dropped", network.dropped_message_count());
+
+    // Recover the operations that were lost, the way peers do when they rejoin.
+    for replica_index in 0..peers {
+        for other_index in 0..peers {
+            synchronize(&mut buffers, replica_index, other_index);
+        }
+    }
+
+    let first_buffer = &buffers[0];
+    for buffer in &buffers {
+        assert_eq!(
+            buffer.text(),
+            first_buffer.text(),
+            "Replica {} text != Replica 0 text",
+            buffer.replica_id
+        );
+        assert_eq!(buffer.version(), first_buffer.version());
+        assert_eq!(buffer.deferred_ops_len(), 0);
         buffer.check_invariants();
+
+        // Every anchor resolves to the same position on every replica.
+        for (anchor_replica_index, anchor) in &anchors {
+            assert_eq!(
+                anchor.to_offset(buffer),
+                anchor.to_offset(first_buffer),
+                "anchor created by replica {} resolves differently on replica {}",
+                anchor_replica_index,
+                buffer.replica_id
+            );
+        }
     }
 }
+
+/// Applies the operations observed by one replica that another replica is missing.
+fn synchronize(buffers: &mut [Buffer], replica_index: usize, other_index: usize) {
+    let version = buffers[replica_index].version();
+    let missing_ops = buffers[other_index]
+        .operations()
+        .values()
+        .filter(|op| !version.observed(op.timestamp()))
+        .cloned()
+        .collect::<Vec<_>>();
+    buffers[replica_index].apply_ops(missing_ops);
+}
@@ -29,6 +29,7 @@ use std::{
     fmt::Display,
     future::Future,
     iter::Iterator,
+    mem,
     num::NonZeroU64,
     ops::{self, Deref, Range, Sub},
     str,
@@ -111,16 +112,34 @@ pub struct BufferSnapshot {
     fragments: SumTree<Fragment>,
     insertions: SumTree<InsertionFragment>,
     pub version: clock::Global,
+    resync_count: usize,
 }
 
 #[derive(Clone, Debug)]
 pub struct HistoryEntry {
     transaction: Transaction,
+    metadata: TransactionMetadata,
     first_edit_at: Instant,
     last_edit_at: Instant,
     suppress_grouping: bool,
 }
 
+/// Describes where the edits of a transaction came from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TransactionMetadata {
+    /// The name of the command that made the edits, e.g. `editor::Rename`.
+    pub command: Option<Arc<str>>,
+    /// The tool that made the edits on the user's behalf, e.g. "formatter" or
+    /// "rename". Transactions without a tool are manual edits.
+    pub tool: Option<Arc<str>>,
+}
+
+impl TransactionMetadata {
+    pub fn is_manual(&self) -> bool {
+        self.tool.is_none()
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Transaction {
     pub id: TransactionId,
@@ -132,6 +151,15 @@ impl HistoryEntry {
     pub fn transaction_id(&self) -> TransactionId {
         self.transaction.id
     }
+
+    pub fn metadata(&self) -> &TransactionMetadata {
+        &self.metadata
+    }
+
+    /// The replica that made the edits of this transaction.
+    pub fn author(&self) -> Option<ReplicaId> {
+        Some(self.transaction.edit_ids.first()?.replica_id)
+    }
 }
 
 struct History {
@@ -186,6 +214,7 @@ impl History {
                     start,
                     edit_ids: Default::default(),
                 },
+                metadata: TransactionMetadata::default(),
                 first_edit_at: now,
                 last_edit_at: now,
                 suppress_grouping: false,
@@ -283,6 +312,7 @@ impl History {
         assert_eq!(self.transaction_depth, 0);
         self.undo_stack.push(HistoryEntry {
             transaction,
+            metadata: TransactionMetadata::default(),
             first_edit_at: now,
             last_edit_at: now,
             suppress_grouping: false,
@@ -335,6 +365,21 @@ impl History {
         &self.redo_stack[redo_stack_start_len..]
     }
 
+    /// Moves the transactions made since the last manual edit to the redo stack.
+    fn remove_from_undo_after_manual_edit(&mut self) -> &[HistoryEntry] {
+        assert_eq!(self.transaction_depth, 0);
+
+        let redo_stack_start_len = self.redo_stack.len();
+        let manual_edit_ix = self
+            .undo_stack
+            .iter()
+            .rposition(|entry| entry.metadata.is_manual());
+        let start_ix = manual_edit_ix.map_or(0, |ix| ix + 1);
+        self.redo_stack
+            .extend(self.undo_stack.drain(start_ix..).rev());
+        &self.redo_stack[redo_stack_start_len..]
+    }
+
     fn forget(&mut self, transaction_id: TransactionId) -> Option<Transaction> {
         assert_eq!(self.transaction_depth, 0);
         if let Some(entry_ix) = self
@@ -354,30 +399,34 @@ impl History {
         }
     }
 
-    fn transaction(&self, transaction_id: TransactionId) -> Option<&Transaction> {
-        let entry = self
-            .undo_stack
+    fn entry(&self, transaction_id: TransactionId) -> Option<&HistoryEntry> {
+        self.undo_stack
             .iter()
             .rfind(|entry| entry.transaction.id == transaction_id)
             .or_else(|| {
                 self.redo_stack
                     .iter()
                     .rfind(|entry| entry.transaction.id == transaction_id)
-            })?;
-        Some(&entry.transaction)
+            })
     }
 
-    fn transaction_mut(&mut self, transaction_id: TransactionId) -> Option<&mut Transaction> {
-        let entry = self
-            .undo_stack
+    fn entry_mut(&mut self, transaction_id: TransactionId) -> Option<&mut HistoryEntry> {
+        self.undo_stack
             .iter_mut()
             .rfind(|entry| entry.transaction.id == transaction_id)
             .or_else(|| {
                 self.redo_stack
                     .iter_mut()
                     .rfind(|entry| entry.transaction.id == transaction_id)
-            })?;
-        Some(&mut entry.transaction)
+            })
+    }
+
+    fn transaction(&self, transaction_id: TransactionId) -> Option<&Transaction> {
+        Some(&self.entry(transaction_id)?.transaction)
+    }
+
+    fn transaction_mut(&mut self, transaction_id: TransactionId) -> Option<&mut Transaction> {
+        Some(&mut self.entry_mut(transaction_id)?.transaction)
     }
 
     fn merge_transactions(&mut self, transaction: TransactionId, destination: TransactionId) {
@@ -696,6 +745,7 @@ impl Buffer {
                 insertions,
                 version,
                 undo_map: Default::default(),
+                resync_count: 0,
             },
             history,
             deferred_ops: OperationQueue::new(),
@@ -1317,6 +1367,51 @@ impl Buffer {
         self.history.redo_stack.last()
     }
 
+    /// Returns the transactions of the undo and redo stacks, from the bottom of each stack.
+    pub fn undo_history(&self) -> (Vec<Transaction>, Vec<Transaction>) {
+        let transactions = |stack: &[HistoryEntry]| {
+            stack
+                .iter()
+                .map(|entry| entry.transaction.clone())
+                .collect()
+        };
+        (
+            transactions(&self.history.undo_stack),
+            transactions(&self.history.redo_stack),
+        )
+    }
+
+    /// Replaces the undo and redo stacks, e.g. after replaying the operations of a persisted
+    /// buffer. The restored transactions aren't grouped with the ones that follow.
+    pub fn restore_undo_history(
+        &mut self,
+        undo_stack: Vec<Transaction>,
+        redo_stack: Vec<Transaction>,
+        now: Instant,
+    ) {
+        assert_eq!(self.history.transaction_depth, 0);
+        let mut entries = |transactions: Vec<Transaction>| {
+            transactions
+                .into_iter()
+                .map(|transaction| {
+                    // Transaction ids are ticks of the clock, which must not be reused.
+                    self.lamport_clock.observe(transaction.id);
+                    HistoryEntry {
+                        transaction,
+                        metadata: TransactionMetadata::default(),
+                        first_edit_at: now,
+                        last_edit_at: now,
+                        suppress_grouping: true,
+                    }
+                })
+                .collect()
+        };
+        let undo_stack = entries(undo_stack);
+        let redo_stack = entries(redo_stack);
+        self.history.undo_stack = undo_stack;
+        self.history.redo_stack = redo_stack;
+    }
+
     pub fn start_transaction(&mut self) -> Option<TransactionId> {
         self.start_transaction_at(Instant::now())
     }
@@ -1390,10 +1485,44 @@ impl Buffer {
             .collect()
     }
 
+    /// Undoes the transactions made since the last manual edit, e.g. to revert
+    /// everything a formatter or a rename changed on top of it.
+    pub fn undo_to_last_manual_edit(&mut self) -> Vec<Operation> {
+        let transactions = self
+            .history
+            .remove_from_undo_after_manual_edit()
+            .iter()
+            .map(|entry| entry.transaction.clone())
+            .collect::<Vec<_>>();
+
+        transactions
+            .into_iter()
+            .map(|transaction| self.undo_or_redo(transaction))
+            .collect()
+    }
+
     pub fn forget_transaction(&mut self, transaction_id: TransactionId) {
         self.history.forget(transaction_id);
     }
 
+    pub fn transaction_metadata(
+        &self,
+        transaction_id: TransactionId,
+    ) -> Option<&TransactionMetadata> {
+        Some(&self.history.entry(transaction_id)?.metadata)
+    }
+
+    /// Describes where the edits of a transaction in the undo history came from.
+    pub fn set_transaction_metadata(
+        &mut self,
+        transaction_id: TransactionId,
+        metadata: TransactionMetadata,
+    ) {
+        if let Some(entry) = self.history.entry_mut(transaction_id) {
+            entry.metadata = metadata;
+        }
+    }
+
     pub fn merge_transactions(&mut self, transaction: TransactionId, destination: TransactionId) {
         self.history.merge_transactions(transaction, destination);
     }
@@ -1592,6 +1721,25 @@ impl Buffer {
         self.wait_for_version_txs.clear();
     }
 
+    /// Replaces the state of this replica with the one of another replica of the buffer, at the
+    /// same version, after their texts diverged. Anchors remain valid, since both replicas
+    /// contain the same insertions, but the local undo history is lost. Subscribers are notified
+    /// that the whole text was replaced.
+    pub fn resync(&mut self, replica: &mut Buffer) {
+        let old_len = self.visible_text.len();
+        let resync_count = self.snapshot.resync_count + 1;
+        mem::swap(&mut self.snapshot, &mut replica.snapshot);
+        mem::swap(&mut self.history, &mut replica.history);
+        mem::swap(&mut self.deferred_ops, &mut replica.deferred_ops);
+        mem::swap(&mut self.deferred_replicas, &mut replica.deferred_replicas);
+        self.lamport_clock.observe(replica.lamport_clock);
+        self.snapshot.resync_count = resync_count;
+        self.subscriptions.publish_mut([Edit {
+            old: 0..old_len,
+            new: 0..self.visible_text.len(),
+        }]);
+    }
+
     fn resolve_edit(&mut self, edit_id: clock::Lamport) {
         for mut tx in self
             .edit_id_resolvers
@@ -1917,6 +2065,18 @@ impl BufferSnapshot {
         self.visible_text.to_string()
     }
 
+    /// Returns a hash of the visible text, which replicas can compare to check
+    /// that they converged without exchanging the text itself.
+    pub fn fingerprint(&self) -> Fingerprint {
+        self.visible_text.fingerprint()
+    }
+
+    /// The number of times the text was replaced by [`Buffer::resync`], which doesn't change
+    /// its version.
+    pub fn resync_count(&self) -> usize {
+        self.resync_count
+    }
+
     pub fn line_ending(&self) -> LineEnding {
         self.line_ending
     }
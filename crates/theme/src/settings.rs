@@ -6,7 +6,8 @@ use crate::{
 use anyhow::Result;
 use derive_more::{Deref, DerefMut};
 use gpui::{
-    px, App, Font, FontFallbacks, FontFeatures, FontStyle, FontWeight, Global, Pixels, Window,
+    px, App, Font, FontFallbacks, FontFeatures, FontScript, FontStyle, FontWeight, Global, Pixels,
+    SharedString, Window,
 };
 use refineable::Refineable;
 use schemars::{
@@ -17,7 +18,7 @@ use schemars::{
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use settings::{add_references_to_properties, Settings, SettingsJsonSchemaParams, SettingsSources};
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 use util::ResultExt as _;
 
 const MIN_FONT_SIZE: Pixels = px(6.0);
@@ -105,6 +106,9 @@ pub struct ThemeSettings {
     ///
     /// The terminal font family can be overridden using it's own setting.
     pub buffer_font: Font,
+    /// The fonts to use, per script, for characters the buffer and UI fonts
+    /// have no glyphs for.
+    pub font_script_fallbacks: BTreeMap<FontScript, Vec<SharedString>>,
     /// The line height for buffers, and the terminal.
     ///
     /// Changing this may affect the spacing of some UI elements.
@@ -392,6 +396,10 @@ pub struct ThemeSettingsContent {
     #[serde(default)]
     #[schemars(default = "default_font_features")]
     pub buffer_font_features: Option<FontFeatures>,
+    /// The fonts to use, per script, for characters the buffer and UI fonts
+    /// have no glyphs for.
+    #[serde(default)]
+    pub font_script_fallbacks: Option<BTreeMap<FontScript, Vec<String>>>,
     /// The name of the Zed theme to use.
     #[serde(default)]
     pub theme: Option<ThemeSelection>,
@@ -676,6 +684,7 @@ impl settings::Settings for ThemeSettings {
                 style: FontStyle::default(),
             },
             buffer_font_size: defaults.buffer_font_size.unwrap().into(),
+            font_script_fallbacks: BTreeMap::default(),
             buffer_line_height: defaults.buffer_line_height.unwrap(),
             theme_selection: defaults.theme.clone(),
             active_theme: themes
@@ -733,6 +742,16 @@ impl settings::Settings for ThemeSettings {
                 this.ui_font.weight = clamp_font_weight(value);
             }
 
+            if let Some(value) = &value.font_script_fallbacks {
+                this.font_script_fallbacks = value
+                    .iter()
+                    .map(|(script, families)| {
+                        let families = families.iter().cloned().map(SharedString::from).collect();
+                        (*script, families)
+                    })
+                    .collect();
+            }
+
             if let Some(value) = &value.theme {
                 this.theme_selection = Some(value.clone());
 
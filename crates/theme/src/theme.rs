@@ -19,16 +19,17 @@ mod schema;
 mod settings;
 mod styles;
 
+use std::collections::BTreeMap;
 use std::path::Path;
 use std::sync::Arc;
 
-use ::settings::Settings;
+use ::settings::{Settings, SettingsStore};
 use anyhow::Result;
 use fallback_themes::apply_status_color_defaults;
 use fs::Fs;
 use gpui::{
-    px, App, AssetSource, HighlightStyle, Hsla, Pixels, Refineable, SharedString, WindowAppearance,
-    WindowBackgroundAppearance,
+    px, App, AssetSource, FontScript, Global, HighlightStyle, Hsla, Pixels, Refineable,
+    SharedString, WindowAppearance, WindowBackgroundAppearance,
 };
 use serde::Deserialize;
 use uuid::Uuid;
@@ -101,18 +102,72 @@ pub fn init(themes_to_load: LoadThemes, cx: &mut App) {
 
     ThemeSettings::register(cx);
     FontFamilyCache::init_global(cx);
+
+    let mut font_script_fallbacks = ThemeSettings::get_global(cx).font_script_fallbacks.clone();
+    apply_font_script_fallbacks(&font_script_fallbacks, cx);
+    let theme = cx.theme().clone();
+    cx.set_global(GlobalThemeGeneration {
+        generation: 0,
+        theme,
+    });
+    cx.observe_global::<SettingsStore>(move |cx| {
+        let new_font_script_fallbacks = &ThemeSettings::get_global(cx).font_script_fallbacks;
+        if *new_font_script_fallbacks != font_script_fallbacks {
+            font_script_fallbacks = new_font_script_fallbacks.clone();
+            apply_font_script_fallbacks(&font_script_fallbacks, cx);
+        }
+
+        let theme = cx.theme().clone();
+        if !Arc::ptr_eq(&cx.global::<GlobalThemeGeneration>().theme, &theme) {
+            let theme_generation = cx.global_mut::<GlobalThemeGeneration>();
+            theme_generation.generation += 1;
+            theme_generation.theme = theme;
+        }
+    })
+    .detach();
+}
+
+/// The number of times the active theme changed, and the theme it changed to last.
+struct GlobalThemeGeneration {
+    generation: usize,
+    theme: Arc<Theme>,
+}
+
+impl Global for GlobalThemeGeneration {}
+
+fn apply_font_script_fallbacks(
+    font_script_fallbacks: &BTreeMap<FontScript, Vec<SharedString>>,
+    cx: &mut App,
+) {
+    cx.text_system().set_script_fallbacks(
+        font_script_fallbacks
+            .iter()
+            .map(|(script, families)| (*script, families.clone()))
+            .collect(),
+    );
 }
 
 /// Implementing this trait allows accessing the active theme.
 pub trait ActiveTheme {
     /// Returns the active theme.
     fn theme(&self) -> &Arc<Theme>;
+
+    /// Returns the number of times the active theme changed.
+    ///
+    /// Caches of styles computed from the theme can store it, to recompute them lazily once
+    /// it changed rather than all at once when the theme is switched.
+    fn theme_generation(&self) -> usize;
 }
 
 impl ActiveTheme for App {
     fn theme(&self) -> &Arc<Theme> {
         &ThemeSettings::get_global(self).active_theme
     }
+
+    fn theme_generation(&self) -> usize {
+        self.try_global::<GlobalThemeGeneration>()
+            .map_or(0, |theme_generation| theme_generation.generation)
+    }
 }
 
 /// A theme family is a grouping of themes under a single name.
@@ -64,9 +64,7 @@ impl Vim {
                     }
 
                     // create a classifier
-                    let classifier = map
-                        .buffer_snapshot
-                        .char_classifier_at(selection.head().to_point(map));
+                    let classifier = map.char_classifier_at(selection.head().to_point(map));
 
                     let mut last_selection = selection.clone();
                     for _ in 0..times {
@@ -120,9 +118,7 @@ impl Vim {
                     selection.swap_head_tail();
 
                     // create a classifier
-                    let classifier = map
-                        .buffer_snapshot
-                        .char_classifier_at(selection.head().to_point(map));
+                    let classifier = map.char_classifier_at(selection.head().to_point(map));
 
                     let mut last_selection = selection.clone();
                     for _ in 0..times {
@@ -60,9 +60,8 @@ impl Vim {
                                 if let Motion::CurrentLine = motion {
                                     let mut start_offset =
                                         selection.start.to_offset(map, Bias::Left);
-                                    let classifier = map
-                                        .buffer_snapshot
-                                        .char_classifier_at(selection.start.to_point(map));
+                                    let classifier =
+                                        map.char_classifier_at(selection.start.to_point(map));
                                     for (ch, offset) in map.buffer_chars_at(start_offset) {
                                         if ch == '\n' || !classifier.is_whitespace(ch) {
                                             break;
@@ -137,9 +136,7 @@ fn expand_changed_word_selection(
     use_subword: bool,
 ) -> bool {
     let is_in_word = || {
-        let classifier = map
-            .buffer_snapshot
-            .char_classifier_at(selection.start.to_point(map));
+        let classifier = map.char_classifier_at(selection.start.to_point(map));
         let in_word = map
             .buffer_chars_at(selection.head().to_offset(map, Bias::Left))
             .next()
@@ -8,7 +8,7 @@ use crate::{
 use editor::{
     display_map::{DisplaySnapshot, ToDisplayPoint},
     movement::{self, FindRange},
-    Bias, DisplayPoint, Editor,
+    surrounding_object_range, Bias, DisplayPoint, Editor, SurroundingObject,
 };
 use gpui::{actions, impl_actions, Window};
 use itertools::Itertools;
@@ -42,6 +42,7 @@ pub enum Object {
     Class,
     Comment,
     EntireFile,
+    Enclosing(SurroundingObject),
 }
 
 #[derive(Clone, Deserialize, JsonSchema, PartialEq)]
@@ -64,7 +65,14 @@ struct IndentObj {
     include_below: bool,
 }
 
-impl_actions!(vim, [Word, Subword, IndentObj]);
+/// An object enclosing the cursor, resolved like the ones of `editor::SelectInside`.
+#[derive(Clone, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct Enclosing {
+    object: SurroundingObject,
+}
+
+impl_actions!(vim, [Word, Subword, IndentObj, Enclosing]);
 
 actions!(
     vim,
@@ -169,6 +177,13 @@ pub fn register(editor: &mut Editor, cx: &mut Context<Vim>) {
             vim.object(Object::IndentObj { include_below }, window, cx)
         },
     );
+    Vim::action(
+        editor,
+        cx,
+        |vim, &Enclosing { object }: &Enclosing, window, cx| {
+            vim.object(Object::Enclosing(object), window, cx)
+        },
+    );
 }
 
 impl Vim {
@@ -195,6 +210,13 @@ impl Object {
             | Object::AnyQuotes
             | Object::VerticalBars
             | Object::DoubleQuotes => false,
+            Object::Enclosing(object) => !matches!(
+                object,
+                SurroundingObject::AnyQuotes
+                    | SurroundingObject::SingleQuotes
+                    | SurroundingObject::DoubleQuotes
+                    | SurroundingObject::BackQuotes
+            ),
             Object::Sentence
             | Object::Paragraph
             | Object::AnyBrackets
@@ -220,6 +242,7 @@ impl Object {
             | Object::Paragraph
             | Object::Argument
             | Object::IndentObj { .. } => false,
+            Object::Enclosing(object) => object != SurroundingObject::Argument,
             Object::Quotes
             | Object::BackQuotes
             | Object::AnyQuotes
@@ -262,6 +285,7 @@ impl Object {
             | Object::Tag
             | Object::Comment
             | Object::Argument
+            | Object::Enclosing(_)
             | Object::IndentObj { .. } => Mode::Visual,
             Object::Method | Object::Class => {
                 if around {
@@ -395,6 +419,12 @@ impl Object {
             Object::Argument => argument(map, relative_to, around),
             Object::IndentObj { include_below } => indent(map, relative_to, around, include_below),
             Object::EntireFile => entire_file(map),
+            Object::Enclosing(object) => {
+                let offset = relative_to.to_offset(map, Bias::Left);
+                let range =
+                    surrounding_object_range(&map.buffer_snapshot, offset..offset, object, around)?;
+                Some(range.start.to_display_point(map)..range.end.to_display_point(map))
+            }
         }
     }
 
@@ -472,7 +502,6 @@ fn in_word(
 ) -> Option<Range<DisplayPoint>> {
     // Use motion::right so that we consider the character under the cursor when looking for the start
     let classifier = map
-        .buffer_snapshot
         .char_classifier_at(relative_to.to_point(map))
         .ignore_punctuation(ignore_punctuation);
     let start = movement::find_preceding_boundary_display_point(
@@ -497,7 +526,6 @@ fn in_subword(
     let offset = relative_to.to_offset(map, Bias::Left);
     // Use motion::right so that we consider the character under the cursor when looking for the start
     let classifier = map
-        .buffer_snapshot
         .char_classifier_at(relative_to.to_point(map))
         .ignore_punctuation(ignore_punctuation);
     let in_subword = map
@@ -641,7 +669,6 @@ fn around_word(
 ) -> Option<Range<DisplayPoint>> {
     let offset = relative_to.to_offset(map, Bias::Left);
     let classifier = map
-        .buffer_snapshot
         .char_classifier_at(offset)
         .ignore_punctuation(ignore_punctuation);
     let in_word = map
@@ -695,7 +722,6 @@ fn around_subword(
 ) -> Option<Range<DisplayPoint>> {
     // Use motion::right so that we consider the character under the cursor when looking for the start
     let classifier = map
-        .buffer_snapshot
         .char_classifier_at(relative_to.to_point(map))
         .ignore_punctuation(ignore_punctuation);
     let start = movement::find_preceding_boundary_display_point(
@@ -754,7 +780,6 @@ fn around_next_word(
     ignore_punctuation: bool,
 ) -> Option<Range<DisplayPoint>> {
     let classifier = map
-        .buffer_snapshot
         .char_classifier_at(relative_to.to_point(map))
         .ignore_punctuation(ignore_punctuation);
     // Get the start of the word
@@ -1445,11 +1470,12 @@ fn surrounding_markers(
 
 #[cfg(test)]
 mod test {
+    use editor::SurroundingObject;
     use gpui::KeyBinding;
     use indoc::indoc;
 
     use crate::{
-        object::AnyBrackets,
+        object::{AnyBrackets, Enclosing},
         state::Mode,
         test::{NeovimBackedTestContext, VimTestContext},
     };
@@ -2472,6 +2498,32 @@ mod test {
             Mode::Visual,
         );
     }
+    #[gpui::test]
+    async fn test_enclosing_object(cx: &mut gpui::TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.update(|_, cx| {
+            cx.bind_keys([KeyBinding::new(
+                "x",
+                Enclosing {
+                    object: SurroundingObject::Argument,
+                },
+                Some("vim_operator == a || vim_operator == i"),
+            )]);
+        });
+
+        cx.set_state("call(one, twˇo, three)", Mode::Normal);
+        cx.simulate_keystrokes("d a x");
+        cx.assert_state("call(one, ˇthree)", Mode::Normal);
+
+        cx.set_state("call(one, twˇo, three)", Mode::Normal);
+        cx.simulate_keystrokes("c i x");
+        cx.assert_state("call(one, ˇ, three)", Mode::Insert);
+
+        cx.set_state("call(one, twˇo, three)", Mode::Normal);
+        cx.simulate_keystrokes("v i x");
+        cx.assert_state("call(one, «twoˇ», three)", Mode::Visual);
+    }
+
     #[gpui::test]
     async fn test_around_containing_word_indent(cx: &mut gpui::TestAppContext) {
         let mut cx = NeovimBackedTestContext::new(cx).await;
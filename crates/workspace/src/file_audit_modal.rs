@@ -0,0 +1,274 @@
+//! A modal listing the files found by a [`FileAudit`], with checkboxes choosing how they're
+//! converted and which of them are.
+
+use crate::{notifications::NotificationId, ModalView, Toast, Workspace};
+use gpui::{
+    uniform_list, App, Context, DismissEvent, Entity, EventEmitter, FocusHandle, Focusable,
+    Subscription, UniformListScrollHandle, WeakEntity, Window,
+};
+use itertools::Itertools as _;
+use language::LineEnding;
+use project::file_audit::{
+    AuditedFile, FileAudit, FileAuditEvent, FileConversion, FileFormatIssues,
+};
+use ui::{prelude::*, Checkbox, CheckboxWithLabel};
+
+pub struct FileAuditModal {
+    audit: Entity<FileAudit>,
+    workspace: WeakEntity<Workspace>,
+    conversion: FileConversion,
+    progress: Option<(usize, usize)>,
+    focus_handle: FocusHandle,
+    scroll_handle: UniformListScrollHandle,
+    _subscription: Subscription,
+}
+
+impl FileAuditModal {
+    pub fn new(
+        audit: Entity<FileAudit>,
+        workspace: WeakEntity<Workspace>,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let _subscription = cx.subscribe(&audit, |this, _, event, cx| {
+            this.progress = match event {
+                FileAuditEvent::Progress { processed, total } => Some((*processed, *total)),
+                FileAuditEvent::Finished => None,
+            };
+            cx.notify();
+        });
+        Self {
+            audit,
+            workspace,
+            conversion: FileConversion {
+                line_ending: Some(LineEnding::Unix),
+                replace_tabs: false,
+                remove_byte_order_mark: false,
+            },
+            progress: None,
+            focus_handle: cx.focus_handle(),
+            scroll_handle: UniformListScrollHandle::new(),
+            _subscription,
+        }
+    }
+
+    fn cancel(&mut self, _: &menu::Cancel, _: &mut Window, cx: &mut Context<Self>) {
+        cx.emit(DismissEvent);
+    }
+
+    /// Converts the checked files, and reports those that were skipped because they have
+    /// unsaved changes.
+    fn confirm(&mut self, _: &menu::Confirm, window: &mut Window, cx: &mut Context<Self>) {
+        if !self.can_convert(cx) {
+            return;
+        }
+        let audit = self.audit.clone();
+        let convert = audit.update(cx, |audit, cx| audit.convert(self.conversion, cx));
+        let workspace = self.workspace.clone();
+        cx.spawn_in(window, |_, mut cx| async move {
+            let skipped_paths = convert.await?;
+            // The audit keeps converting once the modal is dismissed.
+            drop(audit);
+            if !skipped_paths.is_empty() {
+                let message = format!(
+                    "Skipped files with unsaved changes: {}",
+                    skipped_paths
+                        .iter()
+                        .map(|path| path.path.to_string_lossy())
+                        .join(", ")
+                );
+                workspace.update(&mut cx, |workspace, cx| {
+                    workspace.show_toast(
+                        Toast::new(NotificationId::unique::<FileAudit>(), message),
+                        cx,
+                    )
+                })?;
+            }
+            anyhow::Ok(())
+        })
+        .detach_and_log_err(cx);
+        cx.emit(DismissEvent);
+    }
+
+    fn can_convert(&self, cx: &App) -> bool {
+        let audit = self.audit.read(cx);
+        !audit.is_running() && audit.preview(&self.conversion).next().is_some()
+    }
+
+    fn render_option(
+        &self,
+        id: &'static str,
+        label: &'static str,
+        checked: bool,
+        set: fn(&mut FileConversion, bool),
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let this = cx.entity().downgrade();
+        CheckboxWithLabel::new(
+            id,
+            Label::new(label),
+            checked.into(),
+            move |state, _, cx| {
+                this.update(cx, |this, cx| {
+                    set(&mut this.conversion, state.selected());
+                    cx.notify();
+                })
+                .ok();
+            },
+        )
+    }
+
+    fn render_file(
+        &self,
+        ix: usize,
+        file: &AuditedFile,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let audit = self.audit.clone();
+        let path = file.path.clone();
+        h_flex()
+            .id(("file-audit-file", ix))
+            .gap_2()
+            .child(
+                Checkbox::new(("file-audit-included", ix), file.included.into())
+                    .disabled(file.issues.invalid_utf8)
+                    .on_click(move |state, _, cx| {
+                        audit.update(cx, |audit, cx| {
+                            audit.set_included(&path, state.selected(), cx)
+                        });
+                    }),
+            )
+            .child(
+                Label::new(file.path.path.to_string_lossy().into_owned())
+                    .buffer_font(cx)
+                    .single_line(),
+            )
+            .child(
+                Label::new(issue_labels(&file.issues))
+                    .size(LabelSize::Small)
+                    .color(if file.issues.invalid_utf8 {
+                        Color::Warning
+                    } else {
+                        Color::Muted
+                    }),
+            )
+    }
+}
+
+/// Describes the issues of a file.
+fn issue_labels(issues: &FileFormatIssues) -> String {
+    [
+        (issues.crlf_line_endings, "CRLF line endings"),
+        (issues.tab_indentation, "tab indentation"),
+        (issues.byte_order_mark, "byte order mark"),
+        (issues.invalid_utf8, "not UTF-8"),
+    ]
+    .into_iter()
+    .filter_map(|(has_issue, label)| has_issue.then_some(label))
+    .join(", ")
+}
+
+impl Render for FileAuditModal {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let audit = self.audit.read(cx);
+        let file_count = audit.files().len();
+        let convert_count = audit.preview(&self.conversion).count();
+        let headline = if let Some((processed, total)) = self.progress {
+            format!("Auditing files… {processed}/{total}")
+        } else if audit.is_running() {
+            "Auditing files…".to_string()
+        } else if file_count == 0 {
+            "No files with CRLF line endings, tab indentation or byte order marks".to_string()
+        } else {
+            format!(
+                "{file_count} files have CRLF line endings, tab indentation or byte order marks"
+            )
+        };
+
+        v_flex()
+            .key_context("FileAuditModal")
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::cancel))
+            .on_action(cx.listener(Self::confirm))
+            .elevation_3(cx)
+            .w(rems(40.))
+            .p_4()
+            .gap_2()
+            .child(Headline::new(headline).size(HeadlineSize::Small))
+            .child(
+                v_flex()
+                    .gap_1()
+                    .child(self.render_option(
+                        "file-audit-line-endings",
+                        "Convert line endings to LF",
+                        self.conversion.line_ending.is_some(),
+                        |conversion, checked| {
+                            conversion.line_ending = checked.then_some(LineEnding::Unix)
+                        },
+                        cx,
+                    ))
+                    .child(self.render_option(
+                        "file-audit-tabs",
+                        "Replace leading tabs with spaces, by each file's tab size",
+                        self.conversion.replace_tabs,
+                        |conversion, checked| conversion.replace_tabs = checked,
+                        cx,
+                    ))
+                    .child(self.render_option(
+                        "file-audit-byte-order-marks",
+                        "Remove byte order marks",
+                        self.conversion.remove_byte_order_mark,
+                        |conversion, checked| conversion.remove_byte_order_mark = checked,
+                        cx,
+                    )),
+            )
+            .child(
+                uniform_list(
+                    cx.entity(),
+                    "file-audit-files",
+                    file_count,
+                    |this, range, _, cx| {
+                        let files = this.audit.read(cx).files()[range.clone()].to_vec();
+                        range
+                            .zip(files)
+                            .map(|(ix, file)| this.render_file(ix, &file, cx))
+                            .collect()
+                    },
+                )
+                .track_scroll(self.scroll_handle.clone())
+                .h(rems(20.)),
+            )
+            .child(
+                h_flex()
+                    .justify_end()
+                    .gap_1()
+                    .child(
+                        Button::new("file-audit-cancel", "Cancel")
+                            .style(ButtonStyle::Subtle)
+                            .color(Color::Muted)
+                            .on_click(cx.listener(|_, _, _, cx| cx.emit(DismissEvent))),
+                    )
+                    .child(
+                        Button::new(
+                            "file-audit-convert",
+                            format!("Convert {convert_count} Files"),
+                        )
+                        .style(ButtonStyle::Filled)
+                        .color(Color::Accent)
+                        .disabled(!self.can_convert(cx))
+                        .on_click(cx.listener(|this, _, window, cx| {
+                            this.confirm(&menu::Confirm, window, cx)
+                        })),
+                    ),
+            )
+    }
+}
+
+impl Focusable for FileAuditModal {
+    fn focus_handle(&self, _: &App) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl EventEmitter<DismissEvent> for FileAuditModal {}
+impl ModalView for FileAuditModal {}
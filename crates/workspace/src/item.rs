@@ -6,7 +6,7 @@ use crate::{
     DelayedDebouncedEditAction, FollowableViewRegistry, ItemNavHistory, SerializableItemRegistry,
     ToolbarItemLocation, ViewId, Workspace, WorkspaceId,
 };
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use client::{
     proto::{self, PeerId},
     Client,
@@ -152,6 +152,29 @@ pub enum ItemEvent {
     Edit,
 }
 
+/// The cursor, scroll position and folds of a closed item, persisted so that reopening it in
+/// a later session restores them. Positions are `(row, column)` pairs.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClosedItemState {
+    pub cursor: (u32, u32),
+    pub scroll_top_row: u32,
+    pub folds: Vec<Range<(u32, u32)>>,
+}
+
+/// Where an item scrolls a revealed range to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RevealPosition {
+    /// The center of the viewport.
+    #[default]
+    Center,
+    /// Just enough for the range to be visible.
+    Fit,
+    /// The top of the viewport.
+    Top,
+    /// The given number of rows below the top of the viewport.
+    RowsFromTop(u32),
+}
+
 // TODO: Combine this with existing HighlightedText struct?
 pub struct BreadcrumbText {
     pub text: String,
@@ -234,9 +257,42 @@ pub trait Item: Focusable + EventEmitter<Self::Event> + Render + Sized {
     fn deactivated(&mut self, _window: &mut Window, _: &mut Context<Self>) {}
     fn discarded(&self, _project: Entity<Project>, _window: &mut Window, _cx: &mut Context<Self>) {}
     fn workspace_deactivated(&mut self, _window: &mut Window, _: &mut Context<Self>) {}
+    /// Called when the pane of the item gets hidden by another pane being maximized, or shown
+    /// again once it's restored.
+    fn pane_visibility_changed(
+        &mut self,
+        _visible: bool,
+        _window: &mut Window,
+        _: &mut Context<Self>,
+    ) {
+    }
     fn navigate(&mut self, _: Box<dyn Any>, _window: &mut Window, _: &mut Context<Self>) -> bool {
         false
     }
+    /// Selects the range of the item's buffer and scrolls to it, highlighting it for a moment
+    /// when `flash` is true. Returns false when the item has no buffer to reveal a range of.
+    fn reveal_range(
+        &mut self,
+        _range: Range<language::Point>,
+        _position: RevealPosition,
+        _flash: bool,
+        _window: &mut Window,
+        _: &mut Context<Self>,
+    ) -> bool {
+        false
+    }
+
+    /// Returns the state to restore when the item is reopened after being closed.
+    fn closed_state(&mut self, _: &mut Context<Self>) -> Option<ClosedItemState> {
+        None
+    }
+    fn restore_closed_state(
+        &mut self,
+        _state: ClosedItemState,
+        _window: &mut Window,
+        _: &mut Context<Self>,
+    ) {
+    }
 
     fn telemetry_event_text(&self) -> Option<&'static str> {
         None
@@ -302,6 +358,21 @@ pub trait Item: Focusable + EventEmitter<Self::Event> + Render + Sized {
     ) -> Task<Result<()>> {
         unimplemented!("reload() must be implemented if can_save() returns true")
     }
+    fn can_merge(&self, _cx: &App) -> bool {
+        false
+    }
+    /// Merges the item's unsaved changes with the changes made to its files on disk, making the
+    /// changes that don't conflict with the item's, and returns whether none did. The
+    /// conflicting changes are opened for the user to resolve if `resolve_conflicts` is true.
+    /// Items that [can't merge](Self::can_merge) return an error.
+    fn merge(
+        &mut self,
+        _resolve_conflicts: bool,
+        _window: &mut Window,
+        _cx: &mut Context<Self>,
+    ) -> Task<Result<bool>> {
+        Task::ready(Err(anyhow!("item can't be merged")))
+    }
 
     fn act_as_type<'a>(
         &'a self,
@@ -470,7 +541,18 @@ pub trait ItemHandle: 'static + Send {
     fn deactivated(&self, window: &mut Window, cx: &mut App);
     fn discarded(&self, project: Entity<Project>, window: &mut Window, cx: &mut App);
     fn workspace_deactivated(&self, window: &mut Window, cx: &mut App);
+    fn pane_visibility_changed(&self, visible: bool, window: &mut Window, cx: &mut App);
     fn navigate(&self, data: Box<dyn Any>, window: &mut Window, cx: &mut App) -> bool;
+    fn reveal_range(
+        &self,
+        range: Range<language::Point>,
+        position: RevealPosition,
+        flash: bool,
+        window: &mut Window,
+        cx: &mut App,
+    ) -> bool;
+    fn closed_state(&self, cx: &mut App) -> Option<ClosedItemState>;
+    fn restore_closed_state(&self, state: ClosedItemState, window: &mut Window, cx: &mut App);
     fn item_id(&self) -> EntityId;
     fn to_any(&self) -> AnyView;
     fn is_dirty(&self, cx: &App) -> bool;
@@ -497,6 +579,13 @@ pub trait ItemHandle: 'static + Send {
         window: &mut Window,
         cx: &mut App,
     ) -> Task<Result<()>>;
+    fn can_merge(&self, cx: &App) -> bool;
+    fn merge(
+        &self,
+        resolve_conflicts: bool,
+        window: &mut Window,
+        cx: &mut App,
+    ) -> Task<Result<bool>>;
     fn act_as_type(&self, type_id: TypeId, cx: &App) -> Option<AnyView>;
     fn to_followable_item_handle(&self, cx: &App) -> Option<Box<dyn FollowableItemHandle>>;
     fn to_serializable_item_handle(&self, cx: &App) -> Option<Box<dyn SerializableItemHandle>>;
@@ -862,10 +951,37 @@ impl<T: Item> ItemHandle for Entity<T> {
         self.update(cx, |this, cx| this.workspace_deactivated(window, cx));
     }
 
+    fn pane_visibility_changed(&self, visible: bool, window: &mut Window, cx: &mut App) {
+        self.update(cx, |this, cx| {
+            this.pane_visibility_changed(visible, window, cx)
+        });
+    }
+
     fn navigate(&self, data: Box<dyn Any>, window: &mut Window, cx: &mut App) -> bool {
         self.update(cx, |this, cx| this.navigate(data, window, cx))
     }
 
+    fn reveal_range(
+        &self,
+        range: Range<language::Point>,
+        position: RevealPosition,
+        flash: bool,
+        window: &mut Window,
+        cx: &mut App,
+    ) -> bool {
+        self.update(cx, |this, cx| {
+            this.reveal_range(range, position, flash, window, cx)
+        })
+    }
+
+    fn closed_state(&self, cx: &mut App) -> Option<ClosedItemState> {
+        self.update(cx, |this, cx| this.closed_state(cx))
+    }
+
+    fn restore_closed_state(&self, state: ClosedItemState, window: &mut Window, cx: &mut App) {
+        self.update(cx, |this, cx| this.restore_closed_state(state, window, cx))
+    }
+
     fn item_id(&self) -> EntityId {
         self.entity_id()
     }
@@ -919,6 +1035,19 @@ impl<T: Item> ItemHandle for Entity<T> {
         self.update(cx, |item, cx| item.reload(project, window, cx))
     }
 
+    fn can_merge(&self, cx: &App) -> bool {
+        self.read(cx).can_merge(cx)
+    }
+
+    fn merge(
+        &self,
+        resolve_conflicts: bool,
+        window: &mut Window,
+        cx: &mut App,
+    ) -> Task<Result<bool>> {
+        self.update(cx, |item, cx| item.merge(resolve_conflicts, window, cx))
+    }
+
     fn act_as_type<'a>(&'a self, type_id: TypeId, cx: &'a App) -> Option<AnyView> {
         self.read(cx).act_as_type(type_id, self, cx)
     }
@@ -0,0 +1,254 @@
+//! Frees the memory of what isn't shown, when the workspaces use more than their budget, or all
+//! the time in low-memory mode.
+//!
+//! The governor samples the memory used every few seconds: the text of the open buffers, their
+//! syntax trees, the laid out lines cached by the windows, and the entries indexed for the
+//! worktrees. Syntax trees and laid out lines aren't measured, but estimated from the size of the
+//! text they're for and from their count. When the estimate exceeds `memory_budget_mb`, or when
+//! `low_memory_mode` is on, everything that can be computed again is evicted:
+//! - the layouts of the lines the windows drew last, which their next frames lay out again;
+//! - the syntax trees of the buffers that aren't shown by the active item of a pane, and weren't
+//!   queried since the last sample, which are parsed again when an item showing them is
+//!   activated, or when they're edited;
+//! - the git texts that the diffs of clean buffers no item shows were computed from.
+//!
+//! Buffers themselves aren't released: they stay loaded for as long as whatever opened them, like
+//! a search or an assistant context, holds them.
+//!
+//! The last sample is available through [`MemoryGovernor::metrics`]. Evictions over budget are
+//! logged and reported to telemetry.
+
+use crate::{ItemHandle, Workspace, WorkspaceSettings};
+use collections::HashSet;
+use gpui::{App, Entity, EntityId, Global, WindowHandle};
+use language::Buffer;
+use project::Project;
+use serde::Serialize;
+use settings::Settings as _;
+use std::time::Duration;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The estimated bytes of syntax tree per byte of parsed text.
+const SYNTAX_TREE_BYTES_PER_TEXT_BYTE: usize = 8;
+
+/// The estimated bytes of a laid out line.
+const LINE_LAYOUT_BYTES: usize = 1024;
+
+/// The estimated bytes of an entry indexed for a worktree.
+const INDEX_ENTRY_BYTES: usize = 256;
+
+/// A sample of the memory used by the workspaces.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct MemoryMetrics {
+    /// The bytes of text of the open buffers.
+    pub rope_bytes: usize,
+    /// The bytes of the syntax trees of the open buffers, estimated from the size of their text.
+    pub estimated_syntax_tree_bytes: usize,
+    /// The number of laid out lines cached by the windows.
+    pub cached_line_layouts: usize,
+    /// The number of entries indexed for the worktrees.
+    pub index_entries: usize,
+    /// The number of open buffers whose syntax trees are evicted.
+    pub evicted_syntax_trees: usize,
+    /// The number of times memory was freed since launch.
+    pub evictions: usize,
+}
+
+impl MemoryMetrics {
+    /// The bytes of the laid out lines, estimated from their count.
+    pub fn estimated_line_layout_bytes(&self) -> usize {
+        self.cached_line_layouts * LINE_LAYOUT_BYTES
+    }
+
+    /// The bytes of the indexed entries, estimated from their count.
+    pub fn estimated_index_bytes(&self) -> usize {
+        self.index_entries * INDEX_ENTRY_BYTES
+    }
+
+    /// The estimated bytes used in total.
+    pub fn estimated_bytes(&self) -> usize {
+        self.rope_bytes
+            + self.estimated_syntax_tree_bytes
+            + self.estimated_line_layout_bytes()
+            + self.estimated_index_bytes()
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryGovernor {
+    metrics: MemoryMetrics,
+}
+
+impl Global for MemoryGovernor {}
+
+/// What the items of the workspaces show, as the entity ids of their project items.
+#[derive(Default)]
+struct ShownItems {
+    /// The project items shown by the active items of the panes.
+    active: HashSet<EntityId>,
+    /// The project items shown by any item.
+    all: HashSet<EntityId>,
+}
+
+impl MemoryGovernor {
+    pub(crate) fn init(cx: &mut App) {
+        cx.set_global(Self::default());
+        cx.spawn(|cx| async move {
+            loop {
+                cx.background_executor().timer(SAMPLE_INTERVAL).await;
+                if cx.update(Self::sample).is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+    }
+
+    /// The last sample of the memory used by the workspaces.
+    pub fn metrics(cx: &App) -> Option<MemoryMetrics> {
+        cx.try_global::<Self>().map(|governor| governor.metrics)
+    }
+
+    /// Samples the memory used, and frees what can be computed again when it's over budget.
+    pub fn sample(cx: &mut App) {
+        let workspaces = cx
+            .windows()
+            .into_iter()
+            .filter_map(|window| window.downcast::<Workspace>())
+            .collect::<Vec<_>>();
+        let mut metrics = MemoryMetrics {
+            evictions: Self::metrics(cx).unwrap_or_default().evictions,
+            ..MemoryMetrics::default()
+        };
+        let mut shown = ShownItems::default();
+        let mut buffers = Vec::new();
+        for workspace in &workspaces {
+            workspace
+                .update(cx, |workspace, window, cx| {
+                    metrics.cached_line_layouts += window.text_system().cached_line_count();
+                    for pane in workspace.panes() {
+                        let pane = pane.read(cx);
+                        for item in pane.items() {
+                            item.for_each_project_item(cx, &mut |id, _| {
+                                shown.all.insert(id);
+                            });
+                        }
+                        if let Some(item) = pane.active_item() {
+                            item.for_each_project_item(cx, &mut |id, _| {
+                                shown.active.insert(id);
+                            });
+                        }
+                    }
+
+                    let project = workspace.project().clone();
+                    for worktree in project.read(cx).worktrees(cx) {
+                        metrics.index_entries += worktree.read(cx).entry_count();
+                    }
+                    for buffer in project.read(cx).opened_buffers(cx) {
+                        buffers.push((buffer, project.clone()));
+                    }
+                })
+                .ok();
+        }
+        for (buffer, _) in &buffers {
+            let buffer = buffer.read(cx);
+            metrics.rope_bytes += buffer.len();
+            if buffer.is_syntax_tree_evicted() {
+                metrics.evicted_syntax_trees += 1;
+            } else if buffer.language().is_some() {
+                metrics.estimated_syntax_tree_bytes +=
+                    buffer.len() * SYNTAX_TREE_BYTES_PER_TEXT_BYTE;
+            }
+        }
+
+        let settings = WorkspaceSettings::get_global(cx);
+        let budget = settings.memory_budget_mb as usize * 1024 * 1024;
+        let over_budget = metrics.estimated_bytes() > budget;
+        if over_budget || settings.low_memory_mode {
+            let estimated_bytes = metrics.estimated_bytes();
+            let released_diff_bases = Self::evict(&mut metrics, &workspaces, &buffers, &shown, cx);
+            if over_budget {
+                log::info!(
+                    "estimated memory use of {}MB is over its budget of {}MB, evicted caches down to {}MB",
+                    estimated_bytes / (1024 * 1024),
+                    budget / (1024 * 1024),
+                    metrics.estimated_bytes() / (1024 * 1024),
+                );
+                telemetry::event!(
+                    "Memory Over Budget",
+                    estimated_bytes,
+                    estimated_bytes_after_eviction = metrics.estimated_bytes(),
+                    evicted_syntax_trees = metrics.evicted_syntax_trees,
+                    released_diff_bases
+                );
+            }
+        }
+        cx.default_global::<Self>().metrics = metrics;
+    }
+
+    /// Evicts everything that can be computed again, returning the number of buffers whose diff
+    /// bases were released.
+    fn evict(
+        metrics: &mut MemoryMetrics,
+        workspaces: &[WindowHandle<Workspace>],
+        buffers: &[(Entity<Buffer>, Entity<Project>)],
+        shown: &ShownItems,
+        cx: &mut App,
+    ) -> usize {
+        metrics.evictions += 1;
+        metrics.cached_line_layouts = 0;
+        for workspace in workspaces {
+            workspace
+                .update(cx, |_, window, _| {
+                    let text_system = window.text_system();
+                    text_system.evict_line_layouts();
+                    metrics.cached_line_layouts += text_system.cached_line_count();
+                })
+                .ok();
+        }
+
+        let mut released_diff_bases = 0;
+        for (buffer, project) in buffers {
+            let buffer_entity_id = buffer.entity_id();
+            if shown.active.contains(&buffer_entity_id) {
+                continue;
+            }
+            let len = buffer.read(cx).len();
+            if buffer.update(cx, |buffer, cx| buffer.evict_syntax_tree(cx)) {
+                metrics.estimated_syntax_tree_bytes = metrics
+                    .estimated_syntax_tree_bytes
+                    .saturating_sub(len * SYNTAX_TREE_BYTES_PER_TEXT_BYTE);
+                metrics.evicted_syntax_trees += 1;
+            }
+
+            if !shown.all.contains(&buffer_entity_id) && !buffer.read(cx).is_dirty() {
+                let buffer_id = buffer.read(cx).remote_id();
+                let buffer_store = project.read(cx).buffer_store().clone();
+                if buffer_store.update(cx, |buffer_store, cx| {
+                    buffer_store.release_diff_bases(buffer_id, cx)
+                }) {
+                    released_diff_bases += 1;
+                }
+            }
+        }
+        released_diff_bases
+    }
+}
+
+/// Parses the buffers shown by the item again, if their syntax trees were evicted.
+pub(crate) fn restore_syntax_trees(item: &dyn ItemHandle, project: &Entity<Project>, cx: &mut App) {
+    if MemoryGovernor::metrics(cx).map_or(true, |metrics| metrics.evicted_syntax_trees == 0) {
+        return;
+    }
+
+    let mut shown = HashSet::default();
+    item.for_each_project_item(cx, &mut |id, _| {
+        shown.insert(id);
+    });
+    for buffer in project.read(cx).opened_buffers(cx) {
+        if shown.contains(&buffer.entity_id()) && buffer.read(cx).is_syntax_tree_evicted() {
+            buffer.update(cx, |buffer, cx| buffer.restore_syntax_tree(cx));
+        }
+    }
+}
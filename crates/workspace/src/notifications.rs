@@ -1,7 +1,7 @@
 use crate::{Toast, Workspace};
 use gpui::{
     svg, AnyView, App, AppContext as _, AsyncWindowContext, ClipboardItem, Context, DismissEvent,
-    Entity, EventEmitter, PromptLevel, Render, ScrollHandle, Task,
+    Entity, EventEmitter, PromptLevel, Render, ScrollHandle, Task, TaskRecovery,
 };
 use parking_lot::Mutex;
 use std::sync::{Arc, LazyLock};
@@ -780,6 +780,39 @@ where
     }
 }
 
+/// Shows the panics of supervised background tasks, in one notification per task, leaving
+/// their messages to the log.
+pub fn show_task_panics(cx: &mut App) {
+    struct TaskPanicNotification;
+
+    cx.on_task_panic(|panic, recovery, cx| {
+        let message: SharedString = match recovery {
+            TaskRecovery::Restarted => format!(
+                "The {} stopped unexpectedly and was restarted.",
+                panic.task_name
+            ),
+            TaskRecovery::Degraded => format!(
+                "The {} stopped unexpectedly and wasn't restarted.",
+                panic.task_name
+            ),
+        }
+        .into();
+        let id = NotificationId::composite::<TaskPanicNotification>(panic.task_name.clone());
+        show_app_notification(id, cx, move |cx| {
+            cx.new({
+                let message = message.clone();
+                move |_cx| {
+                    simple_message_notification::MessageNotification::new(message)
+                        .primary_message("Open Log")
+                        .primary_on_click(|window, cx| {
+                            window.dispatch_action(Box::new(crate::OpenLog), cx)
+                        })
+                }
+            })
+        });
+    });
+}
+
 pub trait NotifyTaskExt {
     fn detach_and_notify_err(self, window: &mut Window, cx: &mut App);
 }
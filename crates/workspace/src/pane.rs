@@ -1,14 +1,16 @@
 use crate::{
     item::{
-        ActivateOnClose, ClosePosition, Item, ItemHandle, ItemSettings, PreviewTabsSettings,
-        ShowDiagnostics, TabContentParams, TabTooltipContent, WeakItemHandle,
+        ActivateOnClose, ClosePosition, ClosedItemState, Item, ItemHandle, ItemSettings,
+        PreviewTabsSettings, ShowDiagnostics, TabContentParams, TabTooltipContent, WeakItemHandle,
     },
     move_item,
     notifications::NotifyResultExt,
+    prompt_in_workspace,
     toolbar::Toolbar,
     workspace_settings::{AutosaveSetting, TabBarSettings, WorkspaceSettings},
     CloseWindow, CopyPath, CopyRelativePath, NewFile, NewTerminal, OpenInTerminal, OpenTerminal,
-    OpenVisible, SplitDirection, ToggleFileFinder, ToggleProjectSymbols, ToggleZoom, Workspace,
+    OpenVisible, PromptOptions, SplitDirection, ToggleFileFinder, ToggleProjectSymbols, ToggleZoom,
+    Workspace,
 };
 use anyhow::Result;
 use collections::{BTreeSet, HashMap, HashSet, VecDeque};
@@ -23,7 +25,7 @@ use gpui::{
 use itertools::Itertools;
 use language::DiagnosticSeverity;
 use parking_lot::Mutex;
-use project::{Project, ProjectEntryId, ProjectPath, WorktreeId};
+use project::{DiagnosticBadge, Project, ProjectEntryId, ProjectPath, WorktreeId};
 use schemars::JsonSchema;
 use serde::Deserialize;
 use settings::{Settings, SettingsStore};
@@ -318,7 +320,7 @@ pub struct Pane {
     pub new_item_context_menu_handle: PopoverMenuHandle<ContextMenu>,
     pub split_item_context_menu_handle: PopoverMenuHandle<ContextMenu>,
     pinned_tab_count: usize,
-    diagnostics: HashMap<ProjectPath, DiagnosticSeverity>,
+    diagnostics: HashMap<ProjectPath, DiagnosticBadge>,
     zoom_out_on_close: bool,
 }
 
@@ -342,6 +344,8 @@ struct NavHistoryState {
     forward_stack: VecDeque<NavigationEntry>,
     closed_stack: VecDeque<NavigationEntry>,
     paths_by_item: HashMap<EntityId, (ProjectPath, Option<PathBuf>)>,
+    /// The state of the closed items, persisted with their paths.
+    closed_states: HashMap<EntityId, ClosedItemState>,
     pane: WeakEntity<Pane>,
     next_timestamp: Arc<AtomicUsize>,
 }
@@ -397,7 +401,12 @@ impl Pane {
             cx.on_focus_in(&focus_handle, window, Pane::focus_in),
             cx.on_focus_out(&focus_handle, window, Pane::focus_out),
             cx.observe_global::<SettingsStore>(Self::settings_changed),
-            cx.subscribe(&project, Self::project_events),
+            cx.observe(project.read(cx).diagnostic_badges(), |this, _, cx| {
+                if ItemSettings::get_global(cx).show_diagnostics != ShowDiagnostics::Off {
+                    this.update_diagnostics(cx);
+                    cx.notify();
+                }
+            }),
         ];
 
         let handle = cx.entity().downgrade();
@@ -418,6 +427,7 @@ impl Pane {
                 forward_stack: Default::default(),
                 closed_stack: Default::default(),
                 paths_by_item: Default::default(),
+                closed_states: Default::default(),
                 pane: handle.clone(),
                 next_timestamp,
             }))),
@@ -623,24 +633,6 @@ impl Pane {
         cx.notify();
     }
 
-    fn project_events(
-        &mut self,
-        _project: Entity<Project>,
-        event: &project::Event,
-        cx: &mut Context<Self>,
-    ) {
-        match event {
-            project::Event::DiskBasedDiagnosticsFinished { .. }
-            | project::Event::DiagnosticsUpdated { .. } => {
-                if ItemSettings::get_global(cx).show_diagnostics != ShowDiagnostics::Off {
-                    self.update_diagnostics(cx);
-                    cx.notify();
-                }
-            }
-            _ => {}
-        }
-    }
-
     fn update_diagnostics(&mut self, cx: &mut Context<Self>) {
         let Some(project) = self.project.upgrade() else {
             return;
@@ -649,17 +641,16 @@ impl Pane {
         self.diagnostics = if show_diagnostics != ShowDiagnostics::Off {
             project
                 .read(cx)
-                .diagnostic_summaries(false, cx)
-                .filter_map(|(project_path, _, diagnostic_summary)| {
-                    if diagnostic_summary.error_count > 0 {
-                        Some((project_path, DiagnosticSeverity::ERROR))
-                    } else if diagnostic_summary.warning_count > 0
-                        && show_diagnostics != ShowDiagnostics::Errors
-                    {
-                        Some((project_path, DiagnosticSeverity::WARNING))
-                    } else {
-                        None
+                .diagnostic_badges()
+                .read(cx)
+                .badges()
+                .filter_map(|(project_path, badge)| {
+                    let mut badge = *badge;
+                    if show_diagnostics == ShowDiagnostics::Errors {
+                        badge.warning_count = 0;
                     }
+                    badge.max_severity()?;
+                    Some((project_path.clone(), badge))
                 })
                 .collect()
         } else {
@@ -1453,7 +1444,7 @@ impl Pane {
             .for_each(|&index| self._remove_item(index, false, false, None, window, cx));
     }
 
-    pub(super) fn file_names_for_prompt(
+    pub fn file_names_for_prompt(
         items: &mut dyn Iterator<Item = &Box<dyn ItemHandle>>,
         all_dirty_items: usize,
         cx: &App,
@@ -1481,13 +1472,15 @@ impl Pane {
                 file_names.push(format!(".. {} files not shown", not_shown_files));
             }
         }
-        (
+        let prompt = if all_dirty_items == 1 {
+            "Do you want to save changes to the following file?".to_string()
+        } else {
             format!(
                 "Do you want to save changes to the following {} files?",
                 all_dirty_items
-            ),
-            file_names.join("\n"),
-        )
+            )
+        };
+        (prompt, file_names.join("\n"))
     }
 
     pub fn close_items(
@@ -1531,17 +1524,25 @@ impl Pane {
                 let answer = pane.update_in(&mut cx, |_, window, cx| {
                     let (prompt, detail) =
                         Self::file_names_for_prompt(&mut dirty_items.iter(), dirty_items.len(), cx);
-                    window.prompt(
-                        PromptLevel::Warning,
-                        &prompt,
-                        Some(&detail),
-                        &["Save all", "Discard all", "Cancel"],
-                        cx,
-                    )
+                    let options = PromptOptions::new(PromptLevel::Warning, prompt)
+                        .detail(detail)
+                        .choice("Save all")
+                        .choice("Discard all")
+                        .cancel("Cancel");
+                    prompt_in_workspace(&workspace, options, window, cx)
                 })?;
                 match answer.await {
                     Ok(0) => save_intent = SaveIntent::SaveAll,
-                    Ok(1) => save_intent = SaveIntent::Skip,
+                    Ok(1) => {
+                        save_intent = SaveIntent::Skip;
+                        let project =
+                            workspace.read_with(&cx, |workspace, _| workspace.project().clone())?;
+                        cx.update(|window, cx| {
+                            for item in &dirty_items {
+                                item.discarded(project.clone(), window, cx);
+                            }
+                        })?;
+                    }
                     _ => {}
                 }
             }
@@ -1740,6 +1741,13 @@ impl Pane {
         self.nav_history.set_mode(NavigationMode::ClosingItem);
         item.deactivated(window, cx);
         self.nav_history.set_mode(mode);
+        if let Some(state) = item.closed_state(cx) {
+            self.nav_history
+                .0
+                .lock()
+                .closed_states
+                .insert(item.item_id(), state);
+        }
 
         if self.is_active_preview_item(item.item_id()) {
             self.set_preview_item_id(None, cx);
@@ -1826,13 +1834,11 @@ impl Pane {
             if has_deleted_file && is_singleton {
                 let answer = pane.update_in(cx, |pane, window, cx| {
                     pane.activate_item(item_ix, true, true, window, cx);
-                    window.prompt(
-                        PromptLevel::Warning,
-                        DELETED_MESSAGE,
-                        None,
-                        &["Save", "Close", "Cancel"],
-                        cx,
-                    )
+                    let options = PromptOptions::new(PromptLevel::Warning, DELETED_MESSAGE)
+                        .choice("Save")
+                        .choice("Close")
+                        .cancel("Cancel");
+                    prompt_in_workspace(&pane.workspace, options, window, cx)
                 })?;
                 match answer.await {
                     Ok(0) => {
@@ -1850,24 +1856,31 @@ impl Pane {
                 }
                 return Ok(true);
             } else {
+                let can_merge = cx.update(|_, cx| item.can_merge(cx))?;
                 let answer = pane.update_in(cx, |pane, window, cx| {
                     pane.activate_item(item_ix, true, true, window, cx);
-                    window.prompt(
-                        PromptLevel::Warning,
-                        CONFLICT_MESSAGE,
-                        None,
-                        &["Overwrite", "Discard", "Cancel"],
-                        cx,
-                    )
+                    let mut options = PromptOptions::new(PromptLevel::Warning, CONFLICT_MESSAGE)
+                        .choice("Overwrite");
+                    if can_merge {
+                        options = options.choice("Merge");
+                    }
+                    let options = options.choice("Discard").cancel("Cancel");
+                    prompt_in_workspace(&pane.workspace, options, window, cx)
                 })?;
-                match answer.await {
-                    Ok(0) => {
+                match (answer.await, can_merge) {
+                    (Ok(0), _) => {
                         pane.update_in(cx, |_, window, cx| {
                             item.save(should_format, project, window, cx)
                         })?
                         .await?
                     }
-                    Ok(1) => {
+                    // The conflicting changes are opened for the user to resolve before saving.
+                    (Ok(1), true) => {
+                        pane.update_in(cx, |_, window, cx| item.merge(true, window, cx))?
+                            .await?;
+                        return Ok(false);
+                    }
+                    (Ok(1), false) | (Ok(2), true) => {
                         pane.update_in(cx, |_, window, cx| item.reload(project, window, cx))?
                             .await?
                     }
@@ -1888,13 +1901,11 @@ impl Pane {
                         if pane.save_modals_spawned.insert(item_id) {
                             pane.activate_item(item_ix, true, true, window, cx);
                             let prompt = dirty_message_for(item.project_path(cx));
-                            Some(window.prompt(
-                                PromptLevel::Warning,
-                                &prompt,
-                                None,
-                                &["Save", "Don't Save", "Cancel"],
-                                cx,
-                            ))
+                            let options = PromptOptions::new(PromptLevel::Warning, prompt)
+                                .choice("Save")
+                                .choice("Don't Save")
+                                .cancel("Cancel");
+                            Some(prompt_in_workspace(&pane.workspace, options, window, cx))
                         } else {
                             None
                         }
@@ -1984,6 +1995,15 @@ impl Pane {
         );
         if Self::can_autosave_item(item, cx) {
             item.save(format, project, window, cx)
+        } else if item.has_conflict(cx) && item.can_merge(cx) {
+            // Make the changes made on disk that don't conflict with the item's. The item is
+            // saved by the next autosave once nothing conflicts, and the item doesn't merge again
+            // while it's known to conflict.
+            let merge = item.merge(false, window, cx);
+            cx.spawn(|_| async move {
+                merge.await?;
+                Ok(())
+            })
         } else {
             Task::ready(Ok(()))
         }
@@ -2180,9 +2200,10 @@ impl Pane {
             cx,
         );
 
-        let item_diagnostic = item
+        let item_badge = item
             .project_path(cx)
-            .map_or(None, |project_path| self.diagnostics.get(&project_path));
+            .and_then(|project_path| self.diagnostics.get(&project_path).copied());
+        let item_diagnostic = item_badge.and_then(|badge| badge.max_severity());
 
         let decorated_icon = item_diagnostic.map_or(None, |diagnostic| {
             let icon = match item.tab_icon(window, cx) {
@@ -2196,8 +2217,7 @@ impl Pane {
                 cx.theme().colors().tab_bar_background
             };
 
-            let (icon_decoration, icon_color) = if matches!(diagnostic, &DiagnosticSeverity::ERROR)
-            {
+            let (icon_decoration, icon_color) = if diagnostic == DiagnosticSeverity::ERROR {
                 (IconDecorationKind::X, Color::Error)
             } else {
                 (IconDecorationKind::Triangle, Color::Warning)
@@ -2218,8 +2238,8 @@ impl Pane {
 
         let icon = if decorated_icon.is_none() {
             match item_diagnostic {
-                Some(&DiagnosticSeverity::ERROR) => None,
-                Some(&DiagnosticSeverity::WARNING) => None,
+                Some(DiagnosticSeverity::ERROR) => None,
+                Some(DiagnosticSeverity::WARNING) => None,
                 _ => item
                     .tab_icon(window, cx)
                     .map(|icon| icon.color(Color::Muted)),
@@ -2380,7 +2400,19 @@ impl Pane {
                         })
                         .flatten(),
                     )
-                    .child(label),
+                    .child(label)
+                    .when_some(item_badge, |this, badge| {
+                        let color = if badge.error_count > 0 {
+                            Color::Error
+                        } else {
+                            Color::Warning
+                        };
+                        this.child(
+                            Label::new(badge.count().to_string())
+                                .size(LabelSize::XSmall)
+                                .color(color),
+                        )
+                    }),
             );
 
         let single_entry_to_resolve = {
@@ -3466,7 +3498,16 @@ impl NavHistory {
             }
             NavigationMode::ClosingItem => {
                 if state.closed_stack.len() >= MAX_NAVIGATION_HISTORY_LEN {
-                    state.closed_stack.pop_front();
+                    if let Some(evicted) = state.closed_stack.pop_front() {
+                        let evicted_id = evicted.item.id();
+                        if !state
+                            .closed_stack
+                            .iter()
+                            .any(|entry| entry.item.id() == evicted_id)
+                        {
+                            state.closed_states.remove(&evicted_id);
+                        }
+                    }
                 }
                 state.closed_stack.push_back(NavigationEntry {
                     item,
@@ -3482,6 +3523,7 @@ impl NavHistory {
     pub fn remove_item(&mut self, item_id: EntityId) {
         let mut state = self.0.lock();
         state.paths_by_item.remove(&item_id);
+        state.closed_states.remove(&item_id);
         state
             .backward_stack
             .retain(|entry| entry.item.id() != item_id);
@@ -3496,6 +3538,26 @@ impl NavHistory {
     pub fn path_for_item(&self, item_id: EntityId) -> Option<(ProjectPath, Option<PathBuf>)> {
         self.0.lock().paths_by_item.get(&item_id).cloned()
     }
+
+    pub fn has_closed_items(&self) -> bool {
+        !self.0.lock().closed_stack.is_empty()
+    }
+
+    /// Returns the closed items that can be reopened, from the least to the most recently
+    /// closed, along with the timestamps they were closed at.
+    pub fn closed_items(&self) -> Vec<(usize, PathBuf, Option<ClosedItemState>)> {
+        let state = self.0.lock();
+        state
+            .closed_stack
+            .iter()
+            .filter_map(|entry| {
+                let item_id = entry.item.id();
+                let abs_path = state.paths_by_item.get(&item_id)?.1.clone()?;
+                let closed_state = state.closed_states.get(&item_id).cloned();
+                Some((entry.timestamp, abs_path, closed_state))
+            })
+            .collect()
+    }
 }
 
 impl NavHistoryState {
@@ -29,25 +29,44 @@ const VERTICAL_MIN_SIZE: f32 = 100.;
 #[derive(Clone)]
 pub struct PaneGroup {
     pub root: Member,
+    /// The pane rendered in place of the whole group, while the other ones are hidden. The
+    /// layout tree is kept as is, to be rendered again once the pane is restored.
+    maximized: Option<Entity<Pane>>,
 }
 
 impl PaneGroup {
     pub fn with_root(root: Member) -> Self {
-        Self { root }
+        Self {
+            root,
+            maximized: None,
+        }
     }
 
     pub fn new(pane: Entity<Pane>) -> Self {
         Self {
             root: Member::Pane(pane),
+            maximized: None,
         }
     }
 
+    pub fn maximized_pane(&self) -> Option<&Entity<Pane>> {
+        self.maximized.as_ref()
+    }
+
+    /// Renders the pane in place of the whole group, or the whole group again when `None`.
+    /// Panes not in the group, or the only pane of the group, are not maximized.
+    pub fn set_maximized_pane(&mut self, pane: Option<Entity<Pane>>) {
+        self.maximized =
+            pane.filter(|pane| matches!(self.root, Member::Axis(_)) && self.root.contains(pane));
+    }
+
     pub fn split(
         &mut self,
         old_pane: &Entity<Pane>,
         new_pane: &Entity<Pane>,
         direction: SplitDirection,
     ) -> Result<()> {
+        self.maximized = None;
         match &mut self.root {
             Member::Pane(pane) => {
                 if pane == old_pane {
@@ -86,6 +105,9 @@ impl PaneGroup {
                 if let Some(last_pane) = axis.remove(pane)? {
                     self.root = last_pane;
                 }
+                if self.maximized.as_ref() == Some(pane) || matches!(self.root, Member::Pane(_)) {
+                    self.maximized = None;
+                }
                 Ok(true)
             }
         }
@@ -134,7 +156,15 @@ impl PaneGroup {
         window: &mut Window,
         cx: &mut Context<Workspace>,
     ) -> impl IntoElement {
-        self.root.render(
+        let maximized_root;
+        let root = match &self.maximized {
+            Some(pane) => {
+                maximized_root = Member::Pane(pane.clone());
+                &maximized_root
+            }
+            None => &self.root,
+        };
+        root.render(
             project,
             0,
             follower_states,
@@ -416,9 +446,19 @@ impl PaneAxis {
         }
     }
 
+    /// Restores an axis with the flexes it was saved with, falling back to members of equal
+    /// sizes when they don't match its members.
     pub fn load(axis: Axis, members: Vec<Member>, flexes: Option<Vec<f32>>) -> Self {
-        let flexes = flexes.unwrap_or_else(|| vec![1.; members.len()]);
-        // debug_assert!(members.len() == flexes.len());
+        let flexes = flexes
+            .filter(|flexes| {
+                flexes.len() == members.len()
+                    && flexes.iter().all(|flex| flex.is_finite() && *flex > 0.)
+            })
+            .map(|mut flexes| {
+                normalize_flexes(&mut flexes);
+                flexes
+            })
+            .unwrap_or_else(|| vec![1.; members.len()]);
 
         let flexes = Arc::new(Mutex::new(flexes));
         let bounding_boxes = Arc::new(Mutex::new(vec![None; members.len()]));
@@ -446,12 +486,18 @@ impl PaneAxis {
                 Member::Pane(pane) => {
                     if pane == old_pane {
                         if direction.axis() == self.axis {
+                            // The new pane takes half of the old one's size, leaving the
+                            // others as they were.
+                            let mut flexes = self.flexes.lock();
+                            let half = flexes[idx] / 2.;
+                            flexes[idx] = half;
                             if direction.increasing() {
                                 idx += 1;
                             }
 
                             self.members.insert(idx, Member::Pane(new_pane.clone()));
-                            *self.flexes.lock() = vec![1.; self.members.len()];
+                            flexes.insert(idx, half);
+                            normalize_flexes(&mut flexes);
                         } else {
                             *member =
                                 Member::new_axis(old_pane.clone(), new_pane.clone(), direction);
@@ -490,8 +536,14 @@ impl PaneAxis {
 
         if found_pane {
             if let Some(idx) = remove_member {
+                // The size of the removed pane goes to its neighbor.
                 self.members.remove(idx);
-                *self.flexes.lock() = vec![1.; self.members.len()];
+                let mut flexes = self.flexes.lock();
+                let flex = flexes.remove(idx);
+                if let Some(neighbor) = flexes.get_mut(idx.saturating_sub(1)) {
+                    *neighbor += flex;
+                }
+                normalize_flexes(&mut flexes);
             }
 
             if self.members.len() == 1 {
@@ -724,6 +776,18 @@ impl PaneAxis {
     }
 }
 
+/// Scales the flexes of an axis so that they add up to the number of its members, which is what
+/// their sizes are laid out relative to, keeping their proportions.
+fn normalize_flexes(flexes: &mut [f32]) {
+    let total = flexes.iter().sum::<f32>();
+    if total > 0. {
+        let scale = flexes.len() as f32 / total;
+        for flex in flexes.iter_mut() {
+            *flex *= scale;
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum SplitDirection {
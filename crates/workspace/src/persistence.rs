@@ -1,6 +1,9 @@
 pub mod model;
 
-use std::{path::Path, str::FromStr};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use anyhow::{anyhow, bail, Context, Result};
 use client::DevServerProjectId;
@@ -19,7 +22,7 @@ use ui::px;
 use util::{maybe, ResultExt};
 use uuid::Uuid;
 
-use crate::WorkspaceId;
+use crate::{item::ClosedItemState, WorkspaceId};
 
 use model::{
     GroupId, LocalPaths, PaneId, SerializedItem, SerializedPane, SerializedPaneGroup,
@@ -383,6 +386,17 @@ define_connection! {
     sql!(
         ALTER TABLE toolchains ADD COLUMN raw_json TEXT DEFAULT "{}";
     ),
+    sql!(
+        CREATE TABLE closed_items (
+            workspace_id INTEGER NOT NULL,
+            position INTEGER NOT NULL,
+            abs_path BLOB NOT NULL,
+            state TEXT,
+            FOREIGN KEY(workspace_id) REFERENCES workspaces(workspace_id)
+            ON DELETE CASCADE,
+            PRIMARY KEY(workspace_id, position)
+        ) STRICT;
+    ),
     ];
 }
 
@@ -1074,6 +1088,63 @@ impl WorkspaceDb {
         }
     }
 
+    query! {
+        async fn closed_item_rows(workspace_id: WorkspaceId) -> Result<Vec<(PathBuf, Option<String>)>> {
+            SELECT abs_path, state
+            FROM closed_items
+            WHERE workspace_id = ?
+            ORDER BY position
+        }
+    }
+
+    /// Returns the items that can be reopened in the given workspace, from the least to the
+    /// most recently closed.
+    pub(crate) async fn closed_items(
+        &self,
+        workspace_id: WorkspaceId,
+    ) -> Result<Vec<(PathBuf, Option<ClosedItemState>)>> {
+        Ok(self
+            .closed_item_rows(workspace_id)
+            .await?
+            .into_iter()
+            .map(|(abs_path, state)| {
+                let state = state.and_then(|state| serde_json::from_str(&state).log_err());
+                (abs_path, state)
+            })
+            .collect())
+    }
+
+    /// Replaces the items that can be reopened in the given workspace, which are
+    /// ordered from the least to the most recently closed.
+    pub(crate) async fn set_closed_items(
+        &self,
+        workspace_id: WorkspaceId,
+        closed_items: Vec<(PathBuf, Option<ClosedItemState>)>,
+    ) -> Result<()> {
+        self.write(move |conn| {
+            conn.exec_bound(sql!(
+                DELETE FROM closed_items WHERE workspace_id = ?
+            ))?(workspace_id)
+            .context("Clearing old closed items")?;
+
+            let mut insert = conn
+                .exec_bound(sql!(
+                    INSERT INTO closed_items(workspace_id, position, abs_path, state)
+                    VALUES (?, ?, ?, ?)
+                ))
+                .context("Preparing insertion")?;
+            for (position, (abs_path, state)) in closed_items.into_iter().enumerate() {
+                let state = state
+                    .map(|state| serde_json::to_string(&state))
+                    .transpose()?;
+                insert((workspace_id, position, abs_path, state))?;
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
     pub async fn toolchain(
         &self,
         workspace_id: WorkspaceId,
@@ -1376,6 +1447,48 @@ mod tests {
         assert_eq!(workspace, round_trip_workspace.unwrap());
     }
 
+    #[gpui::test]
+    async fn test_closed_items() {
+        env_logger::try_init().ok();
+
+        let db = WorkspaceDb(open_test_db("test_closed_items").await);
+        let workspace = SerializedWorkspace {
+            id: WorkspaceId(1),
+            location: SerializedWorkspaceLocation::from_local_paths(["/tmp"]),
+            center_group: Default::default(),
+            window_bounds: Default::default(),
+            display: Default::default(),
+            docks: Default::default(),
+            centered_layout: false,
+            session_id: None,
+            window_id: None,
+        };
+        db.save_workspace(workspace).await;
+        assert_eq!(db.closed_items(WorkspaceId(1)).await.unwrap(), Vec::new());
+
+        let state = ClosedItemState {
+            cursor: (3, 4),
+            scroll_top_row: 1,
+            folds: vec![(5, 0)..(8, 1)],
+        };
+        let closed_items = vec![
+            (PathBuf::from("/tmp/a.rs"), Some(state)),
+            (PathBuf::from("/tmp/b.rs"), None),
+        ];
+        db.set_closed_items(WorkspaceId(1), closed_items.clone())
+            .await
+            .unwrap();
+        assert_eq!(db.closed_items(WorkspaceId(1)).await.unwrap(), closed_items);
+
+        db.set_closed_items(WorkspaceId(1), vec![(PathBuf::from("/tmp/c.rs"), None)])
+            .await
+            .unwrap();
+        assert_eq!(
+            db.closed_items(WorkspaceId(1)).await.unwrap(),
+            vec![(PathBuf::from("/tmp/c.rs"), None)]
+        );
+    }
+
     #[gpui::test]
     async fn test_workspace_assignment() {
         env_logger::try_init().ok();
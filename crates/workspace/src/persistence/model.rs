@@ -371,7 +371,11 @@ impl SerializedPaneGroup {
                 let mut current_active_pane = None;
                 let mut members = Vec::new();
                 let mut items = Vec::new();
-                for child in children {
+                // Keep the flexes of the children that could be restored, so that they still
+                // match the members.
+                let flexes = flexes.filter(|flexes| flexes.len() == children.len());
+                let mut member_flexes = flexes.as_ref().map(|_| Vec::new());
+                for (ix, child) in children.into_iter().enumerate() {
                     if let Some((new_member, active_pane, new_items)) = child
                         .deserialize(project, workspace_id, workspace.clone(), cx)
                         .await
@@ -379,6 +383,11 @@ impl SerializedPaneGroup {
                         members.push(new_member);
                         items.extend(new_items);
                         current_active_pane = current_active_pane.or(active_pane);
+                        if let Some((member_flexes, flexes)) =
+                            member_flexes.as_mut().zip(flexes.as_ref())
+                        {
+                            member_flexes.push(flexes[ix]);
+                        }
                     }
                 }
 
@@ -391,7 +400,7 @@ impl SerializedPaneGroup {
                 }
 
                 Some((
-                    Member::Axis(PaneAxis::load(axis.0, members, flexes)),
+                    Member::Axis(PaneAxis::load(axis.0, members, member_flexes)),
                     current_active_pane,
                     items,
                 ))
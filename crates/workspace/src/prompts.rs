@@ -0,0 +1,170 @@
+//! Confirmations asked of the user, queued so that a workspace shows one at a time.
+//!
+//! A window only shows one prompt, and showing another one dismisses the first. Prompts asked
+//! through [`Workspace::prompt`] wait for the ones asked before them to be answered instead. The
+//! first choice of a prompt is the one confirmed with the keyboard, and its cancel choice the one
+//! made with escape, or when the prompt is dismissed along with its window. Tests can script the
+//! answers to the prompts to come, by the labels of their choices, with
+//! [`Workspace::script_prompt_answers`].
+
+use crate::Workspace;
+use anyhow::{anyhow, Result};
+use futures::channel::oneshot;
+use gpui::{App, Context, PromptLevel, SharedString, Task, WeakEntity, Window};
+use std::collections::VecDeque;
+
+/// A confirmation asked of the user.
+#[derive(Clone, Debug)]
+pub struct PromptOptions {
+    pub level: PromptLevel,
+    pub message: SharedString,
+    pub detail: Option<SharedString>,
+    /// The labels of the choices, the first one being confirmed with the keyboard.
+    pub choices: Vec<SharedString>,
+    /// The index of the choice made when the prompt is dismissed.
+    pub cancel: Option<usize>,
+}
+
+impl PromptOptions {
+    pub fn new(level: PromptLevel, message: impl Into<SharedString>) -> Self {
+        Self {
+            level,
+            message: message.into(),
+            detail: None,
+            choices: Vec::new(),
+            cancel: None,
+        }
+    }
+
+    pub fn detail(mut self, detail: impl Into<SharedString>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn choice(mut self, label: impl Into<SharedString>) -> Self {
+        self.choices.push(label.into());
+        self
+    }
+
+    /// Adds the choice made when the prompt is dismissed.
+    pub fn cancel(mut self, label: impl Into<SharedString>) -> Self {
+        self.cancel = Some(self.choices.len());
+        self.choice(label)
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct PromptQueue {
+    pending: VecDeque<(PromptOptions, oneshot::Sender<usize>)>,
+    showing: bool,
+    scripted_answers: VecDeque<SharedString>,
+}
+
+impl Workspace {
+    /// Shows the prompt once the prompts asked before it are answered, resolving to the index of
+    /// the choice made.
+    pub fn prompt(
+        &mut self,
+        options: PromptOptions,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<usize>> {
+        let cancel = options.cancel;
+        let (answer_tx, answer_rx) = oneshot::channel();
+        self.prompts.pending.push_back((options, answer_tx));
+        self.show_next_prompt(window, cx);
+        cx.background_executor().spawn(async move {
+            match answer_rx.await {
+                Ok(answer) => Ok(answer),
+                Err(_) => cancel.ok_or_else(|| anyhow!("prompt was dismissed")),
+            }
+        })
+    }
+
+    /// Answers the prompts to come with the choices of the given labels, in order, without
+    /// showing them.
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn script_prompt_answers(&mut self, labels: impl IntoIterator<Item = &'static str>) {
+        self.prompts
+            .scripted_answers
+            .extend(labels.into_iter().map(SharedString::from));
+    }
+
+    fn show_next_prompt(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.prompts.showing {
+            return;
+        }
+        while let Some((options, answer_tx)) = self.prompts.pending.pop_front() {
+            if let Some(label) = self.prompts.scripted_answers.pop_front() {
+                let answer = options
+                    .choices
+                    .iter()
+                    .position(|choice| *choice == label)
+                    .unwrap_or_else(|| {
+                        panic!("{label:?} isn't a choice of prompt {:?}", options.message)
+                    });
+                answer_tx.send(answer).ok();
+                continue;
+            }
+
+            let choices = options
+                .choices
+                .iter()
+                .map(|choice| choice.as_ref())
+                .collect::<Vec<_>>();
+            let answer = window.prompt(
+                options.level,
+                &options.message,
+                options.detail.as_deref(),
+                &choices,
+                cx,
+            );
+            self.prompts.showing = true;
+            cx.spawn_in(window, |this, mut cx| async move {
+                if let Ok(answer) = answer.await {
+                    answer_tx.send(answer).ok();
+                }
+                this.update_in(&mut cx, |this, window, cx| {
+                    this.prompts.showing = false;
+                    this.show_next_prompt(window, cx);
+                })
+                .ok();
+            })
+            .detach();
+            return;
+        }
+    }
+}
+
+/// Asks the prompt through the workspace, or the window directly when the workspace was closed.
+pub fn prompt_in_workspace(
+    workspace: &WeakEntity<Workspace>,
+    options: PromptOptions,
+    window: &mut Window,
+    cx: &mut App,
+) -> Task<Result<usize>> {
+    if let Some(workspace) = workspace.upgrade() {
+        return workspace.update(cx, |workspace, cx| workspace.prompt(options, window, cx));
+    }
+
+    let choices = options
+        .choices
+        .iter()
+        .map(|choice| choice.as_ref())
+        .collect::<Vec<_>>();
+    let answer = window.prompt(
+        options.level,
+        &options.message,
+        options.detail.as_deref(),
+        &choices,
+        cx,
+    );
+    cx.background_executor().spawn(async move {
+        match answer.await {
+            Ok(answer) => Ok(answer),
+            Err(_) => options
+                .cancel
+                .ok_or_else(|| anyhow!("prompt was dismissed")),
+        }
+    })
+}
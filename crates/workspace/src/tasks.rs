@@ -3,7 +3,10 @@ use project::TaskSourceKind;
 use remote::ConnectionState;
 use task::{ResolvedTask, TaskContext, TaskTemplate};
 
-use crate::Workspace;
+use crate::{notifications::NotificationId, Toast, Workspace};
+
+/// The notification shown when a task of an untrusted worktree is scheduled.
+struct UntrustedWorktreeTask;
 
 pub fn schedule_task(
     workspace: &mut Workspace,
@@ -46,6 +49,31 @@ pub fn schedule_resolved_task(
     omit_history: bool,
     cx: &mut Context<Workspace>,
 ) {
+    let worktree_id = match &task_source_kind {
+        TaskSourceKind::Worktree { id, .. } | TaskSourceKind::Detected { id, .. } => Some(*id),
+        TaskSourceKind::UserInput
+        | TaskSourceKind::AbsPath { .. }
+        | TaskSourceKind::Language { .. } => None,
+    };
+    if let Some(worktree_id) = worktree_id {
+        if !workspace
+            .project
+            .read(cx)
+            .is_worktree_trusted(worktree_id, cx)
+        {
+            let message = format!(
+                "\"{}\" was not run, because its folder is not trusted. \
+                Run `workspace: trust project` to run the tasks of this project.",
+                resolved_task.resolved_label
+            );
+            workspace.show_toast(
+                Toast::new(NotificationId::unique::<UntrustedWorktreeTask>(), message),
+                cx,
+            );
+            return;
+        }
+    }
+
     if let Some(spawn_in_terminal) = resolved_task.resolved.take() {
         if !omit_history {
             resolved_task.resolved = Some(spawn_in_terminal.clone());
@@ -1,10 +1,13 @@
 pub mod dock;
+mod file_audit_modal;
 pub mod item;
+mod memory_governor;
 mod modal_layer;
 pub mod notifications;
 pub mod pane;
 pub mod pane_group;
 mod persistence;
+mod prompts;
 pub mod searchable;
 pub mod shared_screen;
 mod status_bar;
@@ -23,6 +26,7 @@ use collections::{hash_map, HashMap, HashSet};
 use derive_more::{Deref, DerefMut};
 pub use dock::Panel;
 use dock::{Dock, DockPosition, PanelButtons, PanelHandle, RESIZE_HANDLE_SIZE};
+pub use file_audit_modal::FileAuditModal;
 use futures::{
     channel::{
         mpsc::{self, UnboundedReceiver, UnboundedSender},
@@ -40,11 +44,13 @@ use gpui::{
     WindowBounds, WindowHandle, WindowId, WindowOptions,
 };
 pub use item::{
-    FollowableItem, FollowableItemHandle, Item, ItemHandle, ItemSettings, PreviewTabsSettings,
-    ProjectItem, SerializableItem, SerializableItemHandle, WeakItemHandle,
+    ClosedItemState, FollowableItem, FollowableItemHandle, Item, ItemHandle, ItemSettings,
+    PreviewTabsSettings, ProjectItem, RevealPosition, SerializableItem, SerializableItemHandle,
+    WeakItemHandle,
 };
 use itertools::Itertools;
 use language::{LanguageRegistry, Rope};
+pub use memory_governor::{MemoryGovernor, MemoryMetrics};
 pub use modal_layer::*;
 use node_runtime::NodeRuntime;
 use notifications::{simple_message_notification::MessageNotification, DetachAndPromptErr};
@@ -60,8 +66,11 @@ use persistence::{
 };
 use postage::stream::Stream;
 use project::{
-    DirectoryLister, Project, ProjectEntryId, ProjectPath, ResolvedPath, Worktree, WorktreeId,
+    worktree_trust::WorktreeTrust, DirectoryLister, Project, ProjectEntryId, ProjectPath,
+    ResolvedPath, Worktree, WorktreeId,
 };
+use prompts::PromptQueue;
+pub use prompts::{prompt_in_workspace, PromptOptions};
 use remote::{ssh_session::ConnectionIdentifier, SshClientDelegate, SshConnectionOptions};
 use schemars::JsonSchema;
 use serde::Deserialize;
@@ -82,6 +91,7 @@ use std::{
     collections::hash_map::DefaultHasher,
     env,
     hash::{Hash, Hasher},
+    ops::Range,
     path::{Path, PathBuf},
     rc::Rc,
     sync::{atomic::AtomicUsize, Arc, LazyLock, Weak},
@@ -105,6 +115,9 @@ use crate::persistence::{
 };
 
 pub const SERIALIZATION_THROTTLE_TIME: Duration = Duration::from_millis(200);
+const MAX_PERSISTED_CLOSED_ITEMS: usize = 64;
+/// How much the active pane grows or shrinks by with each resize command.
+const PANE_RESIZE_STEP: Pixels = px(20.);
 
 static ZED_WINDOW_SIZE: LazyLock<Option<Size<Pixels>>> = LazyLock::new(|| {
     env::var("ZED_WINDOW_SIZE")
@@ -130,13 +143,18 @@ actions!(
         ActivateNextWindow,
         ActivatePreviousWindow,
         AddFolderToProject,
+        AuditFileFormats,
         ClearAllNotifications,
         CloseAllDocks,
         CloseWindow,
         CopyPath,
         CopyRelativePath,
+        DecreaseActivePaneHeight,
+        DecreaseActivePaneWidth,
         Feedback,
         FollowNextCollaborator,
+        IncreaseActivePaneHeight,
+        IncreaseActivePaneWidth,
         MoveFocusedPanelToNextPosition,
         NewCenterTerminal,
         NewFile,
@@ -150,13 +168,17 @@ actions!(
         OpenInTerminal,
         OpenComponentPreview,
         ReloadActiveItem,
+        ResetPaneSizes,
+        RevokeProjectTrust,
         SaveAs,
         SaveWithoutFormat,
         ToggleBottomDock,
         ToggleCenteredLayout,
         ToggleLeftDock,
+        ToggleMaximizePane,
         ToggleRightDock,
         ToggleZoom,
+        TrustProject,
         Unfollow,
         Welcome,
     ]
@@ -379,6 +401,10 @@ fn prompt_and_open_paths(app_state: Arc<AppState>, options: PathPromptOptions, c
 
 pub fn init(app_state: Arc<AppState>, cx: &mut App) {
     init_settings(cx);
+    init_worktree_trust(cx);
+    init_idle_interval(cx);
+    notifications::show_task_panics(cx);
+    MemoryGovernor::init(cx);
     component::init();
     theme_preview::init(cx);
 
@@ -420,6 +446,45 @@ pub fn init(app_state: Arc<AppState>, cx: &mut App) {
     });
 }
 
+const TRUSTED_PATHS_KEY: &str = "trusted_paths";
+
+/// Restores the folders trusted in previous sessions, and persists future changes.
+fn init_worktree_trust(cx: &mut App) {
+    let trusted_paths = db::kvp::KEY_VALUE_STORE
+        .read_kvp(TRUSTED_PATHS_KEY)
+        .log_err()
+        .flatten()
+        .and_then(|paths| serde_json::from_str::<Vec<PathBuf>>(&paths).log_err())
+        .unwrap_or_default();
+    WorktreeTrust::set_trusted_paths(trusted_paths, cx);
+
+    cx.observe_global::<WorktreeTrust>(|cx| {
+        let trusted_paths = WorktreeTrust::trusted_paths(cx).collect::<Vec<_>>();
+        let Some(trusted_paths) = serde_json::to_string(&trusted_paths).log_err() else {
+            return;
+        };
+        cx.background_executor()
+            .spawn(db::kvp::KEY_VALUE_STORE.write_kvp(TRUSTED_PATHS_KEY.to_string(), trusted_paths))
+            .detach_and_log_err(cx);
+    })
+    .detach();
+}
+
+/// Keeps the app's idle interval in sync with the `idle_work_delay_milliseconds` setting.
+fn init_idle_interval(cx: &mut App) {
+    let idle_interval = |cx: &App| {
+        Duration::from_millis(WorkspaceSettings::get_global(cx).idle_work_delay_milliseconds)
+    };
+    cx.set_idle_interval(idle_interval(cx));
+    cx.observe_global::<settings::SettingsStore>(move |cx| {
+        let interval = idle_interval(cx);
+        if cx.idle_interval() != interval {
+            cx.set_idle_interval(interval);
+        }
+    })
+    .detach();
+}
+
 #[derive(Clone, Default, Deref, DerefMut)]
 struct ProjectItemOpeners(Vec<ProjectItemOpener>);
 
@@ -768,6 +833,45 @@ pub enum Event {
     ZoomChanged,
 }
 
+/// The pane [`Workspace::open_path_at`] opens a path in.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum OpenTarget {
+    /// The active center pane, activating the path's item if the pane has one already.
+    #[default]
+    ActivePane,
+    /// The preview tab of the active center pane.
+    Preview,
+    /// The pane next to the active center pane, split from it if there's none.
+    AdjacentPane,
+    /// A new pane, split from the active center pane in the given direction.
+    Split(SplitDirection),
+    /// The given pane.
+    Pane(WeakEntity<Pane>),
+}
+
+/// How [`Workspace::open_path_at`] opens a path.
+#[derive(Clone, Debug)]
+pub struct OpenAtOptions {
+    pub target: OpenTarget,
+    /// Whether the opened item gets focused.
+    pub focus: bool,
+    /// Where the item scrolls the range to.
+    pub position: RevealPosition,
+    /// Whether the range is highlighted for a moment, to be spotted once revealed.
+    pub flash: bool,
+}
+
+impl Default for OpenAtOptions {
+    fn default() -> Self {
+        Self {
+            target: OpenTarget::default(),
+            focus: true,
+            position: RevealPosition::default(),
+            flash: true,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum OpenVisible {
     All,
@@ -816,6 +920,7 @@ pub struct Workspace {
     last_active_view_id: Option<proto::ViewId>,
     status_bar: Entity<StatusBar>,
     modal_layer: Entity<ModalLayer>,
+    prompts: PromptQueue,
     titlebar_item: Option<AnyView>,
     notifications: Vec<(NotificationId, AnyView)>,
     project: Entity<Project>,
@@ -841,6 +946,10 @@ pub struct Workspace {
     serialized_ssh_project: Option<SerializedSshProject>,
     _items_serializer: Task<Result<()>>,
     session_id: Option<String>,
+    /// Items that were closed in a previous session, from the least to the most
+    /// recently closed, along with the state to restore. They're reopened once the
+    /// panes' own closed item stacks are exhausted.
+    restored_closed_items: Vec<(PathBuf, Option<ClosedItemState>)>,
 }
 
 impl EventEmitter<Event> for Workspace {}
@@ -900,7 +1009,8 @@ impl Workspace {
                     }
                 }
 
-                project::Event::DisconnectedFromSshRemote => {
+                project::Event::DisconnectedFromSshRemote
+                | project::Event::ReconnectedToSshRemote => {
                     this.update_window_edited(window, cx);
                 }
 
@@ -1108,6 +1218,7 @@ impl Workspace {
             last_active_view_id: None,
             status_bar,
             modal_layer,
+            prompts: PromptQueue::default(),
             titlebar_item: None,
             notifications: Default::default(),
             left_dock,
@@ -1138,6 +1249,7 @@ impl Workspace {
             _items_serializer,
             session_id: Some(session_id),
             serialized_ssh_project: None,
+            restored_closed_items: Vec::new(),
         }
     }
 
@@ -1582,6 +1694,21 @@ impl Workspace {
         window: &mut Window,
         cx: &mut Context<Workspace>,
     ) -> Task<Result<()>> {
+        let has_closed_items = self.active_pane.read(cx).nav_history().has_closed_items();
+        if !has_closed_items {
+            if let Some((abs_path, state)) = self.restored_closed_items.pop() {
+                let open_task = self.open_abs_path(abs_path, true, window, cx);
+                self.serialize_workspace(window, cx);
+                return cx.spawn_in(window, |_, mut cx| async move {
+                    let item = open_task.await?;
+                    if let Some(state) = state {
+                        cx.update(|window, cx| item.restore_closed_state(state, window, cx))?;
+                    }
+                    Ok(())
+                });
+            }
+        }
+
         self.navigate_history(
             self.active_pane().downgrade(),
             NavigationMode::ReopeningClosedItem,
@@ -1590,6 +1717,18 @@ impl Workspace {
         )
     }
 
+    /// Audits the line endings, indentation and byte order marks of the project's files, listing
+    /// them in a modal choosing how, and which of them, to convert.
+    pub fn audit_file_formats(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let audit = self
+            .project
+            .update(cx, |project, cx| project.audit_file_formats(cx));
+        let workspace = cx.entity().downgrade();
+        self.toggle_modal(window, cx, |window, cx| {
+            FileAuditModal::new(audit, workspace, window, cx)
+        });
+    }
+
     pub fn client(&self) -> &Arc<Client> {
         &self.app_state.client
     }
@@ -1865,14 +2004,14 @@ impl Workspace {
                     && workspace_count == 1
                     && active_call.read_with(&cx, |call, _| call.room().is_some())?
                 {
-                    let answer = cx.update(|window, cx| {
-                        window.prompt(
+                    let answer = this.update_in(&mut cx, |this, window, cx| {
+                        let options = PromptOptions::new(
                             PromptLevel::Warning,
                             "Do you want to leave the current call?",
-                            None,
-                            &["Close window and hang up", "Cancel"],
-                            cx,
                         )
+                        .choice("Close window and hang up")
+                        .cancel("Cancel");
+                        this.prompt(options, window, cx)
                     })?;
 
                     if answer.await.log_err() == Some(1) {
@@ -1977,57 +2116,29 @@ impl Workspace {
         if self.project.read(cx).is_disconnected(cx) {
             return Task::ready(Ok(true));
         }
-        let dirty_items = self
-            .panes
-            .iter()
-            .flat_map(|pane| {
-                pane.read(cx).items().filter_map(|item| {
-                    if item.is_dirty(cx) {
-                        item.tab_description(0, cx);
-                        Some((pane.downgrade(), item.boxed_clone()))
-                    } else {
-                        None
-                    }
-                })
-            })
-            .collect::<Vec<_>>();
+        let dirty_items = self.dirty_items(cx);
 
-        let project = self.project.clone();
         cx.spawn_in(window, |workspace, mut cx| async move {
             let dirty_items = if save_intent == SaveIntent::Close && !dirty_items.is_empty() {
-                let (serialize_tasks, remaining_dirty_items) =
-                    workspace.update_in(&mut cx, |workspace, window, cx| {
-                        let mut remaining_dirty_items = Vec::new();
-                        let mut serialize_tasks = Vec::new();
-                        for (pane, item) in dirty_items {
-                            if let Some(task) = item
-                                .to_serializable_item_handle(cx)
-                                .and_then(|handle| handle.serialize(workspace, true, window, cx))
-                            {
-                                serialize_tasks.push(task);
-                            } else {
-                                remaining_dirty_items.push((pane, item));
-                            }
-                        }
-                        (serialize_tasks, remaining_dirty_items)
-                    })?;
-
-                futures::future::try_join_all(serialize_tasks).await?;
+                let remaining_dirty_items = workspace
+                    .update_in(&mut cx, |workspace, window, cx| {
+                        workspace.serialize_dirty_items(dirty_items, window, cx)
+                    })?
+                    .await?;
 
                 if remaining_dirty_items.len() > 1 {
-                    let answer = workspace.update_in(&mut cx, |_, window, cx| {
+                    let answer = workspace.update_in(&mut cx, |workspace, window, cx| {
                         let (prompt, detail) = Pane::file_names_for_prompt(
                             &mut remaining_dirty_items.iter().map(|(_, handle)| handle),
                             remaining_dirty_items.len(),
                             cx,
                         );
-                        window.prompt(
-                            PromptLevel::Warning,
-                            &prompt,
-                            Some(&detail),
-                            &["Save all", "Discard all", "Cancel"],
-                            cx,
-                        )
+                        let options = PromptOptions::new(PromptLevel::Warning, prompt)
+                            .detail(detail)
+                            .choice("Save all")
+                            .choice("Discard all")
+                            .cancel("Cancel");
+                        workspace.prompt(options, window, cx)
                     })?;
                     match answer.await.log_err() {
                         Some(0) => save_intent = SaveIntent::SaveAll,
@@ -2041,7 +2152,79 @@ impl Workspace {
                 dirty_items
             };
 
-            for (pane, item) in dirty_items {
+            workspace
+                .update_in(&mut cx, |workspace, window, cx| {
+                    workspace.save_items(dirty_items, save_intent, window, cx)
+                })?
+                .await
+        })
+    }
+
+    fn dirty_items(&self, cx: &App) -> Vec<(WeakEntity<Pane>, Box<dyn ItemHandle>)> {
+        self.panes
+            .iter()
+            .flat_map(|pane| {
+                pane.read(cx).items().filter_map(|item| {
+                    if item.is_dirty(cx) {
+                        item.tab_description(0, cx);
+                        Some((pane.downgrade(), item.boxed_clone()))
+                    } else {
+                        None
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Persists the dirty items whose unsaved changes are restored with the session, returning
+    /// the ones that would lose their changes when the workspace closes.
+    pub fn unsaved_items_on_close(
+        &mut self,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Vec<(WeakEntity<Pane>, Box<dyn ItemHandle>)>>> {
+        if self.project.read(cx).is_disconnected(cx) {
+            return Task::ready(Ok(Vec::new()));
+        }
+        let dirty_items = self.dirty_items(cx);
+        self.serialize_dirty_items(dirty_items, window, cx)
+    }
+
+    fn serialize_dirty_items(
+        &mut self,
+        dirty_items: Vec<(WeakEntity<Pane>, Box<dyn ItemHandle>)>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Vec<(WeakEntity<Pane>, Box<dyn ItemHandle>)>>> {
+        let mut remaining_dirty_items = Vec::new();
+        let mut serialize_tasks = Vec::new();
+        for (pane, item) in dirty_items {
+            if let Some(task) = item
+                .to_serializable_item_handle(cx)
+                .and_then(|handle| handle.serialize(self, true, window, cx))
+            {
+                serialize_tasks.push(task);
+            } else {
+                remaining_dirty_items.push((pane, item));
+            }
+        }
+        cx.spawn(|_, _| async move {
+            futures::future::try_join_all(serialize_tasks).await?;
+            Ok(remaining_dirty_items)
+        })
+    }
+
+    /// Saves the items with the given intent, returning false when the user cancels.
+    pub fn save_items(
+        &mut self,
+        items: Vec<(WeakEntity<Pane>, Box<dyn ItemHandle>)>,
+        save_intent: SaveIntent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<bool>> {
+        let project = self.project.clone();
+        cx.spawn_in(window, |_, mut cx| async move {
+            for (pane, item) in items {
                 let (singleton, project_entry_ids) =
                     cx.update(|_, cx| (item.is_singleton(cx), item.project_entry_ids(cx)))?;
                 if singleton || !project_entry_ids.is_empty() {
@@ -2647,6 +2830,7 @@ impl Workspace {
     }
 
     fn add_pane(&mut self, window: &mut Window, cx: &mut Context<Self>) -> Entity<Pane> {
+        self.restore_maximized_pane(window, cx);
         let pane = cx.new(|cx| {
             let mut pane = Pane::new(
                 self.weak_handle(),
@@ -2855,6 +3039,53 @@ impl Workspace {
         })
     }
 
+    /// Opens the path in the pane chosen by the options, then selects the range of its item and
+    /// reveals it. This is how search results, diagnostics, definitions and paths passed to the
+    /// CLI are opened, so that they all pick panes, scroll and highlight the same way.
+    pub fn open_path_at(
+        &mut self,
+        path: impl Into<ProjectPath>,
+        range: Range<language::Point>,
+        options: OpenAtOptions,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Box<dyn ItemHandle>>> {
+        let open_path = match options.target {
+            OpenTarget::ActivePane => {
+                self.open_path_preview(path, None, options.focus, false, window, cx)
+            }
+            OpenTarget::Preview => {
+                self.open_path_preview(path, None, options.focus, true, window, cx)
+            }
+            OpenTarget::AdjacentPane => {
+                let pane = self.adjacent_pane(window, cx).downgrade();
+                self.open_path_preview(path, Some(pane), options.focus, false, window, cx)
+            }
+            OpenTarget::Split(direction) => {
+                self.split_path_preview(path, false, Some(direction), window, cx)
+            }
+            OpenTarget::Pane(pane) => {
+                self.open_path_preview(path, Some(pane), options.focus, false, window, cx)
+            }
+        };
+        cx.spawn_in(window, |this, mut cx| async move {
+            let item = open_path.await?;
+            this.update_in(&mut cx, |this, window, cx| {
+                // Moving to the range isn't a navigation within the item, so it stays out of the
+                // history, which goes back to where the path was opened from.
+                let pane = this.pane_for(item.as_ref());
+                if let Some(pane) = &pane {
+                    pane.update(cx, |pane, _| pane.disable_history());
+                }
+                item.reveal_range(range, options.position, options.flash, window, cx);
+                if let Some(pane) = &pane {
+                    pane.update(cx, |pane, _| pane.enable_history());
+                }
+            })?;
+            Ok(item)
+        })
+    }
+
     pub fn split_path(
         &mut self,
         path: impl Into<ProjectPath>,
@@ -3273,12 +3504,15 @@ impl Workspace {
         } else {
             self.center
                 .resize(&self.active_pane, axis, amount, &self.bounds);
+            self.serialize_workspace(window, cx);
         }
         cx.notify();
     }
 
-    pub fn reset_pane_sizes(&mut self, cx: &mut Context<Self>) {
+    /// Gives all the panes of each split the same size.
+    pub fn reset_pane_sizes(&mut self, window: &mut Window, cx: &mut Context<Self>) {
         self.center.reset_pane_sizes();
+        self.serialize_workspace(window, cx);
         cx.notify();
     }
 
@@ -3297,6 +3531,15 @@ impl Workspace {
             self.set_active_pane(&pane, window, cx);
         }
 
+        if self
+            .center
+            .maximized_pane()
+            .map_or(false, |maximized| *maximized != pane)
+            && self.panes.contains(&pane)
+        {
+            self.restore_maximized_pane(window, cx);
+        }
+
         if self.last_active_center_pane.is_none() {
             self.last_active_center_pane = Some(pane.downgrade());
         }
@@ -3377,6 +3620,9 @@ impl Workspace {
                     self.active_item_path_changed(window, cx);
                     self.update_active_view_for_followers(window, cx);
                 }
+                if let Some(item) = pane.read(cx).active_item() {
+                    memory_governor::restore_syntax_trees(item.as_ref(), &self.project, cx);
+                }
                 serialize_workspace = *focus_changed || pane != self.active_pane();
             }
             pane::Event::UserSavedItem { item, save_intent } => {
@@ -3567,6 +3813,9 @@ impl Workspace {
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
+        if self.center.maximized_pane() == Some(&pane) {
+            self.restore_maximized_pane(window, cx);
+        }
         if self.center.remove(&pane).unwrap() {
             self.force_remove_pane(&pane, &focus_on, window, cx);
             self.unfollow_in_pane(&pane, window, cx);
@@ -4471,6 +4720,12 @@ impl Workspace {
         }
     }
 
+    /// Serializes the workspace now rather than after the pending delay, e.g. before quitting.
+    pub fn flush_serialization(&mut self, window: &mut Window, cx: &mut Context<Self>) -> Task<()> {
+        self._schedule_serialize.take();
+        self.serialize_workspace_internal(window, cx)
+    }
+
     fn serialize_workspace_internal(&self, window: &mut Window, cx: &mut App) -> Task<()> {
         let Some(database_id) = self.database_id() else {
             return Task::ready(());
@@ -4611,11 +4866,46 @@ impl Workspace {
                 session_id: self.session_id.clone(),
                 window_id: Some(window.window_handle().window_id().as_u64()),
             };
-            return window.spawn(cx, |_| persistence::DB.save_workspace(serialized_workspace));
+            let closed_items = self.closed_items(cx);
+            return window.spawn(cx, |_| async move {
+                persistence::DB.save_workspace(serialized_workspace).await;
+                persistence::DB
+                    .set_closed_items(database_id, closed_items)
+                    .await
+                    .log_err();
+            });
         }
         Task::ready(())
     }
 
+    /// Returns the items that can be reopened, from the least to the most recently
+    /// closed in any pane.
+    fn closed_items(&self, cx: &App) -> Vec<(PathBuf, Option<ClosedItemState>)> {
+        let mut pane_closed_items = self
+            .panes
+            .iter()
+            .flat_map(|pane| pane.read(cx).nav_history().closed_items())
+            .collect::<Vec<_>>();
+        pane_closed_items.sort_by_key(|(timestamp, _, _)| *timestamp);
+
+        let mut closed_items = self.restored_closed_items.clone();
+        closed_items.extend(
+            pane_closed_items
+                .into_iter()
+                .map(|(_, abs_path, state)| (abs_path, state)),
+        );
+
+        let mut seen = HashSet::default();
+        let mut closed_items = closed_items
+            .into_iter()
+            .rev()
+            .filter(|(abs_path, _)| seen.insert(abs_path.clone()))
+            .take(MAX_PERSISTED_CLOSED_ITEMS)
+            .collect::<Vec<_>>();
+        closed_items.reverse();
+        closed_items
+    }
+
     async fn serialize_items(
         this: &WeakEntity<Self>,
         items_rx: UnboundedReceiver<Box<dyn SerializableItemHandle>>,
@@ -4717,6 +5007,12 @@ impl Workspace {
                 })
                 .collect::<Vec<_>>();
 
+            let closed_items = persistence::DB
+                .closed_items(serialized_workspace.id)
+                .await
+                .log_err()
+                .unwrap_or_default();
+
             // Remove old panes from workspace panes list
             workspace.update_in(&mut cx, |workspace, window, cx| {
                 if let Some((center_group, active_pane)) = center_group {
@@ -4732,6 +5028,8 @@ impl Workspace {
                     }
                 }
 
+                workspace.restored_closed_items = closed_items;
+
                 let docks = serialized_workspace.docks;
 
                 for (dock, serialized_dock) in [
@@ -4799,6 +5097,16 @@ impl Workspace {
             .on_action(cx.listener(Self::activate_pane_at_index))
             .on_action(cx.listener(Self::move_item_to_pane_at_index))
             .on_action(cx.listener(Self::move_focused_panel_to_next_position))
+            .on_action(cx.listener(|workspace, _: &TrustProject, _, cx| {
+                workspace
+                    .project
+                    .update(cx, |project, cx| project.set_worktrees_trusted(true, cx));
+            }))
+            .on_action(cx.listener(|workspace, _: &RevokeProjectTrust, _, cx| {
+                workspace
+                    .project
+                    .update(cx, |project, cx| project.set_worktrees_trusted(false, cx));
+            }))
             .on_action(cx.listener(|workspace, _: &Unfollow, window, cx| {
                 let pane = workspace.active_pane().clone();
                 workspace.unfollow_in_pane(&pane, window, cx);
@@ -4868,6 +5176,29 @@ impl Workspace {
             .on_action(cx.listener(|workspace, _: &SwapPaneDown, _, cx| {
                 workspace.swap_pane_in_direction(SplitDirection::Down, cx)
             }))
+            .on_action(
+                cx.listener(|workspace, _: &IncreaseActivePaneWidth, window, cx| {
+                    workspace.resize_pane(gpui::Axis::Horizontal, PANE_RESIZE_STEP, window, cx)
+                }),
+            )
+            .on_action(
+                cx.listener(|workspace, _: &DecreaseActivePaneWidth, window, cx| {
+                    workspace.resize_pane(gpui::Axis::Horizontal, -PANE_RESIZE_STEP, window, cx)
+                }),
+            )
+            .on_action(
+                cx.listener(|workspace, _: &IncreaseActivePaneHeight, window, cx| {
+                    workspace.resize_pane(gpui::Axis::Vertical, PANE_RESIZE_STEP, window, cx)
+                }),
+            )
+            .on_action(
+                cx.listener(|workspace, _: &DecreaseActivePaneHeight, window, cx| {
+                    workspace.resize_pane(gpui::Axis::Vertical, -PANE_RESIZE_STEP, window, cx)
+                }),
+            )
+            .on_action(cx.listener(|workspace, _: &ResetPaneSizes, window, cx| {
+                workspace.reset_pane_sizes(window, cx)
+            }))
             .on_action(cx.listener(|this, _: &ToggleLeftDock, window, cx| {
                 this.toggle_dock(DockPosition::Left, window, cx);
             }))
@@ -4896,7 +5227,13 @@ impl Workspace {
                     workspace.reopen_closed_item(window, cx).detach();
                 },
             ))
+            .on_action(cx.listener(
+                |workspace: &mut Workspace, _: &AuditFileFormats, window, cx| {
+                    workspace.audit_file_formats(window, cx);
+                },
+            ))
             .on_action(cx.listener(Workspace::toggle_centered_layout))
+            .on_action(cx.listener(Workspace::toggle_maximize_pane))
     }
 
     #[cfg(any(test, feature = "test-support"))]
@@ -4986,6 +5323,63 @@ impl Workspace {
         cx.notify();
     }
 
+    /// Renders the active pane in place of all the center panes, or all of them again if it
+    /// already is. The layout of the panes is kept, as are their sizes.
+    pub fn toggle_maximize_pane(
+        &mut self,
+        _: &ToggleMaximizePane,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.center.maximized_pane().is_some() {
+            self.restore_maximized_pane(window, cx);
+            return;
+        }
+
+        let Some(active_pane) = self
+            .last_active_center_pane
+            .as_ref()
+            .and_then(|pane| pane.upgrade())
+        else {
+            return;
+        };
+        self.center.set_maximized_pane(Some(active_pane.clone()));
+        if self.center.maximized_pane().is_none() {
+            return;
+        }
+        for pane in self.center.panes() {
+            if *pane != active_pane {
+                Self::set_pane_visible(pane, false, window, cx);
+            }
+        }
+        window.focus(&active_pane.focus_handle(cx));
+        cx.notify();
+    }
+
+    fn restore_maximized_pane(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(maximized_pane) = self.center.maximized_pane().cloned() else {
+            return;
+        };
+        self.center.set_maximized_pane(None);
+        for pane in self.center.panes() {
+            if *pane != maximized_pane {
+                Self::set_pane_visible(pane, true, window, cx);
+            }
+        }
+        cx.notify();
+    }
+
+    fn set_pane_visible(pane: &Entity<Pane>, visible: bool, window: &mut Window, cx: &mut App) {
+        let items = pane
+            .read(cx)
+            .items()
+            .map(|item| item.boxed_clone())
+            .collect::<Vec<_>>();
+        for item in items {
+            item.pane_visibility_changed(visible, window, cx);
+        }
+    }
+
     fn adjust_padding(padding: Option<f32>) -> f32 {
         padding
             .unwrap_or(Self::DEFAULT_PADDING)
@@ -5264,7 +5658,7 @@ impl Render for Workspace {
         context.add("Workspace");
         context.set("keyboard_layout", cx.keyboard_layout().clone());
         let centered_layout = self.centered_layout
-            && self.center.panes().len() == 1
+            && (self.center.panes().len() == 1 || self.center.maximized_pane().is_some())
             && self.active_item(cx).is_some();
         let render_padding = |size| {
             (size > 0.0).then(|| {
@@ -5722,18 +6116,17 @@ async fn join_channel_internal(
     if should_prompt {
         if let Some(workspace) = requesting_window {
             let answer = workspace
-                .update(cx, |_, window, cx| {
-                    window.prompt(
-                        PromptLevel::Warning,
-                        "Do you want to switch channels?",
-                        Some("Leaving this call will unshare your current project."),
-                        &["Yes, Join Channel", "Cancel"],
-                        cx,
-                    )
+                .update(cx, |workspace, window, cx| {
+                    let options =
+                        PromptOptions::new(PromptLevel::Warning, "Do you want to switch channels?")
+                            .detail("Leaving this call will unshare your current project.")
+                            .choice("Yes, Join Channel")
+                            .cancel("Cancel");
+                    workspace.prompt(options, window, cx)
                 })?
                 .await;
 
-            if answer == Ok(1) {
+            if answer.ok() == Some(1) {
                 return Ok(false);
             }
         } else {
@@ -5870,7 +6263,7 @@ pub fn join_channel(
             log::error!("failed to join channel: {}", err);
             if let Some(active_window) = active_window {
                 active_window
-                    .update(&mut cx, |_, window, cx| {
+                    .update(&mut cx, |workspace, window, cx| {
                         let detail: SharedString = match err.error_code() {
                             ErrorCode::SignedOut => {
                                 "Please sign in to continue.".into()
@@ -5887,12 +6280,11 @@ pub fn join_channel(
                             ErrorCode::Disconnected => "Please check your internet connection and try again.".into(),
                             _ => format!("{}\n\nPlease try again.", err).into(),
                         };
-                        window.prompt(
-                            PromptLevel::Critical,
-                            "Failed to join channel",
-                            Some(&detail),
-                            &["Ok"],
-                        cx)
+                        let options =
+                            PromptOptions::new(PromptLevel::Critical, "Failed to join channel")
+                                .detail(detail)
+                                .cancel("Ok");
+                        workspace.prompt(options, window, cx)
                     })?
                     .await
                     .ok();
@@ -6366,14 +6758,12 @@ pub fn reload(reload: &Reload, cx: &mut App) {
     let mut prompt = None;
     if let (true, Some(window)) = (should_confirm, workspace_windows.first()) {
         prompt = window
-            .update(cx, |_, window, cx| {
-                window.prompt(
-                    PromptLevel::Info,
-                    "Are you sure you want to restart?",
-                    None,
-                    &["Restart", "Cancel"],
-                    cx,
-                )
+            .update(cx, |workspace, window, cx| {
+                let options =
+                    PromptOptions::new(PromptLevel::Info, "Are you sure you want to restart?")
+                        .choice("Restart")
+                        .cancel("Cancel");
+                workspace.prompt(options, window, cx)
             })
             .ok();
     }
@@ -6768,14 +7158,15 @@ mod tests {
             ItemEvent,
         },
     };
-    use fs::FakeFs;
+    use fs::{FakeFs, Fs as _};
     use gpui::{
         px, DismissEvent, Empty, EventEmitter, FocusHandle, Focusable, Render, TestAppContext,
         UpdateGlobal, VisualTestContext,
     };
-    use project::{Project, ProjectEntryId};
+    use project::{project_settings::ProjectSettings, Project, ProjectEntryId, TaskSourceKind};
     use serde_json::json;
     use settings::SettingsStore;
+    use task::{TaskContext, TaskTemplate};
 
     #[gpui::test]
     async fn test_tab_disambiguation(cx: &mut TestAppContext) {
@@ -6966,6 +7357,154 @@ mod tests {
         assert!(!task.await.unwrap());
     }
 
+    #[gpui::test]
+    async fn test_prompts_are_queued(cx: &mut TestAppContext) {
+        init_test(cx);
+
+        let fs = FakeFs::new(cx.executor());
+        let project = Project::test(fs, [], cx).await;
+        let (workspace, cx) =
+            cx.add_window_view(|window, cx| Workspace::test_new(project.clone(), window, cx));
+
+        let options = |message: &'static str| {
+            PromptOptions::new(PromptLevel::Info, message)
+                .choice("Discard")
+                .choice("Keep")
+                .cancel("Cancel")
+        };
+        let (first, second) = workspace.update_in(cx, |workspace, window, cx| {
+            (
+                workspace.prompt(options("first"), window, cx),
+                workspace.prompt(options("second"), window, cx),
+            )
+        });
+
+        // The second prompt is shown once the first one is answered.
+        assert!(cx.has_pending_prompt());
+        cx.simulate_prompt_answer(1);
+        cx.executor().run_until_parked();
+        assert_eq!(first.await.unwrap(), 1);
+        assert!(cx.has_pending_prompt());
+        cx.simulate_prompt_answer(0);
+        cx.executor().run_until_parked();
+        assert_eq!(second.await.unwrap(), 0);
+        assert!(!cx.has_pending_prompt());
+
+        // Scripted answers are chosen by label, without showing the prompts.
+        let (first, second) = workspace.update_in(cx, |workspace, window, cx| {
+            workspace.script_prompt_answers(["Cancel", "Keep"]);
+            (
+                workspace.prompt(options("first"), window, cx),
+                workspace.prompt(options("second"), window, cx),
+            )
+        });
+        assert!(!cx.has_pending_prompt());
+        assert_eq!(first.await.unwrap(), 2);
+        assert_eq!(second.await.unwrap(), 1);
+    }
+
+    #[gpui::test]
+    async fn test_audit_file_formats(cx: &mut TestAppContext) {
+        init_test(cx);
+
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/root",
+            json!({ "a.txt": "one\r\ntwo\r\n", "b.txt": "three\r\n" }),
+        )
+        .await;
+        let project = Project::test(fs.clone(), ["/root".as_ref()], cx).await;
+        let (workspace, cx) =
+            cx.add_window_view(|window, cx| Workspace::test_new(project.clone(), window, cx));
+
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_local_buffer("/root/b.txt", cx)
+            })
+            .await
+            .unwrap();
+        buffer.update(cx, |buffer, cx| buffer.edit([(0..0, "zero\n")], None, cx));
+
+        workspace.update_in(cx, |workspace, window, cx| {
+            workspace.audit_file_formats(window, cx)
+        });
+        cx.executor().run_until_parked();
+        workspace.update(cx, |workspace, cx| {
+            assert!(workspace.active_modal::<FileAuditModal>(cx).is_some());
+        });
+
+        cx.dispatch_action(menu::Confirm);
+        cx.executor().run_until_parked();
+        workspace.update(cx, |workspace, cx| {
+            assert!(workspace.active_modal::<FileAuditModal>(cx).is_none());
+        });
+
+        // The file with unsaved changes is skipped.
+        assert_eq!(fs.load("/root/a.txt".as_ref()).await.unwrap(), "one\ntwo\n");
+        assert_eq!(fs.load("/root/b.txt".as_ref()).await.unwrap(), "three\r\n");
+    }
+
+    #[gpui::test]
+    async fn test_tasks_of_untrusted_worktrees(cx: &mut TestAppContext) {
+        init_test(cx);
+        cx.update(|cx| {
+            SettingsStore::update_global(cx, |settings, cx| {
+                settings.update_user_settings::<ProjectSettings>(cx, |settings| {
+                    settings.workspace_trust.enabled = true;
+                });
+            });
+        });
+
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree("/root", json!({ "Cargo.toml": "" })).await;
+        let project = Project::test(fs, ["/root".as_ref()], cx).await;
+        let (workspace, cx) =
+            cx.add_window_view(|window, cx| Workspace::test_new(project.clone(), window, cx));
+        let worktree_id = project.update(cx, |project, cx| {
+            project.worktrees(cx).next().unwrap().read(cx).id()
+        });
+
+        let spawned_tasks = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|_, cx| {
+            let spawned_tasks = spawned_tasks.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let Event::SpawnTask { action } = event {
+                    spawned_tasks.borrow_mut().push(action.label.clone());
+                }
+            })
+            .detach();
+        });
+        let schedule_build = |cx: &mut VisualTestContext| {
+            workspace.update(cx, |workspace, cx| {
+                let task_source_kind = TaskSourceKind::Detected {
+                    id: worktree_id,
+                    manifest_path: PathBuf::from("Cargo.toml"),
+                    detector: "cargo".into(),
+                };
+                let template = TaskTemplate {
+                    label: "cargo build".to_string(),
+                    command: "cargo".to_string(),
+                    ..TaskTemplate::default()
+                };
+                tasks::schedule_task(
+                    workspace,
+                    task_source_kind,
+                    &template,
+                    &TaskContext::default(),
+                    false,
+                    cx,
+                );
+            });
+        };
+
+        schedule_build(cx);
+        assert_eq!(*spawned_tasks.borrow(), Vec::<String>::new());
+
+        project.update(cx, |project, cx| project.set_worktrees_trusted(true, cx));
+        schedule_build(cx);
+        assert_eq!(*spawned_tasks.borrow(), ["cargo build"]);
+    }
+
     #[gpui::test]
     async fn test_close_window_with_serializable_items(cx: &mut TestAppContext) {
         init_test(cx);
@@ -7811,6 +8350,108 @@ mod tests {
             assert_eq!(active_item.item_id(), last_item.item_id());
         });
     }
+
+    #[gpui::test]
+    async fn test_toggle_maximize_pane(cx: &mut gpui::TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        let project = Project::test(fs, None, cx).await;
+        let (workspace, cx) =
+            cx.add_window_view(|window, cx| Workspace::test_new(project, window, cx));
+
+        // A single pane isn't maximized.
+        let first_pane = workspace.update(cx, |workspace, _| workspace.active_pane().clone());
+        cx.dispatch_action(ToggleMaximizePane);
+        workspace.update(cx, |workspace, _| {
+            assert_eq!(workspace.center.maximized_pane(), None);
+        });
+
+        let second_pane = split_pane(cx, &workspace);
+        cx.dispatch_action(ToggleMaximizePane);
+        workspace.update(cx, |workspace, _| {
+            assert_eq!(workspace.center.maximized_pane(), Some(&second_pane));
+            assert_eq!(workspace.center.panes().len(), 2);
+        });
+        cx.dispatch_action(ToggleMaximizePane);
+        workspace.update(cx, |workspace, _| {
+            assert_eq!(workspace.center.maximized_pane(), None);
+        });
+
+        // Focusing a hidden pane restores the maximized one.
+        cx.dispatch_action(ToggleMaximizePane);
+        cx.focus(&first_pane);
+        workspace.update(cx, |workspace, _| {
+            assert_eq!(workspace.center.maximized_pane(), None);
+            assert_eq!(workspace.active_pane(), &first_pane);
+        });
+
+        // So does splitting.
+        cx.dispatch_action(ToggleMaximizePane);
+        split_pane(cx, &workspace);
+        workspace.update(cx, |workspace, _| {
+            assert_eq!(workspace.center.maximized_pane(), None);
+            assert_eq!(workspace.center.panes().len(), 3);
+        });
+    }
+
+    #[gpui::test]
+    async fn test_pane_split_sizes(cx: &mut gpui::TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        let project = Project::test(fs, None, cx).await;
+        let (workspace, cx) =
+            cx.add_window_view(|window, cx| Workspace::test_new(project, window, cx));
+        let flexes = |cx: &mut VisualTestContext| {
+            workspace.update(cx, |workspace, _| match &workspace.center.root {
+                Member::Axis(axis) => axis.flexes.lock().clone(),
+                Member::Pane(_) => Vec::new(),
+            })
+        };
+        let assert_flexes = |expected: &[f32], cx: &mut VisualTestContext| {
+            let flexes = flexes(cx);
+            assert_eq!(flexes.len(), expected.len(), "{flexes:?} != {expected:?}");
+            for (flex, expected_flex) in flexes.iter().zip(expected) {
+                assert!(
+                    (flex - expected_flex).abs() < 0.001,
+                    "{flexes:?} != {expected:?}"
+                );
+            }
+        };
+
+        let second_pane = split_pane(cx, &workspace);
+        assert_flexes(&[1., 1.], cx);
+        workspace.update(cx, |workspace, _| {
+            if let Member::Axis(axis) = &workspace.center.root {
+                *axis.flexes.lock() = vec![1.5, 0.5];
+            }
+        });
+
+        // Splitting a pane halves it, keeping the size of the others.
+        let third_pane = workspace.update_in(cx, |workspace, window, cx| {
+            workspace.split_pane(second_pane.clone(), SplitDirection::Right, window, cx)
+        });
+        assert_flexes(&[2.25, 0.375, 0.375], cx);
+
+        // Removing it gives its size back to its neighbor.
+        workspace.update_in(cx, |workspace, window, cx| {
+            workspace.remove_pane(third_pane, None, window, cx)
+        });
+        assert_flexes(&[1.5, 0.5], cx);
+
+        cx.dispatch_action(ResetPaneSizes);
+        assert_flexes(&[1., 1.], cx);
+
+        // Restored sizes that don't match the members are ignored.
+        let members = workspace.update(cx, |workspace, _| match &workspace.center.root {
+            Member::Axis(axis) => axis.members.clone(),
+            Member::Pane(_) => Vec::new(),
+        });
+        let axis = PaneAxis::load(gpui::Axis::Horizontal, members.clone(), Some(vec![3., 1.]));
+        assert_eq!(*axis.flexes.lock(), vec![1.5, 0.5]);
+        let axis = PaneAxis::load(gpui::Axis::Horizontal, members, Some(vec![1., 1., 1.]));
+        assert_eq!(*axis.flexes.lock(), vec![1., 1.]);
+    }
+
     struct TestModal(FocusHandle);
 
     impl TestModal {
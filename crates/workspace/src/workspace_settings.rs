@@ -16,6 +16,7 @@ pub struct WorkspaceSettings {
     pub confirm_quit: bool,
     pub show_call_status_icon: bool,
     pub autosave: AutosaveSetting,
+    pub atomic_save: bool,
     pub restore_on_startup: RestoreOnStartupBehavior,
     pub drop_target_size: f32,
     pub when_closing_with_no_tabs: CloseWindowWhenNoItems,
@@ -23,6 +24,9 @@ pub struct WorkspaceSettings {
     pub command_aliases: HashMap<String, String>,
     pub show_user_picture: bool,
     pub max_tabs: Option<NonZeroUsize>,
+    pub idle_work_delay_milliseconds: u64,
+    pub low_memory_mode: bool,
+    pub memory_budget_mb: u64,
 }
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema)]
@@ -109,6 +113,12 @@ pub struct WorkspaceSettingsContent {
     ///
     /// Default: off
     pub autosave: Option<AutosaveSetting>,
+    /// Whether to save files by writing them beside the file and moving them into place, so
+    /// that an interrupted save leaves the previous contents rather than a truncated file.
+    /// Files that can't be saved this way are written in place.
+    ///
+    /// Default: true
+    pub atomic_save: Option<bool>,
     /// Controls previous session restoration in freshly launched Zed instance.
     /// Values: none, last_workspace, last_session
     /// Default: last_session
@@ -141,6 +151,22 @@ pub struct WorkspaceSettingsContent {
     //
     // Default: none
     pub max_tabs: Option<NonZeroUsize>,
+    /// How long Zed must go without input before running deferred background work,
+    /// like scanning for TODO comments, in milliseconds.
+    ///
+    /// Default: 1000
+    pub idle_work_delay_milliseconds: Option<u64>,
+    /// Whether to free the memory of what isn't shown all the time, rather than only when
+    /// memory use exceeds `memory_budget_mb`. Background tabs are parsed again when they're
+    /// activated.
+    ///
+    /// Default: false
+    pub low_memory_mode: Option<bool>,
+    /// The estimated memory use, in megabytes, over which the memory of what isn't shown is
+    /// freed.
+    ///
+    /// Default: 4096
+    pub memory_budget_mb: Option<u64>,
 }
 
 #[derive(Deserialize)]
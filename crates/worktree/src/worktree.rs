@@ -6,7 +6,7 @@ mod worktree_tests;
 use ::ignore::gitignore::{Gitignore, GitignoreBuilder};
 use anyhow::{anyhow, Context as _, Result};
 use clock::ReplicaId;
-use collections::{HashMap, HashSet, VecDeque};
+use collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use fs::{copy_recursive, Fs, MTime, PathEvent, RemoveOptions, Watcher};
 use futures::{
     channel::{
@@ -26,7 +26,8 @@ use git::{
     GitHostingProviderRegistry, COMMIT_MESSAGE, DOT_GIT, FSMONITOR_DAEMON, GITIGNORE, INDEX_LOCK,
 };
 use gpui::{
-    App, AppContext as _, AsyncApp, BackgroundExecutor, Context, Entity, EventEmitter, Task,
+    supervised, App, AppContext as _, AsyncApp, BackgroundExecutor, Context, Entity, EventEmitter,
+    Task, TaskRecovery,
 };
 use ignore::IgnoreStack;
 use language::DiskState;
@@ -72,13 +73,16 @@ use util::{
     paths::{home_dir, PathMatcher, SanitizedPath},
     ResultExt,
 };
-pub use worktree_settings::WorktreeSettings;
+pub use worktree_settings::{SymlinkPolicy, WorktreeSettings};
 
 #[cfg(feature = "test-support")]
 pub const FS_WATCH_LATENCY: Duration = Duration::from_millis(100);
 #[cfg(not(feature = "test-support"))]
 pub const FS_WATCH_LATENCY: Duration = Duration::from_millis(100);
 
+/// How often worktrees on network file systems, which aren't watched, are checked for changes.
+pub const NETWORK_MOUNT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
 /// A set of local or remote files that are being opened as part of a project.
 /// Responsible for tracking related FS (for local)/collab (for remote) events and corresponding updates.
 /// Stores git repositories data and the diagnostics for the file(s).
@@ -127,6 +131,17 @@ pub struct LocalWorktree {
     next_entry_id: Arc<AtomicUsize>,
     settings: WorktreeSettings,
     share_private_files: bool,
+    polled_paths: Arc<Mutex<PolledPaths>>,
+}
+
+/// The paths checked for changes when a worktree is polled, instead of watched.
+#[derive(Default)]
+struct PolledPaths {
+    /// The directories expanded in the project panel, whose children are polled along with
+    /// the ones of the root.
+    expanded_dirs: HashSet<Arc<Path>>,
+    /// The files open in buffers.
+    open_files: HashSet<Arc<Path>>,
 }
 
 pub struct PathPrefixScanRequest {
@@ -740,6 +755,7 @@ impl Worktree {
                 fs_case_sensitive,
                 visible,
                 settings,
+                polled_paths: Default::default(),
             };
             worktree.start_background_scanner(scan_requests_rx, path_prefixes_to_scan_rx, cx);
             Worktree::Local(worktree)
@@ -1124,10 +1140,16 @@ impl Worktree {
 
         self.get_children_ids_recursive(path, &mut ids);
 
-        for id in ids {
-            cx.emit(Event::DeletedEntry(id));
-        }
-        Some(task)
+        // The entries are reported once they're deleted, so that their items aren't closed
+        // when they couldn't be, e.g. when trashing isn't supported.
+        Some(cx.spawn(|this, mut cx| async move {
+            task.await?;
+            this.update(&mut cx, |_, cx| {
+                for id in ids {
+                    cx.emit(Event::DeletedEntry(id));
+                }
+            })
+        }))
     }
 
     fn get_children_ids_recursive(&self, path: &Path, ids: &mut Vec<ProjectEntryId>) {
@@ -1396,6 +1418,18 @@ impl LocalWorktree {
         !self.share_private_files && self.settings.is_path_private(path)
     }
 
+    /// Sets the directories expanded in the project panel, whose children are checked for
+    /// changes when the worktree is polled.
+    pub fn set_expanded_dirs(&self, dirs: impl IntoIterator<Item = Arc<Path>>) {
+        self.polled_paths.lock().expanded_dirs = dirs.into_iter().collect();
+    }
+
+    /// Sets the files open in buffers, which are checked for changes when the worktree is
+    /// polled.
+    pub fn set_open_files(&self, files: impl IntoIterator<Item = Arc<Path>>) {
+        self.polled_paths.lock().open_files = files.into_iter().collect();
+    }
+
     fn restart_background_scanners(&mut self, cx: &Context<Worktree>) {
         let (scan_requests_tx, scan_requests_rx) = channel::unbounded();
         let (path_prefixes_to_scan_tx, path_prefixes_to_scan_rx) = channel::unbounded();
@@ -1426,12 +1460,24 @@ impl LocalWorktree {
         let fs = self.fs.clone();
         let git_hosting_provider_registry = GitHostingProviderRegistry::try_global(cx);
         let settings = self.settings.clone();
+        let polled_paths = self.polled_paths.clone();
         let (scan_states_tx, mut scan_states_rx) = mpsc::unbounded();
+        let task_name = format!("file scanner of {}", snapshot.root_name());
         let background_scanner = cx.background_executor().spawn({
             let abs_path = snapshot.abs_path.as_path().to_path_buf();
             let background = cx.background_executor().clone();
-            async move {
-                let (events, watcher) = fs.watch(&abs_path, FS_WATCH_LATENCY).await;
+            supervised(task_name, async move {
+                let poll_for_changes =
+                    settings.file_scan_poll_network_mounts && fs.is_network_mount(&abs_path).await;
+                let (events, watcher) = if poll_for_changes {
+                    log::info!("polling {abs_path:?} for changes, since it's on a network mount");
+                    let events: Pin<Box<dyn Send + Stream<Item = Vec<PathEvent>>>> =
+                        Box::pin(futures::stream::pending());
+                    let watcher: Arc<dyn Watcher> = Arc::new(PollingWatcher);
+                    (events, watcher)
+                } else {
+                    fs.watch(&abs_path, FS_WATCH_LATENCY).await
+                };
                 let fs_case_sensitive = fs.is_case_sensitive().await.unwrap_or_else(|e| {
                     log::error!("Failed to determine whether filesystem is case sensitive: {e:#}");
                     true
@@ -1459,6 +1505,8 @@ impl LocalWorktree {
                     share_private_files,
                     settings,
                     watcher,
+                    poll_for_changes,
+                    polled_paths,
                 };
 
                 scanner
@@ -1466,6 +1514,19 @@ impl LocalWorktree {
                         events.map(|events| events.into_iter().map(Into::into).collect()),
                     ))
                     .await;
+            })
+        });
+        // The worktree keeps its latest snapshot when the scanner panics, but isn't updated
+        // anymore with the changes of its files.
+        let background_scanner = cx.spawn(|this, mut cx| async move {
+            if let Err(panic) = background_scanner.await {
+                this.update(&mut cx, |this, cx| {
+                    let this = this.as_local_mut().unwrap();
+                    *this.is_scanning.0.borrow_mut() = false;
+                    cx.report_task_panic(&panic, TaskRecovery::Degraded);
+                    cx.notify();
+                })
+                .ok();
             }
         });
         let scan_state_updater = cx.spawn(|this, mut cx| async move {
@@ -3219,8 +3280,10 @@ impl LocalSnapshot {
 }
 
 impl BackgroundScannerState {
-    fn should_scan_directory(&self, entry: &Entry) -> bool {
-        (!entry.is_external && (!entry.is_ignored || entry.is_always_included))
+    fn should_scan_directory(&self, entry: &Entry, settings: &WorktreeSettings) -> bool {
+        (!entry.is_external
+            && (!entry.is_ignored || entry.is_always_included)
+            && !settings.is_scan_deferred(&entry.path, entry.canonical_path.is_some()))
             || entry.path.file_name() == Some(*DOT_GIT)
             || entry.path.file_name() == Some(local_settings_folder_relative_path().as_os_str())
             || self.scanned_dirs.contains(&entry.id) // If we've ever scanned it, keep scanning
@@ -4238,6 +4301,22 @@ struct BackgroundScanner {
     watcher: Arc<dyn Watcher>,
     settings: WorktreeSettings,
     share_private_files: bool,
+    /// Whether the worktree is checked for changes periodically, instead of being watched.
+    poll_for_changes: bool,
+    polled_paths: Arc<Mutex<PolledPaths>>,
+}
+
+/// Stands in for the watcher of worktrees that are polled for changes.
+struct PollingWatcher;
+
+impl Watcher for PollingWatcher {
+    fn add(&self, _: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    fn remove(&self, _: &Path) -> Result<()> {
+        Ok(())
+    }
 }
 
 #[derive(PartialEq)]
@@ -4392,8 +4471,75 @@ impl BackgroundScanner {
                     }
                     self.process_events(paths.into_iter().map(Into::into).collect()).await;
                 }
+
+                _ = self.next_poll().fuse() => {
+                    let paths = self.poll_changed_paths().await;
+                    if !paths.is_empty() {
+                        self.process_events(paths).await;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn next_poll(&self) {
+        if self.poll_for_changes {
+            self.executor.timer(NETWORK_MOUNT_POLL_INTERVAL).await;
+        } else {
+            futures::future::pending::<()>().await;
+        }
+    }
+
+    /// Returns the paths of the polled entries that were changed or removed since they were
+    /// scanned, according to their modification times. These are the open files, the expanded
+    /// directories and the root, and their children.
+    async fn poll_changed_paths(&self) -> Vec<PathBuf> {
+        let (root_abs_path, entries) = {
+            let state = self.state.lock();
+            let polled_paths = self.polled_paths.lock();
+            let snapshot = &state.snapshot;
+            let root_path = Arc::<Path>::from(Path::new(""));
+            let mut entries = BTreeMap::new();
+            for dir_path in polled_paths.expanded_dirs.iter().chain([&root_path]) {
+                let Some(dir) = snapshot.entry_for_path(dir_path) else {
+                    continue;
+                };
+                if dir.kind.is_unloaded() {
+                    continue;
+                }
+                for entry in [dir].into_iter().chain(snapshot.child_entries(dir_path)) {
+                    entries.insert(entry.path.clone(), entry.mtime);
+                }
+            }
+            for file_path in &polled_paths.open_files {
+                if let Some(entry) = snapshot.entry_for_path(file_path) {
+                    entries.insert(entry.path.clone(), entry.mtime);
+                }
+            }
+            (snapshot.abs_path.clone(), entries)
+        };
+        let Some(root_canonical_path) = self
+            .fs
+            .canonicalize(root_abs_path.as_path())
+            .await
+            .log_err()
+        else {
+            return Vec::new();
+        };
+
+        let mut changed_paths = Vec::new();
+        for (path, mtime) in entries {
+            let abs_path = if path.as_os_str().is_empty() {
+                root_canonical_path.clone()
+            } else {
+                root_canonical_path.join(&path)
+            };
+            let metadata = self.fs.metadata(&abs_path).await.ok().flatten();
+            if metadata.map(|metadata| metadata.mtime) != mtime {
+                changed_paths.push(abs_path);
             }
         }
+        changed_paths
     }
 
     async fn process_scan_request(&self, mut request: ScanRequest, scanning: bool) -> bool {
@@ -4853,6 +4999,7 @@ impl BackgroundScanner {
 
                 // Avoid recursing until crash in the case of a recursive symlink
                 if job.ancestor_inodes.contains(&child_entry.inode) {
+                    log::debug!("not scanning {child_path:?}, since it links to an ancestor");
                     new_jobs.push(None);
                 } else {
                     let mut ancestor_inodes = job.ancestor_inodes.clone();
@@ -4894,7 +5041,7 @@ impl BackgroundScanner {
         for entry in &mut new_entries {
             state.reuse_entry_id(entry);
             if entry.is_dir() {
-                if state.should_scan_directory(entry) {
+                if state.should_scan_directory(entry, &self.settings) {
                     job_ix += 1;
                 } else {
                     log::debug!("defer scanning directory {:?}", entry.path);
@@ -5077,7 +5224,7 @@ impl BackgroundScanner {
                     fs_entry.is_always_included = self.settings.is_path_always_included(path);
 
                     if let (Some(scan_queue_tx), true) = (&scan_queue_tx, is_dir) {
-                        if state.should_scan_directory(&fs_entry)
+                        if state.should_scan_directory(&fs_entry, &self.settings)
                             || (fs_entry.path.as_os_str().is_empty()
                                 && abs_path.file_name() == Some(*DOT_GIT))
                         {
@@ -5237,7 +5384,7 @@ impl BackgroundScanner {
                 // Scan any directories that were previously ignored and weren't previously scanned.
                 if was_ignored && !entry.is_ignored && entry.kind.is_unloaded() {
                     let state = self.state.lock();
-                    if state.should_scan_directory(&entry) {
+                    if state.should_scan_directory(&entry, &self.settings) {
                         state.enqueue_scan_dir(abs_path.clone(), &entry, &job.scan_queue);
                     }
                 }
@@ -11,9 +11,23 @@ use util::paths::PathMatcher;
 pub struct WorktreeSettings {
     pub file_scan_inclusions: PathMatcher,
     pub file_scan_exclusions: PathMatcher,
+    pub file_scan_deferrals: PathMatcher,
+    pub file_scan_symlinks: SymlinkPolicy,
+    pub file_scan_poll_network_mounts: bool,
     pub private_files: PathMatcher,
 }
 
+/// How the worktree scanner treats symlinks to directories.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SymlinkPolicy {
+    /// Scan the targets of symlinks, stopping at links to their own ancestors.
+    #[default]
+    Follow,
+    /// List symlinks without scanning their targets until they're expanded.
+    ShowAsLink,
+}
+
 impl WorktreeSettings {
     pub fn is_path_private(&self, path: &Path) -> bool {
         path.ancestors()
@@ -29,6 +43,14 @@ impl WorktreeSettings {
         path.ancestors()
             .any(|ancestor| self.file_scan_inclusions.is_match(&ancestor))
     }
+
+    /// Whether the contents of the directory are only scanned once it's expanded.
+    pub fn is_scan_deferred(&self, path: &Path, is_symlink: bool) -> bool {
+        (is_symlink && self.file_scan_symlinks == SymlinkPolicy::ShowAsLink)
+            || path
+                .ancestors()
+                .any(|ancestor| self.file_scan_deferrals.is_match(ancestor))
+    }
 }
 
 #[derive(Clone, Default, Serialize, Deserialize, JsonSchema)]
@@ -59,6 +81,25 @@ pub struct WorktreeSettingsContent {
     #[serde(default)]
     pub file_scan_inclusions: Option<Vec<String>>,
 
+    /// List the directories matching these globs without scanning their contents until
+    /// they're expanded in the project panel, as for directories ignored by git.
+    /// Default: []
+    #[serde(default)]
+    pub file_scan_deferrals: Option<Vec<String>>,
+
+    /// Whether to scan the targets of symlinks to directories, or to list the symlinks
+    /// without scanning their targets until they're expanded.
+    /// Default: follow
+    #[serde(default)]
+    pub file_scan_symlinks: Option<SymlinkPolicy>,
+
+    /// Whether to periodically check the expanded directories and open files of worktrees
+    /// on network file systems for changes, instead of relying on file system events that
+    /// those may not report.
+    /// Default: true
+    #[serde(default)]
+    pub file_scan_poll_network_mounts: Option<bool>,
+
     /// Treat the files matching these globs as `.env` files.
     /// Default: [ "**/.env*" ]
     pub private_files: Option<Vec<String>>,
@@ -72,6 +113,7 @@ impl Settings for WorktreeSettings {
     fn load(sources: SettingsSources<Self::FileContent>, _: &mut App) -> anyhow::Result<Self> {
         let result: WorktreeSettingsContent = sources.json_merge()?;
         let mut file_scan_exclusions = result.file_scan_exclusions.unwrap_or_default();
+        let mut file_scan_deferrals = result.file_scan_deferrals.unwrap_or_default();
         let mut private_files = result.private_files.unwrap_or_default();
         let mut parsed_file_scan_inclusions: Vec<String> = result
             .file_scan_inclusions
@@ -85,10 +127,14 @@ impl Settings for WorktreeSettings {
             .filter(|p| p != "")
             .collect();
         file_scan_exclusions.sort();
+        file_scan_deferrals.sort();
         private_files.sort();
         parsed_file_scan_inclusions.sort();
         Ok(Self {
             file_scan_exclusions: path_matchers(&file_scan_exclusions, "file_scan_exclusions")?,
+            file_scan_deferrals: path_matchers(&file_scan_deferrals, "file_scan_deferrals")?,
+            file_scan_symlinks: result.file_scan_symlinks.unwrap_or_default(),
+            file_scan_poll_network_mounts: result.file_scan_poll_network_mounts.unwrap_or(true),
             private_files: path_matchers(&private_files, "private_files")?,
             file_scan_inclusions: path_matchers(
                 &parsed_file_scan_inclusions,
@@ -1,6 +1,6 @@
 use crate::{
     worktree_settings::WorktreeSettings, Entry, EntryKind, Event, PathChange, Snapshot,
-    WorkDirectory, Worktree, WorktreeModelHandle,
+    SymlinkPolicy, WorkDirectory, Worktree, WorktreeModelHandle, NETWORK_MOUNT_POLL_INTERVAL,
 };
 use anyhow::Result;
 use fs::{FakeFs, Fs, RealFs, RemoveOptions};
@@ -342,6 +342,153 @@ async fn test_symlinks_pointing_outside(cx: &mut TestAppContext) {
     );
 }
 
+#[gpui::test]
+async fn test_file_scan_deferrals_and_symlink_policy(cx: &mut TestAppContext) {
+    init_test(cx);
+    cx.update(|cx| {
+        cx.update_global::<SettingsStore, _>(|store, cx| {
+            store.update_user_settings::<WorktreeSettings>(cx, |settings| {
+                settings.file_scan_deferrals = Some(vec!["**/vendor".to_string()]);
+                settings.file_scan_symlinks = Some(SymlinkPolicy::ShowAsLink);
+            });
+        });
+    });
+    let fs = FakeFs::new(cx.background_executor.clone());
+    fs.insert_tree(
+        "/root",
+        json!({
+            "src": {
+                "a.rs": "",
+            },
+            "vendor": {
+                "dep": {
+                    "b.rs": "",
+                },
+            },
+        }),
+    )
+    .await;
+    fs.create_symlink("/root/link".as_ref(), "src".into())
+        .await
+        .unwrap();
+
+    let tree = Worktree::local(
+        Path::new("/root"),
+        true,
+        fs.clone(),
+        Default::default(),
+        &mut cx.to_async(),
+    )
+    .await
+    .unwrap();
+    cx.read(|cx| tree.read(cx).as_local().unwrap().scan_complete())
+        .await;
+
+    // Deferred directories and symlinks are listed without their contents.
+    tree.read_with(cx, |tree, _| {
+        assert_eq!(
+            tree.entries(true, 0)
+                .map(|entry| (entry.path.as_ref(), entry.kind))
+                .collect::<Vec<_>>(),
+            vec![
+                (Path::new(""), EntryKind::Dir),
+                (Path::new("link"), EntryKind::UnloadedDir),
+                (Path::new("src"), EntryKind::Dir),
+                (Path::new("src/a.rs"), EntryKind::File),
+                (Path::new("vendor"), EntryKind::UnloadedDir),
+            ]
+        );
+    });
+
+    // Expanding a deferred directory scans it, but not its subdirectories.
+    tree.read_with(cx, |tree, _| {
+        tree.as_local()
+            .unwrap()
+            .refresh_entries_for_paths(vec![Path::new("vendor").into()])
+    })
+    .recv()
+    .await;
+    tree.read_with(cx, |tree, _| {
+        assert_eq!(
+            tree.entries(true, 0)
+                .map(|entry| (entry.path.as_ref(), entry.kind))
+                .collect::<Vec<_>>(),
+            vec![
+                (Path::new(""), EntryKind::Dir),
+                (Path::new("link"), EntryKind::UnloadedDir),
+                (Path::new("src"), EntryKind::Dir),
+                (Path::new("src/a.rs"), EntryKind::File),
+                (Path::new("vendor"), EntryKind::Dir),
+                (Path::new("vendor/dep"), EntryKind::UnloadedDir),
+            ]
+        );
+    });
+}
+
+#[gpui::test]
+async fn test_polling_network_mounts(cx: &mut TestAppContext) {
+    init_test(cx);
+    let fs = FakeFs::new(cx.background_executor.clone());
+    fs.insert_tree(
+        "/root",
+        json!({
+            "a.txt": "a",
+            "b.txt": "b",
+            "dir": {
+                "c.txt": "c",
+            },
+        }),
+    )
+    .await;
+    fs.set_network_mount("/root");
+
+    let tree = Worktree::local(
+        Path::new("/root"),
+        true,
+        fs.clone(),
+        Default::default(),
+        &mut cx.to_async(),
+    )
+    .await
+    .unwrap();
+    cx.read(|cx| tree.read(cx).as_local().unwrap().scan_complete())
+        .await;
+
+    fs.insert_file("/root/a.txt", b"changed".to_vec()).await;
+    fs.remove_file("/root/b.txt".as_ref(), Default::default())
+        .await
+        .unwrap();
+    cx.executor().run_until_parked();
+
+    // The changes are only noticed once the worktree is polled.
+    tree.read_with(cx, |tree, _| {
+        assert!(tree.entry_for_path("b.txt").is_some());
+    });
+    cx.executor().advance_clock(NETWORK_MOUNT_POLL_INTERVAL);
+    cx.executor().run_until_parked();
+    tree.read_with(cx, |tree, _| {
+        assert_eq!(tree.entry_for_path("a.txt").unwrap().size, 7);
+        assert!(tree.entry_for_path("b.txt").is_none());
+    });
+
+    // Only the children of the root and of the expanded directories, and the open files,
+    // are polled.
+    fs.insert_file("/root/dir/c.txt", b"changed".to_vec()).await;
+    cx.executor().advance_clock(NETWORK_MOUNT_POLL_INTERVAL);
+    cx.executor().run_until_parked();
+    tree.read_with(cx, |tree, _| {
+        assert_eq!(tree.entry_for_path("dir/c.txt").unwrap().size, 1);
+        tree.as_local()
+            .unwrap()
+            .set_open_files([Arc::from(Path::new("dir/c.txt"))]);
+    });
+    cx.executor().advance_clock(NETWORK_MOUNT_POLL_INTERVAL);
+    cx.executor().run_until_parked();
+    tree.read_with(cx, |tree, _| {
+        assert_eq!(tree.entry_for_path("dir/c.txt").unwrap().size, 7);
+    });
+}
+
 #[cfg(target_os = "macos")]
 #[gpui::test]
 async fn test_renaming_case_only(cx: &mut TestAppContext) {
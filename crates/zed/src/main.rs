@@ -2,6 +2,7 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod reliability;
+mod startup_profile;
 mod zed;
 
 use anyhow::{anyhow, Context as _, Result};
@@ -36,6 +37,7 @@ use release_channel::{AppCommitSha, AppVersion, ReleaseChannel};
 use session::{AppSession, Session};
 use settings::{watch_config_file, Settings, SettingsStore};
 use simplelog::ConfigBuilder;
+use startup_profile::{StartupPhase, StartupProfile};
 use std::{
     env,
     fs::OpenOptions,
@@ -43,6 +45,7 @@ use std::{
     path::{Path, PathBuf},
     process,
     sync::Arc,
+    time::Instant,
 };
 use theme::{ActiveTheme, SystemAppearance, ThemeRegistry, ThemeSettings};
 use time::UtcOffset;
@@ -169,6 +172,7 @@ fn fail_to_open_window(e: anyhow::Error, _cx: &mut App) {
 }
 
 fn main() {
+    let launched_at = Instant::now();
     menu::init();
     zed_actions::init();
 
@@ -288,8 +292,10 @@ fn main() {
         if let Some(app_commit_sha) = app_commit_sha {
             AppCommitSha::set_global(app_commit_sha, cx);
         }
+        StartupProfile::init(launched_at, cx);
         settings::init(cx);
         handle_settings_file_changes(user_settings_file_rx, cx, handle_settings_changed);
+        StartupProfile::record(StartupPhase::SettingsLoaded, cx);
         handle_keymap_file_changes(user_keymap_file_rx, cx);
         client::init_settings(cx);
         let user_agent = format!(
@@ -417,6 +423,7 @@ fn main() {
 
         SystemAppearance::init(cx);
         theme::init(theme::LoadThemes::All(Box::new(Assets)), cx);
+        StartupProfile::record(StartupPhase::ThemeResolved, cx);
         theme_extension::init(
             extension_host_proxy.clone(),
             ThemeRegistry::global(cx),
@@ -551,6 +558,13 @@ fn main() {
         );
         telemetry.flush_events();
 
+        fs.set_atomic_save(WorkspaceSettings::get_global(cx).atomic_save);
+        cx.observe_global::<SettingsStore>({
+            let fs = fs.clone();
+            move |cx| fs.set_atomic_save(WorkspaceSettings::get_global(cx).atomic_save)
+        })
+        .detach();
+
         let fs = app_state.fs.clone();
         load_user_themes_in_background(fs.clone(), cx);
         watch_themes(fs.clone(), cx);
@@ -569,6 +583,9 @@ fn main() {
         .detach_and_log_err(cx);
 
         let args = Args::parse();
+        if args.profile_startup {
+            StartupProfile::write_report_to(paths::logs_dir().join("startup-profile.json"), cx);
+        }
         let urls: Vec<_> = args
             .paths_or_urls
             .iter()
@@ -587,6 +604,7 @@ fn main() {
         {
             Some(request) => {
                 handle_open_request(request, app_state.clone(), cx);
+                StartupProfile::record(StartupPhase::SessionRestored, cx);
             }
             None => {
                 cx.spawn({
@@ -595,6 +613,8 @@ fn main() {
                         if let Err(e) = restore_or_create_workspace(app_state, &mut cx).await {
                             fail_to_open_window_async(e, &mut cx)
                         }
+                        cx.update(|cx| StartupProfile::record(StartupPhase::SessionRestored, cx))
+                            .log_err();
                     }
                 })
                 .detach();
@@ -1006,6 +1026,11 @@ struct Args {
     /// Instructs zed to run as a dev server on this machine. (not implemented)
     #[arg(long)]
     dev_server_token: Option<String>,
+
+    /// Records how long each phase of startup takes, writing a report to
+    /// `startup-profile.json` in the logs directory once the session is restored.
+    #[arg(long)]
+    profile_startup: bool,
 }
 
 #[derive(Clone, Debug)]
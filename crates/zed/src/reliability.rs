@@ -4,7 +4,7 @@ use backtrace::{self, Backtrace};
 use chrono::Utc;
 use client::{telemetry, TelemetrySettings};
 use db::kvp::KEY_VALUE_STORE;
-use gpui::{App, SemanticVersion};
+use gpui::{supervised_task_name, App, SemanticVersion};
 use http_client::{self, HttpClient, HttpClientWithUrl, HttpRequestExt, Method};
 use paths::{crashes_dir, crashes_retired_dir};
 use project::Project;
@@ -33,8 +33,13 @@ pub fn init_panic_hook(
     let is_pty = stdout_is_a_pty();
 
     panic::set_hook(Box::new(move |info| {
-        let prior_panic_count = PANIC_COUNT.fetch_add(1, Ordering::SeqCst);
-        if prior_panic_count > 0 {
+        // The panics of supervised tasks are caught and recovered from by their owners, so
+        // they're reported without aborting.
+        let supervised_task = supervised_task_name();
+        let recovered = supervised_task.is_some();
+        if let Some(task_name) = &supervised_task {
+            log::error!("supervised task {task_name} panicked");
+        } else if PANIC_COUNT.fetch_add(1, Ordering::SeqCst) > 0 {
             // Give the panic-ing thread time to write the panic file
             loop {
                 std::thread::yield_now();
@@ -73,6 +78,9 @@ pub fn init_panic_hook(
                 },
                 backtrace,
             );
+            if recovered {
+                return;
+            }
             std::process::exit(-1);
         }
         let main_module_base_address = get_main_module_base_address();
@@ -125,6 +133,7 @@ pub fn init_panic_hook(
             system_id: system_id.clone(),
             installation_id: installation_id.clone(),
             session_id: session_id.clone(),
+            recovered,
         };
 
         if let Some(panic_data_json) = serde_json::to_string_pretty(&panic_data).log_err() {
@@ -147,7 +156,9 @@ pub fn init_panic_hook(
             }
         }
 
-        std::process::abort();
+        if !recovered {
+            std::process::abort();
+        }
     }));
 }
 
@@ -530,7 +541,10 @@ async fn upload_panic(
     panic: telemetry_events::Panic,
     most_recent_panic: &mut Option<(i64, String)>,
 ) -> Result<bool> {
-    *most_recent_panic = Some((panic.panicked_on, panic.payload.clone()));
+    // Crashes are only attributed to the panics that weren't recovered from.
+    if !panic.recovered {
+        *most_recent_panic = Some((panic.panicked_on, panic.payload.clone()));
+    }
 
     let json_bytes = serde_json::to_vec(&PanicRequest { panic }).unwrap();
 
@@ -0,0 +1,161 @@
+//! Timestamps of the phases of startup, reported with `--profile-startup`.
+//!
+//! Each phase is recorded once, when it is first reached, as the time elapsed since launch.
+//! Phases reached later than their budget are logged as warnings and reported to telemetry
+//! in every run, while the report is only written with `--profile-startup`. It's written once
+//! the first window was painted and the session restored, listing the phases a launch didn't
+//! go through, like scanning a worktree when no project is opened, as not reached.
+
+use gpui::{App, Global, Window};
+use serde::Serialize;
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+use util::ResultExt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupPhase {
+    SettingsLoaded,
+    ThemeResolved,
+    WorktreeScanStarted,
+    FirstWindowPainted,
+    SessionRestored,
+}
+
+impl StartupPhase {
+    const ALL: [Self; 5] = [
+        Self::SettingsLoaded,
+        Self::ThemeResolved,
+        Self::WorktreeScanStarted,
+        Self::FirstWindowPainted,
+        Self::SessionRestored,
+    ];
+
+    /// The time since launch by which the phase is expected to be reached.
+    fn budget(self) -> Duration {
+        match self {
+            Self::SettingsLoaded => Duration::from_millis(250),
+            Self::ThemeResolved => Duration::from_millis(400),
+            Self::WorktreeScanStarted => Duration::from_millis(1000),
+            Self::FirstWindowPainted => Duration::from_millis(1000),
+            Self::SessionRestored => Duration::from_millis(2000),
+        }
+    }
+}
+
+pub struct StartupProfile {
+    launched_at: Instant,
+    phases: Vec<(StartupPhase, Duration)>,
+    report_path: Option<PathBuf>,
+}
+
+impl Global for StartupProfile {}
+
+#[derive(Serialize)]
+struct StartupReport {
+    phases: Vec<PhaseReport>,
+}
+
+#[derive(Serialize)]
+struct PhaseReport {
+    phase: StartupPhase,
+    /// The time since launch, unless the phase wasn't reached.
+    elapsed_ms: Option<f64>,
+    budget_ms: f64,
+    over_budget: bool,
+}
+
+impl StartupProfile {
+    pub fn init(launched_at: Instant, cx: &mut App) {
+        cx.set_global(Self {
+            launched_at,
+            phases: Vec::new(),
+            report_path: None,
+        });
+    }
+
+    /// Writes the report to the given path once startup is complete.
+    pub fn write_report_to(path: PathBuf, cx: &mut App) {
+        if let Some(profile) = cx.try_global_mut::<Self>() {
+            profile.report_path = Some(path);
+            profile.write_report_if_complete();
+        }
+    }
+
+    /// Records that the phase was reached, unless it already was.
+    pub fn record(phase: StartupPhase, cx: &mut App) {
+        let Some(profile) = cx.try_global_mut::<Self>() else {
+            return;
+        };
+        if profile.has_reached(phase) {
+            return;
+        }
+
+        let elapsed = profile.launched_at.elapsed();
+        profile.phases.push((phase, elapsed));
+        if elapsed > phase.budget() {
+            log::warn!(
+                "startup phase {phase:?} was reached after {elapsed:?}, over its budget of {:?}",
+                phase.budget()
+            );
+            telemetry::event!(
+                "Startup Phase Over Budget",
+                phase = format!("{phase:?}"),
+                elapsed_ms = elapsed.as_millis() as u64,
+                budget_ms = phase.budget().as_millis() as u64
+            );
+        }
+        profile.write_report_if_complete();
+    }
+
+    /// Records the first paint of the window, when it is the first window to be painted.
+    pub fn record_first_paint(window: &Window, cx: &App) {
+        if cx.try_global::<Self>().map_or(true, |profile| {
+            profile.has_reached(StartupPhase::FirstWindowPainted)
+        }) {
+            return;
+        }
+        window.on_next_frame(|_, cx| Self::record(StartupPhase::FirstWindowPainted, cx));
+    }
+
+    fn has_reached(&self, phase: StartupPhase) -> bool {
+        self.phases.iter().any(|(recorded, _)| *recorded == phase)
+    }
+
+    fn write_report_if_complete(&mut self) {
+        if !self.has_reached(StartupPhase::FirstWindowPainted)
+            || !self.has_reached(StartupPhase::SessionRestored)
+        {
+            return;
+        }
+        let Some(path) = self.report_path.take() else {
+            return;
+        };
+
+        let report = StartupReport {
+            phases: StartupPhase::ALL
+                .into_iter()
+                .map(|phase| {
+                    let elapsed = self
+                        .phases
+                        .iter()
+                        .find(|(recorded, _)| *recorded == phase)
+                        .map(|(_, elapsed)| *elapsed);
+                    PhaseReport {
+                        phase,
+                        elapsed_ms: elapsed.map(|elapsed| elapsed.as_secs_f64() * 1000.),
+                        budget_ms: phase.budget().as_secs_f64() * 1000.,
+                        over_budget: elapsed.map_or(false, |elapsed| elapsed > phase.budget()),
+                    }
+                })
+                .collect(),
+        };
+        if let Some(json) = serde_json::to_string_pretty(&report).log_err() {
+            if std::fs::write(&path, json).log_err().is_some() {
+                log::info!("wrote the startup profile to {path:?}");
+            }
+        }
+    }
+}
@@ -1,4 +1,7 @@
 mod app_menus;
+#[cfg(not(target_os = "windows"))]
+mod control_socket;
+mod discarded_changes;
 pub mod inline_completion_registry;
 #[cfg(any(target_os = "linux", target_os = "freebsd"))]
 pub(crate) mod linux_prompts;
@@ -7,9 +10,12 @@ pub(crate) mod mac_only_instance;
 mod migrate;
 mod open_listener;
 mod quick_action_bar;
+mod quit;
+mod scratchpads;
 #[cfg(target_os = "windows")]
 pub(crate) mod windows_only_instance;
 
+use crate::startup_profile::{StartupPhase, StartupProfile};
 use anyhow::Context as _;
 pub use app_menus::*;
 use assets::Assets;
@@ -44,12 +50,11 @@ use rope::Rope;
 use search::project_search::ProjectSearchBar;
 use settings::{
     initial_project_settings_content, initial_tasks_content, update_settings_file,
-    InvalidSettingsError, KeymapFile, KeymapFileLoadResult, Settings, SettingsStore,
+    InvalidSettingsError, KeybindSource, KeymapFile, KeymapFileLoadResult, Settings, SettingsStore,
     DEFAULT_KEYMAP_PATH, VIM_KEYMAP_PATH,
 };
 use std::any::TypeId;
 use std::path::PathBuf;
-use std::sync::atomic::{self, AtomicBool};
 use std::time::Duration;
 use std::{borrow::Cow, ops::Deref, path::Path, sync::Arc};
 use terminal_view::terminal_panel::{self, TerminalPanel};
@@ -61,15 +66,12 @@ use uuid::Uuid;
 use vim_mode_setting::VimModeSetting;
 use welcome::{BaseKeymap, MultibufferHint};
 use workspace::notifications::{dismiss_app_notification, show_app_notification, NotificationId};
-use workspace::CloseIntent;
 use workspace::{
     create_and_open_local_file, notifications::simple_message_notification::MessageNotification,
-    open_new, AppState, NewFile, NewWindow, OpenLog, Toast, Workspace, WorkspaceSettings,
+    open_new, AppState, NewFile, NewWindow, OpenLog, Toast, Workspace,
 };
 use workspace::{notifications::DetachAndPromptErr, Pane};
-use zed_actions::{
-    OpenAccountSettings, OpenBrowser, OpenServerSettings, OpenSettings, OpenZedUrl, Quit,
-};
+use zed_actions::{OpenAccountSettings, OpenBrowser, OpenServerSettings, OpenSettings, OpenZedUrl};
 
 actions!(
     zed,
@@ -97,11 +99,15 @@ pub fn init(cx: &mut App) {
     cx.on_action(|_: &HideOthers, cx| cx.hide_other_apps());
     #[cfg(target_os = "macos")]
     cx.on_action(|_: &ShowAll, cx| cx.unhide_other_apps());
-    cx.on_action(quit);
+    cx.on_action(quit::quit);
+    scratchpads::init(cx);
 
     if ReleaseChannel::global(cx) == ReleaseChannel::Dev {
         cx.on_action(test_panic);
     }
+
+    #[cfg(not(target_os = "windows"))]
+    control_socket::init(cx);
 }
 
 pub fn build_window_options(display_uuid: Option<Uuid>, cx: &mut App) -> WindowOptions {
@@ -153,6 +159,19 @@ pub fn initialize_workspace(
         let center_pane = workspace.active_pane().clone();
         initialize_pane(workspace, &center_pane, window, cx);
 
+        StartupProfile::record_first_paint(window, cx);
+        let project = workspace.project().clone();
+        if project.read(cx).worktrees(cx).next().is_some() {
+            StartupProfile::record(StartupPhase::WorktreeScanStarted, cx);
+        } else {
+            cx.subscribe(&project, |_, _, event, cx| {
+                if let project::Event::WorktreeAdded(_) = event {
+                    StartupProfile::record(StartupPhase::WorktreeScanStarted, cx);
+                }
+            })
+            .detach();
+        }
+
         cx.subscribe_in(&workspace_handle, window, {
             move |workspace, _, event, window, cx| match event {
                 workspace::Event::PaneAdded(pane) => {
@@ -233,6 +252,7 @@ pub fn initialize_workspace(
 
         initialize_panels(prompt_builder.clone(), window, cx);
         register_actions(app_state.clone(), workspace, window, cx);
+        scratchpads::restore_scratchpads(workspace, window, cx);
 
         workspace.focus_handle(cx).focus(window);
     })
@@ -649,6 +669,17 @@ fn register_actions(
                 open_telemetry_log_file(workspace, window, cx);
             },
         )
+        .register_action(discarded_changes::toggle)
+        .register_action(scratchpads::new_scratchpad)
+        .register_action(scratchpads::open_scratchpads)
+        .register_action(
+            move |workspace: &mut Workspace,
+                  _: &zed_actions::OpenPendingTelemetryEvents,
+                  window: &mut Window,
+                  cx: &mut Context<Workspace>| {
+                open_pending_telemetry_events(workspace, window, cx);
+            },
+        )
         .register_action(
             move |_: &mut Workspace,
                   _: &zed_actions::OpenKeymap,
@@ -949,72 +980,6 @@ fn install_cli(
     .detach_and_prompt_err("Error installing zed cli", window, cx, |_, _, _| None);
 }
 
-static WAITING_QUIT_CONFIRMATION: AtomicBool = AtomicBool::new(false);
-fn quit(_: &Quit, cx: &mut App) {
-    if WAITING_QUIT_CONFIRMATION.load(atomic::Ordering::Acquire) {
-        return;
-    }
-
-    let should_confirm = WorkspaceSettings::get_global(cx).confirm_quit;
-    cx.spawn(|mut cx| async move {
-        let mut workspace_windows = cx.update(|cx| {
-            cx.windows()
-                .into_iter()
-                .filter_map(|window| window.downcast::<Workspace>())
-                .collect::<Vec<_>>()
-        })?;
-
-        // If multiple windows have unsaved changes, and need a save prompt,
-        // prompt in the active window before switching to a different window.
-        cx.update(|cx| {
-            workspace_windows.sort_by_key(|window| window.is_active(cx) == Some(false));
-        })
-        .log_err();
-
-        if should_confirm {
-            if let Some(workspace) = workspace_windows.first() {
-                let answer = workspace
-                    .update(&mut cx, |_, window, cx| {
-                        window.prompt(
-                            PromptLevel::Info,
-                            "Are you sure you want to quit?",
-                            None,
-                            &["Quit", "Cancel"],
-                            cx,
-                        )
-                    })
-                    .log_err();
-
-                if let Some(answer) = answer {
-                    WAITING_QUIT_CONFIRMATION.store(true, atomic::Ordering::Release);
-                    let answer = answer.await.ok();
-                    WAITING_QUIT_CONFIRMATION.store(false, atomic::Ordering::Release);
-                    if answer != Some(0) {
-                        return Ok(());
-                    }
-                }
-            }
-        }
-
-        // If the user cancels any save prompt, then keep the app open.
-        for window in workspace_windows {
-            if let Some(should_close) = window
-                .update(&mut cx, |workspace, window, cx| {
-                    workspace.prepare_to_close(CloseIntent::Quit, window, cx)
-                })
-                .log_err()
-            {
-                if !should_close.await? {
-                    return Ok(());
-                }
-            }
-        }
-        cx.update(|cx| cx.quit())?;
-        anyhow::Ok(())
-    })
-    .detach_and_log_err(cx);
-}
-
 fn open_log_file(workspace: &mut Workspace, window: &mut Window, cx: &mut Context<Workspace>) {
     const MAX_LINES: usize = 1000;
     workspace
@@ -1346,7 +1311,7 @@ fn show_markdown_app_notification<F>(
 fn reload_keymaps(cx: &mut App, user_key_bindings: Vec<KeyBinding>) {
     cx.clear_key_bindings();
     load_default_keymap(cx);
-    cx.bind_keys(user_key_bindings);
+    cx.bind_keys(with_source(user_key_bindings, KeybindSource::User));
     cx.set_menus(app_menus());
     cx.set_dock_menu(vec![MenuItem::action("New Window", workspace::NewWindow)]);
 }
@@ -1357,17 +1322,35 @@ pub fn load_default_keymap(cx: &mut App) {
         return;
     }
 
-    cx.bind_keys(KeymapFile::load_asset(DEFAULT_KEYMAP_PATH, cx).unwrap());
+    cx.bind_keys(with_source(
+        KeymapFile::load_asset(DEFAULT_KEYMAP_PATH, cx).unwrap(),
+        KeybindSource::Default,
+    ));
 
     if let Some(asset_path) = base_keymap.asset_path() {
-        cx.bind_keys(KeymapFile::load_asset(asset_path, cx).unwrap());
+        cx.bind_keys(with_source(
+            KeymapFile::load_asset(asset_path, cx).unwrap(),
+            KeybindSource::Base,
+        ));
     }
 
     if VimModeSetting::get_global(cx).0 {
-        cx.bind_keys(KeymapFile::load_asset(VIM_KEYMAP_PATH, cx).unwrap());
+        cx.bind_keys(with_source(
+            KeymapFile::load_asset(VIM_KEYMAP_PATH, cx).unwrap(),
+            KeybindSource::Vim,
+        ));
     }
 }
 
+fn with_source(
+    key_bindings: Vec<KeyBinding>,
+    source: KeybindSource,
+) -> impl IntoIterator<Item = KeyBinding> {
+    key_bindings
+        .into_iter()
+        .map(move |binding| binding.with_meta(source.meta()))
+}
+
 pub fn handle_settings_changed(error: Option<anyhow::Error>, cx: &mut App) {
     struct SettingsParseErrorNotification;
     let id = NotificationId::unique::<SettingsParseErrorNotification>();
@@ -1578,6 +1561,68 @@ fn open_telemetry_log_file(
     }).detach();
 }
 
+fn open_pending_telemetry_events(
+    workspace: &mut Workspace,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    workspace
+        .with_local_workspace(window, cx, move |workspace, window, cx| {
+            let app_state = workspace.app_state().clone();
+            let payload = app_state
+                .client
+                .telemetry()
+                .pending_payload()
+                .unwrap_or_else(|error| {
+                    format!("// Failed to serialize the pending events: {error}")
+                });
+            let header = concat!(
+                "// These are the events that have been collected, but not reported yet.\n",
+                "// They are sent with the next upload, exactly as shown below.\n",
+                "// Telemetry can be disabled via the `settings.json` file.\n",
+            );
+            let content = format!("{header}\n{payload}");
+            cx.spawn_in(window, |workspace, mut cx| async move {
+                let json = app_state
+                    .languages
+                    .language_for_name("JSON")
+                    .await
+                    .log_err();
+                workspace
+                    .update_in(&mut cx, |workspace, window, cx| {
+                        let project = workspace.project().clone();
+                        let buffer = project.update(cx, |project, cx| {
+                            project.create_local_buffer(&content, json, cx)
+                        });
+                        let buffer = cx.new(|cx| {
+                            MultiBuffer::singleton(buffer, cx)
+                                .with_title("Pending Telemetry".into())
+                        });
+                        workspace.add_item_to_active_pane(
+                            Box::new(cx.new(|cx| {
+                                let mut editor = Editor::for_multibuffer(
+                                    buffer,
+                                    Some(project),
+                                    true,
+                                    window,
+                                    cx,
+                                );
+                                editor.set_breadcrumb_header("Pending Telemetry".into());
+                                editor
+                            })),
+                            None,
+                            true,
+                            window,
+                            cx,
+                        );
+                    })
+                    .log_err();
+            })
+            .detach();
+        })
+        .detach();
+}
+
 fn open_bundled_file(
     workspace: &Workspace,
     text: Cow<'static, str>,
@@ -2223,6 +2268,71 @@ mod tests {
             .unwrap();
     }
 
+    #[gpui::test]
+    async fn test_quit_prompts_once_for_all_windows(cx: &mut TestAppContext) {
+        let app_state = init_test(cx);
+        cx.update(|cx| {
+            SettingsStore::update_global(cx, |store, cx| {
+                store.update_user_settings::<ProjectSettings>(cx, |settings| {
+                    settings.session.restore_unsaved_buffers = false
+                });
+            });
+        });
+        app_state
+            .fs
+            .as_fake()
+            .insert_tree(
+                path!("/root"),
+                json!({"dir1": {"a": "aaa"}, "dir2": {"b": "bbb"}}),
+            )
+            .await;
+
+        for path in [path!("/root/dir1/a"), path!("/root/dir2/b")] {
+            cx.update(|cx| {
+                open_paths(
+                    &[PathBuf::from(path)],
+                    app_state.clone(),
+                    workspace::OpenOptions::default(),
+                    cx,
+                )
+            })
+            .await
+            .unwrap();
+        }
+        assert_eq!(cx.update(|cx| cx.windows().len()), 2);
+
+        for window in cx.update(|cx| cx.windows()) {
+            let window = window.downcast::<Workspace>().unwrap();
+            window
+                .update(cx, |workspace, window, cx| {
+                    let editor = workspace
+                        .active_item(cx)
+                        .unwrap()
+                        .downcast::<Editor>()
+                        .unwrap();
+                    editor.update(cx, |editor, cx| editor.insert("EDIT", window, cx));
+                })
+                .unwrap();
+        }
+
+        cx.update(|cx| quit::quit(&zed_actions::Quit, cx));
+        cx.run_until_parked();
+        assert!(cx.has_pending_prompt());
+        cx.simulate_prompt_answer(0);
+        cx.run_until_parked();
+        assert!(!cx.has_pending_prompt());
+
+        let fs = app_state.fs.clone();
+        assert_eq!(
+            fs.load(path!("/root/dir1/a").as_ref()).await.unwrap(),
+            "EDITaaa"
+        );
+        assert_eq!(
+            fs.load(path!("/root/dir2/b").as_ref()).await.unwrap(),
+            "EDITbbb"
+        );
+    }
+
     #[gpui::test]
     async fn test_new_empty_workspace(cx: &mut TestAppContext) {
         let app_state = init_test(cx);
@@ -0,0 +1,575 @@
+//! A local socket speaking line-delimited JSON-RPC 2.0, which lets external
+//! tools and test harnesses drive the editor. It is only started when the
+//! `ZED_CONTROL_SOCKET` environment variable names the socket, which is
+//! created in [`paths::control_sockets_dir`] and only accessible to the user.
+
+use anyhow::{Context as _, Result};
+use editor::{scroll::Autoscroll, Editor};
+use futures::{
+    channel::mpsc, select_biased, AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt,
+    StreamExt,
+};
+use gpui::{App, AppContext as _, AsyncApp, Entity, Global, WindowHandle};
+use language::{Buffer, BufferEvent, Point};
+use project::search::SearchQuery;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use smol::{
+    io::BufReader,
+    net::unix::{UnixListener, UnixStream},
+};
+use std::{
+    ffi::OsString,
+    fs::Permissions,
+    ops::Range,
+    os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt},
+    path::{Path, PathBuf},
+};
+use util::{paths::PathMatcher, ResultExt};
+use workspace::Workspace;
+
+const PARSE_ERROR: i32 = -32700;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+const SERVER_ERROR: i32 = -32000;
+
+/// The connections that subscribed to editor events.
+#[derive(Default)]
+struct ControlSocket {
+    subscribers: Vec<mpsc::UnboundedSender<String>>,
+}
+
+impl Global for ControlSocket {}
+
+impl ControlSocket {
+    fn notify(&mut self, method: &str, params: Value) {
+        let notification = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+        let notification = notification.to_string();
+        self.subscribers
+            .retain(|subscriber| subscriber.unbounded_send(notification.clone()).is_ok());
+    }
+}
+
+pub fn init(cx: &mut App) {
+    let Some(socket_name) = std::env::var_os("ZED_CONTROL_SOCKET") else {
+        return;
+    };
+    let socket_path = match control_socket_path(&socket_name) {
+        Ok(socket_path) => socket_path,
+        Err(error) => {
+            log::error!("{error:#}");
+            return;
+        }
+    };
+
+    cx.set_global(ControlSocket::default());
+    cx.observe_new(|_: &mut Buffer, _, cx| {
+        let buffer = cx.entity();
+        cx.subscribe(&buffer, |buffer, _, event, cx| {
+            if let BufferEvent::Saved = event {
+                if let Some(path) = buffer_abs_path(buffer, cx) {
+                    cx.update_global(|socket: &mut ControlSocket, _| {
+                        socket.notify("didSave", json!({ "path": path }))
+                    });
+                }
+            }
+        })
+        .detach();
+    })
+    .detach();
+
+    cx.spawn(|cx| async move { listen(socket_path, cx).await.log_err() })
+        .detach();
+}
+
+/// Returns the path of the socket with the given name in the control sockets directory,
+/// creating the directory, only accessible to the user, if needed.
+fn control_socket_path(socket_name: &OsString) -> Result<PathBuf> {
+    let file_name = Path::new(socket_name)
+        .file_name()
+        .filter(|file_name| file_name == socket_name)
+        .with_context(|| format!("invalid control socket name {socket_name:?}"))?;
+    let dir = paths::control_sockets_dir();
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .with_context(|| format!("creating control sockets directory {dir:?}"))?;
+    std::fs::set_permissions(dir, Permissions::from_mode(0o700))
+        .with_context(|| format!("restricting control sockets directory {dir:?}"))?;
+    Ok(dir.join(file_name))
+}
+
+async fn listen(socket_path: PathBuf, cx: AsyncApp) -> Result<()> {
+    if let Ok(metadata) = std::fs::symlink_metadata(&socket_path) {
+        anyhow::ensure!(
+            metadata.file_type().is_socket(),
+            "not replacing {socket_path:?} with a control socket, since it isn't a socket"
+        );
+        std::fs::remove_file(&socket_path)
+            .with_context(|| format!("removing stale control socket {socket_path:?}"))?;
+    }
+    let listener = UnixListener::bind(&socket_path)
+        .with_context(|| format!("binding control socket {socket_path:?}"))?;
+    std::fs::set_permissions(&socket_path, Permissions::from_mode(0o600))
+        .with_context(|| format!("restricting control socket {socket_path:?}"))?;
+    log::info!("listening for control connections on {socket_path:?}");
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        cx.spawn(|cx| async move { handle_connection(stream, cx).await.log_err() })
+            .detach();
+    }
+}
+
+async fn handle_connection(stream: UnixStream, cx: AsyncApp) -> Result<()> {
+    serve(BufReader::new(stream.clone()), stream, cx).await
+}
+
+/// Answers the requests read from `reader`, one per line, writing the responses and the
+/// notifications subscribed to to `writer`, until `reader` is exhausted.
+async fn serve(
+    reader: impl AsyncBufRead + Unpin,
+    mut writer: impl AsyncWrite + Unpin,
+    mut cx: AsyncApp,
+) -> Result<()> {
+    let (notifications_tx, mut notifications_rx) = mpsc::unbounded();
+    let mut requests = reader.lines().fuse();
+    loop {
+        let message = select_biased! {
+            notification = notifications_rx.next() => notification,
+            request = requests.next() => match request {
+                Some(request) => Some(handle_request(&request?, &notifications_tx, &mut cx).await),
+                None => break,
+            },
+        };
+        if let Some(message) = message {
+            writer.write_all(message.as_bytes()).await?;
+            writer.write_all(b"\n").await?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize)]
+struct RpcError {
+    code: i32,
+    message: String,
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(error: anyhow::Error) -> Self {
+        Self {
+            code: SERVER_ERROR,
+            message: format!("{error:#}"),
+        }
+    }
+}
+
+async fn handle_request(
+    request: &str,
+    notifications_tx: &mpsc::UnboundedSender<String>,
+    cx: &mut AsyncApp,
+) -> String {
+    let request = match serde_json::from_str::<Request>(request) {
+        Ok(request) => request,
+        Err(error) => {
+            return response(
+                Value::Null,
+                Err(RpcError {
+                    code: PARSE_ERROR,
+                    message: error.to_string(),
+                }),
+            )
+        }
+    };
+
+    let result = async {
+        let params = request.params;
+        match request.method.as_str() {
+            "openFile" => Ok(open_file(parse_params(params)?, cx).await?),
+            "listBuffers" => Ok(list_buffers(cx)?),
+            "getSelections" => Ok(get_selections(parse_params(params)?, cx)?),
+            "setSelections" => Ok(set_selections(parse_params(params)?, cx)?),
+            "applyEdit" => Ok(apply_edit(parse_params(params)?, cx).await?),
+            "replaceAll" => Ok(replace_all(parse_params(params)?, cx).await?),
+            "subscribe" => {
+                let notifications_tx = notifications_tx.clone();
+                cx.update_global(|socket: &mut ControlSocket, _| {
+                    socket.subscribers.push(notifications_tx)
+                })?;
+                Ok(Value::Null)
+            }
+            method => Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("unknown method {method:?}"),
+            }),
+        }
+    }
+    .await;
+    response(request.id, result)
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> String {
+    let response = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    };
+    response.to_string()
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|error| RpcError {
+        code: INVALID_PARAMS,
+        message: error.to_string(),
+    })
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+struct Position {
+    row: u32,
+    column: u32,
+}
+
+impl From<Point> for Position {
+    fn from(point: Point) -> Self {
+        Self {
+            row: point.row,
+            column: point.column,
+        }
+    }
+}
+
+impl From<Position> for Point {
+    fn from(position: Position) -> Self {
+        Point::new(position.row, position.column)
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+struct PositionRange {
+    start: Position,
+    end: Position,
+}
+
+impl From<PositionRange> for Range<Point> {
+    fn from(range: PositionRange) -> Self {
+        range.start.into()..range.end.into()
+    }
+}
+
+#[derive(Deserialize)]
+struct OpenFileParams {
+    path: PathBuf,
+    #[serde(flatten)]
+    position: Option<Position>,
+}
+
+#[derive(Deserialize)]
+struct PathParams {
+    path: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct SetSelectionsParams {
+    path: PathBuf,
+    selections: Vec<PositionRange>,
+}
+
+#[derive(Deserialize)]
+struct TextEdit {
+    range: PositionRange,
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct ApplyEditParams {
+    path: PathBuf,
+    edits: Vec<TextEdit>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReplaceAllParams {
+    query: String,
+    replacement: String,
+    #[serde(default)]
+    regex: bool,
+    #[serde(default)]
+    case_sensitive: bool,
+    #[serde(default)]
+    whole_word: bool,
+    /// Globs of the files to search, all files being searched if empty.
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    /// Returns the diffs of the replacements without editing the buffers.
+    #[serde(default)]
+    dry_run: bool,
+}
+
+async fn open_file(params: OpenFileParams, cx: &mut AsyncApp) -> Result<Value> {
+    let window = cx.update(|cx| {
+        cx.active_window()
+            .and_then(|window| window.downcast::<Workspace>())
+            .or_else(|| workspace::local_workspace_windows(cx).into_iter().next())
+            .context("no workspace window is open")
+    })??;
+    let item = window
+        .update(cx, |workspace, window, cx| {
+            workspace.open_abs_path(params.path, true, window, cx)
+        })?
+        .await?;
+    if let Some((position, editor)) = params.position.zip(item.downcast::<Editor>()) {
+        window.update(cx, |_, window, cx| {
+            editor.update(cx, |editor, cx| {
+                editor.go_to_singleton_buffer_point(position.into(), window, cx)
+            })
+        })?;
+    }
+    Ok(Value::Null)
+}
+
+fn list_buffers(cx: &mut AsyncApp) -> Result<Value> {
+    cx.update(|cx| {
+        let mut buffers = Vec::new();
+        for window in workspace::local_workspace_windows(cx) {
+            let Ok(workspace) = window.read(cx) else {
+                continue;
+            };
+            for buffer in workspace.project().read(cx).opened_buffers(cx) {
+                let buffer = buffer.read(cx);
+                if let Some(path) = buffer_abs_path(buffer, cx) {
+                    buffers.push(json!({ "path": path, "dirty": buffer.is_dirty() }));
+                }
+            }
+        }
+        Value::Array(buffers)
+    })
+}
+
+fn get_selections(params: PathParams, cx: &mut AsyncApp) -> Result<Value> {
+    let (_, editor) = editor_for_path(&params.path, cx)?;
+    let selections = editor.update(cx, |editor, cx| {
+        editor
+            .selections
+            .all::<Point>(cx)
+            .into_iter()
+            .map(|selection| PositionRange {
+                start: selection.start.into(),
+                end: selection.end.into(),
+            })
+            .collect::<Vec<_>>()
+    })?;
+    Ok(serde_json::to_value(selections)?)
+}
+
+fn set_selections(params: SetSelectionsParams, cx: &mut AsyncApp) -> Result<Value> {
+    let (window, editor) = editor_for_path(&params.path, cx)?;
+    window.update(cx, |_, window, cx| {
+        editor.update(cx, |editor, cx| {
+            editor.change_selections(Some(Autoscroll::fit()), window, cx, |selections| {
+                selections.select_ranges(params.selections.into_iter().map(Range::<Point>::from))
+            })
+        })
+    })?;
+    Ok(Value::Null)
+}
+
+async fn apply_edit(params: ApplyEditParams, cx: &mut AsyncApp) -> Result<Value> {
+    let open_buffer = cx.update(|cx| {
+        let window = workspace::local_workspace_windows(cx)
+            .into_iter()
+            .next()
+            .context("no workspace window is open")?;
+        let project = window.read(cx)?.project().clone();
+        anyhow::Ok(project.update(cx, |project, cx| {
+            project.open_local_buffer(&params.path, cx)
+        }))
+    })??;
+    let buffer = open_buffer.await?;
+    buffer.update(cx, |buffer, cx| {
+        let edits = params
+            .edits
+            .into_iter()
+            .map(|edit| {
+                let range = Range::<Point>::from(edit.range);
+                let range = buffer.clip_point(range.start, language::Bias::Left)
+                    ..buffer.clip_point(range.end, language::Bias::Right);
+                (range, edit.text)
+            })
+            .collect::<Vec<_>>();
+        buffer.edit(edits, None, cx);
+    })?;
+    Ok(Value::Null)
+}
+
+async fn replace_all(params: ReplaceAllParams, cx: &mut AsyncApp) -> Result<Value> {
+    let files_to_include = PathMatcher::new(&params.include)?;
+    let files_to_exclude = PathMatcher::new(&params.exclude)?;
+    let query = if params.regex {
+        SearchQuery::regex(
+            params.query,
+            params.whole_word,
+            params.case_sensitive,
+            false,
+            files_to_include,
+            files_to_exclude,
+            None,
+        )?
+    } else {
+        SearchQuery::text(
+            params.query,
+            params.whole_word,
+            params.case_sensitive,
+            false,
+            files_to_include,
+            files_to_exclude,
+            None,
+        )?
+    }
+    .with_replacement(params.replacement);
+
+    let replace = cx.update(|cx| {
+        let window = workspace::local_workspace_windows(cx)
+            .into_iter()
+            .next()
+            .context("no workspace window is open")?;
+        let project = window.read(cx)?.project().clone();
+        anyhow::Ok(project.update(cx, |project, cx| {
+            project.replace_all(query, params.dry_run, cx)
+        }))
+    })??;
+    let replacements = replace
+        .await?
+        .into_iter()
+        .map(|replacement| {
+            json!({
+                "path": replacement.path,
+                "replacements": replacement.replacement_count,
+                "diff": replacement.diff,
+            })
+        })
+        .collect();
+    Ok(Value::Array(replacements))
+}
+
+fn editor_for_path(
+    path: &Path,
+    cx: &mut AsyncApp,
+) -> Result<(WindowHandle<Workspace>, Entity<Editor>)> {
+    cx.update(|cx| {
+        workspace::local_workspace_windows(cx)
+            .into_iter()
+            .find_map(|window| {
+                let workspace = window.read(cx).ok()?;
+                let editor = workspace.items_of_type::<Editor>(cx).find(|editor| {
+                    let buffer = editor.read(cx).buffer().read(cx).as_singleton();
+                    buffer.map_or(false, |buffer| {
+                        buffer_abs_path(buffer.read(cx), cx).as_deref() == Some(path)
+                    })
+                })?;
+                Some((window, editor))
+            })
+    })?
+    .with_context(|| format!("no editor is open for {path:?}"))
+}
+
+fn buffer_abs_path(buffer: &Buffer, cx: &App) -> Option<PathBuf> {
+    Some(buffer.file()?.as_local()?.abs_path(cx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::TestAppContext;
+
+    async fn serve_requests(requests: &[Value], cx: &mut TestAppContext) -> Vec<Value> {
+        let mut input = String::new();
+        for request in requests {
+            input.push_str(&request.to_string());
+            input.push('\n');
+        }
+        input.push_str("not json\n");
+
+        let mut output = Vec::new();
+        serve(
+            futures::io::Cursor::new(input.into_bytes()),
+            &mut output,
+            cx.to_async(),
+        )
+        .await
+        .unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[gpui::test]
+    async fn test_serve(cx: &mut TestAppContext) {
+        cx.update(|cx| cx.set_global(ControlSocket::default()));
+
+        let responses = serve_requests(
+            &[
+                json!({ "jsonrpc": "2.0", "id": 1, "method": "listBuffers" }),
+                json!({ "jsonrpc": "2.0", "id": 2, "method": "unknown" }),
+                json!({ "jsonrpc": "2.0", "id": 3, "method": "getSelections", "params": {} }),
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 4,
+                    "method": "getSelections",
+                    "params": { "path": "/a.rs" },
+                }),
+                json!({ "jsonrpc": "2.0", "id": 5, "method": "subscribe" }),
+            ],
+            cx,
+        )
+        .await;
+
+        assert_eq!(responses.len(), 6);
+        assert_eq!(
+            responses[0],
+            json!({ "jsonrpc": "2.0", "id": 1, "result": [] })
+        );
+        assert_eq!(responses[1]["id"], 2);
+        assert_eq!(responses[1]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(responses[2]["id"], 3);
+        assert_eq!(responses[2]["error"]["code"], INVALID_PARAMS);
+        assert_eq!(responses[3]["id"], 4);
+        assert_eq!(responses[3]["error"]["code"], SERVER_ERROR);
+        assert_eq!(
+            responses[4],
+            json!({ "jsonrpc": "2.0", "id": 5, "result": null })
+        );
+        assert_eq!(responses[5]["id"], Value::Null);
+        assert_eq!(responses[5]["error"]["code"], PARSE_ERROR);
+
+        // The connection's subscription is dropped along with it.
+        cx.update(|cx| {
+            cx.update_global(|socket: &mut ControlSocket, _| {
+                assert_eq!(socket.subscribers.len(), 1);
+                socket.notify("didSave", json!({ "path": "/a.rs" }));
+                assert!(socket.subscribers.is_empty());
+            })
+        });
+    }
+
+    #[test]
+    fn test_control_socket_path() {
+        assert!(control_socket_path(&OsString::from("../zed.sock")).is_err());
+        assert!(control_socket_path(&OsString::from("/tmp/zed.sock")).is_err());
+        assert!(control_socket_path(&OsString::from("")).is_err());
+    }
+}
@@ -0,0 +1,349 @@
+//! A picker listing the contents of dirty buffers that were closed without saving,
+//! which opens the selected ones in a new buffer.
+
+use chrono::{DateTime, Local, Utc};
+use editor::{Editor, MultiBuffer};
+use fuzzy::{match_strings, StringMatch, StringMatchCandidate};
+use gpui::{
+    App, Context, DismissEvent, Entity, EventEmitter, FocusHandle, Focusable, Task, WeakEntity,
+    Window,
+};
+use picker::{Picker, PickerDelegate};
+use project::DiscardedChange;
+use std::{path::PathBuf, sync::Arc};
+use ui::{prelude::*, HighlightedLabel, ListItem, ListItemSpacing};
+use util::ResultExt;
+use workspace::{ModalView, Workspace};
+
+pub fn toggle(
+    workspace: &mut Workspace,
+    _: &zed_actions::OpenDiscardedChanges,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    let journal = workspace.project().read(cx).discarded_changes_journal();
+    let weak_workspace = cx.entity().downgrade();
+    cx.spawn_in(window, |workspace, mut cx| async move {
+        journal
+            .remove_expired(Utc::now().timestamp_millis())
+            .await
+            .log_err();
+        let entries = journal.entries().await?;
+        workspace.update_in(&mut cx, |workspace, window, cx| {
+            workspace.toggle_modal(window, cx, |window, cx| {
+                DiscardedChanges::new(entries, weak_workspace, window, cx)
+            });
+        })
+    })
+    .detach_and_log_err(cx);
+}
+
+pub struct DiscardedChanges {
+    picker: Entity<Picker<DiscardedChangesDelegate>>,
+}
+
+impl DiscardedChanges {
+    fn new(
+        entries: Vec<(PathBuf, DiscardedChange)>,
+        workspace: WeakEntity<Workspace>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let candidates = entries
+            .iter()
+            .enumerate()
+            .map(|(ix, (_, change))| StringMatchCandidate::new(ix, &change_label(change)))
+            .collect();
+        let delegate = DiscardedChangesDelegate {
+            discarded_changes: cx.entity().downgrade(),
+            workspace,
+            entries,
+            candidates,
+            matches: Vec::new(),
+            selected_index: 0,
+        };
+        let picker = cx.new(|cx| Picker::uniform_list(delegate, window, cx));
+        Self { picker }
+    }
+}
+
+impl Render for DiscardedChanges {
+    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+        v_flex().w(rems(34.)).child(self.picker.clone())
+    }
+}
+
+impl Focusable for DiscardedChanges {
+    fn focus_handle(&self, cx: &App) -> FocusHandle {
+        self.picker.focus_handle(cx)
+    }
+}
+
+impl EventEmitter<DismissEvent> for DiscardedChanges {}
+impl ModalView for DiscardedChanges {}
+
+pub struct DiscardedChangesDelegate {
+    discarded_changes: WeakEntity<DiscardedChanges>,
+    workspace: WeakEntity<Workspace>,
+    entries: Vec<(PathBuf, DiscardedChange)>,
+    candidates: Vec<StringMatchCandidate>,
+    matches: Vec<StringMatch>,
+    selected_index: usize,
+}
+
+impl PickerDelegate for DiscardedChangesDelegate {
+    type ListItem = ListItem;
+
+    fn placeholder_text(&self, _window: &mut Window, _cx: &mut App) -> Arc<str> {
+        "Recover recently discarded changes…".into()
+    }
+
+    fn no_matches_text(&self, _window: &mut Window, _cx: &mut App) -> SharedString {
+        "No discarded changes".into()
+    }
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn set_selected_index(
+        &mut self,
+        ix: usize,
+        _window: &mut Window,
+        _: &mut Context<Picker<Self>>,
+    ) {
+        self.selected_index = ix;
+    }
+
+    fn update_matches(
+        &mut self,
+        query: String,
+        window: &mut Window,
+        cx: &mut Context<Picker<Self>>,
+    ) -> Task<()> {
+        let background = cx.background_executor().clone();
+        let candidates = self.candidates.clone();
+        cx.spawn_in(window, |this, mut cx| async move {
+            let matches = if query.is_empty() {
+                candidates
+                    .into_iter()
+                    .map(|candidate| StringMatch {
+                        candidate_id: candidate.id,
+                        string: candidate.string,
+                        positions: Vec::new(),
+                        score: 0.0,
+                    })
+                    .collect()
+            } else {
+                match_strings(
+                    &candidates,
+                    &query,
+                    false,
+                    100,
+                    &Default::default(),
+                    background,
+                )
+                .await
+            };
+
+            this.update(&mut cx, |this, cx| {
+                let delegate = &mut this.delegate;
+                delegate.matches = matches;
+                delegate.selected_index = delegate
+                    .selected_index
+                    .min(delegate.matches.len().saturating_sub(1));
+                cx.notify();
+            })
+            .log_err();
+        })
+    }
+
+    fn confirm(&mut self, _: bool, window: &mut Window, cx: &mut Context<Picker<Self>>) {
+        let Some(mat) = self.matches.get(self.selected_index) else {
+            return;
+        };
+        let (_, change) = self.entries[mat.candidate_id].clone();
+        self.workspace
+            .update(cx, |workspace, cx| {
+                open_discarded_change(workspace, change, window, cx)
+            })
+            .log_err();
+        self.dismissed(window, cx);
+    }
+
+    fn dismissed(&mut self, _: &mut Window, cx: &mut Context<Picker<Self>>) {
+        self.discarded_changes
+            .update(cx, |_, cx| cx.emit(DismissEvent))
+            .log_err();
+    }
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        _: &mut Window,
+        _: &mut Context<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let mat = &self.matches[ix];
+        let (_, change) = &self.entries[mat.candidate_id];
+        Some(
+            ListItem::new(ix)
+                .inset(true)
+                .spacing(ListItemSpacing::Sparse)
+                .toggle_state(selected)
+                .child(HighlightedLabel::new(
+                    mat.string.clone(),
+                    mat.positions.clone(),
+                ))
+                .end_slot(
+                    Label::new(discarded_at_label(change))
+                        .size(LabelSize::Small)
+                        .color(Color::Muted),
+                ),
+        )
+    }
+}
+
+fn change_label(change: &DiscardedChange) -> String {
+    change.path.as_ref().map_or_else(
+        || "untitled".to_string(),
+        |path| path.to_string_lossy().into_owned(),
+    )
+}
+
+fn discarded_at_label(change: &DiscardedChange) -> String {
+    DateTime::from_timestamp_millis(change.discarded_at)
+        .map(|discarded_at| {
+            discarded_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_default()
+}
+
+/// Opens the discarded contents in a new buffer, using the language of the
+/// original file, so that they can be compared, copied or saved again.
+fn open_discarded_change(
+    workspace: &mut Workspace,
+    change: DiscardedChange,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    let languages = workspace.app_state().languages.clone();
+    let title = format!("Discarded changes to {}", change_label(&change));
+    cx.spawn_in(window, |workspace, mut cx| async move {
+        let language = match &change.path {
+            Some(path) => languages.language_for_file_path(path).await.log_err(),
+            None => None,
+        };
+        workspace.update_in(&mut cx, |workspace, window, cx| {
+            let project = workspace.project().clone();
+            let buffer = project.update(cx, |project, cx| {
+                project.create_local_buffer(&change.text, language, cx)
+            });
+            let buffer = cx.new(|cx| MultiBuffer::singleton(buffer, cx).with_title(title));
+            workspace.add_item_to_active_pane(
+                Box::new(
+                    cx.new(|cx| Editor::for_multibuffer(buffer, Some(project), true, window, cx)),
+                ),
+                None,
+                true,
+                window,
+                cx,
+            );
+        })
+    })
+    .detach_and_log_err(cx);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::{TestAppContext, VisualTestContext};
+    use project::Project;
+    use workspace::AppState;
+
+    #[gpui::test]
+    async fn test_recovering_discarded_changes(cx: &mut TestAppContext) {
+        let app_state = init_test(cx);
+        let project = Project::test(app_state.fs.clone(), [], cx).await;
+        let (workspace, cx) =
+            cx.add_window_view(|window, cx| Workspace::test_new(project.clone(), window, cx));
+
+        let now = Utc::now().timestamp_millis();
+        let journal = project.read_with(cx, |project, _| project.discarded_changes_journal());
+        for (discriminant, (path, text, discarded_at)) in [
+            (Some("/code/main.rs"), "fn main() {}", now - 1),
+            (None, "notes", now),
+            (Some("/code/expired.rs"), "fn expired() {}", 0),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let change = DiscardedChange {
+                path: path.map(PathBuf::from),
+                text: text.to_string(),
+                discarded_at,
+            };
+            journal.record(&change, discriminant as u64).await.unwrap();
+        }
+
+        workspace.update_in(cx, |workspace, window, cx| {
+            toggle(workspace, &zed_actions::OpenDiscardedChanges, window, cx)
+        });
+        cx.run_until_parked();
+        let picker = workspace.update(cx, |workspace, cx| {
+            workspace
+                .active_modal::<DiscardedChanges>(cx)
+                .unwrap()
+                .read(cx)
+                .picker
+                .clone()
+        });
+        let match_labels = |cx: &mut VisualTestContext| {
+            picker.update(cx, |picker, _| {
+                picker
+                    .delegate
+                    .matches
+                    .iter()
+                    .map(|mat| mat.string.clone())
+                    .collect::<Vec<_>>()
+            })
+        };
+        // Expired changes are removed, and the most recently discarded are listed first.
+        assert_eq!(match_labels(cx), ["untitled", "/code/main.rs"]);
+
+        picker.update_in(cx, |picker, window, cx| {
+            picker.update_matches("main".to_string(), window, cx)
+        });
+        cx.run_until_parked();
+        assert_eq!(match_labels(cx), ["/code/main.rs"]);
+
+        picker.update_in(cx, |picker, window, cx| {
+            picker.delegate.confirm(false, window, cx)
+        });
+        cx.run_until_parked();
+        let editor = workspace.update(cx, |workspace, cx| {
+            assert!(workspace.active_modal::<DiscardedChanges>(cx).is_none());
+            workspace.active_item_as::<Editor>(cx).unwrap()
+        });
+        editor.update(cx, |editor, cx| assert_eq!(editor.text(cx), "fn main() {}"));
+    }
+
+    fn init_test(cx: &mut TestAppContext) -> Arc<AppState> {
+        cx.update(|cx| {
+            let state = AppState::test(cx);
+            theme::init(theme::LoadThemes::JustBase, cx);
+            language::init(cx);
+            editor::init(cx);
+            workspace::init_settings(cx);
+            Project::init_settings(cx);
+            state
+        })
+    }
+}
@@ -24,7 +24,7 @@ use std::time::Duration;
 use util::paths::PathWithPosition;
 use util::ResultExt;
 use welcome::{show_welcome_view, FIRST_OPEN};
-use workspace::item::ItemHandle;
+use workspace::item::{ItemHandle, RevealPosition};
 use workspace::{AppState, OpenOptions, SerializedWorkspaceLocation, Workspace};
 
 #[derive(Default, Debug)]
@@ -61,9 +61,17 @@ impl OpenRequest {
         Ok(this)
     }
 
+    /// Parses the path of a `file://` or `zed://file` URL, which may end with
+    /// a `:<line>:<column>` position.
     fn parse_file_path(&mut self, file: &str) {
         if let Some(decoded) = urlencoding::decode(file).log_err() {
-            self.open_paths.push(decoded.into_owned())
+            // Windows paths are written like `/C:/Users/...` in URLs.
+            let path = if cfg!(windows) && decoded.get(2..3) == Some(":") {
+                decoded.strip_prefix('/').unwrap_or(&decoded)
+            } else {
+                decoded.as_ref()
+            };
+            self.open_paths.push(path.to_string())
         }
     }
 
@@ -231,15 +239,11 @@ pub async fn open_paths_with_positions(
         let Some(point) = caret_positions.remove(path) else {
             continue;
         };
-        if let Some(active_editor) = item.downcast::<Editor>() {
-            workspace
-                .update(cx, |_, window, cx| {
-                    active_editor.update(cx, |editor, cx| {
-                        editor.go_to_singleton_buffer_point(point, window, cx);
-                    });
-                })
-                .log_err();
-        }
+        workspace
+            .update(cx, |_, window, cx| {
+                item.reveal_range(point..point, RevealPosition::Center, true, window, cx);
+            })
+            .log_err();
     }
 
     Ok((workspace, items))
@@ -538,7 +542,29 @@ mod tests {
     use util::path;
     use workspace::{AppState, Workspace};
 
-    use crate::zed::{open_listener::open_local_workspace, tests::init_test};
+    use crate::zed::{
+        open_listener::{open_local_workspace, OpenRequest},
+        tests::init_test,
+    };
+
+    #[gpui::test]
+    fn test_parse_file_urls(cx: &mut TestAppContext) {
+        let request = cx
+            .update(|cx| {
+                OpenRequest::parse(
+                    vec![
+                        "zed://file/home/user/my%20project/main.rs:12:5".into(),
+                        "file:///home/user/notes.md".into(),
+                    ],
+                    cx,
+                )
+            })
+            .unwrap();
+        assert_eq!(
+            request.open_paths,
+            ["/home/user/my project/main.rs:12:5", "/home/user/notes.md"]
+        );
+    }
 
     #[gpui::test]
     async fn test_open_workspace_with_directory(cx: &mut TestAppContext) {
@@ -0,0 +1,163 @@
+//! The sequence run when quitting the app.
+//!
+//! The unsaved changes of every window are settled by a single prompt, shown in the active
+//! window, rather than one prompt per window. The app then waits, up to a timeout, for the
+//! session and the local history of discarded buffers to be written, and for the saves still
+//! writing their files, like autosaves, to finish, before it exits. The other tasks of the
+//! windows' entities, like wrapping, parsing and searching, are cancelled with the windows.
+
+use futures::{future, select_biased, FutureExt as _};
+use gpui::{App, PromptLevel};
+use settings::Settings;
+use std::{
+    sync::atomic::{self, AtomicBool},
+    time::Duration,
+};
+use util::ResultExt;
+use workspace::{Pane, SaveIntent, Workspace, WorkspaceSettings};
+use zed_actions::Quit;
+
+/// How long quitting waits for the session, the local history and the saves in progress to be
+/// written.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
+
+static WAITING_QUIT_CONFIRMATION: AtomicBool = AtomicBool::new(false);
+
+pub fn quit(_: &Quit, cx: &mut App) {
+    if WAITING_QUIT_CONFIRMATION.load(atomic::Ordering::Acquire) {
+        return;
+    }
+
+    let should_confirm = WorkspaceSettings::get_global(cx).confirm_quit;
+    cx.spawn(|mut cx| async move {
+        let mut workspace_windows = cx.update(|cx| {
+            cx.windows()
+                .into_iter()
+                .filter_map(|window| window.downcast::<Workspace>())
+                .collect::<Vec<_>>()
+        })?;
+
+        // Prompt in the active window, rather than in whichever window comes first.
+        cx.update(|cx| {
+            workspace_windows.sort_by_key(|window| window.is_active(cx) == Some(false));
+        })
+        .log_err();
+
+        if should_confirm {
+            if let Some(workspace) = workspace_windows.first() {
+                let answer = workspace
+                    .update(&mut cx, |_, window, cx| {
+                        window.prompt(
+                            PromptLevel::Info,
+                            "Are you sure you want to quit?",
+                            None,
+                            &["Quit", "Cancel"],
+                            cx,
+                        )
+                    })
+                    .log_err();
+
+                if let Some(answer) = answer {
+                    WAITING_QUIT_CONFIRMATION.store(true, atomic::Ordering::Release);
+                    let answer = answer.await.ok();
+                    WAITING_QUIT_CONFIRMATION.store(false, atomic::Ordering::Release);
+                    if answer != Some(0) {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        // Collect the changes that would be lost in every window.
+        let mut unsaved_items = Vec::new();
+        for window in &workspace_windows {
+            if let Some(task) = window
+                .update(&mut cx, |workspace, window, cx| {
+                    workspace.unsaved_items_on_close(window, cx)
+                })
+                .log_err()
+            {
+                let items = task.await?;
+                if !items.is_empty() {
+                    unsaved_items.push((*window, items));
+                }
+            }
+        }
+
+        if let Some((first_window, _)) = unsaved_items.first() {
+            let answer = first_window.update(&mut cx, |_, window, cx| {
+                let unsaved_count = unsaved_items.iter().map(|(_, items)| items.len()).sum();
+                let (prompt, detail) = Pane::file_names_for_prompt(
+                    &mut unsaved_items
+                        .iter()
+                        .flat_map(|(_, items)| items.iter().map(|(_, item)| item)),
+                    unsaved_count,
+                    cx,
+                );
+                window.prompt(
+                    PromptLevel::Warning,
+                    &prompt,
+                    Some(&detail),
+                    &["Save all", "Discard all", "Cancel"],
+                    cx,
+                )
+            })?;
+            WAITING_QUIT_CONFIRMATION.store(true, atomic::Ordering::Release);
+            let answer = answer.await.ok();
+            WAITING_QUIT_CONFIRMATION.store(false, atomic::Ordering::Release);
+
+            match answer {
+                Some(0) => {
+                    // If the user cancels saving any item, e.g. when picking its path, then
+                    // keep the app open.
+                    for (window, items) in unsaved_items {
+                        let saved = window
+                            .update(&mut cx, |workspace, window, cx| {
+                                workspace.save_items(items, SaveIntent::SaveAll, window, cx)
+                            })?
+                            .await?;
+                        if !saved {
+                            return Ok(());
+                        }
+                    }
+                }
+                Some(1) => {
+                    for (window, items) in unsaved_items {
+                        window.update(&mut cx, |workspace, window, cx| {
+                            let project = workspace.project().clone();
+                            for (_, item) in items {
+                                item.discarded(project.clone(), window, cx);
+                            }
+                        })?;
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+
+        let flushes = workspace_windows
+            .iter()
+            .filter_map(|window| {
+                window
+                    .update(&mut cx, |workspace, window, cx| {
+                        let (local_history, saves) =
+                            workspace.project().update(cx, |project, cx| {
+                                (project.flush_discarded_changes(cx), project.wait_for_saves(cx))
+                            });
+                        let session = workspace.flush_serialization(window, cx);
+                        future::join3(local_history, saves, session)
+                    })
+                    .log_err()
+            })
+            .collect::<Vec<_>>();
+        let timeout = cx.background_executor().timer(FLUSH_TIMEOUT);
+        select_biased! {
+            _ = future::join_all(flushes).fuse() => {}
+            _ = timeout.fuse() => log::error!("timed out writing the session and files before quitting"),
+        }
+
+        cx.update(|cx| cx.quit())?;
+        anyhow::Ok(())
+    })
+    .detach_and_log_err(cx);
+}
@@ -0,0 +1,659 @@
+//! Scratchpads are untitled buffers associated with a language, whose contents are kept
+//! across restarts so that quick experiments aren't lost. The scratchpads open when the app
+//! quits are reopened on startup, and a picker lists the existing scratchpads, followed by the
+//! languages new ones can be created in. Empty scratchpads aren't kept.
+
+use anyhow::{anyhow, Context as _, Result};
+use chrono::{DateTime, Local};
+use collections::HashMap;
+use editor::{Editor, MultiBuffer};
+use fs::{Fs, RemoveOptions};
+use futures::StreamExt as _;
+use fuzzy::{match_strings, StringMatch, StringMatchCandidate};
+use gpui::{
+    App, AppContext as _, Context, DismissEvent, Entity, EventEmitter, FocusHandle, Focusable,
+    Global, Subscription, Task, WeakEntity, Window,
+};
+use language::{Buffer, BufferEvent};
+use picker::{Picker, PickerDelegate};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, sync::Arc, time::Duration};
+use ui::{prelude::*, HighlightedLabel, ListItem, ListItemSpacing};
+use util::ResultExt;
+use workspace::{ModalView, Workspace};
+use zed_actions::{NewScratchpad, OpenScratchpads};
+
+/// How long to wait after an edit before persisting a scratchpad.
+const SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// The persisted state of a scratchpad.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Scratchpad {
+    /// When the scratchpad was created, in milliseconds since the Unix epoch.
+    pub id: i64,
+    pub language: String,
+    pub text: String,
+    /// When the scratchpad was last edited, in milliseconds since the Unix epoch.
+    pub updated_at: i64,
+    /// Whether the scratchpad is open, and is to be reopened on startup if it still was when
+    /// the app quit.
+    #[serde(default)]
+    pub open: bool,
+}
+
+/// Stores scratchpads as one JSON file each.
+#[derive(Clone)]
+pub struct ScratchpadStore {
+    fs: Arc<dyn Fs>,
+    dir: PathBuf,
+}
+
+impl ScratchpadStore {
+    pub fn new(fs: Arc<dyn Fs>, dir: PathBuf) -> Self {
+        Self { fs, dir }
+    }
+
+    pub async fn save(&self, scratchpad: &Scratchpad) -> Result<()> {
+        self.fs.create_dir(&self.dir).await?;
+        self.fs
+            .atomic_write(
+                self.path_for_id(scratchpad.id),
+                serde_json::to_string(scratchpad)?,
+            )
+            .await
+    }
+
+    /// Returns the stored scratchpads, most recently edited first. Empty scratchpads aren't
+    /// returned, and are removed.
+    pub async fn scratchpads(&self) -> Result<Vec<Scratchpad>> {
+        if !self.fs.is_dir(&self.dir).await {
+            return Ok(Vec::new());
+        }
+        let mut scratchpads = Vec::new();
+        let mut read_dir = self.fs.read_dir(&self.dir).await?;
+        while let Some(path) = read_dir.next().await {
+            let path = path?;
+            if path
+                .extension()
+                .map_or(true, |extension| extension != "json")
+            {
+                continue;
+            }
+            let scratchpad = self
+                .fs
+                .load(&path)
+                .await
+                .and_then(|content| {
+                    serde_json::from_str::<Scratchpad>(&content)
+                        .with_context(|| format!("parsing scratchpad {path:?}"))
+                })
+                .log_err();
+            match scratchpad {
+                Some(scratchpad) if scratchpad.text.is_empty() => {
+                    self.remove(scratchpad.id).await.log_err();
+                }
+                Some(scratchpad) => scratchpads.push(scratchpad),
+                None => {}
+            }
+        }
+        scratchpads.sort_by_key(|scratchpad| std::cmp::Reverse(scratchpad.updated_at));
+        Ok(scratchpads)
+    }
+
+    /// Saves the scratchpad, or removes it if it's empty.
+    pub async fn save_or_remove(&self, scratchpad: &Scratchpad) -> Result<()> {
+        if scratchpad.text.is_empty() {
+            self.remove(scratchpad.id).await
+        } else {
+            self.save(scratchpad).await
+        }
+    }
+
+    pub async fn remove(&self, id: i64) -> Result<()> {
+        self.fs
+            .remove_file(
+                &self.path_for_id(id),
+                RemoveOptions {
+                    recursive: false,
+                    ignore_if_not_exists: true,
+                },
+            )
+            .await
+    }
+
+    fn path_for_id(&self, id: i64) -> PathBuf {
+        self.dir.join(format!("{id}.json"))
+    }
+}
+
+/// The buffers of the scratchpads opened in this session, which are persisted as they're edited.
+#[derive(Default)]
+struct OpenScratchpads {
+    buffers: HashMap<i64, OpenScratchpad>,
+    /// Whether the scratchpads open in the previous session were reopened.
+    restored: bool,
+    /// Whether the app is quitting, in which case the released buffers stay open.
+    quitting: bool,
+}
+
+impl Global for OpenScratchpads {}
+
+struct OpenScratchpad {
+    buffer: WeakEntity<Buffer>,
+    save_task: Option<Task<()>>,
+    _subscriptions: [Subscription; 2],
+}
+
+pub fn init(cx: &mut App) {
+    cx.on_app_quit(|cx| {
+        cx.default_global::<OpenScratchpads>().quitting = true;
+        async {}
+    })
+    .detach();
+}
+
+/// Reopens the scratchpads that were open when the app last quit, in the first local workspace
+/// of the session.
+pub fn restore_scratchpads(
+    workspace: &mut Workspace,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    if !workspace.project().read(cx).is_local()
+        || std::mem::replace(&mut cx.default_global::<OpenScratchpads>().restored, true)
+    {
+        return;
+    }
+
+    let store = store(workspace);
+    cx.spawn_in(window, |workspace, mut cx| async move {
+        let scratchpads = store.scratchpads().await?;
+        workspace.update_in(&mut cx, |workspace, window, cx| {
+            for scratchpad in scratchpads.into_iter().rev() {
+                if scratchpad.open {
+                    open_scratchpad(workspace, scratchpad, window, cx);
+                }
+            }
+        })
+    })
+    .detach_and_log_err(cx);
+}
+
+fn store(workspace: &Workspace) -> ScratchpadStore {
+    ScratchpadStore::new(
+        workspace.app_state().fs.clone(),
+        paths::scratchpads_dir().clone(),
+    )
+}
+
+pub fn new_scratchpad(
+    workspace: &mut Workspace,
+    action: &NewScratchpad,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    match &action.language {
+        Some(language) => create_scratchpad(workspace, language.clone(), window, cx),
+        None => toggle_picker(workspace, false, window, cx),
+    }
+}
+
+pub fn open_scratchpads(
+    workspace: &mut Workspace,
+    _: &OpenScratchpads,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    toggle_picker(workspace, true, window, cx);
+}
+
+fn toggle_picker(
+    workspace: &mut Workspace,
+    include_existing: bool,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    let store = store(workspace);
+    let language_names = workspace.app_state().languages.language_names();
+    let weak_workspace = cx.entity().downgrade();
+    cx.spawn_in(window, |workspace, mut cx| async move {
+        let scratchpads = if include_existing {
+            store.scratchpads().await?
+        } else {
+            Vec::new()
+        };
+        workspace.update_in(&mut cx, |workspace, window, cx| {
+            workspace.toggle_modal(window, cx, |window, cx| {
+                ScratchpadPicker::new(scratchpads, language_names, weak_workspace, window, cx)
+            });
+        })
+    })
+    .detach_and_log_err(cx);
+}
+
+fn create_scratchpad(
+    workspace: &mut Workspace,
+    language: String,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    let now = chrono::Utc::now().timestamp_millis();
+    let scratchpad = Scratchpad {
+        id: now,
+        language,
+        text: String::new(),
+        updated_at: now,
+        open: true,
+    };
+    open_scratchpad(workspace, scratchpad, window, cx);
+}
+
+/// Opens the scratchpad in the active pane, reusing its buffer if it's already open.
+fn open_scratchpad(
+    workspace: &mut Workspace,
+    scratchpad: Scratchpad,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    let project = workspace.project().clone();
+    if !project.read(cx).is_local() {
+        workspace.show_error(
+            &anyhow!("Scratchpads are only available in local projects"),
+            cx,
+        );
+        return;
+    }
+
+    let store = store(workspace);
+    let languages = workspace.app_state().languages.clone();
+    cx.spawn_in(window, |workspace, mut cx| async move {
+        let language = languages
+            .language_for_name(&scratchpad.language)
+            .await
+            .log_err();
+        workspace.update_in(&mut cx, |workspace, window, cx| {
+            let open_buffer = cx
+                .try_global::<OpenScratchpads>()
+                .and_then(|open| open.buffers.get(&scratchpad.id))
+                .and_then(|open| open.buffer.upgrade());
+            let buffer = match open_buffer {
+                Some(buffer) => buffer,
+                None => {
+                    let buffer = project.update(cx, |project, cx| {
+                        project.create_local_buffer(&scratchpad.text, language, cx)
+                    });
+                    persist_edits(buffer.clone(), scratchpad.clone(), store, cx);
+                    buffer
+                }
+            };
+            let title = format!("Scratchpad ({})", scratchpad.language);
+            let buffer = cx.new(|cx| MultiBuffer::singleton(buffer, cx).with_title(title));
+            workspace.add_item_to_active_pane(
+                Box::new(
+                    cx.new(|cx| Editor::for_multibuffer(buffer, Some(project), true, window, cx)),
+                ),
+                None,
+                true,
+                window,
+                cx,
+            );
+        })
+    })
+    .detach_and_log_err(cx);
+}
+
+/// Saves the scratchpad when its buffer is edited, and once more when the buffer is released,
+/// which closes the scratchpad unless the app is quitting, or removes it if it's empty.
+fn persist_edits(
+    buffer: Entity<Buffer>,
+    scratchpad: Scratchpad,
+    store: ScratchpadStore,
+    cx: &mut App,
+) {
+    let id = scratchpad.id;
+    let scratchpad = Scratchpad {
+        open: true,
+        ..scratchpad
+    };
+    let on_edit = cx.subscribe(&buffer, {
+        let store = store.clone();
+        let scratchpad = scratchpad.clone();
+        move |buffer, event: &BufferEvent, cx| {
+            if !matches!(event, BufferEvent::Edited) {
+                return;
+            }
+            let scratchpad = Scratchpad {
+                text: buffer.read(cx).text(),
+                updated_at: chrono::Utc::now().timestamp_millis(),
+                ..scratchpad.clone()
+            };
+            let store = store.clone();
+            let save_task = cx.spawn(|cx| async move {
+                cx.background_executor().timer(SAVE_DEBOUNCE).await;
+                store.save_or_remove(&scratchpad).await.log_err();
+            });
+            if let Some(open) = cx
+                .default_global::<OpenScratchpads>()
+                .buffers
+                .get_mut(&scratchpad.id)
+            {
+                open.save_task = Some(save_task);
+            }
+        }
+    });
+    let on_release = cx.observe_release(&buffer, {
+        let store = store.clone();
+        let scratchpad = scratchpad.clone();
+        move |buffer, cx| {
+            let open_scratchpads = cx.default_global::<OpenScratchpads>();
+            let quitting = open_scratchpads.quitting;
+            if let Some(open) = open_scratchpads.buffers.remove(&id) {
+                // When quitting, only save again if the last edits haven't been saved yet.
+                if open.save_task.is_some() || !quitting {
+                    let scratchpad = Scratchpad {
+                        text: buffer.text(),
+                        updated_at: chrono::Utc::now().timestamp_millis(),
+                        open: quitting,
+                        ..scratchpad
+                    };
+                    cx.background_spawn(async move {
+                        store.save_or_remove(&scratchpad).await.log_err()
+                    })
+                    .detach();
+                }
+            }
+        }
+    });
+
+    if !scratchpad.text.is_empty() {
+        cx.background_spawn(async move { store.save(&scratchpad).await.log_err() })
+            .detach();
+    }
+    cx.default_global::<OpenScratchpads>().buffers.insert(
+        id,
+        OpenScratchpad {
+            buffer: buffer.downgrade(),
+            save_task: None,
+            _subscriptions: [on_edit, on_release],
+        },
+    );
+}
+
+pub struct ScratchpadPicker {
+    picker: Entity<Picker<ScratchpadPickerDelegate>>,
+}
+
+impl ScratchpadPicker {
+    fn new(
+        scratchpads: Vec<Scratchpad>,
+        language_names: Vec<String>,
+        workspace: WeakEntity<Workspace>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let items = scratchpads
+            .into_iter()
+            .map(ScratchpadItem::Existing)
+            .chain(language_names.into_iter().map(ScratchpadItem::New))
+            .collect::<Vec<_>>();
+        let candidates = items
+            .iter()
+            .enumerate()
+            .map(|(ix, item)| StringMatchCandidate::new(ix, &item.label()))
+            .collect();
+        let delegate = ScratchpadPickerDelegate {
+            scratchpad_picker: cx.entity().downgrade(),
+            workspace,
+            items,
+            candidates,
+            matches: Vec::new(),
+            selected_index: 0,
+        };
+        let picker = cx.new(|cx| Picker::uniform_list(delegate, window, cx));
+        Self { picker }
+    }
+}
+
+impl Render for ScratchpadPicker {
+    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+        v_flex().w(rems(34.)).child(self.picker.clone())
+    }
+}
+
+impl Focusable for ScratchpadPicker {
+    fn focus_handle(&self, cx: &App) -> FocusHandle {
+        self.picker.focus_handle(cx)
+    }
+}
+
+impl EventEmitter<DismissEvent> for ScratchpadPicker {}
+impl ModalView for ScratchpadPicker {}
+
+enum ScratchpadItem {
+    Existing(Scratchpad),
+    /// Creates a scratchpad in the named language.
+    New(String),
+}
+
+impl ScratchpadItem {
+    fn label(&self) -> String {
+        match self {
+            Self::Existing(scratchpad) => {
+                let first_line = scratchpad
+                    .text
+                    .lines()
+                    .map(str::trim)
+                    .find(|line| !line.is_empty())
+                    .unwrap_or("empty");
+                format!("{}: {first_line}", scratchpad.language)
+            }
+            Self::New(language) => format!("New {language} scratchpad"),
+        }
+    }
+}
+
+pub struct ScratchpadPickerDelegate {
+    scratchpad_picker: WeakEntity<ScratchpadPicker>,
+    workspace: WeakEntity<Workspace>,
+    items: Vec<ScratchpadItem>,
+    candidates: Vec<StringMatchCandidate>,
+    matches: Vec<StringMatch>,
+    selected_index: usize,
+}
+
+impl PickerDelegate for ScratchpadPickerDelegate {
+    type ListItem = ListItem;
+
+    fn placeholder_text(&self, _window: &mut Window, _cx: &mut App) -> Arc<str> {
+        "Open a scratchpad, or create one in a language…".into()
+    }
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn set_selected_index(
+        &mut self,
+        ix: usize,
+        _window: &mut Window,
+        _: &mut Context<Picker<Self>>,
+    ) {
+        self.selected_index = ix;
+    }
+
+    fn update_matches(
+        &mut self,
+        query: String,
+        window: &mut Window,
+        cx: &mut Context<Picker<Self>>,
+    ) -> Task<()> {
+        let background = cx.background_executor().clone();
+        let candidates = self.candidates.clone();
+        cx.spawn_in(window, |this, mut cx| async move {
+            let matches = if query.is_empty() {
+                candidates
+                    .into_iter()
+                    .map(|candidate| StringMatch {
+                        candidate_id: candidate.id,
+                        string: candidate.string,
+                        positions: Vec::new(),
+                        score: 0.0,
+                    })
+                    .collect()
+            } else {
+                match_strings(
+                    &candidates,
+                    &query,
+                    false,
+                    100,
+                    &Default::default(),
+                    background,
+                )
+                .await
+            };
+
+            this.update(&mut cx, |this, cx| {
+                let delegate = &mut this.delegate;
+                delegate.matches = matches;
+                delegate.selected_index = delegate
+                    .selected_index
+                    .min(delegate.matches.len().saturating_sub(1));
+                cx.notify();
+            })
+            .log_err();
+        })
+    }
+
+    /// Opens or creates the selected scratchpad. A secondary confirmation deletes it instead.
+    fn confirm(&mut self, secondary: bool, window: &mut Window, cx: &mut Context<Picker<Self>>) {
+        let Some(mat) = self.matches.get(self.selected_index) else {
+            return;
+        };
+        let item = &self.items[mat.candidate_id];
+        self.workspace
+            .update(cx, |workspace, cx| match item {
+                ScratchpadItem::Existing(scratchpad) if secondary => {
+                    let store = store(workspace);
+                    let id = scratchpad.id;
+                    cx.background_spawn(async move { store.remove(id).await.log_err() })
+                        .detach();
+                }
+                ScratchpadItem::Existing(scratchpad) => {
+                    open_scratchpad(workspace, scratchpad.clone(), window, cx)
+                }
+                ScratchpadItem::New(language) => {
+                    create_scratchpad(workspace, language.clone(), window, cx)
+                }
+            })
+            .log_err();
+        self.dismissed(window, cx);
+    }
+
+    fn dismissed(&mut self, _: &mut Window, cx: &mut Context<Picker<Self>>) {
+        self.scratchpad_picker
+            .update(cx, |_, cx| cx.emit(DismissEvent))
+            .log_err();
+    }
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        _: &mut Window,
+        _: &mut Context<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let mat = &self.matches[ix];
+        let end_label = match &self.items[mat.candidate_id] {
+            ScratchpadItem::Existing(scratchpad) => {
+                DateTime::from_timestamp_millis(scratchpad.updated_at)
+                    .map(|updated_at| {
+                        updated_at
+                            .with_timezone(&Local)
+                            .format("%Y-%m-%d %H:%M")
+                            .to_string()
+                    })
+                    .unwrap_or_default()
+            }
+            ScratchpadItem::New(_) => String::new(),
+        };
+        Some(
+            ListItem::new(ix)
+                .inset(true)
+                .spacing(ListItemSpacing::Sparse)
+                .toggle_state(selected)
+                .child(HighlightedLabel::new(
+                    mat.string.clone(),
+                    mat.positions.clone(),
+                ))
+                .end_slot(
+                    Label::new(end_label)
+                        .size(LabelSize::Small)
+                        .color(Color::Muted),
+                ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fs::FakeFs;
+    use gpui::TestAppContext;
+
+    #[gpui::test]
+    async fn test_scratchpad_store(cx: &mut TestAppContext) {
+        let fs = FakeFs::new(cx.executor());
+        let store = ScratchpadStore::new(fs.clone(), PathBuf::from("/scratchpads"));
+        assert_eq!(store.scratchpads().await.unwrap(), Vec::new());
+
+        let mut rust = Scratchpad {
+            id: 1,
+            language: "Rust".into(),
+            text: "fn main() {}".into(),
+            updated_at: 1,
+            open: false,
+        };
+        let python = Scratchpad {
+            id: 2,
+            language: "Python".into(),
+            text: "print(1)".into(),
+            updated_at: 2,
+            open: true,
+        };
+        store.save(&rust).await.unwrap();
+        store.save(&python).await.unwrap();
+        assert_eq!(
+            store.scratchpads().await.unwrap(),
+            [python.clone(), rust.clone()]
+        );
+
+        rust.text = "fn main() { todo!() }".into();
+        rust.updated_at = 3;
+        store.save(&rust).await.unwrap();
+        assert_eq!(
+            store.scratchpads().await.unwrap(),
+            [rust.clone(), python.clone()]
+        );
+
+        store.remove(rust.id).await.unwrap();
+        assert_eq!(store.scratchpads().await.unwrap(), [python.clone()]);
+
+        // Empty scratchpads aren't kept.
+        let empty = Scratchpad {
+            id: 3,
+            language: "Rust".into(),
+            text: String::new(),
+            updated_at: 4,
+            open: true,
+        };
+        store.save_or_remove(&empty).await.unwrap();
+        assert_eq!(store.scratchpads().await.unwrap(), [python.clone()]);
+        store.save(&empty).await.unwrap();
+        assert_eq!(store.scratchpads().await.unwrap(), [python]);
+        assert!(!fs.is_file(&store.path_for_id(empty.id)).await);
+    }
+}
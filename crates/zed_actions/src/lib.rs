@@ -23,7 +23,15 @@ pub struct OpenZedUrl {
     pub url: String,
 }
 
-impl_actions!(zed, [OpenBrowser, OpenZedUrl]);
+/// Creates a scratchpad in the given language, or in one picked from a list.
+#[derive(Clone, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct NewScratchpad {
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+impl_actions!(zed, [OpenBrowser, OpenZedUrl, NewScratchpad]);
 
 actions!(
     zed,
@@ -38,6 +46,9 @@ actions!(
         Extensions,
         OpenLicenses,
         OpenTelemetryLog,
+        OpenPendingTelemetryEvents,
+        OpenDiscardedChanges,
+        OpenScratchpads,
         DecreaseBufferFontSize,
         IncreaseBufferFontSize,
         ResetBufferFontSize,